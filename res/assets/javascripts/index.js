@@ -2,10 +2,21 @@ var IndexManager = (function() {
   return {
     _REFRESH_TIMEOUT : 5000,
     _REFRESH_INTERVAL : 20000,
+    _RELOAD_DEBOUNCE : 500,
+
+    _SOCKET : null,
+    _EVENTS_SOURCE : null,
+    _RELOAD_TIMEOUT : null,
 
     _SELECTOR_ASIDE : null,
     _SELECTOR_MAIN : null,
 
+    _TOOLTIPS_REPLICA : {
+      healthy : "This replica reports as healthy.",
+      sick : "This replica reports as sick.",
+      dead : "This replica reports as dead."
+    },
+
     bind : function() {
       IndexManager._SELECTOR_ASIDE = (
         (document.getElementsByTagName("aside") || [])[0]
@@ -15,14 +26,184 @@ var IndexManager = (function() {
       );
 
       if (IndexManager._SELECTOR_ASIDE && IndexManager._SELECTOR_MAIN) {
+        // Prefer live updates, if supported (falls back to periodic refreshes)
+        if (window.WebSocket) {
+          IndexManager.__bind_socket();
+        } else {
+          IndexManager.__bind_events();
+        }
+      }
+    },
+
+    __bind_socket : function() {
+      var protocol = (window.location.protocol === "https:") ? "wss:" : "ws:";
+
+      IndexManager._SOCKET = new WebSocket(
+        protocol + "//" + window.location.host + IndexManager.__path("/ws")
+      );
+
+      IndexManager._SOCKET.onmessage = function(message) {
+        try {
+          IndexManager.__apply_events(JSON.parse(message.data));
+        } catch (_) {
+          IndexManager.__schedule_reload();
+        }
+      };
+
+      IndexManager._SOCKET.onclose = function() {
+        // Socket went away? Fall back to Server-Sent Events
+        IndexManager._SOCKET = null;
+
+        IndexManager.__bind_events();
+      };
+    },
+
+    __bind_events : function() {
+      if (!window.EventSource) {
         IndexManager.__schedule_refresh();
+
+        return;
+      }
+
+      IndexManager._EVENTS_SOURCE = new EventSource(
+        IndexManager.__path("/status/events")
+      );
+
+      IndexManager._EVENTS_SOURCE.addEventListener("state", function(event) {
+        try {
+          IndexManager.__apply_events([JSON.parse(event.data)]);
+        } catch (_) {
+          IndexManager.__schedule_reload();
+        }
+      });
+
+      IndexManager._EVENTS_SOURCE.onerror = function() {
+        // Stream cannot be recovered? Fall back to periodic refreshes
+        if (IndexManager._EVENTS_SOURCE.readyState === EventSource.CLOSED) {
+          IndexManager._EVENTS_SOURCE = null;
+
+          IndexManager.__schedule_refresh();
+        }
+      };
+    },
+
+    __apply_events : function(events) {
+      var needs_reload = false;
+
+      for (var i = 0; i < events.length; i++) {
+        // Apply state diff in place (reload whole page if it cannot be applied)
+        if (IndexManager.__apply_event(events[i]) !== true) {
+          needs_reload = true;
+        }
+      }
+
+      if (needs_reload === true) {
+        IndexManager.__schedule_reload();
+      }
+    },
+
+    __apply_event : function(event) {
+      // General status changes affect many page elements (eg. header text)
+      if (event.kind === "general" || event.previous_status === null) {
+        window.STATUS_GENERAL = event.status;
+
+        return false;
+      }
+
+      var probe_sel = IndexManager.__find_element(
+        document, "data-probe", event.probe_id
+      );
+
+      if (!probe_sel) {
+        return false;
+      }
+
+      if (event.kind === "probe") {
+        return IndexManager.__swap_status(
+          probe_sel.getElementsByClassName("badge")[0], event.status
+        );
+      }
+
+      var node_sel = IndexManager.__find_element(
+        probe_sel, "data-node", event.node_id
+      );
+
+      if (!node_sel) {
+        return false;
+      }
+
+      if (event.kind === "node") {
+        return (
+          IndexManager.__swap_status(node_sel, event.status) &&
+            IndexManager.__swap_status(
+              node_sel.getElementsByTagName("label")[0], event.status
+            )
+        );
+      }
+
+      var replica_sel = IndexManager.__find_element(
+        node_sel, "data-replica", event.replica_id
+      );
+
+      if (!replica_sel) {
+        return false;
+      }
+
+      var tooltip_sel = (
+        replica_sel.getElementsByClassName("tooltip-main")[0]
+      );
+
+      if (tooltip_sel && IndexManager._TOOLTIPS_REPLICA[event.status]) {
+        tooltip_sel.textContent = IndexManager._TOOLTIPS_REPLICA[event.status];
+      }
+
+      return IndexManager.__swap_status(replica_sel, event.status);
+    },
+
+    __find_element : function(parent_sel, attribute, value) {
+      var elements = parent_sel.querySelectorAll("[" + attribute + "]");
+
+      for (var i = 0; i < elements.length; i++) {
+        if (elements[i].getAttribute(attribute) === value) {
+          return elements[i];
+        }
+      }
+
+      return null;
+    },
+
+    __swap_status : function(element, status) {
+      if (!element) {
+        return false;
+      }
+
+      element.className = element.className.replace(
+        /status-(healthy|sick|dead)/g, ("status-" + status)
+      );
+
+      return true;
+    },
+
+    __schedule_reload : function() {
+      // Debounce reloads (as many states change at once)
+      if (IndexManager._RELOAD_TIMEOUT === null) {
+        IndexManager._RELOAD_TIMEOUT = setTimeout(function() {
+          IndexManager._RELOAD_TIMEOUT = null;
+
+          IndexManager.__load(
+            IndexManager.__path("/"), "document",
+
+            IndexManager.__handle_base_done,
+            IndexManager.__handle_base_error
+          );
+        }, IndexManager._RELOAD_DEBOUNCE);
       }
     },
 
     __schedule_refresh : function() {
       setTimeout(function() {
         IndexManager.__load(
-          "/status/text/", "text",
+          IndexManager.__path("/status/text/"), "text",
 
           IndexManager.__handle_status_text_done_from_request,
           IndexManager.__handle_status_text_error
@@ -41,7 +222,7 @@ var IndexManager = (function() {
         window.STATUS_GENERAL = status;
 
         IndexManager.__load(
-          "/", "document",
+          IndexManager.__path("/"), "document",
 
           IndexManager.__handle_base_done,
           IndexManager.__handle_base_error
@@ -79,13 +260,21 @@ var IndexManager = (function() {
         }
       }
 
-      IndexManager.__schedule_refresh();
+      // Live updates are not in use? Schedule next refresh
+      if (IndexManager._SOCKET === null && IndexManager._EVENTS_SOURCE === null) {
+        IndexManager.__schedule_refresh();
+      }
     },
 
     __handle_base_error : function() {
       IndexManager.__handle_base_done(null);
     },
 
+    __path : function(path) {
+      // Prefix path with base path (if Vigil is mounted under a sub-path)
+      return (window.BASE_PATH || "") + path;
+    },
+
     __load : function(path, type, fn_handle_done, fn_handle_error) {
       var request = new XMLHttpRequest();
 
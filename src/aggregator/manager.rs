@@ -4,17 +4,28 @@
 // Copyright: 2018, Valerian Saliou <valerian@valeriansaliou.name>
 // License: Mozilla Public License v2.0 (MPL v2.0)
 
-use std::iter::FromIterator;
+use std::iter::{self, FromIterator};
 use std::thread;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 use time;
 use time::format_description::FormatItem;
 
-use crate::config::config::ConfigNotifyReminderBackoffFunction;
-use crate::notifier::generic::Notification;
-use crate::prober::manager::STORE as PROBER_STORE;
+use crate::config::config::{
+    ConfigNotify, ConfigNotifyQuietHoursAction, ConfigNotifyReminderBackoffFunction,
+};
+use crate::notifier::generic::{find_channel, is_probe_routed, Notification, DEFAULT_CHANNEL};
+use crate::notifier::limit;
+use crate::notifier::quiet;
+use crate::prober::events;
+use crate::prober::manager::{
+    publish_snapshot, Store, StoreChannel, StoreChannelDigest, StoreChannelDigestProbe,
+    STORE as PROBER_STORE,
+};
 use crate::prober::mode::Mode;
 use crate::prober::status::Status;
+use crate::storage::incidents;
+use crate::storage::sqlite::{self as storage, StorageReplicaState, StorageTransition};
+use crate::subscriber::dispatcher::{self as subscriber, SubscriberEvent};
 use crate::APP_CONF;
 
 #[cfg(feature = "notifier-email")]
@@ -47,6 +58,45 @@ use crate::notifier::matrix::MatrixNotifier;
 #[cfg(feature = "notifier-webhook")]
 use crate::notifier::webhook::WebHookNotifier;
 
+#[cfg(feature = "notifier-opsgenie")]
+use crate::notifier::opsgenie::OpsgenieNotifier;
+
+#[cfg(feature = "notifier-ntfy")]
+use crate::notifier::ntfy::NtfyNotifier;
+
+#[cfg(feature = "notifier-teams")]
+use crate::notifier::teams::TeamsNotifier;
+
+#[cfg(feature = "notifier-discord")]
+use crate::notifier::discord::DiscordNotifier;
+
+#[cfg(feature = "notifier-mattermost")]
+use crate::notifier::mattermost::MattermostNotifier;
+
+#[cfg(feature = "notifier-rocketchat")]
+use crate::notifier::rocketchat::RocketChatNotifier;
+
+#[cfg(feature = "notifier-googlechat")]
+use crate::notifier::googlechat::GoogleChatNotifier;
+
+#[cfg(feature = "notifier-signal")]
+use crate::notifier::signal::SignalNotifier;
+
+#[cfg(feature = "notifier-sms")]
+use crate::notifier::sms::SMSNotifier;
+
+#[cfg(feature = "notifier-sns")]
+use crate::notifier::sns::SNSNotifier;
+
+#[cfg(feature = "notifier-mqtt")]
+use crate::notifier::mqtt::MQTTNotifier;
+
+#[cfg(feature = "notifier-irc")]
+use crate::notifier::irc::IRCNotifier;
+
+#[cfg(feature = "notifier-apprise")]
+use crate::notifier::apprise::AppriseNotifier;
+
 lazy_static! {
     static ref TIME_NOW_FORMATTER: Vec<FormatItem<'static>> = time::format_description::parse(
         "[hour]:[minute]:[second] UTC[offset_hour sign:mandatory]:[offset_minute]"
@@ -55,12 +105,32 @@ lazy_static! {
 }
 
 const AGGREGATE_INTERVAL_SECONDS: u64 = 10;
+const STORAGE_PRUNE_INTERVAL_SECONDS: u64 = 3600;
 
 struct BumpedStates {
+    channel: String,
     status: Status,
     replicas: Vec<String>,
+    diagnostics: Vec<(String, String)>,
     changed: bool,
     startup: bool,
+    reminder: Option<u16>,
+    flapping: Option<String>,
+    held: Option<u32>,
+    suppressed: Option<u32>,
+    digest: Option<Vec<(String, Status)>>,
+    downtime: Option<Duration>,
+}
+
+struct BumpedProbe {
+    id: String,
+    status: Status,
+    replicas: Vec<String>,
+    diagnostics: Vec<(String, String)>,
+    acknowledged: bool,
+    indirectly_affected: bool,
+    started_flapping: bool,
+    previous_status: Option<Status>,
 }
 
 fn check_child_status(parent_status: &Status, child_status: &Status) -> Option<Status> {
@@ -73,8 +143,15 @@ fn check_child_status(parent_status: &Status, child_status: &Status) -> Option<S
     }
 }
 
-fn scan_and_bump_states() -> Option<BumpedStates> {
-    let mut bumped_replicas = Vec::new();
+fn scan_and_bump_states(is_startup: bool) -> Vec<BumpedStates> {
+    let mut bumped_probes = Vec::new();
+    let mut transitioned_nodes = Vec::new();
+    let mut storage_transitions = Vec::new();
+    let mut storage_replica_states = Vec::new();
+    let mut transitioned_probes = Vec::new();
+    let mut expired_disabled_probes = Vec::new();
+
+    let has_storage = storage::is_enabled();
 
     let mut store = PROBER_STORE.write().unwrap();
 
@@ -84,6 +161,8 @@ fn scan_and_bump_states() -> Option<BumpedStates> {
         debug!("aggregate probe: {}", probe_id);
 
         let mut probe_status = Status::Healthy;
+        let mut bumped_replicas = Vec::new();
+        let mut bumped_diagnostics = Vec::new();
 
         for (node_id, node) in probe.nodes.iter_mut() {
             debug!("aggregate node: {}:{}", probe_id, node_id);
@@ -167,8 +246,41 @@ fn scan_and_bump_states() -> Option<BumpedStates> {
                             }
                         }
                     }
+                    Mode::Heartbeat => {
+                        // Assign stored status by default (heartbeats might report failures)
+                        replica_status = replica.status.to_owned();
+
+                        // Compare delays and compute a new status? (heartbeats must be received \
+                        //   within their interval, or after each scheduled run once its grace \
+                        //   period is over)
+                        if let Some(ref replica_report) = replica.report {
+                            let now = SystemTime::now();
+
+                            let is_missed = match node.options.heartbeat_schedule {
+                                Some(ref schedule) => now
+                                    .checked_sub(Duration::from_secs(node.options.heartbeat_grace))
+                                    .and_then(|until| schedule.previous(until))
+                                    .map_or(false, |last_run| replica_report.time < last_run),
+                                None => now.duration_since(replica_report.time).map_or(
+                                    false,
+                                    |duration_since_report| {
+                                        duration_since_report >= replica_report.interval
+                                    },
+                                ),
+                            };
+
+                            if is_missed == true {
+                                debug!(
+                                    "replica: {}:{}:{} is dead because its heartbeat was missed",
+                                    probe_id, node_id, replica_id
+                                );
+
+                                replica_status = Status::Dead;
+                            }
+                        }
+                    }
                     _ => {
-                        // Forward stored status (eg. 'poll' or 'script' nodes)
+                        // Forward stored status (eg. 'poll', 'script' or 'transaction' nodes)
                         replica_status = replica.status.to_owned();
                     }
                 }
@@ -185,7 +297,29 @@ fn scan_and_bump_states() -> Option<BumpedStates> {
 
                 // Append bumped replica path?
                 if replica_status == Status::Dead {
-                    bumped_replicas.push(format!("{}:{}:{}", probe_id, node_id, replica_id));
+                    let replica_path = format!("{}:{}:{}", probe_id, node_id, replica_id);
+
+                    // Append captured diagnostic? (captured when the replica went dead)
+                    if let Some(diagnostic) = node.diagnostics.get(replica_id) {
+                        bumped_diagnostics
+                            .push((replica_path.to_owned(), diagnostic.output.to_owned()));
+                    }
+
+                    bumped_replicas.push(replica_path);
+                }
+
+                // Append stored replica state? (only for replicas that Vigil probes itself)
+                if has_storage == true
+                    && (node.mode == Mode::Poll
+                        || node.mode == Mode::Script
+                        || node.mode == Mode::Transaction)
+                {
+                    storage_replica_states.push(StorageReplicaState {
+                        probe_id: probe_id.to_owned(),
+                        node_id: node_id.to_owned(),
+                        replica_id: replica_id.to_owned(),
+                        status: replica_status.to_owned(),
+                    });
                 }
 
                 replica.status = replica_status;
@@ -201,12 +335,31 @@ fn scan_and_bump_states() -> Option<BumpedStates> {
                 probe_id, node_id, node_status
             );
 
+            // Append transitioned node path? (used for transition counters)
+            if node.status != node_status {
+                transitioned_nodes.push((probe_id.to_owned(), node_id.to_owned()));
+            }
+
+            // Append stored node transition? (always upon startup, as uptime is computed from \
+            //   stored transitions)
+            if has_storage == true && (is_startup == true || node.status != node_status) {
+                storage_transitions.push(StorageTransition {
+                    probe_id: Some(probe_id.to_owned()),
+                    node_id: Some(node_id.to_owned()),
+                    status: node_status.to_owned(),
+                });
+            }
+
             node.status = node_status;
         }
 
         // Bump general status with worst node status?
-        if let Some(worst_status) = check_child_status(&general_status, &probe_status) {
-            general_status = worst_status;
+        // Notice: disabled probes are not monitored, thus their (stale) status is not accounted \
+        //   for in the general status.
+        if probe.disabled == false {
+            if let Some(worst_status) = check_child_status(&general_status, &probe_status) {
+                general_status = worst_status;
+            }
         }
 
         debug!(
@@ -214,106 +367,645 @@ fn scan_and_bump_states() -> Option<BumpedStates> {
             probe_id, probe_status
         );
 
+        // Detect flapping probe? (ie. probe that transitioned too many times recently)
+        let (mut started_flapping, mut flapping_previous_status) = (false, None);
+
+        if let Some(flap_threshold) = APP_CONF.metrics.flap_threshold {
+            let now = SystemTime::now();
+            let flap_window = Duration::from_secs(APP_CONF.metrics.flap_window);
+
+            if probe.status != probe_status {
+                probe.transitions.push(now);
+            }
+
+            probe.transitions.retain(|transition| {
+                now.duration_since(*transition).unwrap_or_default() < flap_window
+            });
+
+            // Notice: probes only stop flapping once they did not transition for a whole \
+            //   window, so that they do not keep on entering and leaving the flapping state.
+            if probe.flapping == false && probe.transitions.len() > flap_threshold {
+                info!("probe: {} started flapping", probe_id);
+
+                probe.flapping = true;
+                probe.flapping_status = Some(probe.status.to_owned());
+
+                started_flapping = true;
+            } else if probe.flapping == true && probe.transitions.is_empty() == true {
+                info!("probe: {} stopped flapping", probe_id);
+
+                probe.flapping = false;
+
+                flapping_previous_status = probe.flapping_status.take();
+            }
+        } else if probe.flapping == true {
+            // Flap detection was disabled (eg. upon configuration reload)
+            probe.flapping = false;
+            probe.transitions.clear();
+
+            flapping_previous_status = probe.flapping_status.take();
+        }
+
+        // Append transitioned probe? (used for automatic incidents and subscribers)
+        // Notice: transitions of flapping probes are not reported, only the status that they \
+        //   settled on once they stop flapping is.
+        let previous_status = flapping_previous_status.unwrap_or(probe.status.to_owned());
+
+        let transitioned_previous_status =
+            if probe.flapping == false && previous_status != probe_status {
+                transitioned_probes.push(SubscriberEvent {
+                    probe_id: probe_id.to_owned(),
+                    probe_label: probe.label.to_owned(),
+                    status: probe_status.to_owned(),
+                    previous_status: previous_status.to_owned(),
+                    replicas: bumped_replicas.to_owned(),
+                });
+
+                Some(previous_status)
+            } else {
+                None
+            };
+
+        // Append stored probe transition?
+        if has_storage == true && (is_startup == true || probe.status != probe_status) {
+            storage_transitions.push(StorageTransition {
+                probe_id: Some(probe_id.to_owned()),
+                node_id: None,
+                status: probe_status.to_owned(),
+            });
+        }
+
+        // Clear probe acknowledgment? (once expired, or once the probe has recovered)
+        if let Some(acknowledged_until) = probe.acknowledged_until {
+            if SystemTime::now() >= acknowledged_until
+                || (probe.status != Status::Healthy && probe_status == Status::Healthy)
+            {
+                debug!("cleared acknowledgment for probe: {}", probe_id);
+
+                probe.acknowledged_until = None;
+            }
+        }
+
+        probe.acknowledged = probe.acknowledged_until.is_some();
+
+        // Enable probe again? (once its disable duration is over)
+        if let Some(disabled_until) = probe.disabled_until {
+            if SystemTime::now() >= disabled_until {
+                info!("disable duration is over for probe: {}", probe_id);
+
+                probe.disabled = false;
+                probe.disabled_until = None;
+
+                expired_disabled_probes.push(probe_id.to_owned());
+            }
+        }
+
+        // Append bumped probe (used to route notifications to channels; flapping probes are \
+        //   held to their status from before they started flapping)
+        // Notice: disabled probes are not monitored, thus they must not alert channels (their \
+        //   status might be stale, or dead as reports are not expected anymore).
+        if probe.disabled == false {
+            bumped_probes.push(BumpedProbe {
+                id: probe_id.to_owned(),
+                status: probe
+                    .flapping_status
+                    .to_owned()
+                    .unwrap_or(probe_status.to_owned()),
+                replicas: bumped_replicas,
+                diagnostics: bumped_diagnostics,
+                acknowledged: probe.acknowledged,
+                indirectly_affected: false,
+                started_flapping: started_flapping,
+                previous_status: transitioned_previous_status,
+            });
+        }
+
         probe.status = probe_status;
     }
 
+    // Mark probes depending on a dead probe as indirectly affected (their notifications are \
+    //   silenced, as the root cause lies in the dead probe they depend on)
+    let dead_probes: Vec<String> = store
+        .states
+        .probes
+        .iter()
+        .filter(|(_, probe)| probe.disabled == false && probe.status == Status::Dead)
+        .map(|(probe_id, _)| probe_id.to_owned())
+        .collect();
+
+    for bumped_probe in bumped_probes.iter_mut() {
+        if let Some(probe) = store.states.probes.get_mut(&bumped_probe.id) {
+            let indirectly_affected = probe
+                .depends_on
+                .iter()
+                .any(|dependency| dead_probes.contains(dependency));
+
+            if indirectly_affected != probe.indirectly_affected {
+                info!(
+                    "probe: {} is {} indirectly affected",
+                    bumped_probe.id,
+                    if indirectly_affected == true {
+                        "now"
+                    } else {
+                        "no longer"
+                    }
+                );
+            }
+
+            probe.indirectly_affected = indirectly_affected;
+            bumped_probe.indirectly_affected = indirectly_affected;
+        }
+    }
+
+    // Bump node transition counters
+    for transitioned_node in transitioned_nodes {
+        *store
+            .counters
+            .transitions
+            .entry(transitioned_node)
+            .or_insert(0) += 1;
+    }
+
     // Check if general status has changed
     let has_changed = store.states.status != general_status;
 
+    // Append stored general transition?
+    if has_storage == true && (is_startup == true || has_changed == true) {
+        storage_transitions.push(StorageTransition {
+            probe_id: None,
+            node_id: None,
+            status: general_status.to_owned(),
+        });
+    }
+
+    // Bump notifier channel states (each channel only accounts for probes routed to it)
+    let mut bumped_states = Vec::new();
+
+    if let Some(ref notify) = APP_CONF.notify {
+        for (channel_name, channel_notify) in iter_notify_channels(notify) {
+            if let Some(channel_bumped_states) =
+                bump_channel_state(&mut store, channel_name, channel_notify, &bumped_probes)
+            {
+                bumped_states.push(channel_bumped_states);
+            }
+
+            // Notify about probes that started flapping (once, instead of upon each transition)
+            for probe in bumped_probes.iter() {
+                if probe.started_flapping == true
+                    && probe.acknowledged == false
+                    && probe.indirectly_affected == false
+                    && is_probe_routed(&probe.id, channel_name) == true
+                {
+                    bumped_states.push(BumpedStates {
+                        channel: channel_name.to_owned(),
+                        status: Status::Sick,
+                        replicas: Vec::new(),
+                        diagnostics: Vec::new(),
+                        changed: true,
+                        startup: false,
+                        reminder: None,
+                        flapping: Some(probe.id.to_owned()),
+                        held: None,
+                        suppressed: None,
+                        digest: None,
+                        downtime: None,
+                    });
+                }
+            }
+        }
+    }
+
+    // Reflect default channel backoff counter in states
+    let default_reminder_backoff_counter = store
+        .channels
+        .get(DEFAULT_CHANNEL)
+        .map(|channel| channel.reminder_backoff_counter);
+
+    if let Some(reminder_backoff_counter) = default_reminder_backoff_counter {
+        store.states.notifier.reminder_backoff_counter = reminder_backoff_counter;
+    }
+
+    // Bump stored values
+    store.states.status = general_status.to_owned();
+    store.states.date = Some(time_now_as_string());
+
+    // List state changes (this includes replica changes that were bumped by the prober)
+    let state_events = events::scan(&store.states);
+
+    publish_snapshot(&store);
+
+    // Release store lock before writing to storage (as this might be slow)
+    drop(store);
+
+    events::publish(&state_events);
+
+    if has_storage == true {
+        storage::save_transitions(&storage_transitions);
+        storage::save_replica_states(&storage_replica_states);
+
+        for expired_disabled_probe in expired_disabled_probes.iter() {
+            storage::remove_disabled_service(expired_disabled_probe);
+        }
+
+        for transitioned_probe in transitioned_probes.iter() {
+            incidents::handle_probe_transition(
+                &transitioned_probe.probe_id,
+                &transitioned_probe.probe_label,
+                &transitioned_probe.status,
+            );
+        }
+    }
+
+    // Dispatch transitions to subscribers (this does not block)
+    subscriber::dispatch(transitioned_probes);
+
+    bumped_states
+}
+
+fn bump_channel_state(
+    store: &mut Store,
+    channel_name: &str,
+    notify: &ConfigNotify,
+    bumped_probes: &[BumpedProbe],
+) -> Option<BumpedStates> {
+    let mut channel_status = Status::Healthy;
+    let mut unacknowledged_status = Status::Healthy;
+    let mut channel_replicas = Vec::new();
+    let mut channel_diagnostics = Vec::new();
+    let mut has_probes = false;
+
+    // Compute channel status with the worst status of probes routed to this channel
+    for probe in bumped_probes {
+        if is_probe_routed(&probe.id, channel_name) == true {
+            has_probes = true;
+
+            if let Some(worst_status) = check_child_status(&channel_status, &probe.status) {
+                channel_status = worst_status;
+            }
+
+            // Acknowledged and indirectly affected probes are silenced (ie. they are not part \
+            //   of notifications)
+            if probe.acknowledged == false && probe.indirectly_affected == false {
+                if let Some(worst_status) =
+                    check_child_status(&unacknowledged_status, &probe.status)
+                {
+                    unacknowledged_status = worst_status;
+                }
+
+                channel_replicas.extend(probe.replicas.iter().cloned());
+                channel_diagnostics.extend(probe.diagnostics.iter().cloned());
+            }
+        }
+    }
+
+    // No probe routed to this channel? (only summarize notifications that got held back or \
+    //   suppressed, eg. on channels that notifications get rerouted or escalated to)
+    if has_probes == false {
+        return bump_channel_summary(store, channel_name, notify);
+    }
+
+    // Notice: channels start from the previous general status, which might have been restored \
+    //   from storage, so that a restart does not trigger a spurious notification.
+    let previous_general_status = store.states.status.to_owned();
+
+    let channel = store
+        .channels
+        .entry(channel_name.to_owned())
+        .or_insert_with(|| StoreChannel {
+            status: previous_general_status,
+            ..Default::default()
+        });
+
+    // Check if channel status has changed
+    let has_changed = channel.status != channel_status;
+
     // Check if should dispatch notification later (only if critical)
     // Allow for cases:
     //   - healthy >> dead
     //   - sick    >> dead
     //   - dead    >> sick
     //   - dead    >> healthy
-    let mut should_notify = (store.states.status != Status::Dead && general_status == Status::Dead)
-        || (store.states.status == Status::Dead && general_status != Status::Dead);
+    let mut should_notify = (channel.status != Status::Dead && channel_status == Status::Dead)
+        || (channel.status == Status::Dead && channel_status != Status::Dead);
+
+    // Silence notifications? (channel is only dead because of acknowledged or indirectly \
+    //   affected probes)
+    // Notice: recoveries are still notified, as they are not downtime alerts.
+    let is_silenced = channel_status == Status::Dead && unacknowledged_status != Status::Dead;
+
+    if is_silenced == true && should_notify == true {
+        info!(
+            "silenced notification on channel: {}, as dead probes are acknowledged or indirectly affected",
+            channel_name
+        );
+
+        should_notify = false;
+    }
+
+    // Group transitions into a digest? (a notification is sent once the digest window is over)
+    // Notice: this avoids sending a burst of notifications upon cascading failures, as the \
+    //   transitions that happen within the window are listed in a single notification.
+    if notify.digest == true {
+        let transitions = bumped_probes.iter().filter(|probe| {
+            probe.previous_status.is_some() == true
+                && probe.acknowledged == false
+                && probe.indirectly_affected == false
+                && is_probe_routed(&probe.id, channel_name) == true
+        });
+
+        if channel.digest.is_none() == true && should_notify == true && has_changed == true {
+            info!(
+                "opened digest window on channel: {}, for {}s",
+                channel_name, notify.digest_window
+            );
+
+            channel.digest = Some(StoreChannelDigest {
+                since: SystemTime::now(),
+                status: channel.status.to_owned(),
+                probes: Vec::new(),
+            });
+        }
+
+        if let Some(ref mut digest) = channel.digest {
+            for probe in transitions {
+                match digest.probes.iter_mut().find(|item| item.id == probe.id) {
+                    Some(item) => item.status = probe.status.to_owned(),
+                    None => digest.probes.push(StoreChannelDigestProbe {
+                        id: probe.id.to_owned(),
+                        previous_status: probe
+                            .previous_status
+                            .to_owned()
+                            .unwrap_or(Status::Healthy),
+                        status: probe.status.to_owned(),
+                    }),
+                }
+            }
+
+            should_notify = false;
+        }
+    }
 
     // Reset the backoff counter whenever we are not dead (yet, stored status changed)
-    if has_changed == true && general_status != Status::Dead {
-        store.states.notifier.reminder_backoff_counter = 1;
+    if has_changed == true && channel_status != Status::Dead {
+        channel.reminder_backoff_counter = 1;
+    }
+
+    // Reset the reminder counter whenever status changes
+    if has_changed == true {
+        channel.reminder_count = 0;
     }
 
     // Check if should re-notify? (in case status did not change; only if dead)
     // Notice: this is used to send periodic reminders of downtime (ie. 'still down' messages)
-    if has_changed == false && should_notify == false && general_status == Status::Dead {
-        debug!("status unchanged, but may need to re-notify; checking");
-
-        if let Some(ref notify) = APP_CONF.notify {
-            match (store.notified, notify.reminder_interval) {
-                (Some(last_notified), Some(reminder_interval)) => {
-                    if let Ok(duration_since_notified) =
-                        SystemTime::now().duration_since(last_notified)
-                    {
-                        // Notice: we use backoff counter all the time because if it is disabled, \
-                        //   then the value is 1 at any time, thus not impacting the interval.
-                        let reminder_backoff_counter =
-                            store.states.notifier.reminder_backoff_counter;
-                        let reminder_interval_backoff = Duration::from_secs(
-                            reminder_interval
-                                * (reminder_backoff_counter as u64)
-                                    .pow(notify.reminder_backoff_function as u32),
-                        );
+    if has_changed == false
+        && should_notify == false
+        && is_silenced == false
+        && channel_status == Status::Dead
+        && channel.digest.is_none() == true
+    {
+        debug!(
+            "status unchanged on channel: {}, but may need to re-notify; checking",
+            channel_name
+        );
 
-                        debug!(
-                            "checking if should re-notify about unchanged status ({}s / {}↑)",
-                            reminder_interval_backoff.as_secs(),
-                            reminder_backoff_counter
+        match (channel.notified, notify.reminder_interval) {
+            (Some(last_notified), Some(reminder_interval)) => {
+                if let Ok(duration_since_notified) = SystemTime::now().duration_since(last_notified)
+                {
+                    // Notice: we use backoff counter all the time because if it is disabled, \
+                    //   then the value is 1 at any time, thus not impacting the interval.
+                    let reminder_backoff_counter = channel.reminder_backoff_counter;
+                    let reminder_interval_backoff = Duration::from_secs(
+                        reminder_interval
+                            * (reminder_backoff_counter as u64)
+                                .pow(notify.reminder_backoff_function as u32),
+                    );
+
+                    debug!(
+                        "checking if should re-notify about unchanged status ({}s / {}↑)",
+                        reminder_interval_backoff.as_secs(),
+                        reminder_backoff_counter
+                    );
+
+                    // Duration since last notified exceeds reminder interval? Should re-notify
+                    if duration_since_notified >= reminder_interval_backoff {
+                        info!(
+                            "should re-notify about unchanged status on channel: {}",
+                            channel_name
                         );
 
-                        // Duration since last notified exceeds reminder interval? Should re-notify
-                        if duration_since_notified >= reminder_interval_backoff {
-                            info!("should re-notify about unchanged status");
+                        should_notify = true;
 
-                            should_notify = true;
+                        channel.reminder_count = channel.reminder_count.saturating_add(1);
 
-                            // Increment the backoff counter? (a backoff function is set, \
-                            //   therefore reminders backoff is enabled)
-                            if notify.reminder_backoff_function
-                                != ConfigNotifyReminderBackoffFunction::None
-                                && store.states.notifier.reminder_backoff_counter
-                                    < notify.reminder_backoff_limit
-                            {
-                                store.states.notifier.reminder_backoff_counter += 1;
+                        // Increment the backoff counter? (a backoff function is set, \
+                        //   therefore reminders backoff is enabled)
+                        if notify.reminder_backoff_function
+                            != ConfigNotifyReminderBackoffFunction::None
+                            && channel.reminder_backoff_counter < notify.reminder_backoff_limit
+                        {
+                            channel.reminder_backoff_counter += 1;
 
-                                debug!(
-                                    "incremented re-notify backoff counter to: {} (limit: {})",
-                                    store.states.notifier.reminder_backoff_counter,
-                                    notify.reminder_backoff_limit
-                                );
-                            }
-                        } else {
                             debug!(
-                                "should not re-notify about unchanged status (interval: {})",
-                                reminder_interval
+                                "incremented re-notify backoff counter to: {} (limit: {})",
+                                channel.reminder_backoff_counter, notify.reminder_backoff_limit
                             );
                         }
+                    } else {
+                        debug!(
+                            "should not re-notify about unchanged status (interval: {})",
+                            reminder_interval
+                        );
                     }
                 }
-                _ => {}
             }
+            _ => {}
         }
     }
 
-    // Bump stored values
-    store.states.status = general_status.to_owned();
-    store.states.date = Some(time_now_as_string());
+    // Digest window is over? (notify about the transitions it grouped, if still relevant)
+    let (mut digest, mut has_digest_changed) = (None, false);
+
+    let is_digest_over = channel.digest.as_ref().map_or(false, |digest| {
+        SystemTime::now()
+            .duration_since(digest.since)
+            .map_or(true, |duration| duration.as_secs() >= notify.digest_window)
+    });
+
+    if is_digest_over == true {
+        if let Some(channel_digest) = channel.digest.take() {
+            // Notice: probes that went down and recovered within the window (or the other way \
+            //   around) are left out, as well as the whole digest if the channel status went \
+            //   back to where it was when the window opened.
+            let has_crossed =
+                (channel_digest.status == Status::Dead) != (channel_status == Status::Dead);
+
+            if has_crossed == true && is_silenced == false {
+                info!(
+                    "digest window is over on channel: {}, notifying about {} transition(s)",
+                    channel_name,
+                    channel_digest.probes.len()
+                );
+
+                should_notify = true;
+                has_digest_changed = true;
+
+                let digest_probes: Vec<(String, Status)> = channel_digest
+                    .probes
+                    .into_iter()
+                    .filter(|probe| {
+                        (probe.previous_status == Status::Dead) != (probe.status == Status::Dead)
+                    })
+                    .map(|probe| (probe.id, probe.status))
+                    .collect();
+
+                if digest_probes.is_empty() == false {
+                    digest = Some(digest_probes);
+                }
+            } else {
+                info!(
+                    "digest window is over on channel: {}, with nothing to notify",
+                    channel_name
+                );
+            }
+        }
+    }
+
+    // Summarize notifications that were held back or suppressed? (if any)
+    let (held, suppressed) = take_channel_summaries(channel, channel_name, notify);
+
+    if held.is_some() == true || suppressed.is_some() == true {
+        should_notify = true;
+    }
+
+    // Track channel downtime (used to tell for how long the channel has been, or was, down)
+    if channel.status != Status::Dead && channel_status == Status::Dead {
+        channel.dead_since = Some(SystemTime::now());
+        channel.recovered_at = None;
+    } else if channel.status == Status::Dead && channel_status != Status::Dead {
+        channel.recovered_at = Some(SystemTime::now());
+    }
+
+    // Bump stored channel status
+    channel.status = channel_status.to_owned();
 
     if should_notify == true {
-        store.notified = Some(SystemTime::now());
+        channel.notified = Some(SystemTime::now());
 
         Some(BumpedStates {
-            status: general_status,
-            replicas: bumped_replicas,
-            changed: has_changed,
+            channel: channel_name.to_owned(),
+            status: channel_status,
+            replicas: channel_replicas,
+            diagnostics: channel_diagnostics,
+            changed: has_changed || has_digest_changed,
             startup: false,
+            reminder: if has_changed == false
+                && held.is_none() == true
+                && suppressed.is_none() == true
+                && has_digest_changed == false
+            {
+                Some(channel.reminder_count)
+            } else {
+                None
+            },
+            flapping: None,
+            held: held,
+            suppressed: suppressed,
+            digest: digest,
+            downtime: channel.dead_since.and_then(|dead_since| {
+                channel
+                    .recovered_at
+                    .unwrap_or_else(SystemTime::now)
+                    .duration_since(dead_since)
+                    .ok()
+            }),
         })
     } else {
         None
     }
 }
 
-fn time_now_as_string() -> String {
+fn bump_channel_summary(
+    store: &mut Store,
+    channel_name: &str,
+    notify: &ConfigNotify,
+) -> Option<BumpedStates> {
+    // Notice: as no probe is routed to this channel, the summary carries the general status.
+    let general_status = store.states.status.to_owned();
+
+    let channel = store.channels.get_mut(channel_name)?;
+
+    let (held, suppressed) = take_channel_summaries(channel, channel_name, notify);
+
+    if held.is_none() == true && suppressed.is_none() == true {
+        return None;
+    }
+
+    channel.notified = Some(SystemTime::now());
+
+    Some(BumpedStates {
+        channel: channel_name.to_owned(),
+        status: general_status,
+        replicas: Vec::new(),
+        diagnostics: Vec::new(),
+        changed: false,
+        startup: false,
+        reminder: None,
+        flapping: None,
+        held: held,
+        suppressed: suppressed,
+        digest: None,
+        downtime: None,
+    })
+}
+
+fn take_channel_summaries(
+    channel: &mut StoreChannel,
+    channel_name: &str,
+    notify: &ConfigNotify,
+) -> (Option<u32>, Option<u32>) {
+    // Quiet hours are over, and notifications were held back? (summarize current status)
+    let mut held = None;
+
+    if channel.held > 0 && quiet::active(notify).is_none() == true {
+        info!(
+            "quiet hours are over on channel: {}, summarizing {} held notification(s)",
+            channel_name, channel.held
+        );
+
+        held = Some(channel.held);
+
+        channel.held = 0;
+    }
+
+    // Rate limit has room again, and notifications were suppressed? (summarize current status)
+    let mut suppressed = None;
+
+    if channel.suppressed > 0 && limit::available(channel_name, notify) == true {
+        info!(
+            "rate limit has room again on channel: {}, summarizing {} suppressed notification(s)",
+            channel_name, channel.suppressed
+        );
+
+        suppressed = Some(channel.suppressed);
+
+        channel.suppressed = 0;
+    }
+
+    (held, suppressed)
+}
+
+fn iter_notify_channels(notify: &ConfigNotify) -> impl Iterator<Item = (&str, &ConfigNotify)> {
+    // Notice: the default channel holds notifiers that are configured at the top-level
+    iter::once((DEFAULT_CHANNEL, notify)).chain(
+        notify
+            .channels
+            .iter()
+            .map(|(channel_name, channel_notify)| (channel_name.as_str(), channel_notify)),
+    )
+}
+
+pub fn time_now_as_string() -> String {
     time::OffsetDateTime::now_utc()
         .format(&TIME_NOW_FORMATTER)
         .unwrap_or("?".to_string())
@@ -321,59 +1013,220 @@ fn time_now_as_string() -> String {
 
 fn dispatch_startup_notification() {
     if let Some(ref conf_notify) = APP_CONF.notify {
-        if conf_notify.startup_notification == true {
-            debug!("sending aggregate startup notification...");
-
-            notify(&BumpedStates {
-                status: Status::Healthy,
-                replicas: Vec::new(),
-                changed: true,
-                startup: true,
-            });
+        for (channel_name, channel_notify) in iter_notify_channels(conf_notify) {
+            if channel_notify.startup_notification == true {
+                debug!(
+                    "sending aggregate startup notification on channel: {}...",
+                    channel_name
+                );
+
+                notify(&BumpedStates {
+                    channel: channel_name.to_owned(),
+                    status: Status::Healthy,
+                    replicas: Vec::new(),
+                    diagnostics: Vec::new(),
+                    changed: true,
+                    startup: true,
+                    reminder: None,
+                    flapping: None,
+                    held: None,
+                    suppressed: None,
+                    digest: None,
+                    downtime: None,
+                });
+            }
         }
     }
 }
 
 fn notify(bumped_states: &BumpedStates) {
-    let notification = Notification {
+    let mut notification = Notification {
+        channel: &bumped_states.channel,
         status: &bumped_states.status,
         time: time_now_as_string(),
         replicas: Vec::from_iter(bumped_states.replicas.iter().map(String::as_str)),
+        diagnostics: Vec::from_iter(
+            bumped_states
+                .diagnostics
+                .iter()
+                .map(|(replica, output)| (replica.as_str(), output.as_str())),
+        ),
         changed: bumped_states.changed,
         startup: bumped_states.startup,
+        reminder: bumped_states.reminder,
+        flapping: bumped_states.flapping.as_deref(),
+        held: bumped_states.held,
+        suppressed: bumped_states.suppressed,
+        digest: bumped_states.digest.as_ref().map(|digest| {
+            digest
+                .iter()
+                .map(|(probe_id, status)| (probe_id.as_str(), status))
+                .collect()
+        }),
+        downtime: bumped_states.downtime,
+        test: false,
     };
 
-    if let Some(ref notify) = APP_CONF.notify {
-        #[cfg(feature = "notifier-email")]
-        Notification::dispatch::<EmailNotifier>(notify, &notification).ok();
+    let notify = match find_channel(&bumped_states.channel) {
+        Some(notify) => notify,
+        None => return,
+    };
 
-        #[cfg(feature = "notifier-twilio")]
-        Notification::dispatch::<TwilioNotifier>(notify, &notification).ok();
+    // Channel does not get notified about this status? (eg. recovery-only channels)
+    // Notice: startup notifications are not filtered, as they are toggled on their own.
+    if bumped_states.startup == false && notify.notify_on.contains(&bumped_states.status) == false {
+        debug!(
+            "skipped {:?} notification on channel: {}, as it does not notify on this status",
+            bumped_states.status, bumped_states.channel
+        );
 
-        #[cfg(feature = "notifier-slack")]
-        Notification::dispatch::<SlackNotifier>(notify, &notification).ok();
+        return;
+    }
 
-        #[cfg(feature = "notifier-zulip")]
-        Notification::dispatch::<ZulipNotifier>(notify, &notification).ok();
+    // Channel is in quiet hours? (hold notification back, or reroute it to another channel)
+    if let Some(quiet_hours) = quiet::active(notify) {
+        match (quiet_hours.action, quiet_hours.reroute_to.as_ref()) {
+            (ConfigNotifyQuietHoursAction::Reroute, Some(reroute_to)) => {
+                if let Some(reroute_notify) = find_channel(reroute_to) {
+                    info!(
+                        "rerouted notification from channel: {} to channel: {} (quiet hours)",
+                        bumped_states.channel, reroute_to
+                    );
 
-        #[cfg(feature = "notifier-telegram")]
-        Notification::dispatch::<TelegramNotifier>(notify, &notification).ok();
+                    // Notice: the notification is dispatched as if it belonged to the fallback \
+                    //   channel, so that retries also happen on the fallback channel.
+                    notification.channel = reroute_to;
 
-        #[cfg(feature = "notifier-pushover")]
-        Notification::dispatch::<PushoverNotifier>(notify, &notification).ok();
+                    dispatch_limited(reroute_notify, &notification);
 
-        #[cfg(feature = "notifier-gotify")]
-        Notification::dispatch::<GotifyNotifier>(notify, &notification).ok();
+                    return;
+                }
+            }
+            _ => {
+                info!(
+                    "held back notification on channel: {} (quiet hours)",
+                    bumped_states.channel
+                );
 
-        #[cfg(feature = "notifier-xmpp")]
-        Notification::dispatch::<XMPPNotifier>(notify, &notification).ok();
+                hold(&bumped_states.channel);
+
+                return;
+            }
+        }
+    }
 
-        #[cfg(feature = "notifier-matrix")]
-        Notification::dispatch::<MatrixNotifier>(notify, &notification).ok();
+    dispatch_limited(notify, &notification);
+}
 
-        #[cfg(feature = "notifier-webhook")]
-        Notification::dispatch::<WebHookNotifier>(notify, &notification).ok();
+pub fn dispatch_limited(notify: &ConfigNotify, notification: &Notification) {
+    // Channel went over its rate limit? (suppress notification, it gets summarized later)
+    // Notice: startup notifications are not rate-limited, as they are only sent once.
+    if notification.startup == false && limit::acquire(notification.channel, notify) == false {
+        info!(
+            "suppressed notification on channel: {} (rate limit)",
+            notification.channel
+        );
+
+        // Notice: a suppressed summary carries the notifications it was summarizing, which \
+        //   must not get lost.
+        suppress(
+            notification.channel,
+            notification.suppressed.unwrap_or(0).saturating_add(1),
+        );
+
+        return;
     }
+
+    dispatch(notify, notification);
+}
+
+fn dispatch(notify: &ConfigNotify, notification: &Notification) {
+    #[cfg(feature = "notifier-email")]
+    Notification::dispatch::<EmailNotifier>(notify, notification).ok();
+
+    #[cfg(feature = "notifier-twilio")]
+    Notification::dispatch::<TwilioNotifier>(notify, notification).ok();
+
+    #[cfg(feature = "notifier-slack")]
+    Notification::dispatch::<SlackNotifier>(notify, notification).ok();
+
+    #[cfg(feature = "notifier-zulip")]
+    Notification::dispatch::<ZulipNotifier>(notify, notification).ok();
+
+    #[cfg(feature = "notifier-telegram")]
+    Notification::dispatch::<TelegramNotifier>(notify, notification).ok();
+
+    #[cfg(feature = "notifier-pushover")]
+    Notification::dispatch::<PushoverNotifier>(notify, notification).ok();
+
+    #[cfg(feature = "notifier-gotify")]
+    Notification::dispatch::<GotifyNotifier>(notify, notification).ok();
+
+    #[cfg(feature = "notifier-xmpp")]
+    Notification::dispatch::<XMPPNotifier>(notify, notification).ok();
+
+    #[cfg(feature = "notifier-matrix")]
+    Notification::dispatch::<MatrixNotifier>(notify, notification).ok();
+
+    #[cfg(feature = "notifier-webhook")]
+    Notification::dispatch::<WebHookNotifier>(notify, notification).ok();
+
+    #[cfg(feature = "notifier-opsgenie")]
+    Notification::dispatch::<OpsgenieNotifier>(notify, notification).ok();
+
+    #[cfg(feature = "notifier-ntfy")]
+    Notification::dispatch::<NtfyNotifier>(notify, notification).ok();
+
+    #[cfg(feature = "notifier-teams")]
+    Notification::dispatch::<TeamsNotifier>(notify, notification).ok();
+
+    #[cfg(feature = "notifier-discord")]
+    Notification::dispatch::<DiscordNotifier>(notify, notification).ok();
+
+    #[cfg(feature = "notifier-mattermost")]
+    Notification::dispatch::<MattermostNotifier>(notify, notification).ok();
+
+    #[cfg(feature = "notifier-rocketchat")]
+    Notification::dispatch::<RocketChatNotifier>(notify, notification).ok();
+
+    #[cfg(feature = "notifier-googlechat")]
+    Notification::dispatch::<GoogleChatNotifier>(notify, notification).ok();
+
+    #[cfg(feature = "notifier-signal")]
+    Notification::dispatch::<SignalNotifier>(notify, notification).ok();
+
+    #[cfg(feature = "notifier-sms")]
+    Notification::dispatch::<SMSNotifier>(notify, notification).ok();
+
+    #[cfg(feature = "notifier-sns")]
+    Notification::dispatch::<SNSNotifier>(notify, notification).ok();
+
+    #[cfg(feature = "notifier-mqtt")]
+    Notification::dispatch::<MQTTNotifier>(notify, notification).ok();
+
+    #[cfg(feature = "notifier-irc")]
+    Notification::dispatch::<IRCNotifier>(notify, notification).ok();
+
+    #[cfg(feature = "notifier-apprise")]
+    Notification::dispatch::<AppriseNotifier>(notify, notification).ok();
+}
+
+fn suppress(channel_name: &str, count: u32) {
+    // Count suppressed notifications (those get summarized once the rate limit has room again)
+    // Notice: channels might not have been bumped yet if no probe is routed to them (eg. \
+    //   channels that notifications get rerouted or escalated to), thus they are created here.
+    let mut store = PROBER_STORE.write().unwrap();
+    let channel = store.channels.entry(channel_name.to_owned()).or_default();
+
+    channel.suppressed = channel.suppressed.saturating_add(count);
+}
+
+fn hold(channel_name: &str) {
+    // Count held notification (those get summarized once quiet hours are over)
+    let mut store = PROBER_STORE.write().unwrap();
+    let channel = store.channels.entry(channel_name.to_owned()).or_default();
+
+    channel.held = channel.held.saturating_add(1);
 }
 
 pub fn run() {
@@ -381,22 +1234,49 @@ pub fn run() {
     dispatch_startup_notification();
 
     // Start aggregate loop
+    let mut is_startup = true;
+    let mut pruned_at = Instant::now();
+
     loop {
         debug!("running an aggregate operation...");
 
         // Should notify after bump?
-        let bumped_states = scan_and_bump_states();
+        let bumped_states = scan_and_bump_states(is_startup);
 
-        if let Some(ref bumped_states_inner) = bumped_states {
+        is_startup = false;
+
+        for bumped_states_inner in bumped_states.iter() {
             notify(bumped_states_inner);
         }
 
         info!(
             "ran aggregate operation (notified: {})",
-            bumped_states.is_some()
+            bumped_states.is_empty() == false
         );
 
+        // Prune expired history entries (storage is also pruned upon startup)
+        if pruned_at.elapsed() >= Duration::from_secs(STORAGE_PRUNE_INTERVAL_SECONDS) {
+            storage::prune();
+
+            pruned_at = Instant::now();
+        }
+
         // Hold for next aggregate run
         thread::sleep(Duration::from_secs(AGGREGATE_INTERVAL_SECONDS));
     }
 }
+
+pub fn reload_channels() {
+    // Forget about channels that are not configured anymore (so that they start over if added back)
+    let mut store = PROBER_STORE.write().unwrap();
+
+    match APP_CONF.notify {
+        Some(ref notify) => store.channels.retain(|channel_name, _| {
+            iter_notify_channels(notify)
+                .any(|(notify_channel_name, _)| notify_channel_name == channel_name.as_str())
+        }),
+        None => store.channels.clear(),
+    }
+
+    info!("reloaded aggregator channels");
+}
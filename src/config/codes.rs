@@ -0,0 +1,135 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::fmt;
+
+use serde::de::{Error, Visitor};
+use serde::{Deserialize, Deserializer};
+
+#[derive(Clone, Debug)]
+pub struct StatusCodes {
+    ranges: Vec<(u16, u16)>,
+}
+
+impl StatusCodes {
+    pub fn parse(expression: &str) -> Result<StatusCodes, String> {
+        let mut ranges = Vec::new();
+
+        for part in expression.split(',').map(str::trim) {
+            // Acquire range (eg. '401', '2xx' or '500-503')
+            let range = if let Some(class) = part.strip_suffix("xx") {
+                let class = parse_code(class, 1, 5)?;
+
+                (class * 100, class * 100 + 99)
+            } else if let Some((start, end)) = part.split_once('-') {
+                (parse_code(start, 100, 599)?, parse_code(end, 100, 599)?)
+            } else {
+                let code = parse_code(part, 100, 599)?;
+
+                (code, code)
+            };
+
+            if range.0 > range.1 {
+                return Err(format!("invalid status code range: {}", part));
+            }
+
+            ranges.push(range);
+        }
+
+        Ok(StatusCodes { ranges: ranges })
+    }
+
+    pub fn contains(&self, code: u16) -> bool {
+        self.ranges
+            .iter()
+            .any(|(start, end)| code >= *start && code <= *end)
+    }
+}
+
+impl<'de> Deserialize<'de> for StatusCodes {
+    fn deserialize<D>(de: D) -> Result<StatusCodes, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct StatusCodesVisitor;
+
+        impl<'de> Visitor<'de> for StatusCodesVisitor {
+            type Value = StatusCodes;
+
+            fn expecting(&self, format: &mut fmt::Formatter) -> fmt::Result {
+                format.write_str("a list of status codes (eg. '2xx,401')")
+            }
+
+            fn visit_str<E: Error>(self, value: &str) -> Result<StatusCodes, E> {
+                StatusCodes::parse(value).map_err(E::custom)
+            }
+        }
+
+        de.deserialize_str(StatusCodesVisitor)
+    }
+}
+
+fn parse_code(value: &str, minimum: u16, maximum: u16) -> Result<u16, String> {
+    match value.trim().parse::<u16>() {
+        Ok(code) if code >= minimum && code <= maximum => Ok(code),
+        _ => Err(format!("invalid status code: {}", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contains(expression: &str, code: u16) -> bool {
+        StatusCodes::parse(expression).unwrap().contains(code)
+    }
+
+    #[test]
+    fn it_matches_status_classes() {
+        assert!(contains("2xx", 200));
+        assert!(contains("2xx", 299));
+        assert!(!contains("2xx", 199));
+        assert!(!contains("2xx", 300));
+        assert!(contains("5xx", 503));
+    }
+
+    #[test]
+    fn it_matches_status_ranges() {
+        assert!(contains("500-503", 500));
+        assert!(contains("500-503", 503));
+        assert!(!contains("500-503", 504));
+        assert!(contains("404-404", 404));
+    }
+
+    #[test]
+    fn it_matches_single_status_codes() {
+        assert!(contains("401", 401));
+        assert!(!contains("401", 400));
+    }
+
+    #[test]
+    fn it_matches_status_lists() {
+        assert!(contains("2xx, 401,500-503", 204));
+        assert!(contains("2xx, 401,500-503", 401));
+        assert!(contains("2xx, 401,500-503", 502));
+        assert!(!contains("2xx, 401,500-503", 403));
+    }
+
+    #[test]
+    fn it_rejects_invalid_status_codes() {
+        assert!(StatusCodes::parse("6xx").is_err());
+        assert!(StatusCodes::parse("0xx").is_err());
+        assert!(StatusCodes::parse("xx").is_err());
+        assert!(StatusCodes::parse("503-500").is_err());
+        assert!(StatusCodes::parse("500-").is_err());
+        assert!(StatusCodes::parse("99").is_err());
+        assert!(StatusCodes::parse("600").is_err());
+        assert!(StatusCodes::parse("abc").is_err());
+        assert!(StatusCodes::parse("").is_err());
+        assert!(StatusCodes::parse("200,").is_err());
+        assert!(StatusCodes::parse("200,,404").is_err());
+    }
+}
@@ -7,11 +7,16 @@
 use std::net::SocketAddr;
 use std::path::PathBuf;
 
+use indexmap::IndexMap;
 use url_serde::SerdeUrl;
 
+use super::codes::StatusCodes;
 use super::defaults;
 use super::regex::Regex;
+use super::schedule::Schedule;
 use crate::prober::mode::Mode;
+use crate::prober::severity::Severity;
+use crate::prober::status::Status;
 
 #[derive(Deserialize)]
 pub struct Config {
@@ -19,7 +24,12 @@ pub struct Config {
     pub assets: ConfigAssets,
     pub branding: ConfigBranding,
     pub metrics: ConfigMetrics,
+    pub storage: Option<ConfigStorage>,
+    pub incidents: Option<ConfigIncidents>,
+    pub subscriptions: Option<ConfigSubscriptions>,
+    pub discovery: Option<ConfigDiscovery>,
     pub plugins: Option<ConfigPlugins>,
+    pub tracing: Option<ConfigTracing>,
     pub notify: Option<ConfigNotify>,
     pub probe: ConfigProbe,
 }
@@ -29,13 +39,79 @@ pub struct ConfigServer {
     #[serde(default = "defaults::server_log_level")]
     pub log_level: String,
 
+    #[serde(default = "defaults::server_log_format")]
+    pub log_format: ConfigServerLogFormat,
+
+    #[serde(default)]
+    pub log_modules: IndexMap<String, String>,
+
     #[serde(default = "defaults::server_inet")]
     pub inet: SocketAddr,
 
     #[serde(default = "defaults::server_workers")]
     pub workers: usize,
 
+    #[serde(default = "defaults::server_compress")]
+    pub compress: bool,
+
+    #[serde(default)]
+    pub base_path: String,
+
     pub reporter_token: String,
+    pub reporter_secret: Option<String>,
+
+    #[serde(default = "defaults::server_reporter_signature_window")]
+    pub reporter_signature_window: u64,
+
+    pub manager_token: Option<String>,
+    pub tls: Option<ConfigServerTLS>,
+    pub cors: Option<ConfigServerCORS>,
+    pub rate_limit: Option<ConfigServerRateLimit>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ConfigServerLogFormat {
+    #[serde(rename = "pretty")]
+    Pretty,
+
+    #[serde(rename = "json")]
+    JSON,
+}
+
+#[derive(Deserialize, PartialEq)]
+pub struct ConfigServerCORS {
+    pub allowed_origins: Vec<String>,
+
+    #[serde(default = "defaults::server_cors_allowed_methods")]
+    pub allowed_methods: Vec<String>,
+
+    #[serde(default)]
+    pub allowed_headers: Vec<String>,
+
+    #[serde(default = "defaults::server_cors_max_age")]
+    pub max_age: usize,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigServerRateLimit {
+    #[serde(default)]
+    pub trust_forwarded_for: bool,
+
+    pub public: Option<ConfigServerRateLimitBucket>,
+    pub reporter: Option<ConfigServerRateLimitBucket>,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigServerRateLimitBucket {
+    pub rate: f64,
+    pub burst: u32,
+}
+
+#[derive(Deserialize, Clone, PartialEq)]
+pub struct ConfigServerTLS {
+    pub certificate_path: PathBuf,
+    pub key_path: PathBuf,
+    pub reload_interval: Option<u64>,
 }
 
 #[derive(Deserialize)]
@@ -58,6 +134,18 @@ pub struct ConfigBranding {
     pub website_url: SerdeUrl,
     pub support_url: SerdeUrl,
     pub custom_html: Option<String>,
+
+    #[serde(default = "defaults::branding_badge_color_healthy")]
+    pub badge_color_healthy: String,
+
+    #[serde(default = "defaults::branding_badge_color_sick")]
+    pub badge_color_sick: String,
+
+    #[serde(default = "defaults::branding_badge_color_dead")]
+    pub badge_color_dead: String,
+
+    #[serde(default = "defaults::branding_badge_color_unknown")]
+    pub badge_color_unknown: String,
 }
 
 #[derive(Deserialize)]
@@ -68,6 +156,9 @@ pub struct ConfigMetrics {
     #[serde(default = "defaults::metrics_poll_retry")]
     pub poll_retry: u64,
 
+    #[serde(default = "defaults::metrics_poll_parallelism")]
+    pub poll_parallelism: usize,
+
     #[serde(default = "defaults::metrics_poll_http_status_healthy_above")]
     pub poll_http_status_healthy_above: u16,
 
@@ -80,6 +171,10 @@ pub struct ConfigMetrics {
     #[serde(default = "defaults::metrics_poll_delay_sick")]
     pub poll_delay_sick: u64,
 
+    pub poll_tls_expiry_sick_below: Option<u16>,
+    pub poll_proxy: Option<SerdeUrl>,
+    pub poll_dns_cache_ttl: Option<u64>,
+
     #[serde(default = "defaults::metrics_push_delay_dead")]
     pub push_delay_dead: u64,
 
@@ -94,6 +189,102 @@ pub struct ConfigMetrics {
 
     #[serde(default = "defaults::metrics_local_delay_dead")]
     pub local_delay_dead: u64,
+
+    pub flap_threshold: Option<usize>,
+
+    #[serde(default = "defaults::metrics_flap_window")]
+    pub flap_window: u64,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigStorage {
+    pub path: PathBuf,
+
+    #[serde(default = "defaults::storage_history_retention")]
+    pub history_retention: u64,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigIncidents {
+    #[serde(default = "defaults::incidents_automatic")]
+    pub automatic: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigSubscriptions {
+    pub secret: String,
+
+    #[serde(default = "defaults::subscriptions_retry_attempts")]
+    pub retry_attempts: u8,
+
+    #[serde(default = "defaults::subscriptions_retry_delay")]
+    pub retry_delay: u64,
+
+    #[serde(default)]
+    pub hook: Vec<ConfigSubscriptionsHook>,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigSubscriptionsHook {
+    pub url: SerdeUrl,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigDiscovery {
+    pub kubernetes: Option<ConfigDiscoveryKubernetes>,
+    pub consul: Option<ConfigDiscoveryConsul>,
+    pub docker: Option<ConfigDiscoveryDocker>,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigDiscoveryKubernetes {
+    #[serde(default = "defaults::discovery_kubernetes_api_url")]
+    pub api_url: SerdeUrl,
+
+    #[serde(default = "defaults::discovery_kubernetes_token_path")]
+    pub token_path: PathBuf,
+
+    #[serde(default = "defaults::discovery_kubernetes_ca_path")]
+    pub ca_path: PathBuf,
+
+    #[serde(default)]
+    pub namespaces: Vec<String>,
+
+    #[serde(default = "defaults::discovery_kubernetes_refresh_interval")]
+    pub refresh_interval: u64,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigDiscoveryConsul {
+    #[serde(default = "defaults::discovery_consul_api_url")]
+    pub api_url: SerdeUrl,
+
+    pub token: Option<String>,
+    pub datacenter: Option<String>,
+
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    #[serde(default = "defaults::discovery_consul_service_id")]
+    pub service_id: String,
+
+    #[serde(default = "defaults::discovery_consul_service_label")]
+    pub service_label: String,
+
+    #[serde(default = "defaults::discovery_consul_scheme")]
+    pub scheme: String,
+
+    #[serde(default = "defaults::discovery_consul_refresh_interval")]
+    pub refresh_interval: u64,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigDiscoveryDocker {
+    #[serde(default = "defaults::discovery_docker_socket_path")]
+    pub socket_path: PathBuf,
+
+    #[serde(default = "defaults::discovery_docker_refresh_interval")]
+    pub refresh_interval: u64,
 }
 
 #[derive(Deserialize)]
@@ -101,6 +292,7 @@ pub struct ConfigNotify {
     #[serde(default = "defaults::notify_startup_notification")]
     pub startup_notification: bool,
 
+    #[serde(alias = "reminders_interval")]
     pub reminder_interval: Option<u64>,
 
     #[serde(default = "defaults::notify_reminder_backoff_function")]
@@ -109,6 +301,42 @@ pub struct ConfigNotify {
     #[serde(default = "defaults::notify_reminder_backoff_limit")]
     pub reminder_backoff_limit: u16,
 
+    #[serde(default = "defaults::notify_digest")]
+    pub digest: bool,
+
+    #[serde(default = "defaults::notify_digest_window")]
+    pub digest_window: u64,
+
+    #[serde(default = "defaults::notify_acknowledge_duration")]
+    pub acknowledge_duration: u64,
+
+    #[serde(default = "defaults::notify_acknowledge_link_expiry")]
+    pub acknowledge_link_expiry: u64,
+
+    pub rate_limit: Option<u32>,
+
+    #[serde(default = "defaults::notify_rate_limit_window")]
+    pub rate_limit_window: u64,
+
+    pub global_rate_limit: Option<u32>,
+
+    #[serde(default = "defaults::notify_rate_limit_window")]
+    pub global_rate_limit_window: u64,
+
+    #[serde(default = "defaults::notify_min_severity")]
+    pub min_severity: Severity,
+
+    #[serde(default = "defaults::notify_notify_on")]
+    pub notify_on: Vec<Status>,
+
+    #[serde(default = "defaults::notify_queue")]
+    pub queue: ConfigNotifyQueue,
+
+    pub template: Option<ConfigNotifyTemplate>,
+
+    #[serde(default)]
+    pub quiet_hours: Vec<ConfigNotifyQuietHours>,
+
     pub email: Option<ConfigNotifyEmail>,
     pub twilio: Option<ConfigNotifyTwilio>,
     pub slack: Option<ConfigNotifySlack>,
@@ -119,6 +347,109 @@ pub struct ConfigNotify {
     pub xmpp: Option<ConfigNotifyXMPP>,
     pub matrix: Option<ConfigNotifyMatrix>,
     pub webhook: Option<ConfigNotifyWebHook>,
+    pub opsgenie: Option<ConfigNotifyOpsgenie>,
+    pub ntfy: Option<ConfigNotifyNtfy>,
+    pub teams: Option<ConfigNotifyTeams>,
+    pub discord: Option<ConfigNotifyDiscord>,
+    pub mattermost: Option<ConfigNotifyMattermost>,
+    pub rocketchat: Option<ConfigNotifyRocketChat>,
+    pub googlechat: Option<ConfigNotifyGoogleChat>,
+    pub signal: Option<ConfigNotifySignal>,
+    pub sms: Option<ConfigNotifySMS>,
+    pub sns: Option<ConfigNotifySNS>,
+    pub mqtt: Option<ConfigNotifyMQTT>,
+    pub irc: Option<ConfigNotifyIRC>,
+    pub apprise: Option<ConfigNotifyApprise>,
+
+    #[serde(default)]
+    pub channels: IndexMap<String, ConfigNotify>,
+
+    #[serde(default)]
+    pub escalations: IndexMap<String, ConfigNotifyEscalation>,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigNotifyEscalation {
+    pub steps: Vec<ConfigNotifyEscalationStep>,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigNotifyEscalationStep {
+    #[serde(default)]
+    pub after: u64,
+
+    pub notify: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigNotifyQuietHours {
+    #[serde(default)]
+    pub days: Vec<ConfigNotifyQuietHoursDay>,
+
+    pub from: String,
+    pub to: String,
+
+    #[serde(default = "defaults::notify_quiet_hours_utc_offset")]
+    pub utc_offset: String,
+
+    #[serde(default = "defaults::notify_quiet_hours_action")]
+    pub action: ConfigNotifyQuietHoursAction,
+
+    pub reroute_to: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ConfigNotifyQuietHoursDay {
+    #[serde(rename = "mon")]
+    Monday,
+
+    #[serde(rename = "tue")]
+    Tuesday,
+
+    #[serde(rename = "wed")]
+    Wednesday,
+
+    #[serde(rename = "thu")]
+    Thursday,
+
+    #[serde(rename = "fri")]
+    Friday,
+
+    #[serde(rename = "sat")]
+    Saturday,
+
+    #[serde(rename = "sun")]
+    Sunday,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ConfigNotifyQuietHoursAction {
+    #[serde(rename = "suppress")]
+    Suppress,
+
+    #[serde(rename = "reroute")]
+    Reroute,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigNotifyTemplate {
+    pub subject: Option<String>,
+    pub body: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigNotifyQueue {
+    #[serde(default = "defaults::notify_queue_retry_attempts")]
+    pub retry_attempts: u32,
+
+    #[serde(default = "defaults::notify_queue_retry_delay")]
+    pub retry_delay: u64,
+
+    #[serde(default = "defaults::notify_queue_retry_delay_maximum")]
+    pub retry_delay_maximum: u64,
+
+    pub path: Option<PathBuf>,
+    pub dead_letter_path: Option<PathBuf>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
@@ -154,10 +485,37 @@ pub struct ConfigPluginsRabbitMQ {
     pub queue_loaded_retry_delay: Option<u64>,
 }
 
+#[derive(Deserialize)]
+pub struct ConfigTracing {
+    pub endpoint: SerdeUrl,
+
+    #[serde(default = "defaults::tracing_service_name")]
+    pub service_name: String,
+
+    #[serde(default)]
+    pub headers: IndexMap<String, String>,
+
+    #[serde(default = "defaults::tracing_sample_ratio")]
+    pub sample_ratio: f64,
+
+    #[serde(default = "defaults::tracing_export_interval")]
+    pub export_interval: u64,
+
+    #[serde(default = "defaults::tracing_max_queue")]
+    pub max_queue: usize,
+}
+
 #[derive(Deserialize)]
 pub struct ConfigNotifyEmail {
     pub to: String,
     pub from: String,
+    pub reply_to: Option<String>,
+
+    #[serde(default)]
+    pub to_groups: IndexMap<String, Vec<String>>,
+
+    #[serde(default = "defaults::notify_email_html")]
+    pub html: bool,
 
     #[serde(default = "defaults::notify_email_smtp_host")]
     pub smtp_host: String,
@@ -167,14 +525,26 @@ pub struct ConfigNotifyEmail {
 
     pub smtp_username: Option<String>,
     pub smtp_password: Option<String>,
+    pub smtp_oauth2: Option<ConfigNotifyEmailOAuth2>,
 
     #[serde(default = "defaults::notify_email_smtp_encrypt")]
     pub smtp_encrypt: bool,
 
+    #[serde(default = "defaults::notify_email_smtp_reuse_timeout")]
+    pub smtp_reuse_timeout: u64,
+
     #[serde(default = "defaults::notify_generic_reminders_only")]
     pub reminders_only: bool,
 }
 
+#[derive(Deserialize)]
+pub struct ConfigNotifyEmailOAuth2 {
+    pub token_url: SerdeUrl,
+    pub client_id: String,
+    pub client_secret: String,
+    pub scope: Option<String>,
+}
+
 #[derive(Deserialize)]
 pub struct ConfigNotifyTwilio {
     pub to: Vec<String>,
@@ -193,6 +563,8 @@ pub struct ConfigNotifySlack {
     #[serde(default = "defaults::notify_slack_mention_channel")]
     pub mention_channel: bool,
 
+    pub signing_secret: Option<String>,
+
     #[serde(default = "defaults::notify_generic_reminders_only")]
     pub reminders_only: bool,
 }
@@ -204,6 +576,9 @@ pub struct ConfigNotifyZulip {
     pub channel: String,
     pub api_url: SerdeUrl,
 
+    #[serde(default = "defaults::notify_zulip_topic")]
+    pub topic: String,
+
     #[serde(default = "defaults::notify_generic_reminders_only")]
     pub reminders_only: bool,
 }
@@ -212,6 +587,12 @@ pub struct ConfigNotifyZulip {
 pub struct ConfigNotifyTelegram {
     pub bot_token: String,
     pub chat_id: String,
+    pub topic_id: Option<i64>,
+
+    #[serde(default = "defaults::notify_telegram_threads")]
+    pub threads: bool,
+
+    pub webhook_secret: Option<String>,
 
     #[serde(default = "defaults::notify_generic_reminders_only")]
     pub reminders_only: bool,
@@ -230,6 +611,12 @@ pub struct ConfigNotifyPushover {
 pub struct ConfigNotifyGotify {
     pub app_url: SerdeUrl,
     pub app_token: String,
+    pub priority_dead: Option<u8>,
+    pub priority_sick: Option<u8>,
+    pub priority_healthy: Option<u8>,
+
+    #[serde(default = "defaults::notify_gotify_priority_reminder")]
+    pub priority_reminder: u8,
 
     #[serde(default = "defaults::notify_generic_reminders_only")]
     pub reminders_only: bool,
@@ -237,9 +624,15 @@ pub struct ConfigNotifyGotify {
 
 #[derive(Deserialize)]
 pub struct ConfigNotifyXMPP {
-    pub to: String,
+    pub to: Option<String>,
     pub from: String,
     pub xmpp_password: String,
+    pub room: Option<String>,
+
+    #[serde(default = "defaults::notify_xmpp_room_nick")]
+    pub room_nick: String,
+
+    pub room_password: Option<String>,
 
     #[serde(default = "defaults::notify_generic_reminders_only")]
     pub reminders_only: bool,
@@ -248,6 +641,12 @@ pub struct ConfigNotifyXMPP {
 #[derive(Deserialize)]
 pub struct ConfigNotifyWebHook {
     pub hook_url: SerdeUrl,
+    pub method: Option<String>,
+
+    #[serde(default)]
+    pub headers: IndexMap<String, String>,
+
+    pub body: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -256,12 +655,200 @@ pub struct ConfigNotifyMatrix {
     pub access_token: String,
     pub room_id: String,
 
+    #[serde(default = "defaults::notify_matrix_html")]
+    pub html: bool,
+
+    #[serde(default = "defaults::notify_generic_reminders_only")]
+    pub reminders_only: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigNotifyDiscord {
+    pub hook_url: SerdeUrl,
+
+    #[serde(default = "defaults::notify_generic_reminders_only")]
+    pub reminders_only: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigNotifyMattermost {
+    pub hook_url: SerdeUrl,
+    pub channel: Option<String>,
+    pub username: Option<String>,
+    pub icon_url: Option<SerdeUrl>,
+
+    #[serde(default = "defaults::notify_mattermost_mention_channel")]
+    pub mention_channel: bool,
+
+    #[serde(default = "defaults::notify_generic_reminders_only")]
+    pub reminders_only: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigNotifyRocketChat {
+    pub hook_url: SerdeUrl,
+    pub channel: Option<String>,
+    pub alias: Option<String>,
+    pub emoji: Option<String>,
+
+    #[serde(default = "defaults::notify_rocketchat_mention_all")]
+    pub mention_all: bool,
+
+    #[serde(default = "defaults::notify_generic_reminders_only")]
+    pub reminders_only: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigNotifyGoogleChat {
+    pub hook_url: SerdeUrl,
+
+    #[serde(default = "defaults::notify_generic_reminders_only")]
+    pub reminders_only: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigNotifySignal {
+    pub api_url: SerdeUrl,
+    pub number: String,
+    pub recipients: Vec<String>,
+
+    #[serde(default = "defaults::notify_generic_reminders_only")]
+    pub reminders_only: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigNotifySMS {
+    pub recipients: Vec<String>,
+    pub gateway_url: String,
+
+    #[serde(default = "defaults::notify_sms_method")]
+    pub method: String,
+
+    pub auth_header: Option<String>,
+    pub body: Option<String>,
+
+    #[serde(default = "defaults::notify_sms_content_type")]
+    pub content_type: String,
+
+    #[serde(default = "defaults::notify_sms_max_length")]
+    pub max_length: usize,
+
+    #[serde(default = "defaults::notify_generic_reminders_only")]
+    pub reminders_only: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigNotifySNS {
+    pub topic_arn: String,
+    pub region: Option<String>,
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+    pub session_token: Option<String>,
+
+    #[serde(default = "defaults::notify_generic_reminders_only")]
+    pub reminders_only: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigNotifyMQTT {
+    pub server_url: SerdeUrl,
+    pub username: Option<String>,
+    pub password: Option<String>,
+
+    #[serde(default = "defaults::notify_mqtt_client_id")]
+    pub client_id: String,
+
+    #[serde(default = "defaults::notify_mqtt_topic")]
+    pub topic: String,
+
+    #[serde(default = "defaults::notify_mqtt_qos")]
+    pub qos: u8,
+
+    #[serde(default = "defaults::notify_mqtt_retain")]
+    pub retain: bool,
+
+    #[serde(default = "defaults::notify_generic_reminders_only")]
+    pub reminders_only: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigNotifyIRC {
+    pub server_url: SerdeUrl,
+    pub nick: String,
+    pub channel: String,
+    pub channel_key: Option<String>,
+    pub sasl_username: Option<String>,
+    pub sasl_password: Option<String>,
+
+    #[serde(default = "defaults::notify_generic_reminders_only")]
+    pub reminders_only: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigNotifyApprise {
+    pub server_url: SerdeUrl,
+
+    #[serde(default)]
+    pub urls: Vec<String>,
+
+    pub config_key: Option<String>,
+    pub tag: Option<String>,
+
     #[serde(default = "defaults::notify_generic_reminders_only")]
     pub reminders_only: bool,
 }
 
+#[derive(Deserialize)]
+pub struct ConfigNotifyTeams {
+    pub hook_url: SerdeUrl,
+
+    #[serde(default = "defaults::notify_generic_reminders_only")]
+    pub reminders_only: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigNotifyNtfy {
+    #[serde(default = "defaults::notify_ntfy_server_url")]
+    pub server_url: SerdeUrl,
+
+    pub topic: String,
+    pub access_token: Option<String>,
+
+    #[serde(default = "defaults::notify_generic_reminders_only")]
+    pub reminders_only: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigNotifyOpsgenie {
+    pub api_key: String,
+
+    #[serde(default = "defaults::notify_opsgenie_api_url")]
+    pub api_url: SerdeUrl,
+
+    #[serde(default)]
+    pub teams: Vec<String>,
+
+    #[serde(default = "defaults::notify_opsgenie_priority")]
+    pub priority: ConfigNotifyOpsgeniePriority,
+
+    #[serde(default = "defaults::notify_generic_reminders_only")]
+    pub reminders_only: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ConfigNotifyOpsgeniePriority {
+    P1,
+    P2,
+    P3,
+    P4,
+    P5,
+}
+
 #[derive(Deserialize)]
 pub struct ConfigProbe {
+    pub probes_dir: Option<PathBuf>,
+
+    #[serde(default)]
     pub service: Vec<ConfigProbeService>,
 }
 
@@ -269,27 +856,163 @@ pub struct ConfigProbe {
 pub struct ConfigProbeService {
     pub id: String,
     pub label: String,
+
+    #[serde(default)]
+    pub groups: Vec<String>,
+
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+
+    #[serde(default = "defaults::probe_service_severity")]
+    pub severity: Severity,
+
+    pub reporter_secret: Option<String>,
+    pub latency_sick_ms: Option<u64>,
+    pub latency_dead_ms: Option<u64>,
+    pub retries: Option<u64>,
+    pub retry_delay: Option<u64>,
+    pub resolve: Option<ConfigProbeServiceResolve>,
+    pub dns_resolver: Option<String>,
+    pub notify: Option<Vec<String>>,
+    pub escalation: Option<String>,
+    pub opsgenie_teams: Option<Vec<String>>,
+    pub opsgenie_priority: Option<ConfigNotifyOpsgeniePriority>,
+
+    #[serde(default)]
+    pub metadata: IndexMap<String, String>,
+
     pub node: Vec<ConfigProbeServiceNode>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Debug, Clone)]
+pub enum ConfigProbeServiceResolve {
+    #[serde(rename = "any")]
+    Any,
+
+    #[serde(rename = "ipv4")]
+    IPv4,
+
+    #[serde(rename = "ipv6")]
+    IPv6,
+
+    #[serde(rename = "both")]
+    Both,
+}
+
+#[derive(Deserialize, Clone, Default)]
 pub struct ConfigProbeServiceNode {
     pub id: String,
     pub label: String,
     pub mode: Mode,
     pub replicas: Option<Vec<String>>,
-    pub scripts: Option<Vec<String>>,
+    pub scripts: Option<Vec<ConfigProbeServiceNodeScript>>,
+    pub steps: Option<Vec<ConfigProbeServiceNodeStep>>,
+    pub heartbeat_interval: Option<u64>,
+    pub heartbeat_schedule: Option<Schedule>,
+
+    #[serde(default = "defaults::probe_service_node_heartbeat_grace")]
+    pub heartbeat_grace: u64,
+
     #[serde(default)]
     #[serde(with = "http_serde::header_map")]
     pub http_headers: http::HeaderMap,
     pub http_method: Option<ConfigProbeServiceNodeHTTPMethod>,
     pub http_body: Option<String>,
     pub http_body_healthy_match: Option<Regex>,
+    pub http_body_healthy_contains: Option<String>,
+    pub http_status_healthy: Option<StatusCodes>,
+    pub http_redirect_policy: Option<ConfigProbeServiceNodeHTTPRedirectPolicy>,
+    pub http_redirect_maximum: Option<u8>,
+    pub http_redirect_final_url_match: Option<Regex>,
+    pub http_proxy: Option<SerdeUrl>,
+
+    #[serde(default)]
+    pub http_proxy_bypass: bool,
+
+    pub tls_client_certificate: Option<String>,
+    pub tls_client_key: Option<String>,
+    pub tcp_connect_timeout: Option<u64>,
+    pub tcp_banner_healthy_match: Option<Regex>,
+    pub grpc_authority: Option<String>,
+    pub websocket_pong_timeout: Option<u64>,
+
+    #[serde(default)]
+    pub smtp_starttls: bool,
+
+    pub database_password: Option<String>,
+    pub amqp_queue: Option<String>,
+    pub kafka_brokers_minimum: Option<u32>,
+    pub kafka_topic: Option<String>,
+    pub ldap_bind_dn: Option<String>,
+    pub ssh_host_key_fingerprint: Option<String>,
+    pub ntp_stratum_sick_above: Option<u8>,
+    pub ntp_offset_sick_above: Option<u64>,
+
+    #[serde(default)]
+    pub elastic_tls_insecure: bool,
+
+    pub promql_query: Option<String>,
+    pub promql_sick_above: Option<f64>,
+    pub promql_dead_above: Option<f64>,
+    pub promql_sick_below: Option<f64>,
+    pub promql_dead_below: Option<f64>,
+    pub snmp_community: Option<String>,
+    pub snmp_auth_protocol: Option<ConfigProbeServiceNodeSNMPAuthProtocol>,
+    pub snmp_sick_above: Option<f64>,
+    pub snmp_dead_above: Option<f64>,
+    pub snmp_sick_below: Option<f64>,
+    pub snmp_dead_below: Option<f64>,
+    pub icmp_count: Option<u16>,
+    pub icmp_loss_sick_above: Option<f32>,
+    pub icmp_loss_dead_above: Option<f32>,
+    pub icmp_rtt_sick_above: Option<u64>,
+    pub icmp_rtt_dead_above: Option<u64>,
+    pub diagnostic_command: Option<String>,
+
+    #[serde(default)]
+    pub diagnostic_tcp_trace: bool,
+
     pub rabbitmq_queue: Option<String>,
     pub rabbitmq_queue_nack_healthy_below: Option<u32>,
     pub rabbitmq_queue_nack_dead_above: Option<u32>,
 }
 
+#[derive(Deserialize, Clone)]
+#[serde(untagged)]
+pub enum ConfigProbeServiceNodeScript {
+    Source(String),
+    Options(ConfigProbeServiceNodeScriptOptions),
+}
+
+#[derive(Deserialize, Clone)]
+pub struct ConfigProbeServiceNodeScriptOptions {
+    pub source: String,
+
+    #[serde(default)]
+    pub args: Vec<String>,
+
+    #[serde(default)]
+    pub env: IndexMap<String, String>,
+
+    pub cwd: Option<String>,
+    pub timeout: Option<u64>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct ConfigProbeServiceNodeStep {
+    pub url: String,
+    pub method: Option<ConfigProbeServiceNodeHTTPMethod>,
+
+    #[serde(default)]
+    #[serde(with = "http_serde::header_map")]
+    pub headers: http::HeaderMap,
+    pub body: Option<String>,
+    pub status: Option<u16>,
+    pub body_healthy_match: Option<Regex>,
+    pub body_healthy_contains: Option<String>,
+    pub extract: Option<IndexMap<String, Regex>>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum ConfigProbeServiceNodeHTTPMethod {
     #[serde(rename = "HEAD")]
@@ -306,4 +1029,31 @@ pub enum ConfigProbeServiceNodeHTTPMethod {
 
     #[serde(rename = "PATCH")]
     Patch,
+
+    #[serde(rename = "DELETE")]
+    Delete,
+
+    #[serde(rename = "OPTIONS")]
+    Options,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub enum ConfigProbeServiceNodeHTTPRedirectPolicy {
+    #[serde(rename = "none")]
+    None,
+
+    #[serde(rename = "follow")]
+    Follow,
+
+    #[serde(rename = "deny")]
+    Deny,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum ConfigProbeServiceNodeSNMPAuthProtocol {
+    #[serde(rename = "md5")]
+    MD5,
+
+    #[serde(rename = "sha")]
+    SHA,
 }
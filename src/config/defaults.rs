@@ -7,12 +7,26 @@
 use std::net::SocketAddr;
 use std::path::PathBuf;
 
-use super::config::ConfigNotifyReminderBackoffFunction;
+use serde::de::value::{Error as DeserializerError, StrDeserializer};
+use serde::de::IntoDeserializer;
+use serde::Deserialize;
+use url_serde::SerdeUrl;
+
+use super::config::{
+    ConfigNotifyOpsgeniePriority, ConfigNotifyQueue, ConfigNotifyQuietHoursAction,
+    ConfigNotifyReminderBackoffFunction, ConfigServerLogFormat,
+};
+use crate::prober::severity::Severity;
+use crate::prober::status::Status;
 
 pub fn server_log_level() -> String {
     "error".to_string()
 }
 
+pub fn server_log_format() -> ConfigServerLogFormat {
+    ConfigServerLogFormat::Pretty
+}
+
 pub fn server_inet() -> SocketAddr {
     "[::1]:8080".parse().unwrap()
 }
@@ -21,6 +35,22 @@ pub fn server_workers() -> usize {
     4
 }
 
+pub fn server_compress() -> bool {
+    true
+}
+
+pub fn server_reporter_signature_window() -> u64 {
+    300
+}
+
+pub fn server_cors_allowed_methods() -> Vec<String> {
+    vec!["GET".to_string()]
+}
+
+pub fn server_cors_max_age() -> usize {
+    3600
+}
+
 pub fn assets_path() -> PathBuf {
     PathBuf::from("./res/assets/")
 }
@@ -29,6 +59,22 @@ pub fn branding_page_title() -> String {
     "Status Page".to_string()
 }
 
+pub fn branding_badge_color_healthy() -> String {
+    "#0EB033".to_string()
+}
+
+pub fn branding_badge_color_sick() -> String {
+    "#F18000".to_string()
+}
+
+pub fn branding_badge_color_dead() -> String {
+    "#E10000".to_string()
+}
+
+pub fn branding_badge_color_unknown() -> String {
+    "#9F9F9F".to_string()
+}
+
 pub fn metrics_poll_interval() -> u64 {
     120
 }
@@ -37,6 +83,10 @@ pub fn metrics_poll_retry() -> u64 {
     2
 }
 
+pub fn metrics_poll_parallelism() -> usize {
+    32
+}
+
 pub fn metrics_poll_http_status_healthy_above() -> u16 {
     200
 }
@@ -73,6 +123,86 @@ pub fn metrics_local_delay_dead() -> u64 {
     40
 }
 
+pub fn metrics_flap_window() -> u64 {
+    600
+}
+
+pub fn storage_history_retention() -> u64 {
+    90
+}
+
+pub fn incidents_automatic() -> bool {
+    true
+}
+
+pub fn subscriptions_retry_attempts() -> u8 {
+    5
+}
+
+pub fn subscriptions_retry_delay() -> u64 {
+    2
+}
+
+pub fn discovery_kubernetes_api_url() -> SerdeUrl {
+    make_url("https://kubernetes.default.svc")
+}
+
+pub fn discovery_kubernetes_token_path() -> PathBuf {
+    PathBuf::from("/var/run/secrets/kubernetes.io/serviceaccount/token")
+}
+
+pub fn discovery_kubernetes_ca_path() -> PathBuf {
+    PathBuf::from("/var/run/secrets/kubernetes.io/serviceaccount/ca.crt")
+}
+
+pub fn discovery_kubernetes_refresh_interval() -> u64 {
+    30
+}
+
+pub fn discovery_consul_api_url() -> SerdeUrl {
+    make_url("http://127.0.0.1:8500")
+}
+
+pub fn discovery_consul_service_id() -> String {
+    "consul".to_string()
+}
+
+pub fn discovery_consul_service_label() -> String {
+    "Consul".to_string()
+}
+
+pub fn discovery_consul_scheme() -> String {
+    "tcp".to_string()
+}
+
+pub fn discovery_consul_refresh_interval() -> u64 {
+    30
+}
+
+pub fn discovery_docker_socket_path() -> PathBuf {
+    PathBuf::from("/var/run/docker.sock")
+}
+
+pub fn discovery_docker_refresh_interval() -> u64 {
+    10
+}
+
+pub fn tracing_service_name() -> String {
+    "vigil".to_string()
+}
+
+pub fn tracing_sample_ratio() -> f64 {
+    1.0
+}
+
+pub fn tracing_export_interval() -> u64 {
+    5
+}
+
+pub fn tracing_max_queue() -> usize {
+    2048
+}
+
 pub fn notify_startup_notification() -> bool {
     true
 }
@@ -85,6 +215,68 @@ pub fn notify_reminder_backoff_limit() -> u16 {
     3
 }
 
+pub fn notify_digest() -> bool {
+    false
+}
+
+pub fn notify_digest_window() -> u64 {
+    30
+}
+
+pub fn notify_acknowledge_duration() -> u64 {
+    3600
+}
+
+pub fn notify_acknowledge_link_expiry() -> u64 {
+    86400
+}
+
+pub fn notify_rate_limit_window() -> u64 {
+    600
+}
+
+pub fn notify_min_severity() -> Severity {
+    Severity::Info
+}
+
+pub fn notify_notify_on() -> Vec<Status> {
+    vec![Status::Dead, Status::Sick, Status::Healthy]
+}
+
+pub fn notify_queue() -> ConfigNotifyQueue {
+    ConfigNotifyQueue {
+        retry_attempts: notify_queue_retry_attempts(),
+        retry_delay: notify_queue_retry_delay(),
+        retry_delay_maximum: notify_queue_retry_delay_maximum(),
+        path: None,
+        dead_letter_path: None,
+    }
+}
+
+pub fn notify_queue_retry_attempts() -> u32 {
+    8
+}
+
+pub fn notify_queue_retry_delay() -> u64 {
+    5
+}
+
+pub fn notify_queue_retry_delay_maximum() -> u64 {
+    600
+}
+
+pub fn notify_quiet_hours_utc_offset() -> String {
+    "+00:00".to_string()
+}
+
+pub fn notify_quiet_hours_action() -> ConfigNotifyQuietHoursAction {
+    ConfigNotifyQuietHoursAction::Suppress
+}
+
+pub fn notify_email_html() -> bool {
+    true
+}
+
 pub fn notify_email_smtp_host() -> String {
     "localhost".to_string()
 }
@@ -97,10 +289,98 @@ pub fn notify_email_smtp_encrypt() -> bool {
     true
 }
 
+pub fn notify_email_smtp_reuse_timeout() -> u64 {
+    60
+}
+
 pub fn notify_slack_mention_channel() -> bool {
     false
 }
 
+pub fn notify_zulip_topic() -> String {
+    "Vigil status".to_string()
+}
+
+pub fn notify_telegram_threads() -> bool {
+    true
+}
+
+pub fn notify_gotify_priority_reminder() -> u8 {
+    10
+}
+
+pub fn notify_xmpp_room_nick() -> String {
+    "vigil".to_string()
+}
+
+pub fn notify_matrix_html() -> bool {
+    true
+}
+
+pub fn notify_mattermost_mention_channel() -> bool {
+    false
+}
+
+pub fn notify_rocketchat_mention_all() -> bool {
+    false
+}
+
+pub fn notify_sms_method() -> String {
+    "POST".to_string()
+}
+
+pub fn notify_sms_content_type() -> String {
+    "application/json".to_string()
+}
+
+pub fn notify_sms_max_length() -> usize {
+    1000
+}
+
+pub fn notify_mqtt_client_id() -> String {
+    "vigil".to_string()
+}
+
+pub fn notify_mqtt_topic() -> String {
+    "vigil/{group}/{probe}/state".to_string()
+}
+
+pub fn notify_mqtt_qos() -> u8 {
+    0
+}
+
+pub fn notify_mqtt_retain() -> bool {
+    true
+}
+
+pub fn notify_ntfy_server_url() -> SerdeUrl {
+    make_url("https://ntfy.sh")
+}
+
+pub fn notify_opsgenie_api_url() -> SerdeUrl {
+    make_url("https://api.opsgenie.com")
+}
+
+pub fn notify_opsgenie_priority() -> ConfigNotifyOpsgeniePriority {
+    ConfigNotifyOpsgeniePriority::P3
+}
+
 pub fn notify_generic_reminders_only() -> bool {
     false
 }
+
+pub fn probe_service_severity() -> Severity {
+    Severity::Critical
+}
+
+pub fn probe_service_node_heartbeat_grace() -> u64 {
+    300
+}
+
+fn make_url(url: &str) -> SerdeUrl {
+    // Notice: serialized URLs are built through their deserializer, as they wrap another \
+    //   version of the URL type than the one used across Vigil.
+    let deserializer: StrDeserializer<DeserializerError> = url.into_deserializer();
+
+    SerdeUrl::deserialize(deserializer).expect("invalid default url")
+}
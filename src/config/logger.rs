@@ -4,19 +4,61 @@
 // Copyright: 2018, Valerian Saliou <valerian@valeriansaliou.name>
 // License: Mozilla Public License v2.0 (MPL v2.0)
 
+use std::str::FromStr;
+
 use log;
 use log::{Level, LevelFilter, Metadata, Record, SetLoggerError};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use super::config::ConfigServerLogFormat;
+use crate::APP_CONF;
+
+pub static ACCESS_TARGET: &'static str = "vigil::access";
+
+lazy_static! {
+    static ref LOGGER: ConfigLogger = ConfigLogger::from_config();
+}
+
+pub struct ConfigLogger {
+    format: ConfigServerLogFormat,
+    level: LevelFilter,
+    modules: Vec<(String, LevelFilter)>,
+}
 
-pub struct ConfigLogger;
+#[derive(Serialize)]
+struct LogLine<'a> {
+    time: String,
+    level: String,
+    target: &'a str,
+    message: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request: Option<LogRequest<'a>>,
+}
+
+#[derive(Serialize)]
+pub struct LogRequest<'a> {
+    pub method: &'a str,
+    pub path: &'a str,
+    pub status: u16,
+    pub latency_ms: u64,
+    pub peer: Option<String>,
+}
 
 impl log::Log for ConfigLogger {
     fn enabled(&self, metadata: &Metadata) -> bool {
-        metadata.level() <= Level::Debug
+        metadata.level() <= self.level_for(metadata.target())
     }
 
     fn log(&self, record: &Record) {
         if self.enabled(record.metadata()) {
-            println!("({}) - {}", record.level(), record.args());
+            self.write(
+                record.level(),
+                record.target(),
+                record.args().to_string(),
+                None,
+            );
         }
     }
 
@@ -24,8 +66,95 @@ impl log::Log for ConfigLogger {
 }
 
 impl ConfigLogger {
-    pub fn init(level: LevelFilter) -> Result<(), SetLoggerError> {
-        log::set_max_level(level);
-        log::set_boxed_logger(Box::new(ConfigLogger))
+    pub fn init() -> Result<(), SetLoggerError> {
+        // Notice: the max level is the most verbose of all levels, as log macros filter out \
+        //   records above it before the logger gets a chance to check per-module levels.
+        log::set_max_level(
+            LOGGER
+                .modules
+                .iter()
+                .map(|(_, level)| *level)
+                .fold(LOGGER.level, |max_level, level| max_level.max(level)),
+        );
+        log::set_logger(&*LOGGER)
+    }
+
+    pub fn log_request(request: LogRequest) {
+        let level = if request.status >= 500 {
+            Level::Warn
+        } else {
+            Level::Info
+        };
+
+        if level <= LOGGER.level_for(ACCESS_TARGET) {
+            LOGGER.write(
+                level,
+                ACCESS_TARGET,
+                format!(
+                    "{} {} {} {}ms",
+                    request.method, request.path, request.status, request.latency_ms
+                ),
+                Some(request),
+            );
+        }
+    }
+
+    fn from_config() -> Self {
+        let mut modules: Vec<(String, LevelFilter)> = APP_CONF
+            .server
+            .log_modules
+            .iter()
+            .filter_map(|(module, level)| {
+                LevelFilter::from_str(level)
+                    .ok()
+                    .map(|level| (module.to_owned(), level))
+            })
+            .collect();
+
+        // Most specific modules come first, so that they take precedence over their parents
+        modules.sort_by(|(module, _), (other_module, _)| other_module.len().cmp(&module.len()));
+
+        ConfigLogger {
+            format: APP_CONF.server.log_format,
+            level: LevelFilter::from_str(&APP_CONF.server.log_level).expect("invalid log level"),
+            modules: modules,
+        }
+    }
+
+    fn level_for(&self, target: &str) -> LevelFilter {
+        self.modules
+            .iter()
+            .find(|(module, _)| {
+                target == module
+                    || (target.starts_with(module.as_str()) == true
+                        && target[module.len()..].starts_with("::") == true)
+            })
+            .map(|(_, level)| *level)
+            .unwrap_or(self.level)
+    }
+
+    fn write(&self, level: Level, target: &str, message: String, request: Option<LogRequest>) {
+        let time = OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .unwrap_or("?".to_string());
+
+        match self.format {
+            ConfigServerLogFormat::Pretty => {
+                println!("{} ({}) {} - {}", time, level, target, message);
+            }
+            ConfigServerLogFormat::JSON => {
+                let line = LogLine {
+                    time: time,
+                    level: level.to_string(),
+                    target: target,
+                    message: message,
+                    request: request,
+                };
+
+                if let Ok(line) = serde_json::to_string(&line) {
+                    println!("{}", line);
+                }
+            }
+        }
     }
 }
@@ -6,7 +6,10 @@
 
 mod defaults;
 
+pub mod codes;
 pub mod config;
 pub mod logger;
 pub mod reader;
 pub mod regex;
+pub mod schedule;
+pub mod shared;
@@ -5,53 +5,539 @@
 // License: Mozilla Public License v2.0 (MPL v2.0)
 
 use std::collections::hash_set::HashSet;
-use std::fs::File;
+use std::env;
+use std::fmt;
+use std::fs::{self, File};
 use std::io::Read;
+use std::iter;
+use std::path::Path;
+use std::str::FromStr;
 
-use toml;
+use http::header::{HeaderName, HeaderValue};
+use http::Method;
+use log::LevelFilter;
+use regex::Regex;
+use tera::Tera;
+use toml::{self, Value};
+use url::Url;
+use url_serde::SerdeUrl;
 
 use super::config::*;
+use crate::notifier::generic::DEFAULT_CHANNEL;
+use crate::notifier::quiet;
+use crate::prober::acknowledge::ACKNOWLEDGE_DURATION_MAXIMUM_SECONDS;
+use crate::prober::dns;
+use crate::prober::mode::Mode;
+use crate::prober::replica::ReplicaURL;
+use crate::prober::tls;
+use crate::storage::uptime::WINDOW_DAYS_MAXIMUM as UPTIME_WINDOW_DAYS_MAXIMUM;
 use crate::APP_ARGS;
 
+lazy_static! {
+    static ref ENVIRONMENT_PLACEHOLDER_REGEX: Regex =
+        Regex::new(r"\$(\$?)\{([A-Za-z_][A-Za-z0-9_]*)\}").unwrap();
+}
+
 pub struct ConfigReader;
 
+#[derive(Serialize)]
+pub struct ConfigError {
+    pub path: String,
+    pub message: String,
+}
+
+impl ConfigError {
+    fn new(path: &str, message: String) -> ConfigError {
+        ConfigError {
+            path: path.to_owned(),
+            message: message,
+        }
+    }
+
+    pub fn describe_all(errors: &[ConfigError]) -> String {
+        errors
+            .iter()
+            .map(|error| error.to_string())
+            .collect::<Vec<String>>()
+            .join("; ")
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.is_empty() == true {
+            write!(formatter, "{}", self.message)
+        } else {
+            write!(formatter, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
 impl ConfigReader {
     pub fn make() -> Config {
+        Self::read().unwrap_or_else(|errors| {
+            panic!(
+                "invalid configuration: {}",
+                ConfigError::describe_all(&errors)
+            )
+        })
+    }
+
+    pub fn read() -> Result<Config, Vec<ConfigError>> {
         debug!("reading config file: {}", &APP_ARGS.config);
 
-        let mut file = File::open(&APP_ARGS.config).expect("cannot find config file");
+        let mut file = File::open(&APP_ARGS.config).map_err(|err| {
+            vec![ConfigError::new(
+                "",
+                format!("cannot find config file: {}", err),
+            )]
+        })?;
         let mut conf = String::new();
 
-        file.read_to_string(&mut conf)
-            .expect("cannot read config file");
+        file.read_to_string(&mut conf).map_err(|err| {
+            vec![ConfigError::new(
+                "",
+                format!("cannot read config file: {}", err),
+            )]
+        })?;
 
         debug!("read config file: {}", &APP_ARGS.config);
 
         // Parse configuration
-        let config = toml::from_str(&conf).expect("syntax error in config file");
+        let mut value: Value = toml::from_str(&conf).map_err(|err| {
+            vec![ConfigError::new(
+                "",
+                format!("syntax error in config file: {}", err),
+            )]
+        })?;
+
+        // Interpolate environment variables (before values get interpreted)
+        let mut errors = Vec::new();
+
+        Self::interpolate("", &mut value, &mut errors);
+
+        // Include services defined in the probes directory (if any)
+        Self::include_probes(&mut value, &mut errors);
+
+        if errors.is_empty() == false {
+            return Err(errors);
+        }
+
+        let config: Config = value.try_into().map_err(|err| {
+            vec![ConfigError::new(
+                "",
+                format!("invalid config file: {}", err),
+            )]
+        })?;
 
         // Validate configuration
-        Self::validate(&config);
+        Self::validate(&config, &mut errors);
+
+        if errors.is_empty() == false {
+            return Err(errors);
+        }
+
+        Ok(config)
+    }
 
-        config
+    fn include_probes(value: &mut Value, errors: &mut Vec<ConfigError>) {
+        let probes_dir = match value
+            .get("probe")
+            .and_then(|probe| probe.get("probes_dir"))
+            .and_then(|probes_dir| probes_dir.as_str())
+        {
+            Some(probes_dir) => probes_dir.to_owned(),
+            None => return,
+        };
+
+        debug!("reading probes directory: {}", probes_dir);
+
+        // List service files (sorted by name, so that services are listed in a stable order)
+        let mut probe_paths: Vec<_> = match fs::read_dir(&probes_dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| {
+                    path.is_file() == true
+                        && path.extension().and_then(|extension| extension.to_str()) == Some("toml")
+                })
+                .collect(),
+            Err(err) => {
+                errors.push(ConfigError::new(
+                    "probe.probes_dir",
+                    format!("cannot read probes directory: {}", err),
+                ));
+
+                return;
+            }
+        };
+
+        probe_paths.sort();
+
+        let mut services = Vec::new();
+
+        for probe_path in probe_paths.iter() {
+            if let Some(service) = Self::read_probe(probe_path, errors) {
+                services.push(service);
+            }
+        }
+
+        debug!(
+            "read {} services from probes directory: {}",
+            services.len(),
+            probes_dir
+        );
+
+        // Append included services to services defined in the configuration file
+        if let Some(Value::Table(probe)) = value.get_mut("probe") {
+            match probe.get_mut("service") {
+                Some(Value::Array(probe_services)) => probe_services.extend(services),
+                _ => {
+                    probe.insert("service".to_string(), Value::Array(services));
+                }
+            }
+        }
     }
 
-    fn validate(config: &Config) {
+    fn read_probe(probe_path: &Path, errors: &mut Vec<ConfigError>) -> Option<Value> {
+        let probe_path_string = probe_path.to_string_lossy();
+
+        let conf = match fs::read_to_string(probe_path) {
+            Ok(conf) => conf,
+            Err(err) => {
+                errors.push(ConfigError::new(
+                    &probe_path_string,
+                    format!("cannot read probe file: {}", err),
+                ));
+
+                return None;
+            }
+        };
+
+        match toml::from_str::<Value>(&conf) {
+            Ok(mut service) => {
+                // Notice: included files are not part of the main configuration value tree, \
+                //   thus environment variables are interpolated separately.
+                Self::interpolate(&probe_path_string, &mut service, errors);
+
+                Some(service)
+            }
+            Err(err) => {
+                errors.push(ConfigError::new(
+                    &probe_path_string,
+                    format!("syntax error in probe file: {}", err),
+                ));
+
+                None
+            }
+        }
+    }
+
+    fn interpolate(path: &str, value: &mut Value, errors: &mut Vec<ConfigError>) {
+        match *value {
+            Value::String(ref mut text) => {
+                if text.contains("${") == true {
+                    match Self::interpolate_text(text) {
+                        Ok(interpolated) => *text = interpolated,
+                        Err(variable) => errors.push(ConfigError::new(
+                            path,
+                            format!("undefined environment variable: {}", variable),
+                        )),
+                    }
+                }
+            }
+            Value::Array(ref mut values) => {
+                for (index, value) in values.iter_mut().enumerate() {
+                    Self::interpolate(&format!("{}[{}]", path, index), value, errors);
+                }
+            }
+            Value::Table(ref mut table) => {
+                for (key, value) in table.iter_mut() {
+                    let key_path = if path.is_empty() == true {
+                        key.to_owned()
+                    } else {
+                        format!("{}.{}", path, key)
+                    };
+
+                    Self::interpolate(&key_path, value, errors);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn interpolate_text(text: &str) -> Result<String, String> {
+        let mut interpolated = String::new();
+        let mut last_end = 0;
+
+        for captures in ENVIRONMENT_PLACEHOLDER_REGEX.captures_iter(text) {
+            let placeholder = captures.get(0).unwrap();
+
+            interpolated.push_str(&text[last_end..placeholder.start()]);
+
+            if &captures[1] == "$" {
+                // Escaped placeholder (eg. '$${VAR}' stands for a literal '${VAR}')
+                interpolated.push_str(&placeholder.as_str()[1..]);
+            } else {
+                let variable = env::var(&captures[2]).map_err(|_| captures[2].to_owned())?;
+
+                interpolated.push_str(&variable);
+            }
+
+            last_end = placeholder.end();
+        }
+
+        interpolated.push_str(&text[last_end..]);
+
+        Ok(interpolated)
+    }
+
+    fn validate(config: &Config, errors: &mut Vec<ConfigError>) {
+        // Validate assets (templates must be loadable)
+        Self::validate_assets(config, errors);
+
+        // Validate server
+        Self::validate_server(config, errors);
+
+        // Validate metrics
+        Self::validate_metrics(config, errors);
+
+        // Validate storage
+        Self::validate_storage(config, errors);
+
         // Validate all identifiers
-        Self::validate_identifiers(config)
+        Self::validate_identifiers(config, errors);
+
+        // Validate all nodes
+        Self::validate_nodes(config, errors);
+
+        // Validate all notify channels
+        Self::validate_notify_channels(config, errors);
+
+        // Validate all notifiers
+        Self::validate_notifiers(config, errors);
+
+        // Validate subscriptions
+        Self::validate_subscriptions(config, errors);
+
+        // Validate discovery
+        Self::validate_discovery(config, errors);
+
+        // Validate tracing
+        Self::validate_tracing(config, errors);
     }
 
-    fn validate_identifiers(config: &Config) {
+    fn validate_assets(config: &Config, errors: &mut Vec<ConfigError>) {
+        let templates_path = config.assets.path.join("templates");
+
+        if templates_path.is_dir() == false {
+            errors.push(ConfigError::new(
+                "assets.path",
+                format!("templates directory not found: {:?}", templates_path),
+            ));
+
+            return;
+        }
+
+        match Tera::new(&templates_path.join("*").to_string_lossy()) {
+            Ok(tera) => {
+                if tera
+                    .get_template_names()
+                    .any(|template_name| template_name == "index.tera")
+                    == false
+                {
+                    errors.push(ConfigError::new(
+                        "assets.path",
+                        "missing template: index.tera".to_string(),
+                    ));
+                }
+
+                // Acknowledgment links are only sent if the manager token is set
+                if config.server.manager_token.is_some() == true
+                    && tera
+                        .get_template_names()
+                        .any(|template_name| template_name == "acknowledge.tera")
+                        == false
+                {
+                    errors.push(ConfigError::new(
+                        "assets.path",
+                        "missing template: acknowledge.tera".to_string(),
+                    ));
+                }
+            }
+            Err(err) => errors.push(ConfigError::new(
+                "assets.path",
+                format!("invalid templates: {}", err),
+            )),
+        }
+    }
+
+    fn validate_server(config: &Config, errors: &mut Vec<ConfigError>) {
+        let log_levels = iter::once(("server.log_level".to_string(), &config.server.log_level))
+            .chain(
+                config
+                    .server
+                    .log_modules
+                    .iter()
+                    .map(|(module, level)| (format!("server.log_modules.{}", module), level)),
+            );
+
+        for (path, level) in log_levels {
+            if LevelFilter::from_str(level).is_err() == true {
+                errors.push(ConfigError::new(
+                    &path,
+                    format!("invalid log level: {}", level),
+                ));
+            }
+        }
+
+        if config.server.base_path.is_empty() == false
+            && config.server.base_path.starts_with('/') == false
+        {
+            errors.push(ConfigError::new(
+                "server.base_path",
+                format!("base path must start with '/': {}", config.server.base_path),
+            ));
+        }
+
+        if config.server.reporter_secret.as_deref() == Some("") {
+            errors.push(ConfigError::new(
+                "server.reporter_secret",
+                "secret must not be empty".to_string(),
+            ));
+        }
+
+        if config.server.reporter_signature_window == 0 {
+            errors.push(ConfigError::new(
+                "server.reporter_signature_window",
+                "window must be greater than 0".to_string(),
+            ));
+        }
+
+        if let Some(ref cors) = config.server.cors {
+            // Notice: origins must match the 'Origin' header sent by browsers exactly (ie. \
+            //   scheme, host and port only, with no trailing slash).
+            for (index, origin) in cors.allowed_origins.iter().enumerate() {
+                let is_valid = origin == "*"
+                    || Url::parse(origin)
+                        .map(|url| url.origin().ascii_serialization() == *origin)
+                        .unwrap_or(false);
+
+                if is_valid == false {
+                    errors.push(ConfigError::new(
+                        &format!("server.cors.allowed_origins[{}]", index),
+                        format!("invalid origin: {}", origin),
+                    ));
+                }
+            }
+
+            for (index, method) in cors.allowed_methods.iter().enumerate() {
+                if Method::from_bytes(method.as_bytes()).is_err() == true {
+                    errors.push(ConfigError::new(
+                        &format!("server.cors.allowed_methods[{}]", index),
+                        format!("invalid method: {}", method),
+                    ));
+                }
+            }
+
+            for (index, header) in cors.allowed_headers.iter().enumerate() {
+                if HeaderName::from_bytes(header.as_bytes()).is_err() == true {
+                    errors.push(ConfigError::new(
+                        &format!("server.cors.allowed_headers[{}]", index),
+                        format!("invalid header: {}", header),
+                    ));
+                }
+            }
+        }
+
+        if let Some(ref rate_limit) = config.server.rate_limit {
+            for (name, bucket) in [
+                ("public", rate_limit.public.as_ref()),
+                ("reporter", rate_limit.reporter.as_ref()),
+            ] {
+                if let Some(bucket) = bucket {
+                    if bucket.rate <= 0.0 || bucket.burst == 0 {
+                        errors.push(ConfigError::new(
+                            &format!("server.rate_limit.{}", name),
+                            "rate and burst must be greater than 0".to_string(),
+                        ));
+                    }
+                }
+            }
+        }
+
+        if let Some(ref tls) = config.server.tls {
+            if tls.certificate_path.is_file() == false {
+                errors.push(ConfigError::new(
+                    "server.tls.certificate_path",
+                    format!("certificate file not found: {:?}", tls.certificate_path),
+                ));
+            }
+
+            if tls.key_path.is_file() == false {
+                errors.push(ConfigError::new(
+                    "server.tls.key_path",
+                    format!("key file not found: {:?}", tls.key_path),
+                ));
+            }
+
+            if tls.reload_interval == Some(0) {
+                errors.push(ConfigError::new(
+                    "server.tls.reload_interval",
+                    "reload interval cannot be 0".to_string(),
+                ));
+            }
+        }
+    }
+
+    fn validate_metrics(config: &Config, errors: &mut Vec<ConfigError>) {
+        if config.metrics.poll_parallelism == 0 {
+            errors.push(ConfigError::new(
+                "metrics.poll_parallelism",
+                "poll parallelism must be at least 1".to_string(),
+            ));
+        }
+
+        if config.metrics.flap_threshold == Some(0) {
+            errors.push(ConfigError::new(
+                "metrics.flap_threshold",
+                "flap threshold must be at least 1".to_string(),
+            ));
+        }
+
+        if let Some(ref poll_proxy) = config.metrics.poll_proxy {
+            Self::validate_proxy_url("metrics.poll_proxy", poll_proxy, errors);
+        }
+    }
+
+    fn validate_storage(config: &Config, errors: &mut Vec<ConfigError>) {
+        // Notice: uptime windows are computed from the transitions history, which must be \
+        //   retained for at least as long as the largest uptime window.
+        if let Some(ref storage) = config.storage {
+            if storage.history_retention < UPTIME_WINDOW_DAYS_MAXIMUM {
+                errors.push(ConfigError::new(
+                    "storage.history_retention",
+                    format!(
+                        "history retention must be at least {} days, got: {}",
+                        UPTIME_WINDOW_DAYS_MAXIMUM, storage.history_retention
+                    ),
+                ));
+            }
+        }
+    }
+
+    fn validate_identifiers(config: &Config, errors: &mut Vec<ConfigError>) {
         // Scan for service identifier duplicates
         let mut service_identifiers = HashSet::new();
 
         for service in config.probe.service.iter() {
+            let service_path = format!("probe.service[{}]", service.id);
+
             // Service identifier was already previously inserted? (caught a duplicate)
             if service_identifiers.insert(&service.id) == false {
-                panic!(
-                    "configuration has duplicate service identifier: {}",
-                    service.id
-                )
+                errors.push(ConfigError::new(
+                    &service_path,
+                    "duplicate service identifier".to_string(),
+                ));
             }
 
             // Scan for node identifier duplicates
@@ -60,12 +546,1159 @@ impl ConfigReader {
             for node in service.node.iter() {
                 // Node identifier was already previously inserted? (caught a duplicate)
                 if node_identifiers.insert(&node.id) == false {
-                    panic!(
-                        "configuration has duplicate node identifier: {} in service: {}",
-                        node.id, service.id
+                    errors.push(ConfigError::new(
+                        &format!("{}.node[{}]", service_path, node.id),
+                        "duplicate node identifier".to_string(),
+                    ));
+                }
+            }
+        }
+
+        // Scan for dependencies on unknown services
+        for service in config.probe.service.iter() {
+            for dependency in service.depends_on.iter() {
+                if dependency == &service.id {
+                    errors.push(ConfigError::new(
+                        &format!("probe.service[{}].depends_on", service.id),
+                        "service cannot depend on itself".to_string(),
+                    ));
+                } else if service_identifiers.contains(dependency) == false {
+                    errors.push(ConfigError::new(
+                        &format!("probe.service[{}].depends_on", service.id),
+                        format!("unknown service identifier: {}", dependency),
+                    ));
+                }
+            }
+        }
+    }
+
+    fn validate_nodes(config: &Config, errors: &mut Vec<ConfigError>) {
+        // Notice: this is checked here rather than when filling the prober store, so that an \
+        //   invalid configuration gets rejected upon reload instead of crashing.
+        for service in config.probe.service.iter() {
+            if service.reporter_secret.as_deref() == Some("") {
+                errors.push(ConfigError::new(
+                    &format!("probe.service[{}].reporter_secret", service.id),
+                    "secret must not be empty".to_string(),
+                ));
+            }
+
+            if let (Some(latency_sick_ms), Some(latency_dead_ms)) =
+                (service.latency_sick_ms, service.latency_dead_ms)
+            {
+                if latency_sick_ms >= latency_dead_ms {
+                    errors.push(ConfigError::new(
+                        &format!("probe.service[{}].latency_sick_ms", service.id),
+                        "sick latency must be lower than dead latency".to_string(),
+                    ));
+                }
+            }
+
+            if let Some(ref dns_resolver) = service.dns_resolver {
+                if dns::parse_nameserver(dns_resolver).is_none() == true {
+                    errors.push(ConfigError::new(
+                        &format!("probe.service[{}].dns_resolver", service.id),
+                        format!("invalid resolver address: {}", dns_resolver),
+                    ));
+                }
+            }
+
+            for node in service.node.iter() {
+                let node_path = format!("probe.service[{}].node[{}]", service.id, node.id);
+
+                if let Some(ref replicas) = node.replicas {
+                    if node.mode != Mode::Poll {
+                        errors.push(ConfigError::new(
+                            &node_path,
+                            "non-poll node cannot have replicas".to_string(),
+                        ));
+                    }
+
+                    for replica in replicas.iter() {
+                        match ReplicaURL::parse_from(replica) {
+                            Ok(ReplicaURL::PromQL(_)) | Ok(ReplicaURL::PromQLS(_))
+                                if node.promql_query.is_none() == true =>
+                            {
+                                errors.push(ConfigError::new(
+                                    &format!("{}.promql_query", node_path),
+                                    "promql replicas need a query".to_string(),
+                                ));
+                            }
+                            Ok(ReplicaURL::SNMP(_, _, Some(_), _))
+                                if node.database_password.is_none() == true =>
+                            {
+                                errors.push(ConfigError::new(
+                                    &format!("{}.database_password", node_path),
+                                    "snmpv3 replicas need a password".to_string(),
+                                ));
+                            }
+                            Ok(_) => {}
+                            Err(_) => {
+                                errors.push(ConfigError::new(
+                                    &format!("{}.replicas", node_path),
+                                    format!("invalid replica url: {}", replica),
+                                ));
+                            }
+                        }
+                    }
+                }
+
+                if (node.http_redirect_maximum.is_some() == true
+                    || node.http_redirect_final_url_match.is_some() == true)
+                    && matches!(
+                        node.http_redirect_policy,
+                        Some(ConfigProbeServiceNodeHTTPRedirectPolicy::Follow)
+                    ) == false
+                {
+                    errors.push(ConfigError::new(
+                        &format!("{}.http_redirect_policy", node_path),
+                        "redirect options need the follow redirect policy".to_string(),
+                    ));
+                }
+
+                if node.icmp_count == Some(0) {
+                    errors.push(ConfigError::new(
+                        &format!("{}.icmp_count", node_path),
+                        "icmp count must be greater than zero".to_string(),
+                    ));
+                }
+
+                for (name, loss) in [
+                    ("icmp_loss_sick_above", node.icmp_loss_sick_above),
+                    ("icmp_loss_dead_above", node.icmp_loss_dead_above),
+                ] {
+                    if let Some(loss) = loss {
+                        if loss < 0.0 || loss > 1.0 {
+                            errors.push(ConfigError::new(
+                                &format!("{}.{}", node_path, name),
+                                format!("loss ratio must be between 0.0 and 1.0, got: {}", loss),
+                            ));
+                        }
+                    }
+                }
+
+                // Notice: the dead loss threshold defaults to a full loss when it is not set
+                if let Some(icmp_loss_sick_above) = node.icmp_loss_sick_above {
+                    if icmp_loss_sick_above > node.icmp_loss_dead_above.unwrap_or(1.0) {
+                        errors.push(ConfigError::new(
+                            &format!("{}.icmp_loss_sick_above", node_path),
+                            "sick loss ratio must not be above dead loss ratio".to_string(),
+                        ));
+                    }
+                }
+
+                if let (Some(icmp_rtt_sick_above), Some(icmp_rtt_dead_above)) =
+                    (node.icmp_rtt_sick_above, node.icmp_rtt_dead_above)
+                {
+                    if icmp_rtt_sick_above > icmp_rtt_dead_above {
+                        errors.push(ConfigError::new(
+                            &format!("{}.icmp_rtt_sick_above", node_path),
+                            "sick round-trip time must not be above dead round-trip time"
+                                .to_string(),
+                        ));
+                    }
+                }
+
+                if let Some(ref http_proxy) = node.http_proxy {
+                    Self::validate_proxy_url(
+                        &format!("{}.http_proxy", node_path),
+                        http_proxy,
+                        errors,
+                    );
+                }
+
+                match (&node.tls_client_certificate, &node.tls_client_key) {
+                    // Notice: certificates stored in files are not checked there, as they \
+                    //   might get provisioned after startup (eg. by a certificate manager).
+                    (Some(certificate), Some(key))
+                        if tls::is_inline_pem(certificate) == true
+                            && tls::is_inline_pem(key) == true =>
+                    {
+                        if let Err(err) = tls::make_client_identity(certificate, key) {
+                            errors.push(ConfigError::new(
+                                &format!("{}.tls_client_certificate", node_path),
+                                format!("invalid client certificate: {}", err),
+                            ));
+                        }
+                    }
+                    (Some(_), None) | (None, Some(_)) => {
+                        errors.push(ConfigError::new(
+                            &format!("{}.tls_client_key", node_path),
+                            "client certificate and key must be set together".to_string(),
+                        ));
+                    }
+                    _ => {}
+                }
+
+                if node.grpc_authority.as_deref() == Some("") {
+                    errors.push(ConfigError::new(
+                        &format!("{}.grpc_authority", node_path),
+                        "authority must not be empty".to_string(),
+                    ));
+                }
+
+                if node.websocket_pong_timeout == Some(0) {
+                    errors.push(ConfigError::new(
+                        &format!("{}.websocket_pong_timeout", node_path),
+                        "pong timeout must be greater than 0".to_string(),
+                    ));
+                }
+
+                if let Some(ref fingerprint) = node.ssh_host_key_fingerprint {
+                    // Notice: fingerprints are SHA-256 hashes, as printed by 'ssh-keygen -l'
+                    let fingerprint = fingerprint
+                        .trim()
+                        .trim_start_matches("SHA256:")
+                        .trim_end_matches('=');
+
+                    if base64::decode_config(fingerprint, base64::STANDARD_NO_PAD)
+                        .map(|hash| hash.len() == 32)
+                        .unwrap_or(false)
+                        == false
+                    {
+                        errors.push(ConfigError::new(
+                            &format!("{}.ssh_host_key_fingerprint", node_path),
+                            "fingerprint must be a sha256 fingerprint".to_string(),
+                        ));
+                    }
+                }
+
+                if let Some(stratum) = node.ntp_stratum_sick_above {
+                    if stratum < 1 || stratum > 15 {
+                        errors.push(ConfigError::new(
+                            &format!("{}.ntp_stratum_sick_above", node_path),
+                            "stratum must be between 1 and 15".to_string(),
+                        ));
+                    }
+                }
+
+                if (node.diagnostic_command.is_some() == true || node.diagnostic_tcp_trace == true)
+                    && node.mode != Mode::Poll
+                {
+                    errors.push(ConfigError::new(
+                        &node_path,
+                        "non-poll node cannot have diagnostics".to_string(),
+                    ));
+                }
+
+                if let Some(ref command) = node.diagnostic_command {
+                    if command.trim().is_empty() == true {
+                        errors.push(ConfigError::new(
+                            &format!("{}.diagnostic_command", node_path),
+                            "diagnostic command must not be empty".to_string(),
+                        ));
+                    }
+                }
+
+                match (&node.mode, &node.steps) {
+                    (Mode::Transaction, None) => errors.push(ConfigError::new(
+                        &node_path,
+                        "transaction node must have steps".to_string(),
+                    )),
+                    (Mode::Transaction, Some(steps)) if steps.is_empty() == true => {
+                        errors.push(ConfigError::new(
+                            &format!("{}.steps", node_path),
+                            "steps must not be empty".to_string(),
+                        ))
+                    }
+                    (Mode::Transaction, _) | (_, None) => {}
+                    (_, Some(_)) => errors.push(ConfigError::new(
+                        &node_path,
+                        "non-transaction node cannot have steps".to_string(),
+                    )),
+                }
+
+                if node.scripts.is_some() == true && node.mode != Mode::Script {
+                    errors.push(ConfigError::new(
+                        &node_path,
+                        "non-script node cannot have scripts".to_string(),
+                    ));
+                }
+
+                for script in node.scripts.iter().flatten() {
+                    if let ConfigProbeServiceNodeScript::Options(options) = script {
+                        if options.timeout == Some(0) {
+                            errors.push(ConfigError::new(
+                                &format!("{}.scripts", node_path),
+                                "script timeout must be at least 1 second".to_string(),
+                            ));
+                        }
+                    }
+                }
+
+                match (
+                    &node.mode,
+                    node.heartbeat_interval,
+                    node.heartbeat_schedule.is_some(),
+                ) {
+                    (Mode::Heartbeat, None, false) | (Mode::Heartbeat, Some(_), true) => errors
+                        .push(ConfigError::new(
+                            &node_path,
+                            "heartbeat node must have either a heartbeat interval or schedule"
+                                .to_string(),
+                        )),
+                    (Mode::Heartbeat, Some(0), false) => errors.push(ConfigError::new(
+                        &format!("{}.heartbeat_interval", node_path),
+                        "heartbeat interval must be greater than 0".to_string(),
+                    )),
+                    (Mode::Heartbeat, _, _) | (_, None, false) => {}
+                    (_, _, _) => errors.push(ConfigError::new(
+                        &node_path,
+                        "non-heartbeat node cannot have a heartbeat interval or schedule"
+                            .to_string(),
+                    )),
+                }
+            }
+        }
+    }
+
+    fn validate_notify_channels(config: &Config, errors: &mut Vec<ConfigError>) {
+        let channels = config.notify.as_ref().map(|notify| &notify.channels);
+
+        // Scan for invalid channel definitions
+        if let Some(channels) = channels {
+            for (channel_name, channel) in channels.iter() {
+                let channel_path = format!("notify.channels.{}", channel_name);
+
+                if channel_name == DEFAULT_CHANNEL {
+                    errors.push(ConfigError::new(
+                        &channel_path,
+                        "reserved notify channel name".to_string(),
+                    ));
+                }
+
+                if channel.channels.is_empty() == false {
+                    errors.push(ConfigError::new(
+                        &channel_path,
+                        "nested channels are not allowed".to_string(),
+                    ));
+                }
+
+                if channel.escalations.is_empty() == false {
+                    errors.push(ConfigError::new(
+                        &channel_path,
+                        "escalation policies can only be defined in notify".to_string(),
+                    ));
+                }
+            }
+        }
+
+        let is_known_channel = |channel_name: &str| {
+            channel_name == DEFAULT_CHANNEL
+                || channels
+                    .map(|channels| channels.contains_key(channel_name))
+                    .unwrap_or(false)
+        };
+
+        // Scan for invalid escalation policies
+        let escalations = config.notify.as_ref().map(|notify| &notify.escalations);
+
+        if let Some(escalations) = escalations {
+            for (policy_name, policy) in escalations.iter() {
+                let policy_path = format!("notify.escalations.{}", policy_name);
+
+                if policy.steps.is_empty() == true {
+                    errors.push(ConfigError::new(
+                        &format!("{}.steps", policy_path),
+                        "at least one step is required".to_string(),
+                    ));
+                }
+
+                for (index, step) in policy.steps.iter().enumerate() {
+                    let step_path = format!("{}.steps[{}]", policy_path, index);
+
+                    if step.notify.is_empty() == true {
+                        errors.push(ConfigError::new(
+                            &format!("{}.notify", step_path),
+                            "at least one channel is required".to_string(),
+                        ));
+                    }
+
+                    for step_channel in step.notify.iter() {
+                        if is_known_channel(step_channel) == false {
+                            errors.push(ConfigError::new(
+                                &format!("{}.notify", step_path),
+                                format!("unknown notify channel: {}", step_channel),
+                            ));
+                        }
+                    }
+
+                    // Notice: steps are fired in order, thus a step cannot come before the \
+                    //   step that precedes it.
+                    if index > 0 && step.after < policy.steps[index - 1].after {
+                        errors.push(ConfigError::new(
+                            &format!("{}.after", step_path),
+                            "steps must be ordered by ascending delay".to_string(),
+                        ));
+                    }
+                }
+            }
+        }
+
+        // Scan for routes to unknown channels
+        for service in config.probe.service.iter() {
+            if let Some(ref service_channels) = service.notify {
+                for service_channel in service_channels.iter() {
+                    if is_known_channel(service_channel) == false {
+                        errors.push(ConfigError::new(
+                            &format!("probe.service[{}].notify", service.id),
+                            format!("unknown notify channel: {}", service_channel),
+                        ));
+                    }
+                }
+            }
+
+            if let Some(ref service_escalation) = service.escalation {
+                let is_known = escalations
+                    .map(|escalations| escalations.contains_key(service_escalation))
+                    .unwrap_or(false);
+
+                if is_known == false {
+                    errors.push(ConfigError::new(
+                        &format!("probe.service[{}].escalation", service.id),
+                        format!("unknown escalation policy: {}", service_escalation),
+                    ));
+                }
+            }
+        }
+    }
+
+    fn validate_notifiers(config: &Config, errors: &mut Vec<ConfigError>) {
+        if let Some(ref notify) = config.notify {
+            Self::validate_notifiers_channel("notify", notify, errors);
+
+            for (channel_name, channel) in notify.channels.iter() {
+                Self::validate_notifiers_channel(
+                    &format!("notify.channels.{}", channel_name),
+                    channel,
+                    errors,
+                );
+            }
+
+            // Quiet hours can only reroute notifications to another existing channel
+            let channels = iter::once(("notify".to_string(), DEFAULT_CHANNEL, notify)).chain(
+                notify.channels.iter().map(|(channel_name, channel)| {
+                    (
+                        format!("notify.channels.{}", channel_name),
+                        channel_name.as_str(),
+                        channel,
                     )
+                }),
+            );
+
+            for (path, channel_name, channel) in channels {
+                for (index, quiet_hours) in channel.quiet_hours.iter().enumerate() {
+                    if let Some(ref reroute_to) = quiet_hours.reroute_to {
+                        if reroute_to == channel_name {
+                            errors.push(ConfigError::new(
+                                &format!("{}.quiet_hours[{}].reroute_to", path, index),
+                                "cannot reroute to the same channel".to_string(),
+                            ));
+                        } else if reroute_to != DEFAULT_CHANNEL
+                            && notify.channels.contains_key(reroute_to) == false
+                        {
+                            errors.push(ConfigError::new(
+                                &format!("{}.quiet_hours[{}].reroute_to", path, index),
+                                format!("unknown channel: {}", reroute_to),
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn validate_notifiers_channel(
+        path: &str,
+        notify: &ConfigNotify,
+        errors: &mut Vec<ConfigError>,
+    ) {
+        if notify.queue.retry_delay == 0 {
+            errors.push(ConfigError::new(
+                &format!("{}.queue.retry_delay", path),
+                "retry delay must be greater than zero".to_string(),
+            ));
+        }
+
+        if notify.queue.retry_delay_maximum < notify.queue.retry_delay {
+            errors.push(ConfigError::new(
+                &format!("{}.queue.retry_delay_maximum", path),
+                "maximum retry delay must not be lower than retry delay".to_string(),
+            ));
+        }
+
+        if notify.reminder_interval == Some(0) {
+            errors.push(ConfigError::new(
+                &format!("{}.reminder_interval", path),
+                "reminder interval must be greater than zero".to_string(),
+            ));
+        }
+
+        if notify.acknowledge_duration == 0
+            || notify.acknowledge_duration > ACKNOWLEDGE_DURATION_MAXIMUM_SECONDS
+        {
+            errors.push(ConfigError::new(
+                &format!("{}.acknowledge_duration", path),
+                format!(
+                    "acknowledge duration must be between 1 and {} seconds",
+                    ACKNOWLEDGE_DURATION_MAXIMUM_SECONDS
+                ),
+            ));
+        }
+
+        if notify.notify_on.is_empty() == true {
+            errors.push(ConfigError::new(
+                &format!("{}.notify_on", path),
+                "no status to notify on".to_string(),
+            ));
+        }
+
+        if notify.digest == true && notify.digest_window == 0 {
+            errors.push(ConfigError::new(
+                &format!("{}.digest_window", path),
+                "digest window must be greater than zero".to_string(),
+            ));
+        }
+
+        for (key, limit, window) in [
+            ("rate_limit", notify.rate_limit, notify.rate_limit_window),
+            (
+                "global_rate_limit",
+                notify.global_rate_limit,
+                notify.global_rate_limit_window,
+            ),
+        ] {
+            if limit == Some(0) {
+                errors.push(ConfigError::new(
+                    &format!("{}.{}", path, key),
+                    "rate limit must be greater than zero".to_string(),
+                ));
+            }
+
+            if limit.is_some() == true && window == 0 {
+                errors.push(ConfigError::new(
+                    &format!("{}.{}_window", path, key),
+                    "rate limit window must be greater than zero".to_string(),
+                ));
+            }
+        }
+
+        if let Some(ref email) = notify.email {
+            let addresses = [("to", &email.to), ("from", &email.from)]
+                .into_iter()
+                .map(|(key, address)| (key.to_string(), address))
+                .chain(
+                    email
+                        .reply_to
+                        .iter()
+                        .map(|address| ("reply_to".to_string(), address)),
+                )
+                .chain(email.to_groups.iter().flat_map(|(group, addresses)| {
+                    addresses
+                        .iter()
+                        .map(move |address| (format!("to_groups.{}", group), address))
+                }));
+
+            for (key, address) in addresses {
+                if address.contains('@') == false {
+                    errors.push(ConfigError::new(
+                        &format!("{}.email.{}", path, key),
+                        format!("invalid email address: {}", address),
+                    ));
+                }
+            }
+
+            // Notice: XOAUTH2 authenticates a mailbox user with an access token, thus the user \
+            //   must be known, while a password is not used.
+            if email.smtp_oauth2.is_some() == true && email.smtp_username.is_none() == true {
+                errors.push(ConfigError::new(
+                    &format!("{}.email.smtp_username", path),
+                    "username is required to authenticate with oauth2".to_string(),
+                ));
+            }
+        }
+
+        if let Some(ref twilio) = notify.twilio {
+            if twilio.to.is_empty() == true {
+                errors.push(ConfigError::new(
+                    &format!("{}.twilio.to", path),
+                    "no recipient phone number".to_string(),
+                ));
+            }
+        }
+
+        if let Some(ref slack) = notify.slack {
+            if slack.signing_secret.as_deref().map(str::is_empty) == Some(true) {
+                errors.push(ConfigError::new(
+                    &format!("{}.slack.signing_secret", path),
+                    "signing secret must not be empty".to_string(),
+                ));
+            }
+        }
+
+        if let Some(ref telegram) = notify.telegram {
+            if let Some(ref webhook_secret) = telegram.webhook_secret {
+                // Notice: Telegram only accepts secret tokens made of 1 to 256 characters, among \
+                //   'A-Z', 'a-z', '0-9', '_' and '-'; an empty secret would also match updates \
+                //   that come without any secret token.
+                let is_valid = webhook_secret.len() <= 256
+                    && webhook_secret.chars().all(|character| {
+                        character.is_ascii_alphanumeric() || "_-".contains(character)
+                    });
+
+                if webhook_secret.is_empty() == true {
+                    errors.push(ConfigError::new(
+                        &format!("{}.telegram.webhook_secret", path),
+                        "webhook secret must not be empty".to_string(),
+                    ));
+                } else if is_valid == false {
+                    errors.push(ConfigError::new(
+                        &format!("{}.telegram.webhook_secret", path),
+                        "webhook secret must be 1 to 256 letters, digits, '_' or '-'".to_string(),
+                    ));
+                }
+            }
+        }
+
+        if let Some(ref xmpp) = notify.xmpp {
+            if xmpp.to.is_none() == true && xmpp.room.is_none() == true {
+                errors.push(ConfigError::new(
+                    &format!("{}.xmpp.to", path),
+                    "no recipient or room".to_string(),
+                ));
+            }
+
+            // Notice: rooms are given as bare JIDs, as the nick is appended as their resource.
+            if let Some(ref room) = xmpp.room {
+                if room.contains('@') == false || room.contains('/') == true {
+                    errors.push(ConfigError::new(
+                        &format!("{}.xmpp.room", path),
+                        format!("invalid room jid: {}", room),
+                    ));
+                }
+            }
+
+            if xmpp.room_nick.trim().is_empty() == true {
+                errors.push(ConfigError::new(
+                    &format!("{}.xmpp.room_nick", path),
+                    "room nick must not be empty".to_string(),
+                ));
+            }
+        }
+
+        if let Some(ref signal) = notify.signal {
+            if signal.recipients.is_empty() == true {
+                errors.push(ConfigError::new(
+                    &format!("{}.signal.recipients", path),
+                    "no recipient phone number or group".to_string(),
+                ));
+            }
+        }
+
+        if let Some(ref sms) = notify.sms {
+            if sms.recipients.is_empty() == true {
+                errors.push(ConfigError::new(
+                    &format!("{}.sms.recipients", path),
+                    "no recipient phone number".to_string(),
+                ));
+            }
+
+            let templates = [
+                ("gateway_url", Some(&sms.gateway_url)),
+                ("body", sms.body.as_ref()),
+            ];
+
+            for (key, template) in templates {
+                if let Some(template) = template {
+                    if let Err(err) = Tera::default().add_raw_template(key, template) {
+                        errors.push(ConfigError::new(
+                            &format!("{}.sms.{}", path, key),
+                            format!("invalid template: {}", err),
+                        ));
+                    }
+                }
+            }
+
+            // Notice: gateway URLs that are not templates can be checked right away
+            if sms.gateway_url.contains("{{") == false && sms.gateway_url.contains("{%") == false {
+                match Url::parse(sms.gateway_url.trim()) {
+                    Ok(url) => {
+                        if url.scheme() != "http" && url.scheme() != "https" {
+                            errors.push(ConfigError::new(
+                                &format!("{}.sms.gateway_url", path),
+                                format!("url is not http(s): {}", url.as_str()),
+                            ));
+                        }
+                    }
+                    Err(err) => errors.push(ConfigError::new(
+                        &format!("{}.sms.gateway_url", path),
+                        format!("invalid url: {}", err),
+                    )),
+                }
+            }
+
+            if Method::from_bytes(sms.method.trim().to_uppercase().as_bytes()).is_err() == true {
+                errors.push(ConfigError::new(
+                    &format!("{}.sms.method", path),
+                    format!("invalid method: {}", sms.method),
+                ));
+            }
+
+            let headers = [
+                ("auth_header", sms.auth_header.as_ref()),
+                ("content_type", Some(&sms.content_type)),
+            ];
+
+            for (key, value) in headers {
+                if let Some(value) = value {
+                    if HeaderValue::from_str(value).is_err() == true {
+                        errors.push(ConfigError::new(
+                            &format!("{}.sms.{}", path, key),
+                            "invalid header value".to_string(),
+                        ));
+                    }
+                }
+            }
+
+            if sms.max_length <= 4 {
+                errors.push(ConfigError::new(
+                    &format!("{}.sms.max_length", path),
+                    "maximum length is too short".to_string(),
+                ));
+            }
+        }
+
+        if let Some(ref sns) = notify.sns {
+            // Notice: topic ARNs are formatted as 'arn:<partition>:sns:<region>:<account>:<name>'
+            let arn_parts: Vec<&str> = sns.topic_arn.split(':').collect();
+
+            if arn_parts.len() != 6 || arn_parts[0] != "arn" || arn_parts[2] != "sns" {
+                errors.push(ConfigError::new(
+                    &format!("{}.sns.topic_arn", path),
+                    format!("invalid topic arn: {}", sns.topic_arn),
+                ));
+            } else if sns.region.is_none() == true && arn_parts[3].is_empty() == true {
+                errors.push(ConfigError::new(
+                    &format!("{}.sns.region", path),
+                    "no region set, and none found in topic arn".to_string(),
+                ));
+            }
+
+            if sns.access_key_id.is_some() != sns.secret_access_key.is_some() {
+                errors.push(ConfigError::new(
+                    &format!("{}.sns.secret_access_key", path),
+                    "access key id and secret access key must be set together".to_string(),
+                ));
+            }
+        }
+
+        if let Some(ref mqtt) = notify.mqtt {
+            if ["mqtt", "mqtts"].contains(&mqtt.server_url.scheme()) == false
+                || mqtt.server_url.host_str().is_none() == true
+            {
+                errors.push(ConfigError::new(
+                    &format!("{}.mqtt.server_url", path),
+                    format!("url is not mqtt(s): {}", mqtt.server_url.as_str()),
+                ));
+            }
+
+            if mqtt.client_id.is_empty() == true {
+                errors.push(ConfigError::new(
+                    &format!("{}.mqtt.client_id", path),
+                    "client id cannot be empty".to_string(),
+                ));
+            }
+
+            // Notice: wildcards are only allowed when subscribing, not when publishing
+            if mqtt.topic.is_empty() == true
+                || mqtt.topic.contains('+') == true
+                || mqtt.topic.contains('#') == true
+            {
+                errors.push(ConfigError::new(
+                    &format!("{}.mqtt.topic", path),
+                    format!("invalid topic: {}", mqtt.topic),
+                ));
+            }
+
+            if mqtt.qos > 2 {
+                errors.push(ConfigError::new(
+                    &format!("{}.mqtt.qos", path),
+                    format!("qos must be 0, 1 or 2, got: {}", mqtt.qos),
+                ));
+            }
+        }
+
+        if let Some(ref irc) = notify.irc {
+            if ["irc", "ircs"].contains(&irc.server_url.scheme()) == false
+                || irc.server_url.host_str().is_none() == true
+            {
+                errors.push(ConfigError::new(
+                    &format!("{}.irc.server_url", path),
+                    format!("url is not irc(s): {}", irc.server_url.as_str()),
+                ));
+            }
+
+            if irc.nick.is_empty() == true || irc.nick.contains(char::is_whitespace) == true {
+                errors.push(ConfigError::new(
+                    &format!("{}.irc.nick", path),
+                    format!("invalid nick: {}", irc.nick),
+                ));
+            }
+
+            if irc.channel.starts_with(['#', '&']) == false
+                || irc.channel.contains(char::is_whitespace) == true
+                || irc.channel.contains(',') == true
+            {
+                errors.push(ConfigError::new(
+                    &format!("{}.irc.channel", path),
+                    format!("invalid channel: {}", irc.channel),
+                ));
+            }
+
+            if irc.sasl_username.is_some() == true && irc.sasl_password.is_none() == true {
+                errors.push(ConfigError::new(
+                    &format!("{}.irc.sasl_password", path),
+                    "sasl username is set without a password".to_string(),
+                ));
+            }
+        }
+
+        if let Some(ref apprise) = notify.apprise {
+            match (apprise.urls.is_empty(), &apprise.config_key) {
+                (true, None) => {
+                    errors.push(ConfigError::new(
+                        &format!("{}.apprise.urls", path),
+                        "no target url or config key".to_string(),
+                    ));
+                }
+                (false, Some(_)) => {
+                    errors.push(ConfigError::new(
+                        &format!("{}.apprise.urls", path),
+                        "target urls cannot be set along with a config key".to_string(),
+                    ));
+                }
+                _ => {}
+            }
+
+            // Notice: Apprise only accepts config keys made of 1 to 128 characters, among \
+            //   'A-Z', 'a-z', '0-9', '_' and '-'.
+            if let Some(ref config_key) = apprise.config_key {
+                let is_valid = config_key.is_empty() == false
+                    && config_key.len() <= 128
+                    && config_key.chars().all(|character| {
+                        character.is_ascii_alphanumeric() || "_-".contains(character)
+                    });
+
+                if is_valid == false {
+                    errors.push(ConfigError::new(
+                        &format!("{}.apprise.config_key", path),
+                        format!("invalid config key: {}", config_key),
+                    ));
+                }
+            }
+        }
+
+        if let Some(ref gotify) = notify.gotify {
+            let priorities = [
+                ("priority_dead", gotify.priority_dead),
+                ("priority_sick", gotify.priority_sick),
+                ("priority_healthy", gotify.priority_healthy),
+                ("priority_reminder", Some(gotify.priority_reminder)),
+            ];
+
+            for (key, priority) in priorities {
+                if priority.map_or(false, |priority| priority > 10) == true {
+                    errors.push(ConfigError::new(
+                        &format!("{}.gotify.{}", path, key),
+                        "priority must be between 0 and 10".to_string(),
+                    ));
+                }
+            }
+        }
+
+        for (index, quiet_hours) in notify.quiet_hours.iter().enumerate() {
+            let quiet_path = format!("{}.quiet_hours[{}]", path, index);
+
+            for (key, value) in [("from", &quiet_hours.from), ("to", &quiet_hours.to)] {
+                if let Err(err) = quiet::parse_time(value) {
+                    errors.push(ConfigError::new(&format!("{}.{}", quiet_path, key), err));
+                }
+            }
+
+            if let Err(err) = quiet::parse_utc_offset(&quiet_hours.utc_offset) {
+                errors.push(ConfigError::new(&format!("{}.utc_offset", quiet_path), err));
+            }
+
+            match (quiet_hours.action, quiet_hours.reroute_to.is_some()) {
+                (ConfigNotifyQuietHoursAction::Reroute, false) => {
+                    errors.push(ConfigError::new(
+                        &format!("{}.reroute_to", quiet_path),
+                        "reroute action requires a channel to reroute to".to_string(),
+                    ));
+                }
+                (ConfigNotifyQuietHoursAction::Suppress, true) => {
+                    errors.push(ConfigError::new(
+                        &format!("{}.reroute_to", quiet_path),
+                        "only applies to the reroute action".to_string(),
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(ref template) = notify.template {
+            let templates = [
+                ("subject", template.subject.as_ref()),
+                ("body", template.body.as_ref()),
+            ];
+
+            for (key, template) in templates {
+                if let Some(template) = template {
+                    if let Err(err) = Tera::default().add_raw_template(key, template) {
+                        errors.push(ConfigError::new(
+                            &format!("{}.template.{}", path, key),
+                            format!("invalid template: {}", err),
+                        ));
+                    }
+                }
+            }
+        }
+
+        if let Some(ref webhook) = notify.webhook {
+            let templates = [
+                ("method", webhook.method.as_ref()),
+                ("body", webhook.body.as_ref()),
+            ];
+
+            for (key, template) in templates {
+                if let Some(template) = template {
+                    if let Err(err) = Tera::default().add_raw_template(key, template) {
+                        errors.push(ConfigError::new(
+                            &format!("{}.webhook.{}", path, key),
+                            format!("invalid template: {}", err),
+                        ));
+                    }
+                }
+            }
+
+            // Notice: methods that are not templates can be checked right away
+            if let Some(ref method) = webhook.method {
+                if method.contains("{{") == false
+                    && method.contains("{%") == false
+                    && Method::from_bytes(method.trim().as_bytes()).is_err() == true
+                {
+                    errors.push(ConfigError::new(
+                        &format!("{}.webhook.method", path),
+                        format!("invalid method: {}", method),
+                    ));
                 }
             }
+
+            for (name, template) in webhook.headers.iter() {
+                if HeaderName::from_bytes(name.as_bytes()).is_err() == true {
+                    errors.push(ConfigError::new(
+                        &format!("{}.webhook.headers", path),
+                        format!("invalid header name: {}", name),
+                    ));
+                }
+
+                if let Err(err) = Tera::default().add_raw_template(name, template) {
+                    errors.push(ConfigError::new(
+                        &format!("{}.webhook.headers.{}", path, name),
+                        format!("invalid template: {}", err),
+                    ));
+                }
+            }
+        }
+
+        if let Some(ref pushover) = notify.pushover {
+            if pushover.user_keys.is_empty() == true {
+                errors.push(ConfigError::new(
+                    &format!("{}.pushover.user_keys", path),
+                    "no recipient user key".to_string(),
+                ));
+            }
+        }
+
+        // Scan for notifier URLs that cannot be requested
+        let urls: Vec<(&str, Option<&SerdeUrl>)> = vec![
+            (
+                "slack.hook_url",
+                notify.slack.as_ref().map(|slack| &slack.hook_url),
+            ),
+            (
+                "zulip.api_url",
+                notify.zulip.as_ref().map(|zulip| &zulip.api_url),
+            ),
+            (
+                "gotify.app_url",
+                notify.gotify.as_ref().map(|gotify| &gotify.app_url),
+            ),
+            (
+                "matrix.homeserver_url",
+                notify.matrix.as_ref().map(|matrix| &matrix.homeserver_url),
+            ),
+            (
+                "webhook.hook_url",
+                notify.webhook.as_ref().map(|webhook| &webhook.hook_url),
+            ),
+            (
+                "opsgenie.api_url",
+                notify.opsgenie.as_ref().map(|opsgenie| &opsgenie.api_url),
+            ),
+            (
+                "ntfy.server_url",
+                notify.ntfy.as_ref().map(|ntfy| &ntfy.server_url),
+            ),
+            (
+                "teams.hook_url",
+                notify.teams.as_ref().map(|teams| &teams.hook_url),
+            ),
+            (
+                "discord.hook_url",
+                notify.discord.as_ref().map(|discord| &discord.hook_url),
+            ),
+            (
+                "mattermost.hook_url",
+                notify
+                    .mattermost
+                    .as_ref()
+                    .map(|mattermost| &mattermost.hook_url),
+            ),
+            (
+                "rocketchat.hook_url",
+                notify
+                    .rocketchat
+                    .as_ref()
+                    .map(|rocketchat| &rocketchat.hook_url),
+            ),
+            (
+                "googlechat.hook_url",
+                notify
+                    .googlechat
+                    .as_ref()
+                    .map(|googlechat| &googlechat.hook_url),
+            ),
+            (
+                "signal.api_url",
+                notify.signal.as_ref().map(|signal| &signal.api_url),
+            ),
+            (
+                "apprise.server_url",
+                notify.apprise.as_ref().map(|apprise| &apprise.server_url),
+            ),
+        ];
+
+        for (key, url) in urls {
+            if let Some(url) = url {
+                Self::validate_http_url(&format!("{}.{}", path, key), url, errors);
+            }
+        }
+    }
+
+    fn validate_subscriptions(config: &Config, errors: &mut Vec<ConfigError>) {
+        if let Some(ref subscriptions) = config.subscriptions {
+            if subscriptions.secret.is_empty() == true {
+                errors.push(ConfigError::new(
+                    "subscriptions.secret",
+                    "secret cannot be empty".to_string(),
+                ));
+            }
+
+            for (index, hook) in subscriptions.hook.iter().enumerate() {
+                Self::validate_http_url(
+                    &format!("subscriptions.hook[{}].url", index),
+                    &hook.url,
+                    errors,
+                );
+            }
+        }
+    }
+
+    fn validate_discovery(config: &Config, errors: &mut Vec<ConfigError>) {
+        if let Some(ref consul) = config
+            .discovery
+            .as_ref()
+            .and_then(|discovery| discovery.consul.as_ref())
+        {
+            if ["tcp", "http", "https"].contains(&consul.scheme.as_str()) == false {
+                errors.push(ConfigError::new(
+                    "discovery.consul.scheme",
+                    format!("unsupported replica scheme: {}", consul.scheme),
+                ));
+            }
+        }
+    }
+
+    fn validate_tracing(config: &Config, errors: &mut Vec<ConfigError>) {
+        if let Some(ref tracing) = config.tracing {
+            Self::validate_http_url("tracing.endpoint", &tracing.endpoint, errors);
+
+            if tracing.service_name.is_empty() == true {
+                errors.push(ConfigError::new(
+                    "tracing.service_name",
+                    "service name is empty".to_string(),
+                ));
+            }
+
+            if tracing.sample_ratio < 0.0 || tracing.sample_ratio > 1.0 {
+                errors.push(ConfigError::new(
+                    "tracing.sample_ratio",
+                    format!(
+                        "sample ratio must be between 0.0 and 1.0, got: {}",
+                        tracing.sample_ratio
+                    ),
+                ));
+            }
+
+            if tracing.export_interval == 0 {
+                errors.push(ConfigError::new(
+                    "tracing.export_interval",
+                    "export interval must be greater than zero".to_string(),
+                ));
+            }
+
+            if tracing.max_queue == 0 {
+                errors.push(ConfigError::new(
+                    "tracing.max_queue",
+                    "max queue must be greater than zero".to_string(),
+                ));
+            }
+
+            for (name, value) in tracing.headers.iter() {
+                if HeaderName::from_bytes(name.as_bytes()).is_err() == true {
+                    errors.push(ConfigError::new(
+                        "tracing.headers",
+                        format!("invalid header name: {}", name),
+                    ));
+                }
+
+                if HeaderValue::from_str(value).is_err() == true {
+                    errors.push(ConfigError::new(
+                        &format!("tracing.headers.{}", name),
+                        "invalid header value".to_string(),
+                    ));
+                }
+            }
+        }
+    }
+
+    fn validate_http_url(path: &str, url: &SerdeUrl, errors: &mut Vec<ConfigError>) {
+        if url.scheme() != "http" && url.scheme() != "https" {
+            errors.push(ConfigError::new(
+                path,
+                format!("url is not http(s): {}", url.as_str()),
+            ));
+        }
+    }
+
+    fn validate_proxy_url(path: &str, url: &SerdeUrl, errors: &mut Vec<ConfigError>) {
+        match url.scheme() {
+            "http" | "https" | "socks5" | "socks5h" => {}
+            scheme => errors.push(ConfigError::new(
+                path,
+                format!("unsupported proxy scheme: {}", scheme),
+            )),
         }
     }
 }
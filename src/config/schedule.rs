@@ -0,0 +1,302 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::de::{Error, Visitor};
+use serde::{Deserialize, Deserializer};
+use time::{Date, OffsetDateTime};
+
+// Notice: 5 years is enough to find a run of any valid schedule (eg. on February 29th, which \
+//   might only happen on a given weekday every few years).
+const SCHEDULE_LOOKBEHIND_DAYS: u32 = 5 * 366;
+
+#[derive(Clone, Debug)]
+pub struct Schedule {
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days: Vec<bool>,
+    months: Vec<bool>,
+    weekdays: Vec<bool>,
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl Schedule {
+    pub fn parse(expression: &str) -> Result<Schedule, String> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+
+        if fields.len() != 5 {
+            return Err(format!(
+                "expected 5 fields (minute hour day month weekday), got: {}",
+                fields.len()
+            ));
+        }
+
+        let mut weekdays = parse_field(fields[4], 0, 7)?;
+
+        // Sunday can be written as either 0 or 7
+        if weekdays[7] == true {
+            weekdays[0] = true;
+        }
+
+        weekdays.truncate(7);
+
+        Ok(Schedule {
+            minutes: parse_field(fields[0], 0, 59)?,
+            hours: parse_field(fields[1], 0, 23)?,
+            days: parse_field(fields[2], 1, 31)?,
+            months: parse_field(fields[3], 1, 12)?,
+            weekdays: weekdays,
+            // Notice: as in cron, fields starting with a star are unrestricted (eg. '*/1')
+            days_restricted: fields[2].starts_with('*') == false,
+            weekdays_restricted: fields[4].starts_with('*') == false,
+        })
+    }
+
+    pub fn previous(&self, before: SystemTime) -> Option<SystemTime> {
+        // Acquire latest scheduled run at or before given time (in UTC)
+        let before = OffsetDateTime::from_unix_timestamp(
+            before.duration_since(UNIX_EPOCH).ok()?.as_secs() as i64,
+        )
+        .ok()?;
+
+        let mut date = before.date();
+
+        for day_index in 0..SCHEDULE_LOOKBEHIND_DAYS {
+            if self.matches_date(date) == true {
+                // Only consider times up to the given time on its own day
+                let limit = if day_index == 0 {
+                    (before.hour() as usize, before.minute() as usize)
+                } else {
+                    (23, 59)
+                };
+
+                for hour in (0..=limit.0).rev() {
+                    if self.hours[hour] == false {
+                        continue;
+                    }
+
+                    let minute_limit = if hour == limit.0 { limit.1 } else { 59 };
+
+                    if let Some(minute) = (0..=minute_limit)
+                        .rev()
+                        .find(|minute| self.minutes[*minute])
+                    {
+                        let run = date
+                            .with_hms(hour as u8, minute as u8, 0)
+                            .ok()?
+                            .assume_utc()
+                            .unix_timestamp();
+
+                        return Some(UNIX_EPOCH + Duration::from_secs(run as u64));
+                    }
+                }
+            }
+
+            date = date.previous_day()?;
+        }
+
+        None
+    }
+
+    fn matches_date(&self, date: Date) -> bool {
+        if self.months[u8::from(date.month()) as usize] == false {
+            return false;
+        }
+
+        let (matches_day, matches_weekday) = (
+            self.days[date.day() as usize],
+            self.weekdays[date.weekday().number_days_from_sunday() as usize],
+        );
+
+        // Notice: as in cron, if both the day and the weekday are restricted, any of them \
+        //   matching is enough.
+        match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => matches_day == true || matches_weekday == true,
+            _ => matches_day == true && matches_weekday == true,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Schedule {
+    fn deserialize<D>(de: D) -> Result<Schedule, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ScheduleVisitor;
+
+        impl<'de> Visitor<'de> for ScheduleVisitor {
+            type Value = Schedule;
+
+            fn expecting(&self, format: &mut fmt::Formatter) -> fmt::Result {
+                format.write_str("a cron expression")
+            }
+
+            fn visit_str<E: Error>(self, value: &str) -> Result<Schedule, E> {
+                Schedule::parse(value).map_err(E::custom)
+            }
+        }
+
+        de.deserialize_str(ScheduleVisitor)
+    }
+}
+
+fn parse_field(field: &str, minimum: usize, maximum: usize) -> Result<Vec<bool>, String> {
+    let mut values = vec![false; maximum + 1];
+
+    for part in field.split(',') {
+        // Acquire step (eg. '*/5')
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (
+                range,
+                step.parse::<usize>()
+                    .ok()
+                    .filter(|step| *step > 0)
+                    .ok_or(format!("invalid step: {}", part))?,
+            ),
+            None => (part, 1),
+        };
+
+        // Acquire range (eg. '*', '1-5' or '3')
+        let (start, end) = if range == "*" {
+            (minimum, maximum)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (parse_value(start, part)?, parse_value(end, part)?)
+        } else {
+            let value = parse_value(range, part)?;
+
+            // Notice: a single value with a step runs up to the maximum (eg. '10/15')
+            (value, if step > 1 { maximum } else { value })
+        };
+
+        if start < minimum || end > maximum || start > end {
+            return Err(format!("out of range value: {}", part));
+        }
+
+        for value in (start..=end).step_by(step) {
+            values[value] = true;
+        }
+    }
+
+    Ok(values)
+}
+
+fn parse_value(value: &str, part: &str) -> Result<usize, String> {
+    value
+        .parse::<usize>()
+        .or(Err(format!("invalid value: {}", part)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::Month;
+
+    fn at(year: i32, month: Month, day: u8, hour: u8, minute: u8) -> SystemTime {
+        let timestamp = Date::from_calendar_date(year, month, day)
+            .unwrap()
+            .with_hms(hour, minute, 0)
+            .unwrap()
+            .assume_utc()
+            .unix_timestamp();
+
+        UNIX_EPOCH + Duration::from_secs(timestamp as u64)
+    }
+
+    fn previous(expression: &str, before: SystemTime) -> Option<SystemTime> {
+        Schedule::parse(expression).unwrap().previous(before)
+    }
+
+    #[test]
+    fn it_matches_days_or_weekdays() {
+        // Both restricted: any of the day or the weekday matching is enough
+        assert_eq!(
+            previous("0 0 13 * 5", at(2022, Month::May, 12, 12, 0)),
+            Some(at(2022, Month::May, 6, 0, 0))
+        );
+        assert_eq!(
+            previous("0 0 10 * 5", at(2022, Month::May, 11, 12, 0)),
+            Some(at(2022, Month::May, 10, 0, 0))
+        );
+    }
+
+    #[test]
+    fn it_matches_days_and_weekdays_if_unrestricted() {
+        assert_eq!(
+            previous("0 0 13 * *", at(2022, Month::May, 12, 12, 0)),
+            Some(at(2022, Month::April, 13, 0, 0))
+        );
+        assert_eq!(
+            previous("0 0 13 * */1", at(2022, Month::May, 12, 12, 0)),
+            Some(at(2022, Month::April, 13, 0, 0))
+        );
+        assert_eq!(
+            previous("0 0 */1 * 5", at(2022, Month::May, 12, 12, 0)),
+            Some(at(2022, Month::May, 6, 0, 0))
+        );
+    }
+
+    #[test]
+    fn it_matches_sunday_as_0_and_7() {
+        assert_eq!(
+            previous("0 12 * * 0", at(2022, Month::May, 4, 0, 0)),
+            Some(at(2022, Month::May, 1, 12, 0))
+        );
+        assert_eq!(
+            previous("0 12 * * 7", at(2022, Month::May, 4, 0, 0)),
+            Some(at(2022, Month::May, 1, 12, 0))
+        );
+        assert_eq!(
+            previous("0 12 * * 5-7", at(2022, Month::May, 4, 0, 0)),
+            Some(at(2022, Month::May, 1, 12, 0))
+        );
+    }
+
+    #[test]
+    fn it_matches_steps() {
+        assert_eq!(
+            previous("*/15 * * * *", at(2022, Month::May, 4, 10, 44)),
+            Some(at(2022, Month::May, 4, 10, 30))
+        );
+        assert_eq!(
+            previous("10/20 * * * *", at(2022, Month::May, 4, 10, 5)),
+            Some(at(2022, Month::May, 4, 9, 50))
+        );
+        assert_eq!(
+            previous("0 8-18/5 * * *", at(2022, Month::May, 4, 12, 0)),
+            Some(at(2022, Month::May, 4, 8, 0))
+        );
+    }
+
+    #[test]
+    fn it_matches_given_time() {
+        assert_eq!(
+            previous("30 10 * * *", at(2022, Month::May, 4, 10, 30)),
+            Some(at(2022, Month::May, 4, 10, 30))
+        );
+    }
+
+    #[test]
+    fn it_looks_behind_for_years() {
+        assert_eq!(
+            previous("0 0 29 2 *", at(2022, Month::May, 1, 0, 0)),
+            Some(at(2020, Month::February, 29, 0, 0))
+        );
+        assert_eq!(previous("0 0 31 2 *", at(2022, Month::May, 1, 0, 0)), None);
+    }
+
+    #[test]
+    fn it_rejects_invalid_expressions() {
+        assert!(Schedule::parse("* * * *").is_err());
+        assert!(Schedule::parse("*/0 * * * *").is_err());
+        assert!(Schedule::parse("60 * * * *").is_err());
+        assert!(Schedule::parse("0 0 0 * *").is_err());
+        assert!(Schedule::parse("0 0 * * 8").is_err());
+        assert!(Schedule::parse("5-1 * * * *").is_err());
+    }
+}
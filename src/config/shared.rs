@@ -0,0 +1,36 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::ops::Deref;
+use std::sync::RwLock;
+
+use super::config::Config;
+
+pub struct SharedConfig {
+    current: RwLock<&'static Config>,
+}
+
+impl SharedConfig {
+    pub fn new(config: Config) -> SharedConfig {
+        SharedConfig {
+            current: RwLock::new(Box::leak(Box::new(config))),
+        }
+    }
+
+    pub fn replace(&self, config: Config) {
+        // Notice: replaced configurations are leaked on purpose, as references to them might \
+        //   still be held by running threads; reloads are rare, so this is acceptable.
+        *self.current.write().unwrap() = Box::leak(Box::new(config));
+    }
+}
+
+impl Deref for SharedConfig {
+    type Target = Config;
+
+    fn deref(&self) -> &Config {
+        *self.current.read().unwrap()
+    }
+}
@@ -0,0 +1,210 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use reqwest::blocking::{Client, RequestBuilder};
+use serde::de::DeserializeOwned;
+
+use super::manager::{DiscoveredNode, DiscoveredService};
+use crate::config::config::ConfigDiscoveryConsul;
+use crate::APP_CONF;
+
+pub static SOURCE: &'static str = "consul";
+
+static CONSUL_SERVICE_NAME: &'static str = "consul";
+static CONSUL_CHECK_STATUS_PASSING: &'static str = "passing";
+
+const REQUEST_TIMEOUT_SECONDS: u64 = 10;
+
+lazy_static! {
+    static ref CONSUL_HTTP_CLIENT: Client = Client::builder()
+        .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECONDS))
+        .gzip(true)
+        .build()
+        .unwrap();
+}
+
+#[derive(Deserialize)]
+struct ConsulServiceEntry {
+    #[serde(rename = "Node")]
+    node: ConsulServiceEntryNode,
+
+    #[serde(rename = "Service")]
+    service: ConsulServiceEntryService,
+
+    #[serde(rename = "Checks", default)]
+    checks: Vec<ConsulServiceEntryCheck>,
+}
+
+#[derive(Deserialize)]
+struct ConsulServiceEntryNode {
+    #[serde(rename = "Address")]
+    address: String,
+}
+
+#[derive(Deserialize)]
+struct ConsulServiceEntryService {
+    #[serde(rename = "Address", default)]
+    address: String,
+
+    #[serde(rename = "Port")]
+    port: u16,
+}
+
+#[derive(Deserialize)]
+struct ConsulServiceEntryCheck {
+    #[serde(rename = "Status")]
+    status: String,
+}
+
+pub fn discover() -> Option<Vec<DiscoveredService>> {
+    let config = APP_CONF
+        .discovery
+        .as_ref()
+        .and_then(|discovery| discovery.consul.as_ref())?;
+
+    // List services from catalog (along with the tags of all their instances)
+    let catalog: BTreeMap<String, Vec<String>> = request(
+        config,
+        CONSUL_HTTP_CLIENT.get(&make_url(config, "v1/catalog/services")),
+    )?;
+
+    let mut nodes = Vec::new();
+
+    for (service_name, service_tags) in catalog {
+        // Notice: the Consul service is registered by Consul servers themselves, and thus is not \
+        //   a service that should be monitored.
+        if service_name == CONSUL_SERVICE_NAME {
+            continue;
+        }
+
+        // Only retain services that have all required tags
+        if config.tags.iter().all(|tag| service_tags.contains(tag)) == false {
+            continue;
+        }
+
+        // List service instances (that have all required tags)
+        let mut request_builder = CONSUL_HTTP_CLIENT.get(&make_url(
+            config,
+            &format!("v1/health/service/{}", service_name),
+        ));
+
+        for tag in config.tags.iter() {
+            request_builder = request_builder.query(&[("tag", tag)]);
+        }
+
+        let entries: Vec<ConsulServiceEntry> = request(config, request_builder)?;
+
+        // Generate replicas from healthy instances
+        // Notice: if no instance is healthy, all instances are polled, so that the outage shows \
+        //   on the status page (instead of the service silently having no replica).
+        let (healthy_entries, unhealthy_entries): (Vec<_>, Vec<_>) =
+            entries.into_iter().partition(|entry| {
+                entry
+                    .checks
+                    .iter()
+                    .all(|check| check.status == CONSUL_CHECK_STATUS_PASSING)
+            });
+
+        let polled_entries = if healthy_entries.is_empty() == true {
+            unhealthy_entries
+        } else {
+            healthy_entries
+        };
+
+        let mut replicas: Vec<String> = polled_entries
+            .iter()
+            .map(|entry| make_replica_url(config, entry))
+            .collect();
+
+        replicas.sort();
+        replicas.dedup();
+
+        if replicas.is_empty() == false {
+            nodes.push(DiscoveredNode {
+                id: service_name.to_owned(),
+                label: service_name,
+                replicas: replicas,
+            });
+        }
+    }
+
+    // Notice: all Consul services are grouped into a single service on the status page
+    if nodes.is_empty() == true {
+        Some(Vec::new())
+    } else {
+        Some(vec![DiscoveredService {
+            id: config.service_id.to_owned(),
+            label: config.service_label.to_owned(),
+            nodes: nodes,
+        }])
+    }
+}
+
+fn make_url(config: &ConfigDiscoveryConsul, path: &str) -> String {
+    format!("{}/{}", config.api_url.as_str().trim_end_matches('/'), path)
+}
+
+fn make_replica_url(config: &ConfigDiscoveryConsul, entry: &ConsulServiceEntry) -> String {
+    // Service address defaults to its node address if not set
+    let address = if entry.service.address.is_empty() == true {
+        &entry.node.address
+    } else {
+        &entry.service.address
+    };
+
+    // Wrap IPv6 addresses in brackets
+    let host = if address.contains(':') == true {
+        format!("[{}]", address)
+    } else {
+        address.to_owned()
+    };
+
+    match config.scheme.as_str() {
+        "http" | "https" => format!("{}://{}:{}/", config.scheme, host, entry.service.port),
+        _ => format!("{}://{}:{}", config.scheme, host, entry.service.port),
+    }
+}
+
+fn request<T: DeserializeOwned>(
+    config: &ConfigDiscoveryConsul,
+    mut request_builder: RequestBuilder,
+) -> Option<T> {
+    if let Some(ref datacenter) = config.datacenter {
+        request_builder = request_builder.query(&[("dc", datacenter)]);
+    }
+
+    if let Some(ref token) = config.token {
+        request_builder = request_builder.header("X-Consul-Token", token.as_str());
+    }
+
+    match request_builder.send() {
+        Ok(response) if response.status().is_success() == true => match response.json() {
+            Ok(data) => Some(data),
+            Err(err) => {
+                error!("could not parse consul response: {}", err);
+
+                None
+            }
+        },
+        Ok(response) => {
+            error!(
+                "got consul error response: {} ({})",
+                response.url(),
+                response.status()
+            );
+
+            None
+        }
+        Err(err) => {
+            error!("could not request consul: {}", err);
+
+            None
+        }
+    }
+}
@@ -0,0 +1,173 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+
+use super::manager::{DiscoveredNode, DiscoveredService};
+use crate::config::config::ConfigDiscoveryDocker;
+use crate::APP_CONF;
+
+pub static SOURCE: &'static str = "docker";
+
+static LABEL_PROBE: &'static str = "vigil.probe";
+static LABEL_URL: &'static str = "vigil.url";
+static LABEL_NODE: &'static str = "vigil.node";
+static LABEL_LABEL: &'static str = "vigil.label";
+static LABEL_COMPOSE_SERVICE: &'static str = "com.docker.compose.service";
+
+const REQUEST_TIMEOUT_SECONDS: u64 = 10;
+
+#[derive(Deserialize)]
+struct DockerContainer {
+    #[serde(rename = "Names", default)]
+    names: Vec<String>,
+
+    #[serde(rename = "Labels", default)]
+    labels: HashMap<String, String>,
+}
+
+pub fn discover() -> Option<Vec<DiscoveredService>> {
+    let config = APP_CONF
+        .discovery
+        .as_ref()
+        .and_then(|discovery| discovery.docker.as_ref())?;
+
+    // Notice: only running containers are listed, thus stopped containers get removed
+    let containers: Vec<DockerContainer> = request(config, "/containers/json")?;
+
+    // Group labelled containers per service, then per node
+    let mut services: BTreeMap<String, BTreeMap<String, DiscoveredNode>> = BTreeMap::new();
+
+    for container in containers {
+        let container_name = container
+            .names
+            .first()
+            .map(|name| name.trim_start_matches('/').to_owned())
+            .unwrap_or_default();
+
+        let (probe_id, urls) = match (
+            container.labels.get(LABEL_PROBE),
+            container.labels.get(LABEL_URL),
+        ) {
+            (Some(probe_id), Some(urls)) => (probe_id, urls),
+            (Some(_), None) => {
+                warn!(
+                    "discovery ({}): ignored container with no url: {}",
+                    SOURCE, container_name
+                );
+
+                continue;
+            }
+            _ => continue,
+        };
+
+        // Notice: containers from the same Compose service (ie. scaled containers) are grouped \
+        //   as replicas of the same node.
+        let node_id = container
+            .labels
+            .get(LABEL_NODE)
+            .or_else(|| container.labels.get(LABEL_COMPOSE_SERVICE))
+            .cloned()
+            .unwrap_or(container_name);
+
+        let node = services
+            .entry(probe_id.to_owned())
+            .or_insert_with(BTreeMap::new)
+            .entry(node_id.to_owned())
+            .or_insert_with(|| DiscoveredNode {
+                id: node_id.to_owned(),
+                label: node_id.to_owned(),
+                replicas: Vec::new(),
+            });
+
+        if let Some(label) = container.labels.get(LABEL_LABEL) {
+            node.label = label.to_owned();
+        }
+
+        for url in urls.split(',').map(|url| url.trim()) {
+            if url.is_empty() == false
+                && node.replicas.iter().any(|replica| replica == url) == false
+            {
+                node.replicas.push(url.to_owned());
+            }
+        }
+    }
+
+    Some(
+        services
+            .into_iter()
+            .map(|(probe_id, nodes)| DiscoveredService {
+                id: probe_id.to_owned(),
+                label: probe_id,
+                nodes: nodes.into_iter().map(|(_, node)| node).collect(),
+            })
+            .collect(),
+    )
+}
+
+fn request<T: DeserializeOwned>(config: &ConfigDiscoveryDocker, path: &str) -> Option<T> {
+    debug!("discovery ({}): requesting: {}", SOURCE, path);
+
+    // Notice: the Docker API is served over a UNIX socket, which our HTTP client cannot connect \
+    //   to; a plain HTTP/1.0 request is enough there (the response body is never chunked).
+    let response = UnixStream::connect(&config.socket_path).and_then(|mut stream| {
+        stream.set_read_timeout(Some(Duration::from_secs(REQUEST_TIMEOUT_SECONDS)))?;
+        stream.set_write_timeout(Some(Duration::from_secs(REQUEST_TIMEOUT_SECONDS)))?;
+
+        write!(stream, "GET {} HTTP/1.0\r\nHost: docker\r\n\r\n", path)?;
+
+        let mut response = Vec::new();
+
+        stream.read_to_end(&mut response)?;
+
+        Ok(response)
+    });
+
+    let response = match response {
+        Ok(response) => response,
+        Err(err) => {
+            error!(
+                "could not request docker: {:?} ({})",
+                config.socket_path, err
+            );
+
+            return None;
+        }
+    };
+
+    // Split response head from body
+    let body_start = match response.windows(4).position(|window| window == b"\r\n\r\n") {
+        Some(head_end) => head_end + 4,
+        None => {
+            error!("got invalid docker response on: {}", path);
+
+            return None;
+        }
+    };
+
+    let status_line = String::from_utf8_lossy(&response[..body_start]);
+    let status_line = status_line.lines().next().unwrap_or("");
+
+    if status_line.split(' ').nth(1) != Some("200") {
+        error!("got docker error response on: {} ({})", path, status_line);
+
+        return None;
+    }
+
+    match serde_json::from_slice(&response[body_start..]) {
+        Ok(data) => Some(data),
+        Err(err) => {
+            error!("could not parse docker response on: {} ({})", path, err);
+
+            None
+        }
+    }
+}
@@ -0,0 +1,293 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+use reqwest::header::AUTHORIZATION;
+use reqwest::Certificate;
+use serde::de::DeserializeOwned;
+
+use super::manager::{DiscoveredNode, DiscoveredService};
+use crate::config::config::ConfigDiscoveryKubernetes;
+use crate::APP_CONF;
+
+pub static SOURCE: &'static str = "kubernetes";
+
+static ANNOTATION_PROBE: &'static str = "vigil.io/probe";
+static ANNOTATION_URL: &'static str = "vigil.io/url";
+static ANNOTATION_LABEL: &'static str = "vigil.io/label";
+
+const REQUEST_TIMEOUT_SECONDS: u64 = 10;
+
+#[derive(Deserialize)]
+struct KubernetesList<T> {
+    items: Vec<T>,
+}
+
+#[derive(Deserialize)]
+struct KubernetesObject<S> {
+    metadata: KubernetesMetadata,
+    spec: S,
+}
+
+#[derive(Deserialize)]
+struct KubernetesMetadata {
+    name: String,
+    namespace: String,
+
+    #[serde(default)]
+    annotations: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct KubernetesServiceSpec {
+    #[serde(default)]
+    ports: Vec<KubernetesServicePort>,
+}
+
+#[derive(Deserialize)]
+struct KubernetesServicePort {
+    port: u16,
+}
+
+#[derive(Deserialize)]
+struct KubernetesIngressSpec {
+    #[serde(default)]
+    rules: Vec<KubernetesIngressRule>,
+
+    #[serde(default)]
+    tls: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct KubernetesIngressRule {
+    host: Option<String>,
+}
+
+pub fn discover() -> Option<Vec<DiscoveredService>> {
+    let config = APP_CONF
+        .discovery
+        .as_ref()
+        .and_then(|discovery| discovery.kubernetes.as_ref())?;
+
+    let client = make_client(config)?;
+
+    // Notice: the service account token is read upon each discovery, as it gets rotated
+    let token = match fs::read_to_string(&config.token_path) {
+        Ok(token) => token.trim().to_owned(),
+        Err(err) => {
+            error!(
+                "could not read kubernetes token: {:?} ({})",
+                config.token_path, err
+            );
+
+            return None;
+        }
+    };
+
+    // Notice: an empty namespace list means that all namespaces are watched
+    let namespaces: Vec<Option<&str>> = if config.namespaces.is_empty() == true {
+        vec![None]
+    } else {
+        config
+            .namespaces
+            .iter()
+            .map(|namespace| Some(namespace.as_str()))
+            .collect()
+    };
+
+    // Group discovered nodes per namespace (each namespace maps to a service)
+    let mut namespace_nodes: BTreeMap<String, Vec<DiscoveredNode>> = BTreeMap::new();
+
+    for namespace in namespaces {
+        let services: KubernetesList<KubernetesObject<KubernetesServiceSpec>> = request(
+            &client,
+            &token,
+            &make_url(config, "api/v1", namespace, "services"),
+        )?;
+
+        for service in services.items {
+            let default_url = service.spec.ports.first().map(|port| {
+                format!(
+                    "tcp://{}.{}.svc:{}",
+                    service.metadata.name, service.metadata.namespace, port.port
+                )
+            });
+
+            if let Some(node) = make_node("service", &service.metadata, default_url) {
+                namespace_nodes
+                    .entry(service.metadata.namespace)
+                    .or_insert_with(Vec::new)
+                    .push(node);
+            }
+        }
+
+        let ingresses: KubernetesList<KubernetesObject<KubernetesIngressSpec>> = request(
+            &client,
+            &token,
+            &make_url(config, "apis/networking.k8s.io/v1", namespace, "ingresses"),
+        )?;
+
+        for ingress in ingresses.items {
+            let scheme = if ingress.spec.tls.is_empty() == true {
+                "http"
+            } else {
+                "https"
+            };
+
+            let default_url = ingress
+                .spec
+                .rules
+                .iter()
+                .find_map(|rule| rule.host.as_ref())
+                .map(|host| format!("{}://{}/", scheme, host));
+
+            if let Some(node) = make_node("ingress", &ingress.metadata, default_url) {
+                namespace_nodes
+                    .entry(ingress.metadata.namespace)
+                    .or_insert_with(Vec::new)
+                    .push(node);
+            }
+        }
+    }
+
+    Some(
+        namespace_nodes
+            .into_iter()
+            .map(|(namespace, mut nodes)| {
+                nodes.sort_by(|node, other_node| node.id.cmp(&other_node.id));
+
+                DiscoveredService {
+                    id: namespace.to_owned(),
+                    label: namespace,
+                    nodes: nodes,
+                }
+            })
+            .collect(),
+    )
+}
+
+fn make_client(config: &ConfigDiscoveryKubernetes) -> Option<Client> {
+    let mut builder = Client::builder().timeout(Duration::from_secs(REQUEST_TIMEOUT_SECONDS));
+
+    // Trust cluster certificate authority? (if any, as the API might use a public certificate)
+    if config.ca_path.exists() == true {
+        match fs::read(&config.ca_path).map(|ca| Certificate::from_pem(&ca)) {
+            Ok(Ok(certificate)) => builder = builder.add_root_certificate(certificate),
+            _ => {
+                error!(
+                    "could not load kubernetes certificate authority: {:?}",
+                    config.ca_path
+                );
+
+                return None;
+            }
+        }
+    }
+
+    builder.build().ok()
+}
+
+fn make_url(
+    config: &ConfigDiscoveryKubernetes,
+    group: &str,
+    namespace: Option<&str>,
+    resource: &str,
+) -> String {
+    let api_url = config.api_url.as_str().trim_end_matches('/');
+
+    match namespace {
+        Some(namespace) => format!(
+            "{}/{}/namespaces/{}/{}",
+            api_url, group, namespace, resource
+        ),
+        None => format!("{}/{}/{}", api_url, group, resource),
+    }
+}
+
+fn make_node(
+    kind: &str,
+    metadata: &KubernetesMetadata,
+    default_url: Option<String>,
+) -> Option<DiscoveredNode> {
+    // Only consider annotated objects
+    if metadata
+        .annotations
+        .get(ANNOTATION_PROBE)
+        .map(String::as_str)
+        != Some("true")
+    {
+        return None;
+    }
+
+    // Acquire replica URLs (either explicit, or guessed from the object)
+    let replicas: Vec<String> = match metadata.annotations.get(ANNOTATION_URL) {
+        Some(urls) => urls
+            .split(',')
+            .map(|url| url.trim().to_owned())
+            .filter(|url| url.is_empty() == false)
+            .collect(),
+        None => default_url.into_iter().collect(),
+    };
+
+    if replicas.is_empty() == true {
+        warn!(
+            "discovery ({}): ignored {} with no url: {}/{}",
+            SOURCE, kind, metadata.namespace, metadata.name
+        );
+
+        return None;
+    }
+
+    Some(DiscoveredNode {
+        id: format!("{}-{}", kind, metadata.name),
+        label: metadata
+            .annotations
+            .get(ANNOTATION_LABEL)
+            .cloned()
+            .unwrap_or_else(|| metadata.name.to_owned()),
+        replicas: replicas,
+    })
+}
+
+fn request<T: DeserializeOwned>(client: &Client, token: &str, url: &str) -> Option<T> {
+    debug!("discovery ({}): requesting: {}", SOURCE, url);
+
+    let response = client
+        .get(url)
+        .header(AUTHORIZATION, format!("Bearer {}", token))
+        .send();
+
+    match response {
+        Ok(response_inner) if response_inner.status().is_success() == true => {
+            match response_inner.json() {
+                Ok(data) => Some(data),
+                Err(err) => {
+                    error!("could not parse kubernetes response: {} ({})", url, err);
+
+                    None
+                }
+            }
+        }
+        Ok(response_inner) => {
+            error!(
+                "got kubernetes error response: {} ({})",
+                url,
+                response_inner.status()
+            );
+
+            None
+        }
+        Err(err) => {
+            error!("could not request kubernetes: {} ({})", url, err);
+
+            None
+        }
+    }
+}
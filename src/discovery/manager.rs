@@ -0,0 +1,224 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::collections::HashMap;
+use std::mem;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use indexmap::IndexMap;
+
+use super::{consul, docker, kubernetes};
+use crate::config::config::{ConfigProbeService, ConfigProbeServiceNode};
+use crate::prober::manager::{
+    carry_probe_states, make_probe, publish_snapshot, STORE as PROBER_STORE,
+};
+use crate::prober::mode::Mode;
+use crate::prober::replica::ReplicaURL;
+use crate::prober::severity::Severity;
+use crate::prober::status::Status;
+use crate::APP_CONF;
+
+const DISCOVERY_TICK_SECONDS: u64 = 5;
+
+type DiscoverFn = fn() -> Option<Vec<DiscoveredService>>;
+
+pub struct DiscoveredService {
+    pub id: String,
+    pub label: String,
+    pub nodes: Vec<DiscoveredNode>,
+}
+
+pub struct DiscoveredNode {
+    pub id: String,
+    pub label: String,
+    pub replicas: Vec<String>,
+}
+
+pub fn synchronize(source: &str, services: Vec<DiscoveredService>) {
+    let mut store = PROBER_STORE.write().unwrap();
+
+    let (mut added, mut removed) = (0, 0);
+
+    // Forget about services that are not discovered anymore
+    let service_ids: Vec<&str> = services.iter().map(|service| service.id.as_str()).collect();
+
+    store.states.probes.retain(|probe_id, probe| {
+        let is_gone = probe.discovered_by.as_deref() == Some(source)
+            && service_ids.contains(&probe_id.as_str()) == false;
+
+        if is_gone == true {
+            debug!("discovery ({}): removed service {}", source, probe_id);
+
+            removed += 1;
+        }
+
+        is_gone == false
+    });
+
+    // Insert or refresh discovered services
+    for service in services {
+        // Notice: services defined in the configuration (or discovered from another source) \
+        //   always take precedence over newly-discovered services with the same identifier.
+        if let Some(probe) = store.states.probes.get(&service.id) {
+            if probe.discovered_by.as_deref() != Some(source) {
+                warn!(
+                    "discovery ({}): ignored service {} (identifier already in use)",
+                    source, service.id
+                );
+
+                continue;
+            }
+        }
+
+        let mut probe = make_probe(&make_config_service(source, service), &|_, _, _| {
+            Status::Healthy
+        });
+
+        probe.discovered_by = Some(source.to_owned());
+
+        if let Some(existing_probe) = store.states.probes.get_mut(&probe.id) {
+            // Carry over states from the previous discovery run
+            let previous_probe = mem::replace(existing_probe, probe);
+
+            carry_probe_states(existing_probe, previous_probe);
+        } else {
+            debug!("discovery ({}): added service {}", source, probe.id);
+
+            added += 1;
+
+            store.states.probes.insert(probe.id.to_owned(), probe);
+        }
+    }
+
+    publish_snapshot(&store);
+
+    info!(
+        "synchronized discovered services from: {} (added: {}, removed: {})",
+        source, added, removed
+    );
+}
+
+fn make_config_service(source: &str, service: DiscoveredService) -> ConfigProbeService {
+    ConfigProbeService {
+        id: service.id.to_owned(),
+        label: service.label,
+        groups: vec![source.to_owned()],
+        depends_on: Vec::new(),
+        severity: Severity::Critical,
+        reporter_secret: None,
+        latency_sick_ms: None,
+        latency_dead_ms: None,
+        retries: None,
+        retry_delay: None,
+        resolve: None,
+        dns_resolver: None,
+        notify: None,
+        escalation: None,
+        opsgenie_teams: None,
+        opsgenie_priority: None,
+        metadata: IndexMap::new(),
+        node: service
+            .nodes
+            .into_iter()
+            .map(|node| {
+                // Only retain replicas that can be polled
+                let replicas = node
+                    .replicas
+                    .into_iter()
+                    .filter(|replica| {
+                        let is_valid = ReplicaURL::parse_from(replica).is_ok();
+
+                        if is_valid == false {
+                            warn!(
+                                "discovery ({}): ignored invalid replica url: {} in node: {}:{}",
+                                source, replica, service.id, node.id
+                            );
+                        }
+
+                        is_valid
+                    })
+                    .collect();
+
+                ConfigProbeServiceNode {
+                    id: node.id,
+                    label: node.label,
+                    mode: Mode::Poll,
+                    replicas: Some(replicas),
+                    ..ConfigProbeServiceNode::default()
+                }
+            })
+            .collect(),
+    }
+}
+
+fn list_sources() -> Vec<(&'static str, u64, DiscoverFn)> {
+    let mut sources: Vec<(&'static str, u64, DiscoverFn)> = Vec::new();
+
+    if let Some(ref discovery) = APP_CONF.discovery {
+        if let Some(ref kubernetes) = discovery.kubernetes {
+            sources.push((
+                kubernetes::SOURCE,
+                kubernetes.refresh_interval,
+                kubernetes::discover,
+            ));
+        }
+
+        if let Some(ref consul) = discovery.consul {
+            sources.push((consul::SOURCE, consul.refresh_interval, consul::discover));
+        }
+
+        if let Some(ref docker) = discovery.docker {
+            sources.push((docker::SOURCE, docker.refresh_interval, docker::discover));
+        }
+    }
+
+    sources
+}
+
+pub fn run() {
+    let mut refreshed_at: HashMap<&'static str, Instant> = HashMap::new();
+
+    loop {
+        // Notice: sources are listed upon each tick, as they might change upon reload
+        let sources = list_sources();
+
+        for (source, refresh_interval, discover) in sources.iter() {
+            let should_refresh = refreshed_at
+                .get(source)
+                .map(|refreshed_at| refreshed_at.elapsed().as_secs() >= *refresh_interval)
+                .unwrap_or(true);
+
+            if should_refresh == true {
+                debug!("running a discovery operation on source: {}...", source);
+
+                // Synchronize discovered services (keep previous services if discovery failed)
+                if let Some(services) = discover() {
+                    synchronize(source, services);
+                } else {
+                    error!("could not discover services from source: {}", source);
+                }
+
+                refreshed_at.insert(*source, Instant::now());
+            }
+        }
+
+        // Forget about services from sources that are not configured anymore
+        refreshed_at.retain(|source, _| {
+            let is_configured = sources
+                .iter()
+                .any(|(configured_source, _, _)| configured_source == source);
+
+            if is_configured == false {
+                synchronize(source, Vec::new());
+            }
+
+            is_configured
+        });
+
+        thread::sleep(Duration::from_secs(DISCOVERY_TICK_SECONDS));
+    }
+}
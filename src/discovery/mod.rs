@@ -0,0 +1,11 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+mod consul;
+mod docker;
+mod kubernetes;
+
+pub mod manager;
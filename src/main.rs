@@ -13,36 +13,58 @@ extern crate serde_derive;
 
 mod aggregator;
 mod config;
+mod discovery;
 mod notifier;
 mod prober;
+mod reloader;
 mod responder;
+mod storage;
+mod subscriber;
+mod tracer;
 
 use std::ops::Deref;
-use std::str::FromStr;
+use std::process;
 use std::thread;
 use std::time::Duration;
 
 use clap::{App, Arg};
-use log::LevelFilter;
 
 use crate::aggregator::manager::run as run_aggregator;
-use crate::config::config::Config;
 use crate::config::logger::ConfigLogger;
 use crate::config::reader::ConfigReader;
+use crate::config::shared::SharedConfig;
+use crate::discovery::manager::run as run_discovery;
+use crate::notifier::escalation::run as run_notifier_escalation;
+use crate::notifier::queue::{initialize as initialize_notifier_queue, run as run_notifier_queue};
+use crate::notifier::tester::run as run_notifier_tester;
 use crate::prober::manager::{
     initialize_store as initialize_store_prober, run_poll as run_poll_prober,
-    run_script as run_script_prober,
+    run_publish as run_publish_prober, run_script as run_script_prober,
 };
+use crate::reloader::manager::run as run_reloader;
 use crate::responder::manager::run as run_responder;
+use crate::responder::tls::run_reload as run_responder_tls;
+use crate::storage::sqlite::initialize as initialize_storage;
+use crate::tracer::exporter::run as run_tracer_exporter;
 
 struct AppArgs {
     config: String,
+    check: bool,
+    test_notifiers: bool,
 }
 
 pub static THREAD_NAME_PROBER_POLL: &'static str = "vigil-prober-poll";
+pub static THREAD_NAME_PROBER_POLL_WORKER: &'static str = "vigil-prober-poll-worker";
 pub static THREAD_NAME_PROBER_SCRIPT: &'static str = "vigil-prober-script";
+pub static THREAD_NAME_PROBER_PUBLISH: &'static str = "vigil-prober-publish";
 pub static THREAD_NAME_AGGREGATOR: &'static str = "vigil-aggregator";
+pub static THREAD_NAME_NOTIFIER_QUEUE: &'static str = "vigil-notifier-queue";
+pub static THREAD_NAME_NOTIFIER_ESCALATION: &'static str = "vigil-notifier-escalation";
+pub static THREAD_NAME_RELOADER: &'static str = "vigil-reloader";
+pub static THREAD_NAME_DISCOVERY: &'static str = "vigil-discovery";
 pub static THREAD_NAME_RESPONDER: &'static str = "vigil-responder";
+pub static THREAD_NAME_RESPONDER_TLS: &'static str = "vigil-responder-tls";
+pub static THREAD_NAME_TRACER_EXPORTER: &'static str = "vigil-tracer-exporter";
 
 macro_rules! gen_spawn_managed {
     ($name:expr, $method:ident, $thread_name:ident, $managed_fn:ident) => {
@@ -75,7 +97,7 @@ macro_rules! gen_spawn_managed {
 
 lazy_static! {
     static ref APP_ARGS: AppArgs = make_app_args();
-    static ref APP_CONF: Config = ConfigReader::make();
+    static ref APP_CONF: SharedConfig = SharedConfig::new(ConfigReader::make());
 }
 
 gen_spawn_managed!(
@@ -90,12 +112,54 @@ gen_spawn_managed!(
     THREAD_NAME_PROBER_SCRIPT,
     run_script_prober
 );
+gen_spawn_managed!(
+    "prober-publish",
+    spawn_publish_prober,
+    THREAD_NAME_PROBER_PUBLISH,
+    run_publish_prober
+);
 gen_spawn_managed!(
     "aggregator",
     spawn_aggregator,
     THREAD_NAME_AGGREGATOR,
     run_aggregator
 );
+gen_spawn_managed!(
+    "notifier-queue",
+    spawn_notifier_queue,
+    THREAD_NAME_NOTIFIER_QUEUE,
+    run_notifier_queue
+);
+gen_spawn_managed!(
+    "notifier-escalation",
+    spawn_notifier_escalation,
+    THREAD_NAME_NOTIFIER_ESCALATION,
+    run_notifier_escalation
+);
+gen_spawn_managed!(
+    "discovery",
+    spawn_discovery,
+    THREAD_NAME_DISCOVERY,
+    run_discovery
+);
+gen_spawn_managed!(
+    "reloader",
+    spawn_reloader,
+    THREAD_NAME_RELOADER,
+    run_reloader
+);
+gen_spawn_managed!(
+    "responder-tls",
+    spawn_responder_tls,
+    THREAD_NAME_RESPONDER_TLS,
+    run_responder_tls
+);
+gen_spawn_managed!(
+    "tracer-exporter",
+    spawn_tracer_exporter,
+    THREAD_NAME_TRACER_EXPORTER,
+    run_tracer_exporter
+);
 
 gen_spawn_managed!(
     "responder",
@@ -117,11 +181,23 @@ fn make_app_args() -> AppArgs {
                 .default_value("./config.cfg")
                 .takes_value(true),
         )
+        .arg(
+            Arg::new("check")
+                .long("check")
+                .help("Check configuration file and exit (does not start Vigil)"),
+        )
+        .arg(
+            Arg::new("test-notifiers")
+                .long("test-notifiers")
+                .help("Send a test notification through all configured notifiers and exit"),
+        )
         .get_matches();
 
     // Generate owned app arguments
     AppArgs {
         config: String::from(matches.value_of("config").expect("invalid config value")),
+        check: matches.is_present("check"),
+        test_notifiers: matches.is_present("test-notifiers"),
     }
 }
 
@@ -138,30 +214,117 @@ fn ensure_states() {
     );
 }
 
+fn check_config() -> i32 {
+    // Check configuration (without binding to any port, or starting any probe)
+    match ConfigReader::read() {
+        Ok(_) => {
+            println!("configuration is valid: {}", APP_ARGS.config);
+
+            0
+        }
+        Err(errors) => {
+            for error in errors.iter() {
+                eprintln!("error: {}", error);
+            }
+
+            eprintln!(
+                "configuration is invalid: {} ({} errors)",
+                APP_ARGS.config,
+                errors.len()
+            );
+
+            1
+        }
+    }
+}
+
+fn test_notifiers() -> i32 {
+    // Send test notifications (without starting any probe, nor binding to any port)
+    let outcomes = run_notifier_tester(None);
+
+    if outcomes.is_empty() == true {
+        eprintln!("no notifier is configured");
+
+        return 1;
+    }
+
+    for outcome in outcomes.iter() {
+        println!(
+            "{}: {} ({})",
+            outcome.channel,
+            outcome.notifier,
+            if outcome.success == true {
+                "sent"
+            } else {
+                "failed"
+            }
+        );
+    }
+
+    if outcomes.iter().all(|outcome| outcome.success == true) == true {
+        0
+    } else {
+        1
+    }
+}
+
 fn main() {
+    // Check configuration and exit? (dry-run mode)
+    if APP_ARGS.check == true {
+        process::exit(check_config());
+    }
+
     // Ensure OpenSSL root chain is found on current environment
     openssl_probe::init_ssl_cert_env_vars();
 
     // Initialize shared logger
-    let _logger = ConfigLogger::init(
-        LevelFilter::from_str(&APP_CONF.server.log_level).expect("invalid log level"),
-    );
+    let _logger = ConfigLogger::init();
+
+    // Send test notifications and exit?
+    if APP_ARGS.test_notifiers == true {
+        process::exit(test_notifiers());
+    }
 
     info!("starting up");
 
     // Ensure all states are bound
     ensure_states();
 
+    // Initialize storage (if enabled)
+    initialize_storage();
+
     // Initialize prober store
     initialize_store_prober();
 
+    // Initialize notifier queue (restores pending notifications, if persisted)
+    initialize_notifier_queue();
+
     // Spawn probes (background thread)
     thread::spawn(spawn_poll_prober);
     thread::spawn(spawn_script_prober);
+    thread::spawn(spawn_publish_prober);
 
     // Spawn aggregator (background thread)
     thread::spawn(spawn_aggregator);
 
+    // Spawn notifier queue (background thread)
+    thread::spawn(spawn_notifier_queue);
+
+    // Spawn notifier escalation (background thread)
+    thread::spawn(spawn_notifier_escalation);
+
+    // Spawn discovery (background thread)
+    thread::spawn(spawn_discovery);
+
+    // Spawn reloader (background thread)
+    thread::spawn(spawn_reloader);
+
+    // Spawn TLS certificate reloader (background thread)
+    thread::spawn(spawn_responder_tls);
+
+    // Spawn tracer exporter (background thread)
+    thread::spawn(spawn_tracer_exporter);
+
     // Spawn Web responder (foreground thread)
     spawn_responder();
 
@@ -0,0 +1,176 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+
+use super::generic::{GenericNotifier, Notification, DISPATCH_TIMEOUT_SECONDS};
+use super::template;
+use crate::config::config::ConfigNotify;
+use crate::prober::status::Status;
+use crate::APP_CONF;
+
+lazy_static! {
+    static ref APPRISE_HTTP_CLIENT: Client = Client::builder()
+        .timeout(Duration::from_secs(DISPATCH_TIMEOUT_SECONDS))
+        .gzip(true)
+        .build()
+        .unwrap();
+}
+
+pub struct AppriseNotifier;
+
+#[derive(Serialize)]
+struct ApprisePayload<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    urls: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tag: Option<&'a str>,
+
+    title: &'a str,
+    body: &'a str,
+
+    #[serde(rename = "type")]
+    kind: &'static str,
+
+    format: &'static str,
+}
+
+impl GenericNotifier for AppriseNotifier {
+    fn attempt(notify: &ConfigNotify, notification: &Notification) -> Result<(), bool> {
+        if let Some(ref apprise) = notify.apprise {
+            // Build up the message text
+            let mut message = String::new();
+
+            if notification.test == true {
+                message.push_str("This is a test alert.\n\n");
+            } else if notification.startup == true {
+                message.push_str("This is a startup alert.\n\n");
+            } else if let Some(probe_id) = notification.flapping {
+                message.push_str(&format!("Service is flapping: {}.\n\n", probe_id));
+            } else if let Some(held) = notification.held {
+                message.push_str(&format!(
+                    "Quiet hours are over, {} notification(s) were held back.\n\n",
+                    held
+                ));
+            } else if let Some(suppressed) = notification.suppressed {
+                message.push_str(&format!(
+                    "Rate limit is over, {} notification(s) were suppressed.\n\n",
+                    suppressed
+                ));
+            } else if let Some(digest_label) = notification.digest_label() {
+                message.push_str(&format!("{}.\n\n", digest_label));
+            } else if notification.changed == false {
+                message.push_str(&format!(
+                    "This is a reminder{}.\n\n",
+                    notification.reminder_suffix()
+                ));
+            }
+
+            message.push_str(&format!(
+                "Status: {}\n",
+                notification.status.as_str().to_uppercase()
+            ));
+
+            if notification.replicas.len() > 0 {
+                message.push_str(&format!("Nodes:\n{}\n", &notification.replicas.join("\n")));
+            }
+
+            message.push_str(&format!("Time: {}\n", &notification.time));
+
+            for acknowledge_url in notification.acknowledge_urls() {
+                message.push_str(&format!("Acknowledge: {}\n", acknowledge_url));
+            }
+
+            message.push_str(&format!("URL: {}", APP_CONF.branding.page_url.as_str()));
+
+            // Apply channel template (if any)
+            let rendered = template::render(notify, notification);
+
+            if let Some(body) = rendered.body {
+                message = body;
+            }
+
+            debug!("will send apprise notification with message: {}", &message);
+
+            // Generate URL
+            // See: https://github.com/caronc/apprise-api#api-details
+            // Notice: stateless notifications hold target URLs in their payload, while stateful \
+            //   notifications refer to the target URLs stored on the server under a key.
+            let server_url = apprise.server_url.as_str().trim_end_matches('/');
+
+            let url = match apprise.config_key {
+                Some(ref config_key) => format!("{}/notify/{}", server_url, config_key),
+                None => format!("{}/notify", server_url),
+            };
+
+            // Build payload
+            let title = format!(
+                "{} {} | {}",
+                notification.status.as_icon(),
+                notification.status.as_str().to_uppercase(),
+                APP_CONF.branding.page_title
+            );
+
+            let payload = ApprisePayload {
+                urls: if apprise.config_key.is_none() == true {
+                    Some(apprise.urls.join(", "))
+                } else {
+                    None
+                },
+                tag: apprise.tag.as_deref(),
+                title: rendered.subject.as_deref().unwrap_or(&title),
+                body: &message,
+                kind: if notification.test == true {
+                    "info"
+                } else {
+                    status_to_type(&notification.status)
+                },
+                format: "text",
+            };
+
+            // Submit payload to Apprise
+            let response = APPRISE_HTTP_CLIENT.post(&url).json(&payload).send();
+
+            if let Ok(response_inner) = response {
+                if response_inner.status().is_success() == true {
+                    return Ok(());
+                }
+
+                warn!(
+                    "apprise rejected notification, got status: {}",
+                    response_inner.status()
+                );
+            }
+
+            return Err(true);
+        }
+
+        Err(false)
+    }
+
+    fn can_notify(notify: &ConfigNotify, notification: &Notification) -> bool {
+        if let Some(ref apprise_config) = notify.apprise {
+            notification.expected(apprise_config.reminders_only)
+        } else {
+            false
+        }
+    }
+
+    fn name() -> &'static str {
+        "apprise"
+    }
+}
+
+fn status_to_type(status: &Status) -> &'static str {
+    match status {
+        &Status::Healthy => "success",
+        &Status::Sick => "warning",
+        &Status::Dead => "failure",
+    }
+}
@@ -0,0 +1,204 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+
+use super::generic::{GenericNotifier, Notification, DISPATCH_TIMEOUT_SECONDS};
+use super::template;
+use crate::config::config::ConfigNotify;
+use crate::prober::status::Status;
+use crate::APP_CONF;
+
+// Notice: Discord rejects embeds with more than 25 fields, keep room for the general fields
+const EMBED_REPLICA_FIELDS_MAXIMUM: usize = 20;
+
+lazy_static! {
+    static ref DISCORD_HTTP_CLIENT: Client = Client::builder()
+        .timeout(Duration::from_secs(DISPATCH_TIMEOUT_SECONDS))
+        .gzip(true)
+        .build()
+        .unwrap();
+}
+
+pub struct DiscordNotifier;
+
+#[derive(Serialize)]
+struct DiscordPayload<'a> {
+    username: &'a str,
+    embeds: Vec<DiscordPayloadEmbed<'a>>,
+}
+
+#[derive(Serialize)]
+struct DiscordPayloadEmbed<'a> {
+    title: String,
+    url: &'a str,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+
+    color: u32,
+    fields: Vec<DiscordPayloadEmbedField>,
+}
+
+#[derive(Serialize)]
+struct DiscordPayloadEmbedField {
+    name: String,
+    value: String,
+    inline: bool,
+}
+
+impl GenericNotifier for DiscordNotifier {
+    fn attempt(notify: &ConfigNotify, notification: &Notification) -> Result<(), bool> {
+        if let Some(ref discord) = notify.discord {
+            // Build embed title
+            let title = if notification.test == true {
+                format!(
+                    "This is a test alert, status is: {}.",
+                    notification.status.as_str()
+                )
+            } else if notification.startup == true {
+                format!("Status started up, as: {}.", notification.status.as_str())
+            } else if let Some(probe_id) = notification.flapping {
+                format!("Service is flapping: {}.", probe_id)
+            } else if let Some(held) = notification.held {
+                format!(
+                    "Quiet hours are over ({} held back), status is: {}.",
+                    held,
+                    notification.status.as_str()
+                )
+            } else if let Some(suppressed) = notification.suppressed {
+                format!(
+                    "Rate limit is over ({} suppressed), status is: {}.",
+                    suppressed,
+                    notification.status.as_str()
+                )
+            } else if let Some(digest_label) = notification.digest_label() {
+                format!(
+                    "Status changed to: {} ({}).",
+                    notification.status.as_str(),
+                    digest_label
+                )
+            } else if notification.changed == true {
+                format!("Status changed to: {}.", notification.status.as_str())
+            } else {
+                format!(
+                    "Status is still: {} (reminder{}).",
+                    notification.status.as_str(),
+                    notification.reminder_suffix()
+                )
+            };
+
+            // Apply channel template (if any)
+            let rendered = template::render(notify, notification);
+
+            let mut embed = DiscordPayloadEmbed {
+                title: rendered.subject.unwrap_or(title),
+                url: APP_CONF.branding.page_url.as_str(),
+                description: rendered.body,
+                color: status_to_color(&notification.status),
+                fields: Vec::new(),
+            };
+
+            // Append one field per replica (service name as name, replica URL as value)
+            for replica in notification
+                .replicas
+                .iter()
+                .take(EMBED_REPLICA_FIELDS_MAXIMUM)
+            {
+                let mut replica_parts = replica.splitn(3, ':');
+
+                if let (Some(probe_id), Some(node_id), Some(replica_id)) = (
+                    replica_parts.next(),
+                    replica_parts.next(),
+                    replica_parts.next(),
+                ) {
+                    embed.fields.push(DiscordPayloadEmbedField {
+                        name: format!("{} / {}", probe_id, node_id),
+                        value: format!("`{}`", replica_id),
+                        inline: false,
+                    });
+                }
+            }
+
+            if notification.replicas.len() > EMBED_REPLICA_FIELDS_MAXIMUM {
+                embed.fields.push(DiscordPayloadEmbedField {
+                    name: "Other replicas".to_string(),
+                    value: format!(
+                        "{} more",
+                        notification.replicas.len() - EMBED_REPLICA_FIELDS_MAXIMUM
+                    ),
+                    inline: false,
+                });
+            }
+
+            embed.fields.push(DiscordPayloadEmbedField {
+                name: "Status".to_string(),
+                value: format!("{:?}", notification.status),
+                inline: true,
+            });
+
+            embed.fields.push(DiscordPayloadEmbedField {
+                name: "Time".to_string(),
+                value: notification.time.to_owned(),
+                inline: true,
+            });
+
+            let acknowledge_urls = notification.acknowledge_urls();
+
+            if acknowledge_urls.is_empty() == false {
+                embed.fields.push(DiscordPayloadEmbedField {
+                    name: "Acknowledge".to_string(),
+                    value: acknowledge_urls.join("\n"),
+                    inline: false,
+                });
+            }
+
+            // Build payload
+            let payload = DiscordPayload {
+                username: &APP_CONF.branding.page_title,
+                embeds: vec![embed],
+            };
+
+            // Submit payload to Discord
+            let response = DISCORD_HTTP_CLIENT
+                .post(discord.hook_url.as_str())
+                .json(&payload)
+                .send();
+
+            if let Ok(response_inner) = response {
+                if response_inner.status().is_success() == true {
+                    return Ok(());
+                }
+            }
+
+            return Err(true);
+        }
+
+        Err(false)
+    }
+
+    fn can_notify(notify: &ConfigNotify, notification: &Notification) -> bool {
+        if let Some(ref discord_config) = notify.discord {
+            notification.expected(discord_config.reminders_only)
+        } else {
+            false
+        }
+    }
+
+    fn name() -> &'static str {
+        "discord"
+    }
+}
+
+fn status_to_color(status: &Status) -> u32 {
+    match status {
+        &Status::Healthy => 0x0EB033,
+        &Status::Sick => 0xF18000,
+        &Status::Dead => 0xE10000,
+    }
+}
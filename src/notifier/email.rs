@@ -4,85 +4,191 @@
 // Copyright: 2018, Valerian Saliou <valerian@valeriansaliou.name>
 // License: Mozilla Public License v2.0 (MPL v2.0)
 
-use std::time::Duration;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
 
-use lettre::smtp::authentication::Credentials;
+use lettre::smtp::authentication::{Credentials, Mechanism};
 use lettre::smtp::client::net::ClientTlsParameters;
 use lettre::smtp::{ClientSecurity, ConnectionReuseParameters, SmtpClient, SmtpTransport};
 use lettre::Transport;
-use lettre_email::EmailBuilder;
+use lettre_email::{Email, EmailBuilder};
 use native_tls::TlsConnector;
+use reqwest::blocking::Client;
 
 use super::generic::{GenericNotifier, Notification, DISPATCH_TIMEOUT_SECONDS};
-use crate::config::config::ConfigNotify;
+use super::template;
+use crate::config::config::{ConfigNotify, ConfigNotifyEmail, ConfigNotifyEmailOAuth2};
+use crate::prober::manager::SNAPSHOT as PROBER_SNAPSHOT;
+use crate::prober::status::Status;
 use crate::APP_CONF;
 
+static EMAIL_HTML_TEMPLATE: &'static str = "email.tera";
+
+const OAUTH2_TOKEN_EXPIRE_MARGIN_SECONDS: u64 = 60;
+const OAUTH2_TOKEN_EXPIRE_DEFAULT_SECONDS: u64 = 3600;
+
+lazy_static! {
+    static ref EMAIL_OAUTH2_HTTP_CLIENT: Client = Client::builder()
+        .timeout(Duration::from_secs(DISPATCH_TIMEOUT_SECONDS))
+        .gzip(true)
+        .build()
+        .unwrap();
+    static ref EMAIL_OAUTH2_TOKENS: Mutex<HashMap<String, OAuth2Token>> =
+        Mutex::new(HashMap::new());
+    static ref EMAIL_TRANSPORTS: Mutex<HashMap<String, PooledTransport>> =
+        Mutex::new(HashMap::new());
+    static ref EMAIL_ALERTED_RECIPIENTS: Mutex<HashMap<String, Vec<String>>> =
+        Mutex::new(HashMap::new());
+}
+
 pub struct EmailNotifier;
 
+struct PooledTransport {
+    transport: SmtpTransport,
+    used_at: SystemTime,
+}
+
+struct OAuth2Token {
+    access_token: String,
+    expires_at: SystemTime,
+}
+
+#[derive(Deserialize)]
+struct OAuth2TokenResponse {
+    access_token: String,
+    expires_in: Option<u64>,
+}
+
 impl GenericNotifier for EmailNotifier {
     fn attempt(notify: &ConfigNotify, notification: &Notification) -> Result<(), bool> {
         if let Some(ref email_config) = notify.email {
             let nodes_label = notification.replicas.join(", ");
 
-            // Build up the message text
-            let mut message = String::new();
-
-            if notification.startup == true {
-                message.push_str(&format!(
-                    "Status startup alert from: {}\n",
+            // Build up the message heading
+            let heading = if notification.test == true {
+                format!("Status test alert from: {}", APP_CONF.branding.page_title)
+            } else if notification.startup == true {
+                format!(
+                    "Status startup alert from: {}",
                     APP_CONF.branding.page_title
-                ));
+                )
+            } else if let Some(probe_id) = notification.flapping {
+                format!(
+                    "Service flapping alert for: {} from: {}",
+                    probe_id, APP_CONF.branding.page_title
+                )
+            } else if let Some(held) = notification.held {
+                format!(
+                    "Status summary after quiet hours ({} held back) from: {}",
+                    held, APP_CONF.branding.page_title
+                )
+            } else if let Some(suppressed) = notification.suppressed {
+                format!(
+                    "Status summary after rate limiting ({} suppressed) from: {}",
+                    suppressed, APP_CONF.branding.page_title
+                )
+            } else if let Some(digest_label) = notification.digest_label() {
+                format!(
+                    "Status change digest ({}) from: {}",
+                    digest_label, APP_CONF.branding.page_title
+                )
             } else if notification.changed == true {
-                message.push_str(&format!(
-                    "Status change report from: {}\n",
+                format!(
+                    "Status change report from: {}",
                     APP_CONF.branding.page_title
-                ));
+                )
             } else {
-                message.push_str(&format!(
-                    "Status unchanged reminder from: {}\n",
+                format!(
+                    "Status unchanged reminder{} from: {}",
+                    notification.reminder_suffix(),
                     APP_CONF.branding.page_title
-                ));
-            }
+                )
+            };
+
+            // Build up the message text
+            let mut message = format!("{}\n", heading);
 
             message.push_str("\n--\n");
             message.push_str(&format!("Status: {:?}\n", notification.status));
             message.push_str(&format!("Nodes: {}\n", &nodes_label));
             message.push_str(&format!("Time: {}\n", &notification.time));
+
+            for acknowledge_url in notification.acknowledge_urls() {
+                message.push_str(&format!("Acknowledge: {}\n", acknowledge_url));
+            }
+
             message.push_str(&format!("URL: {}", APP_CONF.branding.page_url.as_str()));
 
             message.push_str("\n--\n");
+
+            // Append diagnostics captured for dead replicas (if any)
+            for (replica, output) in notification.diagnostics.iter() {
+                message.push_str(&format!("\nDiagnostic for: {}\n\n", replica));
+                message.push_str(output);
+                message.push_str("\n--\n");
+            }
             message.push_str("\n");
             message.push_str("To unsubscribe, please edit your status page configuration.");
 
+            // Apply channel template (if any)
+            let rendered = template::render(notify, notification);
+
+            // Render HTML alternative (if enabled, and body is not templated)
+            let html = if email_config.html == true && rendered.body.is_none() == true {
+                template::render_asset(
+                    EMAIL_HTML_TEMPLATE,
+                    notification,
+                    &[("title", heading.as_str())],
+                )
+            } else {
+                None
+            };
+
+            if let Some(body) = rendered.body {
+                message = body;
+            }
+
             debug!("will send email notification with message: {}", &message);
 
             // Build up the email
-            let email_message = EmailBuilder::new()
-                .to(email_config.to.as_str())
+            let mut email_builder = EmailBuilder::new();
+
+            for recipient in acquire_recipients(email_config, notification).iter() {
+                email_builder = email_builder.to(recipient.as_str());
+            }
+
+            if let Some(ref reply_to) = email_config.reply_to {
+                email_builder = email_builder.reply_to(reply_to.as_str());
+            }
+
+            email_builder = email_builder
                 .from((
                     email_config.from.as_str(),
                     APP_CONF.branding.page_title.as_str(),
                 ))
-                .subject(format!(
-                    "{} | {}",
-                    notification.status.as_str().to_uppercase(),
-                    &nodes_label
-                ))
-                .text(message)
-                .build()
-                .or(Err(true))?;
+                .subject(if let Some(subject) = rendered.subject {
+                    subject
+                } else if notification.test == true {
+                    format!("TEST | {}", APP_CONF.branding.page_title)
+                } else {
+                    format!(
+                        "{} | {}",
+                        notification.status.as_str().to_uppercase(),
+                        &nodes_label
+                    )
+                });
+
+            let email_message = match html {
+                Some(html) => email_builder.alternative(html, message),
+                None => email_builder.text(message),
+            }
+            .build()
+            .or(Err(true))?;
 
             // Deliver the message
-            return acquire_transport(
-                &email_config.smtp_host,
-                email_config.smtp_port,
-                email_config.smtp_username.to_owned(),
-                email_config.smtp_password.to_owned(),
-                email_config.smtp_encrypt,
-            )
-            .map(|mut transport| transport.send(email_message.into()))
-            .and(Ok(()))
-            .or(Err(true));
+            return deliver(email_config, notification.channel, email_message);
         }
 
         Err(false)
@@ -101,16 +207,188 @@ impl GenericNotifier for EmailNotifier {
     }
 }
 
-fn acquire_transport(
-    smtp_host: &str,
-    smtp_port: u16,
-    smtp_username: Option<String>,
-    smtp_password: Option<String>,
-    smtp_encrypt: bool,
-) -> Result<SmtpTransport, ()> {
+fn acquire_recipients(
+    email_config: &ConfigNotifyEmail,
+    notification: &Notification,
+) -> Vec<String> {
+    let mut recipients = vec![email_config.to.to_owned()];
+
+    if email_config.to_groups.is_empty() == true || notification.test == true {
+        return recipients;
+    }
+
+    let snapshot = PROBER_SNAPSHOT.load();
+
+    // Acquire groups of affected services (including services that flapped or changed within a \
+    //   digest)
+    let probe_ids = notification
+        .replicas
+        .iter()
+        .map(|replica| replica.split(':').next().unwrap_or(""))
+        .chain(notification.flapping)
+        .chain(
+            notification
+                .digest
+                .iter()
+                .flatten()
+                .map(|(probe_id, _)| *probe_id),
+        );
+
+    let mut group_recipients: Vec<String> = Vec::new();
+
+    for probe_id in probe_ids {
+        let groups = snapshot
+            .states
+            .probes
+            .get(probe_id)
+            .map(|probe| probe.groups.as_slice())
+            .unwrap_or_default();
+
+        for group in groups {
+            for address in email_config.to_groups.get(group).into_iter().flatten() {
+                if group_recipients.contains(address) == false {
+                    group_recipients.push(address.to_owned());
+                }
+            }
+        }
+    }
+
+    // Notice: recoveries are not tied to any replica, thus group recipients are remembered \
+    //   until the channel recovers, so that they get notified of the recovery as well.
+    let mut alerted_recipients = EMAIL_ALERTED_RECIPIENTS.lock().unwrap();
+
+    let channel_alerted_recipients = alerted_recipients
+        .entry(notification.channel.to_string())
+        .or_insert_with(Vec::new);
+
+    for address in channel_alerted_recipients.iter() {
+        if group_recipients.contains(address) == false {
+            group_recipients.push(address.to_owned());
+        }
+    }
+
+    if notification.status == &Status::Healthy {
+        channel_alerted_recipients.clear();
+    } else {
+        *channel_alerted_recipients = group_recipients.clone();
+    }
+
+    for address in group_recipients {
+        if recipients.contains(&address) == false {
+            recipients.push(address);
+        }
+    }
+
+    recipients
+}
+
+fn deliver(
+    email_config: &ConfigNotifyEmail,
+    channel: &str,
+    email_message: Email,
+) -> Result<(), bool> {
+    // Connection reuse disabled? (use a fresh connection for each email)
+    if email_config.smtp_reuse_timeout == 0 {
+        let mut transport = acquire_transport(email_config, false).or(Err(true))?;
+
+        return match transport.send(email_message.into()) {
+            Ok(_) => Ok(()),
+            Err(err) => {
+                report_failure(email_config, err);
+
+                Err(true)
+            }
+        };
+    }
+
+    let pool_key = format!(
+        "{}|{}:{}|{}",
+        channel,
+        email_config.smtp_host,
+        email_config.smtp_port,
+        email_config.smtp_username.as_deref().unwrap_or("")
+    );
+
+    // Notice: the pooled connection is taken out of the pool while in use, so that the pool is \
+    //   not locked while sending, and so that a connection is never used by two senders at once.
+    let pooled = EMAIL_TRANSPORTS.lock().unwrap().remove(&pool_key);
+
+    let pooled = match pooled {
+        Some(mut pooled) => {
+            if pooled.used_at.elapsed().unwrap_or_default()
+                < Duration::from_secs(email_config.smtp_reuse_timeout)
+            {
+                Some(pooled.transport)
+            } else {
+                debug!("closing idle smtp connection for channel: {}", channel);
+
+                pooled.transport.close();
+
+                None
+            }
+        }
+        None => None,
+    };
+
+    let (mut transport, is_reused) = match pooled {
+        Some(transport) => (transport, true),
+        None => (acquire_transport(email_config, true).or(Err(true))?, false),
+    };
+
+    let mut result = transport.send(email_message.clone().into());
+
+    // Reused connection might have been closed by the SMTP server in the meantime (retry once \
+    //   over a fresh connection)
+    if result.is_err() == true && is_reused == true {
+        debug!("reused smtp connection failed, retrying over a fresh connection");
+
+        transport.close();
+
+        transport = acquire_transport(email_config, true).or(Err(true))?;
+        result = transport.send(email_message.into());
+    }
+
+    match result {
+        Ok(_) => {
+            EMAIL_TRANSPORTS.lock().unwrap().insert(
+                pool_key,
+                PooledTransport {
+                    transport: transport,
+                    used_at: SystemTime::now(),
+                },
+            );
+
+            Ok(())
+        }
+        Err(err) => {
+            report_failure(email_config, err);
+
+            transport.close();
+
+            Err(true)
+        }
+    }
+}
+
+fn report_failure(email_config: &ConfigNotifyEmail, err: impl Display) {
+    warn!("could not send email over smtp: {}", err);
+
+    // Notice: the access token might have been revoked before it expired, thus it gets acquired \
+    //   again for the next email, in case the failure is an authentication failure.
+    if let Some(ref oauth2) = email_config.smtp_oauth2 {
+        EMAIL_OAUTH2_TOKENS
+            .lock()
+            .unwrap()
+            .remove(&oauth2_token_key(oauth2));
+    }
+}
+
+fn acquire_transport(email_config: &ConfigNotifyEmail, reuse: bool) -> Result<SmtpTransport, ()> {
+    let smtp_host = &email_config.smtp_host;
+
     let mut security = ClientSecurity::None;
 
-    if smtp_encrypt == true {
+    if email_config.smtp_encrypt == true {
         let connector_builder = TlsConnector::builder();
         if let Ok(connector) = connector_builder.build() {
             security = ClientSecurity::Required(ClientTlsParameters {
@@ -128,16 +406,40 @@ fn acquire_transport(
         }
     }
 
-    match SmtpClient::new(format!("{}:{}", smtp_host, smtp_port), security) {
+    match SmtpClient::new(
+        format!("{}:{}", smtp_host, email_config.smtp_port),
+        security,
+    ) {
         Ok(transport) => {
             let mut transport_builder = transport
                 .timeout(Some(Duration::from_secs(DISPATCH_TIMEOUT_SECONDS)))
-                .connection_reuse(ConnectionReuseParameters::NoReuse);
+                .connection_reuse(if reuse == true {
+                    ConnectionReuseParameters::ReuseUnlimited
+                } else {
+                    ConnectionReuseParameters::NoReuse
+                });
+
+            match (
+                &email_config.smtp_username,
+                &email_config.smtp_password,
+                &email_config.smtp_oauth2,
+            ) {
+                (Some(smtp_username_value), _, Some(smtp_oauth2_value)) => {
+                    // Authenticate with an OAuth2 access token (XOAUTH2), in place of a password
+                    let access_token = acquire_oauth2_token(smtp_oauth2_value)?;
 
-            match (smtp_username, smtp_password) {
-                (Some(smtp_username_value), Some(smtp_password_value)) => {
                     transport_builder = transport_builder
-                        .credentials(Credentials::new(smtp_username_value, smtp_password_value));
+                        .credentials(Credentials::new(
+                            smtp_username_value.to_owned(),
+                            access_token,
+                        ))
+                        .authentication_mechanism(Mechanism::Xoauth2);
+                }
+                (Some(smtp_username_value), Some(smtp_password_value), None) => {
+                    transport_builder = transport_builder.credentials(Credentials::new(
+                        smtp_username_value.to_owned(),
+                        smtp_password_value.to_owned(),
+                    ));
                 }
                 _ => {}
             }
@@ -151,3 +453,91 @@ fn acquire_transport(
         }
     }
 }
+
+fn acquire_oauth2_token(oauth2: &ConfigNotifyEmailOAuth2) -> Result<String, ()> {
+    let token_key = oauth2_token_key(oauth2);
+
+    // Reuse cached access token? (if it does not expire soon)
+    if let Some(token) = EMAIL_OAUTH2_TOKENS.lock().unwrap().get(&token_key) {
+        if token
+            .expires_at
+            .duration_since(SystemTime::now())
+            .map(|remaining| remaining.as_secs() > OAUTH2_TOKEN_EXPIRE_MARGIN_SECONDS)
+            .unwrap_or(false)
+            == true
+        {
+            return Ok(token.access_token.to_owned());
+        }
+    }
+
+    debug!(
+        "acquiring smtp oauth2 access token from: {}",
+        oauth2.token_url.as_str()
+    );
+
+    // Acquire a new access token (with the client credentials grant)
+    let mut form = vec![
+        ("grant_type", "client_credentials"),
+        ("client_id", oauth2.client_id.as_str()),
+        ("client_secret", oauth2.client_secret.as_str()),
+    ];
+
+    if let Some(ref scope) = oauth2.scope {
+        form.push(("scope", scope.as_str()));
+    }
+
+    let response = EMAIL_OAUTH2_HTTP_CLIENT
+        .post(oauth2.token_url.as_str())
+        .form(&form)
+        .send();
+
+    let token = match response {
+        Ok(response) if response.status().is_success() == true => {
+            match response.json::<OAuth2TokenResponse>() {
+                Ok(token) => token,
+                Err(err) => {
+                    error!("could not parse smtp oauth2 token response: {}", err);
+
+                    return Err(());
+                }
+            }
+        }
+        Ok(response) => {
+            error!(
+                "could not acquire smtp oauth2 access token, got status: {}",
+                response.status()
+            );
+
+            return Err(());
+        }
+        Err(err) => {
+            error!("could not acquire smtp oauth2 access token: {}", err);
+
+            return Err(());
+        }
+    };
+
+    EMAIL_OAUTH2_TOKENS.lock().unwrap().insert(
+        token_key,
+        OAuth2Token {
+            access_token: token.access_token.to_owned(),
+            expires_at: SystemTime::now()
+                + Duration::from_secs(
+                    token
+                        .expires_in
+                        .unwrap_or(OAUTH2_TOKEN_EXPIRE_DEFAULT_SECONDS),
+                ),
+        },
+    );
+
+    Ok(token.access_token)
+}
+
+fn oauth2_token_key(oauth2: &ConfigNotifyEmailOAuth2) -> String {
+    format!(
+        "{}|{}|{}",
+        oauth2.token_url.as_str(),
+        oauth2.client_id,
+        oauth2.scope.as_deref().unwrap_or("")
+    )
+}
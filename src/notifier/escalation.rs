@@ -0,0 +1,165 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use super::generic::{find_channel, Notification};
+use crate::aggregator::manager::{dispatch_limited, time_now_as_string};
+use crate::config::config::ConfigNotifyEscalation;
+use crate::prober::manager::SNAPSHOT as PROBER_SNAPSHOT;
+use crate::prober::states::ServiceStatesProbe;
+use crate::prober::status::Status;
+use crate::APP_CONF;
+
+const ESCALATION_TICK_INTERVAL_SECONDS: u64 = 5;
+
+lazy_static! {
+    static ref ESCALATIONS: Mutex<HashMap<String, Escalation>> = Mutex::new(HashMap::new());
+}
+
+struct Escalation {
+    dead_since: SystemTime,
+    steps_fired: usize,
+    channels: Vec<String>,
+}
+
+pub fn run() {
+    loop {
+        tick();
+
+        thread::sleep(Duration::from_secs(ESCALATION_TICK_INTERVAL_SECONDS));
+    }
+}
+
+fn tick() {
+    let snapshot = PROBER_SNAPSHOT.load();
+    let mut escalations = ESCALATIONS.lock().unwrap();
+
+    for probe in snapshot.states.probes.values() {
+        let policy = match acquire_policy(&probe.id) {
+            Some(policy) => policy,
+            None => {
+                // Policy got removed upon a configuration reload? (forget about escalation)
+                escalations.remove(&probe.id);
+
+                continue;
+            }
+        };
+
+        if probe.status == Status::Dead {
+            let escalation = escalations
+                .entry(probe.id.to_owned())
+                .or_insert_with(|| Escalation {
+                    dead_since: SystemTime::now(),
+                    steps_fired: 0,
+                    channels: Vec::new(),
+                });
+
+            // Notice: acknowledged, disabled, indirectly affected or flapping probes do not \
+            //   escalate any further (as they do not alert channels either), although their \
+            //   escalation resumes if they are still down once they are not anymore.
+            if probe.acknowledged == true
+                || probe.disabled == true
+                || probe.indirectly_affected == true
+                || probe.flapping == true
+            {
+                continue;
+            }
+
+            let elapsed = escalation.dead_since.elapsed().unwrap_or_default();
+
+            while let Some(step) = policy.steps.get(escalation.steps_fired) {
+                if elapsed < Duration::from_secs(step.after) {
+                    break;
+                }
+
+                info!(
+                    "escalating probe: {} to step #{} (down for {}s)",
+                    probe.id,
+                    escalation.steps_fired + 1,
+                    elapsed.as_secs()
+                );
+
+                for channel_name in step.notify.iter() {
+                    notify(channel_name, probe, &Status::Dead, elapsed);
+
+                    if escalation.channels.contains(channel_name) == false {
+                        escalation.channels.push(channel_name.to_owned());
+                    }
+                }
+
+                escalation.steps_fired += 1;
+            }
+        } else if let Some(escalation) = escalations.remove(&probe.id) {
+            // Notify channels that got escalated to that the probe recovered
+            let elapsed = escalation.dead_since.elapsed().unwrap_or_default();
+
+            for channel_name in escalation.channels.iter() {
+                notify(channel_name, probe, &probe.status, elapsed);
+            }
+        }
+    }
+
+    // Forget about probes that do not exist anymore (eg. undiscovered probes)
+    escalations.retain(|probe_id, _| snapshot.states.probes.contains_key(probe_id));
+}
+
+fn notify(channel_name: &str, probe: &ServiceStatesProbe, status: &Status, downtime: Duration) {
+    let notify = match find_channel(channel_name) {
+        Some(notify) => notify,
+        None => return,
+    };
+
+    // Acquire dead replicas of probe
+    let replicas: Vec<String> = probe
+        .nodes
+        .iter()
+        .flat_map(|(node_id, node)| {
+            node.replicas
+                .iter()
+                .filter(|(_, replica)| replica.status == Status::Dead)
+                .map(move |(replica_id, _)| format!("{}:{}:{}", probe.id, node_id, replica_id))
+        })
+        .collect();
+
+    // Notice: escalation notifications bypass the 'notify_on' filter and quiet hours of the \
+    //   channels they get sent to, as escalating is meant to reach someone whatever the time.
+    let notification = Notification {
+        channel: channel_name,
+        status: status,
+        time: time_now_as_string(),
+        replicas: replicas.iter().map(String::as_str).collect(),
+        diagnostics: Vec::new(),
+        changed: true,
+        startup: false,
+        reminder: None,
+        flapping: None,
+        held: None,
+        suppressed: None,
+        digest: None,
+        downtime: Some(downtime),
+        test: false,
+    };
+
+    dispatch_limited(notify, &notification);
+}
+
+fn acquire_policy(probe_id: &str) -> Option<&'static ConfigNotifyEscalation> {
+    let policy_name = APP_CONF
+        .probe
+        .service
+        .iter()
+        .find(|service| service.id == probe_id)
+        .and_then(|service| service.escalation.as_ref())?;
+
+    APP_CONF
+        .notify
+        .as_ref()
+        .and_then(|notify| notify.escalations.get(policy_name))
+}
@@ -4,22 +4,35 @@
 // Copyright: 2018, Valerian Saliou <valerian@valeriansaliou.name>
 // License: Mozilla Public License v2.0 (MPL v2.0)
 
-use std::thread;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use super::queue;
 use crate::config::config::ConfigNotify;
+use crate::prober::acknowledge;
+use crate::prober::manager::{mark_snapshot_dirty, STORE as PROBER_STORE};
+use crate::prober::severity::Severity;
 use crate::prober::status::Status;
+use crate::tracer::span::{Span, SpanKind};
+use crate::APP_CONF;
 
-const DISPATCH_TRY_WAIT_SECONDS: u64 = 2;
-const DISPATCH_TRY_ATTEMPT_TIMES: u8 = 3;
 pub const DISPATCH_TIMEOUT_SECONDS: u64 = 10;
+pub const DEFAULT_CHANNEL: &'static str = "default";
 
 pub struct Notification<'a> {
+    pub channel: &'a str,
     pub status: &'a Status,
     pub time: String,
     pub replicas: Vec<&'a str>,
+    pub diagnostics: Vec<(&'a str, &'a str)>,
     pub changed: bool,
     pub startup: bool,
+    pub reminder: Option<u16>,
+    pub flapping: Option<&'a str>,
+    pub held: Option<u32>,
+    pub suppressed: Option<u32>,
+    pub digest: Option<Vec<(&'a str, &'a Status)>>,
+    pub downtime: Option<Duration>,
+    pub test: bool,
 }
 
 pub trait GenericNotifier {
@@ -41,27 +54,35 @@ impl<'a> Notification<'a> {
                 notification.replicas
             );
 
-            for try_index in 1..(DISPATCH_TRY_ATTEMPT_TIMES + 1) {
+            // Notifications still pending for this provider? (queue this one behind them)
+            // Notice: this guarantees that notifications get delivered in order, eg. a recovery \
+            //   notification is never received before the downtime alert it follows.
+            if queue::is_pending(N::name(), notification.channel) == true {
                 debug!(
-                    "dispatch {} notification attempt: #{}",
-                    N::name(),
-                    try_index
+                    "queued {} notification behind pending notifications",
+                    N::name()
                 );
 
-                // Hold on for next try
-                if try_index > 1 {
-                    thread::sleep(Duration::from_secs(DISPATCH_TRY_WAIT_SECONDS))
-                }
+                queue::enqueue(N::name(), notification, 0);
 
-                // Attempt notification dispatch
-                if N::attempt(notify, notification).is_ok() == true {
-                    debug!("dispatched notification to provider: {}", N::name());
+                return Err(true);
+            }
 
-                    return Ok(());
-                }
+            // Attempt notification dispatch (failed dispatches are retried from the queue)
+            if Self::attempt::<N>(notify, notification).is_ok() == true {
+                debug!("dispatched notification to provider: {}", N::name());
+
+                Self::count(N::name(), true);
+
+                return Ok(());
             }
 
-            error!("failed dispatching notification to provider: {}", N::name());
+            warn!(
+                "failed dispatching notification to provider: {}, queued for retry",
+                N::name()
+            );
+
+            queue::enqueue(N::name(), notification, 1);
 
             return Err(true);
         }
@@ -71,6 +92,157 @@ impl<'a> Notification<'a> {
         Err(false)
     }
 
+    pub fn attempt<N: GenericNotifier>(
+        notify: &ConfigNotify,
+        notification: &Notification,
+    ) -> Result<(), bool> {
+        // Trace notification dispatch (a slow provider holds back the notifications behind it)
+        let mut span = Span::root("notifier.dispatch", SpanKind::Client);
+
+        span.set_attribute("vigil.notifier", N::name());
+        span.set_attribute("vigil.channel", notification.channel);
+        span.set_attribute("vigil.status", notification.status.as_str());
+
+        let result = N::attempt(notify, notification);
+
+        if result.is_err() == true {
+            span.set_error("notification dispatch failed");
+        }
+
+        span.end();
+
+        result
+    }
+
+    pub fn count(name: &'static str, success: bool) {
+        // Bump dispatch counters for provider (used for metrics)
+        let mut store = PROBER_STORE.write().unwrap();
+        let counter = store.counters.dispatches.entry(name).or_default();
+
+        if success == true {
+            counter.success += 1;
+        } else {
+            counter.failure += 1;
+        }
+
+        mark_snapshot_dirty();
+    }
+
+    pub fn reminder_suffix(&self) -> String {
+        // Reminder number (if this is a reminder), used to tell reminders apart in messages
+        if let Some(reminder) = self.reminder {
+            format!(" #{}", reminder)
+        } else {
+            String::new()
+        }
+    }
+
+    pub fn digest_label(&self) -> Option<String> {
+        // Summary of the transitions grouped in a digest (eg. '2 services went down: a, b')
+        let digest = self.digest.as_ref()?;
+
+        let mut labels = Vec::new();
+
+        for (is_dead, verb) in [(true, "went down"), (false, "recovered")] {
+            let probe_ids: Vec<&str> = digest
+                .iter()
+                .filter(|(_, status)| (*status == &Status::Dead) == is_dead)
+                .map(|(probe_id, _)| *probe_id)
+                .collect();
+
+            if probe_ids.is_empty() == false {
+                labels.push(format!(
+                    "{} service{} {}: {}",
+                    probe_ids.len(),
+                    if probe_ids.len() == 1 { "" } else { "s" },
+                    verb,
+                    probe_ids.join(", ")
+                ));
+            }
+        }
+
+        Some(labels.join("; "))
+    }
+
+    pub fn downtime_label(&self) -> Option<String> {
+        // Human-readable downtime (eg. '2h 5m'), only the two most significant units are kept
+        let seconds = self.downtime?.as_secs();
+
+        let units = [
+            (seconds / 86400, "d"),
+            ((seconds % 86400) / 3600, "h"),
+            ((seconds % 3600) / 60, "m"),
+            (seconds % 60, "s"),
+        ];
+
+        let label: Vec<String> = units
+            .iter()
+            .skip_while(|(value, _)| *value == 0)
+            .take(2)
+            .filter(|(value, _)| *value > 0)
+            .map(|(value, unit)| format!("{}{}", value, unit))
+            .collect();
+
+        if label.is_empty() == true {
+            Some("0s".to_string())
+        } else {
+            Some(label.join(" "))
+        }
+    }
+
+    pub fn acknowledge_urls(&self) -> Vec<String> {
+        // Acknowledgment links require manager routes
+        if APP_CONF.server.manager_token.is_none() == true {
+            return Vec::new();
+        }
+
+        // Notice: links expire after a while, as they might get forwarded (or leak) along with \
+        //   the notification they are part of.
+        let expires = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0)
+            + APP_CONF
+                .notify
+                .as_ref()
+                .map(|notify| notify.acknowledge_link_expiry)
+                .unwrap_or(0);
+
+        self.acknowledge_probes()
+            .into_iter()
+            .filter_map(|probe_id| {
+                let signature = acknowledge::sign_link(probe_id, expires)?;
+
+                Some(format!(
+                    "{}/ack/{}?expires={}&signature={}",
+                    APP_CONF.branding.page_url.as_str().trim_end_matches('/'),
+                    probe_id,
+                    expires,
+                    signature
+                ))
+            })
+            .collect()
+    }
+
+    pub fn acknowledge_probes(&self) -> Vec<&str> {
+        // Acknowledgments are only relevant for downtime alerts
+        if self.status != &Status::Dead {
+            return Vec::new();
+        }
+
+        let mut probe_ids: Vec<&str> = Vec::new();
+
+        for replica in self.replicas.iter() {
+            if let Some(probe_id) = replica.split(':').next() {
+                if probe_ids.contains(&probe_id) == false {
+                    probe_ids.push(probe_id);
+                }
+            }
+        }
+
+        probe_ids
+    }
+
     pub fn expected(&self, reminders_only: bool) -> bool {
         // Notification may not be expected if status has changed, but we only want to receive \
         //   reminders on this specific notifier channel.
@@ -81,3 +253,40 @@ impl<'a> Notification<'a> {
         }
     }
 }
+
+pub fn find_channel(channel_name: &str) -> Option<&'static ConfigNotify> {
+    // Notice: the default channel holds notifiers that are configured at the top-level
+    APP_CONF.notify.as_ref().and_then(|notify| {
+        if channel_name == DEFAULT_CHANNEL {
+            Some(notify)
+        } else {
+            notify.channels.get(channel_name)
+        }
+    })
+}
+
+pub fn is_probe_routed(probe_id: &str, channel_name: &str) -> bool {
+    let service = APP_CONF
+        .probe
+        .service
+        .iter()
+        .find(|service| service.id == probe_id);
+
+    // Probes with no explicit route are routed to the default channel
+    let is_routed = if let Some(channels) = service.and_then(|service| service.notify.as_ref()) {
+        channels.iter().any(|channel| channel == channel_name)
+    } else {
+        channel_name == DEFAULT_CHANNEL
+    };
+
+    // Notice: probes that are not configured (ie. discovered ones) are considered critical, \
+    //   which is the default severity for configured probes.
+    let severity = service
+        .map(|service| service.severity)
+        .unwrap_or(Severity::Critical);
+
+    is_routed == true
+        && find_channel(channel_name)
+            .map(|notify| severity >= notify.min_severity)
+            .unwrap_or(false)
+}
@@ -0,0 +1,287 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+
+use super::generic::{GenericNotifier, Notification, DISPATCH_TIMEOUT_SECONDS};
+use super::template;
+use crate::config::config::ConfigNotify;
+use crate::prober::status::Status;
+use crate::APP_CONF;
+
+static CARD_ID: &'static str = "vigil-status";
+
+lazy_static! {
+    static ref GOOGLECHAT_HTTP_CLIENT: Client = Client::builder()
+        .timeout(Duration::from_secs(DISPATCH_TIMEOUT_SECONDS))
+        .gzip(true)
+        .build()
+        .unwrap();
+}
+
+pub struct GoogleChatNotifier;
+
+#[derive(Serialize)]
+struct GoogleChatPayload<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+
+    #[serde(rename = "cardsV2")]
+    cards_v2: Vec<GoogleChatPayloadCard<'a>>,
+}
+
+#[derive(Serialize)]
+struct GoogleChatPayloadCard<'a> {
+    #[serde(rename = "cardId")]
+    card_id: &'static str,
+
+    card: GoogleChatPayloadCardContent<'a>,
+}
+
+#[derive(Serialize)]
+struct GoogleChatPayloadCardContent<'a> {
+    header: GoogleChatPayloadCardHeader<'a>,
+    sections: Vec<GoogleChatPayloadCardSection<'a>>,
+}
+
+#[derive(Serialize)]
+struct GoogleChatPayloadCardHeader<'a> {
+    title: String,
+    subtitle: &'a str,
+}
+
+#[derive(Serialize)]
+struct GoogleChatPayloadCardSection<'a> {
+    widgets: Vec<GoogleChatPayloadCardWidget<'a>>,
+}
+
+#[derive(Serialize)]
+enum GoogleChatPayloadCardWidget<'a> {
+    #[serde(rename = "decoratedText")]
+    DecoratedText {
+        #[serde(rename = "topLabel")]
+        top_label: &'static str,
+
+        text: String,
+
+        #[serde(rename = "wrapText")]
+        wrap_text: bool,
+    },
+
+    #[serde(rename = "buttonList")]
+    ButtonList {
+        buttons: Vec<GoogleChatPayloadCardButton<'a>>,
+    },
+}
+
+#[derive(Serialize)]
+struct GoogleChatPayloadCardButton<'a> {
+    text: String,
+
+    #[serde(rename = "onClick")]
+    on_click: GoogleChatPayloadCardButtonClick<'a>,
+}
+
+#[derive(Serialize)]
+struct GoogleChatPayloadCardButtonClick<'a> {
+    #[serde(rename = "openLink")]
+    open_link: GoogleChatPayloadCardButtonLink<'a>,
+}
+
+#[derive(Serialize)]
+struct GoogleChatPayloadCardButtonLink<'a> {
+    url: &'a str,
+}
+
+impl GenericNotifier for GoogleChatNotifier {
+    fn attempt(notify: &ConfigNotify, notification: &Notification) -> Result<(), bool> {
+        if let Some(ref googlechat) = notify.googlechat {
+            // Build card title
+            let title = if notification.test == true {
+                format!(
+                    "This is a test alert, status is: {}.",
+                    notification.status.as_str()
+                )
+            } else if notification.startup == true {
+                format!("Status started up, as: {}.", notification.status.as_str())
+            } else if let Some(probe_id) = notification.flapping {
+                format!("Service is flapping: {}.", probe_id)
+            } else if let Some(held) = notification.held {
+                format!(
+                    "Quiet hours are over ({} held back), status is: {}.",
+                    held,
+                    notification.status.as_str()
+                )
+            } else if let Some(suppressed) = notification.suppressed {
+                format!(
+                    "Rate limit is over ({} suppressed), status is: {}.",
+                    suppressed,
+                    notification.status.as_str()
+                )
+            } else if let Some(digest_label) = notification.digest_label() {
+                format!(
+                    "Status changed to: {} ({}).",
+                    notification.status.as_str(),
+                    digest_label
+                )
+            } else if notification.changed == true {
+                format!("Status changed to: {}.", notification.status.as_str())
+            } else {
+                format!(
+                    "Status is still: {} (reminder{}).",
+                    notification.status.as_str(),
+                    notification.reminder_suffix()
+                )
+            };
+
+            // Apply channel template (if any)
+            let rendered = template::render(notify, notification);
+
+            // Build card widgets
+            let mut widgets = Vec::new();
+
+            widgets.push(GoogleChatPayloadCardWidget::DecoratedText {
+                top_label: "Status",
+                text: format!(
+                    "<font color=\"{}\"><b>{}</b></font>",
+                    status_to_color(&notification.status),
+                    notification.status.as_str().to_uppercase()
+                ),
+                wrap_text: false,
+            });
+
+            // Append affected services (the flapping service is listed as well)
+            let mut services: Vec<&str> = Vec::new();
+
+            for probe_id in notification
+                .replicas
+                .iter()
+                .filter_map(|replica| replica.split(':').next())
+                .chain(notification.flapping)
+            {
+                if services.contains(&probe_id) == false {
+                    services.push(probe_id);
+                }
+            }
+
+            if services.is_empty() == false {
+                widgets.push(GoogleChatPayloadCardWidget::DecoratedText {
+                    top_label: "Services",
+                    text: services.join(", "),
+                    wrap_text: true,
+                });
+            }
+
+            if notification.replicas.is_empty() == false {
+                widgets.push(GoogleChatPayloadCardWidget::DecoratedText {
+                    top_label: "Nodes",
+                    text: notification.replicas.join("<br>"),
+                    wrap_text: true,
+                });
+            }
+
+            // Append downtime (how long services have been down, or were down if they recovered)
+            if let Some(downtime_label) = notification.downtime_label() {
+                widgets.push(GoogleChatPayloadCardWidget::DecoratedText {
+                    top_label: if notification.status == &Status::Dead {
+                        "Down For"
+                    } else {
+                        "Was Down For"
+                    },
+                    text: downtime_label,
+                    wrap_text: false,
+                });
+            }
+
+            widgets.push(GoogleChatPayloadCardWidget::DecoratedText {
+                top_label: "Time",
+                text: notification.time.to_owned(),
+                wrap_text: false,
+            });
+
+            // Append action buttons
+            let acknowledge_urls = notification.acknowledge_urls();
+
+            let mut buttons = vec![GoogleChatPayloadCardButton {
+                text: "Status Page".to_string(),
+                on_click: GoogleChatPayloadCardButtonClick {
+                    open_link: GoogleChatPayloadCardButtonLink {
+                        url: APP_CONF.branding.page_url.as_str(),
+                    },
+                },
+            }];
+
+            for acknowledge_url in acknowledge_urls.iter() {
+                buttons.push(GoogleChatPayloadCardButton {
+                    text: format!(
+                        "Acknowledge {}",
+                        acknowledge_url.rsplit('/').next().unwrap_or("")
+                    ),
+                    on_click: GoogleChatPayloadCardButtonClick {
+                        open_link: GoogleChatPayloadCardButtonLink {
+                            url: acknowledge_url,
+                        },
+                    },
+                });
+            }
+
+            widgets.push(GoogleChatPayloadCardWidget::ButtonList { buttons: buttons });
+
+            // Build payload
+            let payload = GoogleChatPayload {
+                text: rendered.body,
+                cards_v2: vec![GoogleChatPayloadCard {
+                    card_id: CARD_ID,
+                    card: GoogleChatPayloadCardContent {
+                        header: GoogleChatPayloadCardHeader {
+                            title: rendered.subject.unwrap_or(title),
+                            subtitle: &APP_CONF.branding.page_title,
+                        },
+                        sections: vec![GoogleChatPayloadCardSection { widgets: widgets }],
+                    },
+                }],
+            };
+
+            // Submit payload to Google Chat
+            let response = GOOGLECHAT_HTTP_CLIENT
+                .post(googlechat.hook_url.as_str())
+                .json(&payload)
+                .send();
+
+            if let Ok(response_inner) = response {
+                if response_inner.status().is_success() == true {
+                    return Ok(());
+                }
+            }
+
+            return Err(true);
+        }
+
+        Err(false)
+    }
+
+    fn can_notify(notify: &ConfigNotify, notification: &Notification) -> bool {
+        if let Some(ref googlechat_config) = notify.googlechat {
+            notification.expected(googlechat_config.reminders_only)
+        } else {
+            false
+        }
+    }
+
+    fn name() -> &'static str {
+        "googlechat"
+    }
+}
+
+fn status_to_color(status: &Status) -> &'static str {
+    match status {
+        &Status::Healthy => "#2eb886",
+        &Status::Sick => "#daa038",
+        &Status::Dead => "#a30200",
+    }
+}
@@ -11,7 +11,9 @@ use std::time::Duration;
 use reqwest::blocking::Client;
 
 use super::generic::{GenericNotifier, Notification, DISPATCH_TIMEOUT_SECONDS};
+use super::template;
 use crate::config::config::ConfigNotify;
+use crate::prober::status::Status;
 use crate::APP_CONF;
 
 lazy_static! {
@@ -30,10 +32,29 @@ impl GenericNotifier for GotifyNotifier {
             // Build up the message text
             let mut message = String::new();
 
-            if notification.startup == true {
+            if notification.test == true {
+                message.push_str("This is a test alert.\n\n");
+            } else if notification.startup == true {
                 message.push_str("This is a startup alert.\n\n");
+            } else if let Some(probe_id) = notification.flapping {
+                message.push_str(&format!("Service is flapping: {}.\n\n", probe_id));
+            } else if let Some(held) = notification.held {
+                message.push_str(&format!(
+                    "Quiet hours are over, {} notification(s) were held back.\n\n",
+                    held
+                ));
+            } else if let Some(suppressed) = notification.suppressed {
+                message.push_str(&format!(
+                    "Rate limit is over, {} notification(s) were suppressed.\n\n",
+                    suppressed
+                ));
+            } else if let Some(digest_label) = notification.digest_label() {
+                message.push_str(&format!("{}.\n\n", digest_label));
             } else if notification.changed == false {
-                message.push_str("This is a reminder.\n\n");
+                message.push_str(&format!(
+                    "This is a reminder{}.\n\n",
+                    notification.reminder_suffix()
+                ));
             }
 
             message.push_str(&format!(
@@ -43,28 +64,60 @@ impl GenericNotifier for GotifyNotifier {
             message.push_str(&format!("Nodes:\n{}\n", &notification.replicas.join("\n")));
             message.push_str(&format!("Time: {}", &notification.time));
 
+            for acknowledge_url in notification.acknowledge_urls() {
+                message.push_str(&format!("\nAcknowledge: {}", acknowledge_url));
+            }
+
+            // Apply channel template (if any)
+            let rendered = template::render(notify, notification);
+
+            if let Some(body) = rendered.body {
+                message = body;
+            }
+
             debug!("will send Gotify notification with message: {}", &message);
 
             // Generate URL
             // See: https://gotify.net/docs/pushmsg
-            let url = format!(
-                "{}message?token={}",
-                gotify.app_url.as_str(),
-                gotify.app_token
-            );
+            let url = format!("{}/message", gotify.app_url.as_str().trim_end_matches('/'));
+
+            // Map notification to priority (the application default applies if none is set)
+            let priority = if notification.reminder.is_some() == true {
+                Some(gotify.priority_reminder)
+            } else {
+                match notification.status {
+                    Status::Dead => gotify.priority_dead,
+                    Status::Sick => gotify.priority_sick,
+                    Status::Healthy => gotify.priority_healthy,
+                }
+            };
+
+            let priority_value = priority.map(|priority| priority.to_string());
 
             // Build message parameters
             let mut params: HashMap<&str, &str> = HashMap::new();
 
-            params.insert("title", &APP_CONF.branding.page_title);
+            params.insert(
+                "title",
+                rendered
+                    .subject
+                    .as_deref()
+                    .unwrap_or(&APP_CONF.branding.page_title),
+            );
             params.insert("message", &message);
 
-            if notification.changed == false {
-                params.insert("priority", "10");
+            if let Some(ref priority_value) = priority_value {
+                params.insert("priority", priority_value);
             }
 
             // Submit message to Gotify
-            let response = GOTIFY_HTTP_CLIENT.post(&url).form(&params).send();
+            // Notice: the application token is passed as a header rather than in the URL, so \
+            //   that it does not end up in proxy or server access logs.
+            let response = GOTIFY_HTTP_CLIENT
+                .post(&url)
+                .header("X-Gotify-Key", &gotify.app_token)
+                .form(&params)
+                .send();
 
             if let Ok(response_inner) = response {
                 if response_inner.status().is_success() != true {
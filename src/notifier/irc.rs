@@ -0,0 +1,480 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::collections::HashMap;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender, TryRecvError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use native_tls::TlsConnector;
+
+use super::generic::{GenericNotifier, Notification, DISPATCH_TIMEOUT_SECONDS};
+use super::template;
+use crate::config::config::{ConfigNotify, ConfigNotifyIRC};
+use crate::APP_CONF;
+
+const POLL_INTERVAL_MILLISECONDS: u64 = 500;
+const RECONNECT_DELAY_SECONDS: u64 = 10;
+const LINE_MAXIMUM_BYTES: usize = 400;
+const READ_BUFFER_BYTES: usize = 4096;
+
+lazy_static! {
+    static ref IRC_SESSIONS: Mutex<HashMap<String, IRCSession>> = Mutex::new(HashMap::new());
+}
+
+pub struct IRCNotifier;
+
+struct IRCSession {
+    settings: IRCSettings,
+    sender: Sender<IRCRequest>,
+}
+
+#[derive(Clone, PartialEq)]
+struct IRCSettings {
+    host: String,
+    port: u16,
+    tls: bool,
+    nick: String,
+    channel: String,
+    channel_key: Option<String>,
+    sasl: Option<(String, String)>,
+}
+
+struct IRCRequest {
+    lines: Vec<String>,
+    expires_at: Instant,
+    reply: SyncSender<Result<(), String>>,
+}
+
+trait IRCStream: Read + Write + Send {}
+
+impl<T: Read + Write + Send> IRCStream for T {}
+
+struct IRCConnection {
+    stream: Box<dyn IRCStream>,
+    buffer: Vec<u8>,
+}
+
+enum IRCSessionEnd {
+    Reconnect,
+    Exit,
+}
+
+impl GenericNotifier for IRCNotifier {
+    fn attempt(notify: &ConfigNotify, notification: &Notification) -> Result<(), bool> {
+        if let Some(ref irc) = notify.irc {
+            // Build up the message lines (IRC messages cannot span multiple lines)
+            let mut heading = String::new();
+
+            if notification.test == true {
+                heading.push_str("Test alert for: ");
+            } else if notification.startup == true {
+                heading.push_str("Startup alert for: ");
+            } else if let Some(probe_id) = notification.flapping {
+                heading.push_str(&format!("Flapping alert ({}) for: ", probe_id));
+            } else if let Some(held) = notification.held {
+                heading.push_str(&format!("Quiet hours summary ({} held back) for: ", held));
+            } else if let Some(suppressed) = notification.suppressed {
+                heading.push_str(&format!(
+                    "Rate limit summary ({} suppressed) for: ",
+                    suppressed
+                ));
+            } else if let Some(digest_label) = notification.digest_label() {
+                heading.push_str(&format!("Digest ({}) for: ", digest_label));
+            } else if notification.changed == false {
+                heading.push_str(&format!("Reminder{} for: ", notification.reminder_suffix()));
+            }
+
+            heading.push_str(&format!(
+                "{} is \x02{}\x02 ({})",
+                APP_CONF.branding.page_title,
+                notification.status.as_str().to_uppercase(),
+                notification.time
+            ));
+
+            let mut message = vec![heading];
+
+            if notification.replicas.len() > 0 {
+                message.push(format!("Nodes: {}", notification.replicas.join(", ")));
+            }
+
+            for acknowledge_url in notification.acknowledge_urls() {
+                message.push(format!("Acknowledge: {}", acknowledge_url));
+            }
+
+            message.push(format!("URL: {}", APP_CONF.branding.page_url.as_str()));
+
+            // Apply channel template (if any)
+            if let Some(body) = template::render(notify, notification).body {
+                message = body.lines().map(str::to_string).collect();
+            }
+
+            let lines: Vec<String> = message
+                .iter()
+                .filter(|line| line.trim().is_empty() == false)
+                .map(|line| format!("PRIVMSG {} :{}", irc.channel, truncate_line(line)))
+                .collect();
+
+            debug!("will send IRC notification with lines: {:?}", &lines);
+
+            // Hand over message to channel session, and wait for it to be sent
+            let (reply_sender, reply_receiver) = mpsc::sync_channel(1);
+            let timeout = Duration::from_secs(DISPATCH_TIMEOUT_SECONDS);
+
+            acquire_session(notification.channel, irc)
+                .send(IRCRequest {
+                    lines: lines,
+                    expires_at: Instant::now() + timeout,
+                    reply: reply_sender,
+                })
+                .or(Err(true))?;
+
+            return match reply_receiver.recv_timeout(timeout) {
+                Ok(Ok(_)) => Ok(()),
+                Ok(Err(err)) => {
+                    warn!("could not send irc notification: {}", err);
+
+                    Err(true)
+                }
+                Err(_) => Err(true),
+            };
+        }
+
+        Err(false)
+    }
+
+    fn can_notify(notify: &ConfigNotify, notification: &Notification) -> bool {
+        if let Some(ref irc_config) = notify.irc {
+            notification.expected(irc_config.reminders_only)
+        } else {
+            false
+        }
+    }
+
+    fn name() -> &'static str {
+        "irc"
+    }
+}
+
+fn acquire_session(channel_name: &str, irc: &ConfigNotifyIRC) -> Sender<IRCRequest> {
+    let tls = irc.server_url.scheme() == "ircs";
+
+    let settings = IRCSettings {
+        host: irc.server_url.host_str().unwrap_or("").to_string(),
+        port: irc
+            .server_url
+            .port()
+            .unwrap_or(if tls == true { 6697 } else { 6667 }),
+        tls: tls,
+        nick: irc.nick.to_owned(),
+        channel: irc.channel.to_owned(),
+        channel_key: irc.channel_key.to_owned(),
+        sasl: irc.sasl_password.as_ref().map(|password| {
+            (
+                irc.sasl_username.as_ref().unwrap_or(&irc.nick).to_owned(),
+                password.to_owned(),
+            )
+        }),
+    };
+
+    let mut sessions = IRC_SESSIONS.lock().unwrap();
+
+    // Re-use existing session (if its settings did not change upon a configuration reload)
+    // Notice: replaced sessions get closed by their thread, as their sender gets dropped.
+    if let Some(session) = sessions.get(channel_name) {
+        if session.settings == settings {
+            return session.sender.clone();
+        }
+    }
+
+    let (sender, receiver) = mpsc::channel();
+
+    let thread_settings = settings.clone();
+
+    thread::Builder::new()
+        .name(format!("vigil-irc-{}", channel_name))
+        .spawn(move || run_session(thread_settings, receiver))
+        .ok();
+
+    sessions.insert(
+        channel_name.to_string(),
+        IRCSession {
+            settings: settings,
+            sender: sender.clone(),
+        },
+    );
+
+    sender
+}
+
+fn run_session(settings: IRCSettings, receiver: Receiver<IRCRequest>) {
+    loop {
+        match connect(&settings) {
+            Ok(mut connection) => {
+                info!(
+                    "connected to irc server: {}:{} on channel: {}",
+                    settings.host, settings.port, settings.channel
+                );
+
+                match serve(&mut connection, &receiver) {
+                    IRCSessionEnd::Exit => {
+                        let _ = connection.send("QUIT :Configuration changed");
+
+                        return;
+                    }
+                    IRCSessionEnd::Reconnect => {
+                        warn!("lost connection to irc server: {}", settings.host)
+                    }
+                }
+            }
+            Err(err) => warn!(
+                "could not connect to irc server: {} (error: {})",
+                settings.host, err
+            ),
+        }
+
+        // Wait before reconnecting (requests that come in meanwhile cannot be delivered)
+        let reconnect_at = Instant::now() + Duration::from_secs(RECONNECT_DELAY_SECONDS);
+
+        while let Some(timeout) = reconnect_at.checked_duration_since(Instant::now()) {
+            match receiver.recv_timeout(timeout) {
+                Ok(request) => {
+                    let _ = request.reply.send(Err("not connected".to_string()));
+                }
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+    }
+}
+
+fn serve(connection: &mut IRCConnection, receiver: &Receiver<IRCRequest>) -> IRCSessionEnd {
+    loop {
+        // Send pending messages
+        match receiver.try_recv() {
+            // Notice: expired requests are not sent anymore, as they already got reported as \
+            //   failed, and will be retried from the notifier queue (if enabled).
+            Ok(request) if request.expires_at < Instant::now() => {}
+            Ok(request) => {
+                let result = request
+                    .lines
+                    .iter()
+                    .try_for_each(|line| connection.send(line));
+
+                let is_failed = result.is_err();
+
+                let _ = request.reply.send(result);
+
+                if is_failed == true {
+                    return IRCSessionEnd::Reconnect;
+                }
+            }
+            Err(TryRecvError::Empty) => {}
+            Err(TryRecvError::Disconnected) => return IRCSessionEnd::Exit,
+        }
+
+        // Handle incoming lines (this also waits for the poll interval)
+        match connection.receive() {
+            Ok(Some(line)) => {
+                if let Some(token) = line.strip_prefix("PING ") {
+                    if connection.send(&format!("PONG {}", token)).is_err() == true {
+                        return IRCSessionEnd::Reconnect;
+                    }
+                } else if line.starts_with("ERROR ") == true {
+                    return IRCSessionEnd::Reconnect;
+                }
+            }
+            Ok(None) => {}
+            Err(_) => return IRCSessionEnd::Reconnect,
+        }
+    }
+}
+
+fn connect(settings: &IRCSettings) -> Result<IRCConnection, String> {
+    let address = (settings.host.as_str(), settings.port)
+        .to_socket_addrs()
+        .map_err(|err| err.to_string())?
+        .next()
+        .ok_or("could not resolve address".to_string())?;
+
+    let timeout = Duration::from_secs(DISPATCH_TIMEOUT_SECONDS);
+    let stream = TcpStream::connect_timeout(&address, timeout).map_err(|err| err.to_string())?;
+
+    // Notice: reads time out after the poll interval, so that the session can alternate \
+    //   between sending messages and answering pings.
+    stream
+        .set_read_timeout(Some(Duration::from_millis(POLL_INTERVAL_MILLISECONDS)))
+        .and_then(|_| stream.set_write_timeout(Some(timeout)))
+        .map_err(|err| err.to_string())?;
+
+    let stream: Box<dyn IRCStream> = if settings.tls == true {
+        let connector = TlsConnector::new().map_err(|err| err.to_string())?;
+
+        Box::new(
+            connector
+                .connect(&settings.host, stream)
+                .map_err(|err| err.to_string())?,
+        )
+    } else {
+        Box::new(stream)
+    };
+
+    let mut connection = IRCConnection {
+        stream: stream,
+        buffer: Vec::new(),
+    };
+
+    register(&mut connection, settings)?;
+
+    Ok(connection)
+}
+
+fn register(connection: &mut IRCConnection, settings: &IRCSettings) -> Result<(), String> {
+    let mut nick = settings.nick.to_owned();
+
+    // Request SASL capability (if authenticating)
+    if settings.sasl.is_some() == true {
+        connection.send("CAP REQ :sasl")?;
+    }
+
+    connection.send(&format!("NICK {}", nick))?;
+    connection.send(&format!("USER {} 0 * :Vigil", settings.nick))?;
+
+    let deadline = Instant::now() + Duration::from_secs(DISPATCH_TIMEOUT_SECONDS);
+
+    while Instant::now() < deadline {
+        let line = match connection.receive()? {
+            Some(line) => line,
+            None => continue,
+        };
+
+        if let Some(token) = line.strip_prefix("PING ") {
+            connection.send(&format!("PONG {}", token))?;
+
+            continue;
+        }
+
+        // Strip prefix (if any), then acquire command and parameters
+        let line = if line.starts_with(':') == true {
+            line.splitn(2, ' ').nth(1).unwrap_or("")
+        } else {
+            &line
+        };
+
+        let mut parts = line.splitn(2, ' ');
+        let (command, parameters) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+
+        match command {
+            "CAP" if parameters.contains("ACK") == true => {
+                connection.send("AUTHENTICATE PLAIN")?;
+            }
+            "CAP" if parameters.contains("NAK") == true => {
+                return Err("sasl is not supported by server".to_string());
+            }
+            "AUTHENTICATE" if parameters.trim() == "+" => {
+                if let Some((ref username, ref password)) = settings.sasl {
+                    connection.send(&format!(
+                        "AUTHENTICATE {}",
+                        base64::encode(format!("\0{}\0{}", username, password))
+                    ))?;
+                }
+            }
+            "903" => {
+                connection.send("CAP END")?;
+            }
+            "902" | "904" | "905" | "906" => {
+                return Err(format!("sasl authentication failed: {}", parameters));
+            }
+            "433" => {
+                // Nick is already in use, try with a suffix
+                nick.push('_');
+
+                connection.send(&format!("NICK {}", nick))?;
+            }
+            "001" => {
+                // Registered, join channel
+                match settings.channel_key {
+                    Some(ref channel_key) => {
+                        connection.send(&format!("JOIN {} {}", settings.channel, channel_key))?
+                    }
+                    None => connection.send(&format!("JOIN {}", settings.channel))?,
+                }
+            }
+            "366" => {
+                // End of channel names list, channel was joined
+                return Ok(());
+            }
+            "403" | "405" | "471" | "473" | "474" | "475" => {
+                return Err(format!("could not join channel: {}", parameters));
+            }
+            "ERROR" => {
+                return Err(format!("server error: {}", parameters));
+            }
+            _ => {}
+        }
+    }
+
+    Err("registration timed out".to_string())
+}
+
+impl IRCConnection {
+    fn send(&mut self, line: &str) -> Result<(), String> {
+        self.stream
+            .write_all(format!("{}\r\n", line).as_bytes())
+            .and_then(|_| self.stream.flush())
+            .map_err(|err| err.to_string())
+    }
+
+    fn receive(&mut self) -> Result<Option<String>, String> {
+        // Return buffered line (if any)
+        if let Some(line) = self.take_line() {
+            return Ok(Some(line));
+        }
+
+        let mut chunk = [0; READ_BUFFER_BYTES];
+
+        match self.stream.read(&mut chunk) {
+            Ok(0) => Err("connection closed".to_string()),
+            Ok(size) => {
+                self.buffer.extend_from_slice(&chunk[..size]);
+
+                Ok(self.take_line())
+            }
+            Err(err)
+                if err.kind() == ErrorKind::WouldBlock || err.kind() == ErrorKind::TimedOut =>
+            {
+                Ok(None)
+            }
+            Err(err) => Err(err.to_string()),
+        }
+    }
+
+    fn take_line(&mut self) -> Option<String> {
+        let position = self.buffer.iter().position(|byte| *byte == b'\n')?;
+        let line: Vec<u8> = self.buffer.drain(..=position).collect();
+
+        Some(String::from_utf8_lossy(&line).trim_end().to_string())
+    }
+}
+
+fn truncate_line(line: &str) -> &str {
+    // Notice: IRC lines are limited to 512 bytes (including the command and the channel name), \
+    //   thus lines are cut to a safe length, on a character boundary.
+    if line.len() <= LINE_MAXIMUM_BYTES {
+        return line;
+    }
+
+    let mut end = LINE_MAXIMUM_BYTES;
+
+    while line.is_char_boundary(end) == false {
+        end -= 1;
+    }
+
+    &line[..end]
+}
@@ -0,0 +1,88 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use crate::config::config::ConfigNotify;
+use crate::APP_CONF;
+
+lazy_static! {
+    static ref LIMIT_GLOBAL: Mutex<VecDeque<SystemTime>> = Mutex::new(VecDeque::new());
+    static ref LIMIT_CHANNELS: Mutex<HashMap<String, VecDeque<SystemTime>>> =
+        Mutex::new(HashMap::new());
+}
+
+pub fn available(channel_name: &str, notify: &ConfigNotify) -> bool {
+    check(channel_name, notify, false)
+}
+
+pub fn acquire(channel_name: &str, notify: &ConfigNotify) -> bool {
+    check(channel_name, notify, true)
+}
+
+fn check(channel_name: &str, notify: &ConfigNotify, consume: bool) -> bool {
+    let global = APP_CONF
+        .notify
+        .as_ref()
+        .and_then(|notify| notify.global_rate_limit.map(|limit| (limit, notify)));
+
+    let mut global_sent = LIMIT_GLOBAL.lock().unwrap();
+    let mut channels_sent = LIMIT_CHANNELS.lock().unwrap();
+
+    let channel_sent = channels_sent.entry(channel_name.to_owned()).or_default();
+
+    // Notice: a notification must fit in both the channel and global windows, as it would \
+    //   otherwise count towards the global window while being suppressed on its channel.
+    let is_channel_full = notify
+        .rate_limit
+        .map(|limit| is_full(channel_sent, limit, notify.rate_limit_window))
+        .unwrap_or(false);
+
+    let is_global_full = global
+        .map(|(limit, global_notify)| {
+            is_full(
+                &mut global_sent,
+                limit,
+                global_notify.global_rate_limit_window,
+            )
+        })
+        .unwrap_or(false);
+
+    if is_channel_full == true || is_global_full == true {
+        return false;
+    }
+
+    // Count notification in windows (only for limits that are set)
+    if consume == true {
+        let now = SystemTime::now();
+
+        if notify.rate_limit.is_some() == true {
+            channel_sent.push_back(now);
+        }
+        if global.is_some() == true {
+            global_sent.push_back(now);
+        }
+    }
+
+    true
+}
+
+fn is_full(sent: &mut VecDeque<SystemTime>, limit: u32, window: u64) -> bool {
+    // Forget about notifications that were sent before the current window
+    let window = Duration::from_secs(window);
+
+    while let Some(time) = sent.front() {
+        if time.elapsed().unwrap_or_default() >= window {
+            sent.pop_front();
+        } else {
+            break;
+        }
+    }
+
+    sent.len() >= limit as usize
+}
@@ -6,11 +6,13 @@
 // License: Mozilla Public License v2.0 (MPL v2.0)
 
 use std::collections::HashMap;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use reqwest::blocking::Client;
+use url::form_urlencoded;
 
 use super::generic::{GenericNotifier, Notification, DISPATCH_TIMEOUT_SECONDS};
+use super::template;
 use crate::config::config::ConfigNotify;
 use crate::APP_CONF;
 
@@ -24,11 +26,11 @@ lazy_static! {
         format_status,
         format_replicas,
         format_status_page,
-        format_time
+        format_time,
+        format_acknowledge
     ];
 }
 
-static MATRIX_MESSAGE_BODY: &'static str = "You received a Vigil alert.";
 static MATRIX_MESSAGE_TYPE: &'static str = "m.text";
 static MATRIX_MESSAGE_FORMAT: &'static str = "org.matrix.custom.html";
 
@@ -38,29 +40,51 @@ impl GenericNotifier for MatrixNotifier {
     fn attempt(notify: &ConfigNotify, notification: &Notification) -> Result<(), bool> {
         if let Some(ref matrix) = notify.matrix {
             // Build up the message text
-            let message = format_message(notification);
+            let mut message = format_message(notification);
+            let mut is_templated = false;
+
+            // Apply channel template (if any)
+            if let Some(body) = template::render(notify, notification).body {
+                message = body;
+                is_templated = true;
+            }
+
+            // Notice: the plain-text body is shown by clients that do not render HTML (and in \
+            //   push notifications), thus it is always derived from the HTML message.
+            let plain_message = if matrix.html == false && is_templated == true {
+                message.to_owned()
+            } else {
+                format_plain(&message)
+            };
 
             debug!("will send Matrix notification with message: {}", &message);
 
             // Generate URL
-            // See: https://matrix.org/docs/guides/client-server-api#sending-messages
+            // See: https://spec.matrix.org/v1.2/client-server-api/#put_matrixclientv3roomsroomidsendeventtypetxnid
             let url = format!(
-                "{}_matrix/client/r0/rooms/{}/send/m.room.message?access_token={}",
-                matrix.homeserver_url.as_str(),
-                matrix.room_id.as_str(),
-                matrix.access_token.as_str()
+                "{}/_matrix/client/r0/rooms/{}/send/m.room.message/{}",
+                matrix.homeserver_url.as_str().trim_end_matches('/'),
+                form_urlencoded::byte_serialize(matrix.room_id.as_bytes()).collect::<String>(),
+                make_transaction_id()
             );
 
             // Build message parameters
             let mut params: HashMap<&str, &str> = HashMap::new();
 
-            params.insert("body", MATRIX_MESSAGE_BODY);
             params.insert("msgtype", MATRIX_MESSAGE_TYPE);
-            params.insert("format", MATRIX_MESSAGE_FORMAT);
-            params.insert("formatted_body", &message);
+            params.insert("body", &plain_message);
+
+            if matrix.html == true {
+                params.insert("format", MATRIX_MESSAGE_FORMAT);
+                params.insert("formatted_body", &message);
+            }
 
             // Submit message to Matrix
-            let response = MATRIX_HTTP_CLIENT.post(&url).json(&params).send();
+            let response = MATRIX_HTTP_CLIENT
+                .put(&url)
+                .bearer_auth(&matrix.access_token)
+                .json(&params)
+                .send();
 
             if let Ok(response_inner) = response {
                 if response_inner.status().is_success() != true {
@@ -90,19 +114,42 @@ impl GenericNotifier for MatrixNotifier {
 }
 
 fn format_status(notification: &Notification) -> String {
-    let msg = if notification.startup == true {
+    if let Some(probe_id) = notification.flapping {
+        return format!(
+            "<p>{} Service is flapping: <em>{}</em>.</p>",
+            notification.status.as_icon(),
+            probe_id
+        );
+    }
+
+    let msg = if notification.test == true {
+        "This is a test alert, status is"
+    } else if notification.startup == true {
         "Status started up, as"
+    } else if notification.held.is_some() == true {
+        "Quiet hours are over, status is"
+    } else if notification.suppressed.is_some() == true {
+        "Rate limit is over, status is"
     } else if notification.changed == true {
         "Status changed to"
     } else {
         "Status is still"
     };
 
+    let reminder = if notification.reminder.is_some() == true {
+        format!(" (reminder{})", notification.reminder_suffix())
+    } else if let Some(digest_label) = notification.digest_label() {
+        format!(" ({})", digest_label)
+    } else {
+        String::new()
+    };
+
     format!(
-        "<p>{} {}: <em>{}</em>.</p>",
+        "<p>{} {}: <em>{}</em>{}.</p>",
         notification.status.as_icon(),
         msg,
-        notification.status.as_str().to_uppercase()
+        notification.status.as_str().to_uppercase(),
+        reminder
     )
 }
 
@@ -144,6 +191,54 @@ fn format_time(notification: &Notification) -> String {
     format!("<p>Time: {}</p>", notification.time)
 }
 
+fn format_acknowledge(notification: &Notification) -> String {
+    notification
+        .acknowledge_urls()
+        .iter()
+        .map(|acknowledge_url| format!("<p>Acknowledge: {}</p>", acknowledge_url))
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+fn format_plain(message: &str) -> String {
+    // Strip HTML tags from message (paragraphs and list items are put on their own lines)
+    let mut plain = String::new();
+    let mut tag: Option<String> = None;
+
+    for character in message.chars() {
+        if let Some(ref mut tag_name) = tag {
+            if character == '>' {
+                match tag_name.as_str() {
+                    "/p" | "/li" | "br" | "br/" | "br /" => plain.push('\n'),
+                    "li" => plain.push_str("- "),
+                    _ => {}
+                }
+
+                tag = None;
+            } else {
+                tag_name.push(character);
+            }
+        } else if character == '<' {
+            tag = Some(String::new());
+        } else {
+            plain.push(character);
+        }
+    }
+
+    plain.trim_end().to_string()
+}
+
+fn make_transaction_id() -> String {
+    // Transaction identifiers must be unique for a given access token
+    format!(
+        "vigil.{}",
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or(0)
+    )
+}
+
 fn format_message(notification: &Notification) -> String {
     MATRIX_FORMATTERS
         .iter()
@@ -0,0 +1,213 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+
+use super::generic::{GenericNotifier, Notification, DISPATCH_TIMEOUT_SECONDS};
+use super::template;
+use crate::config::config::ConfigNotify;
+use crate::prober::status::Status;
+use crate::APP_CONF;
+
+lazy_static! {
+    static ref MATTERMOST_HTTP_CLIENT: Client = Client::builder()
+        .timeout(Duration::from_secs(DISPATCH_TIMEOUT_SECONDS))
+        .gzip(true)
+        .build()
+        .unwrap();
+}
+
+pub struct MattermostNotifier;
+
+#[derive(Serialize)]
+struct MattermostPayload<'a> {
+    text: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    username: Option<&'a str>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    channel: Option<&'a str>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    icon_url: Option<&'a str>,
+
+    attachments: Vec<MattermostPayloadAttachment<'a>>,
+}
+
+#[derive(Serialize)]
+struct MattermostPayloadAttachment<'a> {
+    fallback: String,
+    color: &'a str,
+    title: &'a str,
+    title_link: &'a str,
+    fields: Vec<MattermostPayloadAttachmentField<'a>>,
+}
+
+#[derive(Serialize)]
+struct MattermostPayloadAttachmentField<'a> {
+    title: &'a str,
+    value: String,
+    short: bool,
+}
+
+impl GenericNotifier for MattermostNotifier {
+    fn attempt(notify: &ConfigNotify, notification: &Notification) -> Result<(), bool> {
+        if let Some(ref mattermost) = notify.mattermost {
+            // Build message
+            // Notice: Mattermost uses standard Markdown, where bold text is wrapped in double \
+            //   asterisks (Slack-style single asterisks render as italic).
+            let message_text = if notification.test == true {
+                format!(
+                    "This is a test alert, status is: **{}**.",
+                    notification.status.as_str()
+                )
+            } else if notification.startup == true {
+                format!(
+                    "Status started up, as: **{}**.",
+                    notification.status.as_str()
+                )
+            } else if let Some(probe_id) = notification.flapping {
+                format!("Service is flapping: **{}**.", probe_id)
+            } else if let Some(held) = notification.held {
+                format!(
+                    "Quiet hours are over ({} held back), status is: **{}**.",
+                    held,
+                    notification.status.as_str()
+                )
+            } else if let Some(suppressed) = notification.suppressed {
+                format!(
+                    "Rate limit is over ({} suppressed), status is: **{}**.",
+                    suppressed,
+                    notification.status.as_str()
+                )
+            } else if let Some(digest_label) = notification.digest_label() {
+                format!(
+                    "Status changed to: **{}** ({}).",
+                    notification.status.as_str(),
+                    digest_label
+                )
+            } else if notification.changed == true {
+                format!("Status changed to: **{}**.", notification.status.as_str())
+            } else {
+                format!(
+                    "Status is still: **{}** (reminder{}).",
+                    notification.status.as_str(),
+                    notification.reminder_suffix()
+                )
+            };
+
+            // Apply channel template (if any)
+            let rendered = template::render(notify, notification);
+            let message_text = rendered.body.unwrap_or(message_text);
+
+            let payload_text = if mattermost.mention_channel == true {
+                format!("@channel {}", &message_text)
+            } else {
+                message_text.to_owned()
+            };
+
+            // Build attachment
+            let mut attachment = MattermostPayloadAttachment {
+                fallback: message_text,
+                color: status_to_color(&notification.status),
+                title: rendered
+                    .subject
+                    .as_deref()
+                    .unwrap_or(&APP_CONF.branding.page_title),
+                title_link: APP_CONF.branding.page_url.as_str(),
+                fields: Vec::new(),
+            };
+
+            if notification.replicas.len() > 0 {
+                attachment.fields.push(MattermostPayloadAttachmentField {
+                    title: "Nodes",
+                    value: notification
+                        .replicas
+                        .iter()
+                        .map(|replica| format!("`{}`", replica))
+                        .collect::<Vec<String>>()
+                        .join("\n"),
+                    short: false,
+                });
+            }
+
+            attachment.fields.push(MattermostPayloadAttachmentField {
+                title: "Status",
+                value: format!("{:?}", notification.status),
+                short: true,
+            });
+
+            attachment.fields.push(MattermostPayloadAttachmentField {
+                title: "Time",
+                value: notification.time.to_owned(),
+                short: true,
+            });
+
+            let acknowledge_urls = notification.acknowledge_urls();
+
+            if acknowledge_urls.is_empty() == false {
+                attachment.fields.push(MattermostPayloadAttachmentField {
+                    title: "Acknowledge",
+                    value: acknowledge_urls.join("\n"),
+                    short: false,
+                });
+            }
+
+            // Build payload
+            let payload = MattermostPayload {
+                text: payload_text,
+                username: mattermost.username.as_deref(),
+                channel: mattermost.channel.as_deref(),
+                icon_url: mattermost
+                    .icon_url
+                    .as_ref()
+                    .map(|icon_url| icon_url.as_str()),
+                attachments: vec![attachment],
+            };
+
+            // Submit payload to Mattermost
+            let response = MATTERMOST_HTTP_CLIENT
+                .post(mattermost.hook_url.as_str())
+                .json(&payload)
+                .send();
+
+            if let Ok(response_inner) = response {
+                if response_inner.status().is_success() == true {
+                    return Ok(());
+                }
+            }
+
+            return Err(true);
+        }
+
+        Err(false)
+    }
+
+    fn can_notify(notify: &ConfigNotify, notification: &Notification) -> bool {
+        if let Some(ref mattermost_config) = notify.mattermost {
+            notification.expected(mattermost_config.reminders_only)
+        } else {
+            false
+        }
+    }
+
+    fn name() -> &'static str {
+        "mattermost"
+    }
+}
+
+fn status_to_color(status: &Status) -> &'static str {
+    // Notice: Mattermost does not support Slack named colors (eg. 'danger'), thus colors are \
+    //   given as hexadecimal codes.
+    match status {
+        &Status::Healthy => "#2eb886",
+        &Status::Sick => "#daa038",
+        &Status::Dead => "#a30200",
+    }
+}
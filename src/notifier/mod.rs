@@ -4,7 +4,14 @@
 // Copyright: 2018, Valerian Saliou <valerian@valeriansaliou.name>
 // License: Mozilla Public License v2.0 (MPL v2.0)
 
+pub mod escalation;
 pub mod generic;
+pub mod limit;
+pub mod queue;
+pub mod quiet;
+pub mod registry;
+pub mod template;
+pub mod tester;
 
 #[cfg(feature = "notifier-email")]
 pub mod email;
@@ -35,3 +42,42 @@ pub mod matrix;
 
 #[cfg(feature = "notifier-webhook")]
 pub mod webhook;
+
+#[cfg(feature = "notifier-discord")]
+pub mod discord;
+
+#[cfg(feature = "notifier-teams")]
+pub mod teams;
+
+#[cfg(feature = "notifier-ntfy")]
+pub mod ntfy;
+
+#[cfg(feature = "notifier-opsgenie")]
+pub mod opsgenie;
+
+#[cfg(feature = "notifier-mattermost")]
+pub mod mattermost;
+
+#[cfg(feature = "notifier-rocketchat")]
+pub mod rocketchat;
+
+#[cfg(feature = "notifier-googlechat")]
+pub mod googlechat;
+
+#[cfg(feature = "notifier-signal")]
+pub mod signal;
+
+#[cfg(feature = "notifier-sms")]
+pub mod sms;
+
+#[cfg(feature = "notifier-sns")]
+pub mod sns;
+
+#[cfg(feature = "notifier-mqtt")]
+pub mod mqtt;
+
+#[cfg(feature = "notifier-irc")]
+pub mod irc;
+
+#[cfg(feature = "notifier-apprise")]
+pub mod apprise;
@@ -0,0 +1,370 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use native_tls::TlsConnector;
+use openssl::rand::rand_bytes;
+
+use super::generic::{is_probe_routed, GenericNotifier, Notification, DISPATCH_TIMEOUT_SECONDS};
+use crate::config::config::{ConfigNotify, ConfigNotifyMQTT};
+use crate::prober::manager::SNAPSHOT as PROBER_SNAPSHOT;
+use crate::prober::status::Status;
+
+const PROTOCOL_LEVEL: u8 = 4;
+const KEEP_ALIVE_SECONDS: u16 = 60;
+const PACKET_MAXIMUM_BYTES: usize = 1024;
+
+const PACKET_CONNECT: u8 = 0x10;
+const PACKET_CONNACK: u8 = 0x20;
+const PACKET_PUBLISH: u8 = 0x30;
+const PACKET_PUBACK: u8 = 0x40;
+const PACKET_PUBREC: u8 = 0x50;
+const PACKET_PUBREL: u8 = 0x62;
+const PACKET_PUBCOMP: u8 = 0x70;
+const PACKET_DISCONNECT: u8 = 0xe0;
+
+static GROUP_DEFAULT: &'static str = "default";
+
+pub struct MQTTNotifier;
+
+#[derive(Serialize)]
+struct MQTTMessage<'a> {
+    #[serde(rename = "type")]
+    _type: &'static str,
+
+    probe: &'a str,
+    label: &'a str,
+    group: &'a str,
+    status: &'a Status,
+    channel: &'a str,
+    time: &'a str,
+}
+
+impl GenericNotifier for MQTTNotifier {
+    fn attempt(notify: &ConfigNotify, notification: &Notification) -> Result<(), bool> {
+        if let Some(ref mqtt) = notify.mqtt {
+            // Acquire message type (same as Webhook payload types)
+            let message_type = if notification.test == true {
+                "test"
+            } else if notification.startup == true {
+                "startup"
+            } else if notification.flapping.is_some() == true {
+                "flapping"
+            } else if notification.held.is_some() == true
+                || notification.suppressed.is_some() == true
+            {
+                "summary"
+            } else if notification.changed == true {
+                "changed"
+            } else {
+                "reminder"
+            };
+
+            // Build messages, one per probe routed to the channel
+            // Notice: the state of all routed probes is published (not only the ones that \
+            //   changed), so that retained states are always kept in sync with the status page.
+            let snapshot = PROBER_SNAPSHOT.load();
+
+            let mut messages = Vec::new();
+
+            for probe in snapshot.states.probes.values() {
+                if is_probe_routed(&probe.id, notification.channel) == false {
+                    continue;
+                }
+
+                let group = probe
+                    .groups
+                    .first()
+                    .map(String::as_str)
+                    .unwrap_or(GROUP_DEFAULT);
+
+                let payload = serde_json::to_vec(&MQTTMessage {
+                    _type: message_type,
+                    probe: &probe.id,
+                    label: &probe.label,
+                    group: group,
+                    status: &probe.status,
+                    channel: notification.channel,
+                    time: &notification.time,
+                })
+                .or(Err(true))?;
+
+                messages.push((
+                    make_topic(mqtt, notification.channel, group, &probe.id),
+                    payload,
+                ));
+            }
+
+            debug!(
+                "will publish {} mqtt message(s) on channel: {}",
+                messages.len(),
+                notification.channel
+            );
+
+            // Submit messages to MQTT broker
+            return publish(mqtt, &messages).map_err(|err| {
+                warn!("could not publish mqtt messages: {}", err);
+
+                true
+            });
+        }
+
+        Err(false)
+    }
+
+    fn can_notify(notify: &ConfigNotify, notification: &Notification) -> bool {
+        if let Some(ref mqtt_config) = notify.mqtt {
+            notification.expected(mqtt_config.reminders_only)
+        } else {
+            false
+        }
+    }
+
+    fn name() -> &'static str {
+        "mqtt"
+    }
+}
+
+fn make_topic(mqtt: &ConfigNotifyMQTT, channel: &str, group: &str, probe_id: &str) -> String {
+    mqtt.topic
+        .replace("{channel}", channel)
+        .replace("{group}", group)
+        .replace("{probe}", probe_id)
+}
+
+fn publish(mqtt: &ConfigNotifyMQTT, messages: &[(String, Vec<u8>)]) -> Result<(), String> {
+    let host = mqtt.server_url.host_str().unwrap_or("");
+    let tls = mqtt.server_url.scheme() == "mqtts";
+
+    let port = mqtt
+        .server_url
+        .port()
+        .unwrap_or(if tls == true { 8883 } else { 1883 });
+
+    let address = (host, port)
+        .to_socket_addrs()
+        .map_err(|err| err.to_string())?
+        .next()
+        .ok_or("could not resolve address".to_string())?;
+
+    let timeout = Duration::from_secs(DISPATCH_TIMEOUT_SECONDS);
+    let stream = TcpStream::connect_timeout(&address, timeout).map_err(|err| err.to_string())?;
+
+    stream
+        .set_read_timeout(Some(timeout))
+        .and_then(|_| stream.set_write_timeout(Some(timeout)))
+        .map_err(|err| err.to_string())?;
+
+    if tls == true {
+        let connector = TlsConnector::new().map_err(|err| err.to_string())?;
+        let tls_stream = connector
+            .connect(host, stream)
+            .map_err(|err| err.to_string())?;
+
+        proceed_session(tls_stream, mqtt, messages)
+    } else {
+        proceed_session(stream, mqtt, messages)
+    }
+}
+
+fn proceed_session<S: Read + Write>(
+    mut stream: S,
+    mqtt: &ConfigNotifyMQTT,
+    messages: &[(String, Vec<u8>)],
+) -> Result<(), String> {
+    // Connect to broker (with a clean session, as nothing needs to be kept in between sessions)
+    let client_id = make_client_id(mqtt);
+
+    let mut flags = 0x02;
+    let mut body = Vec::new();
+
+    if mqtt.username.is_some() == true {
+        flags |= 0x80;
+    }
+    if mqtt.password.is_some() == true {
+        flags |= 0x40;
+    }
+
+    encode_string(&mut body, "MQTT");
+
+    body.push(PROTOCOL_LEVEL);
+    body.push(flags);
+    body.extend_from_slice(&KEEP_ALIVE_SECONDS.to_be_bytes());
+
+    encode_string(&mut body, &client_id);
+
+    if let Some(ref username) = mqtt.username {
+        encode_string(&mut body, username);
+    }
+    if let Some(ref password) = mqtt.password {
+        encode_string(&mut body, password);
+    }
+
+    write_packet(&mut stream, PACKET_CONNECT, &body)?;
+
+    let connack = read_packet(&mut stream, PACKET_CONNACK)?;
+
+    match connack.get(1) {
+        Some(0) => {}
+        Some(code) => return Err(format!("connection refused with code: {}", code)),
+        None => return Err("invalid connection acknowledgment".to_string()),
+    }
+
+    // Publish messages
+    for (index, (topic, payload)) in messages.iter().enumerate() {
+        // Notice: packet identifiers must be non-zero, and are only used for QoS 1 and 2.
+        let packet_id = (index % (u16::MAX as usize) + 1) as u16;
+
+        let mut body = Vec::new();
+
+        encode_string(&mut body, topic);
+
+        if mqtt.qos > 0 {
+            body.extend_from_slice(&packet_id.to_be_bytes());
+        }
+
+        body.extend_from_slice(payload);
+
+        let mut header = PACKET_PUBLISH | (mqtt.qos << 1);
+
+        if mqtt.retain == true {
+            header |= 0x01;
+        }
+
+        write_packet(&mut stream, header, &body)?;
+
+        // Wait for delivery acknowledgment (QoS 1), or complete delivery handshake (QoS 2)
+        match mqtt.qos {
+            1 => expect_packet_id(&read_packet(&mut stream, PACKET_PUBACK)?, packet_id)?,
+            2 => {
+                expect_packet_id(&read_packet(&mut stream, PACKET_PUBREC)?, packet_id)?;
+
+                write_packet(&mut stream, PACKET_PUBREL, &packet_id.to_be_bytes())?;
+
+                expect_packet_id(&read_packet(&mut stream, PACKET_PUBCOMP)?, packet_id)?;
+            }
+            _ => {}
+        }
+    }
+
+    // Disconnect from broker (the result does not matter there)
+    let _ = write_packet(&mut stream, PACKET_DISCONNECT, &[]);
+
+    Ok(())
+}
+
+fn write_packet<S: Write>(stream: &mut S, header: u8, body: &[u8]) -> Result<(), String> {
+    let mut packet = vec![header];
+
+    // Encode remaining length (7 bits per byte, with a continuation bit)
+    let mut length = body.len();
+
+    loop {
+        let mut byte = (length % 128) as u8;
+
+        length /= 128;
+
+        if length > 0 {
+            byte |= 0x80;
+        }
+
+        packet.push(byte);
+
+        if length == 0 {
+            break;
+        }
+    }
+
+    packet.extend_from_slice(body);
+
+    stream.write_all(&packet).map_err(|err| err.to_string())
+}
+
+fn read_packet<S: Read>(stream: &mut S, expected_header: u8) -> Result<Vec<u8>, String> {
+    let mut byte = [0; 1];
+
+    stream
+        .read_exact(&mut byte)
+        .map_err(|err| err.to_string())?;
+
+    let header = byte[0];
+
+    // Decode remaining length
+    let (mut length, mut multiplier) = (0, 1);
+
+    loop {
+        stream
+            .read_exact(&mut byte)
+            .map_err(|err| err.to_string())?;
+
+        length += (byte[0] & 0x7f) as usize * multiplier;
+        multiplier *= 128;
+
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+        if multiplier > 128 * 128 * 128 {
+            return Err("invalid packet length".to_string());
+        }
+    }
+
+    if length > PACKET_MAXIMUM_BYTES {
+        return Err(format!("packet is too large: {} bytes", length));
+    }
+
+    let mut body = vec![0; length];
+
+    stream
+        .read_exact(&mut body)
+        .map_err(|err| err.to_string())?;
+
+    if header != expected_header {
+        return Err(format!(
+            "unexpected packet: {:#04x} (expected: {:#04x})",
+            header, expected_header
+        ));
+    }
+
+    Ok(body)
+}
+
+fn expect_packet_id(body: &[u8], packet_id: u16) -> Result<(), String> {
+    if body.len() >= 2 && u16::from_be_bytes([body[0], body[1]]) == packet_id {
+        Ok(())
+    } else {
+        Err(format!(
+            "unexpected acknowledgment for packet: {}",
+            packet_id
+        ))
+    }
+}
+
+fn encode_string(buffer: &mut Vec<u8>, value: &str) {
+    buffer.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    buffer.extend_from_slice(value.as_bytes());
+}
+
+fn make_client_id(mqtt: &ConfigNotifyMQTT) -> String {
+    // Notice: a random suffix is appended, as brokers drop any existing session that uses \
+    //   the same client identifier (eg. when multiple Vigil instances share a broker).
+    let mut random = [0; 4];
+
+    if rand_bytes(&mut random).is_ok() == true {
+        format!(
+            "{}-{}",
+            mqtt.client_id,
+            random
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<String>()
+        )
+    } else {
+        mqtt.client_id.to_owned()
+    }
+}
@@ -0,0 +1,145 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+
+use super::generic::{GenericNotifier, Notification, DISPATCH_TIMEOUT_SECONDS};
+use super::template;
+use crate::config::config::ConfigNotify;
+use crate::prober::status::Status;
+use crate::APP_CONF;
+
+lazy_static! {
+    static ref NTFY_HTTP_CLIENT: Client = Client::builder()
+        .timeout(Duration::from_secs(DISPATCH_TIMEOUT_SECONDS))
+        .gzip(true)
+        .build()
+        .unwrap();
+}
+
+pub struct NtfyNotifier;
+
+impl GenericNotifier for NtfyNotifier {
+    fn attempt(notify: &ConfigNotify, notification: &Notification) -> Result<(), bool> {
+        if let Some(ref ntfy) = notify.ntfy {
+            // Build up the message text
+            let mut message = String::new();
+
+            if notification.test == true {
+                message.push_str("This is a test alert.\n\n");
+            } else if notification.startup == true {
+                message.push_str("This is a startup alert.\n\n");
+            } else if let Some(probe_id) = notification.flapping {
+                message.push_str(&format!("Service is flapping: {}.\n\n", probe_id));
+            } else if let Some(held) = notification.held {
+                message.push_str(&format!(
+                    "Quiet hours are over, {} notification(s) were held back.\n\n",
+                    held
+                ));
+            } else if let Some(suppressed) = notification.suppressed {
+                message.push_str(&format!(
+                    "Rate limit is over, {} notification(s) were suppressed.\n\n",
+                    suppressed
+                ));
+            } else if let Some(digest_label) = notification.digest_label() {
+                message.push_str(&format!("{}.\n\n", digest_label));
+            } else if notification.changed == false {
+                message.push_str(&format!(
+                    "This is a reminder{}.\n\n",
+                    notification.reminder_suffix()
+                ));
+            }
+
+            message.push_str(&format!(
+                "Status: {}\n",
+                notification.status.as_str().to_uppercase()
+            ));
+
+            if notification.replicas.len() > 0 {
+                message.push_str(&format!("Nodes:\n{}\n", &notification.replicas.join("\n")));
+            }
+
+            message.push_str(&format!("Time: {}", &notification.time));
+
+            for acknowledge_url in notification.acknowledge_urls() {
+                message.push_str(&format!("\nAcknowledge: {}", acknowledge_url));
+            }
+
+            // Apply channel template (if any)
+            let rendered = template::render(notify, notification);
+
+            if let Some(body) = rendered.body {
+                message = body;
+            }
+
+            debug!("will send ntfy notification with message: {}", &message);
+
+            // Generate URL
+            // See: https://docs.ntfy.sh/publish/
+            let url = format!(
+                "{}/{}",
+                ntfy.server_url.as_str().trim_end_matches('/'),
+                ntfy.topic
+            );
+
+            // Build request
+            let mut request = NTFY_HTTP_CLIENT
+                .post(&url)
+                .header(
+                    "Title",
+                    rendered
+                        .subject
+                        .as_deref()
+                        .unwrap_or(&APP_CONF.branding.page_title),
+                )
+                .header("Priority", status_to_priority(&notification.status))
+                .header("Tags", notification.status.as_str())
+                .header("Click", APP_CONF.branding.page_url.as_str())
+                .body(message);
+
+            if let Some(ref access_token) = ntfy.access_token {
+                request = request.bearer_auth(access_token);
+            }
+
+            // Submit message to ntfy
+            let response = request.send();
+
+            if let Ok(response_inner) = response {
+                if response_inner.status().is_success() != true {
+                    return Err(true);
+                }
+            } else {
+                return Err(true);
+            }
+
+            return Ok(());
+        }
+
+        Err(false)
+    }
+
+    fn can_notify(notify: &ConfigNotify, notification: &Notification) -> bool {
+        if let Some(ref ntfy_config) = notify.ntfy {
+            notification.expected(ntfy_config.reminders_only)
+        } else {
+            false
+        }
+    }
+
+    fn name() -> &'static str {
+        "ntfy"
+    }
+}
+
+fn status_to_priority(status: &Status) -> &'static str {
+    match status {
+        &Status::Healthy => "default",
+        &Status::Sick => "high",
+        &Status::Dead => "urgent",
+    }
+}
@@ -0,0 +1,247 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+
+use super::generic::{is_probe_routed, GenericNotifier, Notification, DISPATCH_TIMEOUT_SECONDS};
+use crate::config::config::{ConfigNotify, ConfigNotifyOpsgenie, ConfigNotifyOpsgeniePriority};
+use crate::prober::manager::STORE as PROBER_STORE;
+use crate::prober::status::Status;
+use crate::APP_CONF;
+
+static ALERT_SOURCE: &'static str = "vigil";
+static TEST_ALERT_ID: &'static str = "test";
+
+lazy_static! {
+    static ref OPSGENIE_HTTP_CLIENT: Client = Client::builder()
+        .timeout(Duration::from_secs(DISPATCH_TIMEOUT_SECONDS))
+        .gzip(true)
+        .build()
+        .unwrap();
+    static ref OPSGENIE_OPEN_ALIASES: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+pub struct OpsgenieNotifier;
+
+#[derive(Serialize)]
+struct OpsgenieCreatePayload<'a> {
+    message: String,
+    alias: &'a str,
+    description: String,
+    responders: Vec<OpsgenieCreatePayloadResponder<'a>>,
+    priority: &'a ConfigNotifyOpsgeniePriority,
+    source: &'static str,
+    tags: Vec<&'a str>,
+}
+
+#[derive(Serialize)]
+struct OpsgenieCreatePayloadResponder<'a> {
+    name: &'a str,
+
+    #[serde(rename = "type")]
+    kind: &'static str,
+}
+
+#[derive(Serialize)]
+struct OpsgenieClosePayload {
+    source: &'static str,
+    note: String,
+}
+
+impl GenericNotifier for OpsgenieNotifier {
+    fn attempt(notify: &ConfigNotify, notification: &Notification) -> Result<(), bool> {
+        if let Some(ref opsgenie) = notify.opsgenie {
+            // Test alert? (open then close a dedicated alert, leaving alerts of probes untouched)
+            if notification.test == true {
+                let alias = format!("vigil-{}", TEST_ALERT_ID);
+
+                create_alert(opsgenie, notification, TEST_ALERT_ID, "Test alert", &alias)?;
+
+                return close_alert(opsgenie, "Test alert", &alias);
+            }
+
+            // Acquire statuses of probes routed to channel (release lock as soon as possible)
+            let probe_statuses: Vec<(String, String, Status)> = {
+                PROBER_STORE
+                    .read()
+                    .unwrap()
+                    .states
+                    .probes
+                    .iter()
+                    .filter(|(probe_id, _)| is_probe_routed(probe_id, notification.channel))
+                    .map(|(probe_id, probe)| {
+                        (
+                            probe_id.to_owned(),
+                            probe.label.to_owned(),
+                            probe.status.to_owned(),
+                        )
+                    })
+                    .collect()
+            };
+
+            let mut open_aliases = OPSGENIE_OPEN_ALIASES.lock().unwrap();
+
+            for (probe_id, probe_label, probe_status) in probe_statuses {
+                let alias = format!("vigil-{}", probe_id);
+
+                if probe_status == Status::Dead {
+                    // Open alert for dead probe (Opsgenie de-duplicates alerts on their alias)
+                    create_alert(opsgenie, notification, &probe_id, &probe_label, &alias)?;
+
+                    open_aliases.insert(alias);
+                } else if notification.startup == true || open_aliases.contains(&alias) == true {
+                    // Close alert for recovered probe (also upon startup, as alerts might have \
+                    //   been left open before a restart)
+                    close_alert(opsgenie, &probe_label, &alias)?;
+
+                    open_aliases.remove(&alias);
+                }
+            }
+
+            return Ok(());
+        }
+
+        Err(false)
+    }
+
+    fn can_notify(notify: &ConfigNotify, notification: &Notification) -> bool {
+        // Notice: Opsgenie alerts are opened and closed per-probe from probe statuses, thus \
+        //   flapping alerts are not relevant there.
+        if let Some(ref opsgenie_config) = notify.opsgenie {
+            notification.flapping.is_none() == true
+                && notification.expected(opsgenie_config.reminders_only)
+        } else {
+            false
+        }
+    }
+
+    fn name() -> &'static str {
+        "opsgenie"
+    }
+}
+
+fn create_alert(
+    opsgenie: &ConfigNotifyOpsgenie,
+    notification: &Notification,
+    probe_id: &str,
+    probe_label: &str,
+    alias: &str,
+) -> Result<(), bool> {
+    let probe_service = APP_CONF
+        .probe
+        .service
+        .iter()
+        .find(|service| service.id == probe_id);
+
+    // Acquire team routing and priority (probe-level values take precedence)
+    let teams = probe_service
+        .and_then(|service| service.opsgenie_teams.as_ref())
+        .unwrap_or(&opsgenie.teams);
+    let priority = probe_service
+        .and_then(|service| service.opsgenie_priority.as_ref())
+        .unwrap_or(&opsgenie.priority);
+
+    // Build description from the dead replicas of this probe
+    let replica_prefix = format!("{}:", probe_id);
+
+    let status = if notification.test == true {
+        notification.status
+    } else {
+        &Status::Dead
+    };
+
+    let mut description = format!("Status: {}\n", status.as_str().to_uppercase());
+
+    for replica in notification.replicas.iter() {
+        if replica.starts_with(&replica_prefix) == true {
+            description.push_str(&format!("Node: {}\n", replica));
+        }
+    }
+
+    description.push_str(&format!("Time: {}\n", notification.time));
+    description.push_str(&format!(
+        "Monitor Page: {}",
+        APP_CONF.branding.page_url.as_str()
+    ));
+
+    let payload = OpsgenieCreatePayload {
+        message: if notification.test == true {
+            format!("{} from: {}", probe_label, APP_CONF.branding.page_title)
+        } else {
+            format!("{} is dead", probe_label)
+        },
+        alias: alias,
+        description: description,
+        responders: teams
+            .iter()
+            .map(|team| OpsgenieCreatePayloadResponder {
+                name: team,
+                kind: "team",
+            })
+            .collect(),
+        priority: priority,
+        source: ALERT_SOURCE,
+        tags: vec![ALERT_SOURCE, probe_id],
+    };
+
+    debug!("will create Opsgenie alert with alias: {}", alias);
+
+    // Submit alert to Opsgenie
+    // See: https://docs.opsgenie.com/docs/alert-api#create-alert
+    let response = OPSGENIE_HTTP_CLIENT
+        .post(&format!(
+            "{}/v2/alerts",
+            opsgenie.api_url.as_str().trim_end_matches('/')
+        ))
+        .header("Authorization", format!("GenieKey {}", opsgenie.api_key))
+        .json(&payload)
+        .send();
+
+    if let Ok(response_inner) = response {
+        if response_inner.status().is_success() == true {
+            return Ok(());
+        }
+    }
+
+    Err(true)
+}
+
+fn close_alert(
+    opsgenie: &ConfigNotifyOpsgenie,
+    probe_label: &str,
+    alias: &str,
+) -> Result<(), bool> {
+    let payload = OpsgenieClosePayload {
+        source: ALERT_SOURCE,
+        note: format!("{} has recovered", probe_label),
+    };
+
+    debug!("will close Opsgenie alert with alias: {}", alias);
+
+    // Close alert on Opsgenie
+    // See: https://docs.opsgenie.com/docs/alert-api#close-alert
+    let response = OPSGENIE_HTTP_CLIENT
+        .post(&format!(
+            "{}/v2/alerts/{}/close?identifierType=alias",
+            opsgenie.api_url.as_str().trim_end_matches('/'),
+            alias
+        ))
+        .header("Authorization", format!("GenieKey {}", opsgenie.api_key))
+        .json(&payload)
+        .send();
+
+    if let Ok(response_inner) = response {
+        if response_inner.status().is_success() == true {
+            return Ok(());
+        }
+    }
+
+    Err(true)
+}
@@ -10,6 +10,7 @@ use std::time::Duration;
 use reqwest::blocking::Client;
 
 use super::generic::{GenericNotifier, Notification, DISPATCH_TIMEOUT_SECONDS};
+use super::template;
 use crate::config::config::ConfigNotify;
 use crate::prober::status::Status;
 use crate::APP_CONF;
@@ -32,10 +33,32 @@ impl GenericNotifier for PushoverNotifier {
             // Build up the message text
             let mut message = String::new();
 
-            if notification.startup == true {
+            if notification.test == true {
+                message.push_str("<b><i>This is a test alert.</i></b>\n\n");
+            } else if notification.startup == true {
                 message.push_str("<b><i>This is a startup alert.</i></b>\n\n");
+            } else if let Some(probe_id) = notification.flapping {
+                message.push_str(&format!(
+                    "<b><i>Service is flapping: {}.</i></b>\n\n",
+                    probe_id
+                ));
+            } else if let Some(held) = notification.held {
+                message.push_str(&format!(
+                    "<b><i>Quiet hours are over, {} notification(s) were held back.</i></b>\n\n",
+                    held
+                ));
+            } else if let Some(suppressed) = notification.suppressed {
+                message.push_str(&format!(
+                    "<b><i>Rate limit is over, {} notification(s) were suppressed.</i></b>\n\n",
+                    suppressed
+                ));
+            } else if let Some(digest_label) = notification.digest_label() {
+                message.push_str(&format!("<b><i>{}.</i></b>\n\n", digest_label));
             } else if notification.changed == false {
-                message.push_str("<b><i>This is a reminder.</i></b>\n\n");
+                message.push_str(&format!(
+                    "<b><i>This is a reminder{}.</i></b>\n\n",
+                    notification.reminder_suffix()
+                ));
             }
 
             message.push_str(&format!(
@@ -49,6 +72,17 @@ impl GenericNotifier for PushoverNotifier {
             ));
             message.push_str(&format!("<u>Time:</u> {}", &notification.time));
 
+            for acknowledge_url in notification.acknowledge_urls() {
+                message.push_str(&format!("\n<u>Acknowledge:</u> {}", acknowledge_url));
+            }
+
+            // Apply channel template (if any)
+            let rendered = template::render(notify, notification);
+
+            if let Some(body) = rendered.body {
+                message = body;
+            }
+
             debug!("will send Pushover notification with message: {}", &message);
 
             let mut has_sub_delivery_failure = false;
@@ -62,7 +96,13 @@ impl GenericNotifier for PushoverNotifier {
                 params.insert("user", user_key);
 
                 // Append title & message
-                params.insert("title", &APP_CONF.branding.page_title);
+                params.insert(
+                    "title",
+                    rendered
+                        .subject
+                        .as_deref()
+                        .unwrap_or(&APP_CONF.branding.page_title),
+                );
                 params.insert("message", &message);
                 params.insert("html", "1");
 
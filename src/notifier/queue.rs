@@ -0,0 +1,425 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use openssl::rand::rand_bytes;
+
+use super::generic::{find_channel, Notification};
+use super::registry;
+use crate::prober::status::Status;
+use crate::APP_CONF;
+
+const QUEUE_TICK_INTERVAL_SECONDS: u64 = 1;
+
+lazy_static! {
+    static ref QUEUE: Mutex<Queue> = Mutex::new(Queue {
+        next_id: 0,
+        entries: Vec::new(),
+    });
+}
+
+struct Queue {
+    next_id: u64,
+    entries: Vec<QueuedNotification>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct QueuedNotification {
+    #[serde(skip)]
+    id: u64,
+
+    notifier: String,
+    channel: String,
+    status: Status,
+    time: String,
+    replicas: Vec<String>,
+
+    #[serde(default)]
+    diagnostics: Vec<(String, String)>,
+
+    changed: bool,
+    startup: bool,
+    reminder: Option<u16>,
+    flapping: Option<String>,
+
+    #[serde(default)]
+    held: Option<u32>,
+
+    #[serde(default)]
+    suppressed: Option<u32>,
+
+    #[serde(default)]
+    digest: Option<Vec<(String, Status)>>,
+
+    #[serde(default)]
+    downtime: Option<u64>,
+
+    attempts: u32,
+    retry_at: u64,
+}
+
+#[derive(Serialize)]
+struct DeadLetter<'a> {
+    failed_at: u64,
+
+    #[serde(flatten)]
+    notification: &'a QueuedNotification,
+}
+
+impl QueuedNotification {
+    fn as_notification(&self) -> Notification {
+        Notification {
+            channel: &self.channel,
+            status: &self.status,
+            time: self.time.to_owned(),
+            replicas: self.replicas.iter().map(String::as_str).collect(),
+            diagnostics: self
+                .diagnostics
+                .iter()
+                .map(|(replica, output)| (replica.as_str(), output.as_str()))
+                .collect(),
+            changed: self.changed,
+            startup: self.startup,
+            reminder: self.reminder,
+            flapping: self.flapping.as_deref(),
+            held: self.held,
+            suppressed: self.suppressed,
+            digest: self.digest.as_ref().map(|digest| {
+                digest
+                    .iter()
+                    .map(|(probe_id, status)| (probe_id.as_str(), status))
+                    .collect()
+            }),
+            downtime: self.downtime.map(Duration::from_secs),
+            test: false,
+        }
+    }
+}
+
+pub fn initialize() {
+    // Restore notifications that were still pending when Vigil last stopped (if persisted)
+    if let Some(ref path) = APP_CONF
+        .notify
+        .as_ref()
+        .and_then(|notify| notify.queue.path.as_ref())
+    {
+        if path.exists() == false {
+            return;
+        }
+
+        match fs::read(path)
+            .map_err(|err| err.to_string())
+            .and_then(|data| {
+                serde_json::from_slice::<Vec<QueuedNotification>>(&data)
+                    .map_err(|err| err.to_string())
+            }) {
+            Ok(entries) => {
+                let mut queue = QUEUE.lock().unwrap();
+
+                for mut entry in entries {
+                    entry.id = queue.next_id;
+                    queue.next_id += 1;
+
+                    queue.entries.push(entry);
+                }
+
+                info!(
+                    "restored {} pending notification(s) from queue",
+                    queue.entries.len()
+                );
+            }
+            Err(err) => error!("could not restore notifier queue: {}", err),
+        }
+    }
+}
+
+pub fn depth() -> usize {
+    QUEUE.lock().unwrap().entries.len()
+}
+
+pub fn is_pending(notifier: &str, channel: &str) -> bool {
+    QUEUE
+        .lock()
+        .unwrap()
+        .entries
+        .iter()
+        .any(|entry| entry.notifier == notifier && entry.channel == channel)
+}
+
+pub fn enqueue(notifier: &str, notification: &Notification, attempts: u32) {
+    let mut entry = QueuedNotification {
+        id: 0,
+        notifier: notifier.to_owned(),
+        channel: notification.channel.to_owned(),
+        status: notification.status.to_owned(),
+        time: notification.time.to_owned(),
+        replicas: notification
+            .replicas
+            .iter()
+            .map(|replica| replica.to_string())
+            .collect(),
+        diagnostics: notification
+            .diagnostics
+            .iter()
+            .map(|(replica, output)| (replica.to_string(), output.to_string()))
+            .collect(),
+        changed: notification.changed,
+        startup: notification.startup,
+        reminder: notification.reminder,
+        flapping: notification.flapping.map(str::to_owned),
+        held: notification.held,
+        suppressed: notification.suppressed,
+        digest: notification.digest.as_ref().map(|digest| {
+            digest
+                .iter()
+                .map(|(probe_id, status)| (probe_id.to_string(), (*status).to_owned()))
+                .collect()
+        }),
+        downtime: notification.downtime.map(|downtime| downtime.as_secs()),
+        attempts: attempts,
+        retry_at: 0,
+    };
+
+    // Retries are disabled? (give up right away)
+    if attempts > retry_attempts() {
+        dead_letter(&entry);
+
+        return;
+    }
+
+    // Notice: notifications that never got attempted (ie. queued behind pending ones) are due \
+    //   right away, as they will only be processed once the notifications they follow are gone.
+    entry.retry_at = if attempts == 0 {
+        now_seconds()
+    } else {
+        now_seconds() + backoff_seconds(attempts)
+    };
+
+    let mut queue = QUEUE.lock().unwrap();
+
+    entry.id = queue.next_id;
+    queue.next_id += 1;
+
+    queue.entries.push(entry);
+
+    persist(&queue);
+}
+
+pub fn run() {
+    loop {
+        process_due();
+
+        thread::sleep(Duration::from_secs(QUEUE_TICK_INTERVAL_SECONDS));
+    }
+}
+
+fn process_due() {
+    let now = now_seconds();
+
+    // Pick the first entry of each notifier channel, if it is due
+    // Notice: only the first entry per notifier channel is retried, so that notifications are \
+    //   delivered in the order they were emitted. The queue lock is not held while sending.
+    let due_entries = {
+        let queue = QUEUE.lock().unwrap();
+
+        let mut seen: Vec<(&str, &str)> = Vec::new();
+        let mut due_entries = Vec::new();
+
+        for entry in queue.entries.iter() {
+            let key = (entry.notifier.as_str(), entry.channel.as_str());
+
+            if seen.contains(&key) == true {
+                continue;
+            }
+
+            seen.push(key);
+
+            if entry.retry_at <= now {
+                due_entries.push(entry.clone());
+            }
+        }
+
+        due_entries
+    };
+
+    for entry in due_entries {
+        debug!(
+            "retrying queued {} notification on channel: {} (attempt: #{})",
+            entry.notifier,
+            entry.channel,
+            entry.attempts + 1
+        );
+
+        let result = find_channel(&entry.channel)
+            .ok_or(false)
+            .and_then(|notify| {
+                registry::attempt(&entry.notifier, notify, &entry.as_notification())
+            });
+
+        let mut queue = QUEUE.lock().unwrap();
+
+        let position = match queue.entries.iter().position(|item| item.id == entry.id) {
+            Some(position) => position,
+            None => continue,
+        };
+
+        match result {
+            Ok(name) => {
+                info!(
+                    "dispatched queued notification to provider: {} on channel: {}",
+                    name, entry.channel
+                );
+
+                queue.entries.remove(position);
+
+                Notification::count(name, true);
+            }
+            Err(false) => {
+                warn!(
+                    "dropped queued {} notification, as channel: {} cannot notify anymore",
+                    entry.notifier, entry.channel
+                );
+
+                queue.entries.remove(position);
+            }
+            Err(true) => {
+                let attempts = entry.attempts + 1;
+
+                if attempts > retry_attempts() {
+                    let entry = queue.entries.remove(position);
+
+                    dead_letter(&QueuedNotification {
+                        attempts: attempts,
+                        ..entry
+                    });
+                } else {
+                    let delay = backoff_seconds(attempts);
+
+                    warn!(
+                        "failed dispatching queued {} notification, retrying in {}s",
+                        entry.notifier, delay
+                    );
+
+                    let queued = &mut queue.entries[position];
+
+                    queued.attempts = attempts;
+                    queued.retry_at = now_seconds() + delay;
+                }
+            }
+        }
+
+        persist(&queue);
+    }
+}
+
+fn dead_letter(entry: &QueuedNotification) {
+    error!(
+        "gave up dispatching {} notification on channel: {} after {} attempt(s)",
+        entry.notifier, entry.channel, entry.attempts
+    );
+
+    // Notice: the failure counter is only bumped once retries are exhausted, as a notification \
+    //   that eventually got delivered is not considered as failed.
+    if let Some(name) = registry::find(&entry.notifier) {
+        Notification::count(name, false);
+    }
+
+    if let Some(ref path) = APP_CONF
+        .notify
+        .as_ref()
+        .and_then(|notify| notify.queue.dead_letter_path.as_ref())
+    {
+        let line = serde_json::to_string(&DeadLetter {
+            failed_at: now_seconds(),
+            notification: entry,
+        });
+
+        let result = line.map_err(|err| err.to_string()).and_then(|line| {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .and_then(|mut file| writeln!(file, "{}", line))
+                .map_err(|err| err.to_string())
+        });
+
+        if let Err(err) = result {
+            error!("could not write to notifier dead-letter log: {}", err);
+        }
+    }
+}
+
+fn persist(queue: &Queue) {
+    if let Some(ref path) = APP_CONF
+        .notify
+        .as_ref()
+        .and_then(|notify| notify.queue.path.as_ref())
+    {
+        if let Err(err) = write_atomic(path, &queue.entries) {
+            error!("could not persist notifier queue: {}", err);
+        }
+    }
+}
+
+fn write_atomic(path: &Path, entries: &Vec<QueuedNotification>) -> Result<(), String> {
+    let data = serde_json::to_vec(entries).map_err(|err| err.to_string())?;
+
+    // Write to a temporary file first (so that a crash never leaves a partially-written queue)
+    let path_temporary = path.with_extension("tmp");
+
+    fs::write(&path_temporary, data).map_err(|err| err.to_string())?;
+    fs::rename(&path_temporary, path).map_err(|err| err.to_string())
+}
+
+fn retry_attempts() -> u32 {
+    APP_CONF
+        .notify
+        .as_ref()
+        .map(|notify| notify.queue.retry_attempts)
+        .unwrap_or(0)
+}
+
+fn backoff_seconds(attempts: u32) -> u64 {
+    let (delay, delay_maximum) = APP_CONF
+        .notify
+        .as_ref()
+        .map(|notify| (notify.queue.retry_delay, notify.queue.retry_delay_maximum))
+        .unwrap_or((1, 1));
+
+    // Exponential backoff, capped to the maximum delay
+    let base = delay
+        .saturating_mul(
+            1_u64
+                .checked_shl(attempts.saturating_sub(1))
+                .unwrap_or(u64::MAX),
+        )
+        .min(delay_maximum);
+
+    // Notice: apply a jitter of up to half of the delay, so that notifications that failed \
+    //   together (eg. the provider went down) do not all get retried at the same time.
+    let mut random = [0; 8];
+
+    let jitter = if rand_bytes(&mut random).is_ok() == true && base > 1 {
+        u64::from_le_bytes(random) % (base / 2 + 1)
+    } else {
+        0
+    };
+
+    base - base / 2 + jitter
+}
+
+fn now_seconds() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,103 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use time::{OffsetDateTime, UtcOffset, Weekday};
+
+use crate::config::config::{ConfigNotify, ConfigNotifyQuietHours, ConfigNotifyQuietHoursDay};
+
+pub fn active(notify: &ConfigNotify) -> Option<&ConfigNotifyQuietHours> {
+    let now = OffsetDateTime::now_utc();
+
+    notify
+        .quiet_hours
+        .iter()
+        .find(|quiet_hours| is_active(quiet_hours, now) == true)
+}
+
+pub fn parse_time(value: &str) -> Result<u16, String> {
+    // Parse time of day (eg. '22:30'), as a number of minutes since midnight
+    let (hours, minutes) = value.split_once(':').ok_or(format!(
+        "expected a time formatted as HH:MM, got: {}",
+        value
+    ))?;
+
+    match (hours.parse::<u16>(), minutes.parse::<u16>()) {
+        (Ok(hours_value), Ok(minutes_value))
+            if hours_value < 24 && minutes_value < 60 && minutes.len() == 2 =>
+        {
+            Ok(hours_value * 60 + minutes_value)
+        }
+        _ => Err(format!("invalid time: {}", value)),
+    }
+}
+
+pub fn parse_utc_offset(value: &str) -> Result<UtcOffset, String> {
+    // Parse UTC offset (eg. '+02:00' or '-05:30')
+    let (sign, offset) = if let Some(offset) = value.strip_prefix('+') {
+        (1, offset)
+    } else if let Some(offset) = value.strip_prefix('-') {
+        (-1, offset)
+    } else {
+        return Err(format!(
+            "expected an offset formatted as +HH:MM, got: {}",
+            value
+        ));
+    };
+
+    let minutes = parse_time(offset).map_err(|_| format!("invalid UTC offset: {}", value))?;
+
+    UtcOffset::from_whole_seconds(sign * (minutes as i32) * 60)
+        .map_err(|_| format!("out of range UTC offset: {}", value))
+}
+
+fn is_active(quiet_hours: &ConfigNotifyQuietHours, now: OffsetDateTime) -> bool {
+    // Notice: windows are checked when the configuration is read, thus invalid values cannot \
+    //   happen there (they are considered inactive if they ever do).
+    let (from, to, offset) = match (
+        parse_time(&quiet_hours.from),
+        parse_time(&quiet_hours.to),
+        parse_utc_offset(&quiet_hours.utc_offset),
+    ) {
+        (Ok(from), Ok(to), Ok(offset)) => (from, to, offset),
+        _ => return false,
+    };
+
+    let local = now.to_offset(offset);
+    let minute = local.hour() as u16 * 60 + local.minute() as u16;
+
+    let (today, yesterday) = (local.weekday(), local.weekday().previous());
+
+    if from < to {
+        // Window within a day (eg. '12:00' to '14:00')
+        matches_day(quiet_hours, today) == true && minute >= from && minute < to
+    } else if from > to {
+        // Window over midnight (eg. '22:00' to '07:00'), which belongs to the day it starts on
+        (matches_day(quiet_hours, today) == true && minute >= from)
+            || (matches_day(quiet_hours, yesterday) == true && minute < to)
+    } else {
+        // Window over the whole day (ie. same start and end time)
+        matches_day(quiet_hours, today) == true
+    }
+}
+
+fn matches_day(quiet_hours: &ConfigNotifyQuietHours, weekday: Weekday) -> bool {
+    // No day set? (window applies to every day)
+    if quiet_hours.days.is_empty() == true {
+        return true;
+    }
+
+    let day = match weekday {
+        Weekday::Monday => ConfigNotifyQuietHoursDay::Monday,
+        Weekday::Tuesday => ConfigNotifyQuietHoursDay::Tuesday,
+        Weekday::Wednesday => ConfigNotifyQuietHoursDay::Wednesday,
+        Weekday::Thursday => ConfigNotifyQuietHoursDay::Thursday,
+        Weekday::Friday => ConfigNotifyQuietHoursDay::Friday,
+        Weekday::Saturday => ConfigNotifyQuietHoursDay::Saturday,
+        Weekday::Sunday => ConfigNotifyQuietHoursDay::Sunday,
+    };
+
+    quiet_hours.days.contains(&day)
+}
@@ -0,0 +1,249 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use super::generic::{GenericNotifier, Notification};
+use crate::config::config::ConfigNotify;
+
+#[cfg(feature = "notifier-email")]
+use super::email::EmailNotifier;
+
+#[cfg(feature = "notifier-twilio")]
+use super::twilio::TwilioNotifier;
+
+#[cfg(feature = "notifier-slack")]
+use super::slack::SlackNotifier;
+
+#[cfg(feature = "notifier-zulip")]
+use super::zulip::ZulipNotifier;
+
+#[cfg(feature = "notifier-telegram")]
+use super::telegram::TelegramNotifier;
+
+#[cfg(feature = "notifier-pushover")]
+use super::pushover::PushoverNotifier;
+
+#[cfg(feature = "notifier-gotify")]
+use super::gotify::GotifyNotifier;
+
+#[cfg(feature = "notifier-xmpp")]
+use super::xmpp::XMPPNotifier;
+
+#[cfg(feature = "notifier-matrix")]
+use super::matrix::MatrixNotifier;
+
+#[cfg(feature = "notifier-webhook")]
+use super::webhook::WebHookNotifier;
+
+#[cfg(feature = "notifier-opsgenie")]
+use super::opsgenie::OpsgenieNotifier;
+
+#[cfg(feature = "notifier-ntfy")]
+use super::ntfy::NtfyNotifier;
+
+#[cfg(feature = "notifier-teams")]
+use super::teams::TeamsNotifier;
+
+#[cfg(feature = "notifier-discord")]
+use super::discord::DiscordNotifier;
+
+#[cfg(feature = "notifier-mattermost")]
+use super::mattermost::MattermostNotifier;
+
+#[cfg(feature = "notifier-rocketchat")]
+use super::rocketchat::RocketChatNotifier;
+
+#[cfg(feature = "notifier-googlechat")]
+use super::googlechat::GoogleChatNotifier;
+
+#[cfg(feature = "notifier-signal")]
+use super::signal::SignalNotifier;
+
+#[cfg(feature = "notifier-sms")]
+use super::sms::SMSNotifier;
+
+#[cfg(feature = "notifier-sns")]
+use super::sns::SNSNotifier;
+
+#[cfg(feature = "notifier-mqtt")]
+use super::mqtt::MQTTNotifier;
+
+#[cfg(feature = "notifier-irc")]
+use super::irc::IRCNotifier;
+
+#[cfg(feature = "notifier-apprise")]
+use super::apprise::AppriseNotifier;
+
+pub fn names() -> Vec<&'static str> {
+    let mut names = Vec::new();
+
+    #[cfg(feature = "notifier-email")]
+    names.push("email");
+
+    #[cfg(feature = "notifier-twilio")]
+    names.push("twilio");
+
+    #[cfg(feature = "notifier-slack")]
+    names.push("slack");
+
+    #[cfg(feature = "notifier-zulip")]
+    names.push("zulip");
+
+    #[cfg(feature = "notifier-telegram")]
+    names.push("telegram");
+
+    #[cfg(feature = "notifier-pushover")]
+    names.push("pushover");
+
+    #[cfg(feature = "notifier-gotify")]
+    names.push("gotify");
+
+    #[cfg(feature = "notifier-xmpp")]
+    names.push("xmpp");
+
+    #[cfg(feature = "notifier-matrix")]
+    names.push("matrix");
+
+    #[cfg(feature = "notifier-webhook")]
+    names.push("webhook");
+
+    #[cfg(feature = "notifier-opsgenie")]
+    names.push("opsgenie");
+
+    #[cfg(feature = "notifier-ntfy")]
+    names.push("ntfy");
+
+    #[cfg(feature = "notifier-teams")]
+    names.push("teams");
+
+    #[cfg(feature = "notifier-discord")]
+    names.push("discord");
+
+    #[cfg(feature = "notifier-mattermost")]
+    names.push("mattermost");
+
+    #[cfg(feature = "notifier-rocketchat")]
+    names.push("rocketchat");
+
+    #[cfg(feature = "notifier-googlechat")]
+    names.push("googlechat");
+
+    #[cfg(feature = "notifier-signal")]
+    names.push("signal");
+
+    #[cfg(feature = "notifier-sms")]
+    names.push("sms");
+
+    #[cfg(feature = "notifier-sns")]
+    names.push("sns");
+
+    #[cfg(feature = "notifier-mqtt")]
+    names.push("mqtt");
+
+    #[cfg(feature = "notifier-irc")]
+    names.push("irc");
+
+    #[cfg(feature = "notifier-apprise")]
+    names.push("apprise");
+
+    names
+}
+
+pub fn find(name: &str) -> Option<&'static str> {
+    names().into_iter().find(|known_name| *known_name == name)
+}
+
+pub fn attempt(
+    name: &str,
+    notify: &ConfigNotify,
+    notification: &Notification,
+) -> Result<&'static str, bool> {
+    match name {
+        #[cfg(feature = "notifier-email")]
+        "email" => attempt_with::<EmailNotifier>(notify, notification),
+
+        #[cfg(feature = "notifier-twilio")]
+        "twilio" => attempt_with::<TwilioNotifier>(notify, notification),
+
+        #[cfg(feature = "notifier-slack")]
+        "slack" => attempt_with::<SlackNotifier>(notify, notification),
+
+        #[cfg(feature = "notifier-zulip")]
+        "zulip" => attempt_with::<ZulipNotifier>(notify, notification),
+
+        #[cfg(feature = "notifier-telegram")]
+        "telegram" => attempt_with::<TelegramNotifier>(notify, notification),
+
+        #[cfg(feature = "notifier-pushover")]
+        "pushover" => attempt_with::<PushoverNotifier>(notify, notification),
+
+        #[cfg(feature = "notifier-gotify")]
+        "gotify" => attempt_with::<GotifyNotifier>(notify, notification),
+
+        #[cfg(feature = "notifier-xmpp")]
+        "xmpp" => attempt_with::<XMPPNotifier>(notify, notification),
+
+        #[cfg(feature = "notifier-matrix")]
+        "matrix" => attempt_with::<MatrixNotifier>(notify, notification),
+
+        #[cfg(feature = "notifier-webhook")]
+        "webhook" => attempt_with::<WebHookNotifier>(notify, notification),
+
+        #[cfg(feature = "notifier-opsgenie")]
+        "opsgenie" => attempt_with::<OpsgenieNotifier>(notify, notification),
+
+        #[cfg(feature = "notifier-ntfy")]
+        "ntfy" => attempt_with::<NtfyNotifier>(notify, notification),
+
+        #[cfg(feature = "notifier-teams")]
+        "teams" => attempt_with::<TeamsNotifier>(notify, notification),
+
+        #[cfg(feature = "notifier-discord")]
+        "discord" => attempt_with::<DiscordNotifier>(notify, notification),
+
+        #[cfg(feature = "notifier-mattermost")]
+        "mattermost" => attempt_with::<MattermostNotifier>(notify, notification),
+
+        #[cfg(feature = "notifier-rocketchat")]
+        "rocketchat" => attempt_with::<RocketChatNotifier>(notify, notification),
+
+        #[cfg(feature = "notifier-googlechat")]
+        "googlechat" => attempt_with::<GoogleChatNotifier>(notify, notification),
+
+        #[cfg(feature = "notifier-signal")]
+        "signal" => attempt_with::<SignalNotifier>(notify, notification),
+
+        #[cfg(feature = "notifier-sms")]
+        "sms" => attempt_with::<SMSNotifier>(notify, notification),
+
+        #[cfg(feature = "notifier-sns")]
+        "sns" => attempt_with::<SNSNotifier>(notify, notification),
+
+        #[cfg(feature = "notifier-mqtt")]
+        "mqtt" => attempt_with::<MQTTNotifier>(notify, notification),
+
+        #[cfg(feature = "notifier-irc")]
+        "irc" => attempt_with::<IRCNotifier>(notify, notification),
+
+        #[cfg(feature = "notifier-apprise")]
+        "apprise" => attempt_with::<AppriseNotifier>(notify, notification),
+
+        _ => Err(false),
+    }
+}
+
+fn attempt_with<N: GenericNotifier>(
+    notify: &ConfigNotify,
+    notification: &Notification,
+) -> Result<&'static str, bool> {
+    // Notifier is not configured on this channel? (or does not handle this notification)
+    if N::can_notify(notify, notification) == false {
+        return Err(false);
+    }
+
+    Notification::attempt::<N>(notify, notification)
+        .map(|_| N::name())
+        .map_err(|_| true)
+}
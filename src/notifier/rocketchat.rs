@@ -0,0 +1,202 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+
+use super::generic::{GenericNotifier, Notification, DISPATCH_TIMEOUT_SECONDS};
+use super::template;
+use crate::config::config::ConfigNotify;
+use crate::prober::status::Status;
+use crate::APP_CONF;
+
+lazy_static! {
+    static ref ROCKETCHAT_HTTP_CLIENT: Client = Client::builder()
+        .timeout(Duration::from_secs(DISPATCH_TIMEOUT_SECONDS))
+        .gzip(true)
+        .build()
+        .unwrap();
+}
+
+pub struct RocketChatNotifier;
+
+#[derive(Serialize)]
+struct RocketChatPayload<'a> {
+    text: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alias: Option<&'a str>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    channel: Option<&'a str>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    emoji: Option<&'a str>,
+
+    attachments: Vec<RocketChatPayloadAttachment<'a>>,
+}
+
+#[derive(Serialize)]
+struct RocketChatPayloadAttachment<'a> {
+    title: &'a str,
+    title_link: &'a str,
+    color: &'a str,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+
+    fields: Vec<RocketChatPayloadAttachmentField<'a>>,
+}
+
+#[derive(Serialize)]
+struct RocketChatPayloadAttachmentField<'a> {
+    title: &'a str,
+    value: String,
+    short: bool,
+}
+
+impl GenericNotifier for RocketChatNotifier {
+    fn attempt(notify: &ConfigNotify, notification: &Notification) -> Result<(), bool> {
+        if let Some(ref rocketchat) = notify.rocketchat {
+            // Build message
+            let message_text = if notification.test == true {
+                format!(
+                    "This is a test alert, status is: *{}*.",
+                    notification.status.as_str()
+                )
+            } else if notification.startup == true {
+                format!("Status started up, as: *{}*.", notification.status.as_str())
+            } else if let Some(probe_id) = notification.flapping {
+                format!("Service is flapping: *{}*.", probe_id)
+            } else if let Some(held) = notification.held {
+                format!(
+                    "Quiet hours are over ({} held back), status is: *{}*.",
+                    held,
+                    notification.status.as_str()
+                )
+            } else if let Some(suppressed) = notification.suppressed {
+                format!(
+                    "Rate limit is over ({} suppressed), status is: *{}*.",
+                    suppressed,
+                    notification.status.as_str()
+                )
+            } else if let Some(digest_label) = notification.digest_label() {
+                format!(
+                    "Status changed to: *{}* ({}).",
+                    notification.status.as_str(),
+                    digest_label
+                )
+            } else if notification.changed == true {
+                format!("Status changed to: *{}*.", notification.status.as_str())
+            } else {
+                format!(
+                    "Status is still: *{}* (reminder{}).",
+                    notification.status.as_str(),
+                    notification.reminder_suffix()
+                )
+            };
+
+            // Apply channel template (if any)
+            // Notice: templated bodies go to the attachment text, as the message text is what \
+            //   shows in push notifications and should remain short.
+            let rendered = template::render(notify, notification);
+
+            let message_text = if rocketchat.mention_all == true {
+                format!("@all {}", &message_text)
+            } else {
+                message_text
+            };
+
+            // Build attachment
+            let mut attachment = RocketChatPayloadAttachment {
+                title: rendered
+                    .subject
+                    .as_deref()
+                    .unwrap_or(&APP_CONF.branding.page_title),
+                title_link: APP_CONF.branding.page_url.as_str(),
+                color: status_to_color(&notification.status),
+                text: rendered.body,
+                fields: Vec::new(),
+            };
+
+            if notification.replicas.len() > 0 {
+                attachment.fields.push(RocketChatPayloadAttachmentField {
+                    title: "Nodes",
+                    value: notification.replicas.join("\n"),
+                    short: false,
+                });
+            }
+
+            attachment.fields.push(RocketChatPayloadAttachmentField {
+                title: "Status",
+                value: format!("{:?}", notification.status),
+                short: true,
+            });
+
+            attachment.fields.push(RocketChatPayloadAttachmentField {
+                title: "Time",
+                value: notification.time.to_owned(),
+                short: true,
+            });
+
+            let acknowledge_urls = notification.acknowledge_urls();
+
+            if acknowledge_urls.is_empty() == false {
+                attachment.fields.push(RocketChatPayloadAttachmentField {
+                    title: "Acknowledge",
+                    value: acknowledge_urls.join("\n"),
+                    short: false,
+                });
+            }
+
+            // Build payload
+            let payload = RocketChatPayload {
+                text: message_text,
+                alias: rocketchat.alias.as_deref(),
+                channel: rocketchat.channel.as_deref(),
+                emoji: rocketchat.emoji.as_deref(),
+                attachments: vec![attachment],
+            };
+
+            // Submit payload to Rocket.Chat
+            let response = ROCKETCHAT_HTTP_CLIENT
+                .post(rocketchat.hook_url.as_str())
+                .json(&payload)
+                .send();
+
+            if let Ok(response_inner) = response {
+                if response_inner.status().is_success() == true {
+                    return Ok(());
+                }
+            }
+
+            return Err(true);
+        }
+
+        Err(false)
+    }
+
+    fn can_notify(notify: &ConfigNotify, notification: &Notification) -> bool {
+        if let Some(ref rocketchat_config) = notify.rocketchat {
+            notification.expected(rocketchat_config.reminders_only)
+        } else {
+            false
+        }
+    }
+
+    fn name() -> &'static str {
+        "rocketchat"
+    }
+}
+
+fn status_to_color(status: &Status) -> &'static str {
+    match status {
+        &Status::Healthy => "#2eb886",
+        &Status::Sick => "#daa038",
+        &Status::Dead => "#a30200",
+    }
+}
@@ -0,0 +1,126 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+
+use super::generic::{GenericNotifier, Notification, DISPATCH_TIMEOUT_SECONDS};
+use super::template;
+use crate::config::config::ConfigNotify;
+use crate::APP_CONF;
+
+lazy_static! {
+    static ref SIGNAL_HTTP_CLIENT: Client = Client::builder()
+        .timeout(Duration::from_secs(DISPATCH_TIMEOUT_SECONDS))
+        .gzip(true)
+        .build()
+        .unwrap();
+}
+
+pub struct SignalNotifier;
+
+#[derive(Serialize)]
+struct SignalPayload<'a> {
+    message: String,
+    number: &'a str,
+    recipients: &'a [String],
+}
+
+impl GenericNotifier for SignalNotifier {
+    fn attempt(notify: &ConfigNotify, notification: &Notification) -> Result<(), bool> {
+        if let Some(ref signal) = notify.signal {
+            // Build up the message text
+            let mut message = String::new();
+
+            if notification.test == true {
+                message.push_str("Test alert for: ");
+            } else if notification.startup == true {
+                message.push_str("Startup alert for: ");
+            } else if let Some(probe_id) = notification.flapping {
+                message.push_str(&format!("Flapping alert ({}) for: ", probe_id));
+            } else if let Some(held) = notification.held {
+                message.push_str(&format!("Quiet hours summary ({} held back) for: ", held));
+            } else if let Some(suppressed) = notification.suppressed {
+                message.push_str(&format!(
+                    "Rate limit summary ({} suppressed) for: ",
+                    suppressed
+                ));
+            } else if let Some(digest_label) = notification.digest_label() {
+                message.push_str(&format!("Digest ({}) for: ", digest_label));
+            } else if notification.changed == false {
+                message.push_str(&format!("Reminder{} for: ", notification.reminder_suffix()));
+            }
+
+            message.push_str(&format!("{}\n", APP_CONF.branding.page_title));
+            message.push_str("\n");
+            message.push_str(&format!(
+                "Status: {}\n",
+                notification.status.as_str().to_uppercase()
+            ));
+
+            if notification.replicas.len() > 0 {
+                message.push_str(&format!("Nodes:\n{}\n", &notification.replicas.join("\n")));
+            }
+
+            if let Some(downtime_label) = notification.downtime_label() {
+                message.push_str(&format!("Downtime: {}\n", downtime_label));
+            }
+
+            message.push_str(&format!("Time: {}\n", &notification.time));
+            message.push_str(&format!("URL: {}", APP_CONF.branding.page_url.as_str()));
+
+            for acknowledge_url in notification.acknowledge_urls() {
+                message.push_str(&format!("\nAcknowledge: {}", acknowledge_url));
+            }
+
+            // Apply channel template (if any)
+            if let Some(body) = template::render(notify, notification).body {
+                message = body;
+            }
+
+            debug!("will send Signal notification with message: {}", &message);
+
+            // Build payload
+            // Notice: recipients can either be phone numbers or group identifiers (as listed by \
+            //   signal-cli), a single request is sent for all recipients.
+            let payload = SignalPayload {
+                message: message,
+                number: &signal.number,
+                recipients: &signal.recipients,
+            };
+
+            // Generate URL
+            // See: https://bbernhard.github.io/signal-cli-rest-api/
+            let url = format!("{}/v2/send", signal.api_url.as_str().trim_end_matches('/'));
+
+            // Submit message to signal-cli REST API
+            let response = SIGNAL_HTTP_CLIENT.post(&url).json(&payload).send();
+
+            if let Ok(response_inner) = response {
+                if response_inner.status().is_success() == true {
+                    return Ok(());
+                }
+            }
+
+            return Err(true);
+        }
+
+        Err(false)
+    }
+
+    fn can_notify(notify: &ConfigNotify, notification: &Notification) -> bool {
+        if let Some(ref signal_config) = notify.signal {
+            notification.expected(signal_config.reminders_only)
+        } else {
+            false
+        }
+    }
+
+    fn name() -> &'static str {
+        "signal"
+    }
+}
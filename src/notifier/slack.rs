@@ -4,15 +4,27 @@
 // Copyright: 2018, Valerian Saliou <valerian@valeriansaliou.name>
 // License: Mozilla Public License v2.0 (MPL v2.0)
 
+use std::collections::HashMap;
 use std::time::Duration;
 
 use reqwest::blocking::Client;
 
 use super::generic::{GenericNotifier, Notification, DISPATCH_TIMEOUT_SECONDS};
-use crate::config::config::ConfigNotify;
+use super::template;
+use crate::config::config::{ConfigNotify, ConfigNotifySlack};
+use crate::prober::manager::SNAPSHOT as PROBER_SNAPSHOT;
 use crate::prober::status::Status;
+use crate::storage::uptime;
 use crate::APP_CONF;
 
+// Notice: Slack caps the number of attachments and action elements per message, thus replicas \
+//   and acknowledgment buttons past those limits are only counted.
+const REPLICAS_MAXIMUM: usize = 20;
+const ACKNOWLEDGE_BUTTONS_MAXIMUM: usize = 20;
+const BUTTON_TEXT_MAXIMUM_CHARS: usize = 75;
+
+static UPTIME_WINDOW: &'static str = "30d";
+
 lazy_static! {
     static ref SLACK_HTTP_CLIENT: Client = Client::builder()
         .timeout(Duration::from_secs(DISPATCH_TIMEOUT_SECONDS))
@@ -24,94 +36,170 @@ lazy_static! {
 pub struct SlackNotifier;
 
 #[derive(Serialize)]
-struct SlackPayload<'a> {
+struct SlackPayload {
     text: String,
-    attachments: Vec<SlackPayloadAttachment<'a>>,
+    blocks: Vec<SlackPayloadBlock>,
+    attachments: Vec<SlackPayloadAttachment>,
+}
+
+#[derive(Serialize)]
+struct SlackPayloadAttachment {
+    color: &'static str,
+    blocks: Vec<SlackPayloadBlock>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum SlackPayloadBlock {
+    Section {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        text: Option<SlackPayloadText>,
+
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        fields: Vec<SlackPayloadText>,
+    },
+
+    Context {
+        elements: Vec<SlackPayloadText>,
+    },
+
+    Actions {
+        elements: Vec<SlackPayloadButton>,
+    },
 }
 
 #[derive(Serialize)]
-struct SlackPayloadAttachment<'a> {
-    fallback: String,
-    color: &'a str,
-    fields: Vec<SlackPayloadAttachmentField<'a>>,
+struct SlackPayloadText {
+    #[serde(rename = "type")]
+    _type: &'static str,
+
+    text: String,
 }
 
 #[derive(Serialize)]
-struct SlackPayloadAttachmentField<'a> {
-    title: &'a str,
-    value: &'a str,
-    short: bool,
+struct SlackPayloadButton {
+    #[serde(rename = "type")]
+    _type: &'static str,
+
+    text: SlackPayloadText,
+    action_id: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    style: Option<&'static str>,
+}
+
+impl SlackPayloadText {
+    fn markdown(text: String) -> Self {
+        SlackPayloadText {
+            _type: "mrkdwn",
+            text: text,
+        }
+    }
+
+    fn plain(text: String) -> Self {
+        SlackPayloadText {
+            _type: "plain_text",
+            text: text,
+        }
+    }
 }
 
 impl GenericNotifier for SlackNotifier {
     fn attempt(notify: &ConfigNotify, notification: &Notification) -> Result<(), bool> {
         if let Some(ref slack) = notify.slack {
-            let status_label = format!("{:?}", notification.status);
-            let mut nodes_label = String::new();
-
             // Build message
-            let message_text = if notification.startup == true {
+            let message_text = if notification.test == true {
+                format!(
+                    "This is a test alert, status is: *{}*.",
+                    notification.status.as_str()
+                )
+            } else if notification.startup == true {
                 format!("Status started up, as: *{}*.", notification.status.as_str())
+            } else if let Some(probe_id) = notification.flapping {
+                format!("Service is flapping: *{}*.", probe_id)
+            } else if let Some(held) = notification.held {
+                format!(
+                    "Quiet hours are over ({} held back), status is: *{}*.",
+                    held,
+                    notification.status.as_str()
+                )
+            } else if let Some(suppressed) = notification.suppressed {
+                format!(
+                    "Rate limit is over ({} suppressed), status is: *{}*.",
+                    suppressed,
+                    notification.status.as_str()
+                )
+            } else if let Some(digest_label) = notification.digest_label() {
+                format!(
+                    "Status changed to: *{}* ({}).",
+                    notification.status.as_str(),
+                    digest_label
+                )
             } else if notification.changed == true {
                 format!("Status changed to: *{}*.", notification.status.as_str())
             } else {
-                format!("Status is still: *{}*.", notification.status.as_str())
+                format!(
+                    "Status is still: *{}* (reminder{}).",
+                    notification.status.as_str(),
+                    notification.reminder_suffix()
+                )
             };
 
-            let payload_text = if slack.mention_channel == true {
+            // Apply channel template (if any)
+            let rendered_body = template::render(notify, notification).body;
+            let is_templated = rendered_body.is_some();
+            let message_text = rendered_body.unwrap_or(message_text);
+
+            let mut payload_text = if slack.mention_channel == true {
                 format!("<!channel> {}", &message_text)
             } else {
-                message_text.to_owned()
-            };
-
-            // Build paylaod
-            let mut payload = SlackPayload {
-                text: payload_text,
-                attachments: Vec::new(),
+                message_text
             };
 
-            let mut attachment = SlackPayloadAttachment {
-                fallback: message_text,
-                color: status_to_color(&notification.status),
+            // Build payload blocks
+            let mut blocks = vec![SlackPayloadBlock::Section {
+                text: Some(SlackPayloadText::markdown(payload_text.to_owned())),
                 fields: Vec::new(),
-            };
+            }];
 
-            // Append attachment fields
-            if notification.replicas.len() > 0 {
-                nodes_label.push_str(&notification.replicas.join(", "));
-
-                let nodes_label_titled = format!(" Nodes: *{}*.", nodes_label);
+            blocks.push(SlackPayloadBlock::Section {
+                text: None,
+                fields: make_fields(notification),
+            });
 
-                payload.text.push_str(&nodes_label_titled);
-                attachment.fallback.push_str(&nodes_label_titled);
+            // Append one color-coded attachment per replica
+            let attachments = make_attachments(notification);
 
-                attachment.fields.push(SlackPayloadAttachmentField {
-                    title: "Nodes",
-                    value: &nodes_label,
-                    short: false,
+            if notification.replicas.len() > attachments.len() {
+                blocks.push(SlackPayloadBlock::Context {
+                    elements: vec![SlackPayloadText::markdown(format!(
+                        "…and {} more replica(s).",
+                        notification.replicas.len() - attachments.len()
+                    ))],
                 });
             }
 
-            attachment.fields.push(SlackPayloadAttachmentField {
-                title: "Status",
-                value: &status_label,
-                short: true,
+            blocks.push(SlackPayloadBlock::Actions {
+                elements: make_buttons(slack, notification),
             });
 
-            attachment.fields.push(SlackPayloadAttachmentField {
-                title: "Time",
-                value: &notification.time,
-                short: true,
-            });
-
-            attachment.fields.push(SlackPayloadAttachmentField {
-                title: "Monitor Page",
-                value: APP_CONF.branding.page_url.as_str(),
-                short: false,
-            });
+            // Notice: the text is used as the fallback for notifications and clients that \
+            //   cannot render blocks, thus it lists nodes (unless the message is templated).
+            if notification.replicas.is_empty() == false && is_templated == false {
+                payload_text.push_str(&format!(" Nodes: *{}*.", notification.replicas.join(", ")));
+            }
 
-            // Append attachment
-            payload.attachments.push(attachment);
+            let payload = SlackPayload {
+                text: payload_text,
+                blocks: blocks,
+                attachments: attachments,
+            };
 
             // Submit payload to Slack
             let response = SLACK_HTTP_CLIENT
@@ -144,6 +232,142 @@ impl GenericNotifier for SlackNotifier {
     }
 }
 
+fn make_fields(notification: &Notification) -> Vec<SlackPayloadText> {
+    let mut fields = vec![SlackPayloadText::markdown(format!(
+        "*Status*\n{:?}",
+        notification.status
+    ))];
+
+    // Append downtime (how long services have been down, or were down if they recovered)
+    if let Some(downtime_label) = notification.downtime_label() {
+        fields.push(SlackPayloadText::markdown(format!(
+            "*{}*\n{}",
+            if notification.status == &Status::Dead {
+                "Down For"
+            } else {
+                "Was Down For"
+            },
+            downtime_label
+        )));
+    }
+
+    fields.push(SlackPayloadText::markdown(format!(
+        "*Time*\n{}",
+        notification.time
+    )));
+
+    fields
+}
+
+fn make_attachments(notification: &Notification) -> Vec<SlackPayloadAttachment> {
+    let snapshot = PROBER_SNAPSHOT.load();
+
+    let mut attachments = Vec::new();
+    let mut uptimes: HashMap<&str, Option<f64>> = HashMap::new();
+
+    for replica_path in notification.replicas.iter().take(REPLICAS_MAXIMUM) {
+        // Replica paths are formatted as '<probe_id>:<node_id>:<replica_id>'
+        let mut parts = replica_path.splitn(3, ':');
+
+        let (probe_id, node_id, replica_id) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(probe_id), Some(node_id), Some(replica_id)) => (probe_id, node_id, replica_id),
+            _ => continue,
+        };
+
+        let probe = snapshot.states.probes.get(probe_id);
+        let node = probe.and_then(|probe| probe.nodes.get(node_id));
+        let replica = node.and_then(|node| node.replicas.get(replica_id));
+
+        let mut text = format!(
+            "*{}* › {} › `{}`",
+            probe.map(|probe| probe.label.as_str()).unwrap_or(probe_id),
+            node.map(|node| node.label.as_str()).unwrap_or(node_id),
+            replica_id
+        );
+
+        if let Some(message) = replica.and_then(|replica| replica.message.as_ref()) {
+            text.push_str(&format!("\n{}", message));
+        }
+
+        let mut blocks = vec![SlackPayloadBlock::Section {
+            text: Some(SlackPayloadText::markdown(text)),
+            fields: Vec::new(),
+        }];
+
+        // Append probe uptime (if any, as it requires the storage to be enabled)
+        let uptime = *uptimes.entry(probe_id).or_insert_with(|| {
+            uptime::compute_probe(probe_id).and_then(|uptime| uptime.get(UPTIME_WINDOW))
+        });
+
+        if let Some(uptime) = uptime {
+            blocks.push(SlackPayloadBlock::Context {
+                elements: vec![SlackPayloadText::markdown(format!(
+                    "Uptime ({}): *{:.2}%*",
+                    UPTIME_WINDOW, uptime
+                ))],
+            });
+        }
+
+        attachments.push(SlackPayloadAttachment {
+            color: status_to_color(
+                replica
+                    .map(|replica| &replica.status)
+                    .unwrap_or(notification.status),
+            ),
+            blocks: blocks,
+        });
+    }
+
+    attachments
+}
+
+fn make_buttons(slack: &ConfigNotifySlack, notification: &Notification) -> Vec<SlackPayloadButton> {
+    let mut buttons = vec![SlackPayloadButton {
+        _type: "button",
+        text: SlackPayloadText::plain("Open status page".to_string()),
+        action_id: "open_status_page".to_string(),
+        value: None,
+        url: Some(APP_CONF.branding.page_url.to_string()),
+        style: None,
+    }];
+
+    // Acknowledgments are either handled by the Slack app (through its request URL), or \
+    //   through links to the Manager API (which prompt for credentials)
+    let acknowledge_probes = notification.acknowledge_probes();
+    let acknowledge_urls = notification.acknowledge_urls();
+
+    for (index, probe_id) in acknowledge_probes
+        .iter()
+        .enumerate()
+        .take(ACKNOWLEDGE_BUTTONS_MAXIMUM)
+    {
+        let text = if acknowledge_probes.len() > 1 {
+            format!("Acknowledge {}", probe_id)
+        } else {
+            "Acknowledge".to_string()
+        };
+
+        let (value, url) = if slack.signing_secret.is_some() == true {
+            (Some(probe_id.to_string()), None)
+        } else if let Some(acknowledge_url) = acknowledge_urls.get(index) {
+            (None, Some(acknowledge_url.to_owned()))
+        } else {
+            continue;
+        };
+
+        buttons.push(SlackPayloadButton {
+            _type: "button",
+            text: SlackPayloadText::plain(text.chars().take(BUTTON_TEXT_MAXIMUM_CHARS).collect()),
+            action_id: format!("acknowledge_{}", index),
+            value: value,
+            url: url,
+            style: Some("danger"),
+        });
+    }
+
+    buttons
+}
+
 fn status_to_color(status: &Status) -> &'static str {
     match status {
         &Status::Healthy => "good",
@@ -0,0 +1,193 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::time::Duration;
+
+use reqwest::blocking::{Client, RequestBuilder};
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
+use reqwest::Method;
+use tera::{Context, Tera};
+
+use super::generic::{GenericNotifier, Notification, DISPATCH_TIMEOUT_SECONDS};
+use super::template;
+use crate::config::config::{ConfigNotify, ConfigNotifySMS};
+use crate::prober::status::Status;
+use crate::APP_CONF;
+
+static TEXT_MESSAGE_TRUNCATED_INDICATOR: &'static str = "[..]";
+
+lazy_static! {
+    static ref SMS_HTTP_CLIENT: Client = Client::builder()
+        .timeout(Duration::from_secs(DISPATCH_TIMEOUT_SECONDS))
+        .gzip(true)
+        .build()
+        .unwrap();
+}
+
+pub struct SMSNotifier;
+
+#[derive(Serialize)]
+struct SMSTemplateContext<'a> {
+    recipient: &'a str,
+    message: &'a str,
+    status: &'a Status,
+    channel: &'a str,
+    time: &'a str,
+    page_title: &'a str,
+    page_url: &'a str,
+}
+
+impl GenericNotifier for SMSNotifier {
+    fn attempt(notify: &ConfigNotify, notification: &Notification) -> Result<(), bool> {
+        if let Some(ref sms) = notify.sms {
+            // Build up the message text
+            let mut message = String::new();
+
+            if notification.test == true {
+                message.push_str("Test alert for: ");
+            } else if notification.startup == true {
+                message.push_str("Startup alert for: ");
+            } else if let Some(probe_id) = notification.flapping {
+                message.push_str(&format!("Flapping alert ({}) for: ", probe_id));
+            } else if let Some(held) = notification.held {
+                message.push_str(&format!("Quiet hours summary ({} held back) for: ", held));
+            } else if let Some(suppressed) = notification.suppressed {
+                message.push_str(&format!(
+                    "Rate limit summary ({} suppressed) for: ",
+                    suppressed
+                ));
+            } else if let Some(digest_label) = notification.digest_label() {
+                message.push_str(&format!("Digest ({}) for: ", digest_label));
+            } else if notification.changed == false {
+                message.push_str(&format!("Reminder{} for: ", notification.reminder_suffix()));
+            }
+
+            message.push_str(&format!("{}\n", APP_CONF.branding.page_title));
+            message.push_str("\n");
+            message.push_str(&format!("Status: {:?}\n", notification.status));
+            message.push_str(&format!("Nodes: {}\n", &notification.replicas.join(", ")));
+            message.push_str(&format!("Time: {}\n", &notification.time));
+
+            // Apply channel template (if any)
+            if let Some(body) = template::render(notify, notification).body {
+                message = body;
+            }
+
+            // Trim down message to a maximum length? (gateways might reject or bill for long \
+            //   messages, as they get split into multiple segments)
+            if message.chars().count() > sms.max_length {
+                debug!(
+                    "message for SMS notification is too long, trimming to length: {}",
+                    sms.max_length
+                );
+
+                message = message
+                    .chars()
+                    .take(
+                        sms.max_length
+                            .saturating_sub(TEXT_MESSAGE_TRUNCATED_INDICATOR.len()),
+                    )
+                    .collect();
+
+                message.push_str(TEXT_MESSAGE_TRUNCATED_INDICATOR);
+            }
+
+            debug!("will send SMS notification with message: {}", &message);
+
+            let mut has_sub_delivery_failure = false;
+
+            for recipient in &sms.recipients {
+                // Build request for recipient (from gateway templates)
+                let request = make_request(sms, notification, recipient, &message);
+
+                // Submit message to SMS gateway
+                let response = match request {
+                    Ok(request) => request.send(),
+                    Err(err) => {
+                        error!("failed rendering sms gateway templates: {}", err);
+
+                        has_sub_delivery_failure = true;
+
+                        continue;
+                    }
+                };
+
+                // Check for any failure
+                if let Ok(response_inner) = response {
+                    if response_inner.status().is_success() != true {
+                        has_sub_delivery_failure = true;
+                    }
+                } else {
+                    has_sub_delivery_failure = true;
+                }
+            }
+
+            if has_sub_delivery_failure == true {
+                return Err(true);
+            }
+
+            return Ok(());
+        }
+
+        Err(false)
+    }
+
+    fn can_notify(notify: &ConfigNotify, notification: &Notification) -> bool {
+        if let Some(ref sms_config) = notify.sms {
+            notification.expected(sms_config.reminders_only)
+        } else {
+            false
+        }
+    }
+
+    fn name() -> &'static str {
+        "sms"
+    }
+}
+
+fn make_request(
+    sms: &ConfigNotifySMS,
+    notification: &Notification,
+    recipient: &str,
+    message: &str,
+) -> Result<RequestBuilder, String> {
+    let context = Context::from_serialize(&SMSTemplateContext {
+        recipient: recipient,
+        message: message,
+        status: notification.status,
+        channel: notification.channel,
+        time: &notification.time,
+        page_title: &APP_CONF.branding.page_title,
+        page_url: APP_CONF.branding.page_url.as_str(),
+    })
+    .map_err(|err| err.to_string())?;
+
+    let render =
+        |template: &str| Tera::one_off(template, &context, false).map_err(|err| err.to_string());
+
+    // Render gateway URL
+    // Notice: values that go in the URL should be encoded with the 'urlencode' filter, as the \
+    //   message holds spaces and line breaks.
+    let url = render(&sms.gateway_url)?;
+
+    let method = Method::from_bytes(sms.method.trim().to_uppercase().as_bytes())
+        .map_err(|_| format!("invalid method: {}", sms.method))?;
+
+    let mut request = SMS_HTTP_CLIENT.request(method, url.trim());
+
+    if let Some(ref auth_header) = sms.auth_header {
+        request = request.header(AUTHORIZATION, auth_header.as_str());
+    }
+
+    // Render body (if any, as some gateways only accept query parameters)
+    if let Some(ref body) = sms.body {
+        request = request
+            .header(CONTENT_TYPE, sms.content_type.as_str())
+            .body(render(body)?);
+    }
+
+    Ok(request)
+}
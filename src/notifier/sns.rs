@@ -0,0 +1,568 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::env;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use hmac::{Hmac, Mac};
+use reqwest::blocking::Client;
+use reqwest::header::CONTENT_TYPE;
+use sha2::{Digest, Sha256};
+use time::format_description::FormatItem;
+use time::OffsetDateTime;
+use url::form_urlencoded;
+
+use super::generic::{GenericNotifier, Notification, DISPATCH_TIMEOUT_SECONDS};
+use super::template;
+use crate::config::config::{ConfigNotify, ConfigNotifySNS};
+use crate::prober::status::Status;
+use crate::APP_CONF;
+
+static SNS_API_VERSION: &'static str = "2010-03-31";
+static SNS_CONTENT_TYPE: &'static str = "application/x-www-form-urlencoded; charset=utf-8";
+static SIGNATURE_ALGORITHM: &'static str = "AWS4-HMAC-SHA256";
+static INSTANCE_METADATA_URL: &'static str = "http://169.254.169.254/latest";
+
+const SUBJECT_MAXIMUM_LENGTH: usize = 100;
+const INSTANCE_METADATA_TIMEOUT_SECONDS: u64 = 2;
+const INSTANCE_CREDENTIALS_REFRESH_SECONDS: u64 = 300;
+
+lazy_static! {
+    static ref SNS_HTTP_CLIENT: Client = Client::builder()
+        .timeout(Duration::from_secs(DISPATCH_TIMEOUT_SECONDS))
+        .gzip(true)
+        .build()
+        .unwrap();
+    static ref INSTANCE_METADATA_HTTP_CLIENT: Client = Client::builder()
+        .timeout(Duration::from_secs(INSTANCE_METADATA_TIMEOUT_SECONDS))
+        .no_proxy()
+        .build()
+        .unwrap();
+    static ref INSTANCE_CREDENTIALS: Mutex<Option<(SNSCredentials, Instant)>> = Mutex::new(None);
+    static ref AMZ_DATE_FORMATTER: Vec<FormatItem<'static>> =
+        time::format_description::parse("[year][month][day]T[hour][minute][second]Z")
+            .expect("invalid time format");
+}
+
+pub struct SNSNotifier;
+
+#[derive(Clone)]
+struct SNSCredentials {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SNSInstanceCredentials {
+    #[serde(rename = "AccessKeyId")]
+    access_key_id: String,
+
+    #[serde(rename = "SecretAccessKey")]
+    secret_access_key: String,
+
+    #[serde(rename = "Token")]
+    token: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SNSMessage<'a> {
+    #[serde(rename = "type")]
+    _type: &'static str,
+
+    status: &'a Status,
+    channel: &'a str,
+    time: &'a str,
+    replicas: &'a [&'a str],
+    page: SNSMessagePage<'a>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reminder: Option<u16>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    flapping: Option<&'a str>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    held: Option<u32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suppressed: Option<u32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    digest: Option<Vec<SNSMessageDigest<'a>>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    downtime: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct SNSMessagePage<'a> {
+    title: &'a str,
+    url: &'a str,
+}
+
+#[derive(Serialize)]
+struct SNSMessageDigest<'a> {
+    probe: &'a str,
+    status: &'a Status,
+}
+
+impl GenericNotifier for SNSNotifier {
+    fn attempt(notify: &ConfigNotify, notification: &Notification) -> Result<(), bool> {
+        if let Some(ref sns) = notify.sns {
+            // Acquire message type (same as Webhook payload types)
+            let message_type = if notification.test == true {
+                "test"
+            } else if notification.startup == true {
+                "startup"
+            } else if notification.flapping.is_some() == true {
+                "flapping"
+            } else if notification.held.is_some() == true
+                || notification.suppressed.is_some() == true
+            {
+                "summary"
+            } else if notification.changed == true {
+                "changed"
+            } else {
+                "reminder"
+            };
+
+            // Build subject
+            let rendered = template::render(notify, notification);
+
+            let subject = rendered.subject.unwrap_or_else(|| {
+                format!(
+                    "{} | {}",
+                    notification.status.as_str().to_uppercase(),
+                    APP_CONF.branding.page_title
+                )
+            });
+
+            // Build message (defaults to a JSON message, for downstream automations)
+            let message = match rendered.body {
+                Some(body) => body,
+                None => serde_json::to_string(&SNSMessage {
+                    _type: message_type,
+                    status: notification.status,
+                    channel: notification.channel,
+                    time: &notification.time,
+                    replicas: &notification.replicas,
+                    page: SNSMessagePage {
+                        title: &APP_CONF.branding.page_title,
+                        url: APP_CONF.branding.page_url.as_str(),
+                    },
+                    reminder: notification.reminder,
+                    flapping: notification.flapping,
+                    held: notification.held,
+                    suppressed: notification.suppressed,
+                    digest: notification.digest.as_ref().map(|digest| {
+                        digest
+                            .iter()
+                            .map(|(probe_id, status)| SNSMessageDigest {
+                                probe: probe_id,
+                                status: status,
+                            })
+                            .collect()
+                    }),
+                    downtime: notification.downtime.map(|downtime| downtime.as_secs()),
+                })
+                .or(Err(true))?,
+            };
+
+            // Build form body
+            // Notice: message attributes are set, so that subscriptions can use filter policies \
+            //   (eg. to only deliver 'dead' messages to a Lambda function).
+            let body = form_urlencoded::Serializer::new(String::new())
+                .append_pair("Action", "Publish")
+                .append_pair("Version", SNS_API_VERSION)
+                .append_pair("TopicArn", &sns.topic_arn)
+                .append_pair("Subject", &sanitize_subject(&subject))
+                .append_pair("Message", &message)
+                .append_pair("MessageAttributes.entry.1.Name", "status")
+                .append_pair("MessageAttributes.entry.1.Value.DataType", "String")
+                .append_pair(
+                    "MessageAttributes.entry.1.Value.StringValue",
+                    notification.status.as_str(),
+                )
+                .append_pair("MessageAttributes.entry.2.Name", "type")
+                .append_pair("MessageAttributes.entry.2.Value.DataType", "String")
+                .append_pair("MessageAttributes.entry.2.Value.StringValue", message_type)
+                .append_pair("MessageAttributes.entry.3.Name", "channel")
+                .append_pair("MessageAttributes.entry.3.Value.DataType", "String")
+                .append_pair(
+                    "MessageAttributes.entry.3.Value.StringValue",
+                    notification.channel,
+                )
+                .finish();
+
+            // Acquire credentials
+            let credentials = acquire_credentials(sns).ok_or_else(|| {
+                error!("could not acquire aws credentials for sns notification");
+
+                true
+            })?;
+
+            // Sign request
+            let region = acquire_region(sns).ok_or(false)?;
+            let host = format!("sns.{}.amazonaws.com", region);
+
+            let mut request = SNS_HTTP_CLIENT
+                .post(&format!("https://{}/", host))
+                .header(CONTENT_TYPE, SNS_CONTENT_TYPE);
+
+            for (name, value) in sign_request(&credentials, region, &host, &body) {
+                request = request.header(name, value);
+            }
+
+            // Submit message to SNS
+            let response = request.body(body).send();
+
+            if let Ok(response_inner) = response {
+                if response_inner.status().is_success() == true {
+                    return Ok(());
+                }
+
+                warn!(
+                    "sns refused notification with status: {}",
+                    response_inner.status()
+                );
+            }
+
+            return Err(true);
+        }
+
+        Err(false)
+    }
+
+    fn can_notify(notify: &ConfigNotify, notification: &Notification) -> bool {
+        if let Some(ref sns_config) = notify.sns {
+            notification.expected(sns_config.reminders_only)
+        } else {
+            false
+        }
+    }
+
+    fn name() -> &'static str {
+        "sns"
+    }
+}
+
+fn acquire_region(sns: &ConfigNotifySNS) -> Option<&str> {
+    // Region defaults to the region of the topic (ie. 'arn:aws:sns:<region>:<account>:<name>')
+    sns.region
+        .as_deref()
+        .or_else(|| sns.topic_arn.split(':').nth(3))
+        .filter(|region| region.is_empty() == false)
+}
+
+fn acquire_credentials(sns: &ConfigNotifySNS) -> Option<SNSCredentials> {
+    // Acquire credentials from configuration
+    if let (Some(access_key_id), Some(secret_access_key)) =
+        (&sns.access_key_id, &sns.secret_access_key)
+    {
+        return Some(SNSCredentials {
+            access_key_id: access_key_id.to_owned(),
+            secret_access_key: secret_access_key.to_owned(),
+            session_token: sns.session_token.to_owned(),
+        });
+    }
+
+    // Acquire credentials from environment
+    if let (Ok(access_key_id), Ok(secret_access_key)) = (
+        env::var("AWS_ACCESS_KEY_ID"),
+        env::var("AWS_SECRET_ACCESS_KEY"),
+    ) {
+        return Some(SNSCredentials {
+            access_key_id: access_key_id,
+            secret_access_key: secret_access_key,
+            session_token: env::var("AWS_SESSION_TOKEN").ok(),
+        });
+    }
+
+    // Acquire credentials from instance role (re-using recently acquired credentials)
+    // Notice: instance role credentials get rotated well before they expire, thus they are \
+    //   simply refreshed every few minutes.
+    let mut instance_credentials = INSTANCE_CREDENTIALS.lock().unwrap();
+
+    if let Some((ref credentials, acquired_at)) = *instance_credentials {
+        if acquired_at.elapsed() < Duration::from_secs(INSTANCE_CREDENTIALS_REFRESH_SECONDS) {
+            return Some(credentials.to_owned());
+        }
+    }
+
+    match acquire_instance_credentials() {
+        Ok(credentials) => {
+            *instance_credentials = Some((credentials.to_owned(), Instant::now()));
+
+            Some(credentials)
+        }
+        Err(err) => {
+            warn!("could not acquire aws instance role credentials: {}", err);
+
+            // Fallback on previous credentials (if any), as they might still be valid
+            instance_credentials
+                .as_ref()
+                .map(|(credentials, _)| credentials.to_owned())
+        }
+    }
+}
+
+fn acquire_instance_credentials() -> Result<SNSCredentials, String> {
+    // Acquire instance metadata session token (IMDSv2)
+    let token = INSTANCE_METADATA_HTTP_CLIENT
+        .put(&format!("{}/api/token", INSTANCE_METADATA_URL))
+        .header("X-aws-ec2-metadata-token-ttl-seconds", "60")
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.text())
+        .map_err(|err| err.to_string())?;
+
+    let credentials_url = format!(
+        "{}/meta-data/iam/security-credentials/",
+        INSTANCE_METADATA_URL
+    );
+
+    // Acquire instance role name
+    let role = INSTANCE_METADATA_HTTP_CLIENT
+        .get(&credentials_url)
+        .header("X-aws-ec2-metadata-token", &token)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.text())
+        .map_err(|err| err.to_string())?;
+
+    let role = role
+        .lines()
+        .next()
+        .map(str::trim)
+        .filter(|role| role.is_empty() == false)
+        .ok_or("no instance role".to_string())?;
+
+    // Acquire instance role credentials
+    let credentials = INSTANCE_METADATA_HTTP_CLIENT
+        .get(&format!("{}{}", credentials_url, role))
+        .header("X-aws-ec2-metadata-token", &token)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.json::<SNSInstanceCredentials>())
+        .map_err(|err| err.to_string())?;
+
+    Ok(SNSCredentials {
+        access_key_id: credentials.access_key_id,
+        secret_access_key: credentials.secret_access_key,
+        session_token: credentials.token,
+    })
+}
+
+fn sign_request(
+    credentials: &SNSCredentials,
+    region: &str,
+    host: &str,
+    body: &str,
+) -> Vec<(&'static str, String)> {
+    // Sign request with AWS Signature Version 4
+    // See: https://docs.aws.amazon.com/general/latest/gr/sigv4_signing.html
+    let amz_date = OffsetDateTime::now_utc()
+        .format(&AMZ_DATE_FORMATTER)
+        .unwrap_or_default();
+
+    // Build canonical request (headers must be sorted by name)
+    let mut canonical_headers = vec![
+        ("content-type", SNS_CONTENT_TYPE),
+        ("host", host),
+        ("x-amz-date", amz_date.as_str()),
+    ];
+
+    if let Some(ref session_token) = credentials.session_token {
+        canonical_headers.push(("x-amz-security-token", session_token.as_str()));
+    }
+
+    let (canonical_request, signed_headers) =
+        make_canonical_request("POST", "/", &canonical_headers, body);
+
+    let (scope, signature) = make_signature(
+        &credentials.secret_access_key,
+        &amz_date,
+        region,
+        "sns",
+        &canonical_request,
+    );
+
+    let mut headers = vec![
+        ("X-Amz-Date", amz_date.to_owned()),
+        (
+            "Authorization",
+            format!(
+                "{} Credential={}/{}, SignedHeaders={}, Signature={}",
+                SIGNATURE_ALGORITHM, credentials.access_key_id, scope, signed_headers, signature
+            ),
+        ),
+    ];
+
+    if let Some(ref session_token) = credentials.session_token {
+        headers.push(("X-Amz-Security-Token", session_token.to_owned()));
+    }
+
+    headers
+}
+
+fn make_canonical_request(
+    method: &str,
+    path: &str,
+    headers: &[(&str, &str)],
+    body: &str,
+) -> (String, String) {
+    // Notice: requests are signed without a query string, as SNS parameters are sent in the \
+    //   form body.
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value))
+        .collect();
+
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<&str>>()
+        .join(";");
+
+    let canonical_request = format!(
+        "{}\n{}\n\n{}\n{}\n{}",
+        method,
+        path,
+        canonical_headers,
+        signed_headers,
+        encode_hex(&Sha256::digest(body.as_bytes()))
+    );
+
+    (canonical_request, signed_headers)
+}
+
+fn make_signature(
+    secret_access_key: &str,
+    amz_date: &str,
+    region: &str,
+    service: &str,
+    canonical_request: &str,
+) -> (String, String) {
+    let date_stamp = &amz_date[..amz_date.len().min(8)];
+
+    let scope = format!("{}/{}/{}/aws4_request", date_stamp, region, service);
+
+    let string_to_sign = format!(
+        "{}\n{}\n{}\n{}",
+        SIGNATURE_ALGORITHM,
+        amz_date,
+        scope,
+        encode_hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    // Derive signing key, then sign
+    let mut key = sign_hmac(format!("AWS4{}", secret_access_key).as_bytes(), date_stamp);
+
+    for part in [region, service, "aws4_request"] {
+        key = sign_hmac(&key, part);
+    }
+
+    let signature = encode_hex(&sign_hmac(&key, &string_to_sign));
+
+    (scope, signature)
+}
+
+fn sign_hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("hmac accepts any key size");
+
+    mac.update(data.as_bytes());
+
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn sanitize_subject(subject: &str) -> String {
+    // Notice: SNS subjects must be printable ASCII, must not hold line breaks, and must not \
+    //   exceed 100 characters.
+    let mut subject: String = subject
+        .chars()
+        .map(|character| {
+            if character.is_ascii() == true && character.is_ascii_control() == false {
+                character
+            } else {
+                ' '
+            }
+        })
+        .collect();
+
+    subject.truncate(SUBJECT_MAXIMUM_LENGTH);
+
+    subject.trim().to_string()
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // See: AWS Signature Version 4 test suite ('get-vanilla' and 'post-vanilla')
+    const TEST_SECRET_ACCESS_KEY: &'static str = "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY";
+    const TEST_AMZ_DATE: &'static str = "20150830T123600Z";
+    const TEST_HEADERS: [(&'static str, &'static str); 2] = [
+        ("host", "example.amazonaws.com"),
+        ("x-amz-date", TEST_AMZ_DATE),
+    ];
+
+    #[test]
+    fn it_signs_get_vanilla() {
+        let (canonical_request, signed_headers) =
+            make_canonical_request("GET", "/", &TEST_HEADERS, "");
+
+        assert_eq!(
+            canonical_request,
+            "GET\n/\n\nhost:example.amazonaws.com\nx-amz-date:20150830T123600Z\n\n\
+             host;x-amz-date\n\
+             e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(signed_headers, "host;x-amz-date");
+
+        assert_eq!(
+            encode_hex(&Sha256::digest(canonical_request.as_bytes())),
+            "bb579772317eb040ac9ed261061d46c1f17a8133879d6129b6e1c25292927e63"
+        );
+
+        let (scope, signature) = make_signature(
+            TEST_SECRET_ACCESS_KEY,
+            TEST_AMZ_DATE,
+            "us-east-1",
+            "service",
+            &canonical_request,
+        );
+
+        assert_eq!(scope, "20150830/us-east-1/service/aws4_request");
+        assert_eq!(
+            signature,
+            "5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+    }
+
+    #[test]
+    fn it_signs_post_vanilla() {
+        let (canonical_request, _) = make_canonical_request("POST", "/", &TEST_HEADERS, "");
+
+        let (_, signature) = make_signature(
+            TEST_SECRET_ACCESS_KEY,
+            TEST_AMZ_DATE,
+            "us-east-1",
+            "service",
+            &canonical_request,
+        );
+
+        assert_eq!(
+            signature,
+            "5da7c1a2acd57cee7505fc6676e4e544621c30862966e37dddb68e92efbe5d6b"
+        );
+    }
+}
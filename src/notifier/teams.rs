@@ -0,0 +1,242 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+
+use super::generic::{GenericNotifier, Notification, DISPATCH_TIMEOUT_SECONDS};
+use super::template;
+use crate::config::config::ConfigNotify;
+use crate::prober::status::Status;
+use crate::APP_CONF;
+
+static CARD_CONTENT_TYPE: &'static str = "application/vnd.microsoft.card.adaptive";
+static CARD_SCHEMA: &'static str = "http://adaptivecards.io/schemas/adaptive-card.json";
+static CARD_VERSION: &'static str = "1.4";
+
+lazy_static! {
+    static ref TEAMS_HTTP_CLIENT: Client = Client::builder()
+        .timeout(Duration::from_secs(DISPATCH_TIMEOUT_SECONDS))
+        .gzip(true)
+        .build()
+        .unwrap();
+}
+
+pub struct TeamsNotifier;
+
+#[derive(Serialize)]
+struct TeamsPayload<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+
+    attachments: Vec<TeamsPayloadAttachment<'a>>,
+}
+
+#[derive(Serialize)]
+struct TeamsPayloadAttachment<'a> {
+    #[serde(rename = "contentType")]
+    content_type: &'static str,
+
+    content: TeamsPayloadCard<'a>,
+}
+
+#[derive(Serialize)]
+struct TeamsPayloadCard<'a> {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+
+    #[serde(rename = "type")]
+    kind: &'static str,
+
+    version: &'static str,
+    body: Vec<TeamsPayloadCardElement<'a>>,
+    actions: Vec<TeamsPayloadCardAction<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type")]
+enum TeamsPayloadCardElement<'a> {
+    TextBlock {
+        text: String,
+        weight: &'static str,
+        size: &'static str,
+        color: &'static str,
+        wrap: bool,
+    },
+
+    FactSet {
+        facts: Vec<TeamsPayloadCardFact<'a>>,
+    },
+}
+
+#[derive(Serialize)]
+struct TeamsPayloadCardFact<'a> {
+    title: &'static str,
+    value: &'a str,
+}
+
+#[derive(Serialize)]
+struct TeamsPayloadCardAction<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+
+    title: &'static str,
+    url: &'a str,
+}
+
+impl GenericNotifier for TeamsNotifier {
+    fn attempt(notify: &ConfigNotify, notification: &Notification) -> Result<(), bool> {
+        if let Some(ref teams) = notify.teams {
+            let status_label = format!("{:?}", notification.status);
+            let nodes_label = notification.replicas.join("\n\n");
+            let acknowledge_label = notification.acknowledge_urls().join("\n\n");
+
+            // Build card title
+            let title = if notification.test == true {
+                format!(
+                    "This is a test alert, status is: {}.",
+                    notification.status.as_str()
+                )
+            } else if notification.startup == true {
+                format!("Status started up, as: {}.", notification.status.as_str())
+            } else if let Some(probe_id) = notification.flapping {
+                format!("Service is flapping: {}.", probe_id)
+            } else if let Some(held) = notification.held {
+                format!(
+                    "Quiet hours are over ({} held back), status is: {}.",
+                    held,
+                    notification.status.as_str()
+                )
+            } else if let Some(suppressed) = notification.suppressed {
+                format!(
+                    "Rate limit is over ({} suppressed), status is: {}.",
+                    suppressed,
+                    notification.status.as_str()
+                )
+            } else if let Some(digest_label) = notification.digest_label() {
+                format!(
+                    "Status changed to: {} ({}).",
+                    notification.status.as_str(),
+                    digest_label
+                )
+            } else if notification.changed == true {
+                format!("Status changed to: {}.", notification.status.as_str())
+            } else {
+                format!(
+                    "Status is still: {} (reminder{}).",
+                    notification.status.as_str(),
+                    notification.reminder_suffix()
+                )
+            };
+
+            // Apply channel template (if any)
+            let rendered = template::render(notify, notification);
+
+            // Build card facts
+            let mut facts = vec![
+                TeamsPayloadCardFact {
+                    title: "Status",
+                    value: &status_label,
+                },
+                TeamsPayloadCardFact {
+                    title: "Time",
+                    value: &notification.time,
+                },
+            ];
+
+            if notification.replicas.len() > 0 {
+                facts.push(TeamsPayloadCardFact {
+                    title: "Nodes",
+                    value: &nodes_label,
+                });
+            }
+
+            if acknowledge_label.is_empty() == false {
+                facts.push(TeamsPayloadCardFact {
+                    title: "Acknowledge",
+                    value: &acknowledge_label,
+                });
+            }
+
+            // Build card body
+            let mut body = vec![TeamsPayloadCardElement::TextBlock {
+                text: rendered.subject.unwrap_or(title),
+                weight: "Bolder",
+                size: "Medium",
+                color: status_to_color(&notification.status),
+                wrap: true,
+            }];
+
+            if let Some(text) = rendered.body {
+                body.push(TeamsPayloadCardElement::TextBlock {
+                    text: text,
+                    weight: "Default",
+                    size: "Default",
+                    color: "Default",
+                    wrap: true,
+                });
+            }
+
+            body.push(TeamsPayloadCardElement::FactSet { facts: facts });
+
+            // Build payload
+            let payload = TeamsPayload {
+                kind: "message",
+                attachments: vec![TeamsPayloadAttachment {
+                    content_type: CARD_CONTENT_TYPE,
+                    content: TeamsPayloadCard {
+                        schema: CARD_SCHEMA,
+                        kind: "AdaptiveCard",
+                        version: CARD_VERSION,
+                        body: body,
+                        actions: vec![TeamsPayloadCardAction {
+                            kind: "Action.OpenUrl",
+                            title: "Open status page",
+                            url: APP_CONF.branding.page_url.as_str(),
+                        }],
+                    },
+                }],
+            };
+
+            // Submit payload to Teams
+            let response = TEAMS_HTTP_CLIENT
+                .post(teams.hook_url.as_str())
+                .json(&payload)
+                .send();
+
+            if let Ok(response_inner) = response {
+                if response_inner.status().is_success() == true {
+                    return Ok(());
+                }
+            }
+
+            return Err(true);
+        }
+
+        Err(false)
+    }
+
+    fn can_notify(notify: &ConfigNotify, notification: &Notification) -> bool {
+        if let Some(ref teams_config) = notify.teams {
+            notification.expected(teams_config.reminders_only)
+        } else {
+            false
+        }
+    }
+
+    fn name() -> &'static str {
+        "teams"
+    }
+}
+
+fn status_to_color(status: &Status) -> &'static str {
+    match status {
+        &Status::Healthy => "Good",
+        &Status::Sick => "Warning",
+        &Status::Dead => "Attention",
+    }
+}
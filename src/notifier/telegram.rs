@@ -5,12 +5,15 @@
 // License: Mozilla Public License v2.0 (MPL v2.0)
 
 use std::collections::HashMap;
+use std::sync::RwLock;
 use std::time::Duration;
 
 use reqwest::blocking::Client;
 
 use super::generic::{GenericNotifier, Notification, DISPATCH_TIMEOUT_SECONDS};
-use crate::config::config::ConfigNotify;
+use super::template;
+use crate::config::config::{ConfigNotify, ConfigNotifyTelegram};
+use crate::prober::status::Status;
 use crate::APP_CONF;
 
 lazy_static! {
@@ -19,10 +22,19 @@ lazy_static! {
         .gzip(true)
         .build()
         .unwrap();
+
+    // Notice: maps notify channels to the message that opened their current incident, which \
+    //   further notifications reply to, until the channel recovers.
+    static ref TELEGRAM_INCIDENTS: RwLock<HashMap<String, i64>> = RwLock::new(HashMap::new());
 }
 
 static TELEGRAM_API_BASE_URL: &'static str = "https://api.telegram.org";
 
+// Notice: Telegram caps callback data to 64 bytes, thus probes with longer identifiers get \
+//   acknowledged through a link instead (if possible).
+static TELEGRAM_CALLBACK_ACKNOWLEDGE_PREFIX: &'static str = "ack:";
+const TELEGRAM_CALLBACK_DATA_MAXIMUM_BYTES: usize = 64;
+
 pub struct TelegramNotifier;
 
 #[derive(Serialize)]
@@ -31,6 +43,16 @@ struct TelegramPayload<'a> {
     text: String,
     parse_mode: &'static str,
     disable_web_page_preview: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message_thread_id: Option<i64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reply_to_message_id: Option<i64>,
+
+    allow_sending_without_reply: bool,
+
+    reply_markup: TelegramPayloadReplyMarkup,
 }
 
 #[derive(Serialize)]
@@ -40,27 +62,89 @@ enum TelegramChatID<'a> {
     User(u64),
 }
 
+#[derive(Serialize)]
+struct TelegramPayloadReplyMarkup {
+    inline_keyboard: Vec<Vec<TelegramPayloadButton>>,
+}
+
+#[derive(Serialize)]
+struct TelegramPayloadButton {
+    text: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    callback_data: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TelegramResponse {
+    result: Option<TelegramResponseMessage>,
+}
+
+#[derive(Deserialize)]
+struct TelegramResponseMessage {
+    message_id: i64,
+}
+
 impl GenericNotifier for TelegramNotifier {
     fn attempt(notify: &ConfigNotify, notification: &Notification) -> Result<(), bool> {
         if let Some(ref telegram) = notify.telegram {
-            // Build message
-            let mut message = if notification.startup == true {
+            // Build message (formatted as MarkdownV2, thus dynamic values need to be escaped)
+            let status_label = notification.status.as_str().to_uppercase();
+
+            let mut message = if notification.test == true {
+                format!(
+                    "{} This is a test alert, status is: *{}*\\.\n",
+                    notification.status.as_icon(),
+                    status_label
+                )
+            } else if notification.startup == true {
+                format!(
+                    "{} Status started up, as: *{}*\\.\n",
+                    notification.status.as_icon(),
+                    status_label
+                )
+            } else if let Some(probe_id) = notification.flapping {
                 format!(
-                    "{} Status started up, as: *{}*.\n",
+                    "{} Service is flapping: *{}*\\.\n",
                     notification.status.as_icon(),
-                    notification.status.as_str().to_uppercase()
+                    escape_markdown(probe_id)
+                )
+            } else if let Some(held) = notification.held {
+                format!(
+                    "{} Quiet hours are over \\({} held back\\), status is: *{}*\\.\n",
+                    notification.status.as_icon(),
+                    held,
+                    status_label
+                )
+            } else if let Some(suppressed) = notification.suppressed {
+                format!(
+                    "{} Rate limit is over \\({} suppressed\\), status is: *{}*\\.\n",
+                    notification.status.as_icon(),
+                    suppressed,
+                    status_label
+                )
+            } else if let Some(digest_label) = notification.digest_label() {
+                format!(
+                    "{} Status changed to: *{}* \\({}\\)\\.\n",
+                    notification.status.as_icon(),
+                    status_label,
+                    escape_markdown(&digest_label)
                 )
             } else if notification.changed == true {
                 format!(
-                    "{} Status changed to: *{}*.\n",
+                    "{} Status changed to: *{}*\\.\n",
                     notification.status.as_icon(),
-                    notification.status.as_str().to_uppercase()
+                    status_label
                 )
             } else {
                 format!(
-                    "{} Status is still: *{}*.\n",
+                    "{} Status is still: *{}* \\(reminder{}\\)\\.\n",
                     notification.status.as_icon(),
-                    notification.status.as_str().to_uppercase()
+                    status_label,
+                    escape_markdown(&notification.reminder_suffix())
                 )
             };
 
@@ -75,8 +159,8 @@ impl GenericNotifier for TelegramNotifier {
                 .iter()
                 .map(|(service_and_node, count)| {
                     format!(
-                        "- `{}`: {} {}",
-                        service_and_node,
+                        "\\- `{}`: {} {}",
+                        escape_code(service_and_node),
                         count,
                         notification.status.as_str()
                     )
@@ -85,7 +169,16 @@ impl GenericNotifier for TelegramNotifier {
                 .join("\n");
 
             message.push_str(&nodes_count_list_text);
-            message.push_str(&format!("\nLink: {}", APP_CONF.branding.page_url.as_str()));
+
+            // Apply channel template (if any)
+            // Notice: templates are parsed as legacy Markdown, which does not require \
+            //   punctuation to be escaped (this keeps existing templates working).
+            let mut parse_mode = "MarkdownV2";
+
+            if let Some(body) = template::render(notify, notification).body {
+                message = body;
+                parse_mode = "Markdown";
+            }
 
             debug!("will send Telegram notification with message: {}", &message);
 
@@ -95,12 +188,32 @@ impl GenericNotifier for TelegramNotifier {
                 Err(_) => TelegramChatID::Group(&telegram.chat_id.as_str()),
             };
 
+            // Reply to the message that opened the current incident? (if any)
+            // Notice: test and startup notifications are not part of incidents.
+            let is_threaded = telegram.threads == true
+                && notification.test == false
+                && notification.startup == false;
+
+            let reply_to_message_id = if is_threaded == true {
+                TELEGRAM_INCIDENTS
+                    .read()
+                    .unwrap()
+                    .get(notification.channel)
+                    .copied()
+            } else {
+                None
+            };
+
             // Build payload
             let payload = TelegramPayload {
                 chat_id: chat_id,
                 text: message,
-                parse_mode: "markdown",
+                parse_mode: parse_mode,
                 disable_web_page_preview: true,
+                message_thread_id: telegram.topic_id,
+                reply_to_message_id: reply_to_message_id,
+                allow_sending_without_reply: true,
+                reply_markup: make_reply_markup(telegram, notification),
             };
 
             // Generate target API URL
@@ -118,6 +231,16 @@ impl GenericNotifier for TelegramNotifier {
             // Check for any failure
             if let Ok(response_inner) = response {
                 if response_inner.status().is_success() == true {
+                    if is_threaded == true {
+                        let message_id = response_inner
+                            .json::<TelegramResponse>()
+                            .ok()
+                            .and_then(|response| response.result)
+                            .map(|result| result.message_id);
+
+                        track_incident(notification, reply_to_message_id, message_id);
+                    }
+
                     return Ok(());
                 }
             }
@@ -140,3 +263,79 @@ impl GenericNotifier for TelegramNotifier {
         "telegram"
     }
 }
+
+fn make_reply_markup(
+    telegram: &ConfigNotifyTelegram,
+    notification: &Notification,
+) -> TelegramPayloadReplyMarkup {
+    let mut inline_keyboard = vec![vec![TelegramPayloadButton {
+        text: "Open status page".to_string(),
+        url: Some(APP_CONF.branding.page_url.to_string()),
+        callback_data: None,
+    }]];
+
+    // Acknowledgments are either handled by the bot (through its webhook), or through links to \
+    //   the Manager API (which prompt for credentials)
+    let acknowledge_probes = notification.acknowledge_probes();
+    let acknowledge_urls = notification.acknowledge_urls();
+
+    for (index, probe_id) in acknowledge_probes.iter().enumerate() {
+        let callback_data = format!("{}{}", TELEGRAM_CALLBACK_ACKNOWLEDGE_PREFIX, probe_id);
+
+        let (url, callback_data) = if telegram.webhook_secret.is_some() == true
+            && callback_data.len() <= TELEGRAM_CALLBACK_DATA_MAXIMUM_BYTES
+        {
+            (None, Some(callback_data))
+        } else if let Some(acknowledge_url) = acknowledge_urls.get(index) {
+            (Some(acknowledge_url.to_owned()), None)
+        } else {
+            continue;
+        };
+
+        inline_keyboard.push(vec![TelegramPayloadButton {
+            text: format!("✅ Acknowledge {}", probe_id),
+            url: url,
+            callback_data: callback_data,
+        }]);
+    }
+
+    TelegramPayloadReplyMarkup {
+        inline_keyboard: inline_keyboard,
+    }
+}
+
+fn track_incident(
+    notification: &Notification,
+    reply_to_message_id: Option<i64>,
+    message_id: Option<i64>,
+) {
+    let mut incidents = TELEGRAM_INCIDENTS.write().unwrap();
+
+    if notification.status == &Status::Healthy {
+        // Incident is over (next incident opens a new reply chain)
+        incidents.remove(notification.channel);
+    } else if let (None, Some(message_id)) = (reply_to_message_id, message_id) {
+        // Incident opens (this message is the one further notifications reply to)
+        incidents.insert(notification.channel.to_owned(), message_id);
+    }
+}
+
+fn escape_markdown(text: &str) -> String {
+    // Escape MarkdownV2 reserved characters (see: https://core.telegram.org/bots/api#markdownv2-style)
+    let mut escaped = String::with_capacity(text.len());
+
+    for character in text.chars() {
+        if "_*[]()~`>#+-=|{}.!\\".contains(character) == true {
+            escaped.push('\\');
+        }
+
+        escaped.push(character);
+    }
+
+    escaped
+}
+
+fn escape_code(text: &str) -> String {
+    // Only backticks and backslashes need to be escaped within code entities
+    text.replace('\\', "\\\\").replace('`', "\\`")
+}
@@ -0,0 +1,272 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use indexmap::IndexMap;
+use tera::{Context, Tera};
+
+use super::generic::Notification;
+use crate::config::config::ConfigNotify;
+use crate::prober::manager::SNAPSHOT as PROBER_SNAPSHOT;
+use crate::prober::severity::Severity;
+use crate::prober::status::Status;
+use crate::APP_CONF;
+
+#[derive(Default)]
+pub struct Rendered {
+    pub subject: Option<String>,
+    pub body: Option<String>,
+}
+
+#[derive(Serialize)]
+struct TemplateContext<'a> {
+    kind: &'static str,
+    status: &'a Status,
+    status_icon: &'static str,
+    channel: &'a str,
+    time: &'a str,
+    timestamp: u64,
+    reminder: Option<u16>,
+    flapping: Option<&'a str>,
+    held: Option<u32>,
+    suppressed: Option<u32>,
+    digest: Option<Vec<TemplateDigest<'a>>>,
+    downtime: Option<u64>,
+    replicas: &'a [&'a str],
+    probes: Vec<TemplateProbe<'a>>,
+    diagnostics: Vec<TemplateDiagnostic<'a>>,
+    acknowledge_urls: Vec<String>,
+    page_title: &'a str,
+    page_url: &'a str,
+}
+
+#[derive(Serialize)]
+struct TemplateDigest<'a> {
+    id: &'a str,
+    status: &'a Status,
+}
+
+#[derive(Serialize)]
+struct TemplateDiagnostic<'a> {
+    replica: &'a str,
+    output: &'a str,
+}
+
+#[derive(Serialize)]
+struct TemplateProbe<'a> {
+    id: &'a str,
+    label: String,
+    severity: Severity,
+    groups: Vec<String>,
+    metadata: IndexMap<String, String>,
+    replicas: Vec<TemplateReplica<'a>>,
+}
+
+#[derive(Serialize)]
+struct TemplateReplica<'a> {
+    path: &'a str,
+    node_id: &'a str,
+    replica_id: &'a str,
+}
+
+pub fn render(notify: &ConfigNotify, notification: &Notification) -> Rendered {
+    let template = match notify.template {
+        Some(ref template) => template,
+        None => return Rendered::default(),
+    };
+
+    let context = match Context::from_serialize(&make_context(notification)) {
+        Ok(context) => context,
+        Err(err) => {
+            error!("could not build notification template context: {}", err);
+
+            return Rendered::default();
+        }
+    };
+
+    Rendered {
+        subject: template
+            .subject
+            .as_ref()
+            .and_then(|subject| render_one("subject", subject, &context)),
+        body: template
+            .body
+            .as_ref()
+            .and_then(|body| render_one("body", body, &context)),
+    }
+}
+
+pub fn render_asset(
+    name: &str,
+    notification: &Notification,
+    extras: &[(&str, &str)],
+) -> Option<String> {
+    // Notice: built-in templates are read from the assets directory upon every render, so that \
+    //   they can be customized without restarting Vigil.
+    let path = APP_CONF.assets.path.join("templates").join(name);
+
+    let template = match fs::read_to_string(&path) {
+        Ok(template) => template,
+        Err(err) => {
+            error!("could not read notification template: {:?}: {}", path, err);
+
+            return None;
+        }
+    };
+
+    let mut context = match Context::from_serialize(&make_context(notification)) {
+        Ok(context) => context,
+        Err(err) => {
+            error!("could not build notification template context: {}", err);
+
+            return None;
+        }
+    };
+
+    for (key, value) in extras {
+        context.insert(*key, value);
+    }
+
+    render_one(name, &template, &context)
+}
+
+fn render_one(key: &str, template: &str, context: &Context) -> Option<String> {
+    // Notice: a template that fails to render falls back to the built-in message, as it is \
+    //   better to send a plain notification than no notification at all.
+    match Tera::one_off(template, context, false) {
+        Ok(rendered) => Some(rendered),
+        Err(err) => {
+            error!(
+                "could not render notification {} template, using default: {}",
+                key, err
+            );
+
+            None
+        }
+    }
+}
+
+fn make_context<'a>(notification: &'a Notification) -> TemplateContext<'a> {
+    let kind = if notification.test == true {
+        "test"
+    } else if notification.startup == true {
+        "startup"
+    } else if notification.flapping.is_some() == true {
+        "flapping"
+    } else if notification.held.is_some() == true || notification.suppressed.is_some() == true {
+        "summary"
+    } else if notification.changed == true {
+        "changed"
+    } else {
+        "reminder"
+    };
+
+    TemplateContext {
+        kind: kind,
+        status: notification.status,
+        status_icon: notification.status.as_icon(),
+        channel: notification.channel,
+        time: &notification.time,
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0),
+        reminder: notification.reminder,
+        flapping: notification.flapping,
+        held: notification.held,
+        suppressed: notification.suppressed,
+        digest: notification.digest.as_ref().map(|digest| {
+            digest
+                .iter()
+                .map(|(probe_id, status)| TemplateDigest {
+                    id: probe_id,
+                    status: status,
+                })
+                .collect()
+        }),
+        downtime: notification.downtime.map(|downtime| downtime.as_secs()),
+        replicas: &notification.replicas,
+        probes: make_probes(notification),
+        diagnostics: notification
+            .diagnostics
+            .iter()
+            .map(|(replica, output)| TemplateDiagnostic {
+                replica: *replica,
+                output: *output,
+            })
+            .collect(),
+        acknowledge_urls: notification.acknowledge_urls(),
+        page_title: &APP_CONF.branding.page_title,
+        page_url: APP_CONF.branding.page_url.as_str(),
+    }
+}
+
+fn make_probes<'a>(notification: &'a Notification) -> Vec<TemplateProbe<'a>> {
+    let snapshot = PROBER_SNAPSHOT.load();
+
+    let mut probes: Vec<TemplateProbe> = Vec::new();
+
+    // Group replicas per probe (the flapping probe is listed as well, although it has no replica)
+    let paths = notification
+        .replicas
+        .iter()
+        .map(|path| (*path, true))
+        .chain(notification.flapping.map(|probe_id| (probe_id, false)));
+
+    for (path, is_replica) in paths {
+        // Notice: replica identifiers might contain colons (eg. 'tcp://host:80'), thus only \
+        //   the probe and node identifiers are split from the path.
+        let mut parts = path.splitn(3, ':');
+
+        let (probe_id, node_id, replica_id) = (
+            parts.next().unwrap_or(""),
+            parts.next().unwrap_or(""),
+            parts.next().unwrap_or(""),
+        );
+
+        let index = match probes.iter().position(|probe| probe.id == probe_id) {
+            Some(index) => index,
+            None => {
+                let probe = snapshot.states.probes.get(probe_id);
+
+                probes.push(TemplateProbe {
+                    id: probe_id,
+                    label: probe
+                        .map(|probe| probe.label.to_owned())
+                        .unwrap_or_else(|| probe_id.to_owned()),
+                    severity: probe
+                        .map(|probe| probe.severity)
+                        .unwrap_or(Severity::Critical),
+                    groups: probe
+                        .map(|probe| probe.groups.to_owned())
+                        .unwrap_or_default(),
+                    metadata: APP_CONF
+                        .probe
+                        .service
+                        .iter()
+                        .find(|service| service.id == probe_id)
+                        .map(|service| service.metadata.to_owned())
+                        .unwrap_or_default(),
+                    replicas: Vec::new(),
+                });
+
+                probes.len() - 1
+            }
+        };
+
+        if is_replica == true {
+            probes[index].replicas.push(TemplateReplica {
+                path: path,
+                node_id: node_id,
+                replica_id: replica_id,
+            });
+        }
+    }
+
+    probes
+}
@@ -0,0 +1,92 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::iter;
+
+use super::generic::{Notification, DEFAULT_CHANNEL};
+use super::registry;
+use crate::aggregator::manager::time_now_as_string;
+use crate::prober::status::Status;
+use crate::APP_CONF;
+
+#[derive(Serialize)]
+pub struct TestOutcome {
+    pub channel: String,
+    pub notifier: &'static str,
+    pub success: bool,
+}
+
+pub fn run(notifier: Option<&str>) -> Vec<TestOutcome> {
+    let mut outcomes = Vec::new();
+
+    if let Some(ref notify) = APP_CONF.notify {
+        let channels = iter::once((DEFAULT_CHANNEL, notify)).chain(
+            notify
+                .channels
+                .iter()
+                .map(|(channel_name, channel_notify)| (channel_name.as_str(), channel_notify)),
+        );
+
+        for (channel_name, channel_notify) in channels {
+            // Notice: the test notification is marked as unchanged, so that notifiers set to only \
+            //   send reminders do not skip it. It bypasses the retry queue and dispatch counters.
+            let notification = Notification {
+                channel: channel_name,
+                status: &Status::Healthy,
+                time: time_now_as_string(),
+                replicas: Vec::new(),
+                diagnostics: Vec::new(),
+                changed: false,
+                startup: false,
+                reminder: None,
+                flapping: None,
+                held: None,
+                suppressed: None,
+                digest: None,
+                downtime: None,
+                test: true,
+            };
+
+            for name in registry::names() {
+                if notifier.map(|notifier| notifier == name).unwrap_or(true) == false {
+                    continue;
+                }
+
+                match registry::attempt(name, channel_notify, &notification) {
+                    Ok(_) => {
+                        info!(
+                            "sent test notification to provider: {} on channel: {}",
+                            name, channel_name
+                        );
+
+                        outcomes.push(TestOutcome {
+                            channel: channel_name.to_owned(),
+                            notifier: name,
+                            success: true,
+                        });
+                    }
+                    Err(true) => {
+                        warn!(
+                            "failed sending test notification to provider: {} on channel: {}",
+                            name, channel_name
+                        );
+
+                        outcomes.push(TestOutcome {
+                            channel: channel_name.to_owned(),
+                            notifier: name,
+                            success: false,
+                        });
+                    }
+                    Err(false) => {
+                        // Notifier is not configured on this channel (skip it)
+                    }
+                }
+            }
+        }
+    }
+
+    outcomes
+}
@@ -10,6 +10,7 @@ use std::time::Duration;
 use reqwest::blocking::Client;
 
 use super::generic::{GenericNotifier, Notification, DISPATCH_TIMEOUT_SECONDS};
+use super::template;
 use crate::config::config::ConfigNotify;
 use crate::APP_CONF;
 
@@ -33,10 +34,23 @@ impl GenericNotifier for TwilioNotifier {
             // Build up the message text
             let mut message = String::new();
 
-            if notification.startup == true {
+            if notification.test == true {
+                message.push_str("Test alert for: ");
+            } else if notification.startup == true {
                 message.push_str("Startup alert for: ");
+            } else if let Some(probe_id) = notification.flapping {
+                message.push_str(&format!("Flapping alert ({}) for: ", probe_id));
+            } else if let Some(held) = notification.held {
+                message.push_str(&format!("Quiet hours summary ({} held back) for: ", held));
+            } else if let Some(suppressed) = notification.suppressed {
+                message.push_str(&format!(
+                    "Rate limit summary ({} suppressed) for: ",
+                    suppressed
+                ));
+            } else if let Some(digest_label) = notification.digest_label() {
+                message.push_str(&format!("Digest ({}) for: ", digest_label));
             } else if notification.changed == false {
-                message.push_str("Reminder for: ");
+                message.push_str(&format!("Reminder{} for: ", notification.reminder_suffix()));
             }
 
             message.push_str(&format!("{}\n", APP_CONF.branding.page_title));
@@ -45,6 +59,11 @@ impl GenericNotifier for TwilioNotifier {
             message.push_str(&format!("Nodes: {}\n", &notification.replicas.join(", ")));
             message.push_str(&format!("Time: {}\n", &notification.time));
 
+            // Apply channel template (if any)
+            if let Some(body) = template::render(notify, notification).body {
+                message = body;
+            }
+
             // Trim down message to a maximum length? (most SMS receivers and networks support \
             //   up to 1600 characters by re-building message segments)
             if message.len() > TEXT_MESSAGE_MAXIMUM_LENGTH {
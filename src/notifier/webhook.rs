@@ -6,10 +6,14 @@
 
 use std::time::Duration;
 
-use reqwest::blocking::Client;
+use reqwest::blocking::{Client, RequestBuilder};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use reqwest::Method;
+use tera::{Context, Tera};
 
 use super::generic::{GenericNotifier, Notification, DISPATCH_TIMEOUT_SECONDS};
-use crate::config::config::ConfigNotify;
+use crate::config::config::{ConfigNotify, ConfigNotifyWebHook};
+use crate::prober::manager::SNAPSHOT as PROBER_SNAPSHOT;
 use crate::prober::status::Status;
 use crate::APP_CONF;
 
@@ -32,6 +36,52 @@ struct WebHookPayload<'a> {
     time: &'a str,
     replicas: &'a [&'a str],
     page: WebHookPayloadPage<'a>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reminder: Option<u16>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    flapping: Option<&'a str>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    held: Option<u32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suppressed: Option<u32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    digest: Option<Vec<WebHookPayloadDigest<'a>>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    downtime: Option<u64>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    acknowledge_urls: Vec<String>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    diagnostics: Vec<WebHookPayloadDiagnostic<'a>>,
+}
+
+#[derive(Serialize)]
+struct WebHookTemplateContext<'a> {
+    #[serde(flatten)]
+    payload: &'a WebHookPayload<'a>,
+
+    channel: &'a str,
+    changed: bool,
+    startup: bool,
+    replica_details: Vec<WebHookTemplateReplica<'a>>,
+}
+
+#[derive(Serialize)]
+struct WebHookTemplateReplica<'a> {
+    path: &'a str,
+    probe_id: &'a str,
+    probe_label: Option<String>,
+    node_id: &'a str,
+    node_label: Option<String>,
+    replica_id: &'a str,
+    diagnostic: Option<&'a str>,
 }
 
 #[derive(Serialize)]
@@ -44,6 +94,15 @@ pub enum WebHookPayloadType {
 
     #[serde(rename = "reminder")]
     Reminder,
+
+    #[serde(rename = "flapping")]
+    Flapping,
+
+    #[serde(rename = "summary")]
+    Summary,
+
+    #[serde(rename = "test")]
+    Test,
 }
 
 #[derive(Serialize)]
@@ -52,12 +111,32 @@ struct WebHookPayloadPage<'a> {
     url: &'a str,
 }
 
+#[derive(Serialize)]
+struct WebHookPayloadDigest<'a> {
+    probe: &'a str,
+    status: &'a Status,
+}
+
+#[derive(Serialize)]
+struct WebHookPayloadDiagnostic<'a> {
+    replica: &'a str,
+    output: &'a str,
+}
+
 impl GenericNotifier for WebHookNotifier {
     fn attempt(notify: &ConfigNotify, notification: &Notification) -> Result<(), bool> {
         if let Some(ref webhook) = notify.webhook {
             // Acquire hook type
-            let hook_type = if notification.startup == true {
+            let hook_type = if notification.test == true {
+                WebHookPayloadType::Test
+            } else if notification.startup == true {
                 WebHookPayloadType::Startup
+            } else if notification.flapping.is_some() == true {
+                WebHookPayloadType::Flapping
+            } else if notification.held.is_some() == true
+                || notification.suppressed.is_some() == true
+            {
+                WebHookPayloadType::Summary
             } else if notification.changed == true {
                 WebHookPayloadType::Changed
             } else {
@@ -74,13 +153,48 @@ impl GenericNotifier for WebHookNotifier {
                     title: APP_CONF.branding.page_title.as_str(),
                     url: APP_CONF.branding.page_url.as_str(),
                 },
+                reminder: notification.reminder,
+                flapping: notification.flapping,
+                held: notification.held,
+                suppressed: notification.suppressed,
+                digest: notification.digest.as_ref().map(|digest| {
+                    digest
+                        .iter()
+                        .map(|(probe_id, status)| WebHookPayloadDigest {
+                            probe: probe_id,
+                            status: status,
+                        })
+                        .collect()
+                }),
+                downtime: notification.downtime.map(|downtime| downtime.as_secs()),
+                acknowledge_urls: notification.acknowledge_urls(),
+                diagnostics: notification
+                    .diagnostics
+                    .iter()
+                    .map(|(replica, output)| WebHookPayloadDiagnostic {
+                        replica: replica,
+                        output: output,
+                    })
+                    .collect(),
             };
 
-            // Submit payload to Web Hooks
-            let response = WEBHOOK_HTTP_CLIENT
-                .post(webhook.hook_url.as_str())
-                .json(&payload)
-                .send();
+            // Submit payload to Web Hooks (the request might be customized with templates)
+            let request = if webhook.method.is_some() == true
+                || webhook.headers.is_empty() == false
+                || webhook.body.is_some() == true
+            {
+                make_template_request(webhook, notification, &payload).map_err(|err| {
+                    error!("failed rendering webhook templates: {}", err);
+
+                    true
+                })?
+            } else {
+                WEBHOOK_HTTP_CLIENT
+                    .post(webhook.hook_url.as_str())
+                    .json(&payload)
+            };
+
+            let response = request.send();
 
             if let Ok(response_inner) = response {
                 if response_inner.status().is_success() == true {
@@ -102,3 +216,105 @@ impl GenericNotifier for WebHookNotifier {
         "webhook"
     }
 }
+
+fn make_template_request(
+    webhook: &ConfigNotifyWebHook,
+    notification: &Notification,
+    payload: &WebHookPayload,
+) -> Result<RequestBuilder, String> {
+    let context = Context::from_serialize(&WebHookTemplateContext {
+        payload: payload,
+        channel: notification.channel,
+        changed: notification.changed,
+        startup: notification.startup,
+        replica_details: make_template_replicas(notification),
+    })
+    .map_err(|err| err.to_string())?;
+
+    let render =
+        |template: &str| Tera::one_off(template, &context, false).map_err(|err| err.to_string());
+
+    // Render method (defaults to 'POST')
+    let method = match webhook.method {
+        Some(ref method) => {
+            let method = render(method)?;
+
+            Method::from_bytes(method.trim().to_uppercase().as_bytes())
+                .map_err(|_| format!("invalid method: {}", method))?
+        }
+        None => Method::POST,
+    };
+
+    // Render headers
+    let mut headers = HeaderMap::new();
+
+    for (name, value) in webhook.headers.iter() {
+        headers.insert(
+            HeaderName::from_bytes(name.as_bytes()).map_err(|err| err.to_string())?,
+            HeaderValue::from_str(render(value)?.trim()).map_err(|err| err.to_string())?,
+        );
+    }
+
+    let request = WEBHOOK_HTTP_CLIENT
+        .request(method, webhook.hook_url.as_str())
+        .headers(headers);
+
+    // Render body (defaults to the JSON payload)
+    // Notice: rendered bodies are sent as JSON, unless another content type header is set.
+    match webhook.body {
+        Some(ref body) => {
+            let request = if webhook
+                .headers
+                .keys()
+                .any(|name| name.eq_ignore_ascii_case(CONTENT_TYPE.as_str()))
+                == false
+            {
+                request.header(CONTENT_TYPE, "application/json")
+            } else {
+                request
+            };
+
+            Ok(request.body(render(body)?))
+        }
+        None => Ok(request.json(payload)),
+    }
+}
+
+fn make_template_replicas<'a>(notification: &'a Notification) -> Vec<WebHookTemplateReplica<'a>> {
+    let snapshot = PROBER_SNAPSHOT.load();
+
+    notification
+        .replicas
+        .iter()
+        .map(|path| {
+            let path = *path;
+
+            // Notice: replica identifiers might contain colons (eg. 'tcp://host:80'), thus only \
+            //   the probe and node identifiers are split from the path.
+            let mut parts = path.splitn(3, ':');
+
+            let (probe_id, node_id, replica_id) = (
+                parts.next().unwrap_or(""),
+                parts.next().unwrap_or(""),
+                parts.next().unwrap_or(""),
+            );
+
+            let probe = snapshot.states.probes.get(probe_id);
+            let node = probe.and_then(|probe| probe.nodes.get(node_id));
+
+            WebHookTemplateReplica {
+                path: path,
+                probe_id: probe_id,
+                probe_label: probe.map(|probe| probe.label.to_owned()),
+                node_id: node_id,
+                node_label: node.map(|node| node.label.to_owned()),
+                replica_id: replica_id,
+                diagnostic: notification
+                    .diagnostics
+                    .iter()
+                    .find(|(replica, _)| *replica == path)
+                    .map(|(_, output)| *output),
+            }
+        })
+        .collect()
+}
@@ -11,9 +11,12 @@ use time;
 use libstrophe::{Connection, ConnectionEvent, Context, Stanza, StreamError};
 
 use super::generic::{GenericNotifier, Notification, DISPATCH_TIMEOUT_SECONDS};
+use super::template;
 use crate::config::config::ConfigNotify;
 use crate::APP_CONF;
 
+static MUC_NAMESPACE: &'static str = "http://jabber.org/protocol/muc";
+
 pub struct XMPPNotifier;
 
 impl GenericNotifier for XMPPNotifier {
@@ -24,10 +27,23 @@ impl GenericNotifier for XMPPNotifier {
             // Build up the message text
             let mut message = String::new();
 
-            if notification.startup == true {
+            if notification.test == true {
+                message.push_str("Test alert for: ");
+            } else if notification.startup == true {
                 message.push_str("Startup alert for: ");
+            } else if let Some(probe_id) = notification.flapping {
+                message.push_str(&format!("Flapping alert ({}) for: ", probe_id));
+            } else if let Some(held) = notification.held {
+                message.push_str(&format!("Quiet hours summary ({} held back) for: ", held));
+            } else if let Some(suppressed) = notification.suppressed {
+                message.push_str(&format!(
+                    "Rate limit summary ({} suppressed) for: ",
+                    suppressed
+                ));
+            } else if let Some(digest_label) = notification.digest_label() {
+                message.push_str(&format!("Digest ({}) for: ", digest_label));
             } else if notification.changed == false {
-                message.push_str("Reminder for: ");
+                message.push_str(&format!("Reminder{} for: ", notification.reminder_suffix()));
             }
 
             message.push_str(&format!("{}\n", APP_CONF.branding.page_title));
@@ -35,8 +51,18 @@ impl GenericNotifier for XMPPNotifier {
             message.push_str(&format!("Status: {:?}\n", notification.status));
             message.push_str(&format!("Nodes: {}\n", &notification.replicas.join(", ")));
             message.push_str(&format!("Time: {}\n", &notification.time));
+
+            for acknowledge_url in notification.acknowledge_urls() {
+                message.push_str(&format!("Acknowledge: {}\n", acknowledge_url));
+            }
+
             message.push_str(&format!("URL: {}", APP_CONF.branding.page_url.as_str()));
 
+            // Apply channel template (if any)
+            if let Some(body) = template::render(notify, notification).body {
+                message = body;
+            }
+
             debug!("will send XMPP notification with message: {}", &message);
 
             // Configure connection handler
@@ -49,23 +75,47 @@ impl GenericNotifier for XMPPNotifier {
                     ConnectionEvent::XMPP_CONN_CONNECT => {
                         debug!("connected to XMPP account: {}", &xmpp.from);
 
-                        // Send status message
-                        let mut message_stanza = Stanza::new_message(
-                            Some("chat"),
-                            Some(&format!("vigil-{}", time::now().to_timespec().sec)),
-                            Some(&xmpp.to),
-                        );
-
-                        if message_stanza.set_body(&message).is_ok() == true {
-                            connection.send(&message_stanza);
+                        let mut has_failed = false;
 
-                            {
-                                let mut is_sent_value = is_sent.write().unwrap();
+                        // Send status message to recipient (if any)
+                        if let Some(ref to) = xmpp.to {
+                            match make_message("chat", to, &message) {
+                                Some(message_stanza) => connection.send(&message_stanza),
+                                None => has_failed = true,
+                            }
+                        }
 
-                                *is_sent_value = true;
+                        // Send status message to room (if any)
+                        if let Some(ref room) = xmpp.room {
+                            // Notice: the room is joined right before posting, and left right \
+                            //   after, as stanzas are processed in order by the server. Room \
+                            //   history is not requested, as it is of no use to post a message.
+                            let occupant = format!("{}/{}", room, xmpp.room_nick);
+
+                            let stanzas = (
+                                make_room_presence(&occupant, xmpp.room_password.as_deref(), true),
+                                make_message("groupchat", room, &message),
+                                make_room_presence(&occupant, None, false),
+                            );
+
+                            match stanzas {
+                                (Some(join_stanza), Some(message_stanza), Some(leave_stanza)) => {
+                                    debug!("joining XMPP room: {}", &occupant);
+
+                                    connection.send(&join_stanza);
+                                    connection.send(&message_stanza);
+                                    connection.send(&leave_stanza);
+                                }
+                                _ => has_failed = true,
                             }
                         }
 
+                        if has_failed == false {
+                            let mut is_sent_value = is_sent.write().unwrap();
+
+                            *is_sent_value = true;
+                        }
+
                         // Disconnect immediately
                         connection.disconnect();
                     }
@@ -121,3 +171,53 @@ impl GenericNotifier for XMPPNotifier {
         "xmpp"
     }
 }
+
+fn make_message(kind: &str, to: &str, body: &str) -> Option<Stanza> {
+    let mut message_stanza = Stanza::new_message(
+        Some(kind),
+        Some(&format!("vigil-{}", time::now().to_timespec().sec)),
+        Some(to),
+    );
+
+    message_stanza.set_body(body).ok()?;
+
+    Some(message_stanza)
+}
+
+fn make_room_presence(occupant: &str, password: Option<&str>, available: bool) -> Option<Stanza> {
+    let mut presence_stanza = Stanza::new_presence();
+
+    presence_stanza.set_to(occupant).ok()?;
+
+    if available == true {
+        // Join room (with password, if any)
+        let mut muc_stanza = Stanza::new();
+
+        muc_stanza.set_name("x").ok()?;
+        muc_stanza.set_ns(MUC_NAMESPACE).ok()?;
+
+        let mut history_stanza = Stanza::new();
+
+        history_stanza.set_name("history").ok()?;
+        history_stanza.set_attribute("maxstanzas", "0").ok()?;
+
+        muc_stanza.add_child(history_stanza).ok()?;
+
+        if let Some(password) = password {
+            let (mut password_stanza, mut password_text_stanza) = (Stanza::new(), Stanza::new());
+
+            password_stanza.set_name("password").ok()?;
+            password_text_stanza.set_text(password).ok()?;
+
+            password_stanza.add_child(password_text_stanza).ok()?;
+            muc_stanza.add_child(password_stanza).ok()?;
+        }
+
+        presence_stanza.add_child(muc_stanza).ok()?;
+    } else {
+        // Leave room
+        presence_stanza.set_stanza_type("unavailable").ok()?;
+    }
+
+    Some(presence_stanza)
+}
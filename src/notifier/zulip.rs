@@ -5,21 +5,33 @@
 // Copyright: 2021, Bastien Orivel <eijebong@bananium.fr>
 // License: Mozilla Public License v2.0 (MPL v2.0)
 
+use std::collections::HashMap;
+use std::sync::Mutex;
 use std::time::Duration;
 
 use reqwest::blocking::Client;
 
 use super::generic::{GenericNotifier, Notification, DISPATCH_TIMEOUT_SECONDS};
-use crate::config::config::ConfigNotify;
+use super::template;
+use crate::config::config::{ConfigNotify, ConfigNotifyZulip};
+use crate::prober::manager::SNAPSHOT as PROBER_SNAPSHOT;
 use crate::prober::status::Status;
 use crate::APP_CONF;
 
+static GROUP_DEFAULT: &'static str = "default";
+static GROUP_PLACEHOLDER: &'static str = "{group}";
+
 lazy_static! {
     static ref ZULIP_HTTP_CLIENT: Client = Client::builder()
         .timeout(Duration::from_secs(DISPATCH_TIMEOUT_SECONDS))
         .gzip(true)
         .build()
         .unwrap();
+
+    // Notice: maps channels to the groups that got alerted while down, so that recoveries get \
+    //   posted to the same topics as the alerts they follow.
+    static ref ZULIP_ALERTED_GROUPS: Mutex<HashMap<String, Vec<String>>> =
+        Mutex::new(HashMap::new());
 }
 
 pub struct ZulipNotifier;
@@ -36,63 +48,50 @@ struct ZulipPayload<'a> {
 impl GenericNotifier for ZulipNotifier {
     fn attempt(notify: &ConfigNotify, notification: &Notification) -> Result<(), bool> {
         if let Some(ref zulip) = notify.zulip {
-            let status_label = format!("{:?}", notification.status);
-
-            let status_text = match notification.status {
-                Status::Dead => " *dead* :boom:",
-                Status::Healthy => " *healthy* :check_mark:",
-                Status::Sick => " *sick* :sick:",
-            };
-
-            // Build message
-            let mut message_text = if notification.startup == true {
-                format!("Status started up, as: {}.", status_text)
-            } else if notification.changed {
-                format!("Status changed to: {}.", status_text)
-            } else {
-                format!("Status is still: {}.", status_text)
-            };
-
-            if notification.replicas.len() > 0 {
-                let nodes_label = notification.replicas.join(", ");
-                let nodes_label_titled = format!("\n **Nodes**: *{}*.", nodes_label);
-
-                message_text.push_str(&nodes_label_titled);
+            // Apply channel template (if any)
+            let rendered = template::render(notify, notification);
+
+            // Post a single message? (topics are not mapped to groups, or subject is templated)
+            if rendered.subject.is_some() == true
+                || zulip.topic.contains(GROUP_PLACEHOLDER) == false
+            {
+                let message_text = rendered
+                    .body
+                    .unwrap_or_else(|| make_message(notification, &notification.replicas));
+
+                return submit_message(
+                    zulip,
+                    rendered.subject.as_deref().unwrap_or(&zulip.topic),
+                    &message_text,
+                );
             }
 
-            message_text.push_str(&format!("\n **Status**: {}", &status_label));
-            message_text.push_str(&format!("\n **Time**: {}", &notification.time));
-            message_text.push_str(&format!(
-                "\n **Page**: {}",
-                &APP_CONF.branding.page_url.as_str()
-            ));
-
-            // Submit payload to Zulip
-            let payload = ZulipPayload {
-                type_: "stream",
-                to: &zulip.channel,
-                topic: "Vigil status",
-                content: &message_text,
-            };
-
-            let response = ZULIP_HTTP_CLIENT
-                .post(zulip.api_url.join("messages").unwrap().as_str())
-                .basic_auth(zulip.bot_email.clone(), Some(zulip.bot_api_key.clone()))
-                .form(&payload)
-                .send();
-
-            if let Ok(response_inner) = response {
-                if response_inner.status().is_success() == true {
-                    return Ok(());
-                } else {
-                    warn!(
-                        "could not submit data to zulip: {:?}",
-                        response_inner.text()
-                    );
+            // Post one message per group, each holding the nodes of its group only
+            let mut has_sub_delivery_failure = false;
+
+            for (group, replicas) in acquire_groups(notification) {
+                let message_text = rendered
+                    .body
+                    .to_owned()
+                    .unwrap_or_else(|| make_message(notification, &replicas));
+
+                if submit_message(
+                    zulip,
+                    &zulip.topic.replace(GROUP_PLACEHOLDER, &group),
+                    &message_text,
+                )
+                .is_err()
+                    == true
+                {
+                    has_sub_delivery_failure = true;
                 }
             }
 
-            return Err(true);
+            if has_sub_delivery_failure == true {
+                return Err(true);
+            }
+
+            return Ok(());
         }
 
         Err(false)
@@ -110,3 +109,165 @@ impl GenericNotifier for ZulipNotifier {
         "zulip"
     }
 }
+
+fn make_message(notification: &Notification, replicas: &[&str]) -> String {
+    let status_label = format!("{:?}", notification.status);
+
+    let status_text = match notification.status {
+        Status::Dead => " *dead* :boom:",
+        Status::Healthy => " *healthy* :check_mark:",
+        Status::Sick => " *sick* :sick:",
+    };
+
+    // Build message
+    let mut message_text = if notification.test == true {
+        format!("This is a test alert, status is: {}.", status_text)
+    } else if notification.startup == true {
+        format!("Status started up, as: {}.", status_text)
+    } else if let Some(probe_id) = notification.flapping {
+        format!("Service is flapping: {}.", probe_id)
+    } else if let Some(held) = notification.held {
+        format!(
+            "Quiet hours are over ({} held back), status is: {}.",
+            held, status_text
+        )
+    } else if let Some(suppressed) = notification.suppressed {
+        format!(
+            "Rate limit is over ({} suppressed), status is: {}.",
+            suppressed, status_text
+        )
+    } else if let Some(digest_label) = notification.digest_label() {
+        format!("Status changed to: {} ({}).", status_text, digest_label)
+    } else if notification.changed {
+        format!("Status changed to: {}.", status_text)
+    } else {
+        format!(
+            "Status is still: {} (reminder{}).",
+            status_text,
+            notification.reminder_suffix()
+        )
+    };
+
+    if replicas.len() > 0 {
+        let nodes_label = replicas.join(", ");
+        let nodes_label_titled = format!("\n **Nodes**: *{}*.", nodes_label);
+
+        message_text.push_str(&nodes_label_titled);
+    }
+
+    message_text.push_str(&format!("\n **Status**: {}", &status_label));
+    message_text.push_str(&format!("\n **Time**: {}", &notification.time));
+    message_text.push_str(&format!(
+        "\n **Page**: {}",
+        &APP_CONF.branding.page_url.as_str()
+    ));
+
+    for acknowledge_url in notification.acknowledge_urls() {
+        message_text.push_str(&format!("\n **Acknowledge**: {}", acknowledge_url));
+    }
+
+    message_text
+}
+
+fn acquire_groups<'a>(notification: &Notification<'a>) -> Vec<(String, Vec<&'a str>)> {
+    let snapshot = PROBER_SNAPSHOT.load();
+
+    let group_of = |probe_id: &str| {
+        snapshot
+            .states
+            .probes
+            .get(probe_id)
+            .and_then(|probe| probe.groups.first())
+            .map(String::as_str)
+            .unwrap_or(GROUP_DEFAULT)
+            .to_string()
+    };
+
+    let mut groups: Vec<(String, Vec<&'a str>)> = Vec::new();
+
+    // Group replicas (and services that flapped or went down within a digest) per probe group
+    let probe_ids = notification
+        .replicas
+        .iter()
+        .map(|replica| (replica.split(':').next().unwrap_or(""), Some(*replica)))
+        .chain(notification.flapping.map(|probe_id| (probe_id, None)))
+        .chain(
+            notification
+                .digest
+                .iter()
+                .flatten()
+                .map(|(probe_id, _)| (*probe_id, None)),
+        );
+
+    for (probe_id, replica) in probe_ids {
+        let group = group_of(probe_id);
+
+        let index = match groups.iter().position(|(name, _)| name == &group) {
+            Some(index) => index,
+            None => {
+                groups.push((group, Vec::new()));
+
+                groups.len() - 1
+            }
+        };
+
+        if let Some(replica) = replica {
+            groups[index].1.push(replica);
+        }
+    }
+
+    // Append groups that got alerted before (so that recoveries get posted to their topics)
+    let mut alerted_groups = ZULIP_ALERTED_GROUPS.lock().unwrap();
+
+    let channel_alerted_groups = alerted_groups
+        .entry(notification.channel.to_string())
+        .or_insert_with(Vec::new);
+
+    for group in channel_alerted_groups.iter() {
+        if groups.iter().any(|(name, _)| name == group) == false {
+            groups.push((group.to_owned(), Vec::new()));
+        }
+    }
+
+    if notification.status == &Status::Healthy {
+        channel_alerted_groups.clear();
+    } else {
+        *channel_alerted_groups = groups.iter().map(|(name, _)| name.to_owned()).collect();
+    }
+
+    // Fallback on default group (eg. startup notifications)
+    if groups.is_empty() == true {
+        groups.push((GROUP_DEFAULT.to_string(), Vec::new()));
+    }
+
+    groups
+}
+
+fn submit_message(zulip: &ConfigNotifyZulip, topic: &str, content: &str) -> Result<(), bool> {
+    // Submit payload to Zulip
+    let payload = ZulipPayload {
+        type_: "stream",
+        to: &zulip.channel,
+        topic: topic,
+        content: content,
+    };
+
+    let response = ZULIP_HTTP_CLIENT
+        .post(zulip.api_url.join("messages").unwrap().as_str())
+        .basic_auth(zulip.bot_email.clone(), Some(zulip.bot_api_key.clone()))
+        .form(&payload)
+        .send();
+
+    if let Ok(response_inner) = response {
+        if response_inner.status().is_success() == true {
+            return Ok(());
+        } else {
+            warn!(
+                "could not submit data to zulip: {:?}",
+                response_inner.text()
+            );
+        }
+    }
+
+    Err(true)
+}
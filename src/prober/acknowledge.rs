@@ -0,0 +1,147 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::prober::manager::{publish_snapshot, STORE as PROBER_STORE};
+use crate::APP_CONF;
+
+// Notice: used if there is no notify configuration (acknowledgments only show on the page then)
+const ACKNOWLEDGE_DURATION_FALLBACK_SECONDS: u64 = 3600;
+
+// Notice: acknowledgments are meant to silence an ongoing incident, not to mute a probe forever
+pub const ACKNOWLEDGE_DURATION_MAXIMUM_SECONDS: u64 = 31536000;
+
+pub enum HandleAcknowledgeError {
+    NotFound,
+    InvalidDuration,
+}
+
+pub enum AcknowledgeLinkError {
+    Invalid,
+    Expired,
+}
+
+pub fn handle_acknowledge(
+    probe_id: &str,
+    duration: Option<u64>,
+) -> Result<SystemTime, HandleAcknowledgeError> {
+    // Use configured acknowledgment duration if none is given
+    let duration = Duration::from_secs(duration.unwrap_or_else(|| {
+        APP_CONF
+            .notify
+            .as_ref()
+            .map(|notify| notify.acknowledge_duration)
+            .unwrap_or(ACKNOWLEDGE_DURATION_FALLBACK_SECONDS)
+    }));
+
+    if duration.as_secs() > ACKNOWLEDGE_DURATION_MAXIMUM_SECONDS {
+        return Err(HandleAcknowledgeError::InvalidDuration);
+    }
+
+    debug!(
+        "acknowledge handle: {} for {}s",
+        probe_id,
+        duration.as_secs()
+    );
+
+    let mut store = PROBER_STORE.write().unwrap();
+
+    if let Some(ref mut probe) = store.states.probes.get_mut(probe_id) {
+        let acknowledged_until = SystemTime::now()
+            .checked_add(duration)
+            .ok_or(HandleAcknowledgeError::InvalidDuration)?;
+
+        // Notice: acknowledgment gets cleared by the aggregator upon expiry, or once the probe \
+        //   recovers (ie. is healthy again).
+        probe.acknowledged = true;
+        probe.acknowledged_until = Some(acknowledged_until);
+
+        publish_snapshot(&store);
+
+        info!(
+            "acknowledged probe: {} for {}s",
+            probe_id,
+            duration.as_secs()
+        );
+
+        return Ok(acknowledged_until);
+    }
+
+    Err(HandleAcknowledgeError::NotFound)
+}
+
+pub fn handle_unacknowledge(probe_id: &str) -> Result<(), HandleAcknowledgeError> {
+    debug!("unacknowledge handle: {}", probe_id);
+
+    let mut store = PROBER_STORE.write().unwrap();
+
+    if let Some(ref mut probe) = store.states.probes.get_mut(probe_id) {
+        probe.acknowledged = false;
+        probe.acknowledged_until = None;
+
+        publish_snapshot(&store);
+
+        info!("unacknowledged probe: {}", probe_id);
+
+        return Ok(());
+    }
+
+    Err(HandleAcknowledgeError::NotFound)
+}
+
+pub fn sign_link(probe_id: &str, expires: u64) -> Option<String> {
+    // Notice: acknowledgment links are signed with a key derived from the manager token, which \
+    //   lets anyone holding a link acknowledge this very probe until the link expires, without \
+    //   ever exposing the manager token itself.
+    let mac = make_link_mac(probe_id, expires)?;
+
+    Some(
+        mac.finalize()
+            .into_bytes()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect(),
+    )
+}
+
+pub fn verify_link(
+    probe_id: &str,
+    expires: u64,
+    digest: &[u8],
+) -> Result<(), AcknowledgeLinkError> {
+    make_link_mac(probe_id, expires)
+        .ok_or(AcknowledgeLinkError::Invalid)?
+        .verify_slice(digest)
+        .or(Err(AcknowledgeLinkError::Invalid))?;
+
+    // Check expiry once the signature is known to be valid (the expiry time is signed)
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+
+    if now > expires {
+        return Err(AcknowledgeLinkError::Expired);
+    }
+
+    Ok(())
+}
+
+fn make_link_mac(probe_id: &str, expires: u64) -> Option<Hmac<Sha256>> {
+    let manager_token = APP_CONF.server.manager_token.as_ref()?;
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(manager_token.as_bytes())
+        .expect("hmac accepts any key size");
+
+    // Signed contents are 'acknowledge:<probe_id>:<expires>'
+    mac.update(format!("acknowledge:{}:{}", probe_id, expires).as_bytes());
+
+    Some(mac)
+}
@@ -0,0 +1,369 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::io::{BufReader, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use native_tls::TlsConnector;
+
+use super::dns::Resolver;
+use super::status::Status;
+use crate::APP_CONF;
+
+const PROTOCOL_HEADER: &'static [u8] = b"AMQP\x00\x00\x09\x01";
+const FRAME_MAXIMUM_BYTES: u32 = 131072;
+const FRAME_END: u8 = 0xce;
+const FRAME_METHOD: u8 = 1;
+const FRAME_HEARTBEAT: u8 = 8;
+const CHANNEL_ID: u16 = 1;
+const AUTH_MECHANISM: &'static str = "PLAIN";
+const AUTH_CREDENTIAL_DEFAULT: &'static str = "guest";
+const REPLY_SUCCESS: u16 = 200;
+
+const METHOD_CONNECTION_START: (u16, u16) = (10, 10);
+const METHOD_CONNECTION_START_OK: (u16, u16) = (10, 11);
+const METHOD_CONNECTION_TUNE: (u16, u16) = (10, 30);
+const METHOD_CONNECTION_TUNE_OK: (u16, u16) = (10, 31);
+const METHOD_CONNECTION_OPEN: (u16, u16) = (10, 40);
+const METHOD_CONNECTION_OPEN_OK: (u16, u16) = (10, 41);
+const METHOD_CONNECTION_CLOSE: (u16, u16) = (10, 50);
+const METHOD_CHANNEL_OPEN: (u16, u16) = (20, 10);
+const METHOD_CHANNEL_OPEN_OK: (u16, u16) = (20, 11);
+const METHOD_CHANNEL_CLOSE: (u16, u16) = (20, 40);
+const METHOD_QUEUE_DECLARE: (u16, u16) = (50, 10);
+const METHOD_QUEUE_DECLARE_OK: (u16, u16) = (50, 11);
+
+pub fn check(
+    host: &str,
+    port: u16,
+    tls: bool,
+    user: &Option<String>,
+    virtual_host: &str,
+    password: &Option<String>,
+    queue: &Option<String>,
+    resolver: Resolver,
+) -> Status {
+    match proceed_check(
+        host,
+        port,
+        tls,
+        user,
+        virtual_host,
+        password,
+        queue,
+        resolver,
+    ) {
+        Ok(_) => Status::Healthy,
+        Err(err) => {
+            debug!(
+                "prober poll failed for amqp target: {}:{} (error: {})",
+                host, port, err
+            );
+
+            Status::Dead
+        }
+    }
+}
+
+fn proceed_check(
+    host: &str,
+    port: u16,
+    tls: bool,
+    user: &Option<String>,
+    virtual_host: &str,
+    password: &Option<String>,
+    queue: &Option<String>,
+    resolver: Resolver,
+) -> Result<(), String> {
+    let address = resolver
+        .lookup(host, port)
+        .map_err(|err| err.to_string())?
+        .next()
+        .ok_or("could not resolve address")?;
+
+    debug!("prober poll will fire for amqp target: {}", address);
+
+    let timeout = Duration::from_secs(APP_CONF.metrics.poll_delay_dead);
+    let stream = TcpStream::connect_timeout(&address, timeout).map_err(|err| err.to_string())?;
+
+    stream
+        .set_read_timeout(Some(timeout))
+        .and_then(|_| stream.set_write_timeout(Some(timeout)))
+        .map_err(|err| err.to_string())?;
+
+    // Notice: credentials default to the ones of a pristine broker, as with AMQP URIs
+    let (user, password) = (
+        user.as_deref().unwrap_or(AUTH_CREDENTIAL_DEFAULT),
+        password.as_deref().unwrap_or(AUTH_CREDENTIAL_DEFAULT),
+    );
+
+    if tls == true {
+        let connector = TlsConnector::new().map_err(|err| err.to_string())?;
+
+        let tls_stream = connector
+            .connect(host, stream)
+            .map_err(|err| err.to_string())?;
+
+        proceed_session(
+            BufReader::new(tls_stream),
+            user,
+            password,
+            virtual_host,
+            queue,
+        )
+    } else {
+        proceed_session(BufReader::new(stream), user, password, virtual_host, queue)
+    }
+}
+
+fn proceed_session<S: Read + Write>(
+    mut reader: BufReader<S>,
+    user: &str,
+    password: &str,
+    virtual_host: &str,
+    queue: &Option<String>,
+) -> Result<(), String> {
+    reader
+        .get_mut()
+        .write_all(PROTOCOL_HEADER)
+        .map_err(|err| err.to_string())?;
+
+    // Authenticate (with the 'PLAIN' mechanism, which is always supported by brokers)
+    let start = expect_method(&mut reader, METHOD_CONNECTION_START)?;
+
+    let mechanisms = skip_table(&start, 2)
+        .and_then(|offset| read_long_string(&start, offset))
+        .ok_or("invalid connection start")?;
+
+    if String::from_utf8_lossy(mechanisms)
+        .split_whitespace()
+        .any(|mechanism| mechanism == AUTH_MECHANISM)
+        == false
+    {
+        return Err("plain authentication mechanism not supported".to_string());
+    }
+
+    let mut start_ok = Vec::new();
+
+    push_table(&mut start_ok, &[("product", "vigil")]);
+    push_short_string(&mut start_ok, AUTH_MECHANISM);
+    push_long_string(
+        &mut start_ok,
+        format!("\0{}\0{}", user, password).as_bytes(),
+    );
+    push_short_string(&mut start_ok, "en_US");
+
+    write_method(&mut reader, 0, METHOD_CONNECTION_START_OK, &start_ok)?;
+
+    // Agree on connection parameters (heartbeats are disabled, as the connection is short-lived)
+    let tune = expect_method(&mut reader, METHOD_CONNECTION_TUNE)?;
+
+    let (channel_maximum, frame_maximum) = match (tune.get(0..2), tune.get(2..6)) {
+        (Some(channel_maximum), Some(frame_maximum)) => (
+            channel_maximum,
+            u32::from_be_bytes([
+                frame_maximum[0],
+                frame_maximum[1],
+                frame_maximum[2],
+                frame_maximum[3],
+            ]),
+        ),
+        _ => return Err("invalid connection tune".to_string()),
+    };
+
+    // Notice: a zero frame maximum means that the broker imposes no limit
+    let frame_maximum = if frame_maximum == 0 {
+        FRAME_MAXIMUM_BYTES
+    } else {
+        frame_maximum.min(FRAME_MAXIMUM_BYTES)
+    };
+
+    let mut tune_ok = Vec::new();
+
+    tune_ok.extend_from_slice(channel_maximum);
+    tune_ok.extend_from_slice(&frame_maximum.to_be_bytes());
+    tune_ok.extend_from_slice(&0u16.to_be_bytes());
+
+    write_method(&mut reader, 0, METHOD_CONNECTION_TUNE_OK, &tune_ok)?;
+
+    // Open connection to virtual host
+    let mut open = Vec::new();
+
+    push_short_string(&mut open, virtual_host);
+    push_short_string(&mut open, "");
+
+    open.push(0);
+
+    write_method(&mut reader, 0, METHOD_CONNECTION_OPEN, &open)?;
+    expect_method(&mut reader, METHOD_CONNECTION_OPEN_OK)?;
+
+    // Open channel
+    let mut channel_open = Vec::new();
+
+    push_short_string(&mut channel_open, "");
+
+    write_method(&mut reader, CHANNEL_ID, METHOD_CHANNEL_OPEN, &channel_open)?;
+    expect_method(&mut reader, METHOD_CHANNEL_OPEN_OK)?;
+
+    // Check that queue exists? (if configured, with a passive declare which never creates it)
+    if let Some(queue) = queue {
+        let mut declare = Vec::new();
+
+        declare.extend_from_slice(&0u16.to_be_bytes());
+
+        push_short_string(&mut declare, queue);
+
+        declare.push(0x01);
+
+        push_table(&mut declare, &[]);
+
+        write_method(&mut reader, CHANNEL_ID, METHOD_QUEUE_DECLARE, &declare)?;
+        expect_method(&mut reader, METHOD_QUEUE_DECLARE_OK)?;
+    }
+
+    // Close connection (the reply does not matter there)
+    let mut close = Vec::new();
+
+    close.extend_from_slice(&REPLY_SUCCESS.to_be_bytes());
+
+    push_short_string(&mut close, "");
+
+    close.extend_from_slice(&[0; 4]);
+
+    let _ = write_method(&mut reader, 0, METHOD_CONNECTION_CLOSE, &close);
+
+    Ok(())
+}
+
+fn expect_method<S: Read>(
+    reader: &mut BufReader<S>,
+    expected_method: (u16, u16),
+) -> Result<Vec<u8>, String> {
+    loop {
+        let mut head = [0; 7];
+
+        reader
+            .read_exact(&mut head)
+            .map_err(|err| err.to_string())?;
+
+        let size = u32::from_be_bytes([head[3], head[4], head[5], head[6]]);
+
+        if size > FRAME_MAXIMUM_BYTES {
+            return Err("frame is too large".to_string());
+        }
+
+        // Read payload, along with the frame end marker
+        let mut payload = vec![0; size as usize + 1];
+
+        reader
+            .read_exact(&mut payload)
+            .map_err(|err| err.to_string())?;
+
+        if payload.pop() != Some(FRAME_END) {
+            return Err("invalid frame end".to_string());
+        }
+
+        match head[0] {
+            FRAME_HEARTBEAT => continue,
+            FRAME_METHOD if payload.len() >= 4 => {}
+            _ => return Err(format!("unexpected frame type: {}", head[0])),
+        }
+
+        let method = (
+            u16::from_be_bytes([payload[0], payload[1]]),
+            u16::from_be_bytes([payload[2], payload[3]]),
+        );
+
+        let arguments = payload.split_off(4);
+
+        if method == expected_method {
+            return Ok(arguments);
+        }
+
+        // Broker closed the connection or channel? (eg. authentication failure, unknown \
+        //   virtual host or missing queue)
+        if method == METHOD_CONNECTION_CLOSE || method == METHOD_CHANNEL_CLOSE {
+            let code = arguments
+                .get(0..2)
+                .map(|code| u16::from_be_bytes([code[0], code[1]]))
+                .unwrap_or_default();
+
+            let text = read_short_string(&arguments, 2)
+                .map(|text| String::from_utf8_lossy(text).to_string())
+                .unwrap_or_default();
+
+            return Err(format!("closed by broker: {} {}", code, text));
+        }
+
+        return Err(format!("unexpected method: {:?}", method));
+    }
+}
+
+fn write_method<S: Write>(
+    reader: &mut BufReader<S>,
+    channel: u16,
+    method: (u16, u16),
+    arguments: &[u8],
+) -> Result<(), String> {
+    let mut frame = vec![FRAME_METHOD];
+
+    frame.extend_from_slice(&channel.to_be_bytes());
+    frame.extend_from_slice(&((arguments.len() + 4) as u32).to_be_bytes());
+    frame.extend_from_slice(&method.0.to_be_bytes());
+    frame.extend_from_slice(&method.1.to_be_bytes());
+    frame.extend_from_slice(arguments);
+    frame.push(FRAME_END);
+
+    reader
+        .get_mut()
+        .write_all(&frame)
+        .map_err(|err| err.to_string())
+}
+
+fn push_short_string(buffer: &mut Vec<u8>, value: &str) {
+    let value = &value.as_bytes()[..value.len().min(u8::MAX as usize)];
+
+    buffer.push(value.len() as u8);
+    buffer.extend_from_slice(value);
+}
+
+fn push_long_string(buffer: &mut Vec<u8>, value: &[u8]) {
+    buffer.extend_from_slice(&(value.len() as u32).to_be_bytes());
+    buffer.extend_from_slice(value);
+}
+
+fn push_table(buffer: &mut Vec<u8>, entries: &[(&str, &str)]) {
+    let mut table = Vec::new();
+
+    for (key, value) in entries {
+        push_short_string(&mut table, key);
+
+        table.push(b'S');
+
+        push_long_string(&mut table, value.as_bytes());
+    }
+
+    push_long_string(buffer, &table);
+}
+
+fn read_short_string(buffer: &[u8], offset: usize) -> Option<&[u8]> {
+    let size = *buffer.get(offset)? as usize;
+
+    buffer.get(offset + 1..offset + 1 + size)
+}
+
+fn read_long_string(buffer: &[u8], offset: usize) -> Option<&[u8]> {
+    let size = buffer.get(offset..offset + 4)?;
+    let size = u32::from_be_bytes([size[0], size[1], size[2], size[3]]) as usize;
+
+    buffer.get(offset + 4..offset + 4 + size)
+}
+
+fn skip_table(buffer: &[u8], offset: usize) -> Option<usize> {
+    // Tables are prefixed with their size, like long strings
+    read_long_string(buffer, offset).map(|table| offset + 4 + table.len())
+}
@@ -0,0 +1,224 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::io::{self, Read};
+use std::net::{Shutdown, TcpStream};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::dns::Resolver;
+use super::replica::ReplicaURL;
+
+const COMMAND_TIMEOUT_SECONDS: u64 = 30;
+const COMMAND_WAIT_MILLISECONDS: u64 = 100;
+const OUTPUT_MAXIMUM_BYTES: usize = 16384;
+
+pub struct DiagnosticTarget<'a> {
+    pub probe_id: &'a str,
+    pub node_id: &'a str,
+    pub replica_id: &'a str,
+    pub replica_url: &'a ReplicaURL,
+}
+
+pub fn capture(
+    target: &DiagnosticTarget,
+    command: Option<&str>,
+    tcp_trace: bool,
+    resolver: Resolver,
+    connect_timeout: Duration,
+) -> String {
+    let endpoint = target.replica_url.tcp_endpoint();
+
+    let mut output = String::new();
+
+    if tcp_trace == true {
+        match endpoint {
+            Some((ref host, port)) => {
+                output.push_str(&trace_tcp(host, port, resolver, connect_timeout))
+            }
+            None => output.push_str("tcp trace: replica has no host\n"),
+        }
+    }
+
+    if let Some(command) = command {
+        let (host, port) = endpoint.unwrap_or((String::new(), None));
+
+        // Notice: placeholders get replaced with values from the configuration only, thus they \
+        //   can be trusted to be passed to the shell.
+        let command = command
+            .replace("{{probe}}", target.probe_id)
+            .replace("{{node}}", target.node_id)
+            .replace("{{replica}}", target.replica_id)
+            .replace("{{host}}", &host)
+            .replace(
+                "{{port}}",
+                &port.map(|port| port.to_string()).unwrap_or_default(),
+            );
+
+        if output.is_empty() == false {
+            output.push('\n');
+        }
+
+        output.push_str(&format!("$ {}\n", command));
+
+        match run_command(&command) {
+            Ok((code, command_output)) => {
+                output.push_str(&command_output);
+
+                if command_output.ends_with('\n') == false {
+                    output.push('\n');
+                }
+
+                output.push_str(&format!("(exited with code: {})\n", code));
+            }
+            Err(err) => output.push_str(&format!("(failed: {})\n", err)),
+        }
+    }
+
+    truncate_output(output)
+}
+
+fn trace_tcp(host: &str, port: Option<u16>, resolver: Resolver, timeout: Duration) -> String {
+    let mut trace = String::new();
+
+    // Resolve host (all resolved addresses are traced, not only the one that got probed)
+    let resolve_time = Instant::now();
+
+    let addresses = match resolver.lookup(host, port.unwrap_or(0)) {
+        Ok(addresses) => addresses.collect::<Vec<_>>(),
+        Err(err) => {
+            trace.push_str(&format!(
+                "resolve {}: failed after {}ms ({})\n",
+                host,
+                resolve_time.elapsed().as_millis(),
+                err
+            ));
+
+            return trace;
+        }
+    };
+
+    trace.push_str(&format!(
+        "resolve {}: {} address(es) in {}ms\n",
+        host,
+        addresses.len(),
+        resolve_time.elapsed().as_millis()
+    ));
+
+    for address in addresses.iter() {
+        // Replica is not reached over TCP? (only list resolved addresses)
+        if port.is_none() == true {
+            trace.push_str(&format!("  {}\n", address.ip()));
+
+            continue;
+        }
+
+        let connect_time = Instant::now();
+
+        match TcpStream::connect_timeout(address, timeout) {
+            Ok(stream) => {
+                trace.push_str(&format!(
+                    "  connect {}: connected in {}ms\n",
+                    address,
+                    connect_time.elapsed().as_millis()
+                ));
+
+                stream.shutdown(Shutdown::Both).ok();
+            }
+            Err(err) => {
+                trace.push_str(&format!(
+                    "  connect {}: failed after {}ms ({})\n",
+                    address,
+                    connect_time.elapsed().as_millis(),
+                    err
+                ));
+            }
+        }
+    }
+
+    trace
+}
+
+fn run_command(command: &str) -> Result<(i32, String), String> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| err.to_string())?;
+
+    // Notice: both outputs are captured, as diagnostic tools commonly write details to the \
+    //   error output (eg. 'curl -v'). They are read from separate threads, so that the command \
+    //   is not blocked on full pipes while it is waited for.
+    let readers = [
+        child.stdout.take().map(spawn_reader),
+        child.stderr.take().map(spawn_reader),
+    ];
+
+    let deadline = Instant::now() + Duration::from_secs(COMMAND_TIMEOUT_SECONDS);
+
+    let code = loop {
+        if let Some(exit_status) = child.try_wait().map_err(|err| err.to_string())? {
+            break exit_status.code().unwrap_or(-1);
+        }
+
+        if Instant::now() >= deadline {
+            child.kill().ok();
+            child.wait().ok();
+
+            return Err(format!(
+                "command timed out after {}s",
+                COMMAND_TIMEOUT_SECONDS
+            ));
+        }
+
+        thread::sleep(Duration::from_millis(COMMAND_WAIT_MILLISECONDS));
+    };
+
+    let output = readers
+        .into_iter()
+        .flatten()
+        .filter_map(|reader| reader.join().ok())
+        .collect::<Vec<String>>()
+        .join("");
+
+    Ok((code, output))
+}
+
+fn spawn_reader<R: Read + Send + 'static>(mut source: R) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+
+        io::copy(
+            &mut source.by_ref().take(OUTPUT_MAXIMUM_BYTES as u64),
+            &mut buffer,
+        )
+        .ok();
+
+        // Drain remaining output (so that the command does not block on a full pipe)
+        io::copy(&mut source, &mut io::sink()).ok();
+
+        String::from_utf8_lossy(&buffer).into_owned()
+    })
+}
+
+fn truncate_output(mut output: String) -> String {
+    if output.len() > OUTPUT_MAXIMUM_BYTES {
+        let mut boundary = OUTPUT_MAXIMUM_BYTES;
+
+        while output.is_char_boundary(boundary) == false {
+            boundary -= 1;
+        }
+
+        output.truncate(boundary);
+        output.push_str("\n(output truncated)\n");
+    }
+
+    output
+}
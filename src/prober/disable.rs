@@ -0,0 +1,237 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use indexmap::IndexMap;
+
+use crate::prober::manager::{publish_snapshot, STORE as PROBER_STORE};
+use crate::prober::states::ServiceStatesProbe;
+use crate::storage::sqlite as storage;
+
+// Notice: services meant to be disabled for longer than this should be disabled with no duration
+const DISABLE_DURATION_MAXIMUM_SECONDS: u64 = 31536000;
+
+#[derive(Debug)]
+pub enum DisableSelector<'a> {
+    Group(&'a str),
+    List(&'a [String]),
+}
+
+pub enum HandleDisableError {
+    NotFound,
+    AlreadyDisabled,
+    NotDisabled,
+}
+
+pub fn parse_duration(duration: &str) -> Option<Duration> {
+    // Parse durations such as '90', '90s', '15m', '2h' or '1d' (seconds if there is no unit)
+    let duration = duration.trim();
+
+    let (value, multiplier) = match duration.chars().last() {
+        Some('s') => (&duration[..duration.len() - 1], 1),
+        Some('m') => (&duration[..duration.len() - 1], 60),
+        Some('h') => (&duration[..duration.len() - 1], 3600),
+        Some('d') => (&duration[..duration.len() - 1], 86400),
+        _ => (duration, 1),
+    };
+
+    match value.parse::<u64>() {
+        Ok(value) if value > 0 => value
+            .checked_mul(multiplier)
+            .filter(|seconds| *seconds <= DISABLE_DURATION_MAXIMUM_SECONDS)
+            .map(Duration::from_secs),
+        _ => None,
+    }
+}
+
+pub fn handle_disable(
+    probe_id: &str,
+    duration: Option<Duration>,
+) -> Result<Option<SystemTime>, HandleDisableError> {
+    debug!("disable handle: {} for {:?}", probe_id, duration);
+
+    let mut store = PROBER_STORE.write().unwrap();
+
+    if let Some(ref mut probe) = store.states.probes.get_mut(probe_id) {
+        if probe.disabled == true {
+            return Err(HandleDisableError::AlreadyDisabled);
+        }
+
+        let disabled_until = duration.map(|duration| SystemTime::now() + duration);
+
+        // Notice: disabled probes are not probed anymore, until they get enabled again (either \
+        //   explicitly, or by the aggregator once the disable duration is over).
+        probe.disabled = true;
+        probe.disabled_until = disabled_until;
+
+        publish_snapshot(&store);
+
+        // Release store lock before writing to storage (as this might be slow)
+        drop(store);
+
+        storage::save_disabled_service(probe_id, disabled_until.map(as_timestamp));
+
+        info!("disabled probe: {} for {:?}", probe_id, duration);
+
+        return Ok(disabled_until);
+    }
+
+    Err(HandleDisableError::NotFound)
+}
+
+pub fn handle_enable(probe_id: &str) -> Result<(), HandleDisableError> {
+    debug!("enable handle: {}", probe_id);
+
+    let mut store = PROBER_STORE.write().unwrap();
+
+    if let Some(ref mut probe) = store.states.probes.get_mut(probe_id) {
+        if probe.disabled == false {
+            return Err(HandleDisableError::NotDisabled);
+        }
+
+        probe.disabled = false;
+        probe.disabled_until = None;
+
+        publish_snapshot(&store);
+
+        // Release store lock before writing to storage (as this might be slow)
+        drop(store);
+
+        storage::remove_disabled_service(probe_id);
+
+        info!("enabled probe: {}", probe_id);
+
+        return Ok(());
+    }
+
+    Err(HandleDisableError::NotFound)
+}
+
+pub fn handle_disable_many(
+    selector: DisableSelector,
+    duration: Option<Duration>,
+) -> Result<(Vec<String>, Option<SystemTime>), HandleDisableError> {
+    debug!("disable many handle: {:?} for {:?}", selector, duration);
+
+    let disabled_until = duration.map(|duration| SystemTime::now() + duration);
+
+    // Notice: the store lock is held while resolving and disabling all probes, so that they all \
+    //   get disabled at once (or none of them, if any probe is not found).
+    let mut store = PROBER_STORE.write().unwrap();
+
+    let probe_ids = resolve_selector(&store.states.probes, &selector)?;
+
+    let mut disabled_probe_ids = Vec::new();
+
+    for probe_id in probe_ids {
+        if let Some(ref mut probe) = store.states.probes.get_mut(&probe_id) {
+            // Already-disabled probes are left as-is (including their disable duration)
+            if probe.disabled == true {
+                continue;
+            }
+
+            probe.disabled = true;
+            probe.disabled_until = disabled_until;
+
+            info!("disabled probe: {} for {:?}", probe_id, duration);
+
+            disabled_probe_ids.push(probe_id);
+        }
+    }
+
+    publish_snapshot(&store);
+
+    // Release store lock before writing to storage (as this might be slow)
+    drop(store);
+
+    storage::save_disabled_services(&disabled_probe_ids, disabled_until.map(as_timestamp));
+
+    Ok((disabled_probe_ids, disabled_until))
+}
+
+pub fn handle_enable_many(selector: DisableSelector) -> Result<Vec<String>, HandleDisableError> {
+    debug!("enable many handle: {:?}", selector);
+
+    let mut store = PROBER_STORE.write().unwrap();
+
+    let probe_ids = resolve_selector(&store.states.probes, &selector)?;
+
+    let mut enabled_probe_ids = Vec::new();
+
+    for probe_id in probe_ids {
+        if let Some(ref mut probe) = store.states.probes.get_mut(&probe_id) {
+            if probe.disabled == false {
+                continue;
+            }
+
+            probe.disabled = false;
+            probe.disabled_until = None;
+
+            info!("enabled probe: {}", probe_id);
+
+            enabled_probe_ids.push(probe_id);
+        }
+    }
+
+    publish_snapshot(&store);
+
+    // Release store lock before writing to storage (as this might be slow)
+    drop(store);
+
+    storage::remove_disabled_services(&enabled_probe_ids);
+
+    Ok(enabled_probe_ids)
+}
+
+pub fn list_disabled() -> Vec<String> {
+    PROBER_STORE
+        .read()
+        .unwrap()
+        .states
+        .probes
+        .values()
+        .filter(|probe| probe.disabled == true)
+        .map(|probe| probe.id.to_owned())
+        .collect()
+}
+
+fn resolve_selector(
+    probes: &IndexMap<String, ServiceStatesProbe>,
+    selector: &DisableSelector,
+) -> Result<Vec<String>, HandleDisableError> {
+    let probe_ids: Vec<String> = match *selector {
+        DisableSelector::Group(group_id) => probes
+            .values()
+            .filter(|probe| probe.groups.iter().any(|group| group == group_id))
+            .map(|probe| probe.id.to_owned())
+            .collect(),
+        DisableSelector::List(probe_ids) => {
+            if let Some(probe_id) = probe_ids
+                .iter()
+                .find(|probe_id| probes.contains_key(probe_id.as_str()) == false)
+            {
+                debug!("disable selector references unknown probe: {}", probe_id);
+
+                return Err(HandleDisableError::NotFound);
+            }
+
+            probe_ids.to_vec()
+        }
+    };
+
+    if probe_ids.is_empty() == true {
+        Err(HandleDisableError::NotFound)
+    } else {
+        Ok(probe_ids)
+    }
+}
+
+pub fn as_timestamp(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0)
+}
@@ -0,0 +1,516 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::cmp::min;
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use std::vec;
+
+use openssl::rand::rand_bytes;
+
+use super::family::AddressFamily;
+use crate::APP_CONF;
+
+const NAMESERVER_PORT_DEFAULT: u16 = 53;
+const MESSAGE_MAXIMUM_SIZE: usize = 512;
+const MESSAGE_HEADER_SIZE: usize = 12;
+
+const FLAG_RESPONSE: u16 = 0x8000;
+const FLAG_TRUNCATED: u16 = 0x0200;
+const FLAG_RECURSION_DESIRED: u16 = 0x0100;
+
+const RESPONSE_CODE_MASK: u16 = 0x000f;
+const RESPONSE_CODE_NAME_ERROR: u16 = 3;
+
+const RECORD_TYPE_A: u16 = 1;
+const RECORD_TYPE_AAAA: u16 = 28;
+const RECORD_CLASS_IN: u16 = 1;
+
+lazy_static! {
+    static ref CACHE: RwLock<HashMap<CacheKey, CacheEntry>> = RwLock::new(HashMap::new());
+}
+
+#[derive(PartialEq, Eq, Hash)]
+struct CacheKey {
+    nameserver: Option<SocketAddr>,
+    family: AddressFamily,
+    host: String,
+}
+
+struct CacheEntry {
+    addresses: Vec<IpAddr>,
+    expires_at: Instant,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Resolver {
+    family: AddressFamily,
+    nameserver: Option<SocketAddr>,
+}
+
+impl Resolver {
+    pub fn new(family: AddressFamily, nameserver: Option<SocketAddr>) -> Resolver {
+        Resolver {
+            family: family,
+            nameserver: nameserver,
+        }
+    }
+
+    pub fn family(&self) -> AddressFamily {
+        self.family
+    }
+
+    pub fn overrides_system(&self) -> bool {
+        self.nameserver.is_some() || APP_CONF.metrics.poll_dns_cache_ttl.is_some()
+    }
+
+    pub fn lookup(&self, host: &str, port: u16) -> io::Result<vec::IntoIter<SocketAddr>> {
+        // Notice: only addresses of the requested family are returned, which means that the \
+        //   first returned address is the one that should be connected to.
+        Ok(self
+            .lookup_addresses(host)?
+            .into_iter()
+            .map(|address| SocketAddr::new(address, port))
+            .filter(|address| self.family.matches(address))
+            .collect::<Vec<SocketAddr>>()
+            .into_iter())
+    }
+
+    fn lookup_addresses(&self, host: &str) -> io::Result<Vec<IpAddr>> {
+        // Host is an IP address? (no resolution needed)
+        if let Ok(address) = host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+        {
+            return Ok(vec![address]);
+        }
+
+        let cache_key = CacheKey {
+            nameserver: self.nameserver,
+            family: self.family,
+            host: host.to_lowercase(),
+        };
+
+        if let Some(entry) = CACHE.read().unwrap().get(&cache_key) {
+            if entry.expires_at > Instant::now() {
+                return Ok(entry.addresses.to_owned());
+            }
+        }
+
+        // Notice: the system resolver does not expose record TTLs, thus addresses resolved \
+        //   through it are cached for the maximum cache TTL.
+        let (addresses, ttl) = match self.nameserver {
+            Some(nameserver) => query_nameserver(nameserver, host, self.family)?,
+            None => (
+                (host, 0)
+                    .to_socket_addrs()?
+                    .map(|address| address.ip())
+                    .collect(),
+                None,
+            ),
+        };
+
+        debug!(
+            "prober dns resolved host: {} to: {:?} (ttl: {:?})",
+            host, addresses, ttl
+        );
+
+        // Cache resolved addresses? (if caching is enabled)
+        if let Some(ttl_maximum) = APP_CONF.metrics.poll_dns_cache_ttl {
+            let ttl = min(ttl.unwrap_or(ttl_maximum), ttl_maximum);
+
+            if ttl > 0 && addresses.is_empty() == false {
+                CACHE.write().unwrap().insert(
+                    cache_key,
+                    CacheEntry {
+                        addresses: addresses.to_owned(),
+                        expires_at: Instant::now() + Duration::from_secs(ttl),
+                    },
+                );
+            }
+        }
+
+        Ok(addresses)
+    }
+}
+
+pub fn parse_nameserver(value: &str) -> Option<SocketAddr> {
+    value.parse::<SocketAddr>().ok().or_else(|| {
+        value
+            .parse::<IpAddr>()
+            .ok()
+            .map(|address| SocketAddr::new(address, NAMESERVER_PORT_DEFAULT))
+    })
+}
+
+fn query_nameserver(
+    nameserver: SocketAddr,
+    host: &str,
+    family: AddressFamily,
+) -> io::Result<(Vec<IpAddr>, Option<u64>)> {
+    let timeout = Duration::from_secs(APP_CONF.metrics.poll_delay_dead);
+
+    let socket = if nameserver.is_ipv4() == true {
+        UdpSocket::bind("0.0.0.0:0")?
+    } else {
+        UdpSocket::bind("[::]:0")?
+    };
+
+    socket.set_read_timeout(Some(timeout))?;
+    socket.set_write_timeout(Some(timeout))?;
+    socket.connect(nameserver)?;
+
+    // Query records for the requested families (the lowest TTL is retained)
+    let record_types: &[u16] = match family {
+        AddressFamily::Any => &[RECORD_TYPE_A, RECORD_TYPE_AAAA],
+        AddressFamily::IPv4 => &[RECORD_TYPE_A],
+        AddressFamily::IPv6 => &[RECORD_TYPE_AAAA],
+    };
+
+    let (mut addresses, mut ttl) = (Vec::new(), None);
+
+    for record_type in record_types {
+        let (record_addresses, record_ttl) = query_records(&socket, host, *record_type)
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+
+        addresses.extend(record_addresses);
+
+        if let Some(record_ttl) = record_ttl {
+            ttl = Some(min(ttl.unwrap_or(record_ttl), record_ttl));
+        }
+    }
+
+    if addresses.is_empty() == true {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no address found for host: {}", host),
+        ));
+    }
+
+    Ok((addresses, ttl))
+}
+
+fn query_records(
+    socket: &UdpSocket,
+    host: &str,
+    record_type: u16,
+) -> Result<(Vec<IpAddr>, Option<u64>), String> {
+    let id = make_message_id()?;
+
+    socket
+        .send(&make_query(id, host, record_type)?)
+        .map_err(|err| err.to_string())?;
+
+    let mut response = [0; MESSAGE_MAXIMUM_SIZE];
+
+    // Skip responses to previous queries (eg. a late response that arrived after a timeout)
+    loop {
+        let size = socket.recv(&mut response).map_err(|err| err.to_string())?;
+
+        if size >= 2 && read_u16(&response, 0) == Some(id) {
+            return parse_response(&response[..size], record_type);
+        }
+    }
+}
+
+fn make_query(id: u16, host: &str, record_type: u16) -> Result<Vec<u8>, String> {
+    let mut query = Vec::with_capacity(MESSAGE_HEADER_SIZE + host.len() + 6);
+
+    // Header (with a single question)
+    query.extend(&id.to_be_bytes());
+    query.extend(&FLAG_RECURSION_DESIRED.to_be_bytes());
+    query.extend(&[0, 1, 0, 0, 0, 0, 0, 0]);
+
+    // Question name (as a sequence of length-prefixed labels)
+    for label in host.trim_end_matches('.').split('.') {
+        if label.is_empty() == true || label.len() > 63 {
+            return Err(format!("invalid host: {}", host));
+        }
+
+        query.push(label.len() as u8);
+        query.extend(label.as_bytes());
+    }
+
+    query.push(0);
+    query.extend(&record_type.to_be_bytes());
+    query.extend(&RECORD_CLASS_IN.to_be_bytes());
+
+    Ok(query)
+}
+
+fn parse_response(response: &[u8], record_type: u16) -> Result<(Vec<IpAddr>, Option<u64>), String> {
+    let flags = read_u16(response, 2).ok_or("response is too short")?;
+
+    if flags & FLAG_RESPONSE == 0 {
+        return Err("message is not a response".to_string());
+    }
+    if flags & FLAG_TRUNCATED != 0 {
+        return Err("response is truncated".to_string());
+    }
+
+    // Notice: a non-existent name is not an error, as the host might still have records of \
+    //   the other address family.
+    match flags & RESPONSE_CODE_MASK {
+        0 => {}
+        RESPONSE_CODE_NAME_ERROR => return Ok((Vec::new(), None)),
+        code => return Err(format!("nameserver replied with code: {}", code)),
+    }
+
+    let questions_count = read_u16(response, 4).ok_or("response is too short")?;
+    let answers_count = read_u16(response, 6).ok_or("response is too short")?;
+
+    let mut offset = MESSAGE_HEADER_SIZE;
+
+    for _ in 0..questions_count {
+        offset = skip_name(response, offset).ok_or("invalid question")? + 4;
+    }
+
+    // Collect records of the queried type (the answer also holds any CNAME record followed)
+    let (mut addresses, mut ttl) = (Vec::new(), None);
+
+    for _ in 0..answers_count {
+        offset = skip_name(response, offset).ok_or("invalid answer")?;
+
+        let answer_type = read_u16(response, offset).ok_or("invalid answer")?;
+        let answer_class = read_u16(response, offset + 2).ok_or("invalid answer")?;
+        let answer_ttl = read_u32(response, offset + 4).ok_or("invalid answer")? as u64;
+        let data_length = read_u16(response, offset + 8).ok_or("invalid answer")? as usize;
+
+        offset += 10;
+
+        let data = response
+            .get(offset..(offset + data_length))
+            .ok_or("invalid answer")?;
+
+        offset += data_length;
+
+        if answer_type != record_type || answer_class != RECORD_CLASS_IN {
+            continue;
+        }
+
+        let address = match data.len() {
+            4 => IpAddr::V4(Ipv4Addr::new(data[0], data[1], data[2], data[3])),
+            16 => {
+                let mut octets = [0; 16];
+
+                octets.copy_from_slice(data);
+
+                IpAddr::V6(Ipv6Addr::from(octets))
+            }
+            _ => return Err("invalid address record".to_string()),
+        };
+
+        addresses.push(address);
+        ttl = Some(min(ttl.unwrap_or(answer_ttl), answer_ttl));
+    }
+
+    Ok((addresses, ttl))
+}
+
+fn skip_name(message: &[u8], mut offset: usize) -> Option<usize> {
+    loop {
+        let length = *message.get(offset)?;
+
+        // End of name, or compression pointer (which always ends the name)
+        if length == 0 {
+            return Some(offset + 1);
+        }
+        if length & 0xc0 == 0xc0 {
+            return Some(offset + 2);
+        }
+
+        offset += 1 + length as usize;
+    }
+}
+
+fn make_message_id() -> Result<u16, String> {
+    let mut id = [0; 2];
+
+    // Notice: the message identifier is random, so that responses cannot be easily spoofed
+    rand_bytes(&mut id).map_err(|err| err.to_string())?;
+
+    Ok(u16::from_be_bytes(id))
+}
+
+fn read_u16(buffer: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([
+        *buffer.get(offset)?,
+        *buffer.get(offset + 1)?,
+    ]))
+}
+
+fn read_u32(buffer: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes([
+        *buffer.get(offset)?,
+        *buffer.get(offset + 1)?,
+        *buffer.get(offset + 2)?,
+        *buffer.get(offset + 3)?,
+    ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECORD_TYPE_CNAME: u16 = 5;
+
+    // Notice: the question name is always at the end of the header, which is where \
+    //   compression pointers ('0xc0 0x0c') point to.
+    fn make_response(flags: u16, answers: &[(&[u8], u16, u32, &[u8])]) -> Vec<u8> {
+        let mut response = make_query(0x1234, "example.com", RECORD_TYPE_A).unwrap();
+
+        response[2..4].copy_from_slice(&(FLAG_RESPONSE | flags).to_be_bytes());
+        response[6..8].copy_from_slice(&(answers.len() as u16).to_be_bytes());
+
+        for (name, record_type, ttl, data) in answers {
+            response.extend(*name);
+            response.extend(&record_type.to_be_bytes());
+            response.extend(&RECORD_CLASS_IN.to_be_bytes());
+            response.extend(&ttl.to_be_bytes());
+            response.extend(&(data.len() as u16).to_be_bytes());
+            response.extend(*data);
+        }
+
+        response
+    }
+
+    #[test]
+    fn it_makes_queries() {
+        assert_eq!(
+            make_query(0x1234, "example.com.", RECORD_TYPE_AAAA).unwrap(),
+            vec![
+                0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0, 7, b'e', b'x', b'a', b'm', b'p',
+                b'l', b'e', 3, b'c', b'o', b'm', 0, 0, 28, 0, 1
+            ]
+        );
+
+        assert!(make_query(0x1234, "example..com", RECORD_TYPE_A).is_err());
+        assert!(make_query(0x1234, &"a".repeat(64), RECORD_TYPE_A).is_err());
+    }
+
+    #[test]
+    fn it_parses_address_records() {
+        let response = make_response(
+            0,
+            &[
+                (&[0xc0, 0x0c], RECORD_TYPE_A, 300, &[192, 0, 2, 1]),
+                (&[0xc0, 0x0c], RECORD_TYPE_A, 60, &[192, 0, 2, 2]),
+            ],
+        );
+
+        assert_eq!(
+            parse_response(&response, RECORD_TYPE_A),
+            Ok((
+                vec![
+                    IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)),
+                    IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2))
+                ],
+                Some(60)
+            ))
+        );
+
+        let response = make_response(
+            0,
+            &[(
+                &[0xc0, 0x0c],
+                RECORD_TYPE_AAAA,
+                120,
+                &[0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1],
+            )],
+        );
+
+        assert_eq!(
+            parse_response(&response, RECORD_TYPE_AAAA),
+            Ok((vec!["2001:db8::1".parse().unwrap()], Some(120)))
+        );
+    }
+
+    #[test]
+    fn it_follows_cname_chains() {
+        // 'example.com' is an alias of 'www.example.com', whose address is returned
+        let response = make_response(
+            0,
+            &[
+                (
+                    &[0xc0, 0x0c],
+                    RECORD_TYPE_CNAME,
+                    30,
+                    &[3, b'w', b'w', b'w', 0xc0, 0x0c],
+                ),
+                (
+                    &[3, b'w', b'w', b'w', 0xc0, 0x0c],
+                    RECORD_TYPE_A,
+                    300,
+                    &[192, 0, 2, 1],
+                ),
+            ],
+        );
+
+        assert_eq!(
+            parse_response(&response, RECORD_TYPE_A),
+            Ok((vec![IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))], Some(300)))
+        );
+    }
+
+    #[test]
+    fn it_returns_no_address_for_non_existent_names() {
+        let response = make_response(RESPONSE_CODE_NAME_ERROR, &[]);
+
+        assert_eq!(
+            parse_response(&response, RECORD_TYPE_A),
+            Ok((Vec::new(), None))
+        );
+    }
+
+    #[test]
+    fn it_rejects_failed_responses() {
+        assert!(parse_response(&make_response(2, &[]), RECORD_TYPE_A).is_err());
+        assert!(parse_response(&make_response(FLAG_TRUNCATED, &[]), RECORD_TYPE_A).is_err());
+
+        let mut query = make_query(0x1234, "example.com", RECORD_TYPE_A).unwrap();
+
+        assert!(parse_response(&query, RECORD_TYPE_A).is_err());
+
+        query.truncate(3);
+
+        assert!(parse_response(&query, RECORD_TYPE_A).is_err());
+    }
+
+    #[test]
+    fn it_rejects_malformed_responses() {
+        let response = make_response(0, &[(&[0xc0, 0x0c], RECORD_TYPE_A, 300, &[192, 0, 2, 1])]);
+
+        // Truncated in the middle of the answer name, fields, or data
+        for size in [response.len() - 15, response.len() - 10, response.len() - 1] {
+            assert!(parse_response(&response[..size], RECORD_TYPE_A).is_err());
+        }
+
+        // Address record of an invalid length
+        let response = make_response(0, &[(&[0xc0, 0x0c], RECORD_TYPE_A, 300, &[192, 0, 2])]);
+
+        assert!(parse_response(&response, RECORD_TYPE_A).is_err());
+
+        // Answer name running past the end of the response
+        let response = make_response(0, &[(&[63, b'a'], RECORD_TYPE_A, 300, &[192, 0, 2, 1])]);
+
+        assert!(parse_response(&response, RECORD_TYPE_A).is_err());
+    }
+
+    #[test]
+    fn it_skips_names() {
+        let message = [3, b'w', b'w', b'w', 0xc0, 0x0c, 7, b'e', b'x'];
+
+        assert_eq!(skip_name(&message, 0), Some(6));
+        assert_eq!(skip_name(&message, 4), Some(6));
+        assert_eq!(skip_name(&[0], 0), Some(1));
+        assert_eq!(skip_name(&message, 6), None);
+        assert_eq!(skip_name(&[], 0), None);
+    }
+}
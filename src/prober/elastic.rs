@@ -0,0 +1,142 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+use reqwest::redirect::Policy as RedirectPolicy;
+use reqwest::StatusCode;
+
+use super::family::AddressFamily;
+use super::status::Status;
+use crate::APP_CONF;
+
+const CLUSTER_HEALTH_PATH: &'static str = "/_cluster/health";
+
+lazy_static! {
+    static ref ELASTIC_CLIENTS: RwLock<HashMap<(AddressFamily, bool), Client>> =
+        RwLock::new(HashMap::new());
+}
+
+#[derive(Deserialize)]
+struct ClusterHealthResponse {
+    status: String,
+    cluster_name: Option<String>,
+}
+
+pub fn check(
+    host: &str,
+    port: u16,
+    tls: bool,
+    user: &Option<String>,
+    password: &Option<String>,
+    tls_insecure: bool,
+    family: AddressFamily,
+) -> Status {
+    let url = make_url(host, port, tls);
+
+    debug!("prober poll will fire for elastic target: {}", url);
+
+    let client = match acquire_client(family, tls_insecure) {
+        Ok(client) => client,
+        Err(err) => {
+            debug!(
+                "prober poll could not acquire client for elastic target: {} (error: {})",
+                url, err
+            );
+
+            return Status::Dead;
+        }
+    };
+
+    let mut request = client.get(&url);
+
+    // Authenticate? (if a user or a password is set)
+    if user.is_some() == true || password.is_some() == true {
+        request = request.basic_auth(user.as_deref().unwrap_or_default(), password.as_deref());
+    }
+
+    match request.send() {
+        Ok(response_inner) => {
+            let status_code = response_inner.status();
+
+            debug!(
+                "prober poll result received for elastic target: {} with status: {}",
+                url, status_code
+            );
+
+            if status_code == StatusCode::OK {
+                match response_inner.json::<ClusterHealthResponse>() {
+                    Ok(health) => {
+                        debug!(
+                            "prober poll got cluster health for elastic target: {} (cluster: {:?}, status: {})",
+                            url, health.cluster_name, health.status
+                        );
+
+                        // Notice: a yellow cluster serves all data, but some replica shards are \
+                        //   not allocated, which means it is one node failure away from data loss.
+                        match health.status.as_str() {
+                            "green" => return Status::Healthy,
+                            "yellow" => return Status::Sick,
+                            _ => {}
+                        }
+                    }
+                    Err(err) => debug!(
+                        "could not decode cluster health for elastic target: {} (error: {})",
+                        url, err
+                    ),
+                }
+            }
+        }
+        Err(err) => {
+            debug!(
+                "prober poll result was not received for elastic target: {} (error: {})",
+                url, err
+            );
+        }
+    }
+
+    // Consider as DOWN.
+    Status::Dead
+}
+
+fn acquire_client(family: AddressFamily, tls_insecure: bool) -> Result<Client, String> {
+    let client_key = (family, tls_insecure);
+
+    if let Some(client) = ELASTIC_CLIENTS.read().unwrap().get(&client_key) {
+        return Ok(client.clone());
+    }
+
+    let client = Client::builder()
+        .timeout(Duration::from_secs(APP_CONF.metrics.poll_delay_dead))
+        .gzip(false)
+        .redirect(RedirectPolicy::none())
+        .user_agent(format!("vigil (+{})", APP_CONF.branding.page_url.as_str()))
+        .local_address(family.local_address())
+        .danger_accept_invalid_certs(tls_insecure)
+        .build()
+        .map_err(|err| err.to_string())?;
+
+    ELASTIC_CLIENTS
+        .write()
+        .unwrap()
+        .insert(client_key, client.clone());
+
+    Ok(client)
+}
+
+fn make_url(host: &str, port: u16, tls: bool) -> String {
+    let scheme = if tls == true { "https" } else { "http" };
+
+    // Wrap IPv6 addresses in brackets, as they would otherwise be ambiguous with the port
+    if host.contains(':') == true {
+        format!("{}://[{}]:{}{}", scheme, host, port, CLUSTER_HEALTH_PATH)
+    } else {
+        format!("{}://{}:{}{}", scheme, host, port, CLUSTER_HEALTH_PATH)
+    }
+}
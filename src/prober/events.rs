@@ -0,0 +1,187 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use futures::channel::mpsc::{self, Receiver, Sender};
+
+use super::states::ServiceStates;
+use super::status::Status;
+use crate::storage::sqlite as storage;
+
+const SUBSCRIBERS_MAXIMUM: usize = 1024;
+const SUBSCRIBER_BUFFER_MESSAGES: usize = 16;
+
+type StateEventKey = (Option<String>, Option<String>, Option<String>);
+
+// Notice: serialized events are shared between subscribers (an empty list means keep-alive)
+pub type StateEventsMessage = Arc<Vec<String>>;
+
+lazy_static! {
+    static ref EVENTS_SNAPSHOT: Mutex<HashMap<StateEventKey, Status>> = Mutex::new(HashMap::new());
+    static ref EVENTS_SUBSCRIBERS: Mutex<Vec<Sender<StateEventsMessage>>> = Mutex::new(Vec::new());
+}
+
+#[derive(Serialize)]
+pub struct StateEvent {
+    pub kind: StateEventKind,
+    pub probe_id: Option<String>,
+    pub node_id: Option<String>,
+    pub replica_id: Option<String>,
+    pub status: Status,
+    pub previous_status: Option<Status>,
+    pub time: i64,
+}
+
+#[derive(Serialize)]
+pub enum StateEventKind {
+    #[serde(rename = "general")]
+    General,
+
+    #[serde(rename = "probe")]
+    Probe,
+
+    #[serde(rename = "node")]
+    Node,
+
+    #[serde(rename = "replica")]
+    Replica,
+}
+
+pub fn scan(states: &ServiceStates) -> Vec<StateEvent> {
+    // Compare states with last scanned states, and list everything that changed
+    // Notice: replica states are not only bumped by the aggregator (eg. 'poll' replicas get \
+    //   bumped by the prober), hence why a snapshot is used there.
+    let mut snapshot = EVENTS_SNAPSHOT.lock().unwrap();
+    let mut current = HashMap::new();
+    let mut events = Vec::new();
+
+    let time = storage::time_now();
+
+    let mut compare = |kind: StateEventKind, key: StateEventKey, status: &Status| {
+        let previous_status = snapshot.get(&key).cloned();
+
+        if previous_status.as_ref() != Some(status) {
+            events.push(StateEvent {
+                kind: kind,
+                probe_id: key.0.to_owned(),
+                node_id: key.1.to_owned(),
+                replica_id: key.2.to_owned(),
+                status: status.to_owned(),
+                previous_status: previous_status,
+                time: time,
+            });
+        }
+
+        current.insert(key, status.to_owned());
+    };
+
+    compare(StateEventKind::General, (None, None, None), &states.status);
+
+    for (probe_id, probe) in states.probes.iter() {
+        compare(
+            StateEventKind::Probe,
+            (Some(probe_id.to_owned()), None, None),
+            &probe.status,
+        );
+
+        for (node_id, node) in probe.nodes.iter() {
+            compare(
+                StateEventKind::Node,
+                (Some(probe_id.to_owned()), Some(node_id.to_owned()), None),
+                &node.status,
+            );
+
+            for (replica_id, replica) in node.replicas.iter() {
+                compare(
+                    StateEventKind::Replica,
+                    (
+                        Some(probe_id.to_owned()),
+                        Some(node_id.to_owned()),
+                        Some(replica_id.to_owned()),
+                    ),
+                    &replica.status,
+                );
+            }
+        }
+    }
+
+    // Replace snapshot (this also forgets about flushed replicas)
+    *snapshot = current;
+
+    events
+}
+
+pub fn subscribe() -> Option<Receiver<StateEventsMessage>> {
+    let mut subscribers = EVENTS_SUBSCRIBERS.lock().unwrap();
+
+    // Too many subscribers? (forget about subscribers that went away first)
+    if subscribers.len() >= SUBSCRIBERS_MAXIMUM {
+        subscribers.retain(|subscriber| subscriber.is_closed() == false);
+
+        if subscribers.len() >= SUBSCRIBERS_MAXIMUM {
+            warn!("too many state events subscribers, refusing new subscriber");
+
+            return None;
+        }
+    }
+
+    let (sender, receiver) = mpsc::channel(SUBSCRIBER_BUFFER_MESSAGES);
+
+    subscribers.push(sender);
+
+    Some(receiver)
+}
+
+pub fn publish(events: &[StateEvent]) {
+    let mut subscribers = EVENTS_SUBSCRIBERS.lock().unwrap();
+
+    if subscribers.is_empty() == true {
+        return;
+    }
+
+    // Serialize events once for all subscribers
+    // Notice: an empty message is sent if there are no events (subscribers use it as a \
+    //   keep-alive), which also lets us forget about subscribers that went away.
+    let message: StateEventsMessage = Arc::new(
+        events
+            .iter()
+            .filter_map(|event| match serde_json::to_string(event) {
+                Ok(data) => Some(data),
+                Err(err) => {
+                    error!("could not serialize state event: {}", err);
+
+                    None
+                }
+            })
+            .collect(),
+    );
+
+    // Notice: subscribers that do not keep up with events are dropped (their stream then \
+    //   ends), rather than buffering events for them without bounds.
+    *subscribers = subscribers
+        .drain(..)
+        .filter_map(
+            |mut subscriber| match subscriber.try_send(message.clone()) {
+                Ok(()) => Some(subscriber),
+                Err(err) => {
+                    if err.is_full() == true {
+                        debug!("dropped lagging state events subscriber");
+                    }
+
+                    None
+                }
+            },
+        )
+        .collect();
+
+    debug!(
+        "published {} state events to {} subscribers",
+        events.len(),
+        subscribers.len()
+    );
+}
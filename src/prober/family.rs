@@ -0,0 +1,34 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AddressFamily {
+    Any,
+    IPv4,
+    IPv6,
+}
+
+impl AddressFamily {
+    pub fn matches(&self, address: &SocketAddr) -> bool {
+        match self {
+            AddressFamily::Any => true,
+            AddressFamily::IPv4 => address.is_ipv4(),
+            AddressFamily::IPv6 => address.is_ipv6(),
+        }
+    }
+
+    pub fn local_address(&self) -> Option<IpAddr> {
+        // Notice: binding HTTP clients to an unspecified local address of a given family makes \
+        //   them connect to resolved addresses of this family only.
+        match self {
+            AddressFamily::Any => None,
+            AddressFamily::IPv4 => Some(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+            AddressFamily::IPv6 => Some(IpAddr::V6(Ipv6Addr::UNSPECIFIED)),
+        }
+    }
+}
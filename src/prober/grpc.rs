@@ -0,0 +1,249 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::time::Duration;
+
+use reqwest::blocking::{Client, ClientBuilder};
+use reqwest::header::{CONTENT_TYPE, TE};
+use reqwest::redirect::Policy as RedirectPolicy;
+use reqwest::StatusCode;
+
+use super::dns::Resolver;
+use super::family::AddressFamily;
+use super::status::Status;
+use crate::APP_CONF;
+
+const HEALTH_CHECK_PATH: &'static str = "/grpc.health.v1.Health/Check";
+const HEADER_GRPC_STATUS: &'static str = "grpc-status";
+const GRPC_CONTENT_TYPE: &'static str = "application/grpc";
+const GRPC_FRAME_HEADER_SIZE: usize = 5;
+
+// Notice: the 'status' field of the 'HealthCheckResponse' message (see: \
+//   https://github.com/grpc/grpc/blob/master/doc/health-checking.md)
+const HEALTH_FIELD_STATUS: u64 = 1;
+const HEALTH_STATUS_SERVING: u64 = 1;
+
+lazy_static! {
+    static ref GRPC_CLIENT: Client = make_client_builder(AddressFamily::Any).build().unwrap();
+    static ref GRPC_CLIENT_IPV4: Client = make_client_builder(AddressFamily::IPv4).build().unwrap();
+    static ref GRPC_CLIENT_IPV6: Client = make_client_builder(AddressFamily::IPv6).build().unwrap();
+}
+
+pub fn check(
+    host: &str,
+    port: u16,
+    service: &str,
+    tls: bool,
+    authority: &Option<String>,
+    resolver: Resolver,
+) -> Status {
+    // Build a dedicated client if the authority is overridden, which then resolves to the \
+    //   replica host (this way, both the ':authority' header and the TLS server name match it); \
+    //   this is also the case if the replica host should not be resolved by the system resolver.
+    let name = match authority {
+        Some(authority) => Some(authority.as_str()),
+        None if resolver.overrides_system() == true => Some(host),
+        None => None,
+    };
+
+    let (client, url) = match name {
+        Some(name) => match make_resolved_client(host, port, name, resolver) {
+            Ok(client) => (client, make_url(name, port, tls)),
+            Err(_) => {
+                debug!(
+                    "prober poll did not resolve any address for grpc replica: {}:{}",
+                    host, port
+                );
+
+                return Status::Dead;
+            }
+        },
+        None => (
+            acquire_client(resolver.family()).clone(),
+            make_url(host, port, tls),
+        ),
+    };
+
+    debug!(
+        "prober poll will fire for grpc target: {} with service: '{}'",
+        url, service
+    );
+
+    let response = client
+        .post(&url)
+        .header(CONTENT_TYPE, GRPC_CONTENT_TYPE)
+        .header(TE, "trailers")
+        .body(encode_request(service))
+        .send();
+
+    match response {
+        Ok(response_inner) => {
+            let status_code = response_inner.status();
+
+            // Notice: errors are sent in 'trailers-only' responses, where the gRPC status ends \
+            //   up in the response headers (a missing gRPC status means that it is in trailers).
+            let grpc_status = response_inner
+                .headers()
+                .get(HEADER_GRPC_STATUS)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string);
+
+            debug!(
+                "prober poll result received for grpc target: {} with status: {} and grpc status: {:?}",
+                url, status_code, grpc_status
+            );
+
+            if status_code == StatusCode::OK && grpc_status.as_deref().unwrap_or("0") == "0" {
+                match response_inner
+                    .bytes()
+                    .ok()
+                    .and_then(|body| decode_response(&body))
+                {
+                    Some(HEALTH_STATUS_SERVING) => return Status::Healthy,
+                    Some(serving_status) => debug!(
+                        "prober poll got non-serving status for grpc target: {} (status: {})",
+                        url, serving_status
+                    ),
+                    None => debug!("could not decode response for grpc target: {}", url),
+                }
+            }
+        }
+        Err(err) => {
+            debug!(
+                "prober poll result was not received for grpc target: {} (error: {})",
+                url, err
+            );
+        }
+    }
+
+    // Consider as DOWN.
+    Status::Dead
+}
+
+fn acquire_client(family: AddressFamily) -> &'static Client {
+    match family {
+        AddressFamily::Any => &*GRPC_CLIENT,
+        AddressFamily::IPv4 => &*GRPC_CLIENT_IPV4,
+        AddressFamily::IPv6 => &*GRPC_CLIENT_IPV6,
+    }
+}
+
+fn make_client_builder(family: AddressFamily) -> ClientBuilder {
+    Client::builder()
+        .local_address(family.local_address())
+        .timeout(Duration::from_secs(APP_CONF.metrics.poll_delay_dead))
+        .gzip(false)
+        .redirect(RedirectPolicy::none())
+        .http2_prior_knowledge()
+        .user_agent(format!("vigil (+{})", APP_CONF.branding.page_url.as_str()))
+}
+
+fn make_resolved_client(
+    host: &str,
+    port: u16,
+    name: &str,
+    resolver: Resolver,
+) -> Result<Client, ()> {
+    let address = resolver.lookup(host, port).or(Err(()))?.next().ok_or(())?;
+
+    make_client_builder(AddressFamily::Any)
+        .resolve(name, address)
+        .build()
+        .or(Err(()))
+}
+
+fn make_url(host: &str, port: u16, tls: bool) -> String {
+    let scheme = if tls == true { "https" } else { "http" };
+
+    // Wrap IPv6 addresses in brackets, as they would otherwise be ambiguous with the port
+    if host.contains(':') == true {
+        format!("{}://[{}]:{}{}", scheme, host, port, HEALTH_CHECK_PATH)
+    } else {
+        format!("{}://{}:{}{}", scheme, host, port, HEALTH_CHECK_PATH)
+    }
+}
+
+fn encode_request(service: &str) -> Vec<u8> {
+    // Encode 'HealthCheckRequest' message (its 'service' field is omitted if empty)
+    let mut message = Vec::new();
+
+    if service.is_empty() == false {
+        message.push(0x0a);
+
+        encode_varint(service.len() as u64, &mut message);
+
+        message.extend_from_slice(service.as_bytes());
+    }
+
+    // Wrap message in an uncompressed gRPC frame
+    let mut frame = Vec::with_capacity(GRPC_FRAME_HEADER_SIZE + message.len());
+
+    frame.push(0);
+    frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&message);
+
+    frame
+}
+
+fn decode_response(body: &[u8]) -> Option<u64> {
+    // Unwrap message from gRPC frame (compressed messages are not supported)
+    if body.len() < GRPC_FRAME_HEADER_SIZE || body[0] != 0 {
+        return None;
+    }
+
+    let size = u32::from_be_bytes([body[1], body[2], body[3], body[4]]) as usize;
+    let mut message = body.get(GRPC_FRAME_HEADER_SIZE..GRPC_FRAME_HEADER_SIZE + size)?;
+
+    // Decode 'HealthCheckResponse' message (an omitted status is 'UNKNOWN', ie. 0)
+    let mut status = 0;
+
+    while message.is_empty() == false {
+        let key = decode_varint(&mut message)?;
+
+        match (key >> 3, key & 0x07) {
+            (HEALTH_FIELD_STATUS, 0) => status = decode_varint(&mut message)?,
+            (_, 0) => {
+                decode_varint(&mut message)?;
+            }
+            (_, 1) => message = message.get(8..)?,
+            (_, 2) => {
+                let length = decode_varint(&mut message)? as usize;
+
+                message = message.get(length..)?;
+            }
+            (_, 5) => message = message.get(4..)?,
+            _ => return None,
+        }
+    }
+
+    Some(status)
+}
+
+fn encode_varint(mut value: u64, buffer: &mut Vec<u8>) {
+    while value >= 0x80 {
+        buffer.push((value as u8 & 0x7f) | 0x80);
+
+        value >>= 7;
+    }
+
+    buffer.push(value as u8);
+}
+
+fn decode_varint(buffer: &mut &[u8]) -> Option<u64> {
+    let mut value = 0;
+
+    for (index, byte) in buffer.iter().enumerate().take(10) {
+        value |= ((byte & 0x7f) as u64) << (7 * index);
+
+        if byte & 0x80 == 0 {
+            *buffer = &buffer[index + 1..];
+
+            return Some(value);
+        }
+    }
+
+    None
+}
@@ -0,0 +1,282 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use super::dns::Resolver;
+use super::status::Status;
+use crate::APP_CONF;
+
+const RESPONSE_MAXIMUM_BYTES: usize = 4194304;
+const CLIENT_ID: &'static str = "vigil";
+const CORRELATION_ID: i32 = 1;
+
+// Notice: Metadata v4 is supported by all brokers since Kafka 1.0
+const API_KEY_METADATA: i16 = 3;
+const API_VERSION_METADATA: i16 = 4;
+
+const ERROR_NONE: i16 = 0;
+const ERROR_UNKNOWN_TOPIC_OR_PARTITION: i16 = 3;
+const NODE_NONE: i32 = -1;
+
+struct Cursor<'a> {
+    buffer: &'a [u8],
+}
+
+struct Metadata {
+    brokers: usize,
+    controller_id: i32,
+    topics: Vec<MetadataTopic>,
+}
+
+struct MetadataTopic {
+    error_code: i16,
+    partitions: Vec<(i16, i32)>,
+}
+
+pub fn check(
+    host: &str,
+    port: u16,
+    brokers_minimum: Option<u32>,
+    topic: &Option<String>,
+    resolver: Resolver,
+) -> Status {
+    let metadata = match request_metadata(host, port, topic, resolver) {
+        Ok(metadata) => metadata,
+        Err(err) => {
+            debug!(
+                "prober poll failed for kafka target: {}:{} (error: {})",
+                host, port, err
+            );
+
+            return Status::Dead;
+        }
+    };
+
+    debug!(
+        "prober poll got metadata for kafka target: {}:{} with brokers: {} and controller: {}",
+        host, port, metadata.brokers, metadata.controller_id
+    );
+
+    // Check topic? (if configured, a missing topic is dead, while a topic that is \
+    //   temporarily unavailable or has partitions without a leader is sick)
+    if topic.is_some() == true {
+        match metadata.topics.first() {
+            Some(topic_metadata) if topic_metadata.error_code == ERROR_NONE => {
+                let has_unavailable_partition =
+                    topic_metadata
+                        .partitions
+                        .iter()
+                        .any(|(partition_error_code, leader_id)| {
+                            *partition_error_code != ERROR_NONE || *leader_id == NODE_NONE
+                        });
+
+                if has_unavailable_partition == true {
+                    return Status::Sick;
+                }
+            }
+            Some(topic_metadata)
+                if topic_metadata.error_code != ERROR_UNKNOWN_TOPIC_OR_PARTITION =>
+            {
+                debug!(
+                    "prober poll got topic error for kafka target: {}:{} (error code: {})",
+                    host, port, topic_metadata.error_code
+                );
+
+                return Status::Sick;
+            }
+            _ => return Status::Dead,
+        }
+    }
+
+    // Check cluster health (no active controller, or fewer brokers than expected)
+    if metadata.controller_id == NODE_NONE {
+        return Status::Sick;
+    }
+
+    if let Some(brokers_minimum) = brokers_minimum {
+        if metadata.brokers < brokers_minimum as usize {
+            return Status::Sick;
+        }
+    }
+
+    Status::Healthy
+}
+
+fn request_metadata(
+    host: &str,
+    port: u16,
+    topic: &Option<String>,
+    resolver: Resolver,
+) -> Result<Metadata, String> {
+    let address = resolver
+        .lookup(host, port)
+        .map_err(|err| err.to_string())?
+        .next()
+        .ok_or("could not resolve address")?;
+
+    debug!("prober poll will fire for kafka target: {}", address);
+
+    let timeout = Duration::from_secs(APP_CONF.metrics.poll_delay_dead);
+    let mut stream =
+        TcpStream::connect_timeout(&address, timeout).map_err(|err| err.to_string())?;
+
+    stream
+        .set_read_timeout(Some(timeout))
+        .and_then(|_| stream.set_write_timeout(Some(timeout)))
+        .map_err(|err| err.to_string())?;
+
+    // Build request (an empty topic list returns cluster metadata only)
+    let mut request = Vec::new();
+
+    request.extend_from_slice(&API_KEY_METADATA.to_be_bytes());
+    request.extend_from_slice(&API_VERSION_METADATA.to_be_bytes());
+    request.extend_from_slice(&CORRELATION_ID.to_be_bytes());
+
+    push_string(&mut request, CLIENT_ID);
+
+    match topic {
+        Some(topic) => {
+            request.extend_from_slice(&1i32.to_be_bytes());
+
+            push_string(&mut request, topic);
+        }
+        None => request.extend_from_slice(&0i32.to_be_bytes()),
+    }
+
+    // Do not create topic if it does not exist (we want to know that it is missing)
+    request.push(0);
+
+    stream
+        .write_all(&(request.len() as i32).to_be_bytes())
+        .and_then(|_| stream.write_all(&request))
+        .map_err(|err| err.to_string())?;
+
+    // Read response
+    let mut size = [0; 4];
+
+    stream
+        .read_exact(&mut size)
+        .map_err(|err| err.to_string())?;
+
+    let size = i32::from_be_bytes(size) as usize;
+
+    if size > RESPONSE_MAXIMUM_BYTES {
+        return Err("response is too large".to_string());
+    }
+
+    let mut response = vec![0; size];
+
+    stream
+        .read_exact(&mut response)
+        .map_err(|err| err.to_string())?;
+
+    parse_metadata(&response).ok_or("invalid metadata response".to_string())
+}
+
+fn parse_metadata(response: &[u8]) -> Option<Metadata> {
+    let mut cursor = Cursor { buffer: response };
+
+    if cursor.i32()? != CORRELATION_ID {
+        return None;
+    }
+
+    // Skip throttle time
+    cursor.skip(4)?;
+
+    // Count brokers (node identifier, host, port and rack)
+    let brokers = cursor.i32()?.max(0) as usize;
+
+    for _ in 0..brokers {
+        cursor.skip(4)?;
+        cursor.skip_string()?;
+        cursor.skip(4)?;
+        cursor.skip_string()?;
+    }
+
+    // Skip cluster identifier
+    cursor.skip_string()?;
+
+    let controller_id = cursor.i32()?;
+
+    // Acquire topics (error code, name, internal flag and partitions)
+    let mut topics = Vec::new();
+
+    for _ in 0..cursor.i32()?.max(0) {
+        let error_code = cursor.i16()?;
+
+        cursor.skip_string()?;
+        cursor.skip(1)?;
+
+        let mut partitions = Vec::new();
+
+        // Acquire partitions (error code, index, leader, replicas and in-sync replicas)
+        for _ in 0..cursor.i32()?.max(0) {
+            let partition_error_code = cursor.i16()?;
+
+            cursor.skip(4)?;
+
+            let leader_id = cursor.i32()?;
+
+            for _ in 0..2 {
+                let nodes = cursor.i32()?.max(0) as usize;
+
+                cursor.skip(nodes * 4)?;
+            }
+
+            partitions.push((partition_error_code, leader_id));
+        }
+
+        topics.push(MetadataTopic {
+            error_code: error_code,
+            partitions: partitions,
+        });
+    }
+
+    Some(Metadata {
+        brokers: brokers,
+        controller_id: controller_id,
+        topics: topics,
+    })
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, size: usize) -> Option<&'a [u8]> {
+        let (value, rest) = (self.buffer.get(..size)?, self.buffer.get(size..)?);
+
+        self.buffer = rest;
+
+        Some(value)
+    }
+
+    fn skip(&mut self, size: usize) -> Option<()> {
+        self.take(size).map(|_| ())
+    }
+
+    fn i16(&mut self) -> Option<i16> {
+        self.take(2)
+            .map(|value| i16::from_be_bytes([value[0], value[1]]))
+    }
+
+    fn i32(&mut self) -> Option<i32> {
+        self.take(4)
+            .map(|value| i32::from_be_bytes([value[0], value[1], value[2], value[3]]))
+    }
+
+    fn skip_string(&mut self) -> Option<()> {
+        // Notice: nullable strings have a negative size
+        let size = self.i16()?;
+
+        self.skip(size.max(0) as usize)
+    }
+}
+
+fn push_string(buffer: &mut Vec<u8>, value: &str) {
+    buffer.extend_from_slice(&(value.len() as i16).to_be_bytes());
+    buffer.extend_from_slice(value.as_bytes());
+}
@@ -0,0 +1,235 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use native_tls::TlsConnector;
+
+use super::dns::Resolver;
+use super::status::Status;
+use crate::APP_CONF;
+
+const RESPONSE_MAXIMUM_BYTES: usize = 65536;
+const PROTOCOL_VERSION: u8 = 3;
+
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_INTEGER: u8 = 0x02;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_ENUMERATED: u8 = 0x0a;
+const TAG_BIND_REQUEST: u8 = 0x60;
+const TAG_BIND_RESPONSE: u8 = 0x61;
+const TAG_UNBIND_REQUEST: u8 = 0x42;
+const TAG_AUTHENTICATION_SIMPLE: u8 = 0x80;
+
+const RESULT_SUCCESS: u8 = 0;
+const RESULT_BUSY: u8 = 51;
+const RESULT_UNAVAILABLE: u8 = 52;
+
+enum SessionError {
+    Failed(String),
+    Replied(u8),
+}
+
+pub fn check(
+    host: &str,
+    port: u16,
+    tls: bool,
+    bind_dn: &Option<String>,
+    password: &Option<String>,
+    resolver: Resolver,
+) -> Status {
+    match proceed_check(host, port, tls, bind_dn, password, resolver) {
+        Ok(_) => Status::Healthy,
+        Err(SessionError::Replied(code)) => {
+            debug!(
+                "prober poll got bind error for ldap target: {}:{} (result code: {})",
+                host, port, code
+            );
+
+            // Notice: the directory is up there, but refuses to serve for now
+            if code == RESULT_BUSY || code == RESULT_UNAVAILABLE {
+                Status::Sick
+            } else {
+                Status::Dead
+            }
+        }
+        Err(SessionError::Failed(err)) => {
+            debug!(
+                "prober poll failed for ldap target: {}:{} (error: {})",
+                host, port, err
+            );
+
+            Status::Dead
+        }
+    }
+}
+
+fn proceed_check(
+    host: &str,
+    port: u16,
+    tls: bool,
+    bind_dn: &Option<String>,
+    password: &Option<String>,
+    resolver: Resolver,
+) -> Result<(), SessionError> {
+    let address =
+        resolver
+            .lookup(host, port)
+            .map_err(failed)?
+            .next()
+            .ok_or(SessionError::Failed(
+                "could not resolve address".to_string(),
+            ))?;
+
+    debug!("prober poll will fire for ldap target: {}", address);
+
+    let timeout = Duration::from_secs(APP_CONF.metrics.poll_delay_dead);
+    let stream = TcpStream::connect_timeout(&address, timeout).map_err(failed)?;
+
+    stream
+        .set_read_timeout(Some(timeout))
+        .and_then(|_| stream.set_write_timeout(Some(timeout)))
+        .map_err(failed)?;
+
+    // Notice: binding without a DN nor a password is an anonymous bind
+    let (bind_dn, password) = (
+        bind_dn.as_deref().unwrap_or_default(),
+        password.as_deref().unwrap_or_default(),
+    );
+
+    if tls == true {
+        let connector = TlsConnector::new().map_err(failed)?;
+        let tls_stream = connector.connect(host, stream).map_err(failed)?;
+
+        proceed_session(tls_stream, bind_dn, password)
+    } else {
+        proceed_session(stream, bind_dn, password)
+    }
+}
+
+fn proceed_session<S: Read + Write>(
+    mut stream: S,
+    bind_dn: &str,
+    password: &str,
+) -> Result<(), SessionError> {
+    // Send simple bind request
+    let bind_request = encode(
+        TAG_BIND_REQUEST,
+        &[
+            encode(TAG_INTEGER, &[PROTOCOL_VERSION]),
+            encode(TAG_OCTET_STRING, bind_dn.as_bytes()),
+            encode(TAG_AUTHENTICATION_SIMPLE, password.as_bytes()),
+        ]
+        .concat(),
+    );
+
+    stream
+        .write_all(&make_message(1, &bind_request))
+        .map_err(failed)?;
+
+    // Read bind response
+    let response = read_element(&mut stream, TAG_SEQUENCE)?;
+    let code = read_result_code(&response)
+        .ok_or(SessionError::Failed("invalid bind response".to_string()))?;
+
+    if code != RESULT_SUCCESS {
+        return Err(SessionError::Replied(code));
+    }
+
+    // Close session (the server does not reply to this)
+    let _ = stream.write_all(&make_message(2, &encode(TAG_UNBIND_REQUEST, &[])));
+
+    Ok(())
+}
+
+fn make_message(message_id: u8, operation: &[u8]) -> Vec<u8> {
+    encode(
+        TAG_SEQUENCE,
+        &[&encode(TAG_INTEGER, &[message_id])[..], operation].concat(),
+    )
+}
+
+fn encode(tag: u8, value: &[u8]) -> Vec<u8> {
+    let mut element = vec![tag];
+
+    // Encode length (in short form if possible, otherwise in long form)
+    if value.len() < 0x80 {
+        element.push(value.len() as u8);
+    } else {
+        let length = (value.len() as u32).to_be_bytes();
+        let length = &length[length.iter().take_while(|byte| **byte == 0).count()..];
+
+        element.push(0x80 | length.len() as u8);
+        element.extend_from_slice(length);
+    }
+
+    element.extend_from_slice(value);
+
+    element
+}
+
+fn read_element<S: Read>(stream: &mut S, expected_tag: u8) -> Result<Vec<u8>, SessionError> {
+    let mut head = [0; 2];
+
+    stream.read_exact(&mut head).map_err(failed)?;
+
+    if head[0] != expected_tag {
+        return Err(SessionError::Failed(format!(
+            "unexpected element tag: {}",
+            head[0]
+        )));
+    }
+
+    // Acquire length (which might be in long form)
+    let length = if head[1] & 0x80 == 0 {
+        head[1] as usize
+    } else {
+        let mut length = vec![0; (head[1] & 0x7f) as usize];
+
+        if length.len() > 4 {
+            return Err(SessionError::Failed("invalid element length".to_string()));
+        }
+
+        stream.read_exact(&mut length).map_err(failed)?;
+
+        length
+            .iter()
+            .fold(0, |value, byte| (value << 8) | *byte as usize)
+    };
+
+    if length > RESPONSE_MAXIMUM_BYTES {
+        return Err(SessionError::Failed("response is too large".to_string()));
+    }
+
+    let mut value = vec![0; length];
+
+    stream.read_exact(&mut value).map_err(failed)?;
+
+    Ok(value)
+}
+
+fn read_result_code(message: &[u8]) -> Option<u8> {
+    let mut message = message;
+
+    // Skip message identifier
+    read_element(&mut message, TAG_INTEGER).ok()?;
+
+    // Acquire result code, which comes first in the bind response
+    let mut response: &[u8] = &read_element(&mut message, TAG_BIND_RESPONSE).ok()?;
+    let code = read_element(&mut response, TAG_ENUMERATED).ok()?;
+
+    // Notice: result codes all fit in a single byte
+    match code.as_slice() {
+        [code] => Some(*code),
+        _ => None,
+    }
+}
+
+fn failed<E: ToString>(err: E) -> SessionError {
+    SessionError::Failed(err.to_string())
+}
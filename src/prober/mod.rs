@@ -4,10 +4,33 @@
 // Copyright: 2018, Valerian Saliou <valerian@valeriansaliou.name>
 // License: Mozilla Public License v2.0 (MPL v2.0)
 
-mod replica;
+mod amqp;
+mod diagnostic;
+mod elastic;
+mod family;
+mod grpc;
+mod kafka;
+mod ldap;
+mod mysql;
+mod ntp;
+mod postgres;
+mod promql;
+mod redis;
+mod smtp;
+mod snmp;
+mod ssh;
+mod transaction;
+mod websocket;
 
+pub mod acknowledge;
+pub mod disable;
+pub mod dns;
+pub mod events;
 pub mod manager;
 pub mod mode;
+pub mod replica;
 pub mod report;
+pub mod severity;
 pub mod states;
 pub mod status;
+pub mod tls;
@@ -17,4 +17,16 @@ pub enum Mode {
 
     #[serde(rename = "local")]
     Local,
+
+    #[serde(rename = "heartbeat")]
+    Heartbeat,
+
+    #[serde(rename = "transaction")]
+    Transaction,
+}
+
+impl Default for Mode {
+    fn default() -> Self {
+        Mode::Poll
+    }
 }
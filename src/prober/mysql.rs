@@ -0,0 +1,377 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::io::{BufReader, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use openssl::rsa::{Padding, Rsa};
+use openssl::sha::{sha1, sha256};
+
+use super::dns::Resolver;
+use super::status::Status;
+use crate::APP_CONF;
+
+const PROTOCOL_VERSION: u8 = 10;
+const PACKET_MAXIMUM_BYTES: u32 = 16777215;
+const CHARSET_UTF8MB4: u8 = 45;
+const PROBE_QUERY: &'static str = "SELECT 1";
+
+const CAPABILITY_LONG_PASSWORD: u32 = 0x00000001;
+const CAPABILITY_CONNECT_WITH_DB: u32 = 0x00000008;
+const CAPABILITY_PROTOCOL_41: u32 = 0x00000200;
+const CAPABILITY_SECURE_CONNECTION: u32 = 0x00008000;
+const CAPABILITY_PLUGIN_AUTH: u32 = 0x00080000;
+
+const PACKET_OK: u8 = 0x00;
+const PACKET_AUTH_MORE_DATA: u8 = 0x01;
+const PACKET_EOF: u8 = 0xfe;
+const PACKET_ERR: u8 = 0xff;
+
+const COMMAND_QUIT: u8 = 0x01;
+const COMMAND_QUERY: u8 = 0x03;
+
+const PLUGIN_NATIVE_PASSWORD: &'static str = "mysql_native_password";
+const PLUGIN_CACHING_SHA2_PASSWORD: &'static str = "caching_sha2_password";
+
+const CACHING_SHA2_REQUEST_PUBLIC_KEY: u8 = 0x02;
+const CACHING_SHA2_FAST_AUTH_SUCCESS: u8 = 0x03;
+const CACHING_SHA2_FULL_AUTH_REQUIRED: u8 = 0x04;
+
+struct Connection<S: Read + Write> {
+    reader: BufReader<S>,
+    sequence: u8,
+}
+
+pub fn check(
+    host: &str,
+    port: u16,
+    user: &str,
+    database: &Option<String>,
+    password: &Option<String>,
+    resolver: Resolver,
+) -> Status {
+    match proceed_check(host, port, user, database, password, resolver) {
+        Ok(_) => Status::Healthy,
+        Err(err) => {
+            debug!(
+                "prober poll failed for mysql target: {}:{} (error: {})",
+                host, port, err
+            );
+
+            Status::Dead
+        }
+    }
+}
+
+fn proceed_check(
+    host: &str,
+    port: u16,
+    user: &str,
+    database: &Option<String>,
+    password: &Option<String>,
+    resolver: Resolver,
+) -> Result<(), String> {
+    let address = resolver
+        .lookup(host, port)
+        .map_err(|err| err.to_string())?
+        .next()
+        .ok_or("could not resolve address")?;
+
+    debug!("prober poll will fire for mysql target: {}", address);
+
+    let timeout = Duration::from_secs(APP_CONF.metrics.poll_delay_dead);
+    let stream = TcpStream::connect_timeout(&address, timeout).map_err(|err| err.to_string())?;
+
+    stream
+        .set_read_timeout(Some(timeout))
+        .and_then(|_| stream.set_write_timeout(Some(timeout)))
+        .map_err(|err| err.to_string())?;
+
+    let mut connection = Connection {
+        reader: BufReader::new(stream),
+        sequence: 0,
+    };
+
+    let password = password.as_deref().unwrap_or_default();
+
+    // Acquire server handshake
+    let (mut scramble, plugin) = read_handshake(&connection.read_packet()?)?;
+
+    // Send handshake response
+    let mut capabilities = CAPABILITY_LONG_PASSWORD
+        | CAPABILITY_PROTOCOL_41
+        | CAPABILITY_SECURE_CONNECTION
+        | CAPABILITY_PLUGIN_AUTH;
+
+    if database.is_some() == true {
+        capabilities |= CAPABILITY_CONNECT_WITH_DB;
+    }
+
+    let mut plugin = plugin.unwrap_or(PLUGIN_NATIVE_PASSWORD.to_string());
+    let auth_response = make_auth_response(&plugin, password, &scramble)?;
+
+    let mut response = Vec::new();
+
+    response.extend_from_slice(&capabilities.to_le_bytes());
+    response.extend_from_slice(&PACKET_MAXIMUM_BYTES.to_le_bytes());
+    response.push(CHARSET_UTF8MB4);
+    response.extend_from_slice(&[0; 23]);
+
+    push_string(&mut response, user.as_bytes());
+
+    response.push(auth_response.len() as u8);
+    response.extend_from_slice(&auth_response);
+
+    if let Some(database) = database {
+        push_string(&mut response, database.as_bytes());
+    }
+
+    push_string(&mut response, plugin.as_bytes());
+
+    connection.write_packet(&response)?;
+
+    // Complete authentication (the server might switch plugin, or request more data)
+    loop {
+        let packet = connection.read_packet()?;
+
+        match packet.first() {
+            Some(&PACKET_OK) => break,
+            Some(&PACKET_ERR) => return Err(format!("server error: {}", read_error(&packet))),
+            Some(&PACKET_EOF) => {
+                // Switch authentication plugin (with a new scramble)
+                let mut parts = packet[1..].splitn(2, |byte| *byte == 0);
+
+                plugin = String::from_utf8_lossy(parts.next().unwrap_or_default()).to_string();
+                scramble = trim_scramble(parts.next().unwrap_or_default());
+
+                connection.write_packet(&make_auth_response(&plugin, password, &scramble)?)?;
+            }
+            Some(&PACKET_AUTH_MORE_DATA) if plugin == PLUGIN_CACHING_SHA2_PASSWORD => {
+                match packet.get(1) {
+                    Some(&CACHING_SHA2_FAST_AUTH_SUCCESS) => {}
+                    Some(&CACHING_SHA2_FULL_AUTH_REQUIRED) => {
+                        // Notice: as the connection is not encrypted, the password must be sent \
+                        //   encrypted with the server public key.
+                        connection.write_packet(&[CACHING_SHA2_REQUEST_PUBLIC_KEY])?;
+
+                        let public_key = connection.read_packet()?;
+
+                        if public_key.first() != Some(&PACKET_AUTH_MORE_DATA) {
+                            return Err("could not acquire server public key".to_string());
+                        }
+
+                        connection.write_packet(&encrypt_password(
+                            password,
+                            &scramble,
+                            &public_key[1..],
+                        )?)?;
+                    }
+                    _ => return Err("unexpected authentication data".to_string()),
+                }
+            }
+            _ => return Err("unexpected packet during authentication".to_string()),
+        }
+    }
+
+    // Run probe query (any error fails the probe)
+    connection.sequence = 0;
+
+    let mut query = vec![COMMAND_QUERY];
+
+    query.extend_from_slice(PROBE_QUERY.as_bytes());
+
+    connection.write_packet(&query)?;
+
+    // Read result set, up to the end of rows (column definitions and rows are separated by \
+    //   EOF packets)
+    let mut eof_count = 0;
+
+    while eof_count < 2 {
+        let packet = connection.read_packet()?;
+
+        match packet.first() {
+            Some(&PACKET_ERR) => return Err(format!("server error: {}", read_error(&packet))),
+            Some(&PACKET_EOF) if packet.len() < 9 => eof_count += 1,
+            Some(&PACKET_OK) if eof_count == 0 && packet.len() < 9 => break,
+            _ => {}
+        }
+    }
+
+    // Close session (the server does not reply to this)
+    connection.sequence = 0;
+
+    let _ = connection.write_packet(&[COMMAND_QUIT]);
+
+    Ok(())
+}
+
+impl<S: Read + Write> Connection<S> {
+    fn read_packet(&mut self) -> Result<Vec<u8>, String> {
+        let mut head = [0; 4];
+
+        self.reader
+            .read_exact(&mut head)
+            .map_err(|err| err.to_string())?;
+
+        let size = u32::from_le_bytes([head[0], head[1], head[2], 0]) as usize;
+
+        self.sequence = head[3].wrapping_add(1);
+
+        let mut payload = vec![0; size];
+
+        self.reader
+            .read_exact(&mut payload)
+            .map_err(|err| err.to_string())?;
+
+        Ok(payload)
+    }
+
+    fn write_packet(&mut self, payload: &[u8]) -> Result<(), String> {
+        let mut packet = Vec::with_capacity(payload.len() + 4);
+
+        packet.extend_from_slice(&(payload.len() as u32).to_le_bytes()[..3]);
+        packet.push(self.sequence);
+        packet.extend_from_slice(payload);
+
+        self.sequence = self.sequence.wrapping_add(1);
+
+        self.reader
+            .get_mut()
+            .write_all(&packet)
+            .map_err(|err| err.to_string())
+    }
+}
+
+fn read_handshake(packet: &[u8]) -> Result<(Vec<u8>, Option<String>), String> {
+    match packet.first() {
+        Some(&PROTOCOL_VERSION) => {}
+        Some(&PACKET_ERR) => return Err(format!("server error: {}", read_error(packet))),
+        _ => return Err("unsupported protocol version".to_string()),
+    }
+
+    // Skip server version (null-terminated), then connection identifier
+    let mut offset = packet[1..]
+        .iter()
+        .position(|byte| *byte == 0)
+        .ok_or("truncated handshake")?
+        + 2
+        + 4;
+
+    let scramble_start = packet
+        .get(offset..offset + 8)
+        .ok_or("truncated handshake")?
+        .to_vec();
+
+    // Skip filler, lower capabilities, charset, status, upper capabilities, scramble length \
+    //   and reserved bytes
+    offset += 8 + 1 + 2 + 1 + 2 + 2;
+
+    let scramble_length = *packet.get(offset).ok_or("truncated handshake")? as usize;
+
+    offset += 1 + 10;
+
+    // Acquire the rest of the scramble (at least 13 bytes, including a trailing null byte)
+    let scramble_rest_length = scramble_length.saturating_sub(8).max(13);
+
+    let scramble_rest = packet
+        .get(offset..offset + scramble_rest_length)
+        .ok_or("truncated handshake")?;
+
+    offset += scramble_rest_length;
+
+    let plugin = packet.get(offset..).and_then(|plugin| {
+        plugin
+            .split(|byte| *byte == 0)
+            .next()
+            .filter(|plugin| plugin.is_empty() == false)
+            .map(|plugin| String::from_utf8_lossy(plugin).to_string())
+    });
+
+    Ok((
+        trim_scramble(&[scramble_start.as_slice(), scramble_rest].concat()),
+        plugin,
+    ))
+}
+
+fn make_auth_response(plugin: &str, password: &str, scramble: &[u8]) -> Result<Vec<u8>, String> {
+    if password.is_empty() == true {
+        return Ok(Vec::new());
+    }
+
+    match plugin {
+        PLUGIN_NATIVE_PASSWORD => {
+            // SHA1(password) XOR SHA1(scramble + SHA1(SHA1(password)))
+            let password_hash = sha1(password.as_bytes());
+            let mixed_hash = sha1(&[scramble, &sha1(&password_hash)[..]].concat());
+
+            Ok(xor(&password_hash, &mixed_hash))
+        }
+        PLUGIN_CACHING_SHA2_PASSWORD => {
+            // SHA256(password) XOR SHA256(SHA256(SHA256(password)) + scramble)
+            let password_hash = sha256(password.as_bytes());
+            let mixed_hash = sha256(&[&sha256(&password_hash)[..], scramble].concat());
+
+            Ok(xor(&password_hash, &mixed_hash))
+        }
+        _ => Err(format!("unsupported authentication plugin: {}", plugin)),
+    }
+}
+
+fn encrypt_password(password: &str, scramble: &[u8], public_key: &[u8]) -> Result<Vec<u8>, String> {
+    if scramble.is_empty() == true {
+        return Err("empty scramble".to_string());
+    }
+
+    let key = Rsa::public_key_from_pem(public_key).map_err(|err| err.to_string())?;
+
+    // Obfuscate null-terminated password with the scramble, then encrypt it
+    let mut plain = password.as_bytes().to_vec();
+
+    plain.push(0);
+
+    let obfuscated: Vec<u8> = plain
+        .iter()
+        .enumerate()
+        .map(|(index, byte)| byte ^ scramble[index % scramble.len()])
+        .collect();
+
+    let mut encrypted = vec![0; key.size() as usize];
+
+    let size = key
+        .public_encrypt(&obfuscated, &mut encrypted, Padding::PKCS1_OAEP)
+        .map_err(|err| err.to_string())?;
+
+    encrypted.truncate(size);
+
+    Ok(encrypted)
+}
+
+fn read_error(packet: &[u8]) -> String {
+    // Skip marker, error code and SQL state (which starts with a '#')
+    let offset = if packet.get(3) == Some(&b'#') { 9 } else { 3 };
+
+    packet
+        .get(offset..)
+        .map(|message| String::from_utf8_lossy(message).to_string())
+        .unwrap_or_default()
+}
+
+fn trim_scramble(scramble: &[u8]) -> Vec<u8> {
+    scramble.strip_suffix(&[0]).unwrap_or(scramble).to_vec()
+}
+
+fn push_string(buffer: &mut Vec<u8>, value: &[u8]) {
+    buffer.extend_from_slice(value);
+    buffer.push(0);
+}
+
+fn xor(left: &[u8], right: &[u8]) -> Vec<u8> {
+    left.iter()
+        .zip(right.iter())
+        .map(|(left, right)| left ^ right)
+        .collect()
+}
@@ -0,0 +1,166 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::net::{SocketAddr, UdpSocket};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::dns::Resolver;
+use super::status::Status;
+use crate::APP_CONF;
+
+const PACKET_SIZE: usize = 48;
+const VERSION: u8 = 4;
+const MODE_CLIENT: u8 = 3;
+const MODE_SERVER: u8 = 4;
+const LEAP_UNSYNCHRONIZED: u8 = 3;
+const STRATUM_KISS_OF_DEATH: u8 = 0;
+const STRATUM_MAXIMUM: u8 = 15;
+
+// Notice: NTP timestamps count seconds since 1900, while UNIX timestamps count since 1970
+const EPOCH_DELTA_SECONDS: f64 = 2208988800.0;
+
+struct Reply {
+    leap: u8,
+    stratum: u8,
+    offset: f64,
+}
+
+pub fn check(
+    host: &str,
+    port: u16,
+    stratum_sick_above: Option<u8>,
+    offset_sick_above: Option<u64>,
+    resolver: Resolver,
+) -> Status {
+    let reply = match request_time(host, port, resolver) {
+        Ok(reply) => reply,
+        Err(err) => {
+            debug!(
+                "prober poll failed for ntp target: {}:{} (error: {})",
+                host, port, err
+            );
+
+            return Status::Dead;
+        }
+    };
+
+    let offset_milliseconds = (reply.offset.abs() * 1000.0) as u64;
+
+    debug!(
+        "prober poll got stratum: {} and offset: {}ms for ntp target: {}:{}",
+        reply.stratum, offset_milliseconds, host, port
+    );
+
+    // Server refuses to serve time (ie. kiss-o'-death packet), or does not have a usable \
+    //   time source
+    if reply.stratum == STRATUM_KISS_OF_DEATH || reply.stratum > STRATUM_MAXIMUM {
+        return Status::Dead;
+    }
+
+    // Server answers, but has drifted or is not synchronized to a good enough source
+    if reply.leap == LEAP_UNSYNCHRONIZED
+        || stratum_sick_above
+            .map(|stratum_sick_above| reply.stratum > stratum_sick_above)
+            .unwrap_or(false)
+            == true
+        || offset_sick_above
+            .map(|offset_sick_above| offset_milliseconds > offset_sick_above)
+            .unwrap_or(false)
+            == true
+    {
+        return Status::Sick;
+    }
+
+    Status::Healthy
+}
+
+fn request_time(host: &str, port: u16, resolver: Resolver) -> Result<Reply, String> {
+    let address = resolver
+        .lookup(host, port)
+        .map_err(|err| err.to_string())?
+        .next()
+        .ok_or("could not resolve address")?;
+
+    debug!("prober poll will fire for ntp target: {}", address);
+
+    let socket = match address {
+        SocketAddr::V4(_) => UdpSocket::bind("0.0.0.0:0"),
+        SocketAddr::V6(_) => UdpSocket::bind("[::]:0"),
+    }
+    .map_err(|err| err.to_string())?;
+
+    let timeout = Duration::from_secs(APP_CONF.metrics.poll_delay_dead);
+
+    socket
+        .set_read_timeout(Some(timeout))
+        .and_then(|_| socket.set_write_timeout(Some(timeout)))
+        .and_then(|_| socket.connect(address))
+        .map_err(|err| err.to_string())?;
+
+    // Send request (our transmit timestamp gets echoed as the originate timestamp)
+    let mut request = [0; PACKET_SIZE];
+
+    request[0] = (VERSION << 3) | MODE_CLIENT;
+
+    let transmit_time = now();
+
+    request[40..48].copy_from_slice(&to_timestamp(transmit_time));
+
+    socket.send(&request).map_err(|err| err.to_string())?;
+
+    // Receive reply
+    let mut reply = [0; PACKET_SIZE];
+
+    let size = socket.recv(&mut reply).map_err(|err| err.to_string())?;
+    let receive_time = now();
+
+    if size < PACKET_SIZE {
+        return Err("reply is too short".to_string());
+    }
+
+    if reply[0] & 0x07 != MODE_SERVER {
+        return Err("reply is not from a server".to_string());
+    }
+
+    if reply[24..32] != request[40..48] {
+        return Err("reply does not match request".to_string());
+    }
+
+    // Compute clock offset (from the server receive and transmit timestamps)
+    let server_receive_time = from_timestamp(&reply[32..40]);
+    let server_transmit_time = from_timestamp(&reply[40..48]);
+
+    Ok(Reply {
+        leap: reply[0] >> 6,
+        stratum: reply[1],
+        offset: ((server_receive_time - transmit_time) + (server_transmit_time - receive_time))
+            / 2.0,
+    })
+}
+
+fn now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs_f64())
+        .unwrap_or(0.0)
+        + EPOCH_DELTA_SECONDS
+}
+
+fn to_timestamp(time: f64) -> [u8; 8] {
+    let mut timestamp = [0; 8];
+
+    timestamp[0..4].copy_from_slice(&(time.trunc() as u32).to_be_bytes());
+    timestamp[4..8].copy_from_slice(&((time.fract() * 4294967296.0) as u32).to_be_bytes());
+
+    timestamp
+}
+
+fn from_timestamp(timestamp: &[u8]) -> f64 {
+    let seconds = u32::from_be_bytes([timestamp[0], timestamp[1], timestamp[2], timestamp[3]]);
+    let fraction = u32::from_be_bytes([timestamp[4], timestamp[5], timestamp[6], timestamp[7]]);
+
+    seconds as f64 + fraction as f64 / 4294967296.0
+}
@@ -0,0 +1,385 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::io::{BufReader, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use openssl::hash::{hash, MessageDigest};
+use openssl::pkcs5::pbkdf2_hmac;
+use openssl::rand::rand_bytes;
+use sha2::{Digest, Sha256};
+
+use super::dns::Resolver;
+use super::status::Status;
+use crate::APP_CONF;
+
+const PROTOCOL_VERSION: i32 = 196608;
+const MESSAGE_MAXIMUM_BYTES: usize = 65536;
+const PROBE_QUERY: &'static str = "SELECT 1";
+const SCRAM_MECHANISM: &'static str = "SCRAM-SHA-256";
+
+const AUTHENTICATION_OK: i32 = 0;
+const AUTHENTICATION_CLEARTEXT: i32 = 3;
+const AUTHENTICATION_MD5: i32 = 5;
+const AUTHENTICATION_SASL: i32 = 10;
+const AUTHENTICATION_SASL_CONTINUE: i32 = 11;
+const AUTHENTICATION_SASL_FINAL: i32 = 12;
+
+pub fn check(
+    host: &str,
+    port: u16,
+    user: &str,
+    database: &Option<String>,
+    password: &Option<String>,
+    resolver: Resolver,
+) -> Status {
+    match proceed_check(host, port, user, database, password, resolver) {
+        Ok(_) => Status::Healthy,
+        Err(err) => {
+            debug!(
+                "prober poll failed for postgres target: {}:{} (error: {})",
+                host, port, err
+            );
+
+            Status::Dead
+        }
+    }
+}
+
+fn proceed_check(
+    host: &str,
+    port: u16,
+    user: &str,
+    database: &Option<String>,
+    password: &Option<String>,
+    resolver: Resolver,
+) -> Result<(), String> {
+    let address = resolver
+        .lookup(host, port)
+        .map_err(|err| err.to_string())?
+        .next()
+        .ok_or("could not resolve address")?;
+
+    debug!("prober poll will fire for postgres target: {}", address);
+
+    let timeout = Duration::from_secs(APP_CONF.metrics.poll_delay_dead);
+    let stream = TcpStream::connect_timeout(&address, timeout).map_err(|err| err.to_string())?;
+
+    stream
+        .set_read_timeout(Some(timeout))
+        .and_then(|_| stream.set_write_timeout(Some(timeout)))
+        .map_err(|err| err.to_string())?;
+
+    let mut reader = BufReader::new(stream);
+
+    // Open session (the database defaults to the user name)
+    let mut startup = Vec::new();
+
+    startup.extend_from_slice(&PROTOCOL_VERSION.to_be_bytes());
+
+    for (key, value) in [
+        ("user", user),
+        ("database", database.as_deref().unwrap_or(user)),
+        ("application_name", "vigil"),
+    ] {
+        push_string(&mut startup, key);
+        push_string(&mut startup, value);
+    }
+
+    startup.push(0);
+
+    write_message(&mut reader, None, &startup)?;
+
+    authenticate(&mut reader, user, password.as_deref().unwrap_or_default())?;
+
+    // Run probe query, once the server is ready (any error fails the probe)
+    wait_ready(&mut reader)?;
+
+    let mut query = Vec::new();
+
+    push_string(&mut query, PROBE_QUERY);
+
+    write_message(&mut reader, Some(b'Q'), &query)?;
+
+    wait_ready(&mut reader)?;
+
+    // Close session (the server does not reply to this)
+    let _ = write_message(&mut reader, Some(b'X'), &[]);
+
+    Ok(())
+}
+
+fn authenticate<S: Read + Write>(
+    reader: &mut BufReader<S>,
+    user: &str,
+    password: &str,
+) -> Result<(), String> {
+    let mut scram: Option<(String, String)> = None;
+    let mut server_signature: Option<Vec<u8>> = None;
+    let mut server_verified = false;
+
+    loop {
+        let body = match read_message(reader)? {
+            (b'R', body) => body,
+            (b'E', body) => return Err(format!("server error: {}", read_error(&body))),
+            (kind, _) => {
+                return Err(format!(
+                    "unexpected message during authentication: {}",
+                    kind as char
+                ))
+            }
+        };
+
+        let code = read_i32(&body, 0)?;
+        let data = &body[4..];
+
+        match code {
+            AUTHENTICATION_OK => {
+                // Notice: if a SCRAM exchange happened, the server must have proven that it knows \
+                //   the password as well.
+                if scram.is_some() == true && server_verified == false {
+                    return Err("missing scram server signature".to_string());
+                }
+
+                return Ok(());
+            }
+            AUTHENTICATION_CLEARTEXT => {
+                let mut response = Vec::new();
+
+                push_string(&mut response, password);
+
+                write_message(reader, Some(b'p'), &response)?;
+            }
+            AUTHENTICATION_MD5 => {
+                let salt = data.get(0..4).ok_or("missing md5 salt")?;
+
+                let inner = md5_hex(&[password.as_bytes(), user.as_bytes()].concat())?;
+                let outer = md5_hex(&[inner.as_bytes(), salt].concat())?;
+
+                let mut response = Vec::new();
+
+                push_string(&mut response, &format!("md5{}", outer));
+
+                write_message(reader, Some(b'p'), &response)?;
+            }
+            AUTHENTICATION_SASL => {
+                let has_mechanism = data
+                    .split(|byte| *byte == 0)
+                    .any(|mechanism| mechanism == SCRAM_MECHANISM.as_bytes());
+
+                if has_mechanism == false {
+                    return Err("no supported sasl mechanism".to_string());
+                }
+
+                // Notice: the user name is sent in the startup message, thus it is left empty here
+                let mut nonce = [0; 18];
+
+                rand_bytes(&mut nonce).map_err(|err| err.to_string())?;
+
+                let client_nonce = base64::encode(nonce);
+                let client_first_bare = format!("n=,r={}", client_nonce);
+                let client_first = format!("n,,{}", client_first_bare);
+
+                let mut response = Vec::new();
+
+                push_string(&mut response, SCRAM_MECHANISM);
+
+                response.extend_from_slice(&(client_first.len() as i32).to_be_bytes());
+                response.extend_from_slice(client_first.as_bytes());
+
+                write_message(reader, Some(b'p'), &response)?;
+
+                scram = Some((client_nonce, client_first_bare));
+            }
+            AUTHENTICATION_SASL_CONTINUE => {
+                let (client_nonce, client_first_bare) =
+                    scram.as_ref().ok_or("unexpected sasl continue")?;
+
+                let (client_final, signature) = make_scram_final(
+                    password,
+                    client_nonce,
+                    client_first_bare,
+                    &String::from_utf8_lossy(data),
+                )?;
+
+                write_message(reader, Some(b'p'), client_final.as_bytes())?;
+
+                server_signature = Some(signature);
+            }
+            AUTHENTICATION_SASL_FINAL => {
+                let expected_signature =
+                    server_signature.as_ref().ok_or("unexpected sasl final")?;
+
+                let signature = scram_attribute(&String::from_utf8_lossy(data), "v")
+                    .and_then(|signature| base64::decode(signature).ok())
+                    .ok_or("invalid scram server final message")?;
+
+                if &signature != expected_signature {
+                    return Err("invalid scram server signature".to_string());
+                }
+
+                server_verified = true;
+            }
+            _ => return Err(format!("unsupported authentication method: {}", code)),
+        }
+    }
+}
+
+fn make_scram_final(
+    password: &str,
+    client_nonce: &str,
+    client_first_bare: &str,
+    server_first: &str,
+) -> Result<(String, Vec<u8>), String> {
+    let (nonce, salt, iterations) = match (
+        scram_attribute(server_first, "r"),
+        scram_attribute(server_first, "s").and_then(|salt| base64::decode(salt).ok()),
+        scram_attribute(server_first, "i").and_then(|iterations| iterations.parse().ok()),
+    ) {
+        (Some(nonce), Some(salt), Some(iterations)) => (nonce, salt, iterations),
+        _ => return Err("invalid scram server first message".to_string()),
+    };
+
+    if nonce.starts_with(client_nonce) == false {
+        return Err("invalid scram server nonce".to_string());
+    }
+
+    let mut salted_password = [0; 32];
+
+    pbkdf2_hmac(
+        password.as_bytes(),
+        &salt,
+        iterations,
+        MessageDigest::sha256(),
+        &mut salted_password,
+    )
+    .map_err(|err| err.to_string())?;
+
+    // Notice: 'biws' is the base64-encoded GS2 header (ie. 'n,,')
+    let client_final_bare = format!("c=biws,r={}", nonce);
+
+    let auth_message = format!(
+        "{},{},{}",
+        client_first_bare, server_first, client_final_bare
+    );
+
+    let client_key = hmac_sha256(&salted_password, b"Client Key");
+    let client_signature = hmac_sha256(&Sha256::digest(&client_key), auth_message.as_bytes());
+
+    let client_proof: Vec<u8> = client_key
+        .iter()
+        .zip(client_signature.iter())
+        .map(|(key, signature)| key ^ signature)
+        .collect();
+
+    let server_key = hmac_sha256(&salted_password, b"Server Key");
+    let server_signature = hmac_sha256(&server_key, auth_message.as_bytes());
+
+    Ok((
+        format!("{},p={}", client_final_bare, base64::encode(client_proof)),
+        server_signature,
+    ))
+}
+
+fn wait_ready<S: Read>(reader: &mut BufReader<S>) -> Result<(), String> {
+    // Skip any informational message (eg. parameter status or query results)
+    loop {
+        match read_message(reader)? {
+            (b'Z', _) => return Ok(()),
+            (b'E', body) => return Err(format!("server error: {}", read_error(&body))),
+            _ => {}
+        }
+    }
+}
+
+fn read_message<S: Read>(reader: &mut BufReader<S>) -> Result<(u8, Vec<u8>), String> {
+    let mut head = [0; 5];
+
+    reader
+        .read_exact(&mut head)
+        .map_err(|err| err.to_string())?;
+
+    // Notice: the length includes itself
+    let size = (read_i32(&head, 1)? as usize)
+        .checked_sub(4)
+        .filter(|size| *size <= MESSAGE_MAXIMUM_BYTES)
+        .ok_or("invalid message length")?;
+
+    let mut body = vec![0; size];
+
+    reader
+        .read_exact(&mut body)
+        .map_err(|err| err.to_string())?;
+
+    Ok((head[0], body))
+}
+
+fn write_message<S: Write>(
+    reader: &mut BufReader<S>,
+    kind: Option<u8>,
+    body: &[u8],
+) -> Result<(), String> {
+    let mut message = Vec::with_capacity(body.len() + 5);
+
+    // Notice: the startup message is the only one without a type
+    if let Some(kind) = kind {
+        message.push(kind);
+    }
+
+    message.extend_from_slice(&((body.len() + 4) as i32).to_be_bytes());
+    message.extend_from_slice(body);
+
+    reader
+        .get_mut()
+        .write_all(&message)
+        .map_err(|err| err.to_string())
+}
+
+fn read_error(body: &[u8]) -> String {
+    // Acquire human-readable message field from error fields (eg. 'Mpassword authentication \
+    //   failed for user')
+    body.split(|byte| *byte == 0)
+        .find(|field| field.first() == Some(&b'M'))
+        .map(|field| String::from_utf8_lossy(&field[1..]).to_string())
+        .unwrap_or_default()
+}
+
+fn read_i32(buffer: &[u8], offset: usize) -> Result<i32, String> {
+    buffer
+        .get(offset..offset + 4)
+        .map(|bytes| i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or("truncated message".to_string())
+}
+
+fn push_string(buffer: &mut Vec<u8>, value: &str) {
+    buffer.extend_from_slice(value.as_bytes());
+    buffer.push(0);
+}
+
+fn scram_attribute<'a>(message: &'a str, name: &str) -> Option<&'a str> {
+    message.split(',').find_map(|attribute| {
+        attribute
+            .split_once('=')
+            .filter(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    })
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("hmac accepts any key size");
+
+    mac.update(message);
+
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn md5_hex(value: &[u8]) -> Result<String, String> {
+    let digest = hash(MessageDigest::md5(), value).map_err(|err| err.to_string())?;
+
+    Ok(digest.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
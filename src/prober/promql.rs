@@ -0,0 +1,177 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+use reqwest::header::HeaderMap;
+use reqwest::redirect::Policy as RedirectPolicy;
+use reqwest::StatusCode;
+use serde_json::Value;
+
+use super::family::AddressFamily;
+use super::status::Status;
+use crate::APP_CONF;
+
+const QUERY_PATH: &'static str = "/api/v1/query";
+
+lazy_static! {
+    static ref PROMQL_CLIENT: Client = make_client(AddressFamily::Any);
+    static ref PROMQL_CLIENT_IPV4: Client = make_client(AddressFamily::IPv4);
+    static ref PROMQL_CLIENT_IPV6: Client = make_client(AddressFamily::IPv6);
+}
+
+#[derive(Deserialize)]
+struct QueryResponse {
+    status: String,
+    data: Option<QueryResponseData>,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct QueryResponseData {
+    #[serde(rename = "resultType")]
+    result_type: String,
+    result: Value,
+}
+
+pub struct Thresholds {
+    pub sick_above: Option<f64>,
+    pub dead_above: Option<f64>,
+    pub sick_below: Option<f64>,
+    pub dead_below: Option<f64>,
+}
+
+pub fn check(
+    url: &str,
+    query: &str,
+    headers: &HeaderMap,
+    thresholds: &Thresholds,
+    family: AddressFamily,
+) -> Status {
+    let values = match evaluate_query(url, query, headers, family) {
+        Ok(values) => values,
+        Err(err) => {
+            debug!(
+                "prober poll failed for promql target: {} with query: '{}' (error: {})",
+                url, query, err
+            );
+
+            return Status::Dead;
+        }
+    };
+
+    debug!(
+        "prober poll got values for promql target: {} with query: '{}': {:?}",
+        url, query, values
+    );
+
+    // Notice: an empty result means that the metric is missing, which should not silently \
+    //   pass as healthy (eg. if the exporter that reports it is down).
+    if values.is_empty() == true {
+        return Status::Dead;
+    }
+
+    // Compare values against thresholds (the worst series wins)
+    let exceeds = |value: &f64, above: Option<f64>, below: Option<f64>| {
+        value.is_nan() == true
+            || above.map(|above| *value > above).unwrap_or(false) == true
+            || below.map(|below| *value < below).unwrap_or(false) == true
+    };
+
+    if values
+        .iter()
+        .any(|value| exceeds(value, thresholds.dead_above, thresholds.dead_below))
+        == true
+    {
+        Status::Dead
+    } else if values
+        .iter()
+        .any(|value| exceeds(value, thresholds.sick_above, thresholds.sick_below))
+        == true
+    {
+        Status::Sick
+    } else {
+        Status::Healthy
+    }
+}
+
+fn evaluate_query(
+    url: &str,
+    query: &str,
+    headers: &HeaderMap,
+    family: AddressFamily,
+) -> Result<Vec<f64>, String> {
+    let url = format!("{}{}", url, QUERY_PATH);
+
+    debug!(
+        "prober poll will fire for promql target: {} with query: '{}'",
+        url, query
+    );
+
+    let response = acquire_client(family)
+        .get(&url)
+        .headers(headers.clone())
+        .query(&[("query", query)])
+        .send()
+        .map_err(|err| err.to_string())?;
+
+    let status_code = response.status();
+
+    // Notice: query errors are returned with an error status code, along with a JSON body
+    let response = response
+        .json::<QueryResponse>()
+        .map_err(|_| format!("invalid response with status: {}", status_code))?;
+
+    if status_code != StatusCode::OK || response.status != "success" {
+        return Err(response
+            .error
+            .unwrap_or_else(|| format!("query failed with status: {}", status_code)));
+    }
+
+    let data = response.data.ok_or("response has no data")?;
+
+    // Acquire values (instant vectors hold one value per series, while scalars hold one value)
+    match data.result_type.as_str() {
+        "vector" => data
+            .result
+            .as_array()
+            .ok_or("invalid vector result")?
+            .iter()
+            .map(|series| parse_value(&series["value"]))
+            .collect::<Option<Vec<f64>>>()
+            .ok_or("invalid vector value".to_string()),
+        "scalar" => parse_value(&data.result)
+            .map(|value| vec![value])
+            .ok_or("invalid scalar value".to_string()),
+        result_type => Err(format!("unsupported result type: {}", result_type)),
+    }
+}
+
+fn acquire_client(family: AddressFamily) -> &'static Client {
+    match family {
+        AddressFamily::Any => &*PROMQL_CLIENT,
+        AddressFamily::IPv4 => &*PROMQL_CLIENT_IPV4,
+        AddressFamily::IPv6 => &*PROMQL_CLIENT_IPV6,
+    }
+}
+
+fn make_client(family: AddressFamily) -> Client {
+    Client::builder()
+        .timeout(Duration::from_secs(APP_CONF.metrics.poll_delay_dead))
+        .gzip(false)
+        .redirect(RedirectPolicy::none())
+        .user_agent(format!("vigil (+{})", APP_CONF.branding.page_url.as_str()))
+        .local_address(family.local_address())
+        .build()
+        .unwrap()
+}
+
+fn parse_value(value: &Value) -> Option<f64> {
+    // Values are formatted as '[<timestamp>, "<value>"]' (the value being a string, as it \
+    //   might be 'NaN' or '+Inf')
+    value.get(1)?.as_str()?.parse::<f64>().ok()
+}
@@ -0,0 +1,170 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use native_tls::TlsConnector;
+
+use super::dns::Resolver;
+use super::status::Status;
+use crate::APP_CONF;
+
+const REPLY_MAXIMUM_BYTES: u64 = 1024;
+const ERROR_LOADING: &'static str = "LOADING";
+
+enum SessionError {
+    Failed(String),
+    Loading,
+}
+
+pub fn check(
+    host: &str,
+    port: u16,
+    tls: bool,
+    user: &Option<String>,
+    password: &Option<String>,
+    resolver: Resolver,
+) -> Status {
+    match proceed_check(host, port, tls, user, password, resolver) {
+        Ok(_) => Status::Healthy,
+        Err(SessionError::Loading) => {
+            debug!(
+                "prober poll got loading reply for redis target: {}:{}",
+                host, port
+            );
+
+            // Notice: the server is up but still loading its dataset into memory, thus it \
+            //   cannot serve cached data yet.
+            Status::Sick
+        }
+        Err(SessionError::Failed(err)) => {
+            debug!(
+                "prober poll failed for redis target: {}:{} (error: {})",
+                host, port, err
+            );
+
+            Status::Dead
+        }
+    }
+}
+
+fn proceed_check(
+    host: &str,
+    port: u16,
+    tls: bool,
+    user: &Option<String>,
+    password: &Option<String>,
+    resolver: Resolver,
+) -> Result<(), SessionError> {
+    let address =
+        resolver
+            .lookup(host, port)
+            .map_err(failed)?
+            .next()
+            .ok_or(SessionError::Failed(
+                "could not resolve address".to_string(),
+            ))?;
+
+    debug!("prober poll will fire for redis target: {}", address);
+
+    let timeout = Duration::from_secs(APP_CONF.metrics.poll_delay_dead);
+    let stream = TcpStream::connect_timeout(&address, timeout).map_err(failed)?;
+
+    stream
+        .set_read_timeout(Some(timeout))
+        .and_then(|_| stream.set_write_timeout(Some(timeout)))
+        .map_err(failed)?;
+
+    if tls == true {
+        let connector = TlsConnector::new().map_err(failed)?;
+        let tls_stream = connector.connect(host, stream).map_err(failed)?;
+
+        proceed_session(BufReader::new(tls_stream), user, password)
+    } else {
+        proceed_session(BufReader::new(stream), user, password)
+    }
+}
+
+fn proceed_session<S: Read + Write>(
+    mut reader: BufReader<S>,
+    user: &Option<String>,
+    password: &Option<String>,
+) -> Result<(), SessionError> {
+    // Authenticate? (if a password is set, with an optional ACL user)
+    if let Some(password) = password {
+        let reply = match user {
+            Some(user) => send_command(&mut reader, &["AUTH", user, password])?,
+            None => send_command(&mut reader, &["AUTH", password])?,
+        };
+
+        if reply != "OK" {
+            return Err(SessionError::Failed(format!(
+                "unexpected auth reply: {}",
+                reply
+            )));
+        }
+    }
+
+    let reply = send_command(&mut reader, &["PING"])?;
+
+    if reply != "PONG" {
+        return Err(SessionError::Failed(format!(
+            "unexpected ping reply: {}",
+            reply
+        )));
+    }
+
+    // Close session (the reply does not matter there)
+    let _ = send_command(&mut reader, &["QUIT"]);
+
+    Ok(())
+}
+
+fn send_command<S: Read + Write>(
+    reader: &mut BufReader<S>,
+    arguments: &[&str],
+) -> Result<String, SessionError> {
+    // Encode command as an array of bulk strings
+    let mut command = format!("*{}\r\n", arguments.len());
+
+    for argument in arguments {
+        command.push_str(&format!("${}\r\n{}\r\n", argument.len(), argument));
+    }
+
+    reader
+        .get_mut()
+        .write_all(command.as_bytes())
+        .map_err(failed)?;
+
+    // Read reply line (only simple string and error replies are expected there)
+    let mut line = String::new();
+
+    reader
+        .by_ref()
+        .take(REPLY_MAXIMUM_BYTES)
+        .read_line(&mut line)
+        .map_err(failed)?;
+
+    let line = line.trim_end();
+
+    if let Some(reply) = line.strip_prefix('+') {
+        Ok(reply.to_string())
+    } else if let Some(error) = line.strip_prefix('-') {
+        if error.starts_with(ERROR_LOADING) == true {
+            Err(SessionError::Loading)
+        } else {
+            Err(SessionError::Failed(format!("server error: {}", error)))
+        }
+    } else {
+        Err(SessionError::Failed(format!("invalid reply: {}", line)))
+    }
+}
+
+fn failed<E: ToString>(err: E) -> SessionError {
+    SessionError::Failed(err.to_string())
+}
@@ -6,12 +6,48 @@
 
 use url::{Host, Url};
 
+const SMTP_PORT_DEFAULT: u16 = 25;
+const POSTGRESQL_PORT_DEFAULT: u16 = 5432;
+const MYSQL_PORT_DEFAULT: u16 = 3306;
+const REDIS_PORT_DEFAULT: u16 = 6379;
+const AMQP_PORT_DEFAULT: u16 = 5672;
+const AMQPS_PORT_DEFAULT: u16 = 5671;
+const KAFKA_PORT_DEFAULT: u16 = 9092;
+const LDAP_PORT_DEFAULT: u16 = 389;
+const LDAPS_PORT_DEFAULT: u16 = 636;
+const SSH_PORT_DEFAULT: u16 = 22;
+const NTP_PORT_DEFAULT: u16 = 123;
+const ELASTIC_PORT_DEFAULT: u16 = 9200;
+const PROMQL_PORT_DEFAULT: u16 = 9090;
+const SNMP_PORT_DEFAULT: u16 = 161;
+
 #[derive(Serialize, Debug, Clone)]
 pub enum ReplicaURL {
     ICMP(String),
     TCP(String, u16),
     HTTP(String),
     HTTPS(String),
+    GRPC(String, u16, String),
+    GRPCS(String, u16, String),
+    WS(String),
+    WSS(String),
+    SMTP(String, u16),
+    PostgreSQL(String, u16, String, Option<String>),
+    MySQL(String, u16, String, Option<String>),
+    Redis(String, u16, Option<String>),
+    RedisTLS(String, u16, Option<String>),
+    AMQP(String, u16, Option<String>, String),
+    AMQPS(String, u16, Option<String>, String),
+    Kafka(String, u16),
+    LDAP(String, u16),
+    LDAPS(String, u16),
+    SSH(String, u16),
+    NTP(String, u16),
+    Elastic(String, u16, Option<String>),
+    ElasticTLS(String, u16, Option<String>),
+    PromQL(String),
+    PromQLS(String),
+    SNMP(String, u16, Option<String>, String),
 }
 
 impl ReplicaURL {
@@ -28,6 +64,210 @@ impl ReplicaURL {
                     }
                     _ => Err(()),
                 },
+                "grpc" | "grpcs" => match (url.host(), url.port(), url.query(), url.fragment()) {
+                    (Some(host), Some(port), None, None) => {
+                        // Acquire service name from path (an empty name checks the whole server)
+                        let service = url.path().trim_start_matches('/');
+
+                        if service.contains('/') == true {
+                            return Err(());
+                        }
+
+                        if url.scheme() == "grpcs" {
+                            Ok(ReplicaURL::GRPCS(
+                                Self::host_string(host),
+                                port,
+                                service.to_string(),
+                            ))
+                        } else {
+                            Ok(ReplicaURL::GRPC(
+                                Self::host_string(host),
+                                port,
+                                service.to_string(),
+                            ))
+                        }
+                    }
+                    _ => Err(()),
+                },
+                "smtp" => match (url.host(), url.port(), url.path_segments()) {
+                    (Some(host), port, None) => Ok(ReplicaURL::SMTP(
+                        Self::host_string(host),
+                        port.unwrap_or(SMTP_PORT_DEFAULT),
+                    )),
+                    _ => Err(()),
+                },
+                "postgres" | "postgresql" => {
+                    let (host, port, user, database) =
+                        Self::database_parts(&url, POSTGRESQL_PORT_DEFAULT)?;
+
+                    Ok(ReplicaURL::PostgreSQL(host, port, user, database))
+                }
+                "mysql" => {
+                    let (host, port, user, database) =
+                        Self::database_parts(&url, MYSQL_PORT_DEFAULT)?;
+
+                    Ok(ReplicaURL::MySQL(host, port, user, database))
+                }
+                "redis" | "rediss" => match (url.host(), url.password(), url.query()) {
+                    (Some(host), None, None) => {
+                        // Notice: the database index in path (eg. '/0') is ignored, as it does \
+                        //   not matter to check the server.
+                        let database = url.path().trim_start_matches('/');
+
+                        if database.chars().all(|character| character.is_ascii_digit()) == false {
+                            return Err(());
+                        }
+
+                        let (host, port, user) = (
+                            Self::host_string(host),
+                            url.port().unwrap_or(REDIS_PORT_DEFAULT),
+                            Some(url.username().to_string())
+                                .filter(|user| user.is_empty() == false),
+                        );
+
+                        if url.scheme() == "rediss" {
+                            Ok(ReplicaURL::RedisTLS(host, port, user))
+                        } else {
+                            Ok(ReplicaURL::Redis(host, port, user))
+                        }
+                    }
+                    _ => Err(()),
+                },
+                "amqp" | "amqps" => match (url.host(), url.password(), url.query()) {
+                    (Some(host), None, None) => {
+                        // Acquire virtual host from path (defaults to '/', which must be \
+                        //   written as '%2f' in path, as per AMQP URIs)
+                        let virtual_host = match url.path().strip_prefix('/') {
+                            Some(path) if path.is_empty() == false => {
+                                Self::percent_decode(path).ok_or(())?
+                            }
+                            _ => "/".to_string(),
+                        };
+
+                        let user = Some(url.username().to_string())
+                            .filter(|user| user.is_empty() == false);
+
+                        if url.scheme() == "amqps" {
+                            Ok(ReplicaURL::AMQPS(
+                                Self::host_string(host),
+                                url.port().unwrap_or(AMQPS_PORT_DEFAULT),
+                                user,
+                                virtual_host,
+                            ))
+                        } else {
+                            Ok(ReplicaURL::AMQP(
+                                Self::host_string(host),
+                                url.port().unwrap_or(AMQP_PORT_DEFAULT),
+                                user,
+                                virtual_host,
+                            ))
+                        }
+                    }
+                    _ => Err(()),
+                },
+                "kafka" => match (url.host(), url.path_segments()) {
+                    (Some(host), None) => Ok(ReplicaURL::Kafka(
+                        Self::host_string(host),
+                        url.port().unwrap_or(KAFKA_PORT_DEFAULT),
+                    )),
+                    _ => Err(()),
+                },
+                "ldap" => match (url.host(), url.path_segments()) {
+                    (Some(host), None) => Ok(ReplicaURL::LDAP(
+                        Self::host_string(host),
+                        url.port().unwrap_or(LDAP_PORT_DEFAULT),
+                    )),
+                    _ => Err(()),
+                },
+                "ldaps" => match (url.host(), url.path_segments()) {
+                    (Some(host), None) => Ok(ReplicaURL::LDAPS(
+                        Self::host_string(host),
+                        url.port().unwrap_or(LDAPS_PORT_DEFAULT),
+                    )),
+                    _ => Err(()),
+                },
+                "ssh" => match (url.host(), url.path_segments()) {
+                    (Some(host), None) => Ok(ReplicaURL::SSH(
+                        Self::host_string(host),
+                        url.port().unwrap_or(SSH_PORT_DEFAULT),
+                    )),
+                    _ => Err(()),
+                },
+                "ntp" => match (url.host(), url.path_segments()) {
+                    (Some(host), None) => Ok(ReplicaURL::NTP(
+                        Self::host_string(host),
+                        url.port().unwrap_or(NTP_PORT_DEFAULT),
+                    )),
+                    _ => Err(()),
+                },
+                "elastic" | "elastics" => {
+                    match (url.host(), url.password(), url.path(), url.query()) {
+                        (Some(host), None, "" | "/", None) => {
+                            let (host, port, user) = (
+                                Self::host_string(host),
+                                url.port().unwrap_or(ELASTIC_PORT_DEFAULT),
+                                Some(url.username().to_string())
+                                    .filter(|user| user.is_empty() == false),
+                            );
+
+                            if url.scheme() == "elastics" {
+                                Ok(ReplicaURL::ElasticTLS(host, port, user))
+                            } else {
+                                Ok(ReplicaURL::Elastic(host, port, user))
+                            }
+                        }
+                        _ => Err(()),
+                    }
+                }
+                "promql" | "promqls" => match (url.host_str(), url.password(), url.query()) {
+                    (Some(host), None, None) if url.username().is_empty() == true => {
+                        // Notice: the path is kept as a prefix to the API path, for servers \
+                        //   that are served behind a reverse proxy (eg. '/prometheus').
+                        let (port, path) = (
+                            url.port().unwrap_or(PROMQL_PORT_DEFAULT),
+                            url.path().trim_end_matches('/'),
+                        );
+
+                        if url.scheme() == "promqls" {
+                            Ok(ReplicaURL::PromQLS(format!(
+                                "https://{}:{}{}",
+                                host, port, path
+                            )))
+                        } else {
+                            Ok(ReplicaURL::PromQL(format!(
+                                "http://{}:{}{}",
+                                host, port, path
+                            )))
+                        }
+                    }
+                    _ => Err(()),
+                },
+                "snmp" => match (url.host(), url.password(), url.query()) {
+                    (Some(host), None, None) => {
+                        // Notice: the object identifier is passed in path (eg. '/1.3.6.1.2.1.1.3.0')
+                        let oid = url.path().trim_start_matches('/');
+
+                        if oid.is_empty() == true
+                            || oid.split('.').all(|arc| {
+                                arc.is_empty() == false
+                                    && arc.chars().all(|character| character.is_ascii_digit())
+                            }) == false
+                        {
+                            return Err(());
+                        }
+
+                        Ok(ReplicaURL::SNMP(
+                            Self::host_string(host),
+                            url.port().unwrap_or(SNMP_PORT_DEFAULT),
+                            Some(url.username().to_string())
+                                .filter(|user| user.is_empty() == false),
+                            oid.to_string(),
+                        ))
+                    }
+                    _ => Err(()),
+                },
+                "ws" => Ok(ReplicaURL::WS(url.into())),
+                "wss" => Ok(ReplicaURL::WSS(url.into())),
                 "http" => Ok(ReplicaURL::HTTP(url.into())),
                 "https" => Ok(ReplicaURL::HTTPS(url.into())),
                 _ => Err(()),
@@ -36,6 +276,86 @@ impl ReplicaURL {
         }
     }
 
+    pub fn tcp_endpoint(&self) -> Option<(String, Option<u16>)> {
+        // Notice: replicas that are not checked over TCP (eg. ICMP, NTP or SNMP) have a host, but \
+        //   no TCP port.
+        match self {
+            ReplicaURL::ICMP(host) => Some((host.to_owned(), None)),
+            ReplicaURL::NTP(host, _) | ReplicaURL::SNMP(host, _, _, _) => {
+                Some((host.to_owned(), None))
+            }
+            ReplicaURL::TCP(host, port)
+            | ReplicaURL::GRPC(host, port, _)
+            | ReplicaURL::GRPCS(host, port, _)
+            | ReplicaURL::SMTP(host, port)
+            | ReplicaURL::PostgreSQL(host, port, _, _)
+            | ReplicaURL::MySQL(host, port, _, _)
+            | ReplicaURL::Redis(host, port, _)
+            | ReplicaURL::RedisTLS(host, port, _)
+            | ReplicaURL::AMQP(host, port, _, _)
+            | ReplicaURL::AMQPS(host, port, _, _)
+            | ReplicaURL::Kafka(host, port)
+            | ReplicaURL::LDAP(host, port)
+            | ReplicaURL::LDAPS(host, port)
+            | ReplicaURL::SSH(host, port)
+            | ReplicaURL::Elastic(host, port, _)
+            | ReplicaURL::ElasticTLS(host, port, _) => Some((host.to_owned(), Some(*port))),
+            ReplicaURL::HTTP(url)
+            | ReplicaURL::HTTPS(url)
+            | ReplicaURL::WS(url)
+            | ReplicaURL::WSS(url)
+            | ReplicaURL::PromQL(url)
+            | ReplicaURL::PromQLS(url) => {
+                let url = Url::parse(url).ok()?;
+
+                Some((Self::host_string(url.host()?), url.port_or_known_default()))
+            }
+        }
+    }
+
+    fn database_parts(
+        url: &Url,
+        default_port: u16,
+    ) -> Result<(String, u16, String, Option<String>), ()> {
+        // Notice: passwords are refused in database URLs, as replica URLs are publicly visible \
+        //   (they must be set in the 'database_password' node option instead).
+        match (url.host(), url.username(), url.password(), url.query()) {
+            (Some(host), user, None, None) if user.is_empty() == false => {
+                // Acquire database name from path (if any)
+                let database = url.path().trim_start_matches('/');
+
+                if database.contains('/') == true {
+                    return Err(());
+                }
+
+                Ok((
+                    Self::host_string(host),
+                    url.port().unwrap_or(default_port),
+                    user.to_string(),
+                    Some(database.to_string()).filter(|database| database.is_empty() == false),
+                ))
+            }
+            _ => Err(()),
+        }
+    }
+
+    fn percent_decode(value: &str) -> Option<String> {
+        let mut bytes = Vec::new();
+        let mut characters = value.bytes();
+
+        while let Some(character) = characters.next() {
+            if character == b'%' {
+                let code = [characters.next()?, characters.next()?];
+
+                bytes.push(u8::from_str_radix(std::str::from_utf8(&code).ok()?, 16).ok()?);
+            } else {
+                bytes.push(character);
+            }
+        }
+
+        String::from_utf8(bytes).ok()
+    }
+
     fn host_string(host: Host<&str>) -> String {
         // Convert internal host value into string. This is especially useful for IPv6 addresses, \
         //   which we need returned in '::1' format; as they would otherwise be returned in \
@@ -6,16 +6,19 @@
 
 use std::time::{Duration, SystemTime};
 
+use super::family::AddressFamily;
 use super::states::{
     ServiceStatesProbeNodeRabbitMQ, ServiceStatesProbeNodeReplica,
     ServiceStatesProbeNodeReplicaLoad, ServiceStatesProbeNodeReplicaLoadQueue,
     ServiceStatesProbeNodeReplicaMetrics, ServiceStatesProbeNodeReplicaMetricsSystem,
     ServiceStatesProbeNodeReplicaReport,
 };
-use crate::prober::manager::STORE as PROBER_STORE;
+use crate::prober::manager::{mark_snapshot_dirty, Store, STORE as PROBER_STORE};
 use crate::prober::mode::Mode;
 use crate::prober::status::Status;
 
+pub const HEARTBEAT_REPLICA_ID: &'static str = "heartbeat";
+
 pub enum HandleLoadError {
     InvalidLoad,
     WrongMode,
@@ -27,11 +30,40 @@ pub enum HandleHealthError {
     NotFound,
 }
 
+pub enum HandleHeartbeatError {
+    WrongMode,
+    NotFound,
+}
+
 pub enum HandleFlushError {
     WrongMode,
     NotFound,
 }
 
+pub enum HandleBatchError {
+    InvalidLoad(usize),
+    WrongMode(usize),
+    NotFound(usize),
+}
+
+enum CheckNodeError {
+    WrongMode,
+    NotFound,
+}
+
+pub struct BatchReport<'a> {
+    pub probe_id: &'a str,
+    pub node_id: &'a str,
+    pub replica_id: &'a str,
+    pub interval: u64,
+    pub kind: BatchReportKind<'a>,
+}
+
+pub enum BatchReportKind<'a> {
+    Load(f32, f32),
+    Health(&'a Status),
+}
+
 pub fn handle_load(
     probe_id: &str,
     node_id: &str,
@@ -52,66 +84,26 @@ pub fn handle_load(
 
     let mut store = PROBER_STORE.write().unwrap();
 
-    if let Some(ref mut probe) = store.states.probes.get_mut(probe_id) {
-        if let Some(ref mut node) = probe.nodes.get_mut(node_id) {
-            // Mode isnt push? Dont accept report
-            if node.mode != Mode::Push {
-                return Err(HandleLoadError::WrongMode);
-            }
-
-            // Acquire previous replica status + previous queue load status (follow-up values)
-            let (status, mut metrics, mut load_queue);
-
-            load_queue = ServiceStatesProbeNodeReplicaLoadQueue::default();
-
-            if let Some(ref replica) = node.replicas.get(replica_id) {
-                status = replica.status.to_owned();
-                metrics = replica.metrics.to_owned();
-
-                if let Some(ref replica_load) = replica.load {
-                    load_queue = replica_load.queue.clone();
-                }
-            } else {
-                status = Status::Healthy;
-                metrics = ServiceStatesProbeNodeReplicaMetrics::default();
-            }
+    match check_node(&store, probe_id, node_id, Mode::Push) {
+        Ok(()) => {
+            let queue = store_load(
+                &mut store, probe_id, node_id, replica_id, interval, load_cpu, load_ram,
+            );
 
-            // Assign new system metrics
-            metrics.system = Some(ServiceStatesProbeNodeReplicaMetricsSystem {
-                cpu: (load_cpu * 100.0).round() as u16,
-                ram: (load_ram * 100.0).round() as u16,
-            });
+            mark_snapshot_dirty();
 
-            // Bump stored replica
-            node.replicas.insert(
-                replica_id.to_string(),
-                ServiceStatesProbeNodeReplica {
-                    status: status,
-                    url: None,
-                    script: None,
-                    metrics: metrics,
-                    load: Some(ServiceStatesProbeNodeReplicaLoad {
-                        cpu: load_cpu,
-                        ram: load_ram,
-                        queue: load_queue,
-                    }),
-                    report: Some(ServiceStatesProbeNodeReplicaReport {
-                        time: SystemTime::now(),
-                        interval: Duration::from_secs(interval),
-                    }),
-                },
+            Ok(queue)
+        }
+        Err(CheckNodeError::WrongMode) => Err(HandleLoadError::WrongMode),
+        Err(CheckNodeError::NotFound) => {
+            warn!(
+                "load report could not be stored: {}:{}:{}",
+                probe_id, node_id, replica_id
             );
 
-            return Ok(node.rabbitmq.clone());
+            Err(HandleLoadError::NotFound)
         }
     }
-
-    warn!(
-        "load report could not be stored: {}:{}:{}",
-        probe_id, node_id, replica_id
-    );
-
-    Err(HandleLoadError::NotFound)
 }
 
 pub fn handle_health(
@@ -128,39 +120,76 @@ pub fn handle_health(
 
     let mut store = PROBER_STORE.write().unwrap();
 
+    match check_node(&store, probe_id, node_id, Mode::Local) {
+        Ok(()) => {
+            store_health(&mut store, probe_id, node_id, replica_id, interval, health);
+
+            mark_snapshot_dirty();
+
+            Ok(())
+        }
+        Err(CheckNodeError::WrongMode) => Err(HandleHealthError::WrongMode),
+        Err(CheckNodeError::NotFound) => {
+            warn!(
+                "health report could not be stored: {}:{}:{}",
+                probe_id, node_id, replica_id
+            );
+
+            Err(HandleHealthError::NotFound)
+        }
+    }
+}
+
+pub fn handle_heartbeat(
+    probe_id: &str,
+    node_id: &str,
+    health: Status,
+) -> Result<(), HandleHeartbeatError> {
+    debug!(
+        "heartbeat report handle: {}:{} => {:?}",
+        probe_id, node_id, health
+    );
+
+    let mut store = PROBER_STORE.write().unwrap();
+
     if let Some(ref mut probe) = store.states.probes.get_mut(probe_id) {
         if let Some(ref mut node) = probe.nodes.get_mut(node_id) {
-            // Mode isnt local? Dont accept report
-            if node.mode != Mode::Local {
-                return Err(HandleHealthError::WrongMode);
+            // Mode isnt heartbeat? Dont accept report
+            if node.mode != Mode::Heartbeat {
+                return Err(HandleHeartbeatError::WrongMode);
             }
 
-            // Bump stored replica
+            // Bump stored replica (next heartbeat is expected within the interval, or after \
+            //   next scheduled run; a failed run holds the replica dead until next heartbeat)
             node.replicas.insert(
-                replica_id.to_string(),
+                HEARTBEAT_REPLICA_ID.to_string(),
                 ServiceStatesProbeNodeReplica {
-                    status: health.to_owned(),
+                    status: health,
                     url: None,
                     script: None,
+                    family: AddressFamily::Any,
                     metrics: ServiceStatesProbeNodeReplicaMetrics::default(),
+                    message: None,
                     load: None,
                     report: Some(ServiceStatesProbeNodeReplicaReport {
                         time: SystemTime::now(),
-                        interval: Duration::from_secs(interval),
+                        interval: Duration::from_secs(node.options.heartbeat_interval.unwrap_or(0)),
                     }),
                 },
             );
 
+            mark_snapshot_dirty();
+
             return Ok(());
         }
     }
 
     warn!(
-        "health report could not be stored: {}:{}:{}",
-        probe_id, node_id, replica_id
+        "heartbeat report could not be stored: {}:{}",
+        probe_id, node_id
     );
 
-    Err(HandleHealthError::NotFound)
+    Err(HandleHeartbeatError::NotFound)
 }
 
 pub fn handle_flush(
@@ -182,11 +211,13 @@ pub fn handle_flush(
                 return Err(HandleFlushError::WrongMode);
             }
 
-            return if node.replicas.shift_remove(replica_id).is_none() {
-                Err(HandleFlushError::NotFound)
-            } else {
-                Ok(())
-            };
+            if node.replicas.shift_remove(replica_id).is_none() {
+                return Err(HandleFlushError::NotFound);
+            }
+
+            mark_snapshot_dirty();
+
+            return Ok(());
         }
     }
 
@@ -197,3 +228,189 @@ pub fn handle_flush(
 
     Err(HandleFlushError::NotFound)
 }
+
+pub fn handle_batch(
+    reports: &[BatchReport],
+) -> Result<Vec<Option<ServiceStatesProbeNodeRabbitMQ>>, HandleBatchError> {
+    debug!("batch report handle: {} reports", reports.len());
+
+    let mut store = PROBER_STORE.write().unwrap();
+
+    // Notice: all reports are checked before any gets stored, so that a batch is either \
+    //   stored as a whole or not at all (the store lock is held for the whole batch).
+    for (index, report) in reports.iter().enumerate() {
+        let mode = match report.kind {
+            BatchReportKind::Load(load_cpu, load_ram) => {
+                if load_cpu < 0.00 || load_ram < 0.00 {
+                    return Err(HandleBatchError::InvalidLoad(index));
+                }
+
+                Mode::Push
+            }
+            BatchReportKind::Health(_) => Mode::Local,
+        };
+
+        match check_node(&store, report.probe_id, report.node_id, mode) {
+            Ok(()) => {}
+            Err(CheckNodeError::WrongMode) => return Err(HandleBatchError::WrongMode(index)),
+            Err(CheckNodeError::NotFound) => {
+                warn!(
+                    "batch report could not be stored: {}:{}:{}",
+                    report.probe_id, report.node_id, report.replica_id
+                );
+
+                return Err(HandleBatchError::NotFound(index));
+            }
+        }
+    }
+
+    let queues = reports
+        .iter()
+        .map(|report| match report.kind {
+            BatchReportKind::Load(load_cpu, load_ram) => store_load(
+                &mut store,
+                report.probe_id,
+                report.node_id,
+                report.replica_id,
+                report.interval,
+                load_cpu,
+                load_ram,
+            ),
+            BatchReportKind::Health(health) => {
+                store_health(
+                    &mut store,
+                    report.probe_id,
+                    report.node_id,
+                    report.replica_id,
+                    report.interval,
+                    health,
+                );
+
+                None
+            }
+        })
+        .collect();
+
+    mark_snapshot_dirty();
+
+    Ok(queues)
+}
+
+fn check_node(
+    store: &Store,
+    probe_id: &str,
+    node_id: &str,
+    mode: Mode,
+) -> Result<(), CheckNodeError> {
+    let node = store
+        .states
+        .probes
+        .get(probe_id)
+        .and_then(|probe| probe.nodes.get(node_id))
+        .ok_or(CheckNodeError::NotFound)?;
+
+    // Mode does not match report kind? Dont accept report
+    if node.mode != mode {
+        return Err(CheckNodeError::WrongMode);
+    }
+
+    Ok(())
+}
+
+fn store_load(
+    store: &mut Store,
+    probe_id: &str,
+    node_id: &str,
+    replica_id: &str,
+    interval: u64,
+    load_cpu: f32,
+    load_ram: f32,
+) -> Option<ServiceStatesProbeNodeRabbitMQ> {
+    let node = store
+        .states
+        .probes
+        .get_mut(probe_id)
+        .and_then(|probe| probe.nodes.get_mut(node_id))
+        .expect("node checked beforehand");
+
+    // Acquire previous replica status + previous queue load status (follow-up values)
+    let (status, mut metrics, mut load_queue);
+
+    load_queue = ServiceStatesProbeNodeReplicaLoadQueue::default();
+
+    if let Some(ref replica) = node.replicas.get(replica_id) {
+        status = replica.status.to_owned();
+        metrics = replica.metrics.to_owned();
+
+        if let Some(ref replica_load) = replica.load {
+            load_queue = replica_load.queue.clone();
+        }
+    } else {
+        status = Status::Healthy;
+        metrics = ServiceStatesProbeNodeReplicaMetrics::default();
+    }
+
+    // Assign new system metrics
+    metrics.system = Some(ServiceStatesProbeNodeReplicaMetricsSystem {
+        cpu: (load_cpu * 100.0).round() as u16,
+        ram: (load_ram * 100.0).round() as u16,
+    });
+
+    // Bump stored replica
+    node.replicas.insert(
+        replica_id.to_string(),
+        ServiceStatesProbeNodeReplica {
+            status: status,
+            url: None,
+            script: None,
+            family: AddressFamily::Any,
+            metrics: metrics,
+            message: None,
+            load: Some(ServiceStatesProbeNodeReplicaLoad {
+                cpu: load_cpu,
+                ram: load_ram,
+                queue: load_queue,
+            }),
+            report: Some(ServiceStatesProbeNodeReplicaReport {
+                time: SystemTime::now(),
+                interval: Duration::from_secs(interval),
+            }),
+        },
+    );
+
+    node.rabbitmq.clone()
+}
+
+fn store_health(
+    store: &mut Store,
+    probe_id: &str,
+    node_id: &str,
+    replica_id: &str,
+    interval: u64,
+    health: &Status,
+) {
+    let node = store
+        .states
+        .probes
+        .get_mut(probe_id)
+        .and_then(|probe| probe.nodes.get_mut(node_id))
+        .expect("node checked beforehand");
+
+    // Bump stored replica
+    node.replicas.insert(
+        replica_id.to_string(),
+        ServiceStatesProbeNodeReplica {
+            status: health.to_owned(),
+            url: None,
+            script: None,
+            family: AddressFamily::Any,
+            metrics: ServiceStatesProbeNodeReplicaMetrics::default(),
+            message: None,
+            load: None,
+            report: Some(ServiceStatesProbeNodeReplicaReport {
+                time: SystemTime::now(),
+                interval: Duration::from_secs(interval),
+            }),
+        },
+    );
+}
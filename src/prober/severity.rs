@@ -0,0 +1,33 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+// Notice: variants are ordered from the least to the most severe, so that severities can be \
+//   compared against a minimum severity.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, PartialOrd, Debug)]
+pub enum Severity {
+    #[serde(rename = "info")]
+    Info,
+
+    #[serde(rename = "minor")]
+    Minor,
+
+    #[serde(rename = "major")]
+    Major,
+
+    #[serde(rename = "critical")]
+    Critical,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            &Severity::Info => "info",
+            &Severity::Minor => "minor",
+            &Severity::Major => "major",
+            &Severity::Critical => "critical",
+        }
+    }
+}
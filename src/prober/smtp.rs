@@ -0,0 +1,203 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use native_tls::TlsConnector;
+
+use super::dns::Resolver;
+use super::status::Status;
+use crate::APP_CONF;
+
+const REPLY_MAXIMUM_LINES: usize = 64;
+const REPLY_CODE_READY: u16 = 220;
+const REPLY_CODE_CLOSING: u16 = 221;
+const REPLY_CODE_OK: u16 = 250;
+const EXTENSION_STARTTLS: &'static str = "STARTTLS";
+
+enum SessionError {
+    Failed(String),
+    Replied(&'static str, u16),
+}
+
+pub fn check(host: &str, port: u16, starttls: bool, resolver: Resolver) -> Status {
+    match proceed_check(host, port, starttls, resolver) {
+        Ok(_) => Status::Healthy,
+        Err(SessionError::Replied(command, code)) => {
+            debug!(
+                "prober poll got unexpected reply for smtp target: {}:{} to: {} (code: {})",
+                host, port, command, code
+            );
+
+            // Notice: transient negative replies (eg. '421' when the MTA is overloaded) mean \
+            //   that the MTA is up but cannot accept mail for now.
+            if (400..500).contains(&code) == true {
+                Status::Sick
+            } else {
+                Status::Dead
+            }
+        }
+        Err(SessionError::Failed(err)) => {
+            debug!(
+                "prober poll failed for smtp target: {}:{} (error: {})",
+                host, port, err
+            );
+
+            Status::Dead
+        }
+    }
+}
+
+fn proceed_check(
+    host: &str,
+    port: u16,
+    starttls: bool,
+    resolver: Resolver,
+) -> Result<(), SessionError> {
+    let address =
+        resolver
+            .lookup(host, port)
+            .map_err(failed)?
+            .next()
+            .ok_or(SessionError::Failed(
+                "could not resolve address".to_string(),
+            ))?;
+
+    debug!("prober poll will fire for smtp target: {}", address);
+
+    // Notice: a stuck MTA might accept connections but never greet, which is caught by the \
+    //   read timeout.
+    let timeout = Duration::from_secs(APP_CONF.metrics.poll_delay_dead);
+    let stream = TcpStream::connect_timeout(&address, timeout).map_err(failed)?;
+
+    stream
+        .set_read_timeout(Some(timeout))
+        .and_then(|_| stream.set_write_timeout(Some(timeout)))
+        .map_err(failed)?;
+
+    let mut reader = BufReader::new(stream);
+
+    expect_reply(&mut reader, "banner", REPLY_CODE_READY)?;
+
+    let extensions = send_command(&mut reader, "EHLO", REPLY_CODE_OK)?;
+
+    // Upgrade connection to TLS? (if configured)
+    if starttls == true {
+        // Check that STARTTLS is advertised (the first line holds the server greeting)
+        let has_starttls = extensions.iter().skip(1).any(|extension| {
+            extension
+                .split_whitespace()
+                .next()
+                .map(|keyword| keyword.eq_ignore_ascii_case(EXTENSION_STARTTLS))
+                .unwrap_or(false)
+        });
+
+        if has_starttls == false {
+            return Err(SessionError::Failed(
+                "starttls extension not advertised".to_string(),
+            ));
+        }
+
+        send_command(&mut reader, EXTENSION_STARTTLS, REPLY_CODE_READY)?;
+
+        // Perform TLS handshake (this fails if the certificate chain is invalid, or expired)
+        let connector = TlsConnector::new().map_err(failed)?;
+
+        let tls_stream = connector
+            .connect(host, reader.into_inner())
+            .map_err(failed)?;
+
+        let mut tls_reader = BufReader::new(tls_stream);
+
+        send_command(&mut tls_reader, "EHLO", REPLY_CODE_OK)?;
+        send_quit(&mut tls_reader);
+    } else {
+        send_quit(&mut reader);
+    }
+
+    Ok(())
+}
+
+fn send_command<S: Read + Write>(
+    reader: &mut BufReader<S>,
+    command: &'static str,
+    expected_code: u16,
+) -> Result<Vec<String>, SessionError> {
+    let line = if command == "EHLO" {
+        format!("EHLO {}\r\n", acquire_client_name())
+    } else {
+        format!("{}\r\n", command)
+    };
+
+    reader
+        .get_mut()
+        .write_all(line.as_bytes())
+        .map_err(failed)?;
+
+    expect_reply(reader, command, expected_code)
+}
+
+fn send_quit<S: Read + Write>(reader: &mut BufReader<S>) {
+    // Notice: the MTA already proved to be healthy there, thus the reply does not matter
+    let _ = send_command(reader, "QUIT", REPLY_CODE_CLOSING);
+}
+
+fn expect_reply<R: BufRead>(
+    reader: &mut R,
+    command: &'static str,
+    expected_code: u16,
+) -> Result<Vec<String>, SessionError> {
+    let mut lines = Vec::new();
+
+    // Read reply lines (each line but the last one has a '-' after the code, eg. '250-SIZE')
+    loop {
+        let mut line = String::new();
+
+        if reader.read_line(&mut line).map_err(failed)? == 0 {
+            return Err(SessionError::Failed(format!(
+                "connection closed while waiting for: {}",
+                command
+            )));
+        }
+
+        let code = line
+            .get(0..3)
+            .and_then(|code| code.parse::<u16>().ok())
+            .ok_or(SessionError::Failed(format!(
+                "invalid reply: {}",
+                line.trim_end()
+            )))?;
+
+        if code != expected_code {
+            return Err(SessionError::Replied(command, code));
+        }
+
+        let is_last = line.get(3..4) != Some("-");
+
+        lines.push(line.get(4..).unwrap_or_default().trim_end().to_string());
+
+        if is_last == true {
+            break;
+        }
+
+        if lines.len() >= REPLY_MAXIMUM_LINES {
+            return Err(SessionError::Failed("reply is too long".to_string()));
+        }
+    }
+
+    Ok(lines)
+}
+
+fn acquire_client_name() -> &'static str {
+    // Announce ourselves with the status page domain (as it is the best known name for Vigil)
+    APP_CONF.branding.page_url.host_str().unwrap_or("localhost")
+}
+
+fn failed<E: ToString>(err: E) -> SessionError {
+    SessionError::Failed(err.to_string())
+}
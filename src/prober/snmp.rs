@@ -0,0 +1,738 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::net::{SocketAddr, UdpSocket};
+use std::time::Duration;
+
+use openssl::hash::{Hasher, MessageDigest};
+use openssl::memcmp;
+use openssl::pkey::PKey;
+use openssl::rand::rand_bytes;
+use openssl::sign::Signer;
+
+use super::dns::Resolver;
+use super::status::Status;
+use crate::config::config::ConfigProbeServiceNodeSNMPAuthProtocol;
+use crate::APP_CONF;
+
+const MESSAGE_MAXIMUM_BYTES: usize = 65507;
+const COMMUNITY_DEFAULT: &'static str = "public";
+const VERSION_2C: u8 = 1;
+const VERSION_3: u8 = 3;
+const SECURITY_MODEL_USM: u8 = 3;
+const FLAG_AUTH: u8 = 0x01;
+const FLAG_REPORTABLE: u8 = 0x04;
+const AUTH_PARAMETERS_SIZE: usize = 12;
+const KEY_EXPANSION_BYTES: usize = 1048576;
+
+const TAG_INTEGER: u8 = 0x02;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_NULL: u8 = 0x05;
+const TAG_OBJECT_IDENTIFIER: u8 = 0x06;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_COUNTER32: u8 = 0x41;
+const TAG_GAUGE32: u8 = 0x42;
+const TAG_TIMETICKS: u8 = 0x43;
+const TAG_COUNTER64: u8 = 0x46;
+const TAG_GET_REQUEST: u8 = 0xa0;
+const TAG_RESPONSE: u8 = 0xa2;
+const TAG_REPORT: u8 = 0xa8;
+
+pub struct Thresholds {
+    pub sick_above: Option<f64>,
+    pub dead_above: Option<f64>,
+    pub sick_below: Option<f64>,
+    pub dead_below: Option<f64>,
+}
+
+struct Engine {
+    identifier: Vec<u8>,
+    boots: u32,
+    time: u32,
+}
+
+pub fn check(
+    host: &str,
+    port: u16,
+    oid: &str,
+    user: &Option<String>,
+    community: &Option<String>,
+    password: &Option<String>,
+    auth_protocol: &ConfigProbeServiceNodeSNMPAuthProtocol,
+    thresholds: &Thresholds,
+    resolver: Resolver,
+) -> Status {
+    let value = match proceed_get(
+        host,
+        port,
+        oid,
+        user,
+        community,
+        password,
+        auth_protocol,
+        resolver,
+    ) {
+        Ok(value) => value,
+        Err(err) => {
+            debug!(
+                "prober poll failed for snmp target: {}:{} with oid: {} (error: {})",
+                host, port, oid, err
+            );
+
+            return Status::Dead;
+        }
+    };
+
+    debug!(
+        "prober poll got value for snmp target: {}:{} with oid: {}: {:?}",
+        host, port, oid, value
+    );
+
+    // Compare value against thresholds (non-numeric values are not compared)
+    let exceeds = |above: Option<f64>, below: Option<f64>| match value {
+        Some(value) => {
+            above.map(|above| value > above).unwrap_or(false) == true
+                || below.map(|below| value < below).unwrap_or(false) == true
+        }
+        None => false,
+    };
+
+    if exceeds(thresholds.dead_above, thresholds.dead_below) == true {
+        Status::Dead
+    } else if exceeds(thresholds.sick_above, thresholds.sick_below) == true {
+        Status::Sick
+    } else {
+        Status::Healthy
+    }
+}
+
+fn proceed_get(
+    host: &str,
+    port: u16,
+    oid: &str,
+    user: &Option<String>,
+    community: &Option<String>,
+    password: &Option<String>,
+    auth_protocol: &ConfigProbeServiceNodeSNMPAuthProtocol,
+    resolver: Resolver,
+) -> Result<Option<f64>, String> {
+    let address = resolver
+        .lookup(host, port)
+        .map_err(|err| err.to_string())?
+        .next()
+        .ok_or("could not resolve address")?;
+
+    debug!("prober poll will fire for snmp target: {}", address);
+
+    let socket = match address {
+        SocketAddr::V4(_) => UdpSocket::bind("0.0.0.0:0"),
+        SocketAddr::V6(_) => UdpSocket::bind("[::]:0"),
+    }
+    .map_err(|err| err.to_string())?;
+
+    let timeout = Duration::from_secs(APP_CONF.metrics.poll_delay_dead);
+
+    socket
+        .set_read_timeout(Some(timeout))
+        .and_then(|_| socket.set_write_timeout(Some(timeout)))
+        .and_then(|_| socket.connect(address))
+        .map_err(|err| err.to_string())?;
+
+    let oid = encode_oid(oid).ok_or("invalid oid")?;
+
+    // Use SNMPv3 if a user is set, otherwise use SNMPv2c
+    match user {
+        Some(user) => {
+            let password = password.as_deref().ok_or("snmpv3 requires a password")?;
+
+            get_v3(&socket, &oid, user, password, auth_protocol)
+        }
+        None => get_v2c(
+            &socket,
+            &oid,
+            community.as_deref().unwrap_or(COMMUNITY_DEFAULT),
+        ),
+    }
+}
+
+fn get_v2c(socket: &UdpSocket, oid: &[u8], community: &str) -> Result<Option<f64>, String> {
+    let request_id = make_request_id()?;
+
+    let message = encode(
+        TAG_SEQUENCE,
+        &[
+            encode(TAG_INTEGER, &[VERSION_2C]),
+            encode(TAG_OCTET_STRING, community.as_bytes()),
+            make_get_request(request_id, Some(oid)),
+        ]
+        .concat(),
+    );
+
+    let response = exchange(socket, &message)?;
+
+    // Unwrap response message (version, community and PDU)
+    let mut message = read_element(&mut response.as_slice(), TAG_SEQUENCE)?;
+
+    read_element(&mut message, TAG_INTEGER)?;
+    read_element(&mut message, TAG_OCTET_STRING)?;
+
+    read_response_value(&mut message, request_id)
+}
+
+fn get_v3(
+    socket: &UdpSocket,
+    oid: &[u8],
+    user: &str,
+    password: &str,
+    auth_protocol: &ConfigProbeServiceNodeSNMPAuthProtocol,
+) -> Result<Option<f64>, String> {
+    // Discover authoritative engine (a request without any user gets replied with a report \
+    //   that holds the engine identifier, boots and time)
+    let discovery = make_v3_message(
+        &Engine {
+            identifier: Vec::new(),
+            boots: 0,
+            time: 0,
+        },
+        "",
+        FLAG_REPORTABLE,
+        &make_get_request(make_request_id()?, None),
+    );
+
+    let response = exchange(socket, &discovery)?;
+    let (engine, _, _) = parse_v3_message(&response)?;
+
+    // Send authenticated request
+    let digest = match auth_protocol {
+        ConfigProbeServiceNodeSNMPAuthProtocol::MD5 => MessageDigest::md5(),
+        ConfigProbeServiceNodeSNMPAuthProtocol::SHA => MessageDigest::sha1(),
+    };
+
+    let key = localize_key(digest, password, &engine.identifier)?;
+    let request_id = make_request_id()?;
+
+    let mut message = make_v3_message(
+        &engine,
+        user,
+        FLAG_AUTH | FLAG_REPORTABLE,
+        &make_get_request(request_id, Some(oid)),
+    );
+
+    let offset = message.len() - authentication_tail_size(&message)?;
+    let signature = sign(digest, &key, &message)?;
+
+    message[offset..offset + AUTH_PARAMETERS_SIZE].copy_from_slice(&signature);
+
+    let mut response = exchange(socket, &message)?;
+
+    let (scoped_pdu, authentication_offset) = match parse_v3_message(&response)? {
+        (_, scoped_pdu, Some(authentication_offset)) => (scoped_pdu, authentication_offset),
+        _ => return Err("response is not authenticated".to_string()),
+    };
+
+    // Verify response signature (computed with the authentication parameters zeroed)
+    let response_signature =
+        response[authentication_offset..authentication_offset + AUTH_PARAMETERS_SIZE].to_vec();
+
+    response[authentication_offset..authentication_offset + AUTH_PARAMETERS_SIZE].fill(0);
+
+    if memcmp::eq(&sign(digest, &key, &response)?, &response_signature) == false {
+        return Err("invalid response signature".to_string());
+    }
+
+    // Unwrap scoped PDU (context engine identifier, context name and PDU)
+    let mut scoped_pdu = scoped_pdu.as_slice();
+
+    read_element(&mut scoped_pdu, TAG_OCTET_STRING)?;
+    read_element(&mut scoped_pdu, TAG_OCTET_STRING)?;
+
+    read_response_value(&mut scoped_pdu, request_id)
+}
+
+fn make_v3_message(engine: &Engine, user: &str, flags: u8, pdu: &[u8]) -> Vec<u8> {
+    let global_data = encode(
+        TAG_SEQUENCE,
+        &[
+            encode_integer(make_message_id()),
+            encode_integer(MESSAGE_MAXIMUM_BYTES as u32),
+            encode(TAG_OCTET_STRING, &[flags]),
+            encode(TAG_INTEGER, &[SECURITY_MODEL_USM]),
+        ]
+        .concat(),
+    );
+
+    // Notice: authentication parameters are zeroed, as the signature gets computed over the \
+    //   whole message, then written in place.
+    let authentication_parameters = if flags & FLAG_AUTH != 0 {
+        vec![0; AUTH_PARAMETERS_SIZE]
+    } else {
+        Vec::new()
+    };
+
+    let security_parameters = encode(
+        TAG_SEQUENCE,
+        &[
+            encode(TAG_OCTET_STRING, &engine.identifier),
+            encode_integer(engine.boots),
+            encode_integer(engine.time),
+            encode(TAG_OCTET_STRING, user.as_bytes()),
+            encode(TAG_OCTET_STRING, &authentication_parameters),
+            encode(TAG_OCTET_STRING, &[]),
+        ]
+        .concat(),
+    );
+
+    let scoped_pdu = encode(
+        TAG_SEQUENCE,
+        &[
+            encode(TAG_OCTET_STRING, &engine.identifier),
+            encode(TAG_OCTET_STRING, &[]),
+            pdu.to_vec(),
+        ]
+        .concat(),
+    );
+
+    encode(
+        TAG_SEQUENCE,
+        &[
+            encode(TAG_INTEGER, &[VERSION_3]),
+            global_data,
+            encode(TAG_OCTET_STRING, &security_parameters),
+            scoped_pdu,
+        ]
+        .concat(),
+    )
+}
+
+fn parse_v3_message(message: &[u8]) -> Result<(Engine, Vec<u8>, Option<usize>), String> {
+    let mut body = read_element(&mut &message[..], TAG_SEQUENCE)?;
+
+    read_element(&mut body, TAG_INTEGER)?;
+
+    // Check whether the message is authenticated, and not encrypted
+    let mut global_data = read_element(&mut body, TAG_SEQUENCE)?;
+
+    read_element(&mut global_data, TAG_INTEGER)?;
+    read_element(&mut global_data, TAG_INTEGER)?;
+
+    let flags = read_element(&mut global_data, TAG_OCTET_STRING)?
+        .first()
+        .cloned()
+        .unwrap_or_default();
+
+    if flags & 0x02 != 0 {
+        return Err("encrypted responses are not supported".to_string());
+    }
+
+    // Acquire engine, along with the location of authentication parameters
+    let mut security_parameters = read_element(&mut body, TAG_OCTET_STRING)?;
+    let mut security_parameters = read_element(&mut security_parameters, TAG_SEQUENCE)?;
+
+    let engine = Engine {
+        identifier: read_element(&mut security_parameters, TAG_OCTET_STRING)?.to_vec(),
+        boots: decode_unsigned(read_element(&mut security_parameters, TAG_INTEGER)?),
+        time: decode_unsigned(read_element(&mut security_parameters, TAG_INTEGER)?),
+    };
+
+    read_element(&mut security_parameters, TAG_OCTET_STRING)?;
+
+    let authentication_parameters = read_element(&mut security_parameters, TAG_OCTET_STRING)?;
+
+    let authentication_offset = if flags & FLAG_AUTH != 0 {
+        if authentication_parameters.len() != AUTH_PARAMETERS_SIZE {
+            return Err("invalid authentication parameters".to_string());
+        }
+
+        Some(authentication_parameters.as_ptr() as usize - message.as_ptr() as usize)
+    } else {
+        None
+    };
+
+    let scoped_pdu = read_element(&mut body, TAG_SEQUENCE)?.to_vec();
+
+    Ok((engine, scoped_pdu, authentication_offset))
+}
+
+fn make_get_request(request_id: u32, oid: Option<&[u8]>) -> Vec<u8> {
+    // Notice: engine discovery requests hold no variable binding
+    let bindings = match oid {
+        Some(oid) => encode(
+            TAG_SEQUENCE,
+            &[encode(TAG_OBJECT_IDENTIFIER, oid), encode(TAG_NULL, &[])].concat(),
+        ),
+        None => Vec::new(),
+    };
+
+    encode(
+        TAG_GET_REQUEST,
+        &[
+            encode_integer(request_id),
+            encode(TAG_INTEGER, &[0]),
+            encode(TAG_INTEGER, &[0]),
+            encode(TAG_SEQUENCE, &bindings),
+        ]
+        .concat(),
+    )
+}
+
+fn read_response_value(message: &mut &[u8], request_id: u32) -> Result<Option<f64>, String> {
+    let (tag, mut pdu) = read_any_element(message).ok_or("invalid pdu")?;
+
+    // Agent replied with a report? (eg. unknown user, wrong digest or time window mismatch)
+    if tag == TAG_REPORT {
+        return Err("agent replied with a report".to_string());
+    }
+
+    if tag != TAG_RESPONSE {
+        return Err(format!("unexpected pdu: {}", tag));
+    }
+
+    if decode_unsigned(read_element(&mut pdu, TAG_INTEGER)?) != request_id {
+        return Err("response does not match request".to_string());
+    }
+
+    let error_status = decode_unsigned(read_element(&mut pdu, TAG_INTEGER)?);
+
+    if error_status != 0 {
+        return Err(format!("agent replied with error status: {}", error_status));
+    }
+
+    read_element(&mut pdu, TAG_INTEGER)?;
+
+    // Acquire value from the first variable binding
+    let mut bindings = read_element(&mut pdu, TAG_SEQUENCE)?;
+    let mut binding = read_element(&mut bindings, TAG_SEQUENCE)?;
+
+    read_element(&mut binding, TAG_OBJECT_IDENTIFIER)?;
+
+    let (tag, value) = read_any_element(&mut binding).ok_or("invalid value")?;
+
+    match tag {
+        TAG_INTEGER => Ok(Some(decode_signed(value) as f64)),
+        TAG_COUNTER32 | TAG_GAUGE32 | TAG_TIMETICKS | TAG_COUNTER64 => {
+            Ok(Some(decode_unsigned_long(value) as f64))
+        }
+        TAG_OCTET_STRING => Ok(std::str::from_utf8(value)
+            .ok()
+            .and_then(|value| value.trim().parse::<f64>().ok())),
+        // Notice: the 'noSuchObject', 'noSuchInstance' and 'endOfMibView' exceptions
+        0x80 | 0x81 | 0x82 => Err(format!("agent has no value for oid (exception: {})", tag)),
+        _ => Ok(None),
+    }
+}
+
+fn exchange(socket: &UdpSocket, message: &[u8]) -> Result<Vec<u8>, String> {
+    socket.send(message).map_err(|err| err.to_string())?;
+
+    let mut response = vec![0; MESSAGE_MAXIMUM_BYTES];
+
+    let size = socket.recv(&mut response).map_err(|err| err.to_string())?;
+
+    response.truncate(size);
+
+    Ok(response)
+}
+
+fn localize_key(digest: MessageDigest, password: &str, engine: &[u8]) -> Result<Vec<u8>, String> {
+    if password.is_empty() == true {
+        return Err("password must not be empty".to_string());
+    }
+
+    // Expand password to 1MB, then localize it to the engine (see: RFC 3414, section A.2)
+    let mut hasher = Hasher::new(digest).map_err(|err| err.to_string())?;
+
+    let password = password.as_bytes();
+    let mut expanded = 0;
+
+    while expanded < KEY_EXPANSION_BYTES {
+        let chunk = &password[..password.len().min(KEY_EXPANSION_BYTES - expanded)];
+
+        hasher.update(chunk).map_err(|err| err.to_string())?;
+
+        expanded += chunk.len();
+    }
+
+    let key = hasher.finish().map_err(|err| err.to_string())?;
+
+    let mut hasher = Hasher::new(digest).map_err(|err| err.to_string())?;
+
+    hasher
+        .update(&key)
+        .and_then(|_| hasher.update(engine))
+        .and_then(|_| hasher.update(&key))
+        .map_err(|err| err.to_string())?;
+
+    hasher
+        .finish()
+        .map(|key| key.to_vec())
+        .map_err(|err| err.to_string())
+}
+
+fn sign(digest: MessageDigest, key: &[u8], message: &[u8]) -> Result<Vec<u8>, String> {
+    // Notice: signatures are HMAC digests truncated to 96 bits
+    let key = PKey::hmac(key).map_err(|err| err.to_string())?;
+    let mut signer = Signer::new(digest, &key).map_err(|err| err.to_string())?;
+
+    signer.update(message).map_err(|err| err.to_string())?;
+
+    signer
+        .sign_to_vec()
+        .map(|signature| signature[..AUTH_PARAMETERS_SIZE].to_vec())
+        .map_err(|err| err.to_string())
+}
+
+fn authentication_tail_size(message: &[u8]) -> Result<usize, String> {
+    // Notice: authentication parameters are followed by empty privacy parameters, then by the \
+    //   scoped PDU, which ends the message.
+    let mut body = read_element(&mut &message[..], TAG_SEQUENCE)?;
+
+    read_element(&mut body, TAG_INTEGER)?;
+    read_element(&mut body, TAG_SEQUENCE)?;
+    read_element(&mut body, TAG_OCTET_STRING)?;
+
+    Ok(body.len() + 2 + AUTH_PARAMETERS_SIZE)
+}
+
+fn make_request_id() -> Result<u32, String> {
+    let mut request_id = [0; 4];
+
+    rand_bytes(&mut request_id).map_err(|err| err.to_string())?;
+
+    // Notice: identifiers must be positive 32 bits signed integers
+    Ok(u32::from_be_bytes(request_id) & 0x7fffffff)
+}
+
+fn make_message_id() -> u32 {
+    make_request_id().unwrap_or(1)
+}
+
+fn encode_oid(oid: &str) -> Option<Vec<u8>> {
+    let arcs = oid
+        .split('.')
+        .map(|arc| arc.parse::<u32>().ok())
+        .collect::<Option<Vec<u32>>>()?;
+
+    if arcs.len() < 2 || arcs[0] > 2 || (arcs[0] < 2 && arcs[1] >= 40) {
+        return None;
+    }
+
+    // First two arcs are merged, while others are encoded in base 128
+    // Notice: the second arc is unbounded below the '2' root arc, thus merging might overflow.
+    let first_arc = arcs[0].checked_mul(40)?.checked_add(arcs[1])?;
+
+    let mut encoded = Vec::new();
+
+    for arc in std::iter::once(first_arc).chain(arcs[2..].iter().cloned()) {
+        let mut groups = vec![(arc & 0x7f) as u8];
+        let mut rest = arc >> 7;
+
+        while rest > 0 {
+            groups.push(0x80 | (rest & 0x7f) as u8);
+
+            rest >>= 7;
+        }
+
+        encoded.extend(groups.iter().rev());
+    }
+
+    Some(encoded)
+}
+
+fn encode_integer(value: u32) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let mut bytes = bytes[bytes.iter().take_while(|byte| **byte == 0).count()..].to_vec();
+
+    // Prepend a zero if the value would otherwise be negative
+    if bytes.first().map(|byte| byte & 0x80 != 0).unwrap_or(true) == true {
+        bytes.insert(0, 0);
+    }
+
+    encode(TAG_INTEGER, &bytes)
+}
+
+fn encode(tag: u8, value: &[u8]) -> Vec<u8> {
+    let mut element = vec![tag];
+
+    // Encode length (in short form if possible, otherwise in long form)
+    if value.len() < 0x80 {
+        element.push(value.len() as u8);
+    } else {
+        let length = (value.len() as u32).to_be_bytes();
+        let length = &length[length.iter().take_while(|byte| **byte == 0).count()..];
+
+        element.push(0x80 | length.len() as u8);
+        element.extend_from_slice(length);
+    }
+
+    element.extend_from_slice(value);
+
+    element
+}
+
+fn read_element<'a>(buffer: &mut &'a [u8], expected_tag: u8) -> Result<&'a [u8], String> {
+    match read_any_element(buffer) {
+        Some((tag, value)) if tag == expected_tag => Ok(value),
+        Some((tag, _)) => Err(format!("unexpected element tag: {}", tag)),
+        None => Err("invalid element".to_string()),
+    }
+}
+
+fn read_any_element<'a>(buffer: &mut &'a [u8]) -> Option<(u8, &'a [u8])> {
+    let (tag, head) = (*buffer.get(0)?, *buffer.get(1)?);
+
+    // Acquire length (which might be in long form)
+    let (length, offset) = if head & 0x80 == 0 {
+        (head as usize, 2)
+    } else {
+        let size = (head & 0x7f) as usize;
+
+        if size > 4 {
+            return None;
+        }
+
+        let length = buffer
+            .get(2..2 + size)?
+            .iter()
+            .fold(0, |value, byte| (value << 8) | *byte as usize);
+
+        (length, 2 + size)
+    };
+
+    let value = buffer.get(offset..offset + length)?;
+
+    *buffer = &buffer[offset + length..];
+
+    Some((tag, value))
+}
+
+fn decode_signed(value: &[u8]) -> i64 {
+    let initial = if value.first().map(|byte| byte & 0x80 != 0).unwrap_or(false) == true {
+        -1
+    } else {
+        0
+    };
+
+    value
+        .iter()
+        .take(8)
+        .fold(initial, |decoded, byte| (decoded << 8) | *byte as i64)
+}
+
+fn decode_unsigned(value: &[u8]) -> u32 {
+    decode_unsigned_long(value) as u32
+}
+
+fn decode_unsigned_long(value: &[u8]) -> u64 {
+    value
+        .iter()
+        .fold(0, |decoded, byte| (decoded << 8) | *byte as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_hex(value: &str) -> Vec<u8> {
+        (0..value.len())
+            .step_by(2)
+            .map(|index| u8::from_str_radix(&value[index..index + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn it_encodes_oids() {
+        assert_eq!(
+            encode_oid("1.3.6.1.2.1.1.3.0"),
+            Some(decode_hex("2b06010201010300"))
+        );
+        assert_eq!(
+            encode_oid("1.3.6.1.4.1.311"),
+            Some(decode_hex("2b060104018237"))
+        );
+        assert_eq!(encode_oid("2.999"), Some(decode_hex("8837")));
+    }
+
+    #[test]
+    fn it_rejects_invalid_oids() {
+        assert_eq!(encode_oid("1"), None);
+        assert_eq!(encode_oid("3.1"), None);
+        assert_eq!(encode_oid("1.40"), None);
+        assert_eq!(encode_oid("1.3.a"), None);
+        assert_eq!(encode_oid("2.4294967295"), None);
+    }
+
+    #[test]
+    fn it_localizes_keys() {
+        // See: RFC 3414, appendix A.3
+        let engine = decode_hex("000000000000000000000002");
+
+        assert_eq!(
+            localize_key(MessageDigest::md5(), "maplesyrup", &engine).unwrap(),
+            decode_hex("526f5eed9fcce26f8964c2930787d82b")
+        );
+        assert_eq!(
+            localize_key(MessageDigest::sha1(), "maplesyrup", &engine).unwrap(),
+            decode_hex("6695febc9288e36282235fc7151f128497b38f3f")
+        );
+    }
+
+    #[test]
+    fn it_signs_messages() {
+        // See: RFC 2202, test case 1 (digests are truncated to 96 bits)
+        assert_eq!(
+            sign(MessageDigest::md5(), &[0x0b; 16], b"Hi There").unwrap(),
+            decode_hex("9294727a3638bb1c13f48ef8")
+        );
+        assert_eq!(
+            sign(MessageDigest::sha1(), &[0x0b; 20], b"Hi There").unwrap(),
+            decode_hex("b617318655057264e28bc0b6")
+        );
+    }
+
+    #[test]
+    fn it_locates_authentication_parameters() {
+        let engine = Engine {
+            identifier: decode_hex("000000000000000000000002"),
+            boots: 1,
+            time: 300,
+        };
+
+        let key = localize_key(MessageDigest::sha1(), "maplesyrup", &engine.identifier).unwrap();
+
+        let mut message = make_v3_message(
+            &engine,
+            "vigil",
+            FLAG_AUTH | FLAG_REPORTABLE,
+            &make_get_request(1, Some(&encode_oid("1.3.6.1.2.1.1.3.0").unwrap())),
+        );
+
+        // Parameters are zeroed where the request signature gets written
+        let offset = message.len() - authentication_tail_size(&message).unwrap();
+
+        assert_eq!(message[offset..offset + AUTH_PARAMETERS_SIZE], [0; 12]);
+
+        let signature = sign(MessageDigest::sha1(), &key, &message).unwrap();
+
+        message[offset..offset + AUTH_PARAMETERS_SIZE].copy_from_slice(&signature);
+
+        // Parameters are read back at the same offset, and zeroing them restores the signature
+        let (parsed_engine, _, authentication_offset) = parse_v3_message(&message).unwrap();
+
+        assert_eq!(parsed_engine.identifier, engine.identifier);
+        assert_eq!(authentication_offset, Some(offset));
+        assert_eq!(
+            message[offset..offset + AUTH_PARAMETERS_SIZE],
+            signature[..]
+        );
+
+        message[offset..offset + AUTH_PARAMETERS_SIZE].fill(0);
+
+        assert_eq!(
+            sign(MessageDigest::sha1(), &key, &message).unwrap(),
+            signature
+        );
+    }
+}
@@ -0,0 +1,431 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use openssl::bn::{BigNum, BigNumContext};
+use openssl::derive::Deriver;
+use openssl::ec::{EcGroup, EcKey, EcPoint, PointConversionForm};
+use openssl::ecdsa::EcdsaSig;
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::{Id, PKey};
+use openssl::rand::rand_bytes;
+use openssl::rsa::Rsa;
+use openssl::sha::sha256;
+use openssl::sign::Verifier;
+
+use super::dns::Resolver;
+use super::status::Status;
+use crate::APP_CONF;
+
+const IDENTIFICATION: &'static str = "SSH-2.0-vigil";
+const IDENTIFICATION_MAXIMUM_LINES: usize = 32;
+const IDENTIFICATION_MAXIMUM_BYTES: u64 = 255;
+const PACKET_MAXIMUM_BYTES: usize = 35000;
+const PACKET_MAXIMUM_SKIPPED: usize = 32;
+const FINGERPRINT_PREFIX: &'static str = "SHA256:";
+
+const MESSAGE_DISCONNECT: u8 = 1;
+const MESSAGE_IGNORE: u8 = 2;
+const MESSAGE_DEBUG: u8 = 4;
+const MESSAGE_KEXINIT: u8 = 20;
+const MESSAGE_KEX_ECDH_INIT: u8 = 30;
+const MESSAGE_KEX_ECDH_REPLY: u8 = 31;
+
+const KEX_ALGORITHM: &'static str = "ecdh-sha2-nistp256";
+const CIPHER_ALGORITHM: &'static str = "aes128-ctr";
+const MAC_ALGORITHM: &'static str = "hmac-sha2-256";
+const COMPRESSION_ALGORITHM: &'static str = "none";
+
+// Notice: the server picks the first host key algorithm from this list that it supports, thus \
+//   configured fingerprints should match the host key of this type.
+const HOST_KEY_ALGORITHMS: &'static str =
+    "ssh-ed25519,ecdsa-sha2-nistp256,rsa-sha2-512,rsa-sha2-256";
+
+pub fn check(
+    host: &str,
+    port: u16,
+    host_key_fingerprint: &Option<String>,
+    resolver: Resolver,
+) -> Status {
+    match proceed_check(host, port, host_key_fingerprint, resolver) {
+        Ok(_) => Status::Healthy,
+        Err(err) => {
+            debug!(
+                "prober poll failed for ssh target: {}:{} (error: {})",
+                host, port, err
+            );
+
+            Status::Dead
+        }
+    }
+}
+
+fn proceed_check(
+    host: &str,
+    port: u16,
+    host_key_fingerprint: &Option<String>,
+    resolver: Resolver,
+) -> Result<(), String> {
+    let address = resolver
+        .lookup(host, port)
+        .map_err(|err| err.to_string())?
+        .next()
+        .ok_or("could not resolve address")?;
+
+    debug!("prober poll will fire for ssh target: {}", address);
+
+    let timeout = Duration::from_secs(APP_CONF.metrics.poll_delay_dead);
+    let stream = TcpStream::connect_timeout(&address, timeout).map_err(|err| err.to_string())?;
+
+    stream
+        .set_read_timeout(Some(timeout))
+        .and_then(|_| stream.set_write_timeout(Some(timeout)))
+        .map_err(|err| err.to_string())?;
+
+    let mut reader = BufReader::new(stream);
+
+    // Exchange identifications
+    reader
+        .get_mut()
+        .write_all(format!("{}\r\n", IDENTIFICATION).as_bytes())
+        .map_err(|err| err.to_string())?;
+
+    let server_identification = read_identification(&mut reader)?;
+
+    debug!(
+        "prober poll got identification for ssh target: {}: {}",
+        address, server_identification
+    );
+
+    // Check host key? (if configured)
+    if let Some(host_key_fingerprint) = host_key_fingerprint {
+        let host_key = exchange_keys(&mut reader, &server_identification)?;
+
+        let fingerprint = base64::encode_config(sha256(&host_key), base64::STANDARD_NO_PAD);
+
+        if fingerprint != normalize_fingerprint(host_key_fingerprint) {
+            return Err(format!(
+                "host key fingerprint mismatch: {}{}",
+                FINGERPRINT_PREFIX, fingerprint
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn read_identification<R: BufRead>(reader: &mut R) -> Result<String, String> {
+    // Notice: servers might send other lines before their identification
+    for _ in 0..IDENTIFICATION_MAXIMUM_LINES {
+        let mut line = String::new();
+
+        if reader
+            .by_ref()
+            .take(IDENTIFICATION_MAXIMUM_BYTES)
+            .read_line(&mut line)
+            .map_err(|err| err.to_string())?
+            == 0
+        {
+            return Err("connection closed before identification".to_string());
+        }
+
+        let line = line.trim_end();
+
+        if line.starts_with("SSH-") == true {
+            // Only SSH 2.0 is supported ('1.99' stands for SSH 2.0 with SSH 1 compatibility)
+            return if line.starts_with("SSH-2.0-") == true || line.starts_with("SSH-1.99-") == true
+            {
+                Ok(line.to_string())
+            } else {
+                Err(format!("unsupported protocol version: {}", line))
+            };
+        }
+    }
+
+    Err("identification not received".to_string())
+}
+
+fn exchange_keys<S: Read + Write>(
+    reader: &mut BufReader<S>,
+    server_identification: &str,
+) -> Result<Vec<u8>, String> {
+    // Send our key exchange initialization
+    let mut cookie = [0; 16];
+
+    rand_bytes(&mut cookie).map_err(|err| err.to_string())?;
+
+    let mut client_kexinit = vec![MESSAGE_KEXINIT];
+
+    client_kexinit.extend_from_slice(&cookie);
+
+    for algorithms in [
+        KEX_ALGORITHM,
+        HOST_KEY_ALGORITHMS,
+        CIPHER_ALGORITHM,
+        CIPHER_ALGORITHM,
+        MAC_ALGORITHM,
+        MAC_ALGORITHM,
+        COMPRESSION_ALGORITHM,
+        COMPRESSION_ALGORITHM,
+        "",
+        "",
+    ] {
+        push_string(&mut client_kexinit, algorithms.as_bytes());
+    }
+
+    client_kexinit.extend_from_slice(&[0; 5]);
+
+    write_packet(reader, &client_kexinit)?;
+
+    let server_kexinit = read_packet(reader, MESSAGE_KEXINIT)?;
+
+    // Perform an ECDH key exchange
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).map_err(|err| err.to_string())?;
+    let client_key = EcKey::generate(&group).map_err(|err| err.to_string())?;
+
+    let mut context = BigNumContext::new().map_err(|err| err.to_string())?;
+
+    let client_public = client_key
+        .public_key()
+        .to_bytes(&group, PointConversionForm::UNCOMPRESSED, &mut context)
+        .map_err(|err| err.to_string())?;
+
+    let mut ecdh_init = vec![MESSAGE_KEX_ECDH_INIT];
+
+    push_string(&mut ecdh_init, &client_public);
+
+    write_packet(reader, &ecdh_init)?;
+
+    let ecdh_reply = read_packet(reader, MESSAGE_KEX_ECDH_REPLY)?;
+
+    let mut cursor = &ecdh_reply[1..];
+
+    let (host_key, server_public, signature) = match (
+        read_string(&mut cursor),
+        read_string(&mut cursor),
+        read_string(&mut cursor),
+    ) {
+        (Some(host_key), Some(server_public), Some(signature)) => {
+            (host_key, server_public, signature)
+        }
+        _ => return Err("invalid key exchange reply".to_string()),
+    };
+
+    // Compute shared secret
+    let server_point =
+        EcPoint::from_bytes(&group, server_public, &mut context).map_err(|err| err.to_string())?;
+
+    let shared_secret = EcKey::from_public_key(&group, &server_point)
+        .and_then(PKey::from_ec_key)
+        .and_then(|server_key| {
+            let client_key = PKey::from_ec_key(client_key)?;
+            let mut deriver = Deriver::new(&client_key)?;
+
+            deriver.set_peer(&server_key)?;
+            deriver.derive_to_vec()
+        })
+        .map_err(|err| err.to_string())?;
+
+    // Compute exchange hash, then check that it was signed with the host key (this proves \
+    //   that the server owns the host key, and does not just present it)
+    let mut exchange = Vec::new();
+
+    push_string(&mut exchange, IDENTIFICATION.as_bytes());
+    push_string(&mut exchange, server_identification.as_bytes());
+    push_string(&mut exchange, &client_kexinit);
+    push_string(&mut exchange, &server_kexinit);
+    push_string(&mut exchange, host_key);
+    push_string(&mut exchange, &client_public);
+    push_string(&mut exchange, server_public);
+    push_mpint(&mut exchange, &shared_secret);
+
+    if verify_signature(host_key, signature, &sha256(&exchange))? == false {
+        return Err("invalid host key signature".to_string());
+    }
+
+    Ok(host_key.to_vec())
+}
+
+fn verify_signature(host_key: &[u8], signature: &[u8], data: &[u8]) -> Result<bool, String> {
+    let (mut host_key, mut signature) = (host_key, signature);
+
+    let (key_type, signature_type, signature_blob) = match (
+        read_string(&mut host_key),
+        read_string(&mut signature),
+        read_string(&mut signature),
+    ) {
+        (Some(key_type), Some(signature_type), Some(signature_blob)) => {
+            (key_type, signature_type, signature_blob)
+        }
+        _ => return Err("invalid host key or signature".to_string()),
+    };
+
+    let verified = match (key_type, signature_type) {
+        (b"ssh-ed25519", b"ssh-ed25519") => {
+            let public = read_string(&mut host_key).ok_or("invalid ed25519 host key")?;
+
+            PKey::public_key_from_raw_bytes(public, Id::ED25519)
+                .and_then(|key| {
+                    Verifier::new_without_digest(&key)?.verify_oneshot(signature_blob, data)
+                })
+                .map_err(|err| err.to_string())?
+        }
+        (b"ecdsa-sha2-nistp256", b"ecdsa-sha2-nistp256") => {
+            // Skip curve identifier
+            read_string(&mut host_key).ok_or("invalid ecdsa host key")?;
+
+            let public = read_string(&mut host_key).ok_or("invalid ecdsa host key")?;
+
+            let mut signature_blob = signature_blob;
+
+            let (r, s) = match (
+                read_string(&mut signature_blob),
+                read_string(&mut signature_blob),
+            ) {
+                (Some(r), Some(s)) => (r, s),
+                _ => return Err("invalid ecdsa signature".to_string()),
+            };
+
+            let group =
+                EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).map_err(|err| err.to_string())?;
+            let mut context = BigNumContext::new().map_err(|err| err.to_string())?;
+
+            EcPoint::from_bytes(&group, public, &mut context)
+                .and_then(|point| EcKey::from_public_key(&group, &point))
+                .and_then(|key| {
+                    EcdsaSig::from_private_components(
+                        BigNum::from_slice(r)?,
+                        BigNum::from_slice(s)?,
+                    )?
+                    .verify(&sha256(data), &key)
+                })
+                .map_err(|err| err.to_string())?
+        }
+        (b"ssh-rsa", b"rsa-sha2-256") | (b"ssh-rsa", b"rsa-sha2-512") => {
+            let (exponent, modulus) = match (read_string(&mut host_key), read_string(&mut host_key))
+            {
+                (Some(exponent), Some(modulus)) => (exponent, modulus),
+                _ => return Err("invalid rsa host key".to_string()),
+            };
+
+            let digest = if signature_type == b"rsa-sha2-512" {
+                MessageDigest::sha512()
+            } else {
+                MessageDigest::sha256()
+            };
+
+            BigNum::from_slice(modulus)
+                .and_then(|modulus| Ok((modulus, BigNum::from_slice(exponent)?)))
+                .and_then(|(modulus, exponent)| Rsa::from_public_components(modulus, exponent))
+                .and_then(PKey::from_rsa)
+                .and_then(|key| {
+                    let mut verifier = Verifier::new(digest, &key)?;
+
+                    verifier.update(data)?;
+                    verifier.verify(signature_blob)
+                })
+                .map_err(|err| err.to_string())?
+        }
+        _ => return Err("unsupported host key type".to_string()),
+    };
+
+    Ok(verified)
+}
+
+fn read_packet<S: Read>(
+    reader: &mut BufReader<S>,
+    expected_message: u8,
+) -> Result<Vec<u8>, String> {
+    // Skip informational messages (eg. debug messages)
+    for _ in 0..PACKET_MAXIMUM_SKIPPED {
+        let mut head = [0; 5];
+
+        reader
+            .read_exact(&mut head)
+            .map_err(|err| err.to_string())?;
+
+        let size = u32::from_be_bytes([head[0], head[1], head[2], head[3]]) as usize;
+
+        if size > PACKET_MAXIMUM_BYTES {
+            return Err("packet is too large".to_string());
+        }
+
+        // Read payload, then drop padding
+        let mut payload = vec![0; size.saturating_sub(1)];
+
+        reader
+            .read_exact(&mut payload)
+            .map_err(|err| err.to_string())?;
+
+        payload.truncate(payload.len().saturating_sub(head[4] as usize));
+
+        match payload.first() {
+            Some(message) if *message == expected_message => return Ok(payload),
+            Some(&MESSAGE_IGNORE) | Some(&MESSAGE_DEBUG) => {}
+            Some(&MESSAGE_DISCONNECT) => return Err("disconnected by server".to_string()),
+            _ => return Err(format!("unexpected message: {:?}", payload.first())),
+        }
+    }
+
+    Err("too many skipped packets".to_string())
+}
+
+fn write_packet<S: Write>(reader: &mut BufReader<S>, payload: &[u8]) -> Result<(), String> {
+    // Pad packet to a multiple of 8 bytes (with at least 4 bytes of padding)
+    let padding = 8 - (payload.len() + 5) % 8;
+    let padding = if padding < 4 { padding + 8 } else { padding };
+
+    let mut packet = Vec::with_capacity(payload.len() + padding + 5);
+
+    packet.extend_from_slice(&((payload.len() + padding + 1) as u32).to_be_bytes());
+    packet.push(padding as u8);
+    packet.extend_from_slice(payload);
+    packet.extend(std::iter::repeat(0).take(padding));
+
+    reader
+        .get_mut()
+        .write_all(&packet)
+        .map_err(|err| err.to_string())
+}
+
+fn read_string<'a>(cursor: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let size = cursor.get(0..4)?;
+    let size = u32::from_be_bytes([size[0], size[1], size[2], size[3]]) as usize;
+
+    let value = cursor.get(4..4 + size)?;
+
+    *cursor = &cursor[4 + size..];
+
+    Some(value)
+}
+
+fn push_string(buffer: &mut Vec<u8>, value: &[u8]) {
+    buffer.extend_from_slice(&(value.len() as u32).to_be_bytes());
+    buffer.extend_from_slice(value);
+}
+
+fn push_mpint(buffer: &mut Vec<u8>, value: &[u8]) {
+    // Strip leading zeros, and prepend a zero if the value would otherwise be negative
+    let value = &value[value.iter().take_while(|byte| **byte == 0).count()..];
+
+    if value.first().map(|byte| byte & 0x80 != 0).unwrap_or(false) == true {
+        push_string(buffer, &[&[0][..], value].concat());
+    } else {
+        push_string(buffer, value);
+    }
+}
+
+fn normalize_fingerprint(fingerprint: &str) -> &str {
+    // Accept fingerprints as printed by 'ssh-keygen -l' (eg. 'SHA256:...')
+    fingerprint
+        .trim()
+        .trim_start_matches(FINGERPRINT_PREFIX)
+        .trim_end_matches('=')
+}
@@ -4,16 +4,20 @@
 // Copyright: 2018, Valerian Saliou <valerian@valeriansaliou.name>
 // License: Mozilla Public License v2.0 (MPL v2.0)
 
+use std::net::SocketAddr;
+use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 
 use indexmap::IndexMap;
 
+use super::family::AddressFamily;
 use super::mode::Mode;
 use super::replica::ReplicaURL;
+use super::severity::Severity;
 use super::status::Status;
-use crate::config::{config::ConfigProbeServiceNodeHTTPMethod, regex::Regex};
+use crate::config::config::{ConfigProbeServiceNode, ConfigProbeServiceNodeScript};
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 pub struct ServiceStates {
     pub status: Status,
     pub date: Option<String>,
@@ -21,39 +25,97 @@ pub struct ServiceStates {
     pub notifier: ServiceStatesNotifier,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 pub struct ServiceStatesProbe {
     pub id: String,
     pub label: String,
     pub status: Status,
+    pub severity: Severity,
+    pub acknowledged: bool,
+    pub disabled: bool,
+    pub flapping: bool,
+    pub indirectly_affected: bool,
     pub nodes: IndexMap<String, ServiceStatesProbeNode>,
+
+    #[serde(skip_serializing)]
+    pub acknowledged_until: Option<SystemTime>,
+
+    #[serde(skip_serializing)]
+    pub disabled_until: Option<SystemTime>,
+
+    #[serde(skip_serializing)]
+    pub groups: Vec<String>,
+
+    #[serde(skip_serializing)]
+    pub depends_on: Vec<String>,
+
+    // Notice: flapping probes are held to the status they had when they started flapping for \
+    //   notification purposes, while recent transition times are used to detect flapping.
+    #[serde(skip_serializing)]
+    pub flapping_status: Option<Status>,
+    #[serde(skip_serializing)]
+    pub transitions: Vec<SystemTime>,
+
+    #[serde(skip_serializing)]
+    pub reporter_secret: Option<String>,
+
+    // Notice: discovered probes are not part of the configuration, thus their source must be \
+    //   known to tell them apart (eg. so that they are kept upon configuration reload).
+    #[serde(skip_serializing)]
+    pub discovered_by: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 pub struct ServiceStatesProbeNode {
     pub status: Status,
     pub label: String,
     pub mode: Mode,
     pub replicas: IndexMap<String, ServiceStatesProbeNodeReplica>,
-    #[serde(default)]
-    #[serde(with = "http_serde::header_map")]
-    pub http_headers: http::HeaderMap,
-    pub http_method: Option<ConfigProbeServiceNodeHTTPMethod>,
-    pub http_body: Option<String>,
-    pub http_body_healthy_match: Option<Regex>,
+
+    // Notice: node options might hold secrets (eg. authorization headers, passwords or \
+    //   transaction steps), thus they must never be exposed publicly (eg. via the JSON status \
+    //   API); they are shared with the node configuration, rather than copied over.
+    #[serde(skip_serializing)]
+    pub options: Arc<ConfigProbeServiceNode>,
+    #[serde(skip_serializing)]
+    pub latency_sick_ms: Option<u64>,
+    #[serde(skip_serializing)]
+    pub latency_dead_ms: Option<u64>,
+    #[serde(skip_serializing)]
+    pub retries: Option<u64>,
+    #[serde(skip_serializing)]
+    pub retry_delay: Option<u64>,
+    #[serde(skip_serializing)]
+    pub dns_resolver: Option<SocketAddr>,
+
+    // Notice: diagnostics reveal details about the internal network (eg. hop addresses), thus \
+    //   they are only exposed over the Manager API.
+    #[serde(skip_serializing)]
+    pub diagnostics: IndexMap<String, ServiceStatesProbeNodeDiagnostic>,
+
     pub rabbitmq: Option<ServiceStatesProbeNodeRabbitMQ>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 pub struct ServiceStatesProbeNodeReplica {
     pub status: Status,
     pub url: Option<ReplicaURL>,
-    pub script: Option<String>,
+    #[serde(skip_serializing)]
+    pub script: Option<ConfigProbeServiceNodeScript>,
+    #[serde(skip_serializing)]
+    pub family: AddressFamily,
     pub metrics: ServiceStatesProbeNodeReplicaMetrics,
+    pub message: Option<String>,
     pub load: Option<ServiceStatesProbeNodeReplicaLoad>,
     pub report: Option<ServiceStatesProbeNodeReplicaReport>,
 }
 
+#[derive(Clone)]
+pub struct ServiceStatesProbeNodeDiagnostic {
+    pub time: SystemTime,
+    pub output: String,
+}
+
 #[derive(Serialize, Clone)]
 pub struct ServiceStatesProbeNodeRabbitMQ {
     pub queue: String,
@@ -64,8 +126,10 @@ pub struct ServiceStatesProbeNodeRabbitMQ {
 #[derive(Serialize, Clone, Default)]
 pub struct ServiceStatesProbeNodeReplicaMetrics {
     pub latency: Option<u64>,
+    pub retries: Option<u64>,
     pub system: Option<ServiceStatesProbeNodeReplicaMetricsSystem>,
     pub rabbitmq: Option<ServiceStatesProbeNodeReplicaMetricsRabbitMQ>,
+    pub tls: Option<ServiceStatesProbeNodeReplicaMetricsTLS>,
 }
 
 #[derive(Serialize, Clone)]
@@ -80,7 +144,12 @@ pub struct ServiceStatesProbeNodeReplicaMetricsRabbitMQ {
     pub queue_nack: u32,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
+pub struct ServiceStatesProbeNodeReplicaMetricsTLS {
+    pub days_remaining: i64,
+}
+
+#[derive(Serialize, Clone)]
 pub struct ServiceStatesProbeNodeReplicaLoad {
     pub cpu: f32,
     pub ram: f32,
@@ -93,13 +162,13 @@ pub struct ServiceStatesProbeNodeReplicaLoadQueue {
     pub stalled: bool,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 pub struct ServiceStatesProbeNodeReplicaReport {
     pub time: SystemTime,
     pub interval: Duration,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 pub struct ServiceStatesNotifier {
     pub reminder_backoff_counter: u16,
 }
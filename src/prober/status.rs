@@ -4,6 +4,8 @@
 // Copyright: 2018, Valerian Saliou <valerian@valeriansaliou.name>
 // License: Mozilla Public License v2.0 (MPL v2.0)
 
+use std::str::FromStr;
+
 #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub enum Status {
     #[serde(rename = "healthy")]
@@ -33,3 +35,16 @@ impl Status {
         }
     }
 }
+
+impl FromStr for Status {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "healthy" => Ok(Status::Healthy),
+            "sick" => Ok(Status::Sick),
+            "dead" => Ok(Status::Dead),
+            _ => Err(()),
+        }
+    }
+}
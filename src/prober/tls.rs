@@ -0,0 +1,228 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::fs;
+use std::net::TcpStream;
+use std::time::Duration;
+
+use native_tls::{Identity, TlsConnector};
+use openssl::pkcs12::Pkcs12;
+use openssl::pkey::PKey;
+use openssl::stack::Stack;
+use openssl::x509::X509;
+use time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time};
+
+use super::dns::Resolver;
+
+const DER_TAG_CONTEXT_VERSION: u8 = 0xa0;
+const DER_TAG_UTC_TIME: u8 = 0x17;
+const DER_TAG_GENERALIZED_TIME: u8 = 0x18;
+
+const SECONDS_IN_DAY: i64 = 86400;
+
+const PEM_HEADER_PREFIX: &'static str = "-----BEGIN ";
+
+pub fn check_certificate(
+    host: &str,
+    port: u16,
+    timeout: Duration,
+    resolver: Resolver,
+    identity: Option<&[u8]>,
+) -> Result<i64, ()> {
+    let address = resolver.lookup(host, port).or(Err(()))?.next().ok_or(())?;
+
+    debug!("prober tls will check certificate for target: {}", address);
+
+    let stream = TcpStream::connect_timeout(&address, timeout).or(Err(()))?;
+
+    stream.set_read_timeout(Some(timeout)).or(Err(()))?;
+    stream.set_write_timeout(Some(timeout)).or(Err(()))?;
+
+    // Perform TLS handshake (this fails if the certificate chain is invalid, or expired)
+    let mut connector_builder = TlsConnector::builder();
+
+    // Present client certificate? (servers requiring mutual TLS would abort the handshake)
+    if let Some(identity) = identity {
+        connector_builder.identity(Identity::from_pkcs12(identity, "").or(Err(()))?);
+    }
+
+    let connector = connector_builder.build().or(Err(()))?;
+
+    let tls_stream = connector.connect(host, stream).map_err(|err| {
+        debug!(
+            "prober tls handshake failed for target: {} (error: {})",
+            address, err
+        );
+    })?;
+
+    // Acquire peer certificate expiry date
+    let certificate = tls_stream
+        .peer_certificate()
+        .or(Err(()))?
+        .ok_or(())?
+        .to_der()
+        .or(Err(()))?;
+
+    let not_after = parse_certificate_not_after(&certificate).ok_or(())?;
+
+    // Compute remaining validity days (may be negative, if the certificate already expired)
+    let days_remaining =
+        (not_after - OffsetDateTime::now_utc().unix_timestamp()).div_euclid(SECONDS_IN_DAY);
+
+    debug!(
+        "prober tls got certificate expiring in {} days for target: {}",
+        days_remaining, address
+    );
+
+    Ok(days_remaining)
+}
+
+pub fn read_pem(value: &str) -> Result<String, String> {
+    // Notice: values can either be inline PEM, or a path to a PEM file (which is read upon \
+    //   each use, so that renewed certificates get picked up without restarting)
+    if is_inline_pem(value) == true {
+        Ok(value.to_string())
+    } else {
+        fs::read_to_string(value).map_err(|err| format!("cannot read {}: {}", value, err))
+    }
+}
+
+pub fn is_inline_pem(value: &str) -> bool {
+    value.trim_start().starts_with(PEM_HEADER_PREFIX)
+}
+
+pub fn make_client_identity(certificate: &str, key: &str) -> Result<Vec<u8>, String> {
+    // Notice: the identity is bundled as PKCS#12, as this is the only format that both the \
+    //   HTTP client and the TLS connector accept whatever the private key format (PKCS#1, \
+    //   PKCS#8 or SEC1). Any certificate following the first one is an intermediate.
+    let mut certificates = X509::stack_from_pem(certificate.as_bytes())
+        .map_err(|err| format!("invalid certificate: {}", err))?
+        .into_iter();
+
+    let certificate = certificates
+        .next()
+        .ok_or("no certificate found".to_string())?;
+
+    let key = PKey::private_key_from_pem(key.as_bytes())
+        .map_err(|err| format!("invalid private key: {}", err))?;
+
+    if certificate
+        .public_key()
+        .map(|public_key| public_key.public_eq(&key))
+        .unwrap_or(false)
+        == false
+    {
+        return Err("private key does not match certificate".to_string());
+    }
+
+    let mut chain = Stack::new().map_err(|err| err.to_string())?;
+
+    for intermediate in certificates {
+        chain.push(intermediate).map_err(|err| err.to_string())?;
+    }
+
+    let mut builder = Pkcs12::builder();
+
+    builder.ca(chain);
+
+    builder
+        .build("", "vigil", &key, &certificate)
+        .and_then(|identity| identity.to_der())
+        .map_err(|err| format!("cannot bundle identity: {}", err))
+}
+
+fn parse_certificate_not_after(certificate: &[u8]) -> Option<i64> {
+    // Notice: this walks the DER-encoded X.509 certificate up to its validity period, which is \
+    //   the only value we are interested in. This avoids depending on a full X.509 parser. The \
+    //   structure is as follows: Certificate(TBSCertificate(version?, serialNumber, signature, \
+    //   issuer, validity(notBefore, notAfter), ...), ...).
+    let (_, certificate_start, _) = read_der_header(certificate, 0)?;
+    let (_, tbs_start, _) = read_der_header(certificate, certificate_start)?;
+
+    let mut offset = tbs_start;
+
+    // Skip version (if any, as it is optional)
+    let (tag, start, length) = read_der_header(certificate, offset)?;
+
+    if tag == DER_TAG_CONTEXT_VERSION {
+        offset = start + length;
+    }
+
+    // Skip serial number, signature algorithm and issuer
+    for _ in 0..3 {
+        let (_, start, length) = read_der_header(certificate, offset)?;
+
+        offset = start + length;
+    }
+
+    // Read validity period
+    let (_, validity_start, _) = read_der_header(certificate, offset)?;
+    let (_, not_before_start, not_before_length) = read_der_header(certificate, validity_start)?;
+    let (not_after_tag, not_after_start, not_after_length) =
+        read_der_header(certificate, not_before_start + not_before_length)?;
+
+    let not_after_value = std::str::from_utf8(
+        certificate.get(not_after_start..(not_after_start + not_after_length))?,
+    )
+    .ok()?;
+
+    parse_asn1_time(not_after_tag, not_after_value)
+}
+
+fn read_der_header(data: &[u8], offset: usize) -> Option<(u8, usize, usize)> {
+    let tag = *data.get(offset)?;
+    let length_byte = *data.get(offset + 1)?;
+
+    // Short form length?
+    if length_byte & 0x80 == 0 {
+        return Some((tag, offset + 2, length_byte as usize));
+    }
+
+    // Long form length
+    let length_size = (length_byte & 0x7f) as usize;
+    let mut length = 0;
+
+    for index in 0..length_size {
+        length = (length << 8) | (*data.get(offset + 2 + index)? as usize);
+    }
+
+    Some((tag, offset + 2 + length_size, length))
+}
+
+fn parse_asn1_time(tag: u8, value: &str) -> Option<i64> {
+    // Parse year (UTC times hold a 2-digit year, while generalized times hold a 4-digit year)
+    let (year, rest) = match tag {
+        DER_TAG_UTC_TIME => {
+            let year_short = value.get(0..2)?.parse::<i32>().ok()?;
+
+            (
+                if year_short >= 50 {
+                    1900 + year_short
+                } else {
+                    2000 + year_short
+                },
+                value.get(2..)?,
+            )
+        }
+        DER_TAG_GENERALIZED_TIME => (value.get(0..4)?.parse::<i32>().ok()?, value.get(4..)?),
+        _ => return None,
+    };
+
+    let month = rest.get(0..2)?.parse::<u8>().ok()?;
+    let day = rest.get(2..4)?.parse::<u8>().ok()?;
+    let hour = rest.get(4..6)?.parse::<u8>().ok()?;
+    let minute = rest.get(6..8)?.parse::<u8>().ok()?;
+    let second = rest.get(8..10)?.parse::<u8>().ok()?;
+
+    let date = Date::from_calendar_date(year, Month::try_from(month).ok()?, day).ok()?;
+    let time = Time::from_hms(hour, minute, second).ok()?;
+
+    Some(
+        PrimitiveDateTime::new(date, time)
+            .assume_utc()
+            .unix_timestamp(),
+    )
+}
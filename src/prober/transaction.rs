@@ -0,0 +1,172 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::collections::HashMap;
+
+use reqwest::blocking::Client;
+use reqwest::header::{HeaderMap, HeaderValue, COOKIE, SET_COOKIE};
+use reqwest::Method;
+
+use super::status::Status;
+use crate::config::config::{ConfigProbeServiceNodeHTTPMethod, ConfigProbeServiceNodeStep};
+use crate::APP_CONF;
+
+pub const TRANSACTION_REPLICA_ID: &'static str = "transaction";
+
+pub fn check(client: &Client, steps: &[ConfigProbeServiceNodeStep]) -> Status {
+    // Notice: variables are extracted from responses, while cookies are carried over from \
+    //   one step to the next, so that steps act as a single user session (eg. login first).
+    let mut variables = HashMap::new();
+    let mut cookies = HashMap::new();
+
+    for (index, step) in steps.iter().enumerate() {
+        if let Err(err) = proceed_step(client, step, &mut variables, &mut cookies) {
+            debug!(
+                "prober transaction failed at step #{} for url: {} (error: {})",
+                index, step.url, err
+            );
+
+            return Status::Dead;
+        }
+    }
+
+    Status::Healthy
+}
+
+fn proceed_step(
+    client: &Client,
+    step: &ConfigProbeServiceNodeStep,
+    variables: &mut HashMap<String, String>,
+    cookies: &mut HashMap<String, String>,
+) -> Result<(), String> {
+    let url = interpolate(&step.url, variables);
+
+    let method = match step.method {
+        Some(ConfigProbeServiceNodeHTTPMethod::Head) => Method::HEAD,
+        Some(ConfigProbeServiceNodeHTTPMethod::Get) | None => Method::GET,
+        Some(ConfigProbeServiceNodeHTTPMethod::Post) => Method::POST,
+        Some(ConfigProbeServiceNodeHTTPMethod::Put) => Method::PUT,
+        Some(ConfigProbeServiceNodeHTTPMethod::Patch) => Method::PATCH,
+        Some(ConfigProbeServiceNodeHTTPMethod::Delete) => Method::DELETE,
+        Some(ConfigProbeServiceNodeHTTPMethod::Options) => Method::OPTIONS,
+    };
+
+    // Build request headers (with variables, and cookies received in previous steps)
+    let mut headers = HeaderMap::new();
+
+    for (name, value) in step.headers.iter() {
+        let value = value.to_str().map_err(|err| err.to_string())?;
+
+        headers.insert(
+            name.to_owned(),
+            HeaderValue::from_str(&interpolate(value, variables)).map_err(|err| err.to_string())?,
+        );
+    }
+
+    if cookies.is_empty() == false {
+        let cookie = cookies
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<String>>()
+            .join("; ");
+
+        headers.insert(
+            COOKIE,
+            HeaderValue::from_str(&cookie).map_err(|err| err.to_string())?,
+        );
+    }
+
+    debug!(
+        "prober transaction will fire step for url: {} with method: {}",
+        url, method
+    );
+
+    let mut request = client.request(method, &url).headers(headers);
+
+    if let Some(ref body) = step.body {
+        request = request.body(interpolate(body, variables));
+    }
+
+    let response = request.send().map_err(|err| err.to_string())?;
+
+    // Check status code (defaults to the global healthy status range)
+    let status_code = response.status().as_u16();
+
+    let is_status_healthy = match step.status {
+        Some(status) => status_code == status,
+        None => {
+            status_code >= APP_CONF.metrics.poll_http_status_healthy_above
+                && status_code < APP_CONF.metrics.poll_http_status_healthy_below
+        }
+    };
+
+    if is_status_healthy == false {
+        return Err(format!("unexpected status: {}", status_code));
+    }
+
+    // Store cookies (attributes such as the path or the expiry are ignored)
+    for cookie in response.headers().get_all(SET_COOKIE) {
+        if let Some((name, value)) = cookie
+            .to_str()
+            .ok()
+            .and_then(|cookie| cookie.split(';').next())
+            .and_then(|cookie| cookie.split_once('='))
+        {
+            cookies.insert(name.trim().to_string(), value.trim().to_string());
+        }
+    }
+
+    // Check response body, and extract variables from it? (if configured)
+    if step.body_healthy_match.is_none() == true
+        && step.body_healthy_contains.is_none() == true
+        && step.extract.is_none() == true
+    {
+        return Ok(());
+    }
+
+    let text = response.text().map_err(|err| err.to_string())?;
+
+    if let Some(ref body_match_regex) = step.body_healthy_match {
+        if body_match_regex.is_match(&text) == false {
+            return Err("body does not match".to_string());
+        }
+    }
+
+    if let Some(ref body_contains) = step.body_healthy_contains {
+        if text.contains(body_contains.as_str()) == false {
+            return Err("body does not contain expected text".to_string());
+        }
+    }
+
+    if let Some(ref extract) = step.extract {
+        for (name, extract_regex) in extract.iter() {
+            // Extract first capture group (or the whole match, if the pattern has no group)
+            let captures = extract_regex
+                .captures(&text)
+                .ok_or(format!("could not extract variable: {}", name))?;
+
+            let value = captures
+                .get(1)
+                .or(captures.get(0))
+                .map(|value| value.as_str().to_string())
+                .unwrap_or_default();
+
+            variables.insert(name.to_owned(), value);
+        }
+    }
+
+    Ok(())
+}
+
+fn interpolate(template: &str, variables: &HashMap<String, String>) -> String {
+    let mut interpolated = template.to_string();
+
+    for (name, value) in variables.iter() {
+        interpolated = interpolated.replace(&format!("{{{{{}}}}}", name), value);
+    }
+
+    interpolated
+}
@@ -0,0 +1,300 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+use native_tls::TlsConnector;
+use openssl::rand::rand_bytes;
+use openssl::sha::sha1;
+use reqwest::header::HeaderMap;
+use url::Url;
+
+use super::dns::Resolver;
+use super::status::Status;
+use crate::APP_CONF;
+
+const HANDSHAKE_GUID: &'static str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+const HANDSHAKE_MAXIMUM_BYTES: usize = 8192;
+const PING_PAYLOAD: &'static [u8] = b"vigil";
+
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xa;
+
+pub fn check(
+    url: &str,
+    headers: &HeaderMap,
+    pong_timeout: Option<Duration>,
+    resolver: Resolver,
+) -> Status {
+    match proceed_check(url, headers, pong_timeout, resolver) {
+        Ok(_) => Status::Healthy,
+        Err(err) => {
+            debug!(
+                "prober poll failed for websocket target: {} (error: {})",
+                url, err
+            );
+
+            Status::Dead
+        }
+    }
+}
+
+fn proceed_check(
+    url: &str,
+    headers: &HeaderMap,
+    pong_timeout: Option<Duration>,
+    resolver: Resolver,
+) -> Result<(), String> {
+    let url = Url::parse(url).or(Err("invalid url"))?;
+
+    let (host, port) = match (url.host_str(), url.port_or_known_default()) {
+        (Some(host), Some(port)) => (host.trim_start_matches('[').trim_end_matches(']'), port),
+        _ => return Err("missing host or port".to_string()),
+    };
+
+    let address = resolver
+        .lookup(host, port)
+        .map_err(|err| err.to_string())?
+        .next()
+        .ok_or("could not resolve address")?;
+
+    debug!("prober poll will fire for websocket target: {}", address);
+
+    let timeout = Duration::from_secs(APP_CONF.metrics.poll_delay_dead);
+    let stream = TcpStream::connect_timeout(&address, timeout).map_err(|err| err.to_string())?;
+
+    stream
+        .set_read_timeout(Some(timeout))
+        .and_then(|_| stream.set_write_timeout(Some(timeout)))
+        .map_err(|err| err.to_string())?;
+
+    // Notice: keep a handle on the raw socket, so that its read timeout can still be changed \
+    //   once wrapped in a TLS stream (both handles refer to the same socket).
+    let socket = stream.try_clone().map_err(|err| err.to_string())?;
+
+    if url.scheme() == "wss" {
+        let connector = TlsConnector::new().map_err(|err| err.to_string())?;
+
+        let tls_stream = connector
+            .connect(host, stream)
+            .map_err(|err| err.to_string())?;
+
+        proceed_session(tls_stream, &socket, &url, headers, pong_timeout)
+    } else {
+        proceed_session(stream, &socket, &url, headers, pong_timeout)
+    }
+}
+
+fn proceed_session<S: Read + Write>(
+    stream: S,
+    socket: &TcpStream,
+    url: &Url,
+    headers: &HeaderMap,
+    pong_timeout: Option<Duration>,
+) -> Result<(), String> {
+    let mut reader = BufReader::new(stream);
+
+    // Perform upgrade handshake
+    let key = make_key()?;
+
+    reader
+        .get_mut()
+        .write_all(&make_handshake_request(url, headers, &key))
+        .map_err(|err| err.to_string())?;
+
+    check_handshake_response(&mut reader, &key)?;
+
+    // Send a ping, and expect a pong in return? (if configured)
+    if let Some(pong_timeout) = pong_timeout {
+        reader
+            .get_mut()
+            .write_all(&make_ping_frame()?)
+            .map_err(|err| err.to_string())?;
+
+        let deadline = Instant::now() + pong_timeout;
+
+        loop {
+            // Notice: the server might send other frames before the pong (eg. a greeting \
+            //   message), which are skipped until the deadline is reached.
+            let remaining = deadline
+                .checked_duration_since(Instant::now())
+                .filter(|remaining| remaining.is_zero() == false)
+                .ok_or("pong not received in time")?;
+
+            socket
+                .set_read_timeout(Some(remaining))
+                .map_err(|err| err.to_string())?;
+
+            match read_frame_opcode(&mut reader)? {
+                OPCODE_PONG => break,
+                OPCODE_CLOSE => return Err("connection closed before pong".to_string()),
+                _ => {}
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn make_key() -> Result<String, String> {
+    let mut nonce = [0; 16];
+
+    rand_bytes(&mut nonce).map_err(|err| err.to_string())?;
+
+    Ok(base64::encode(nonce))
+}
+
+fn make_handshake_request(url: &Url, headers: &HeaderMap, key: &str) -> Vec<u8> {
+    let mut path = url.path().to_string();
+
+    if let Some(query) = url.query() {
+        path.push('?');
+        path.push_str(query);
+    }
+
+    // Only mention port in host if it is not the default one
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+
+    let mut request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+            Sec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\nUser-Agent: vigil (+{})\r\n",
+        path,
+        host,
+        key,
+        APP_CONF.branding.page_url.as_str()
+    )
+    .into_bytes();
+
+    for (name, value) in headers.iter() {
+        request.extend_from_slice(name.as_str().as_bytes());
+        request.extend_from_slice(b": ");
+        request.extend_from_slice(value.as_bytes());
+        request.extend_from_slice(b"\r\n");
+    }
+
+    request.extend_from_slice(b"\r\n");
+
+    request
+}
+
+fn check_handshake_response<R: BufRead>(reader: &mut R, key: &str) -> Result<(), String> {
+    let mut lines = Vec::new();
+    let mut size = 0;
+
+    // Read response head, up to the empty line
+    loop {
+        let mut line = String::new();
+
+        size += reader.read_line(&mut line).map_err(|err| err.to_string())?;
+
+        if line.is_empty() == true || size > HANDSHAKE_MAXIMUM_BYTES {
+            return Err("incomplete handshake response".to_string());
+        }
+
+        let line = line.trim_end().to_string();
+
+        if line.is_empty() == true {
+            break;
+        }
+
+        lines.push(line);
+    }
+
+    // Check that the server switched protocols
+    let status_line = lines.first().ok_or("empty handshake response")?;
+
+    if status_line.split_whitespace().nth(1) != Some("101") {
+        return Err(format!("upgrade refused: {}", status_line));
+    }
+
+    // Check that the server acknowledged our key (this proves that the server speaks WebSocket)
+    let expected_accept = base64::encode(sha1(format!("{}{}", key, HANDSHAKE_GUID).as_bytes()));
+
+    let accept = lines.iter().skip(1).find_map(|line| {
+        line.split_once(':').and_then(|(name, value)| {
+            if name.trim().eq_ignore_ascii_case("sec-websocket-accept") == true {
+                Some(value.trim())
+            } else {
+                None
+            }
+        })
+    });
+
+    if accept != Some(expected_accept.as_str()) {
+        return Err("invalid handshake accept key".to_string());
+    }
+
+    Ok(())
+}
+
+fn make_ping_frame() -> Result<Vec<u8>, String> {
+    // Notice: frames sent by clients must be masked
+    let mut mask = [0; 4];
+
+    rand_bytes(&mut mask).map_err(|err| err.to_string())?;
+
+    let mut frame = vec![0x80 | OPCODE_PING, 0x80 | PING_PAYLOAD.len() as u8];
+
+    frame.extend_from_slice(&mask);
+
+    frame.extend(
+        PING_PAYLOAD
+            .iter()
+            .enumerate()
+            .map(|(index, byte)| byte ^ mask[index % 4]),
+    );
+
+    Ok(frame)
+}
+
+fn read_frame_opcode<R: Read>(reader: &mut R) -> Result<u8, String> {
+    let mut head = [0; 2];
+
+    reader
+        .read_exact(&mut head)
+        .map_err(|err| err.to_string())?;
+
+    // Acquire payload size (which might be extended over the next bytes)
+    let size = match head[1] & 0x7f {
+        126 => {
+            let mut size = [0; 2];
+
+            reader
+                .read_exact(&mut size)
+                .map_err(|err| err.to_string())?;
+
+            u16::from_be_bytes(size) as u64
+        }
+        127 => {
+            let mut size = [0; 8];
+
+            reader
+                .read_exact(&mut size)
+                .map_err(|err| err.to_string())?;
+
+            u64::from_be_bytes(size)
+        }
+        size => size as u64,
+    };
+
+    // Skip mask (if any) and payload, as only the opcode matters here
+    let skip = size + if head[1] & 0x80 != 0 { 4 } else { 0 };
+
+    let skipped = std::io::copy(&mut reader.take(skip), &mut std::io::sink())
+        .map_err(|err| err.to_string())?;
+
+    if skipped < skip {
+        return Err("truncated frame".to_string());
+    }
+
+    Ok(head[0] & 0x0f)
+}
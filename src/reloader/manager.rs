@@ -0,0 +1,123 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::sync::{Mutex, RwLock};
+use std::time::SystemTime;
+
+use signal_hook::consts::SIGHUP;
+use signal_hook::iterator::Signals;
+
+use crate::aggregator::manager::reload_channels as reload_channels_aggregator;
+use crate::config::config::ConfigServer;
+use crate::config::reader::{ConfigError, ConfigReader};
+use crate::prober::manager::reload_store as reload_store_prober;
+use crate::APP_CONF;
+
+lazy_static! {
+    static ref RELOAD_LOCK: Mutex<()> = Mutex::new(());
+    pub static ref LAST_RELOAD: RwLock<Option<LastReload>> = RwLock::new(None);
+}
+
+pub struct LastReload {
+    pub at: SystemTime,
+    pub failed: bool,
+}
+
+pub struct Reloaded {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+pub enum ReloadError {
+    InvalidConfig(Vec<ConfigError>),
+}
+
+pub fn reload() -> Result<Reloaded, ReloadError> {
+    // Prevent concurrent reloads (eg. a signal received while reloading over the API)
+    let _lock = RELOAD_LOCK.lock().unwrap();
+
+    info!("reloading configuration...");
+
+    // Read new configuration (the running configuration is kept if it is invalid)
+    let config = ConfigReader::read().map_err(|errors| {
+        error!(
+            "could not reload configuration: {}",
+            ConfigError::describe_all(&errors)
+        );
+
+        mark_reload(true);
+
+        ReloadError::InvalidConfig(errors)
+    })?;
+
+    // Warn about server settings that cannot be reloaded (the HTTP server keeps running with \
+    //   the settings it was started with, until Vigil gets restarted)
+    let unreloadable_changes = list_unreloadable_changes(&APP_CONF.server, &config.server);
+
+    if unreloadable_changes.is_empty() == false {
+        warn!(
+            "changed server settings require a restart to be applied: {}",
+            unreloadable_changes.join(", ")
+        );
+    }
+
+    APP_CONF.replace(config);
+
+    // Apply new configuration to running states
+    // Notice: notifiers read their configuration upon each dispatch, thus forgetting about \
+    //   removed channels is enough to reinitialize them.
+    let (added, removed) = reload_store_prober();
+
+    reload_channels_aggregator();
+
+    mark_reload(false);
+
+    info!("reloaded configuration");
+
+    Ok(Reloaded {
+        added: added,
+        removed: removed,
+    })
+}
+
+fn list_unreloadable_changes(current: &ConfigServer, config: &ConfigServer) -> Vec<&'static str> {
+    let mut changes = Vec::new();
+
+    if current.inet != config.inet {
+        changes.push("server.inet");
+    }
+    if current.workers != config.workers {
+        changes.push("server.workers");
+    }
+    if current.tls != config.tls {
+        changes.push("server.tls");
+    }
+    if current.base_path != config.base_path {
+        changes.push("server.base_path");
+    }
+    if current.cors != config.cors {
+        changes.push("server.cors");
+    }
+
+    changes
+}
+
+fn mark_reload(failed: bool) {
+    *LAST_RELOAD.write().unwrap() = Some(LastReload {
+        at: SystemTime::now(),
+        failed: failed,
+    });
+}
+
+pub fn run() {
+    let mut signals = Signals::new(&[SIGHUP]).expect("could not listen for reload signal");
+
+    for _ in signals.forever() {
+        debug!("received reload signal");
+
+        reload().ok();
+    }
+}
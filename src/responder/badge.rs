@@ -0,0 +1,113 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use crate::prober::status::Status;
+use crate::APP_CONF;
+
+// Notice: text widths are estimated (a rough average for 11px Verdana), as we do not have font \
+//   metrics at hand; this is good enough for short labels.
+const TEXT_CHARACTER_WIDTH: usize = 7;
+const TEXT_PADDING: usize = 10;
+
+const UPTIME_HEALTHY_ABOVE: f64 = 99.0;
+const UPTIME_SICK_ABOVE: f64 = 95.0;
+
+pub static UPTIME_WINDOWS: &'static [&'static str] = &["24h", "7d", "30d", "90d"];
+
+pub fn render_status(kind: &str, label: &str, status: &Status) -> Option<String> {
+    let color = status_to_color(status);
+
+    match kind {
+        "color" => Some(render_color(color)),
+        "icon" => Some(render_icon(status, color)),
+        "text" => Some(render_text(label, status.as_str(), color)),
+        _ => None,
+    }
+}
+
+pub fn render_uptime(label: &str, uptime: Option<f64>) -> String {
+    match uptime {
+        Some(uptime) => {
+            let color = if uptime >= UPTIME_HEALTHY_ABOVE {
+                status_to_color(&Status::Healthy)
+            } else if uptime >= UPTIME_SICK_ABOVE {
+                status_to_color(&Status::Sick)
+            } else {
+                status_to_color(&Status::Dead)
+            };
+
+            render_text(label, &format!("{:.2}%", uptime), color)
+        }
+        None => render_text(label, "unknown", &APP_CONF.branding.badge_color_unknown),
+    }
+}
+
+fn render_color(color: &str) -> String {
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="20" viewBox="0 0 20 20"><rect fill="{}" width="20" height="20"/></svg>"##,
+        escape(color)
+    )
+}
+
+fn render_icon(status: &Status, color: &str) -> String {
+    let mark = match status {
+        &Status::Healthy => {
+            r##"<path d="M5.25,10.5 L8.25,13.5 L14.75,7" stroke="#FFFFFF" stroke-width="2.5" stroke-linecap="square" fill="none"/>"##
+        }
+        &Status::Sick => {
+            r##"<path d="M10,4.75 L10,11.25" stroke="#FFFFFF" stroke-width="3" fill="none"/><circle cx="10" cy="14.75" r="1.6" fill="#FFFFFF"/>"##
+        }
+        &Status::Dead => {
+            r##"<path d="M6,6 L14,14 M14,6 L6,14" stroke="#FFFFFF" stroke-width="2.5" stroke-linecap="square" fill="none"/>"##
+        }
+    };
+
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="20" viewBox="0 0 20 20"><rect fill="{}" width="20" height="20" rx="2"/>{}</svg>"##,
+        escape(color),
+        mark
+    )
+}
+
+fn render_text(label: &str, message: &str, color: &str) -> String {
+    let label_width = text_width(label);
+    let message_width = text_width(message);
+    let width = label_width + message_width;
+
+    let (label, message, color) = (escape(label), escape(message), escape(color));
+
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{label}: {message}"><title>{label}: {message}</title><linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#BBBBBB" stop-opacity=".1"/><stop offset="1" stop-opacity=".1"/></linearGradient><clipPath id="r"><rect width="{width}" height="20" rx="3" fill="#FFFFFF"/></clipPath><g clip-path="url(#r)"><rect width="{label_width}" height="20" fill="#555555"/><rect x="{label_width}" width="{message_width}" height="20" fill="{color}"/><rect width="{width}" height="20" fill="url(#s)"/></g><g fill="#FFFFFF" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11"><text x="{label_x}" y="14">{label}</text><text x="{message_x}" y="14">{message}</text></g></svg>"##,
+        width = width,
+        label = label,
+        message = message,
+        color = color,
+        label_width = label_width,
+        message_width = message_width,
+        label_x = label_width / 2,
+        message_x = label_width + message_width / 2
+    )
+}
+
+fn text_width(text: &str) -> usize {
+    text.chars().count() * TEXT_CHARACTER_WIDTH + TEXT_PADDING
+}
+
+fn status_to_color(status: &Status) -> &'static str {
+    match status {
+        &Status::Healthy => &APP_CONF.branding.badge_color_healthy,
+        &Status::Sick => &APP_CONF.branding.badge_color_sick,
+        &Status::Dead => &APP_CONF.branding.badge_color_dead,
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
@@ -0,0 +1,135 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::str::FromStr;
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use actix_web::dev::HttpResponseBuilder;
+use actix_web::http::header::{HttpDate, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use actix_web::{web::Bytes, HttpRequest, HttpResponse};
+
+use crate::prober::manager::StoreSnapshot;
+
+struct CachedRender {
+    revision: u64,
+    body: Bytes,
+}
+
+lazy_static! {
+    static ref INDEX_CACHE: RwLock<Option<CachedRender>> = RwLock::new(None);
+
+    // Notice: snapshot revisions restart from zero upon each boot, thus entity tags are \
+    //   prefixed with the boot time, so that tags from a previous run never match.
+    static ref BOOT_NONCE: String = format!(
+        "{:x}",
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or(0)
+    );
+}
+
+pub fn render_index<E>(
+    snapshot: &StoreSnapshot,
+    render: impl FnOnce() -> Result<String, E>,
+) -> Result<Bytes, E> {
+    // Serve cached render? (only if rendered from the current snapshot)
+    if let Some(ref cached) = *INDEX_CACHE.read().unwrap() {
+        if cached.revision == snapshot.revision {
+            return Ok(cached.body.clone());
+        }
+    }
+
+    // Notice: concurrent requests might render the same snapshot twice, which is harmless; this \
+    //   avoids holding the cache lock while the template renders.
+    let body = Bytes::from(render()?);
+
+    debug!(
+        "rendered index for snapshot revision: {}",
+        snapshot.revision
+    );
+
+    let mut cache = INDEX_CACHE.write().unwrap();
+
+    if cache
+        .as_ref()
+        .map(|cached| cached.revision < snapshot.revision)
+        .unwrap_or(true)
+        == true
+    {
+        *cache = Some(CachedRender {
+            revision: snapshot.revision,
+            body: body.clone(),
+        });
+    }
+
+    Ok(body)
+}
+
+pub fn is_fresh(request: &HttpRequest, snapshot: &StoreSnapshot) -> bool {
+    let headers = request.headers();
+
+    // Notice: 'If-None-Match' takes precedence over 'If-Modified-Since' when both are sent
+    if let Some(if_none_match) = headers.get(IF_NONE_MATCH) {
+        let etag = make_etag(snapshot);
+
+        return if_none_match
+            .to_str()
+            .map(|if_none_match| {
+                if_none_match
+                    .split(',')
+                    .map(|tag| tag.trim().trim_start_matches("W/"))
+                    .any(|tag| tag == "*" || tag == etag)
+            })
+            .unwrap_or(false);
+    }
+
+    if let Some(if_modified_since) = headers.get(IF_MODIFIED_SINCE) {
+        let if_modified_since = if_modified_since
+            .to_str()
+            .ok()
+            .and_then(|if_modified_since| HttpDate::from_str(if_modified_since).ok())
+            .map(SystemTime::from);
+
+        if let Some(if_modified_since) = if_modified_since {
+            return truncate_to_seconds(snapshot.published_at) <= if_modified_since;
+        }
+    }
+
+    false
+}
+
+pub fn not_modified(snapshot: &StoreSnapshot) -> HttpResponse {
+    with_validators(&mut HttpResponse::NotModified(), snapshot).finish()
+}
+
+pub fn with_validators<'a>(
+    builder: &'a mut HttpResponseBuilder,
+    snapshot: &StoreSnapshot,
+) -> &'a mut HttpResponseBuilder {
+    builder.header(ETAG, make_etag(snapshot)).header(
+        LAST_MODIFIED,
+        HttpDate::from(snapshot.published_at).to_string(),
+    )
+}
+
+fn make_etag(snapshot: &StoreSnapshot) -> String {
+    // Notice: responses are generated from the snapshot, thus its revision identifies them; \
+    //   contents served along with states (eg. incidents and uptime) flag the snapshot as \
+    //   outdated whenever they change, which bumps its revision.
+    format!("\"{}-{}\"", *BOOT_NONCE, snapshot.revision)
+}
+
+fn truncate_to_seconds(time: SystemTime) -> SystemTime {
+    // HTTP dates have a precision of 1 second
+    UNIX_EPOCH
+        + Duration::from_secs(
+            time.duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0),
+        )
+}
@@ -9,6 +9,8 @@ use time;
 use url_serde::SerdeUrl;
 
 use crate::prober::states::ServiceStates;
+use crate::storage::incidents::Incident;
+use crate::storage::uptime::Uptime;
 use crate::APP_CONF;
 
 const LOGO_EXTENSION_SPLIT_SPAN: usize = 4;
@@ -16,6 +18,7 @@ const LOGO_EXTENSION_SPLIT_SPAN: usize = 4;
 lazy_static! {
     pub static ref INDEX_CONFIG: IndexContextConfig = IndexContextConfig {
         runtime_version: env!("CARGO_PKG_VERSION").to_string(),
+        base_path: APP_CONF.server.base_path.trim_end_matches('/').to_string(),
         page_title: APP_CONF.branding.page_title.to_owned(),
         company_name: APP_CONF.branding.company_name.to_owned(),
         icon_color: APP_CONF.branding.icon_color.to_owned(),
@@ -74,13 +77,42 @@ impl Default for IndexContextEnvironment {
 #[derive(Serialize)]
 pub struct IndexContext<'a, 'b> {
     pub states: &'a ServiceStates,
+    pub uptime: Option<Uptime>,
+    pub incidents: Vec<Incident>,
     pub environment: &'a IndexContextEnvironment,
     pub config: &'b IndexContextConfig,
 }
 
+#[derive(Serialize)]
+pub struct AcknowledgeContext<'a> {
+    pub probe_id: &'a str,
+    pub state: AcknowledgeContextState,
+    pub acknowledged_until: Option<String>,
+    pub config: &'a IndexContextConfig,
+}
+
+#[derive(Serialize)]
+pub enum AcknowledgeContextState {
+    #[serde(rename = "confirm")]
+    Confirm,
+
+    #[serde(rename = "acknowledged")]
+    Acknowledged,
+
+    #[serde(rename = "expired")]
+    Expired,
+
+    #[serde(rename = "invalid")]
+    Invalid,
+
+    #[serde(rename = "not_found")]
+    NotFound,
+}
+
 #[derive(Serialize)]
 pub struct IndexContextConfig {
     pub runtime_version: String,
+    pub base_path: String,
     pub page_title: String,
     pub company_name: String,
     pub icon_color: String,
@@ -0,0 +1,127 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::notifier::queue;
+use crate::prober::manager::POLL_HEARTBEAT;
+use crate::reloader::manager::LAST_RELOAD;
+use crate::APP_CONF;
+
+const POLL_STALE_FACTOR: u64 = 3;
+const POLL_STALE_MINIMUM_SECONDS: u64 = 60;
+
+#[derive(Serialize)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    responder: HealthStatus,
+    prober: HealthProber,
+    notifier: HealthNotifier,
+    config: HealthConfig,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq)]
+pub enum HealthStatus {
+    #[serde(rename = "ok")]
+    Ok,
+
+    #[serde(rename = "starting")]
+    Starting,
+
+    #[serde(rename = "stale")]
+    Stale,
+}
+
+#[derive(Serialize)]
+struct HealthProber {
+    status: HealthStatus,
+    heartbeat_age: Option<u64>,
+    cycle_duration: Option<u64>,
+    stale_after: u64,
+}
+
+#[derive(Serialize)]
+struct HealthNotifier {
+    queue_depth: usize,
+}
+
+#[derive(Serialize)]
+struct HealthConfig {
+    status: &'static str,
+    reloaded_at: Option<u64>,
+}
+
+pub fn report() -> HealthReport {
+    // Check poll loop heartbeat
+    // Notice: the heartbeat is considered stale once a few poll cycles were missed, where the \
+    //   duration of the last cycle is accounted for, as a cycle might take longer than its \
+    //   interval (eg. if many replicas time out).
+    let (prober_status, heartbeat_age, cycle_duration, stale_after) =
+        match *POLL_HEARTBEAT.read().unwrap() {
+            Some(ref heartbeat) => {
+                let heartbeat_age = heartbeat
+                    .completed_at
+                    .elapsed()
+                    .unwrap_or_default()
+                    .as_secs();
+                let cycle_duration = heartbeat.duration.as_secs();
+
+                let stale_after = (POLL_STALE_FACTOR
+                    * (APP_CONF.metrics.poll_interval + cycle_duration))
+                    .max(POLL_STALE_MINIMUM_SECONDS);
+
+                (
+                    if heartbeat_age > stale_after {
+                        HealthStatus::Stale
+                    } else {
+                        HealthStatus::Ok
+                    },
+                    Some(heartbeat_age),
+                    Some(cycle_duration),
+                    stale_after,
+                )
+            }
+            None => (HealthStatus::Starting, None, None, 0),
+        };
+
+    // Check configuration (a configuration that failed to reload does not replace the running \
+    //   configuration, which is still valid)
+    let config = match *LAST_RELOAD.read().unwrap() {
+        Some(ref last_reload) => HealthConfig {
+            status: if last_reload.failed == true {
+                "reload_failed"
+            } else {
+                "reloaded"
+            },
+            reloaded_at: Some(as_timestamp(last_reload.at)),
+        },
+        None => HealthConfig {
+            status: "loaded",
+            reloaded_at: None,
+        },
+    };
+
+    HealthReport {
+        status: prober_status,
+        responder: HealthStatus::Ok,
+        prober: HealthProber {
+            status: prober_status,
+            heartbeat_age: heartbeat_age,
+            cycle_duration: cycle_duration,
+            stale_after: stale_after,
+        },
+        notifier: HealthNotifier {
+            queue_depth: queue::depth(),
+        },
+        config: config,
+    }
+}
+
+fn as_timestamp(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
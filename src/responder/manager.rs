@@ -4,9 +4,15 @@
 // Copyright: 2021, Valerian Saliou <valerian@valeriansaliou.name>
 // License: Mozilla Public License v2.0 (MPL v2.0)
 
+use std::time::Instant;
+
+use actix_cors::Cors;
+use actix_web::dev::{BodyEncoding, Service};
 use actix_web::{
     dev::ServiceRequest,
     guard,
+    http::header::ETAG,
+    http::ContentEncoding,
     middleware::{self, normalize::TrailingSlash},
     rt, web, App, Error as ActixError, HttpServer,
 };
@@ -17,9 +23,14 @@ use actix_web_httpauth::{
     },
     middleware::HttpAuthentication,
 };
+use futures::FutureExt;
 use tera::Tera;
 
+use super::ratelimit::{self, RateLimitKind};
 use super::routes;
+use super::tls;
+use crate::config::logger::{ConfigLogger, LogRequest};
+use crate::tracer::span::{Span, SpanContext, SpanKind};
 use crate::APP_CONF;
 
 pub fn run() {
@@ -37,45 +48,375 @@ pub fn run() {
         .into();
 
     let tera = Tera::new(&templates).unwrap();
-    let middleware_auth = HttpAuthentication::basic(authenticate);
+    let middleware_auth = HttpAuthentication::basic(authenticate_reporter);
+    let middleware_manager_auth = HttpAuthentication::basic(authenticate_manager);
+
+    // Notice: routes are mounted under the base path (if any), though the index is mounted at the \
+    //   base path itself, as a scope does not match its own prefix (trailing slashes get trimmed).
+    let base_path = APP_CONF.server.base_path.trim_end_matches('/').to_string();
+
+    let index_path = if base_path.is_empty() == true {
+        "/".to_string()
+    } else {
+        base_path.to_owned()
+    };
 
     let server = HttpServer::new(move || {
         App::new()
             .data(tera.clone())
+            // Notice: the compression middleware is always mounted, as a conditional middleware \
+            //   cannot change the response body type; when compression is disabled, responses \
+            //   are marked to be sent as-is instead.
+            .wrap_fn(|request, service| {
+                service.call(request).map(|result| {
+                    result.map(|mut response| {
+                        if APP_CONF.server.compress == false {
+                            response.response_mut().encoding(ContentEncoding::Identity);
+                        }
+
+                        response
+                    })
+                })
+            })
+            .wrap(middleware::Compress::default())
             .wrap(middleware::NormalizePath::new(TrailingSlash::Trim))
-            .service(routes::assets_javascripts)
-            .service(routes::assets_stylesheets)
-            .service(routes::assets_images)
-            .service(routes::assets_fonts)
-            .service(routes::badge)
-            .service(routes::status_text)
-            .service(routes::robots)
-            .service(routes::index)
-            .data(ConfigAuth::default().realm("Reporter Token"))
+            .wrap_fn(|request, service| {
+                // Log and trace request once responded (with its status and latency)
+                let started_at = Instant::now();
+
+                let (method, path, peer) = (
+                    request.method().to_string(),
+                    request.path().to_string(),
+                    request.peer_addr().map(|address| address.ip().to_string()),
+                );
+
+                // Notice: the trace of the caller is continued if it provides a W3C trace context
+                let span_name = format!("HTTP {}", method);
+
+                let mut span = match request
+                    .headers()
+                    .get("traceparent")
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(SpanContext::from_traceparent)
+                {
+                    Some(parent) => Span::child(&parent, &span_name, SpanKind::Server),
+                    None => Span::root(&span_name, SpanKind::Server),
+                };
+
+                span.set_attribute("http.method", method.as_str());
+                span.set_attribute("http.target", path.as_str());
+
+                if let Some(ref peer) = peer {
+                    span.set_attribute("net.peer.ip", peer.as_str());
+                }
+
+                service.call(request).map(move |result| {
+                    let status = match result {
+                        Ok(ref response) => response.status(),
+                        Err(ref err) => err.as_response_error().status_code(),
+                    };
+
+                    span.set_attribute("http.status_code", status.as_u16());
+
+                    if status.is_server_error() == true {
+                        span.set_error(status.canonical_reason().unwrap_or("server error"));
+                    }
+
+                    span.end();
+
+                    ConfigLogger::log_request(LogRequest {
+                        method: &method,
+                        path: &path,
+                        status: status.as_u16(),
+                        latency_ms: started_at.elapsed().as_millis() as u64,
+                        peer: peer,
+                    });
+
+                    result
+                })
+            })
             .service(
-                web::resource("/reporter/{probe_id}/{node_id}")
-                    .wrap(middleware_auth.clone())
-                    .guard(guard::Post())
-                    .to(routes::reporter_report),
+                web::resource(index_path.as_str())
+                    .wrap_fn(ratelimit::limit(RateLimitKind::Public))
+                    .guard(guard::Get())
+                    .to(routes::index),
             )
             .service(
-                web::resource("/reporter/{probe_id}/{node_id}/{replica_id}")
-                    .wrap(middleware_auth.clone())
-                    .guard(guard::Delete())
-                    .to(routes::reporter_flush),
+                web::scope(&base_path)
+                    .service(routes::assets_javascripts)
+                    .service(routes::assets_stylesheets)
+                    .service(routes::assets_images)
+                    .service(routes::assets_fonts)
+                    .service(
+                        web::scope("/badge")
+                            .wrap_fn(ratelimit::limit(RateLimitKind::Public))
+                            .wrap(middleware::Condition::new(
+                                APP_CONF.server.cors.is_some(),
+                                make_cors(),
+                            ))
+                            .service(routes::badge_uptime)
+                            .service(routes::badge_uptime_probe)
+                            .service(routes::badge_probe)
+                            .service(routes::badge),
+                    )
+                    .service(
+                        web::scope("/status")
+                            .wrap_fn(ratelimit::limit(RateLimitKind::Public))
+                            .wrap(middleware::Condition::new(
+                                APP_CONF.server.cors.is_some(),
+                                make_cors(),
+                            ))
+                            .service(routes::status_text)
+                            .service(routes::status_json)
+                            .service(routes::status_text_probe)
+                            .service(routes::status_json_probe)
+                            .service(routes::status_text_group)
+                            .service(routes::status_json_group)
+                            .service(routes::status_uptime)
+                            .service(routes::status_events),
+                    )
+                    .service(
+                        web::resource("/ws")
+                            .wrap_fn(ratelimit::limit(RateLimitKind::Public))
+                            .guard(guard::Get())
+                            .to(routes::websocket),
+                    )
+                    .service(
+                        web::resource("/metrics")
+                            .wrap_fn(ratelimit::limit(RateLimitKind::Public))
+                            .guard(guard::Get())
+                            .to(routes::metrics),
+                    )
+                    .service(
+                        web::resource("/healthz")
+                            .wrap_fn(ratelimit::limit(RateLimitKind::Public))
+                            .guard(guard::Get())
+                            .to(routes::healthz),
+                    )
+                    .service(
+                        web::resource("/readyz")
+                            .wrap_fn(ratelimit::limit(RateLimitKind::Public))
+                            .guard(guard::Get())
+                            .to(routes::readyz),
+                    )
+                    .service(routes::robots)
+                    .app_data(ConfigAuth::default().realm("Reporter Token"))
+                    .service(
+                        web::resource("/reporter/batch")
+                            .wrap(middleware_auth.clone())
+                            .wrap_fn(ratelimit::limit(RateLimitKind::Reporter))
+                            .guard(guard::Post())
+                            .to(routes::reporter_batch),
+                    )
+                    .service(
+                        web::resource("/reporter/{probe_id}/{node_id}")
+                            .wrap(middleware_auth.clone())
+                            .wrap_fn(ratelimit::limit(RateLimitKind::Reporter))
+                            .guard(guard::Post())
+                            .to(routes::reporter_report),
+                    )
+                    .service(
+                        web::resource("/heartbeat/{probe_id}/{node_id}")
+                            .wrap(middleware_auth.clone())
+                            .wrap_fn(ratelimit::limit(RateLimitKind::Reporter))
+                            .route(web::get().to(routes::reporter_heartbeat))
+                            .route(web::post().to(routes::reporter_heartbeat)),
+                    )
+                    .service(
+                        web::resource("/heartbeat/{probe_id}/{node_id}/fail")
+                            .wrap(middleware_auth.clone())
+                            .wrap_fn(ratelimit::limit(RateLimitKind::Reporter))
+                            .route(web::get().to(routes::reporter_heartbeat_fail))
+                            .route(web::post().to(routes::reporter_heartbeat_fail)),
+                    )
+                    .service(
+                        web::resource("/reporter/{probe_id}/{node_id}/{replica_id}")
+                            .wrap(middleware_auth.clone())
+                            .wrap_fn(ratelimit::limit(RateLimitKind::Reporter))
+                            .guard(guard::Delete())
+                            .to(routes::reporter_flush),
+                    )
+                    .service(
+                        web::resource("/ack/{probe_id}")
+                            .wrap_fn(ratelimit::limit(RateLimitKind::Public))
+                            .route(web::get().to(routes::acknowledge_link_confirm))
+                            .route(web::post().to(routes::acknowledge_link)),
+                    )
+                    // Notice: manager routes are scoped on their own, so that authentication \
+                    //   failures advertise the manager realm, rather than the reporter one.
+                    .service(
+                        web::scope("/manager")
+                            .app_data(ConfigAuth::default().realm("Manager Token"))
+                            .service(
+                                web::resource("/incidents")
+                                    .wrap(middleware_manager_auth.clone())
+                                    .route(web::get().to(routes::manager_incidents_list))
+                                    .route(web::post().to(routes::manager_incident_open)),
+                            )
+                            .service(
+                                web::resource("/incidents/{incident_id}/updates")
+                                    .wrap(middleware_manager_auth.clone())
+                                    .guard(guard::Post())
+                                    .to(routes::manager_incident_update),
+                            )
+                            .service(
+                                web::resource("/incidents/{incident_id}/resolve")
+                                    .wrap(middleware_manager_auth.clone())
+                                    .guard(guard::Post())
+                                    .to(routes::manager_incident_resolve),
+                            )
+                            .service(
+                                web::resource("/subscriptions")
+                                    .wrap(middleware_manager_auth.clone())
+                                    .route(web::get().to(routes::manager_subscriptions_list))
+                                    .route(web::post().to(routes::manager_subscription_add)),
+                            )
+                            .service(
+                                web::resource("/subscriptions/{subscription_id}")
+                                    .wrap(middleware_manager_auth.clone())
+                                    .guard(guard::Delete())
+                                    .to(routes::manager_subscription_remove),
+                            )
+                            .service(
+                                web::resource("/ack/{probe_id}")
+                                    .wrap(middleware_manager_auth.clone())
+                                    .route(web::post().to(routes::manager_acknowledge))
+                                    .route(web::delete().to(routes::manager_unacknowledge)),
+                            )
+                            .service(
+                                web::resource("/probes/{probe_id}")
+                                    .wrap(middleware_manager_auth.clone())
+                                    .guard(guard::Get())
+                                    .to(routes::manager_probe),
+                            )
+                            .service(
+                                web::resource("/notifiers/{name}/test")
+                                    .wrap(middleware_manager_auth.clone())
+                                    .guard(guard::Post())
+                                    .to(routes::manager_notifier_test),
+                            )
+                            .service(
+                                web::resource("/services/disabled")
+                                    .wrap(middleware_manager_auth.clone())
+                                    .guard(guard::Get())
+                                    .to(routes::manager_services_disabled),
+                            )
+                            .service(
+                                web::resource("/services/disable")
+                                    .wrap(middleware_manager_auth.clone())
+                                    .guard(guard::Post())
+                                    .to(routes::manager_services_disable),
+                            )
+                            .service(
+                                web::resource("/services/enable")
+                                    .wrap(middleware_manager_auth.clone())
+                                    .guard(guard::Post())
+                                    .to(routes::manager_services_enable),
+                            )
+                            .service(
+                                web::resource("/services/{probe_id}/disable")
+                                    .wrap(middleware_manager_auth.clone())
+                                    .guard(guard::Post())
+                                    .to(routes::manager_service_disable),
+                            )
+                            .service(
+                                web::resource("/services/{probe_id}/enable")
+                                    .wrap(middleware_manager_auth.clone())
+                                    .guard(guard::Post())
+                                    .to(routes::manager_service_enable),
+                            )
+                            .service(
+                                web::resource("/groups/{group_id}/disable")
+                                    .wrap(middleware_manager_auth.clone())
+                                    .guard(guard::Post())
+                                    .to(routes::manager_group_disable),
+                            )
+                            .service(
+                                web::resource("/groups/{group_id}/enable")
+                                    .wrap(middleware_manager_auth.clone())
+                                    .guard(guard::Post())
+                                    .to(routes::manager_group_enable),
+                            )
+                            .service(
+                                web::resource("/reload")
+                                    .wrap(middleware_manager_auth.clone())
+                                    .guard(guard::Post())
+                                    .to(routes::manager_reload),
+                            ),
+                    )
+                    .service(
+                        web::resource("/notifier/slack/actions")
+                            .wrap_fn(ratelimit::limit(RateLimitKind::Public))
+                            .guard(guard::Post())
+                            .to(routes::notifier_slack_actions),
+                    )
+                    .service(
+                        web::resource("/notifier/telegram/updates")
+                            .wrap_fn(ratelimit::limit(RateLimitKind::Public))
+                            .guard(guard::Post())
+                            .to(routes::notifier_telegram_updates),
+                    ),
             )
     })
-    .workers(APP_CONF.server.workers)
-    .bind(APP_CONF.server.inet)
+    .workers(APP_CONF.server.workers);
+
+    // Bind over HTTPS? (if TLS is configured)
+    let server = match APP_CONF.server.tls {
+        Some(ref tls) => server.bind_openssl(
+            APP_CONF.server.inet,
+            tls::make_acceptor(tls).expect("could not load tls certificate"),
+        ),
+        None => server.bind(APP_CONF.server.inet),
+    }
     .unwrap()
     .run();
 
     runtime.block_on(server).unwrap()
 }
 
-async fn authenticate(
+fn make_cors() -> Cors {
+    // Notice: CORS is only enabled on public read-only routes (ie. status and badges), so that \
+    //   they can be fetched from external dashboards.
+    let mut cors = Cors::default();
+
+    if let Some(ref config) = APP_CONF.server.cors {
+        for origin in config.allowed_origins.iter() {
+            cors = if origin == "*" {
+                cors.allow_any_origin()
+            } else {
+                cors.allowed_origin(origin)
+            };
+        }
+
+        cors = cors
+            .allowed_methods(config.allowed_methods.iter().map(String::as_str))
+            .allowed_headers(config.allowed_headers.iter().map(String::as_str))
+            .expose_headers(vec![ETAG])
+            .max_age(config.max_age);
+    }
+
+    cors
+}
+
+async fn authenticate_reporter(
+    request: ServiceRequest,
+    credentials: BasicAuth,
+) -> Result<ServiceRequest, ActixError> {
+    authenticate(request, credentials, Some(&APP_CONF.server.reporter_token))
+}
+
+async fn authenticate_manager(
     request: ServiceRequest,
     credentials: BasicAuth,
+) -> Result<ServiceRequest, ActixError> {
+    // Notice: manager routes are always rejected if no manager token is configured
+    authenticate(request, credentials, APP_CONF.server.manager_token.as_ref())
+}
+
+fn authenticate(
+    request: ServiceRequest,
+    credentials: BasicAuth,
+    token: Option<&String>,
 ) -> Result<ServiceRequest, ActixError> {
     let password = if let Some(password) = credentials.password() {
         &*password
@@ -83,7 +424,11 @@ async fn authenticate(
         ""
     };
 
-    if password == APP_CONF.server.reporter_token {
+    let is_authorized = token
+        .map(|token| password == token.as_str())
+        .unwrap_or(false);
+
+    if is_authorized == true {
         Ok(request)
     } else {
         let mut error = AuthenticationError::from(
@@ -4,8 +4,18 @@
 // Copyright: 2018, Valerian Saliou <valerian@valeriansaliou.name>
 // License: Mozilla Public License v2.0 (MPL v2.0)
 
+mod badge;
+mod cache;
 mod context;
+mod health;
 mod payload;
+mod prometheus;
+mod ratelimit;
 mod routes;
+mod signature;
+mod slack;
+mod socket;
+mod telegram;
 
 pub mod manager;
+pub mod tls;
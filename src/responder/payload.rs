@@ -4,6 +4,10 @@
 // Copyright: 2021, Valerian Saliou <valerian@valeriansaliou.name>
 // License: Mozilla Public License v2.0 (MPL v2.0)
 
+use indexmap::IndexMap;
+
+use crate::notifier::tester::TestOutcome;
+use crate::prober::states::ServiceStatesProbe;
 use crate::prober::status::Status as HealthStatus;
 
 #[derive(Deserialize)]
@@ -14,8 +18,146 @@ pub struct ReporterPayload {
     pub load: Option<ReporterPayloadLoad>,
 }
 
+#[derive(Deserialize)]
+pub struct ReporterBatchPayload {
+    pub probe: String,
+    pub node: String,
+
+    #[serde(flatten)]
+    pub report: ReporterPayload,
+}
+
 #[derive(Deserialize)]
 pub struct ReporterPayloadLoad {
     pub cpu: f32,
     pub ram: f32,
 }
+
+#[derive(Serialize)]
+pub struct ReporterBatchErrorResponse {
+    pub error: &'static str,
+    pub index: usize,
+}
+
+#[derive(Deserialize)]
+pub struct ManagerIncidentOpenPayload {
+    pub title: String,
+    pub probe_id: Option<String>,
+    pub message: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ManagerIncidentUpdatePayload {
+    pub message: String,
+}
+
+#[derive(Deserialize)]
+pub struct ManagerIncidentResolvePayload {
+    pub message: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ManagerIncidentOpenResponse {
+    pub id: i64,
+}
+
+#[derive(Deserialize)]
+pub struct ManagerAcknowledgePayload {
+    pub duration: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct ManagerAcknowledgeResponse {
+    pub acknowledged_until: u64,
+}
+
+#[derive(Deserialize)]
+pub struct ManagerSubscriptionAddPayload {
+    pub url: String,
+}
+
+#[derive(Serialize)]
+pub struct ManagerSubscriptionAddResponse {
+    pub id: i64,
+}
+
+#[derive(Serialize)]
+pub struct StatusGroupResponse<'a> {
+    pub status: HealthStatus,
+    pub probes: IndexMap<&'a str, &'a ServiceStatesProbe>,
+}
+
+#[derive(Serialize)]
+pub struct ManagerProbeResponse<'a> {
+    #[serde(flatten)]
+    pub probe: &'a ServiceStatesProbe,
+
+    pub diagnostics: IndexMap<&'a str, IndexMap<&'a str, ManagerProbeDiagnosticResponse<'a>>>,
+}
+
+#[derive(Serialize)]
+pub struct ManagerProbeDiagnosticResponse<'a> {
+    pub time: u64,
+    pub output: &'a str,
+}
+
+#[derive(Serialize)]
+pub struct ManagerServiceResponse {
+    pub id: String,
+    pub disabled: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disabled_until: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct ManagerServiceDisableQuery {
+    pub duration: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ManagerServicesPayload {
+    pub services: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct ManagerServicesResponse {
+    pub services: Vec<String>,
+    pub disabled: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disabled_until: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct ManagerNotifierTestResponse {
+    pub results: Vec<TestOutcome>,
+}
+
+#[derive(Serialize)]
+pub struct ManagerServicesDisabledResponse {
+    pub services: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct ManagerErrorResponse {
+    pub error: &'static str,
+}
+
+#[derive(Serialize)]
+pub struct ManagerReloadResponse {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct AcknowledgeLinkQuery {
+    pub expires: u64,
+    pub signature: String,
+}
+
+#[derive(Deserialize)]
+pub struct BadgeQuery {
+    pub kind: Option<String>,
+    pub window: Option<String>,
+}
@@ -0,0 +1,227 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::time::SystemTime;
+
+use crate::prober::manager::StoreSnapshot;
+use crate::prober::status::Status;
+
+pub fn render(snapshot: &StoreSnapshot) -> String {
+    let mut output = String::new();
+
+    // Append general status
+    push_header(
+        &mut output,
+        "vigil_status",
+        "gauge",
+        "General status (0 = healthy, 1 = sick, 2 = dead)",
+    );
+    push_sample(
+        &mut output,
+        "vigil_status",
+        &[],
+        &status_to_gauge(&snapshot.states.status).to_string(),
+    );
+
+    // Append probe statuses
+    push_header(
+        &mut output,
+        "vigil_probe_status",
+        "gauge",
+        "Probe status (0 = healthy, 1 = sick, 2 = dead)",
+    );
+
+    for (probe_id, probe) in snapshot.states.probes.iter() {
+        push_sample(
+            &mut output,
+            "vigil_probe_status",
+            &[("probe", probe_id.as_str())],
+            &status_to_gauge(&probe.status).to_string(),
+        );
+    }
+
+    // Append node statuses
+    push_header(
+        &mut output,
+        "vigil_node_status",
+        "gauge",
+        "Node status (0 = healthy, 1 = sick, 2 = dead)",
+    );
+
+    for (probe_id, probe) in snapshot.states.probes.iter() {
+        for (node_id, node) in probe.nodes.iter() {
+            push_sample(
+                &mut output,
+                "vigil_node_status",
+                &[("probe", probe_id.as_str()), ("node", node_id.as_str())],
+                &status_to_gauge(&node.status).to_string(),
+            );
+        }
+    }
+
+    // Append replica statuses
+    push_header(
+        &mut output,
+        "vigil_replica_status",
+        "gauge",
+        "Replica status (0 = healthy, 1 = sick, 2 = dead)",
+    );
+
+    for (probe_id, probe) in snapshot.states.probes.iter() {
+        for (node_id, node) in probe.nodes.iter() {
+            for (replica_id, replica) in node.replicas.iter() {
+                push_sample(
+                    &mut output,
+                    "vigil_replica_status",
+                    &[
+                        ("probe", probe_id.as_str()),
+                        ("node", node_id.as_str()),
+                        ("replica", replica_id.as_str()),
+                    ],
+                    &status_to_gauge(&replica.status).to_string(),
+                );
+            }
+        }
+    }
+
+    // Append replica latencies (if any)
+    push_header(
+        &mut output,
+        "vigil_replica_latency_seconds",
+        "gauge",
+        "Last measured replica latency",
+    );
+
+    for (probe_id, probe) in snapshot.states.probes.iter() {
+        for (node_id, node) in probe.nodes.iter() {
+            for (replica_id, replica) in node.replicas.iter() {
+                if let Some(latency) = replica.metrics.latency {
+                    push_sample(
+                        &mut output,
+                        "vigil_replica_latency_seconds",
+                        &[
+                            ("probe", probe_id.as_str()),
+                            ("node", node_id.as_str()),
+                            ("replica", replica_id.as_str()),
+                        ],
+                        &format!("{:.3}", latency as f64 / 1000.0),
+                    );
+                }
+            }
+        }
+    }
+
+    // Append replica report staleness (for reporting replicas only)
+    push_header(
+        &mut output,
+        "vigil_replica_report_age_seconds",
+        "gauge",
+        "Time elapsed since the replica last reported",
+    );
+
+    let now = SystemTime::now();
+
+    for (probe_id, probe) in snapshot.states.probes.iter() {
+        for (node_id, node) in probe.nodes.iter() {
+            for (replica_id, replica) in node.replicas.iter() {
+                if let Some(ref report) = replica.report {
+                    if let Ok(report_age) = now.duration_since(report.time) {
+                        push_sample(
+                            &mut output,
+                            "vigil_replica_report_age_seconds",
+                            &[
+                                ("probe", probe_id.as_str()),
+                                ("node", node_id.as_str()),
+                                ("replica", replica_id.as_str()),
+                            ],
+                            &report_age.as_secs().to_string(),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    // Append node transition counters
+    push_header(
+        &mut output,
+        "vigil_node_transitions_total",
+        "counter",
+        "Number of node status transitions",
+    );
+
+    for ((probe_id, node_id), count) in snapshot.counters.transitions.iter() {
+        push_sample(
+            &mut output,
+            "vigil_node_transitions_total",
+            &[("probe", probe_id.as_str()), ("node", node_id.as_str())],
+            &count.to_string(),
+        );
+    }
+
+    // Append notifier dispatch counters
+    push_header(
+        &mut output,
+        "vigil_notifier_dispatches_total",
+        "counter",
+        "Number of notifications dispatched to notifiers",
+    );
+
+    for (notifier, counter) in snapshot.counters.dispatches.iter() {
+        push_sample(
+            &mut output,
+            "vigil_notifier_dispatches_total",
+            &[("notifier", *notifier), ("result", "success")],
+            &counter.success.to_string(),
+        );
+        push_sample(
+            &mut output,
+            "vigil_notifier_dispatches_total",
+            &[("notifier", *notifier), ("result", "failure")],
+            &counter.failure.to_string(),
+        );
+    }
+
+    output
+}
+
+fn push_header(output: &mut String, name: &str, kind: &str, help: &str) {
+    output.push_str(&format!("# HELP {} {}\n", name, help));
+    output.push_str(&format!("# TYPE {} {}\n", name, kind));
+}
+
+fn push_sample(output: &mut String, name: &str, labels: &[(&str, &str)], value: &str) {
+    output.push_str(name);
+
+    if labels.is_empty() == false {
+        let labels_text = labels
+            .iter()
+            .map(|(label_name, label_value)| {
+                format!("{}=\"{}\"", label_name, escape_label_value(label_value))
+            })
+            .collect::<Vec<String>>()
+            .join(",");
+
+        output.push_str(&format!("{{{}}}", labels_text));
+    }
+
+    output.push_str(&format!(" {}\n", value));
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace("\\", "\\\\")
+        .replace("\"", "\\\"")
+        .replace("\n", "\\n")
+}
+
+fn status_to_gauge(status: &Status) -> u8 {
+    match status {
+        &Status::Healthy => 0,
+        &Status::Sick => 1,
+        &Status::Dead => 2,
+    }
+}
@@ -0,0 +1,187 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse};
+use actix_web::http::header::RETRY_AFTER;
+use actix_web::{Error as ActixError, HttpResponse};
+use futures::future::{self, Either, Ready};
+
+use crate::config::config::ConfigServerRateLimitBucket;
+use crate::APP_CONF;
+
+const PRUNE_INTERVAL_SECONDS: u64 = 60;
+const BUCKETS_MAXIMUM: usize = 100000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum RateLimitKind {
+    Public,
+    Reporter,
+}
+
+struct RateLimitBucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+struct RateLimitBuckets {
+    buckets: HashMap<(RateLimitKind, IpAddr), RateLimitBucket>,
+    pruned_at: Instant,
+}
+
+lazy_static! {
+    static ref BUCKETS: Mutex<RateLimitBuckets> = Mutex::new(RateLimitBuckets {
+        buckets: HashMap::new(),
+        pruned_at: Instant::now(),
+    });
+}
+
+impl RateLimitKind {
+    fn config(&self) -> Option<&'static ConfigServerRateLimitBucket> {
+        let rate_limit = APP_CONF.server.rate_limit.as_ref()?;
+
+        match self {
+            RateLimitKind::Public => rate_limit.public.as_ref(),
+            RateLimitKind::Reporter => rate_limit.reporter.as_ref(),
+        }
+    }
+}
+
+impl RateLimitBuckets {
+    fn prune(&mut self, now: Instant) {
+        // Forget about idle clients (ie. whose bucket got refilled)
+        self.buckets.retain(|(bucket_kind, _), bucket| {
+            bucket_kind.config().map_or(false, |bucket_config| {
+                bucket.refill(bucket_config, now);
+
+                bucket.tokens < bucket_config.burst as f64
+            })
+        });
+
+        self.pruned_at = now;
+    }
+}
+
+impl RateLimitBucket {
+    fn refill(&mut self, config: &ConfigServerRateLimitBucket, now: Instant) {
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+
+        self.tokens = (self.tokens + elapsed * config.rate).min(config.burst as f64);
+        self.refilled_at = now;
+    }
+}
+
+pub fn check(kind: RateLimitKind, request: &ServiceRequest) -> Result<(), u64> {
+    // Rate limiting disabled for this kind of routes?
+    let config = match kind.config() {
+        Some(config) => config,
+        None => return Ok(()),
+    };
+
+    let address = match acquire_address(request) {
+        Some(address) => address,
+        None => return Ok(()),
+    };
+
+    let now = Instant::now();
+    let mut buckets = BUCKETS.lock().unwrap();
+
+    if now.duration_since(buckets.pruned_at) >= Duration::from_secs(PRUNE_INTERVAL_SECONDS) {
+        buckets.prune(now);
+    }
+
+    // Notice: the number of tracked clients is capped, so that a flood of clients cannot \
+    //   exhaust memory; if there are still too many active clients once idle ones are \
+    //   forgotten, new clients are let through untracked rather than locked out.
+    if buckets.buckets.len() >= BUCKETS_MAXIMUM
+        && buckets.buckets.contains_key(&(kind, address)) == false
+    {
+        buckets.prune(now);
+
+        if buckets.buckets.len() >= BUCKETS_MAXIMUM {
+            warn!("too many rate limited clients, not tracking: {}", address);
+
+            return Ok(());
+        }
+    }
+
+    // Take a token from client bucket (new clients start with a full bucket)
+    let bucket = buckets
+        .buckets
+        .entry((kind, address))
+        .or_insert_with(|| RateLimitBucket {
+            tokens: config.burst as f64,
+            refilled_at: now,
+        });
+
+    bucket.refill(config, now);
+
+    if bucket.tokens >= 1.0 {
+        bucket.tokens -= 1.0;
+
+        Ok(())
+    } else {
+        debug!("rate limited {:?} request from: {}", kind, address);
+
+        // Retry once a token is available again
+        Err(((1.0 - bucket.tokens) / config.rate).ceil().max(1.0) as u64)
+    }
+}
+
+pub fn limit<S>(
+    kind: RateLimitKind,
+) -> impl FnMut(
+    ServiceRequest,
+    &mut S,
+) -> Either<S::Future, Ready<Result<ServiceResponse, ActixError>>>
+       + Clone
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse, Error = ActixError>,
+{
+    move |request: ServiceRequest, service: &mut S| match check(kind, &request) {
+        Ok(()) => Either::Left(service.call(request)),
+        Err(retry_after) => Either::Right(future::ok(reject(request, retry_after))),
+    }
+}
+
+pub fn reject(request: ServiceRequest, retry_after: u64) -> ServiceResponse {
+    request.into_response(
+        HttpResponse::TooManyRequests()
+            .header(RETRY_AFTER, retry_after.to_string())
+            .finish(),
+    )
+}
+
+fn acquire_address(request: &ServiceRequest) -> Option<IpAddr> {
+    let trust_forwarded_for = APP_CONF
+        .server
+        .rate_limit
+        .as_ref()
+        .map(|rate_limit| rate_limit.trust_forwarded_for)
+        .unwrap_or(false);
+
+    // Notice: forwarded addresses can be forged by clients, thus they are only used if Vigil is \
+    //   explicitly configured to run behind a reverse proxy. Only the right-most hop is used, as \
+    //   it is the one appended by the reverse proxy (any hop before it comes from the client).
+    if trust_forwarded_for == true {
+        if let Some(address) = request
+            .headers()
+            .get_all("x-forwarded-for")
+            .last()
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit(',').next())
+            .and_then(|address| address.trim().parse::<IpAddr>().ok())
+        {
+            return Some(address);
+        }
+    }
+
+    request.peer_addr().map(|address| address.ip())
+}
@@ -4,37 +4,98 @@
 // Copyright: 2021, Valerian Saliou <valerian@valeriansaliou.name>
 // License: Mozilla Public License v2.0 (MPL v2.0)
 
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
 use actix_files::NamedFile;
-use actix_web::{get, web, web::Data, web::Json, HttpResponse};
+use actix_web::{
+    dev::BodyEncoding, get, http::ContentEncoding, web, web::Bytes, web::Data, web::Json,
+    Error as ActixError, HttpRequest, HttpResponse,
+};
+use actix_web_actors::ws;
+use futures::StreamExt;
+use indexmap::IndexMap;
 use tera::Tera;
+use url::Url;
 
-use super::context::{IndexContext, INDEX_CONFIG, INDEX_ENVIRONMENT};
-use super::payload::ReporterPayload;
-use crate::prober::manager::{run_dispatch_plugins, STORE as PROBER_STORE};
+use super::badge as badge_svg;
+use super::cache;
+use super::context::{
+    AcknowledgeContext, AcknowledgeContextState, IndexContext, INDEX_CONFIG, INDEX_ENVIRONMENT,
+};
+use super::health::{self, HealthStatus};
+use super::payload::{
+    AcknowledgeLinkQuery, BadgeQuery, ManagerAcknowledgePayload, ManagerAcknowledgeResponse,
+    ManagerErrorResponse, ManagerIncidentOpenPayload, ManagerIncidentOpenResponse,
+    ManagerIncidentResolvePayload, ManagerIncidentUpdatePayload, ManagerNotifierTestResponse,
+    ManagerProbeDiagnosticResponse, ManagerProbeResponse, ManagerReloadResponse,
+    ManagerServiceDisableQuery, ManagerServiceResponse, ManagerServicesDisabledResponse,
+    ManagerServicesPayload, ManagerServicesResponse, ManagerSubscriptionAddPayload,
+    ManagerSubscriptionAddResponse, ReporterBatchErrorResponse, ReporterBatchPayload,
+    ReporterPayload, StatusGroupResponse,
+};
+use super::prometheus;
+use super::signature;
+use super::slack::{self, HandleActionsError};
+use super::socket::StatusSocket;
+use super::telegram::{self, HandleUpdateError};
+use crate::notifier::{registry, tester};
+use crate::prober::acknowledge::{
+    handle_acknowledge, handle_unacknowledge, verify_link, AcknowledgeLinkError,
+    HandleAcknowledgeError,
+};
+use crate::prober::disable::{
+    as_timestamp, handle_disable, handle_disable_many, handle_enable, handle_enable_many,
+    list_disabled, parse_duration, DisableSelector, HandleDisableError,
+};
+use crate::prober::events;
+use crate::prober::manager::{run_dispatch_plugins, StoreSnapshot, SNAPSHOT as PROBER_SNAPSHOT};
 use crate::prober::report::{
-    handle_flush as handle_flush_report, handle_health as handle_health_report,
-    handle_load as handle_load_report, HandleFlushError, HandleHealthError, HandleLoadError,
+    handle_batch as handle_batch_report, handle_flush as handle_flush_report,
+    handle_health as handle_health_report, handle_heartbeat as handle_heartbeat_report,
+    handle_load as handle_load_report, BatchReport, BatchReportKind, HandleBatchError,
+    HandleFlushError, HandleHealthError, HandleHeartbeatError, HandleLoadError,
 };
+use crate::prober::status::Status;
+use crate::reloader::manager::{reload, ReloadError};
+use crate::storage::incidents::{self, IncidentError};
+use crate::storage::subscriptions::{self, SubscriptionError};
+use crate::storage::uptime;
 use crate::APP_CONF;
 
-#[get("/")]
-async fn index(tera: Data<Tera>) -> HttpResponse {
-    // Notice acquire lock in a block to release it ASAP (ie. before template renders)
-    let context = {
-        IndexContext {
-            states: &PROBER_STORE.read().unwrap().states,
+const BADGE_KIND_DEFAULT: &'static str = "text";
+const BADGE_UPTIME_WINDOW_DEFAULT: &'static str = "30d";
+
+pub async fn index(request: HttpRequest, tera: Data<Tera>) -> HttpResponse {
+    // Notice: states are read from the published snapshot, thus no lock is held while the \
+    //   template renders.
+    let snapshot = PROBER_SNAPSHOT.load_full();
+
+    if cache::is_fresh(&request, &snapshot) == true {
+        return cache::not_modified(&snapshot);
+    }
+
+    // Notice: the rendered page is cached until a new snapshot gets published (uptime and \
+    //   incidents are thus refreshed along with states).
+    let render = cache::render_index(&snapshot, || {
+        let context = IndexContext {
+            states: &snapshot.states,
+            uptime: uptime::compute(),
+            incidents: incidents::timeline(),
             environment: &*INDEX_ENVIRONMENT,
             config: &*INDEX_CONFIG,
-        }
-    };
-    let render = tera.render(
-        "index.tera",
-        &tera::Context::from_serialize(context).unwrap(),
-    );
-    if let Ok(s) = render {
-        HttpResponse::Ok().content_type("text/html").body(s)
-    } else {
-        HttpResponse::InternalServerError().body(format!("Template Error {:?}", render))
+        };
+
+        tera.render(
+            "index.tera",
+            &tera::Context::from_serialize(context).unwrap(),
+        )
+    });
+
+    match render {
+        Ok(body) => cache::with_validators(&mut HttpResponse::Ok(), &snapshot)
+            .content_type("text/html")
+            .body(body),
+        Err(err) => HttpResponse::InternalServerError().body(format!("Template Error {:?}", err)),
     }
 }
 
@@ -43,30 +104,294 @@ async fn robots() -> Option<NamedFile> {
     NamedFile::open(APP_CONF.assets.path.join("public").join("robots.txt")).ok()
 }
 
-#[get("/status/text")]
-async fn status_text() -> &'static str {
-    &PROBER_STORE.read().unwrap().states.status.as_str()
+#[get("/text")]
+async fn status_text(request: HttpRequest) -> HttpResponse {
+    let snapshot = PROBER_SNAPSHOT.load();
+
+    if cache::is_fresh(&request, &snapshot) == true {
+        return cache::not_modified(&snapshot);
+    }
+
+    cache::with_validators(&mut HttpResponse::Ok(), &snapshot)
+        .content_type("text/plain; charset=utf-8")
+        .body(snapshot.states.status.as_str())
+}
+
+#[get("/json")]
+async fn status_json(request: HttpRequest) -> HttpResponse {
+    let snapshot = PROBER_SNAPSHOT.load();
+
+    if cache::is_fresh(&request, &snapshot) == true {
+        return cache::not_modified(&snapshot);
+    }
+
+    cache::with_validators(&mut HttpResponse::Ok(), &snapshot).json(&snapshot.states)
+}
+
+#[get("/text/{probe_id}")]
+async fn status_text_probe(
+    request: HttpRequest,
+    web::Path(probe_id): web::Path<String>,
+) -> HttpResponse {
+    let snapshot = PROBER_SNAPSHOT.load();
+
+    let probe = match snapshot.states.probes.get(&probe_id) {
+        Some(probe) => probe,
+        None => return HttpResponse::NotFound().finish(),
+    };
+
+    if cache::is_fresh(&request, &snapshot) == true {
+        return cache::not_modified(&snapshot);
+    }
+
+    cache::with_validators(&mut HttpResponse::Ok(), &snapshot)
+        .content_type("text/plain; charset=utf-8")
+        .body(probe.status.as_str())
+}
+
+#[get("/json/{probe_id}")]
+async fn status_json_probe(
+    request: HttpRequest,
+    web::Path(probe_id): web::Path<String>,
+) -> HttpResponse {
+    let snapshot = PROBER_SNAPSHOT.load();
+
+    let probe = match snapshot.states.probes.get(&probe_id) {
+        Some(probe) => probe,
+        None => return HttpResponse::NotFound().finish(),
+    };
+
+    if cache::is_fresh(&request, &snapshot) == true {
+        return cache::not_modified(&snapshot);
+    }
+
+    cache::with_validators(&mut HttpResponse::Ok(), &snapshot).json(probe)
+}
+
+#[get("/text/group/{group_id}")]
+async fn status_text_group(
+    request: HttpRequest,
+    web::Path(group_id): web::Path<String>,
+) -> HttpResponse {
+    let snapshot = PROBER_SNAPSHOT.load();
+
+    let group = match make_status_group(&snapshot, &group_id) {
+        Some(group) => group,
+        None => return HttpResponse::NotFound().finish(),
+    };
+
+    if cache::is_fresh(&request, &snapshot) == true {
+        return cache::not_modified(&snapshot);
+    }
+
+    cache::with_validators(&mut HttpResponse::Ok(), &snapshot)
+        .content_type("text/plain; charset=utf-8")
+        .body(group.status.as_str())
+}
+
+#[get("/json/group/{group_id}")]
+async fn status_json_group(
+    request: HttpRequest,
+    web::Path(group_id): web::Path<String>,
+) -> HttpResponse {
+    let snapshot = PROBER_SNAPSHOT.load();
+
+    let group = match make_status_group(&snapshot, &group_id) {
+        Some(group) => group,
+        None => return HttpResponse::NotFound().finish(),
+    };
+
+    if cache::is_fresh(&request, &snapshot) == true {
+        return cache::not_modified(&snapshot);
+    }
+
+    cache::with_validators(&mut HttpResponse::Ok(), &snapshot).json(&group)
+}
+
+#[get("/uptime.json")]
+async fn status_uptime() -> HttpResponse {
+    match uptime::compute() {
+        Some(uptime) => HttpResponse::Ok().json(&uptime),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
+#[get("/events")]
+async fn status_events() -> HttpResponse {
+    // Stream state changes as Server-Sent Events, until the client goes away
+    let receiver = match events::subscribe() {
+        Some(receiver) => receiver,
+        None => return HttpResponse::ServiceUnavailable().finish(),
+    };
+
+    let stream = receiver.map(|message| {
+        let mut data = String::new();
+
+        for event in message.iter() {
+            data.push_str(&format!("event: state\ndata: {}\n\n", event));
+        }
+
+        if data.is_empty() == true {
+            data.push_str(": keep-alive\n\n");
+        }
+
+        Ok::<_, ActixError>(Bytes::from(data))
+    });
+
+    // Notice: events must not be compressed, as compressed streams would not get flushed to the \
+    //   client upon each event.
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .header("Cache-Control", "no-cache")
+        .encoding(ContentEncoding::Identity)
+        .streaming(stream)
+}
+
+// Notice: websocket route is managed in manager due to rate limiting needs
+pub async fn websocket(
+    request: HttpRequest,
+    stream: web::Payload,
+) -> Result<HttpResponse, ActixError> {
+    // Notice: the socket subscribes to state changes before being upgraded, so that no socket \
+    //   is opened if there are too many subscribers already.
+    match events::subscribe() {
+        Some(receiver) => ws::start(StatusSocket::new(receiver), &request, stream),
+        None => Ok(HttpResponse::ServiceUnavailable().finish()),
+    }
+}
+
+// Notice: metrics route is managed in manager due to rate limiting needs
+pub async fn metrics() -> HttpResponse {
+    let metrics = prometheus::render(&PROBER_SNAPSHOT.load());
+
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics)
+}
+
+// Notice: healthz route is managed in manager due to rate limiting needs
+pub async fn healthz() -> HttpResponse {
+    let report = health::report();
+
+    // Notice: Vigil is alive while starting up, as its first poll cycle might take a while; it \
+    //   is only deemed dead if its poll loop stopped beating.
+    if report.status == HealthStatus::Stale {
+        HttpResponse::ServiceUnavailable().json(&report)
+    } else {
+        HttpResponse::Ok().json(&report)
+    }
+}
+
+// Notice: readyz route is managed in manager due to rate limiting needs
+pub async fn readyz() -> HttpResponse {
+    let report = health::report();
+
+    // Vigil is ready once it polled services at least once (states are not known before)
+    if report.status == HealthStatus::Ok {
+        HttpResponse::Ok().json(&report)
+    } else {
+        HttpResponse::ServiceUnavailable().json(&report)
+    }
 }
 
-#[get("/badge/{kind}")]
-async fn badge(web::Path(kind): web::Path<String>) -> Option<NamedFile> {
-    // Notice acquire lock in a block to release it ASAP (ie. before OS access to file)
-    let status = { &PROBER_STORE.read().unwrap().states.status.as_str() };
+#[get("/uptime")]
+async fn badge_uptime(request: HttpRequest, query: web::Query<BadgeQuery>) -> HttpResponse {
+    let window = query
+        .window
+        .as_deref()
+        .unwrap_or(BADGE_UPTIME_WINDOW_DEFAULT);
+
+    if badge_svg::UPTIME_WINDOWS.contains(&window) == false {
+        return HttpResponse::BadRequest().finish();
+    }
+
+    let snapshot = PROBER_SNAPSHOT.load();
 
-    NamedFile::open(
-        APP_CONF
-            .assets
-            .path
-            .join("images")
-            .join("badges")
-            .join(format!("{}-{}-default.svg", kind, status)),
+    if cache::is_fresh(&request, &snapshot) == true {
+        return cache::not_modified(&snapshot);
+    }
+
+    let uptime = uptime::compute().and_then(|uptime| uptime.general.get(window));
+
+    badge_response(
+        &snapshot,
+        badge_svg::render_uptime(&format!("uptime {}", window), uptime),
     )
-    .ok()
+}
+
+#[get("/uptime/{probe_id}")]
+async fn badge_uptime_probe(
+    request: HttpRequest,
+    web::Path(probe_id): web::Path<String>,
+    query: web::Query<BadgeQuery>,
+) -> HttpResponse {
+    let window = query
+        .window
+        .as_deref()
+        .unwrap_or(BADGE_UPTIME_WINDOW_DEFAULT);
+
+    if badge_svg::UPTIME_WINDOWS.contains(&window) == false {
+        return HttpResponse::BadRequest().finish();
+    }
+
+    let snapshot = PROBER_SNAPSHOT.load();
+
+    let label = match snapshot.states.probes.get(&probe_id) {
+        Some(probe) => format!("{} uptime {}", probe.label, window),
+        None => return HttpResponse::NotFound().finish(),
+    };
+
+    // Notice: freshness is checked before uptime gets computed, as it reads from storage
+    if cache::is_fresh(&request, &snapshot) == true {
+        return cache::not_modified(&snapshot);
+    }
+
+    let uptime = uptime::compute()
+        .and_then(|mut uptime| uptime.probes.remove(&probe_id))
+        .and_then(|probe_uptime| probe_uptime.uptime.get(window));
+
+    badge_response(&snapshot, badge_svg::render_uptime(&label, uptime))
+}
+
+#[get("/probe/{probe_id}")]
+async fn badge_probe(
+    request: HttpRequest,
+    web::Path(probe_id): web::Path<String>,
+    query: web::Query<BadgeQuery>,
+) -> HttpResponse {
+    let kind = query.kind.as_deref().unwrap_or(BADGE_KIND_DEFAULT);
+
+    let snapshot = PROBER_SNAPSHOT.load();
+
+    let probe = match snapshot.states.probes.get(&probe_id) {
+        Some(probe) => probe,
+        None => return HttpResponse::NotFound().finish(),
+    };
+
+    match badge_svg::render_status(kind, &probe.label, &probe.status) {
+        Some(_) if cache::is_fresh(&request, &snapshot) == true => cache::not_modified(&snapshot),
+        Some(svg) => badge_response(&snapshot, svg),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
+#[get("/{kind}")]
+async fn badge(request: HttpRequest, web::Path(kind): web::Path<String>) -> HttpResponse {
+    let snapshot = PROBER_SNAPSHOT.load();
+
+    match badge_svg::render_status(&kind, "status", &snapshot.states.status) {
+        Some(_) if cache::is_fresh(&request, &snapshot) == true => cache::not_modified(&snapshot),
+        Some(svg) => badge_response(&snapshot, svg),
+        None => HttpResponse::NotFound().finish(),
+    }
 }
 
 #[get("/assets/fonts/{folder}/{file}")]
 async fn assets_fonts(web::Path((folder, file)): web::Path<(String, String)>) -> Option<NamedFile> {
-    NamedFile::open(APP_CONF.assets.path.join("fonts").join(folder).join(file)).ok()
+    // Notice: fonts are already compressed, thus they are served as-is
+    NamedFile::open(APP_CONF.assets.path.join("fonts").join(folder).join(file))
+        .ok()
+        .map(|font| font.set_content_encoding(ContentEncoding::Identity))
 }
 
 #[get("/assets/images/{folder}/{file}")]
@@ -88,9 +413,21 @@ async fn assets_javascripts(web::Path(file): web::Path<String>) -> Option<NamedF
 
 // Notice: reporter report route is managed in manager due to authentication needs
 pub async fn reporter_report(
+    request: HttpRequest,
     web::Path((probe_id, node_id)): web::Path<(String, String)>,
-    data: Json<ReporterPayload>,
+    body: Bytes,
 ) -> HttpResponse {
+    if verify_reporter_signature(&request, &probe_id, &body) == false {
+        return HttpResponse::Unauthorized().finish();
+    }
+
+    // Notice: the raw body is required to check the signature, thus it is parsed here rather \
+    //   than through the JSON extractor.
+    let data = match serde_json::from_slice::<ReporterPayload>(&body) {
+        Ok(data) => data,
+        Err(_) => return HttpResponse::BadRequest().finish(),
+    };
+
     // Route report to handler (depending on its contents)
     if let Some(ref load) = data.load {
         // Load reports should come for 'push' nodes only
@@ -125,10 +462,129 @@ pub async fn reporter_report(
     }
 }
 
+// Notice: reporter batch route is managed in manager due to authentication needs
+pub async fn reporter_batch(request: HttpRequest, body: Bytes) -> HttpResponse {
+    let data = match serde_json::from_slice::<Vec<ReporterBatchPayload>>(&body) {
+        Ok(data) => data,
+        Err(_) => return HttpResponse::BadRequest().finish(),
+    };
+
+    // Verify batch signature (if a secret applies to any of its probes)
+    // Notice: as the whole batch is signed at once, all its probes must share the same secret.
+    let mut secrets = data
+        .iter()
+        .filter_map(|entry| signature::acquire_secret(&entry.probe))
+        .collect::<Vec<String>>();
+
+    secrets.sort();
+    secrets.dedup();
+
+    if secrets.len() > 1 {
+        warn!("rejected batch report mixing probes with different secrets");
+
+        return HttpResponse::Unauthorized().finish();
+    }
+
+    if let Some(secret) = secrets.first() {
+        if let Err(err) = signature::verify(&request, secret, &body) {
+            warn!("rejected unsigned batch report ({:?})", err);
+
+            return HttpResponse::Unauthorized().finish();
+        }
+    }
+
+    // Map batch entries to reports (load reports take precedence, as for single reports)
+    let mut reports = Vec::with_capacity(data.len());
+
+    for (index, entry) in data.iter().enumerate() {
+        let kind = if let Some(ref load) = entry.report.load {
+            BatchReportKind::Load(load.cpu, load.ram)
+        } else if let Some(ref health) = entry.report.health {
+            BatchReportKind::Health(health)
+        } else {
+            return HttpResponse::BadRequest().json(&ReporterBatchErrorResponse {
+                error: "invalid_report",
+                index: index,
+            });
+        };
+
+        reports.push(BatchReport {
+            probe_id: &entry.probe,
+            node_id: &entry.node,
+            replica_id: &entry.report.replica,
+            interval: entry.report.interval,
+            kind: kind,
+        });
+    }
+
+    match handle_batch_report(&reports) {
+        Ok(forwards) => {
+            // Trigger a plugins check for each load report
+            for (report, forward) in reports.iter().zip(forwards) {
+                if let BatchReportKind::Load(..) = report.kind {
+                    run_dispatch_plugins(report.probe_id, report.node_id, forward);
+                }
+            }
+
+            HttpResponse::Ok().finish()
+        }
+        Err(HandleBatchError::InvalidLoad(index)) => {
+            HttpResponse::BadRequest().json(&ReporterBatchErrorResponse {
+                error: "invalid_load",
+                index: index,
+            })
+        }
+        Err(HandleBatchError::WrongMode(index)) => {
+            HttpResponse::PreconditionFailed().json(&ReporterBatchErrorResponse {
+                error: "wrong_mode",
+                index: index,
+            })
+        }
+        Err(HandleBatchError::NotFound(index)) => {
+            HttpResponse::NotFound().json(&ReporterBatchErrorResponse {
+                error: "not_found",
+                index: index,
+            })
+        }
+    }
+}
+
+// Notice: reporter heartbeat route is managed in manager due to authentication needs
+pub async fn reporter_heartbeat(
+    request: HttpRequest,
+    web::Path((probe_id, node_id)): web::Path<(String, String)>,
+    body: Bytes,
+) -> HttpResponse {
+    if verify_reporter_signature(&request, &probe_id, &body) == false {
+        return HttpResponse::Unauthorized().finish();
+    }
+
+    heartbeat_response(&probe_id, &node_id, Status::Healthy)
+}
+
+// Notice: reporter heartbeat route is managed in manager due to authentication needs
+pub async fn reporter_heartbeat_fail(
+    request: HttpRequest,
+    web::Path((probe_id, node_id)): web::Path<(String, String)>,
+    body: Bytes,
+) -> HttpResponse {
+    if verify_reporter_signature(&request, &probe_id, &body) == false {
+        return HttpResponse::Unauthorized().finish();
+    }
+
+    heartbeat_response(&probe_id, &node_id, Status::Dead)
+}
+
 // Notice: reporter flush route is managed in manager due to authentication needs
 pub async fn reporter_flush(
+    request: HttpRequest,
     web::Path((probe_id, node_id, replica_id)): web::Path<(String, String, String)>,
+    body: Bytes,
 ) -> HttpResponse {
+    if verify_reporter_signature(&request, &probe_id, &body) == false {
+        return HttpResponse::Unauthorized().finish();
+    }
+
     // Flush reports should come for 'push' and 'local' nodes only
     match handle_flush_report(&probe_id, &node_id, &replica_id) {
         Ok(()) => HttpResponse::Ok().finish(),
@@ -136,3 +592,526 @@ pub async fn reporter_flush(
         Err(HandleFlushError::NotFound) => HttpResponse::NotFound().finish(),
     }
 }
+
+// Notice: manager incidents routes are managed in manager due to authentication needs
+pub async fn manager_incidents_list() -> HttpResponse {
+    // List all incidents (resolved ones included)
+    match incidents::list(0) {
+        Ok(incidents) => HttpResponse::Ok().json(&incidents),
+        Err(err) => incident_error_response(err),
+    }
+}
+
+pub async fn manager_incident_open(data: Json<ManagerIncidentOpenPayload>) -> HttpResponse {
+    if data.title.is_empty() == true {
+        return HttpResponse::BadRequest().finish();
+    }
+
+    // Incident attached to a probe? Ensure that the probe exists
+    if let Some(ref probe_id) = data.probe_id {
+        if PROBER_SNAPSHOT.load().states.probes.contains_key(probe_id) == false {
+            return HttpResponse::NotFound().finish();
+        }
+    }
+
+    match incidents::open(
+        &data.title,
+        data.probe_id.as_deref(),
+        false,
+        data.message.as_deref(),
+    ) {
+        Ok(incident_id) => {
+            HttpResponse::Ok().json(&ManagerIncidentOpenResponse { id: incident_id })
+        }
+        Err(err) => incident_error_response(err),
+    }
+}
+
+pub async fn manager_incident_update(
+    web::Path(incident_id): web::Path<i64>,
+    data: Json<ManagerIncidentUpdatePayload>,
+) -> HttpResponse {
+    if data.message.is_empty() == true {
+        return HttpResponse::BadRequest().finish();
+    }
+
+    match incidents::update(incident_id, &data.message) {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(err) => incident_error_response(err),
+    }
+}
+
+pub async fn manager_incident_resolve(
+    web::Path(incident_id): web::Path<i64>,
+    data: Json<ManagerIncidentResolvePayload>,
+) -> HttpResponse {
+    match incidents::resolve(incident_id, data.message.as_deref()) {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(err) => incident_error_response(err),
+    }
+}
+
+// Notice: acknowledgment also accepts requests with no body
+pub async fn manager_acknowledge(
+    web::Path(probe_id): web::Path<String>,
+    data: Option<Json<ManagerAcknowledgePayload>>,
+) -> HttpResponse {
+    let duration = data.and_then(|data| data.duration);
+
+    match handle_acknowledge(&probe_id, duration) {
+        Ok(acknowledged_until) => HttpResponse::Ok().json(&ManagerAcknowledgeResponse {
+            acknowledged_until: acknowledged_until
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0),
+        }),
+        Err(HandleAcknowledgeError::NotFound) => HttpResponse::NotFound().finish(),
+        Err(HandleAcknowledgeError::InvalidDuration) => HttpResponse::BadRequest().finish(),
+    }
+}
+
+// Notice: opening an acknowledgment link does not acknowledge anything, as links might get \
+//   opened by link previewers or mail scanners; a confirmation page is shown instead, which \
+//   submits the acknowledgment.
+pub async fn acknowledge_link_confirm(
+    web::Path(probe_id): web::Path<String>,
+    query: web::Query<AcknowledgeLinkQuery>,
+    tera: Data<Tera>,
+) -> HttpResponse {
+    let state = match verify_acknowledge_link(&probe_id, query.expires, &query.signature) {
+        Ok(()) => AcknowledgeContextState::Confirm,
+        Err(err) => acknowledge_link_error_state(err),
+    };
+
+    render_acknowledge(&tera, &probe_id, state, None)
+}
+
+pub async fn acknowledge_link(
+    web::Path(probe_id): web::Path<String>,
+    query: web::Query<AcknowledgeLinkQuery>,
+    tera: Data<Tera>,
+) -> HttpResponse {
+    if let Err(err) = verify_acknowledge_link(&probe_id, query.expires, &query.signature) {
+        return render_acknowledge(&tera, &probe_id, acknowledge_link_error_state(err), None);
+    }
+
+    match handle_acknowledge(&probe_id, None) {
+        Ok(acknowledged_until) => {
+            info!("acknowledged probe: {} from link", probe_id);
+
+            let acknowledged_until = time::OffsetDateTime::from(acknowledged_until)
+                .format(&time::format_description::well_known::Rfc2822)
+                .ok();
+
+            render_acknowledge(
+                &tera,
+                &probe_id,
+                AcknowledgeContextState::Acknowledged,
+                acknowledged_until,
+            )
+        }
+        Err(HandleAcknowledgeError::NotFound) => {
+            render_acknowledge(&tera, &probe_id, AcknowledgeContextState::NotFound, None)
+        }
+        Err(HandleAcknowledgeError::InvalidDuration) => {
+            render_acknowledge(&tera, &probe_id, AcknowledgeContextState::Invalid, None)
+        }
+    }
+}
+
+pub async fn manager_unacknowledge(web::Path(probe_id): web::Path<String>) -> HttpResponse {
+    match handle_unacknowledge(&probe_id) {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(_) => HttpResponse::NotFound().finish(),
+    }
+}
+
+pub async fn manager_subscriptions_list() -> HttpResponse {
+    match subscriptions::list() {
+        Ok(subscriptions) => HttpResponse::Ok().json(&subscriptions),
+        Err(err) => subscription_error_response(err),
+    }
+}
+
+pub async fn manager_subscription_add(data: Json<ManagerSubscriptionAddPayload>) -> HttpResponse {
+    // Subscriptions are not enabled? (payloads could not be signed)
+    if APP_CONF.subscriptions.is_none() == true {
+        return HttpResponse::ServiceUnavailable().finish();
+    }
+
+    // Only accept valid HTTP(S) URLs
+    match Url::parse(&data.url) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {}
+        _ => return HttpResponse::BadRequest().finish(),
+    }
+
+    match subscriptions::add(&data.url) {
+        Ok(subscription_id) => HttpResponse::Ok().json(&ManagerSubscriptionAddResponse {
+            id: subscription_id,
+        }),
+        Err(err) => subscription_error_response(err),
+    }
+}
+
+pub async fn manager_subscription_remove(
+    web::Path(subscription_id): web::Path<i64>,
+) -> HttpResponse {
+    match subscriptions::remove(subscription_id) {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(err) => subscription_error_response(err),
+    }
+}
+
+pub async fn manager_service_disable(
+    web::Path(probe_id): web::Path<String>,
+    query: web::Query<ManagerServiceDisableQuery>,
+) -> HttpResponse {
+    let duration = match disable_duration(&query) {
+        Ok(duration) => duration,
+        Err(response) => return response,
+    };
+
+    match handle_disable(&probe_id, duration) {
+        Ok(disabled_until) => HttpResponse::Ok().json(&ManagerServiceResponse {
+            id: probe_id,
+            disabled: true,
+            disabled_until: disabled_until.map(as_timestamp),
+        }),
+        Err(err) => disable_error_response(err),
+    }
+}
+
+pub async fn manager_service_enable(web::Path(probe_id): web::Path<String>) -> HttpResponse {
+    match handle_enable(&probe_id) {
+        Ok(()) => HttpResponse::Ok().json(&ManagerServiceResponse {
+            id: probe_id,
+            disabled: false,
+            disabled_until: None,
+        }),
+        Err(err) => disable_error_response(err),
+    }
+}
+
+pub async fn manager_services_disable(
+    query: web::Query<ManagerServiceDisableQuery>,
+    data: Json<ManagerServicesPayload>,
+) -> HttpResponse {
+    let duration = match disable_duration(&query) {
+        Ok(duration) => duration,
+        Err(response) => return response,
+    };
+
+    services_disable_response(handle_disable_many(
+        DisableSelector::List(&data.services),
+        duration,
+    ))
+}
+
+pub async fn manager_services_enable(data: Json<ManagerServicesPayload>) -> HttpResponse {
+    services_enable_response(handle_enable_many(DisableSelector::List(&data.services)))
+}
+
+pub async fn manager_group_disable(
+    web::Path(group_id): web::Path<String>,
+    query: web::Query<ManagerServiceDisableQuery>,
+) -> HttpResponse {
+    let duration = match disable_duration(&query) {
+        Ok(duration) => duration,
+        Err(response) => return response,
+    };
+
+    services_disable_response(handle_disable_many(
+        DisableSelector::Group(&group_id),
+        duration,
+    ))
+}
+
+pub async fn manager_group_enable(web::Path(group_id): web::Path<String>) -> HttpResponse {
+    services_enable_response(handle_enable_many(DisableSelector::Group(&group_id)))
+}
+
+pub async fn manager_probe(web::Path(probe_id): web::Path<String>) -> HttpResponse {
+    let snapshot = PROBER_SNAPSHOT.load();
+
+    match snapshot.states.probes.get(&probe_id) {
+        Some(probe) => HttpResponse::Ok().json(&ManagerProbeResponse {
+            probe: probe,
+            diagnostics: probe
+                .nodes
+                .iter()
+                .filter(|(_, node)| node.diagnostics.is_empty() == false)
+                .map(|(node_id, node)| {
+                    (
+                        node_id.as_str(),
+                        node.diagnostics
+                            .iter()
+                            .map(|(replica_id, diagnostic)| {
+                                (
+                                    replica_id.as_str(),
+                                    ManagerProbeDiagnosticResponse {
+                                        time: diagnostic
+                                            .time
+                                            .duration_since(UNIX_EPOCH)
+                                            .map(|duration| duration.as_secs())
+                                            .unwrap_or(0),
+                                        output: diagnostic.output.as_str(),
+                                    },
+                                )
+                            })
+                            .collect(),
+                    )
+                })
+                .collect(),
+        }),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
+pub async fn manager_notifier_test(web::Path(name): web::Path<String>) -> HttpResponse {
+    // Unknown notifier? (or not built in)
+    let name = match registry::find(&name) {
+        Some(name) => name,
+        None => return HttpResponse::NotFound().json(&ManagerErrorResponse { error: "not_found" }),
+    };
+
+    // Notice: notifiers perform blocking network requests, thus they are run on the thread pool
+    match web::block(move || Ok::<_, ()>(tester::run(Some(name)))).await {
+        Ok(results) if results.is_empty() == true => {
+            HttpResponse::NotFound().json(&ManagerErrorResponse {
+                error: "not_configured",
+            })
+        }
+        Ok(results) => HttpResponse::Ok().json(&ManagerNotifierTestResponse { results: results }),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+pub async fn manager_services_disabled() -> HttpResponse {
+    HttpResponse::Ok().json(&ManagerServicesDisabledResponse {
+        services: list_disabled(),
+    })
+}
+
+pub async fn manager_reload() -> HttpResponse {
+    // Notice: reloading reads files, parses templates and locks the store, which all block, \
+    //   thus it is run on the thread pool
+    match web::block(move || Ok::<_, ()>(reload())).await {
+        Ok(Ok(reloaded)) => HttpResponse::Ok().json(&ManagerReloadResponse {
+            added: reloaded.added,
+            removed: reloaded.removed,
+        }),
+        Ok(Err(ReloadError::InvalidConfig(errors))) => HttpResponse::BadRequest().json(&errors),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+// Notice: Slack interactions are authenticated with the Slack app signing secret, rather than \
+//   with the manager token (as Slack cannot be given credentials to send along).
+pub async fn notifier_slack_actions(request: HttpRequest, body: Bytes) -> HttpResponse {
+    match slack::handle_actions(&request, &body) {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(HandleActionsError::Disabled) => HttpResponse::NotFound().finish(),
+        Err(HandleActionsError::Unauthorized) => HttpResponse::Unauthorized().finish(),
+        Err(HandleActionsError::Invalid) => HttpResponse::BadRequest().finish(),
+    }
+}
+
+// Notice: Telegram updates are authenticated with the secret token that the bot webhook was \
+//   registered with, rather than with the manager token.
+pub async fn notifier_telegram_updates(request: HttpRequest, body: Bytes) -> HttpResponse {
+    match telegram::handle_update(&request, &body) {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(HandleUpdateError::Disabled) => HttpResponse::NotFound().finish(),
+        Err(HandleUpdateError::Unauthorized) => HttpResponse::Unauthorized().finish(),
+        Err(HandleUpdateError::Invalid) => HttpResponse::BadRequest().finish(),
+    }
+}
+
+fn make_status_group<'a>(
+    snapshot: &'a StoreSnapshot,
+    group_id: &str,
+) -> Option<StatusGroupResponse<'a>> {
+    let mut group = StatusGroupResponse {
+        status: Status::Healthy,
+        probes: IndexMap::new(),
+    };
+
+    for probe in snapshot.states.probes.values() {
+        if probe.groups.iter().any(|group| group == group_id) == true {
+            // Notice: disabled probes are listed, but their (stale) status is not accounted for \
+            //   in the group status, as with the general status.
+            if probe.disabled == false
+                && (probe.status == Status::Dead
+                    || (probe.status == Status::Sick && group.status != Status::Dead))
+            {
+                group.status = probe.status.to_owned();
+            }
+
+            group.probes.insert(probe.id.as_str(), probe);
+        }
+    }
+
+    // Unknown group? (no probe belongs to it)
+    if group.probes.is_empty() == true {
+        None
+    } else {
+        Some(group)
+    }
+}
+
+fn badge_response(snapshot: &StoreSnapshot, svg: String) -> HttpResponse {
+    // Notice: badges must not be cached, as they get embedded in third-party pages (they can \
+    //   still be revalidated though, which is cheap)
+    cache::with_validators(&mut HttpResponse::Ok(), snapshot)
+        .content_type("image/svg+xml")
+        .header("Cache-Control", "no-cache, max-age=0")
+        .body(svg)
+}
+
+fn verify_reporter_signature(request: &HttpRequest, probe_id: &str, body: &[u8]) -> bool {
+    // Verify request signature (if a secret applies to this probe)
+    if let Some(secret) = signature::acquire_secret(probe_id) {
+        if let Err(err) = signature::verify(request, &secret, body) {
+            warn!(
+                "rejected unsigned reporter request for probe: {} ({:?})",
+                probe_id, err
+            );
+
+            return false;
+        }
+    }
+
+    true
+}
+
+fn verify_acknowledge_link(
+    probe_id: &str,
+    expires: u64,
+    signature: &str,
+) -> Result<(), AcknowledgeLinkError> {
+    let digest = signature::decode_hex(signature).ok_or(AcknowledgeLinkError::Invalid)?;
+
+    verify_link(probe_id, expires, &digest)
+}
+
+fn heartbeat_response(probe_id: &str, node_id: &str, health: Status) -> HttpResponse {
+    // Heartbeat reports should come for 'heartbeat' nodes only
+    match handle_heartbeat_report(probe_id, node_id, health) {
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(HandleHeartbeatError::WrongMode) => HttpResponse::PreconditionFailed().finish(),
+        Err(HandleHeartbeatError::NotFound) => HttpResponse::NotFound().finish(),
+    }
+}
+
+fn incident_error_response(err: IncidentError) -> HttpResponse {
+    match err {
+        IncidentError::NotFound => HttpResponse::NotFound().finish(),
+        IncidentError::AlreadyResolved => HttpResponse::Conflict().finish(),
+        IncidentError::StorageDisabled => HttpResponse::ServiceUnavailable().finish(),
+        IncidentError::StorageFailure => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+fn disable_duration(query: &ManagerServiceDisableQuery) -> Result<Option<Duration>, HttpResponse> {
+    // Parse optional disable duration (services are disabled until enabled again otherwise)
+    match query.duration {
+        Some(ref duration) => match parse_duration(duration) {
+            Some(duration) => Ok(Some(duration)),
+            None => Err(HttpResponse::BadRequest().json(&ManagerErrorResponse {
+                error: "invalid_duration",
+            })),
+        },
+        None => Ok(None),
+    }
+}
+
+fn services_disable_response(
+    result: Result<(Vec<String>, Option<SystemTime>), HandleDisableError>,
+) -> HttpResponse {
+    match result {
+        Ok((probe_ids, disabled_until)) => HttpResponse::Ok().json(&ManagerServicesResponse {
+            services: probe_ids,
+            disabled: true,
+            disabled_until: disabled_until.map(as_timestamp),
+        }),
+        Err(err) => disable_error_response(err),
+    }
+}
+
+fn services_enable_response(result: Result<Vec<String>, HandleDisableError>) -> HttpResponse {
+    match result {
+        Ok(probe_ids) => HttpResponse::Ok().json(&ManagerServicesResponse {
+            services: probe_ids,
+            disabled: false,
+            disabled_until: None,
+        }),
+        Err(err) => disable_error_response(err),
+    }
+}
+
+fn disable_error_response(err: HandleDisableError) -> HttpResponse {
+    match err {
+        HandleDisableError::NotFound => {
+            HttpResponse::NotFound().json(&ManagerErrorResponse { error: "not_found" })
+        }
+        HandleDisableError::AlreadyDisabled => {
+            HttpResponse::Conflict().json(&ManagerErrorResponse {
+                error: "already_disabled",
+            })
+        }
+        HandleDisableError::NotDisabled => HttpResponse::Conflict().json(&ManagerErrorResponse {
+            error: "not_disabled",
+        }),
+    }
+}
+
+fn subscription_error_response(err: SubscriptionError) -> HttpResponse {
+    match err {
+        SubscriptionError::NotFound => HttpResponse::NotFound().finish(),
+        SubscriptionError::AlreadyExists => HttpResponse::Conflict().finish(),
+        SubscriptionError::StorageDisabled => HttpResponse::ServiceUnavailable().finish(),
+        SubscriptionError::StorageFailure => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+fn acknowledge_link_error_state(err: AcknowledgeLinkError) -> AcknowledgeContextState {
+    match err {
+        AcknowledgeLinkError::Invalid => AcknowledgeContextState::Invalid,
+        AcknowledgeLinkError::Expired => AcknowledgeContextState::Expired,
+    }
+}
+
+fn render_acknowledge(
+    tera: &Tera,
+    probe_id: &str,
+    state: AcknowledgeContextState,
+    acknowledged_until: Option<String>,
+) -> HttpResponse {
+    let mut response = match state {
+        AcknowledgeContextState::Confirm | AcknowledgeContextState::Acknowledged => {
+            HttpResponse::Ok()
+        }
+        AcknowledgeContextState::NotFound => HttpResponse::NotFound(),
+        AcknowledgeContextState::Expired | AcknowledgeContextState::Invalid => {
+            HttpResponse::Forbidden()
+        }
+    };
+
+    let context = AcknowledgeContext {
+        probe_id: probe_id,
+        state: state,
+        acknowledged_until: acknowledged_until,
+        config: &*INDEX_CONFIG,
+    };
+
+    match tera.render(
+        "acknowledge.tera",
+        &tera::Context::from_serialize(context).unwrap(),
+    ) {
+        Ok(body) => response
+            .content_type("text/html")
+            .header("Cache-Control", "no-store")
+            .body(body),
+        Err(err) => HttpResponse::InternalServerError().body(format!("Template Error {:?}", err)),
+    }
+}
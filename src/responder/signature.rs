@@ -0,0 +1,274 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::HttpRequest;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::prober::manager::SNAPSHOT as PROBER_SNAPSHOT;
+use crate::APP_CONF;
+
+const HEADER_SIGNATURE: &'static str = "X-Vigil-Signature";
+const HEADER_TIMESTAMP: &'static str = "X-Vigil-Timestamp";
+const SIGNATURE_PREFIX: &'static str = "sha256=";
+
+lazy_static! {
+    // Notice: maps accepted signatures to the time they can be forgotten at, as any replay \
+    //   past this time would fall out of the timestamp window anyway.
+    static ref SEEN_SIGNATURES: Mutex<HashMap<String, u64>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Debug)]
+pub enum SignatureError {
+    Missing,
+    Invalid,
+    Expired,
+    Replayed,
+}
+
+pub fn acquire_secret(probe_id: &str) -> Option<String> {
+    // Secrets set on probes take precedence over the global secret
+    let probe_secret = PROBER_SNAPSHOT
+        .load()
+        .states
+        .probes
+        .get(probe_id)
+        .and_then(|probe| probe.reporter_secret.to_owned());
+
+    probe_secret.or_else(|| APP_CONF.server.reporter_secret.to_owned())
+}
+
+pub fn verify(request: &HttpRequest, secret: &str, body: &[u8]) -> Result<(), SignatureError> {
+    let (signature, timestamp) = match (
+        acquire_header(request, HEADER_SIGNATURE),
+        acquire_header(request, HEADER_TIMESTAMP),
+    ) {
+        (Some(signature), Some(timestamp)) => (signature, timestamp),
+        _ => return Err(SignatureError::Missing),
+    };
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let window = APP_CONF.server.reporter_signature_window;
+
+    let timestamp = check_signature(signature, timestamp, secret, body, window, now)?;
+
+    check_replay(
+        &mut SEEN_SIGNATURES.lock().unwrap(),
+        signature,
+        timestamp + window,
+        now,
+    )
+}
+
+fn check_signature(
+    signature: &str,
+    timestamp: &str,
+    secret: &str,
+    body: &[u8],
+    window: u64,
+    now: u64,
+) -> Result<u64, SignatureError> {
+    let timestamp_value = timestamp.parse::<u64>().or(Err(SignatureError::Invalid))?;
+
+    let digest = signature
+        .strip_prefix(SIGNATURE_PREFIX)
+        .and_then(decode_hex)
+        .ok_or(SignatureError::Invalid)?;
+
+    // Check that signature matches (signed contents are '<timestamp>.<body>')
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac accepts any key size");
+
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+
+    mac.verify_slice(&digest).or(Err(SignatureError::Invalid))?;
+
+    // Check that report is recent enough (in either direction, to cope with clock skews)
+    if now.saturating_sub(timestamp_value) > window || timestamp_value.saturating_sub(now) > window
+    {
+        return Err(SignatureError::Expired);
+    }
+
+    Ok(timestamp_value)
+}
+
+fn check_replay(
+    seen_signatures: &mut HashMap<String, u64>,
+    signature: &str,
+    expires_at: u64,
+    now: u64,
+) -> Result<(), SignatureError> {
+    // Check that report was not already accepted (hex digests are case-insensitive)
+    let signature = signature.to_ascii_lowercase();
+
+    seen_signatures.retain(|_, seen_expires_at| *seen_expires_at > now);
+
+    if seen_signatures.contains_key(&signature) == true {
+        return Err(SignatureError::Replayed);
+    }
+
+    seen_signatures.insert(signature, expires_at);
+
+    Ok(())
+}
+
+pub fn acquire_header<'a>(request: &'a HttpRequest, name: &str) -> Option<&'a str> {
+    request
+        .headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+}
+
+pub fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    // Notice: digits are checked beforehand, as parsing would accept signs (eg. '+1')
+    if value.len() % 2 != 0 || value.bytes().all(|byte| byte.is_ascii_hexdigit()) == false {
+        return None;
+    }
+
+    (0..value.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(value.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &'static str = "secret";
+    const BODY: &'static [u8] = b"{\"replica\":\"192.168.1.10\",\"interval\":30}";
+
+    fn sign(timestamp: &str, body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(SECRET.as_bytes()).unwrap();
+
+        mac.update(timestamp.as_bytes());
+        mac.update(b".");
+        mac.update(body);
+
+        format!(
+            "{}{}",
+            SIGNATURE_PREFIX,
+            mac.finalize()
+                .into_bytes()
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<String>()
+        )
+    }
+
+    #[test]
+    fn it_accepts_valid_signatures() {
+        let signature = sign("1650000000", BODY);
+
+        assert_eq!(
+            check_signature(&signature, "1650000000", SECRET, BODY, 300, 1650000000).ok(),
+            Some(1650000000)
+        );
+        assert_eq!(
+            check_signature(
+                &signature.to_ascii_uppercase().replace("SHA256=", "sha256="),
+                "1650000000",
+                SECRET,
+                BODY,
+                300,
+                1650000000
+            )
+            .ok(),
+            Some(1650000000)
+        );
+    }
+
+    #[test]
+    fn it_rejects_invalid_signatures() {
+        let signature = sign("1650000000", BODY);
+
+        assert!(matches!(
+            check_signature(&signature, "1650000000", "other", BODY, 300, 1650000000),
+            Err(SignatureError::Invalid)
+        ));
+        assert!(matches!(
+            check_signature(&signature, "1650000000", SECRET, b"{}", 300, 1650000000),
+            Err(SignatureError::Invalid)
+        ));
+        assert!(matches!(
+            check_signature(&signature, "1650000001", SECRET, BODY, 300, 1650000000),
+            Err(SignatureError::Invalid)
+        ));
+        assert!(matches!(
+            check_signature(
+                signature.trim_start_matches(SIGNATURE_PREFIX),
+                "1650000000",
+                SECRET,
+                BODY,
+                300,
+                1650000000
+            ),
+            Err(SignatureError::Invalid)
+        ));
+        assert!(matches!(
+            check_signature(&signature, "now", SECRET, BODY, 300, 1650000000),
+            Err(SignatureError::Invalid)
+        ));
+    }
+
+    #[test]
+    fn it_rejects_signatures_out_of_window() {
+        let signature = sign("1650000000", BODY);
+
+        assert!(check_signature(&signature, "1650000000", SECRET, BODY, 300, 1650000300).is_ok());
+        assert!(check_signature(&signature, "1650000000", SECRET, BODY, 300, 1649999700).is_ok());
+
+        assert!(matches!(
+            check_signature(&signature, "1650000000", SECRET, BODY, 300, 1650000301),
+            Err(SignatureError::Expired)
+        ));
+        assert!(matches!(
+            check_signature(&signature, "1650000000", SECRET, BODY, 300, 1649999699),
+            Err(SignatureError::Expired)
+        ));
+    }
+
+    #[test]
+    fn it_rejects_replayed_signatures() {
+        let mut seen_signatures = HashMap::new();
+
+        assert!(check_replay(&mut seen_signatures, "sha256=ab", 1650000300, 1650000000).is_ok());
+        assert!(check_replay(&mut seen_signatures, "sha256=cd", 1650000300, 1650000000).is_ok());
+
+        assert!(matches!(
+            check_replay(&mut seen_signatures, "sha256=ab", 1650000300, 1650000100),
+            Err(SignatureError::Replayed)
+        ));
+        assert!(matches!(
+            check_replay(&mut seen_signatures, "sha256=AB", 1650000300, 1650000100),
+            Err(SignatureError::Replayed)
+        ));
+
+        // Signatures are forgotten once out of window
+        assert!(check_replay(&mut seen_signatures, "sha256=ab", 1650000600, 1650000300).is_ok());
+        assert_eq!(seen_signatures.len(), 1);
+    }
+
+    #[test]
+    fn it_decodes_hex() {
+        assert_eq!(decode_hex("00ff7a"), Some(vec![0x00, 0xff, 0x7a]));
+        assert_eq!(decode_hex("00FF7A"), Some(vec![0x00, 0xff, 0x7a]));
+        assert_eq!(decode_hex(""), Some(vec![]));
+        assert_eq!(decode_hex("0"), None);
+        assert_eq!(decode_hex("zz"), None);
+        assert_eq!(decode_hex("+1"), None);
+        assert_eq!(decode_hex("é0"), None);
+    }
+}
@@ -0,0 +1,253 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use actix_web::HttpRequest;
+use hmac::{Hmac, Mac};
+use reqwest::blocking::Client;
+use sha2::Sha256;
+use url::{form_urlencoded, Url};
+
+use super::signature::{acquire_header, decode_hex};
+use crate::prober::acknowledge::{handle_acknowledge, HandleAcknowledgeError};
+use crate::APP_CONF;
+
+const HEADER_SIGNATURE: &'static str = "X-Slack-Signature";
+const HEADER_TIMESTAMP: &'static str = "X-Slack-Request-Timestamp";
+const SIGNATURE_PREFIX: &'static str = "v0=";
+const SIGNATURE_WINDOW_SECONDS: u64 = 300;
+const RESPONSE_TIMEOUT_SECONDS: u64 = 10;
+
+const ACTION_ACKNOWLEDGE_PREFIX: &'static str = "acknowledge";
+
+lazy_static! {
+    static ref SLACK_RESPONSE_HTTP_CLIENT: Client = Client::builder()
+        .timeout(Duration::from_secs(RESPONSE_TIMEOUT_SECONDS))
+        .gzip(true)
+        .build()
+        .unwrap();
+}
+
+#[derive(Debug)]
+pub enum HandleActionsError {
+    Disabled,
+    Unauthorized,
+    Invalid,
+}
+
+#[derive(Deserialize)]
+struct SlackInteraction {
+    #[serde(rename = "type")]
+    _type: String,
+
+    user: Option<SlackInteractionUser>,
+    response_url: Option<String>,
+
+    #[serde(default)]
+    actions: Vec<SlackInteractionAction>,
+}
+
+#[derive(Deserialize)]
+struct SlackInteractionUser {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct SlackInteractionAction {
+    action_id: String,
+    value: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SlackResponse {
+    response_type: &'static str,
+    replace_original: bool,
+    text: String,
+}
+
+pub fn handle_actions(request: &HttpRequest, body: &[u8]) -> Result<(), HandleActionsError> {
+    let secrets = acquire_secrets();
+
+    // No Slack app configured? (interactions are not expected)
+    if secrets.is_empty() == true {
+        return Err(HandleActionsError::Disabled);
+    }
+
+    // Notice: any Slack notifier might have sent the message that was interacted with, thus \
+    //   the request is accepted if it is signed with any of the configured secrets.
+    if secrets
+        .iter()
+        .any(|secret| verify(request, secret, body) == true)
+        == false
+    {
+        warn!("rejected slack interaction with an invalid signature");
+
+        return Err(HandleActionsError::Unauthorized);
+    }
+
+    // Parse interaction (sent as a JSON-encoded 'payload' form field)
+    let interaction = form_urlencoded::parse(body)
+        .find(|(key, _)| key == "payload")
+        .and_then(|(_, payload)| serde_json::from_str::<SlackInteraction>(&payload).ok())
+        .ok_or(HandleActionsError::Invalid)?;
+
+    // Only button clicks are handled (other interactions are acknowledged, and ignored)
+    if interaction._type != "block_actions" {
+        return Ok(());
+    }
+
+    let user_id = interaction
+        .user
+        .as_ref()
+        .map(|user| user.id.as_str())
+        .unwrap_or("?");
+
+    let mut replies = Vec::new();
+
+    for action in interaction.actions.iter() {
+        // Notice: 'Open status page' buttons also trigger an interaction, which holds nothing \
+        //   to act upon (the page is opened by Slack itself).
+        if action.action_id.starts_with(ACTION_ACKNOWLEDGE_PREFIX) == false {
+            continue;
+        }
+
+        if let Some(ref probe_id) = action.value {
+            let reply = match handle_acknowledge(probe_id, None) {
+                Ok(acknowledged_until) => {
+                    info!(
+                        "acknowledged probe: {} from slack, by user: {}",
+                        probe_id, user_id
+                    );
+
+                    format!(
+                        ":white_check_mark: <@{}> acknowledged *{}* until <!date^{}^{{date_short_pretty}} {{time}}|later>.",
+                        user_id,
+                        probe_id,
+                        acknowledged_until
+                            .duration_since(UNIX_EPOCH)
+                            .map(|duration| duration.as_secs())
+                            .unwrap_or(0)
+                    )
+                }
+                Err(HandleAcknowledgeError::NotFound) => format!(
+                    ":warning: Could not acknowledge *{}* (not found).",
+                    probe_id
+                ),
+                Err(HandleAcknowledgeError::InvalidDuration) => format!(
+                    ":warning: Could not acknowledge *{}* (invalid duration).",
+                    probe_id
+                ),
+            };
+
+            replies.push(reply);
+        }
+    }
+
+    // Reply in channel (in the background, as Slack expects interactions to be acknowledged \
+    //   within 3 seconds)
+    if let (Some(response_url), false) = (interaction.response_url, replies.is_empty()) {
+        thread::spawn(move || respond(&response_url, replies.join("\n")));
+    }
+
+    Ok(())
+}
+
+fn acquire_secrets() -> Vec<&'static str> {
+    let mut secrets = Vec::new();
+
+    if let Some(ref notify) = APP_CONF.notify {
+        let slacks = notify.slack.iter().chain(
+            notify
+                .channels
+                .values()
+                .filter_map(|channel| channel.slack.as_ref()),
+        );
+
+        for slack in slacks {
+            if let Some(ref signing_secret) = slack.signing_secret {
+                secrets.push(signing_secret.as_str());
+            }
+        }
+    }
+
+    secrets
+}
+
+fn verify(request: &HttpRequest, secret: &str, body: &[u8]) -> bool {
+    let (signature, timestamp) = match (
+        acquire_header(request, HEADER_SIGNATURE),
+        acquire_header(request, HEADER_TIMESTAMP),
+    ) {
+        (Some(signature), Some(timestamp)) => (signature, timestamp),
+        _ => return false,
+    };
+
+    // Check that request is recent enough (this prevents replays)
+    let timestamp_value = match timestamp.parse::<u64>() {
+        Ok(timestamp_value) => timestamp_value,
+        Err(_) => return false,
+    };
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+
+    if now.saturating_sub(timestamp_value) > SIGNATURE_WINDOW_SECONDS
+        || timestamp_value.saturating_sub(now) > SIGNATURE_WINDOW_SECONDS
+    {
+        return false;
+    }
+
+    // Check that signature matches (signed contents are 'v0:<timestamp>:<body>')
+    let digest = match signature
+        .strip_prefix(SIGNATURE_PREFIX)
+        .and_then(decode_hex)
+    {
+        Some(digest) => digest,
+        None => return false,
+    };
+
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac accepts any key size");
+
+    mac.update(b"v0:");
+    mac.update(timestamp.as_bytes());
+    mac.update(b":");
+    mac.update(body);
+
+    mac.verify_slice(&digest).is_ok()
+}
+
+fn respond(response_url: &str, text: String) {
+    // Only reply to Slack URLs (the response URL comes from the request payload)
+    match Url::parse(response_url) {
+        Ok(url) if url.scheme() == "https" && url.host_str() == Some("hooks.slack.com") => {}
+        _ => {
+            warn!("ignored unexpected slack response url: {}", response_url);
+
+            return;
+        }
+    }
+
+    let response = SLACK_RESPONSE_HTTP_CLIENT
+        .post(response_url)
+        .json(&SlackResponse {
+            response_type: "in_channel",
+            replace_original: false,
+            text: text,
+        })
+        .send();
+
+    match response {
+        Ok(response) if response.status().is_success() == true => {
+            debug!("replied to slack interaction");
+        }
+        _ => warn!("could not reply to slack interaction"),
+    }
+}
@@ -0,0 +1,79 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use actix::{Actor, ActorContext, AsyncContext, StreamHandler};
+use actix_web_actors::ws;
+use futures::channel::mpsc::Receiver;
+
+use crate::prober::events::StateEventsMessage;
+
+pub struct StatusSocket {
+    receiver: Option<Receiver<StateEventsMessage>>,
+}
+
+impl StatusSocket {
+    pub fn new(receiver: Receiver<StateEventsMessage>) -> Self {
+        StatusSocket {
+            receiver: Some(receiver),
+        }
+    }
+}
+
+impl Actor for StatusSocket {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, context: &mut Self::Context) {
+        debug!("status socket connected");
+
+        // Forward state changes to the socket
+        if let Some(receiver) = self.receiver.take() {
+            context.add_stream(receiver);
+        }
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for StatusSocket {
+    fn handle(
+        &mut self,
+        message: Result<ws::Message, ws::ProtocolError>,
+        context: &mut Self::Context,
+    ) {
+        // Notice: clients are not expected to send anything, apart from control frames
+        match message {
+            Ok(ws::Message::Ping(bytes)) => context.pong(&bytes),
+            Ok(ws::Message::Close(reason)) => {
+                context.close(reason);
+                context.stop();
+            }
+            Err(_) => context.stop(),
+            _ => {}
+        }
+    }
+}
+
+impl StreamHandler<StateEventsMessage> for StatusSocket {
+    fn handle(&mut self, message: StateEventsMessage, context: &mut Self::Context) {
+        if message.is_empty() == true {
+            // Keep connection alive (also helps detecting dead clients)
+            context.ping(b"");
+        } else {
+            // Send all state changes as a single JSON array (ie. an incremental diff)
+            context.text(format!("[{}]", message.join(",")));
+        }
+    }
+
+    fn finished(&mut self, context: &mut Self::Context) {
+        // Notice: state changes stop if the socket did not keep up with them, thus the client \
+        //   is told to come back later (it would miss state changes otherwise).
+        debug!("status socket lagging, closing it");
+
+        context.close(Some(ws::CloseReason {
+            code: ws::CloseCode::Again,
+            description: Some("lagging".to_string()),
+        }));
+        context.stop();
+    }
+}
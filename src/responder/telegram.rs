@@ -0,0 +1,235 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::thread;
+use std::time::Duration;
+
+use actix_web::HttpRequest;
+use openssl::memcmp;
+use reqwest::blocking::Client;
+
+use crate::config::config::ConfigNotifyTelegram;
+use crate::prober::acknowledge::{handle_acknowledge, HandleAcknowledgeError};
+use crate::APP_CONF;
+
+const HEADER_SECRET_TOKEN: &'static str = "X-Telegram-Bot-Api-Secret-Token";
+const CALLBACK_ACKNOWLEDGE_PREFIX: &'static str = "ack:";
+const RESPONSE_TIMEOUT_SECONDS: u64 = 10;
+
+static TELEGRAM_API_BASE_URL: &'static str = "https://api.telegram.org";
+
+lazy_static! {
+    static ref TELEGRAM_RESPONSE_HTTP_CLIENT: Client = Client::builder()
+        .timeout(Duration::from_secs(RESPONSE_TIMEOUT_SECONDS))
+        .gzip(true)
+        .build()
+        .unwrap();
+}
+
+#[derive(Debug)]
+pub enum HandleUpdateError {
+    Disabled,
+    Unauthorized,
+    Invalid,
+}
+
+#[derive(Deserialize)]
+struct TelegramUpdate {
+    callback_query: Option<TelegramCallbackQuery>,
+}
+
+#[derive(Deserialize)]
+struct TelegramCallbackQuery {
+    id: String,
+    from: TelegramUser,
+    message: Option<TelegramMessage>,
+    data: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TelegramUser {
+    first_name: String,
+    username: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TelegramMessage {
+    message_id: i64,
+    message_thread_id: Option<i64>,
+    chat: TelegramChat,
+}
+
+#[derive(Deserialize)]
+struct TelegramChat {
+    id: i64,
+}
+
+#[derive(Serialize)]
+struct TelegramAnswerCallbackQuery<'a> {
+    callback_query_id: &'a str,
+    text: &'a str,
+}
+
+#[derive(Serialize)]
+struct TelegramReply<'a> {
+    chat_id: i64,
+    text: &'a str,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message_thread_id: Option<i64>,
+
+    reply_to_message_id: i64,
+    allow_sending_without_reply: bool,
+}
+
+pub fn handle_update(request: &HttpRequest, body: &[u8]) -> Result<(), HandleUpdateError> {
+    let secret_token = request
+        .headers()
+        .get(HEADER_SECRET_TOKEN)
+        .and_then(|value| value.to_str().ok());
+
+    // Notice: the bot that the update is for is found from the secret token that it was \
+    //   registered with, as a webhook URL is shared by all bots.
+    let telegrams = acquire_telegrams();
+
+    if telegrams.is_empty() == true {
+        return Err(HandleUpdateError::Disabled);
+    }
+
+    let telegram = secret_token
+        .filter(|secret_token| secret_token.is_empty() == false)
+        .and_then(|secret_token| {
+            telegrams.into_iter().find(|telegram| {
+                let webhook_secret = telegram.webhook_secret.as_deref().unwrap_or("");
+
+                webhook_secret.len() == secret_token.len()
+                    && memcmp::eq(webhook_secret.as_bytes(), secret_token.as_bytes()) == true
+            })
+        })
+        .ok_or_else(|| {
+            warn!("rejected telegram update with an invalid secret token");
+
+            HandleUpdateError::Unauthorized
+        })?;
+
+    let update =
+        serde_json::from_slice::<TelegramUpdate>(body).or(Err(HandleUpdateError::Invalid))?;
+
+    // Only button clicks are handled (other updates are acknowledged, and ignored)
+    let callback_query = match update.callback_query {
+        Some(callback_query) => callback_query,
+        None => return Ok(()),
+    };
+
+    let probe_id = match callback_query
+        .data
+        .as_deref()
+        .and_then(|data| data.strip_prefix(CALLBACK_ACKNOWLEDGE_PREFIX))
+    {
+        Some(probe_id) => probe_id.to_owned(),
+        None => return Ok(()),
+    };
+
+    let user_name = callback_query
+        .from
+        .username
+        .as_ref()
+        .map(|username| format!("@{}", username))
+        .unwrap_or_else(|| callback_query.from.first_name.to_owned());
+
+    let (answer, reply) = match handle_acknowledge(&probe_id, None) {
+        Ok(_) => {
+            info!(
+                "acknowledged probe: {} from telegram, by user: {}",
+                probe_id, user_name
+            );
+
+            (
+                format!("Acknowledged: {}", probe_id),
+                Some(format!("✅ {} acknowledged: {}", user_name, probe_id)),
+            )
+        }
+        Err(HandleAcknowledgeError::NotFound) => (
+            format!("Could not acknowledge: {} (not found)", probe_id),
+            None,
+        ),
+        Err(HandleAcknowledgeError::InvalidDuration) => (
+            format!("Could not acknowledge: {} (invalid duration)", probe_id),
+            None,
+        ),
+    };
+
+    // Answer callback query, and reply in chat (in the background, as this calls the Telegram \
+    //   API, which might be slow)
+    let bot_token = telegram.bot_token.to_owned();
+
+    thread::spawn(move || {
+        respond(
+            &bot_token,
+            "answerCallbackQuery",
+            &TelegramAnswerCallbackQuery {
+                callback_query_id: &callback_query.id,
+                text: &answer,
+            },
+        );
+
+        if let (Some(reply), Some(message)) = (reply, callback_query.message) {
+            respond(
+                &bot_token,
+                "sendMessage",
+                &TelegramReply {
+                    chat_id: message.chat.id,
+                    text: &reply,
+                    message_thread_id: message.message_thread_id,
+                    reply_to_message_id: message.message_id,
+                    allow_sending_without_reply: true,
+                },
+            );
+        }
+    });
+
+    Ok(())
+}
+
+fn acquire_telegrams() -> Vec<&'static ConfigNotifyTelegram> {
+    let mut telegrams = Vec::new();
+
+    if let Some(ref notify) = APP_CONF.notify {
+        let channel_telegrams = notify.telegram.iter().chain(
+            notify
+                .channels
+                .values()
+                .filter_map(|channel| channel.telegram.as_ref()),
+        );
+
+        // Notice: bots with an empty secret are skipped, as they would accept any update that \
+        //   comes with an empty secret token.
+        for telegram in channel_telegrams {
+            if telegram.webhook_secret.as_deref().map(str::is_empty) == Some(false) {
+                telegrams.push(telegram);
+            }
+        }
+    }
+
+    telegrams
+}
+
+fn respond<P: serde::Serialize>(bot_token: &str, method: &str, payload: &P) {
+    let response = TELEGRAM_RESPONSE_HTTP_CLIENT
+        .post(&format!(
+            "{}/bot{}/{}",
+            TELEGRAM_API_BASE_URL, bot_token, method
+        ))
+        .json(payload)
+        .send();
+
+    match response {
+        Ok(response) if response.status().is_success() == true => {
+            debug!("called telegram method: {}", method);
+        }
+        _ => warn!("could not call telegram method: {}", method),
+    }
+}
@@ -0,0 +1,225 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::fs;
+use std::sync::RwLock;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use openssl::error::ErrorStack;
+use openssl::ssl::{
+    ClientHelloResponse, SslAcceptor, SslAcceptorBuilder, SslContext, SslFiletype, SslMethod,
+};
+
+use crate::config::config::ConfigServerTLS;
+use crate::APP_CONF;
+
+lazy_static! {
+    static ref TLS_CONTEXT: RwLock<Option<SslContext>> = RwLock::new(None);
+}
+
+pub fn make_acceptor(config: &ConfigServerTLS) -> Result<SslAcceptorBuilder, ErrorStack> {
+    let mut builder = make_builder(config)?;
+
+    *TLS_CONTEXT.write().unwrap() = Some(make_builder(config)?.build().into_context());
+
+    // Notice: the certificate loaded in the acceptor is never replaced, thus the context holding \
+    //   the latest certificate gets swapped in upon each handshake (so that a reloaded \
+    //   certificate is used without restarting the listener). This is done once the client \
+    //   hello is received, as clients that do not send a server name would not be handled by a \
+    //   server name callback.
+    builder.set_client_hello_callback(|ssl, _| {
+        if let Some(ref context) = *TLS_CONTEXT.read().unwrap() {
+            ssl.set_ssl_context(context)?;
+        }
+
+        Ok(ClientHelloResponse::SUCCESS)
+    });
+
+    Ok(builder)
+}
+
+pub fn run_reload() {
+    // Notice: the TLS configuration is read once, as the listener is bound upon startup and \
+    //   never re-bound; as for any '[server]' option, enabling TLS or changing its paths thus \
+    //   requires a restart, even if the configuration gets reloaded.
+    let tls = match APP_CONF.server.tls {
+        Some(ref tls) if tls.reload_interval.is_some() => tls.clone(),
+        _ => {
+            debug!("tls reload is disabled, stopping reload loop");
+
+            return;
+        }
+    };
+
+    let reload_interval = Duration::from_secs(tls.reload_interval.unwrap_or(0));
+    let mut modified_at = acquire_modified_at(&tls);
+
+    loop {
+        thread::sleep(reload_interval);
+
+        let current_modified_at = acquire_modified_at(&tls);
+
+        if current_modified_at != modified_at {
+            debug!("tls certificate or key changed, reloading...");
+
+            match reload(&tls) {
+                Ok(_) => {
+                    modified_at = current_modified_at;
+
+                    info!("reloaded tls certificate");
+                }
+                Err(err) => {
+                    // Notice: keep on serving the previous certificate, and retry on next tick \
+                    //   (eg. the key might have been written, but not yet the certificate)
+                    error!("could not reload tls certificate: {}", err);
+                }
+            }
+        }
+    }
+}
+
+fn reload(config: &ConfigServerTLS) -> Result<(), ErrorStack> {
+    let context = make_builder(config)?.build().into_context();
+
+    *TLS_CONTEXT.write().unwrap() = Some(context);
+
+    Ok(())
+}
+
+fn make_builder(config: &ConfigServerTLS) -> Result<SslAcceptorBuilder, ErrorStack> {
+    let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls())?;
+
+    builder.set_private_key_file(&config.key_path, SslFiletype::PEM)?;
+    builder.set_certificate_chain_file(&config.certificate_path)?;
+    builder.check_private_key()?;
+
+    Ok(builder)
+}
+
+fn acquire_modified_at(tls: &ConfigServerTLS) -> Option<(SystemTime, SystemTime)> {
+    let certificate_modified_at =
+        fs::metadata(&tls.certificate_path).and_then(|metadata| metadata.modified());
+    let key_modified_at = fs::metadata(&tls.key_path).and_then(|metadata| metadata.modified());
+
+    match (certificate_modified_at, key_modified_at) {
+        (Ok(certificate_modified_at), Ok(key_modified_at)) => {
+            Some((certificate_modified_at, key_modified_at))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::net::{TcpListener, TcpStream};
+    use std::path::PathBuf;
+
+    use openssl::asn1::Asn1Time;
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::PKey;
+    use openssl::rsa::Rsa;
+    use openssl::ssl::{SslConnector, SslVerifyMode};
+    use openssl::x509::{X509NameBuilder, X509};
+
+    fn write_certificate(name: &str) -> ConfigServerTLS {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+
+        let mut subject = X509NameBuilder::new().unwrap();
+
+        subject.append_entry_by_text("CN", name).unwrap();
+
+        let subject = subject.build();
+
+        let mut certificate = X509::builder().unwrap();
+
+        certificate.set_version(2).unwrap();
+        certificate.set_subject_name(&subject).unwrap();
+        certificate.set_issuer_name(&subject).unwrap();
+        certificate.set_pubkey(&key).unwrap();
+        certificate
+            .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        certificate
+            .set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        certificate.sign(&key, MessageDigest::sha256()).unwrap();
+
+        let directory = std::env::temp_dir();
+
+        let (certificate_path, key_path) = (
+            directory.join(format!("vigil-test-{}-{}.crt", std::process::id(), name)),
+            directory.join(format!("vigil-test-{}-{}.key", std::process::id(), name)),
+        );
+
+        fs::write(&certificate_path, certificate.build().to_pem().unwrap()).unwrap();
+        fs::write(&key_path, key.private_key_to_pem_pkcs8().unwrap()).unwrap();
+
+        ConfigServerTLS {
+            certificate_path: certificate_path,
+            key_path: key_path,
+            reload_interval: None,
+        }
+    }
+
+    fn remove_certificate(config: ConfigServerTLS) {
+        for path in [config.certificate_path, config.key_path] {
+            fs::remove_file::<PathBuf>(path).ok();
+        }
+    }
+
+    fn acquire_served_name(acceptor: &SslAcceptor, server_name: bool) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let client = thread::spawn(move || {
+            let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+
+            connector.set_verify(SslVerifyMode::NONE);
+
+            let stream = connector
+                .build()
+                .configure()
+                .unwrap()
+                .use_server_name_indication(server_name)
+                .connect("vigil.test", TcpStream::connect(address).unwrap())
+                .unwrap();
+
+            let certificate = stream.ssl().peer_certificate().unwrap();
+            let name = certificate.subject_name().entries().next().unwrap();
+
+            name.data().as_utf8().unwrap().to_string()
+        });
+
+        let (stream, _) = listener.accept().unwrap();
+
+        // Notice: the server stream is kept until the client is done reading the certificate
+        let _stream = acceptor.accept(stream).unwrap();
+
+        client.join().unwrap()
+    }
+
+    #[test]
+    fn it_serves_reloaded_certificates() {
+        let (first, second) = (write_certificate("first"), write_certificate("second"));
+
+        let acceptor = make_acceptor(&first).unwrap().build();
+
+        assert_eq!(acquire_served_name(&acceptor, true), "first");
+        assert_eq!(acquire_served_name(&acceptor, false), "first");
+
+        // Notice: the acceptor is not re-built, as the listener would keep its acceptor
+        reload(&second).unwrap();
+
+        assert_eq!(acquire_served_name(&acceptor, true), "second");
+        assert_eq!(acquire_served_name(&acceptor, false), "second");
+
+        remove_certificate(first);
+        remove_certificate(second);
+    }
+}
@@ -0,0 +1,346 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use rusqlite::{params, Connection, OptionalExtension};
+use time;
+use time::format_description::FormatItem;
+
+use super::sqlite as storage;
+use crate::prober::manager::mark_snapshot_dirty;
+use crate::prober::status::Status;
+use crate::APP_CONF;
+
+const SECONDS_IN_DAY: i64 = 86400;
+const TIMELINE_RESOLVED_DAYS: i64 = 7;
+
+lazy_static! {
+    static ref INCIDENT_DATE_FORMATTER: Vec<FormatItem<'static>> =
+        time::format_description::parse("[year]-[month]-[day] [hour]:[minute] UTC")
+            .expect("invalid time format");
+}
+
+#[derive(Serialize)]
+pub struct Incident {
+    pub id: i64,
+    pub title: String,
+    pub probe_id: Option<String>,
+    pub automatic: bool,
+    pub opened_at: i64,
+    pub opened_date: String,
+    pub resolved_at: Option<i64>,
+    pub resolved_date: Option<String>,
+    pub updates: Vec<IncidentUpdate>,
+}
+
+#[derive(Serialize)]
+pub struct IncidentUpdate {
+    pub kind: IncidentUpdateKind,
+    pub time: i64,
+    pub date: String,
+    pub message: String,
+}
+
+#[derive(Serialize, PartialEq)]
+pub enum IncidentUpdateKind {
+    #[serde(rename = "update")]
+    Update,
+
+    #[serde(rename = "resolution")]
+    Resolution,
+}
+
+pub enum IncidentError {
+    StorageDisabled,
+    StorageFailure,
+    NotFound,
+    AlreadyResolved,
+}
+
+impl IncidentUpdateKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            &IncidentUpdateKind::Update => "update",
+            &IncidentUpdateKind::Resolution => "resolution",
+        }
+    }
+
+    fn from_name(value: &str) -> Self {
+        match value {
+            "resolution" => IncidentUpdateKind::Resolution,
+            _ => IncidentUpdateKind::Update,
+        }
+    }
+}
+
+pub fn open(
+    title: &str,
+    probe_id: Option<&str>,
+    automatic: bool,
+    message: Option<&str>,
+) -> Result<i64, IncidentError> {
+    let time = storage::time_now();
+
+    let result = storage::with_connection(|connection| {
+        let transaction = connection.transaction()?;
+
+        transaction.execute(
+            "INSERT INTO incidents (title, probe_id, automatic, opened_at) VALUES (?1, ?2, ?3, ?4)",
+            params![title, probe_id, automatic, time],
+        )?;
+
+        let incident_id = transaction.last_insert_rowid();
+
+        if let Some(message) = message {
+            insert_update(
+                &transaction,
+                incident_id,
+                time,
+                IncidentUpdateKind::Update,
+                message,
+            )?;
+        }
+
+        transaction.commit()?;
+
+        Ok(incident_id)
+    });
+
+    match result {
+        Some(Ok(incident_id)) => {
+            info!("opened incident #{}: {}", incident_id, title);
+
+            mark_snapshot_dirty();
+
+            Ok(incident_id)
+        }
+        Some(Err(err)) => {
+            error!("could not open incident: {}", err);
+
+            Err(IncidentError::StorageFailure)
+        }
+        None => Err(IncidentError::StorageDisabled),
+    }
+}
+
+pub fn update(incident_id: i64, message: &str) -> Result<(), IncidentError> {
+    append(incident_id, IncidentUpdateKind::Update, Some(message))
+}
+
+pub fn resolve(incident_id: i64, message: Option<&str>) -> Result<(), IncidentError> {
+    append(incident_id, IncidentUpdateKind::Resolution, message)
+}
+
+pub fn list(resolved_since: i64) -> Result<Vec<Incident>, IncidentError> {
+    let result = storage::with_connection(|connection| {
+        let mut incidents = Vec::new();
+
+        let mut statement = connection.prepare(
+            "SELECT id, title, probe_id, automatic, opened_at, resolved_at FROM incidents \
+                WHERE resolved_at IS NULL OR resolved_at >= ?1 ORDER BY opened_at DESC, id DESC",
+        )?;
+
+        let rows = statement.query_map(params![resolved_since], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, bool>(3)?,
+                row.get::<_, i64>(4)?,
+                row.get::<_, Option<i64>>(5)?,
+            ))
+        })?;
+
+        for row in rows {
+            let (id, title, probe_id, automatic, opened_at, resolved_at) = row?;
+
+            incidents.push(Incident {
+                id: id,
+                title: title,
+                probe_id: probe_id,
+                automatic: automatic,
+                opened_at: opened_at,
+                opened_date: format_date(opened_at),
+                resolved_at: resolved_at,
+                resolved_date: resolved_at.map(format_date),
+                updates: list_updates(connection, id)?,
+            });
+        }
+
+        Ok(incidents)
+    });
+
+    match result {
+        Some(Ok(incidents)) => Ok(incidents),
+        Some(Err(err)) => {
+            error!("could not list incidents: {}", err);
+
+            Err(IncidentError::StorageFailure)
+        }
+        None => Err(IncidentError::StorageDisabled),
+    }
+}
+
+pub fn timeline() -> Vec<Incident> {
+    // Show ongoing incidents, plus incidents that were resolved recently
+    list(storage::time_now() - TIMELINE_RESOLVED_DAYS * SECONDS_IN_DAY).unwrap_or(Vec::new())
+}
+
+pub fn handle_probe_transition(probe_id: &str, probe_label: &str, status: &Status) {
+    // Automatic incidents enabled?
+    if let Some(ref incidents) = APP_CONF.incidents {
+        if incidents.automatic == false {
+            return;
+        }
+
+        let open_incident_id = storage::with_connection(|connection| {
+            connection
+                .query_row(
+                    "SELECT id FROM incidents \
+                        WHERE probe_id = ?1 AND automatic = 1 AND resolved_at IS NULL \
+                        ORDER BY id DESC LIMIT 1",
+                    params![probe_id],
+                    |row| row.get::<_, i64>(0),
+                )
+                .optional()
+        })
+        .and_then(|result| result.ok())
+        .flatten();
+
+        match (status, open_incident_id) {
+            (&Status::Dead, None) => {
+                debug!("opening automatic incident for probe: {}", probe_id);
+
+                open(
+                    &format!("{} outage", probe_label),
+                    Some(probe_id),
+                    true,
+                    Some(&format!("{} is not working as expected.", probe_label)),
+                )
+                .ok();
+            }
+            (&Status::Healthy, Some(incident_id)) | (&Status::Sick, Some(incident_id)) => {
+                debug!("resolving automatic incident for probe: {}", probe_id);
+
+                resolve(
+                    incident_id,
+                    Some(&format!("{} has recovered.", probe_label)),
+                )
+                .ok();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn append(
+    incident_id: i64,
+    kind: IncidentUpdateKind,
+    message: Option<&str>,
+) -> Result<(), IncidentError> {
+    let time = storage::time_now();
+
+    let result = storage::with_connection(|connection| {
+        let transaction = connection.transaction()?;
+
+        let resolved_at = transaction
+            .query_row(
+                "SELECT resolved_at FROM incidents WHERE id = ?1",
+                params![incident_id],
+                |row| row.get::<_, Option<i64>>(0),
+            )
+            .optional()?;
+
+        // Incident must exist and still be open
+        match resolved_at {
+            None => return Ok(Err(IncidentError::NotFound)),
+            Some(Some(_)) => return Ok(Err(IncidentError::AlreadyResolved)),
+            Some(None) => {}
+        }
+
+        if kind == IncidentUpdateKind::Resolution {
+            transaction.execute(
+                "UPDATE incidents SET resolved_at = ?1 WHERE id = ?2",
+                params![time, incident_id],
+            )?;
+        }
+
+        if let Some(message) = message {
+            insert_update(&transaction, incident_id, time, kind, message)?;
+        }
+
+        transaction.commit()?;
+
+        Ok(Ok(()))
+    });
+
+    match result {
+        Some(Ok(Ok(()))) => {
+            mark_snapshot_dirty();
+
+            Ok(())
+        }
+        Some(Ok(result)) => result,
+        Some(Err(err)) => {
+            error!("could not append to incident #{}: {}", incident_id, err);
+
+            Err(IncidentError::StorageFailure)
+        }
+        None => Err(IncidentError::StorageDisabled),
+    }
+}
+
+fn insert_update(
+    connection: &Connection,
+    incident_id: i64,
+    time: i64,
+    kind: IncidentUpdateKind,
+    message: &str,
+) -> rusqlite::Result<usize> {
+    connection.execute(
+        "INSERT INTO incident_updates (incident_id, time, kind, message) VALUES (?1, ?2, ?3, ?4)",
+        params![incident_id, time, kind.as_str(), message],
+    )
+}
+
+fn list_updates(
+    connection: &Connection,
+    incident_id: i64,
+) -> rusqlite::Result<Vec<IncidentUpdate>> {
+    let mut statement = connection.prepare(
+        "SELECT kind, time, message FROM incident_updates \
+            WHERE incident_id = ?1 ORDER BY time DESC, id DESC",
+    )?;
+
+    let rows = statement.query_map(params![incident_id], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, i64>(1)?,
+            row.get::<_, String>(2)?,
+        ))
+    })?;
+
+    let mut updates = Vec::new();
+
+    for row in rows {
+        let (kind, time, message) = row?;
+
+        updates.push(IncidentUpdate {
+            kind: IncidentUpdateKind::from_name(&kind),
+            time: time,
+            date: format_date(time),
+            message: message,
+        });
+    }
+
+    Ok(updates)
+}
+
+fn format_date(time: i64) -> String {
+    time::OffsetDateTime::from_unix_timestamp(time)
+        .ok()
+        .and_then(|date| date.format(&INCIDENT_DATE_FORMATTER).ok())
+        .unwrap_or("?".to_string())
+}
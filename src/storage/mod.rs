@@ -0,0 +1,10 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+pub mod incidents;
+pub mod sqlite;
+pub mod subscriptions;
+pub mod uptime;
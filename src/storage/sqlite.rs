@@ -0,0 +1,446 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+use time;
+
+use rusqlite::{params, Connection};
+
+use crate::prober::status::Status;
+use crate::APP_CONF;
+
+const SECONDS_IN_DAY: i64 = 86400;
+
+static SCHEMA: &'static str = "
+    CREATE TABLE IF NOT EXISTS replica_states (
+        probe_id TEXT NOT NULL,
+        node_id TEXT NOT NULL,
+        replica_id TEXT NOT NULL,
+        status TEXT NOT NULL,
+        updated_at INTEGER NOT NULL,
+
+        PRIMARY KEY (probe_id, node_id, replica_id)
+    );
+
+    CREATE TABLE IF NOT EXISTS transitions (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        time INTEGER NOT NULL,
+        probe_id TEXT,
+        node_id TEXT,
+        status TEXT NOT NULL
+    );
+
+    CREATE INDEX IF NOT EXISTS transitions_lookup ON transitions (probe_id, node_id, time);
+
+    CREATE TABLE IF NOT EXISTS incidents (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        title TEXT NOT NULL,
+        probe_id TEXT,
+        automatic INTEGER NOT NULL,
+        opened_at INTEGER NOT NULL,
+        resolved_at INTEGER
+    );
+
+    CREATE TABLE IF NOT EXISTS incident_updates (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        incident_id INTEGER NOT NULL REFERENCES incidents (id) ON DELETE CASCADE,
+        time INTEGER NOT NULL,
+        kind TEXT NOT NULL,
+        message TEXT NOT NULL
+    );
+
+    CREATE INDEX IF NOT EXISTS incident_updates_lookup ON incident_updates (incident_id, time);
+
+    CREATE TABLE IF NOT EXISTS subscriptions (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        url TEXT NOT NULL UNIQUE,
+        created_at INTEGER NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS disabled_services (
+        probe_id TEXT PRIMARY KEY,
+        disabled_at INTEGER NOT NULL,
+        disabled_until INTEGER
+    );
+";
+
+type ReplicaStateKey = (String, String, String);
+
+lazy_static! {
+    static ref STORAGE: Option<Mutex<Connection>> = open();
+
+    // Notice: replica states are saved upon each aggregate operation, though most of them did \
+    //   not change; stored states are remembered, so that only changed states get written.
+    static ref STORED_REPLICA_STATES: Mutex<HashMap<ReplicaStateKey, Status>> =
+        Mutex::new(HashMap::new());
+}
+
+pub struct StorageTransition {
+    pub probe_id: Option<String>,
+    pub node_id: Option<String>,
+    pub status: Status,
+}
+
+pub struct StorageReplicaState {
+    pub probe_id: String,
+    pub node_id: String,
+    pub replica_id: String,
+    pub status: Status,
+}
+
+fn open() -> Option<Mutex<Connection>> {
+    if let Some(ref storage) = APP_CONF.storage {
+        debug!("opening storage at path: {:?}", storage.path);
+
+        let connection = Connection::open(&storage.path).expect("cannot open storage");
+
+        connection
+            .execute_batch(SCHEMA)
+            .expect("cannot initialize storage schema");
+
+        info!("opened storage at path: {:?}", storage.path);
+
+        Some(Mutex::new(connection))
+    } else {
+        None
+    }
+}
+
+pub fn time_now() -> i64 {
+    time::OffsetDateTime::now_utc().unix_timestamp()
+}
+
+pub fn initialize() {
+    prune();
+}
+
+pub fn prune() {
+    if let (Some(ref storage), Some(ref connection)) = (&APP_CONF.storage, &*STORAGE) {
+        // Prune expired history entries (as the history would grow indefinitely otherwise)
+        // Notice: the last transition of each probe, node and of the general status is always \
+        //   kept, as it holds the status that is still in effect (even if it is older than the \
+        //   retention period), which uptime computation and status restoration rely upon.
+        let prune_before = time_now() - (storage.history_retention as i64) * SECONDS_IN_DAY;
+
+        match connection.lock().unwrap().execute(
+            "DELETE FROM transitions WHERE time < ?1 AND id NOT IN (
+                SELECT MAX(id) FROM transitions GROUP BY probe_id, node_id
+            )",
+            params![prune_before],
+        ) {
+            Ok(count) => info!("pruned {} expired transitions from storage", count),
+            Err(err) => error!("could not prune expired transitions from storage: {}", err),
+        }
+    }
+}
+
+pub fn is_enabled() -> bool {
+    STORAGE.is_some()
+}
+
+pub fn with_connection<T, F>(operation: F) -> Option<rusqlite::Result<T>>
+where
+    F: FnOnce(&mut Connection) -> rusqlite::Result<T>,
+{
+    STORAGE
+        .as_ref()
+        .map(|connection| operation(&mut connection.lock().unwrap()))
+}
+
+pub fn save_replica_states(replica_states: &[StorageReplicaState]) {
+    if let Some(ref connection) = *STORAGE {
+        let mut stored_replica_states = STORED_REPLICA_STATES.lock().unwrap();
+
+        let changed_replica_states: Vec<&StorageReplicaState> = replica_states
+            .iter()
+            .filter(|replica_state| {
+                stored_replica_states.get(&(
+                    replica_state.probe_id.to_owned(),
+                    replica_state.node_id.to_owned(),
+                    replica_state.replica_id.to_owned(),
+                )) != Some(&replica_state.status)
+            })
+            .collect();
+
+        if changed_replica_states.is_empty() == true {
+            return;
+        }
+
+        let mut connection = connection.lock().unwrap();
+        let updated_at = time_now();
+
+        let result = connection.transaction().and_then(|transaction| {
+            for replica_state in changed_replica_states.iter() {
+                transaction.execute(
+                    "INSERT OR REPLACE INTO replica_states \
+                        (probe_id, node_id, replica_id, status, updated_at) \
+                        VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        replica_state.probe_id,
+                        replica_state.node_id,
+                        replica_state.replica_id,
+                        replica_state.status.as_str(),
+                        updated_at
+                    ],
+                )?;
+            }
+
+            transaction.commit()
+        });
+
+        match result {
+            Ok(()) => {
+                for replica_state in changed_replica_states {
+                    stored_replica_states.insert(
+                        (
+                            replica_state.probe_id.to_owned(),
+                            replica_state.node_id.to_owned(),
+                            replica_state.replica_id.to_owned(),
+                        ),
+                        replica_state.status.to_owned(),
+                    );
+                }
+            }
+            Err(err) => error!("could not save replica states to storage: {}", err),
+        }
+    }
+}
+
+pub fn save_transitions(transitions: &[StorageTransition]) {
+    if let Some(ref connection) = *STORAGE {
+        let mut connection = connection.lock().unwrap();
+        let time = time_now();
+
+        let result = connection.transaction().and_then(|transaction| {
+            for transition in transitions {
+                transaction.execute(
+                    "INSERT INTO transitions (time, probe_id, node_id, status) \
+                        VALUES (?1, ?2, ?3, ?4)",
+                    params![
+                        time,
+                        transition.probe_id,
+                        transition.node_id,
+                        transition.status.as_str()
+                    ],
+                )?;
+            }
+
+            transaction.commit()
+        });
+
+        if let Err(err) = result {
+            error!("could not save transitions to storage: {}", err);
+        }
+    }
+}
+
+pub fn restore_replica_states() -> HashMap<ReplicaStateKey, Status> {
+    let mut replica_states = HashMap::new();
+
+    if let Some(ref connection) = *STORAGE {
+        let connection = connection.lock().unwrap();
+
+        let result = connection
+            .prepare("SELECT probe_id, node_id, replica_id, status FROM replica_states")
+            .and_then(|mut statement| {
+                let rows = statement.query_map([], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, String>(3)?,
+                    ))
+                })?;
+
+                for row in rows {
+                    let (probe_id, node_id, replica_id, status) = row?;
+
+                    if let Ok(status) = Status::from_str(&status) {
+                        replica_states.insert((probe_id, node_id, replica_id), status);
+                    }
+                }
+
+                Ok(())
+            });
+
+        if let Err(err) = result {
+            error!("could not restore replica states from storage: {}", err);
+        }
+    }
+
+    // Restored states are known to be stored already
+    *STORED_REPLICA_STATES.lock().unwrap() = replica_states.clone();
+
+    replica_states
+}
+
+pub fn save_disabled_service(probe_id: &str, disabled_until: Option<i64>) {
+    if let Some(ref connection) = *STORAGE {
+        let result = connection.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO disabled_services (probe_id, disabled_at, disabled_until) \
+                VALUES (?1, ?2, ?3)",
+            params![probe_id, time_now(), disabled_until],
+        );
+
+        if let Err(err) = result {
+            error!("could not save disabled service to storage: {}", err);
+        }
+    }
+}
+
+pub fn save_disabled_services(probe_ids: &[String], disabled_until: Option<i64>) {
+    if let Some(ref connection) = *STORAGE {
+        let mut connection = connection.lock().unwrap();
+        let disabled_at = time_now();
+
+        let result = connection.transaction().and_then(|transaction| {
+            for probe_id in probe_ids {
+                transaction.execute(
+                    "INSERT OR REPLACE INTO disabled_services \
+                        (probe_id, disabled_at, disabled_until) VALUES (?1, ?2, ?3)",
+                    params![probe_id, disabled_at, disabled_until],
+                )?;
+            }
+
+            transaction.commit()
+        });
+
+        if let Err(err) = result {
+            error!("could not save disabled services to storage: {}", err);
+        }
+    }
+}
+
+pub fn remove_disabled_service(probe_id: &str) {
+    if let Some(ref connection) = *STORAGE {
+        let result = connection.lock().unwrap().execute(
+            "DELETE FROM disabled_services WHERE probe_id = ?1",
+            params![probe_id],
+        );
+
+        if let Err(err) = result {
+            error!("could not remove disabled service from storage: {}", err);
+        }
+    }
+}
+
+pub fn remove_disabled_services(probe_ids: &[String]) {
+    if let Some(ref connection) = *STORAGE {
+        let mut connection = connection.lock().unwrap();
+
+        let result = connection.transaction().and_then(|transaction| {
+            for probe_id in probe_ids {
+                transaction.execute(
+                    "DELETE FROM disabled_services WHERE probe_id = ?1",
+                    params![probe_id],
+                )?;
+            }
+
+            transaction.commit()
+        });
+
+        if let Err(err) = result {
+            error!("could not remove disabled services from storage: {}", err);
+        }
+    }
+}
+
+pub fn restore_disabled_services() -> HashMap<String, Option<i64>> {
+    let mut disabled_services = HashMap::new();
+
+    if let Some(ref connection) = *STORAGE {
+        let connection = connection.lock().unwrap();
+
+        let result = connection
+            .prepare("SELECT probe_id, disabled_until FROM disabled_services")
+            .and_then(|mut statement| {
+                let rows = statement.query_map([], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, Option<i64>>(1)?))
+                })?;
+
+                for row in rows {
+                    let (probe_id, disabled_until) = row?;
+
+                    disabled_services.insert(probe_id, disabled_until);
+                }
+
+                Ok(())
+            });
+
+        if let Err(err) = result {
+            error!("could not restore disabled services from storage: {}", err);
+        }
+    }
+
+    disabled_services
+}
+
+pub fn restore_general_status() -> Option<Status> {
+    if let Some(ref connection) = *STORAGE {
+        let connection = connection.lock().unwrap();
+
+        let result = connection.query_row(
+            "SELECT status FROM transitions \
+                WHERE probe_id IS NULL AND node_id IS NULL ORDER BY time DESC, id DESC LIMIT 1",
+            [],
+            |row| row.get::<_, String>(0),
+        );
+
+        if let Ok(status) = result {
+            return Status::from_str(&status).ok();
+        }
+    }
+
+    None
+}
+
+pub fn list_transitions(
+    probe_id: Option<&str>,
+    node_id: Option<&str>,
+    since: i64,
+) -> Vec<(i64, Status)> {
+    let mut transitions = Vec::new();
+
+    if let Some(ref connection) = *STORAGE {
+        let connection = connection.lock().unwrap();
+
+        // Notice: the last transition that happened before the lower time bound is also \
+        //   returned, as it holds the status that was in effect at the lower time bound.
+        let result = connection
+            .prepare(
+                "SELECT id, time, status FROM (
+                    SELECT id, time, status FROM transitions
+                        WHERE probe_id IS ?1 AND node_id IS ?2 AND time < ?3
+                        ORDER BY time DESC, id DESC LIMIT 1
+                ) UNION ALL SELECT id, time, status FROM transitions
+                    WHERE probe_id IS ?1 AND node_id IS ?2 AND time >= ?3
+                    ORDER BY 2 ASC, 1 ASC",
+            )
+            .and_then(|mut statement| {
+                let rows = statement.query_map(params![probe_id, node_id, since], |row| {
+                    Ok((row.get::<_, i64>(1)?, row.get::<_, String>(2)?))
+                })?;
+
+                for row in rows {
+                    let (time, status) = row?;
+
+                    if let Ok(status) = Status::from_str(&status) {
+                        transitions.push((time, status));
+                    }
+                }
+
+                Ok(())
+            });
+
+        if let Err(err) = result {
+            error!("could not list transitions from storage: {}", err);
+        }
+    }
+
+    transitions
+}
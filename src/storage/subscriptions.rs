@@ -0,0 +1,107 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use rusqlite::{params, ErrorCode};
+
+use super::sqlite as storage;
+
+#[derive(Serialize)]
+pub struct Subscription {
+    pub id: i64,
+    pub url: String,
+    pub created_at: i64,
+}
+
+pub enum SubscriptionError {
+    StorageDisabled,
+    StorageFailure,
+    NotFound,
+    AlreadyExists,
+}
+
+pub fn add(url: &str) -> Result<i64, SubscriptionError> {
+    let result = storage::with_connection(|connection| {
+        connection.execute(
+            "INSERT INTO subscriptions (url, created_at) VALUES (?1, ?2)",
+            params![url, storage::time_now()],
+        )?;
+
+        Ok(connection.last_insert_rowid())
+    });
+
+    match result {
+        Some(Ok(subscription_id)) => {
+            info!("added subscription #{}: {}", subscription_id, url);
+
+            Ok(subscription_id)
+        }
+        Some(Err(rusqlite::Error::SqliteFailure(err, _)))
+            if err.code == ErrorCode::ConstraintViolation =>
+        {
+            Err(SubscriptionError::AlreadyExists)
+        }
+        Some(Err(err)) => {
+            error!("could not add subscription: {}", err);
+
+            Err(SubscriptionError::StorageFailure)
+        }
+        None => Err(SubscriptionError::StorageDisabled),
+    }
+}
+
+pub fn remove(subscription_id: i64) -> Result<(), SubscriptionError> {
+    let result = storage::with_connection(|connection| {
+        connection.execute(
+            "DELETE FROM subscriptions WHERE id = ?1",
+            params![subscription_id],
+        )
+    });
+
+    match result {
+        Some(Ok(0)) => Err(SubscriptionError::NotFound),
+        Some(Ok(_)) => {
+            info!("removed subscription #{}", subscription_id);
+
+            Ok(())
+        }
+        Some(Err(err)) => {
+            error!(
+                "could not remove subscription #{}: {}",
+                subscription_id, err
+            );
+
+            Err(SubscriptionError::StorageFailure)
+        }
+        None => Err(SubscriptionError::StorageDisabled),
+    }
+}
+
+pub fn list() -> Result<Vec<Subscription>, SubscriptionError> {
+    let result = storage::with_connection(|connection| {
+        let mut statement =
+            connection.prepare("SELECT id, url, created_at FROM subscriptions ORDER BY id ASC")?;
+
+        let rows = statement.query_map([], |row| {
+            Ok(Subscription {
+                id: row.get(0)?,
+                url: row.get(1)?,
+                created_at: row.get(2)?,
+            })
+        })?;
+
+        rows.collect()
+    });
+
+    match result {
+        Some(Ok(subscriptions)) => Ok(subscriptions),
+        Some(Err(err)) => {
+            error!("could not list subscriptions: {}", err);
+
+            Err(SubscriptionError::StorageFailure)
+        }
+        None => Err(SubscriptionError::StorageDisabled),
+    }
+}
@@ -0,0 +1,255 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use indexmap::IndexMap;
+use time;
+
+use super::sqlite as storage;
+use crate::prober::manager::{mark_snapshot_dirty, SNAPSHOT as PROBER_SNAPSHOT};
+use crate::prober::status::Status;
+
+const SECONDS_IN_DAY: i64 = 86400;
+
+pub const WINDOW_DAYS_MAXIMUM: u64 = 90;
+
+const WINDOW_DAYS: [i64; 4] = [1, 7, 30, WINDOW_DAYS_MAXIMUM as i64];
+
+const CACHE_SECONDS: u64 = 60;
+
+lazy_static! {
+    // Notice: uptime is computed from the whole transitions history of all nodes, which is too \
+    //   expensive to do on each request; it is cached for a while instead, as uptime percents \
+    //   barely change from one minute to another.
+    static ref CACHE: Mutex<Option<(Instant, Uptime)>> = Mutex::new(None);
+}
+
+#[derive(Serialize, Clone)]
+pub struct Uptime {
+    pub general: UptimeWindows,
+    pub probes: IndexMap<String, UptimeProbe>,
+    pub groups: IndexMap<String, UptimeWindows>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct UptimeProbe {
+    pub uptime: UptimeWindows,
+    pub nodes: IndexMap<String, UptimeWindows>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct UptimeWindows {
+    #[serde(rename = "24h")]
+    pub day: Option<f64>,
+
+    #[serde(rename = "7d")]
+    pub week: Option<f64>,
+
+    #[serde(rename = "30d")]
+    pub month: Option<f64>,
+
+    #[serde(rename = "90d")]
+    pub quarter: Option<f64>,
+}
+
+impl UptimeWindows {
+    pub fn get(&self, window: &str) -> Option<f64> {
+        match window {
+            "24h" => self.day,
+            "7d" => self.week,
+            "30d" => self.month,
+            "90d" => self.quarter,
+            _ => None,
+        }
+    }
+
+    fn compute(probe_id: Option<&str>, node_id: Option<&str>, now: i64) -> Self {
+        Self::from_transitions(&list_transitions(probe_id, node_id, now), now)
+    }
+
+    fn from_transitions(transitions: &[(i64, Status)], now: i64) -> Self {
+        let mut percents = WINDOW_DAYS
+            .iter()
+            .map(|days| compute_percent(transitions, now - days * SECONDS_IN_DAY, now));
+
+        UptimeWindows {
+            day: percents.next().flatten(),
+            week: percents.next().flatten(),
+            month: percents.next().flatten(),
+            quarter: percents.next().flatten(),
+        }
+    }
+}
+
+pub fn compute() -> Option<Uptime> {
+    // Uptime can only be computed from the stored transitions history
+    if storage::is_enabled() == false {
+        return None;
+    }
+
+    // Notice: the cache lock is held while computing, so that concurrent requests wait for \
+    //   the uptime to be computed once, rather than all computing it at the same time.
+    let mut cache = CACHE.lock().unwrap();
+
+    if let Some((computed_at, ref uptime)) = *cache {
+        if computed_at.elapsed() < Duration::from_secs(CACHE_SECONDS) {
+            return Some(uptime.clone());
+        }
+    }
+
+    let uptime = compute_all();
+
+    *cache = Some((Instant::now(), uptime.clone()));
+
+    // Responses embedding uptime must not be served from client caches anymore
+    mark_snapshot_dirty();
+
+    Some(uptime)
+}
+
+pub fn compute_probe(probe_id: &str) -> Option<UptimeWindows> {
+    // Uptime can only be computed from the stored transitions history
+    if storage::is_enabled() == false {
+        return None;
+    }
+
+    let now = time::OffsetDateTime::now_utc().unix_timestamp();
+
+    Some(UptimeWindows::compute(Some(probe_id), None, now))
+}
+
+fn compute_all() -> Uptime {
+    let now = time::OffsetDateTime::now_utc().unix_timestamp();
+
+    // Notice: probes are read from the published snapshot rather than from the configuration, \
+    //   so that discovered probes also get their uptime computed.
+    let snapshot = PROBER_SNAPSHOT.load_full();
+
+    let (mut probes, mut groups_transitions) = (IndexMap::new(), IndexMap::new());
+
+    for (probe_id, probe) in snapshot.states.probes.iter() {
+        let mut nodes = IndexMap::new();
+
+        for node_id in probe.nodes.keys() {
+            nodes.insert(
+                node_id.to_owned(),
+                UptimeWindows::compute(Some(probe_id), Some(node_id), now),
+            );
+        }
+
+        let transitions = list_transitions(Some(probe_id), None, now);
+
+        probes.insert(
+            probe_id.to_owned(),
+            UptimeProbe {
+                uptime: UptimeWindows::from_transitions(&transitions, now),
+                nodes: nodes,
+            },
+        );
+
+        for group in probe.groups.iter() {
+            groups_transitions
+                .entry(group.to_owned())
+                .or_insert_with(Vec::new)
+                .push(transitions.to_owned());
+        }
+    }
+
+    let groups = groups_transitions
+        .into_iter()
+        .map(|(group, probes_transitions)| {
+            (
+                group,
+                UptimeWindows::from_transitions(&merge_transitions(probes_transitions), now),
+            )
+        })
+        .collect();
+
+    Uptime {
+        general: UptimeWindows::compute(None, None, now),
+        probes: probes,
+        groups: groups,
+    }
+}
+
+fn list_transitions(probe_id: Option<&str>, node_id: Option<&str>, now: i64) -> Vec<(i64, Status)> {
+    // Acquire all transitions that happened over the largest window (at once)
+    storage::list_transitions(
+        probe_id,
+        node_id,
+        now - WINDOW_DAYS[WINDOW_DAYS.len() - 1] * SECONDS_IN_DAY,
+    )
+}
+
+fn merge_transitions(probes_transitions: Vec<Vec<(i64, Status)>>) -> Vec<(i64, Status)> {
+    // Notice: a group is considered down whenever any of its probes is down, thus the \
+    //   transitions of all its probes get merged into a single timeline of the group status.
+    let mut probes_dead = vec![false; probes_transitions.len()];
+
+    let mut events = probes_transitions
+        .into_iter()
+        .enumerate()
+        .flat_map(|(index, transitions)| {
+            transitions
+                .into_iter()
+                .map(move |(time, status)| (time, index, status))
+        })
+        .collect::<Vec<(i64, usize, Status)>>();
+
+    events.sort_by_key(|(time, index, _)| (*time, *index));
+
+    events
+        .into_iter()
+        .map(|(time, index, status)| {
+            probes_dead[index] = status == Status::Dead;
+
+            let status = if probes_dead.contains(&true) == true {
+                Status::Dead
+            } else {
+                Status::Healthy
+            };
+
+            (time, status)
+        })
+        .collect()
+}
+
+fn compute_percent(
+    transitions: &[(i64, Status)],
+    window_start: i64,
+    window_end: i64,
+) -> Option<f64> {
+    let (mut time_up, mut time_total) = (0, 0);
+
+    for (index, (time, status)) in transitions.iter().enumerate() {
+        // Status holds until the next transition (or until now, if this is the last transition)
+        let span_end = transitions
+            .get(index + 1)
+            .map(|(next_time, _)| *next_time)
+            .unwrap_or(window_end)
+            .min(window_end);
+        let span_start = (*time).max(window_start);
+
+        if span_end > span_start {
+            time_total += span_end - span_start;
+
+            // Notice: only 'dead' counts as downtime, as 'sick' services still serve requests.
+            if status != &Status::Dead {
+                time_up += span_end - span_start;
+            }
+        }
+    }
+
+    // Notice: time with no known status (ie. before the first transition) is ignored, and \
+    //   there is no uptime to report if no transition is known at all.
+    if time_total > 0 {
+        Some((time_up as f64 / time_total as f64) * 100.0)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,264 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::collections::HashMap;
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use reqwest::blocking::Client;
+use reqwest::header::CONTENT_TYPE;
+use sha2::Sha256;
+
+use crate::config::config::ConfigSubscriptions;
+use crate::prober::status::Status;
+use crate::storage::sqlite as storage;
+use crate::storage::subscriptions;
+use crate::APP_CONF;
+
+const DELIVER_TIMEOUT_SECONDS: u64 = 10;
+const DELIVER_QUEUE_CAPACITY: usize = 256;
+
+lazy_static! {
+    static ref SUBSCRIBER_HTTP_CLIENT: Client = Client::builder()
+        .timeout(Duration::from_secs(DELIVER_TIMEOUT_SECONDS))
+        .gzip(true)
+        .build()
+        .unwrap();
+    static ref SUBSCRIBER_WORKERS: Mutex<HashMap<String, SyncSender<Arc<SubscriberDelivery>>>> =
+        Mutex::new(HashMap::new());
+}
+
+pub struct SubscriberEvent {
+    pub probe_id: String,
+    pub probe_label: String,
+    pub status: Status,
+    pub previous_status: Status,
+    pub replicas: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct SubscriberPayload<'a> {
+    #[serde(rename = "type")]
+    _type: &'static str,
+
+    time: i64,
+    probe: SubscriberPayloadProbe<'a>,
+    status: &'a Status,
+    previous_status: &'a Status,
+    replicas: &'a [String],
+    page: SubscriberPayloadPage<'a>,
+}
+
+#[derive(Serialize)]
+struct SubscriberPayloadProbe<'a> {
+    id: &'a str,
+    label: &'a str,
+}
+
+#[derive(Serialize)]
+struct SubscriberPayloadPage<'a> {
+    title: &'a str,
+    url: &'a str,
+}
+
+struct SubscriberDelivery {
+    body: String,
+    signature: String,
+}
+
+pub fn dispatch(events: Vec<SubscriberEvent>) {
+    if let Some(ref subscriptions_config) = APP_CONF.subscriptions {
+        if events.is_empty() == true {
+            return;
+        }
+
+        // Acquire subscribed URLs (from configuration, plus those registered over the API)
+        let mut urls: Vec<String> = subscriptions_config
+            .hook
+            .iter()
+            .map(|hook| hook.url.as_str().to_owned())
+            .collect();
+
+        if let Ok(registered_subscriptions) = subscriptions::list() {
+            for subscription in registered_subscriptions {
+                if urls.contains(&subscription.url) == false {
+                    urls.push(subscription.url);
+                }
+            }
+        }
+
+        // Stop workers of subscribers that are gone (they exit once their queue is drained)
+        SUBSCRIBER_WORKERS
+            .lock()
+            .unwrap()
+            .retain(|url, _| urls.contains(url));
+
+        if urls.is_empty() == true {
+            return;
+        }
+
+        // Build signed deliveries (shared between all subscribers)
+        let time = storage::time_now();
+
+        let deliveries: Vec<Arc<SubscriberDelivery>> = events
+            .iter()
+            .filter_map(|event| make_delivery(subscriptions_config, event, time))
+            .map(Arc::new)
+            .collect();
+
+        // Queue deliveries to the worker of each subscriber
+        // Notice: each subscriber has a single long-lived worker, which sends deliveries one \
+        //   after the other, so that transitions are received in the order they happened, even \
+        //   if earlier deliveries are still being retried upon the next aggregate tick.
+        let mut workers = SUBSCRIBER_WORKERS.lock().unwrap();
+
+        for url in urls {
+            for delivery in deliveries.iter() {
+                let sender = workers
+                    .entry(url.to_owned())
+                    .or_insert_with(|| spawn_worker(&url));
+
+                match sender.try_send(delivery.clone()) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(_)) => {
+                        warn!(
+                            "dropped subscriber delivery to: {} (too many pending deliveries)",
+                            url
+                        );
+                    }
+                    Err(TrySendError::Disconnected(delivery)) => {
+                        // Worker is gone (it crashed), thus start a new one
+                        let sender = spawn_worker(&url);
+
+                        sender.try_send(delivery).ok();
+
+                        workers.insert(url.to_owned(), sender);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn spawn_worker(url: &str) -> SyncSender<Arc<SubscriberDelivery>> {
+    let (sender, receiver) = mpsc::sync_channel::<Arc<SubscriberDelivery>>(DELIVER_QUEUE_CAPACITY);
+    let url = url.to_owned();
+
+    debug!("starting subscriber worker for: {}", url);
+
+    thread::spawn(move || {
+        for delivery in receiver.iter() {
+            // Notice: the configuration is read upon each delivery, as it might get reloaded
+            if let Some(ref subscriptions_config) = APP_CONF.subscriptions {
+                deliver(subscriptions_config, &url, &delivery);
+            }
+        }
+
+        debug!("stopped subscriber worker for: {}", url);
+    });
+
+    sender
+}
+
+fn make_delivery(
+    subscriptions_config: &ConfigSubscriptions,
+    event: &SubscriberEvent,
+    time: i64,
+) -> Option<SubscriberDelivery> {
+    let payload = SubscriberPayload {
+        _type: "transition",
+        time: time,
+        probe: SubscriberPayloadProbe {
+            id: &event.probe_id,
+            label: &event.probe_label,
+        },
+        status: &event.status,
+        previous_status: &event.previous_status,
+        replicas: &event.replicas,
+        page: SubscriberPayloadPage {
+            title: APP_CONF.branding.page_title.as_str(),
+            url: APP_CONF.branding.page_url.as_str(),
+        },
+    };
+
+    match serde_json::to_string(&payload) {
+        Ok(body) => {
+            let signature = sign(&subscriptions_config.secret, &body);
+
+            Some(SubscriberDelivery {
+                body: body,
+                signature: signature,
+            })
+        }
+        Err(err) => {
+            error!("could not serialize subscriber payload: {}", err);
+
+            None
+        }
+    }
+}
+
+fn sign(secret: &str, body: &str) -> String {
+    // Sign body with HMAC-SHA256 (receivers verify it using the shared secret)
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac accepts any key size");
+
+    mac.update(body.as_bytes());
+
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn deliver(subscriptions_config: &ConfigSubscriptions, url: &str, delivery: &SubscriberDelivery) {
+    for try_index in 0..(subscriptions_config.retry_attempts as u32 + 1) {
+        // Hold on for next try (with an exponential backoff)
+        // Notice: the delay saturates instead of overflowing, if many retries are configured
+        if try_index > 0 {
+            let delay = subscriptions_config
+                .retry_delay
+                .saturating_mul(2_u64.checked_pow(try_index - 1).unwrap_or(u64::MAX));
+
+            debug!(
+                "will retry subscriber delivery to: {} in {}s (attempt: #{})",
+                url,
+                delay,
+                try_index + 1
+            );
+
+            thread::sleep(Duration::from_secs(delay));
+        }
+
+        let response = SUBSCRIBER_HTTP_CLIENT
+            .post(url)
+            .header(CONTENT_TYPE, "application/json")
+            .header("X-Vigil-Event", "transition")
+            .header(
+                "X-Vigil-Signature",
+                format!("sha256={}", delivery.signature),
+            )
+            .body(delivery.body.to_owned())
+            .send();
+
+        if let Ok(response_inner) = response {
+            if response_inner.status().is_success() == true {
+                debug!("delivered subscriber payload to: {}", url);
+
+                return;
+            }
+        }
+    }
+
+    error!(
+        "failed delivering subscriber payload to: {} (gave up after retries)",
+        url
+    );
+}
@@ -0,0 +1,289 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use reqwest::blocking::Client;
+
+use super::span::{SpanKind, SpanValue};
+use crate::APP_CONF;
+
+const EXPORT_TIMEOUT_SECONDS: u64 = 10;
+const EXPORT_BATCH_SIZE: usize = 512;
+const EXPORT_DISABLED_CHECK_SECONDS: u64 = 10;
+const SCOPE_NAME: &'static str = "vigil";
+
+lazy_static! {
+    static ref TRACER_HTTP_CLIENT: Client = Client::builder()
+        .timeout(Duration::from_secs(EXPORT_TIMEOUT_SECONDS))
+        .gzip(true)
+        .build()
+        .unwrap();
+    static ref QUEUE: Mutex<ExporterQueue> = Mutex::new(ExporterQueue {
+        spans: Vec::new(),
+        dropped: 0,
+    });
+}
+
+struct ExporterQueue {
+    spans: Vec<FinishedSpan>,
+    dropped: u64,
+}
+
+pub struct FinishedSpan {
+    pub trace_id: [u8; 16],
+    pub span_id: [u8; 8],
+    pub parent_span_id: Option<[u8; 8]>,
+    pub name: String,
+    pub kind: SpanKind,
+    pub started_at: SystemTime,
+    pub ended_at: SystemTime,
+    pub attributes: Vec<(&'static str, SpanValue)>,
+    pub error: Option<String>,
+}
+
+// Notice: spans are exported using the JSON encoding of OTLP over HTTP, which is accepted on \
+//   the '/v1/traces' path of OTLP receivers (eg. Tempo, Jaeger or the OpenTelemetry Collector).
+// See: https://opentelemetry.io/docs/specs/otlp/#otlphttp
+#[derive(Serialize)]
+struct OTLPPayload<'a> {
+    #[serde(rename = "resourceSpans")]
+    resource_spans: [OTLPResourceSpans<'a>; 1],
+}
+
+#[derive(Serialize)]
+struct OTLPResourceSpans<'a> {
+    resource: OTLPResource<'a>,
+
+    #[serde(rename = "scopeSpans")]
+    scope_spans: [OTLPScopeSpans<'a>; 1],
+}
+
+#[derive(Serialize)]
+struct OTLPResource<'a> {
+    attributes: Vec<OTLPAttribute<'a>>,
+}
+
+#[derive(Serialize)]
+struct OTLPScopeSpans<'a> {
+    scope: OTLPScope,
+    spans: Vec<OTLPSpan<'a>>,
+}
+
+#[derive(Serialize)]
+struct OTLPScope {
+    name: &'static str,
+    version: &'static str,
+}
+
+#[derive(Serialize)]
+struct OTLPSpan<'a> {
+    #[serde(rename = "traceId")]
+    trace_id: String,
+
+    #[serde(rename = "spanId")]
+    span_id: String,
+
+    #[serde(rename = "parentSpanId", skip_serializing_if = "Option::is_none")]
+    parent_span_id: Option<String>,
+
+    name: &'a str,
+    kind: u8,
+
+    #[serde(rename = "startTimeUnixNano")]
+    start_time_unix_nano: String,
+
+    #[serde(rename = "endTimeUnixNano")]
+    end_time_unix_nano: String,
+
+    attributes: Vec<OTLPAttribute<'a>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<OTLPStatus<'a>>,
+}
+
+#[derive(Serialize)]
+struct OTLPAttribute<'a> {
+    key: &'a str,
+    value: OTLPValue<'a>,
+}
+
+#[derive(Serialize)]
+enum OTLPValue<'a> {
+    #[serde(rename = "stringValue")]
+    String(&'a str),
+
+    #[serde(rename = "intValue")]
+    Int(String),
+
+    #[serde(rename = "boolValue")]
+    Bool(bool),
+}
+
+#[derive(Serialize)]
+struct OTLPStatus<'a> {
+    code: u8,
+    message: &'a str,
+}
+
+pub fn record(span: FinishedSpan) {
+    let max_queue = match APP_CONF.tracing {
+        Some(ref tracing) => tracing.max_queue,
+        None => return,
+    };
+
+    let mut queue = QUEUE.lock().unwrap();
+
+    // Notice: spans are dropped if the collector cannot keep up (or is unreachable), as \
+    //   tracing must never hold memory without bounds.
+    if queue.spans.len() >= max_queue {
+        queue.dropped += 1;
+    } else {
+        queue.spans.push(span);
+    }
+}
+
+pub fn run() {
+    loop {
+        // Notice: the configuration is read on each tick, as tracing might get enabled upon reload
+        let export_interval = APP_CONF
+            .tracing
+            .as_ref()
+            .map(|tracing| tracing.export_interval);
+
+        match export_interval {
+            Some(export_interval) => {
+                thread::sleep(Duration::from_secs(export_interval));
+
+                flush();
+            }
+            None => {
+                thread::sleep(Duration::from_secs(EXPORT_DISABLED_CHECK_SECONDS));
+            }
+        }
+    }
+}
+
+fn flush() {
+    // Acquire pending spans (the queue lock is released right away)
+    let (spans, dropped) = {
+        let mut queue = QUEUE.lock().unwrap();
+
+        let dropped = queue.dropped;
+
+        queue.dropped = 0;
+
+        (std::mem::take(&mut queue.spans), dropped)
+    };
+
+    if dropped > 0 {
+        warn!("dropped {} spans, as the export queue was full", dropped);
+    }
+
+    if let Some(ref tracing) = APP_CONF.tracing {
+        if spans.is_empty() == true {
+            return;
+        }
+
+        debug!("will export {} spans", spans.len());
+
+        let url = format!(
+            "{}/v1/traces",
+            tracing.endpoint.as_str().trim_end_matches('/')
+        );
+
+        for batch in spans.chunks(EXPORT_BATCH_SIZE) {
+            let payload = OTLPPayload {
+                resource_spans: [OTLPResourceSpans {
+                    resource: OTLPResource {
+                        attributes: vec![
+                            OTLPAttribute {
+                                key: "service.name",
+                                value: OTLPValue::String(&tracing.service_name),
+                            },
+                            OTLPAttribute {
+                                key: "service.version",
+                                value: OTLPValue::String(env!("CARGO_PKG_VERSION")),
+                            },
+                        ],
+                    },
+                    scope_spans: [OTLPScopeSpans {
+                        scope: OTLPScope {
+                            name: SCOPE_NAME,
+                            version: env!("CARGO_PKG_VERSION"),
+                        },
+                        spans: batch.iter().map(make_span).collect(),
+                    }],
+                }],
+            };
+
+            let mut request = TRACER_HTTP_CLIENT.post(&url).json(&payload);
+
+            for (name, value) in tracing.headers.iter() {
+                request = request.header(name.as_str(), value.as_str());
+            }
+
+            match request.send() {
+                Ok(response) => {
+                    if response.status().is_success() == true {
+                        debug!("exported {} spans", batch.len());
+                    } else {
+                        warn!(
+                            "could not export {} spans, got status: {}",
+                            batch.len(),
+                            response.status()
+                        );
+                    }
+                }
+                Err(err) => {
+                    warn!("could not export {} spans: {}", batch.len(), err);
+                }
+            }
+        }
+    }
+}
+
+fn make_span(span: &FinishedSpan) -> OTLPSpan {
+    OTLPSpan {
+        trace_id: encode_hex(&span.trace_id),
+        span_id: encode_hex(&span.span_id),
+        parent_span_id: span.parent_span_id.map(|id| encode_hex(&id)),
+        name: &span.name,
+        kind: span.kind.as_otlp(),
+        start_time_unix_nano: as_unix_nanos(span.started_at),
+        end_time_unix_nano: as_unix_nanos(span.ended_at),
+        attributes: span
+            .attributes
+            .iter()
+            .map(|(key, value)| OTLPAttribute {
+                key: *key,
+                value: match value {
+                    SpanValue::String(value) => OTLPValue::String(value),
+                    SpanValue::Int(value) => OTLPValue::Int(value.to_string()),
+                    SpanValue::Bool(value) => OTLPValue::Bool(*value),
+                },
+            })
+            .collect(),
+        status: span.error.as_ref().map(|message| OTLPStatus {
+            code: 2,
+            message: message,
+        }),
+    }
+}
+
+fn encode_hex(id: &[u8]) -> String {
+    id.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn as_unix_nanos(time: SystemTime) -> String {
+    // Notice: 64-bit integers are encoded as strings in the JSON encoding of OTLP
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or(0)
+        .to_string()
+}
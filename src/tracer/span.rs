@@ -0,0 +1,251 @@
+// Vigil
+//
+// Microservices Status Page
+// Copyright: 2022, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::time::SystemTime;
+
+use openssl::rand::rand_bytes;
+
+use super::exporter::{self, FinishedSpan};
+use crate::APP_CONF;
+
+#[derive(Clone, Copy)]
+pub struct SpanContext {
+    pub trace_id: [u8; 16],
+    pub span_id: [u8; 8],
+    pub sampled: bool,
+}
+
+#[derive(Clone, Copy)]
+pub enum SpanKind {
+    Internal,
+    Server,
+    Client,
+}
+
+pub enum SpanValue {
+    String(String),
+    Int(i64),
+    Bool(bool),
+}
+
+pub struct Span {
+    context: SpanContext,
+    parent_span_id: Option<[u8; 8]>,
+    name: String,
+    kind: SpanKind,
+    started_at: SystemTime,
+    attributes: Vec<(&'static str, SpanValue)>,
+    error: Option<String>,
+}
+
+impl SpanContext {
+    pub fn from_traceparent(traceparent: &str) -> Option<SpanContext> {
+        // Parse W3C trace context header (eg. '00-<trace_id>-<parent_id>-<flags>')
+        // See: https://www.w3.org/TR/trace-context/#traceparent-header
+        let parts: Vec<&str> = traceparent.trim().split('-').collect();
+
+        if parts.len() < 4 || parts[0].len() != 2 || parts[0] == "ff" {
+            return None;
+        }
+
+        let (mut trace_id, mut span_id, mut flags) = ([0; 16], [0; 8], [0; 1]);
+
+        if decode_hex(parts[1], &mut trace_id) == false
+            || decode_hex(parts[2], &mut span_id) == false
+            || decode_hex(parts[3], &mut flags) == false
+            || is_valid_id(&trace_id) == false
+            || is_valid_id(&span_id) == false
+        {
+            return None;
+        }
+
+        Some(SpanContext {
+            trace_id: trace_id,
+            span_id: span_id,
+            sampled: flags[0] & 0x01 == 0x01,
+        })
+    }
+}
+
+impl SpanKind {
+    pub fn as_otlp(&self) -> u8 {
+        // See: https://github.com/open-telemetry/opentelemetry-proto (SpanKind)
+        match self {
+            SpanKind::Internal => 1,
+            SpanKind::Server => 2,
+            SpanKind::Client => 3,
+        }
+    }
+}
+
+impl From<&str> for SpanValue {
+    fn from(value: &str) -> Self {
+        SpanValue::String(value.to_owned())
+    }
+}
+
+impl From<String> for SpanValue {
+    fn from(value: String) -> Self {
+        SpanValue::String(value)
+    }
+}
+
+impl From<u64> for SpanValue {
+    fn from(value: u64) -> Self {
+        SpanValue::Int(value as i64)
+    }
+}
+
+impl From<usize> for SpanValue {
+    fn from(value: usize) -> Self {
+        SpanValue::Int(value as i64)
+    }
+}
+
+impl From<u16> for SpanValue {
+    fn from(value: u16) -> Self {
+        SpanValue::Int(value as i64)
+    }
+}
+
+impl From<bool> for SpanValue {
+    fn from(value: bool) -> Self {
+        SpanValue::Bool(value)
+    }
+}
+
+impl Span {
+    pub fn root(name: &str, kind: SpanKind) -> Span {
+        let mut trace_id = [0; 16];
+
+        // Notice: sampling is decided from the trace identifier, so that spans of a trace are \
+        //   either all kept or all dropped; unsampled spans are never recorded.
+        let sampled = make_id(&mut trace_id) == true
+            && APP_CONF
+                .tracing
+                .as_ref()
+                .map(|tracing| should_sample(&trace_id, tracing.sample_ratio))
+                .unwrap_or(false);
+
+        Self::make(trace_id, None, sampled, name, kind)
+    }
+
+    pub fn child(parent: &SpanContext, name: &str, kind: SpanKind) -> Span {
+        Self::make(
+            parent.trace_id,
+            Some(parent.span_id),
+            parent.sampled == true && APP_CONF.tracing.is_some() == true,
+            name,
+            kind,
+        )
+    }
+
+    pub fn context(&self) -> SpanContext {
+        self.context
+    }
+
+    pub fn set_attribute<V: Into<SpanValue>>(&mut self, key: &'static str, value: V) {
+        if self.context.sampled == true {
+            self.attributes.push((key, value.into()));
+        }
+    }
+
+    pub fn set_error(&mut self, message: &str) {
+        if self.context.sampled == true {
+            self.error = Some(message.to_owned());
+        }
+    }
+
+    pub fn end(self) {
+        // Notice: the span gets recorded when dropped, this makes the end explicit.
+    }
+
+    fn make(
+        trace_id: [u8; 16],
+        parent_span_id: Option<[u8; 8]>,
+        sampled: bool,
+        name: &str,
+        kind: SpanKind,
+    ) -> Span {
+        let mut span_id = [0; 8];
+
+        let sampled = sampled == true && make_id(&mut span_id) == true;
+
+        Span {
+            context: SpanContext {
+                trace_id: trace_id,
+                span_id: span_id,
+                sampled: sampled,
+            },
+            parent_span_id: parent_span_id,
+            name: if sampled == true {
+                name.to_owned()
+            } else {
+                String::new()
+            },
+            kind: kind,
+            started_at: SystemTime::now(),
+            attributes: Vec::new(),
+            error: None,
+        }
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if self.context.sampled == true {
+            exporter::record(FinishedSpan {
+                trace_id: self.context.trace_id,
+                span_id: self.context.span_id,
+                parent_span_id: self.parent_span_id,
+                name: std::mem::take(&mut self.name),
+                kind: self.kind,
+                started_at: self.started_at,
+                ended_at: SystemTime::now(),
+                attributes: std::mem::take(&mut self.attributes),
+                error: self.error.take(),
+            });
+        }
+    }
+}
+
+fn make_id(id: &mut [u8]) -> bool {
+    // Notice: an all-zero identifier is invalid, such a span is left unsampled.
+    rand_bytes(id).is_ok() == true && is_valid_id(id) == true
+}
+
+fn is_valid_id(id: &[u8]) -> bool {
+    id.iter().any(|byte| *byte != 0)
+}
+
+fn should_sample(trace_id: &[u8; 16], sample_ratio: f64) -> bool {
+    if sample_ratio >= 1.0 {
+        return true;
+    }
+
+    let mut draw = [0; 8];
+
+    draw.copy_from_slice(&trace_id[8..]);
+
+    (u64::from_be_bytes(draw) as f64) < sample_ratio * (u64::MAX as f64)
+}
+
+fn decode_hex(value: &str, output: &mut [u8]) -> bool {
+    if value.len() != output.len() * 2
+        || value.bytes().all(|byte| byte.is_ascii_hexdigit()) == false
+    {
+        return false;
+    }
+
+    for (index, byte) in output.iter_mut().enumerate() {
+        match u8::from_str_radix(&value[(index * 2)..(index * 2 + 2)], 16) {
+            Ok(decoded) => *byte = decoded,
+            Err(_) => return false,
+        }
+    }
+
+    true
+}